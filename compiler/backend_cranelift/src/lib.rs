@@ -0,0 +1,1151 @@
+//! An experimental backend that compiles the LIR to native code using
+//! [Cranelift](https://cranelift.dev).
+//!
+//! The generated object file is linked against the C runtime of the LLVM
+//! backend (`compiler/backend_inkwell/candy_runtime`), so both backends share
+//! the value representation and the implementations of builtins.
+//!
+//! Only a subset of Candy is supported so far: ints that fit into 64 bits,
+//! texts, tags, lists, structs, functions, and the builtins implemented by the
+//! runtime. Calling other builtins panics at runtime and other constants (like
+//! floats) evaluate to `NotSupported "…"`. Reference counting instructions are
+//! ignored, so values are never freed.
+
+#![warn(clippy::nursery, clippy::pedantic, unused_crate_dependencies)]
+#![allow(
+    clippy::missing_errors_doc,
+    clippy::missing_panics_doc,
+    clippy::module_name_repetitions,
+    clippy::too_many_lines
+)]
+
+use candy_frontend::{
+    builtin_functions::BuiltinFunction,
+    id::CountableId,
+    lir::{Body, BodyId, Constant, ConstantId, Expression, Id, Lir},
+};
+use cranelift_codegen::{
+    ir::{
+        condcodes::IntCC, types, AbiParam, InstBuilder, MemFlags, Signature, TrapCode, Type,
+        UserFuncName, Value,
+    },
+    settings::{self, Configurable},
+    Context,
+};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
+use cranelift_module::{
+    default_libcall_names, DataDescription, DataId, FuncId, Linkage, Module, ModuleError,
+    ModuleResult,
+};
+use cranelift_object::{ObjectBuilder, ObjectModule, ObjectProduct};
+use itertools::Itertools;
+use rustc_hash::FxHashMap;
+use std::{
+    fmt::{self, Display, Formatter, Write},
+    fs, io,
+    process::Command,
+};
+
+const RUNTIME_PATH: &str = "compiler/backend_inkwell/candy_runtime/";
+
+#[derive(Debug)]
+pub enum CodegenError {
+    /// Cranelift can't generate code for the machine we're running on.
+    UnsupportedHost(String),
+    Module(ModuleError),
+}
+impl From<ModuleError> for CodegenError {
+    fn from(error: ModuleError) -> Self {
+        Self::Module(error)
+    }
+}
+impl Display for CodegenError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::UnsupportedHost(reason) => {
+                write!(f, "Cranelift doesn't support this host: {reason}")
+            }
+            Self::Module(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+pub struct CodeGen<'a> {
+    lir: &'a Lir,
+    module: ObjectModule,
+    pointer_type: Type,
+    print_clif: bool,
+    clif: String,
+    runtime_functions: FxHashMap<&'static str, FuncId>,
+    candy_environment: DataId,
+    bodies: Vec<FuncId>,
+    constants: Vec<DataId>,
+    init_constants: FuncId,
+    strings: FxHashMap<String, DataId>,
+    builtin_wrappers: FxHashMap<BuiltinFunction, FuncId>,
+}
+
+impl<'a> CodeGen<'a> {
+    pub fn new(lir: &'a Lir, module_name: &str) -> Result<Self, CodegenError> {
+        let mut flags = settings::builder();
+        flags.set("is_pic", "true").unwrap();
+        flags.set("opt_level", "speed").unwrap();
+        let isa = cranelift_native::builder()
+            .map_err(|reason| CodegenError::UnsupportedHost(reason.to_string()))?
+            .finish(settings::Flags::new(flags))
+            .map_err(|error| CodegenError::UnsupportedHost(error.to_string()))?;
+        let pointer_type = isa.pointer_type();
+        let mut module = ObjectModule::new(ObjectBuilder::new(
+            isa,
+            module_name,
+            default_libcall_names(),
+        )?);
+
+        let runtime_functions = [
+            ("make_candy_int", vec![types::I64], true),
+            ("make_candy_text", vec![pointer_type], true),
+            ("make_candy_tag", vec![pointer_type; 2], true),
+            ("make_candy_list", vec![pointer_type], true),
+            ("make_candy_struct", vec![pointer_type; 2], true),
+            (
+                "make_candy_function",
+                vec![pointer_type, pointer_type, types::I32],
+                true,
+            ),
+            ("get_candy_function_pointer", vec![pointer_type], true),
+            ("get_candy_function_environment", vec![pointer_type], true),
+            ("candy_panic", vec![pointer_type], false),
+            ("malloc", vec![pointer_type], true),
+        ]
+        .into_iter()
+        .map(|(name, parameters, has_return_value)| {
+            let mut signature = module.make_signature();
+            signature
+                .params
+                .extend(parameters.into_iter().map(AbiParam::new));
+            if has_return_value {
+                signature.returns.push(AbiParam::new(pointer_type));
+            }
+            let id = module.declare_function(name, Linkage::Import, &signature)?;
+            Ok((name, id))
+        })
+        .collect::<ModuleResult<_>>()?;
+        let candy_environment =
+            module.declare_data("candy_environment", Linkage::Import, false, false)?;
+
+        let bodies = lir
+            .bodies()
+            .ids_and_bodies()
+            .map(|(id, body)| {
+                let signature = candy_signature(&module, pointer_type, body.parameter_count());
+                module.declare_function(&format!("candy_{id}"), Linkage::Local, &signature)
+            })
+            .try_collect()?;
+
+        // Constants are created once at startup and stored in these slots.
+        let constants = lir
+            .constants()
+            .ids_and_constants()
+            .map(|(id, _)| {
+                let data = module.declare_data(
+                    &format!("candy_constant_{}", id.to_usize()),
+                    Linkage::Local,
+                    true,
+                    false,
+                )?;
+                let mut description = DataDescription::new();
+                description.define_zeroinit(pointer_type.bytes() as usize);
+                module.define_data(data, &description)?;
+                Ok(data)
+            })
+            .collect::<ModuleResult<_>>()?;
+        let init_constants = module.declare_function(
+            "candy_init_constants",
+            Linkage::Local,
+            &module.make_signature(),
+        )?;
+
+        Ok(Self {
+            lir,
+            module,
+            pointer_type,
+            print_clif: false,
+            clif: String::new(),
+            runtime_functions,
+            candy_environment,
+            bodies,
+            constants,
+            init_constants,
+            strings: FxHashMap::default(),
+            builtin_wrappers: FxHashMap::default(),
+        })
+    }
+
+    /// Compiles all bodies and a `main` function that calls the exported
+    /// `main` function of the module with the environment.
+    ///
+    /// If `print_clif` is enabled, the returned module also contains the
+    /// generated Cranelift IR.
+    pub fn compile(mut self, print_clif: bool) -> Result<CandyObject, CodegenError> {
+        self.print_clif = print_clif;
+        let mut context = self.module.make_context();
+        let mut function_builder_context = FunctionBuilderContext::new();
+
+        self.compile_bodies(&mut context, &mut function_builder_context)?;
+        self.compile_main(&mut context, &mut function_builder_context)?;
+
+        Ok(CandyObject {
+            clif: self.clif,
+            product: self.module.finish(),
+        })
+    }
+
+    /// Compiles all bodies and exports C functions for using the module from
+    /// other languages: `{name}_init()` runs the module's top-level code and
+    /// must be called first. Each function exported by the module is then
+    /// available as `{name}_{export}(…)`.
+    ///
+    /// Only exports that are known to be functions at compile time can be
+    /// exposed. The names of all other exports are returned in
+    /// [`CandyLibrary::skipped_exports`].
+    ///
+    /// The LIR must be compiled for [`ExecutionTarget::Module`](candy_frontend::hir_to_mir::ExecutionTarget::Module).
+    pub fn compile_library(
+        mut self,
+        name: &str,
+        print_clif: bool,
+    ) -> Result<CandyLibrary, CodegenError> {
+        self.print_clif = print_clif;
+        let mut context = self.module.make_context();
+        let mut function_builder_context = FunctionBuilderContext::new();
+
+        self.compile_bodies(&mut context, &mut function_builder_context)?;
+
+        let name = c_identifier(name);
+        self.compile_library_init(&mut context, &mut function_builder_context, &name)?;
+        let (exports, skipped_exports) = self.exported_functions();
+        let mut functions = vec![];
+        for (export_name, body_id) in exports {
+            let function_name = format!("{name}_{}", c_identifier(&export_name));
+            let parameter_count = self.lir.bodies().get(body_id).parameter_count();
+            self.compile_export(
+                &mut context,
+                &mut function_builder_context,
+                &function_name,
+                body_id,
+            )?;
+            functions.push((function_name, parameter_count));
+        }
+
+        Ok(CandyLibrary {
+            name,
+            functions,
+            skipped_exports,
+            object: CandyObject {
+                clif: self.clif,
+                product: self.module.finish(),
+            },
+        })
+    }
+
+    fn compile_bodies(
+        &mut self,
+        context: &mut Context,
+        function_builder_context: &mut FunctionBuilderContext,
+    ) -> ModuleResult<()> {
+        for (id, body) in self.lir.bodies().ids_and_bodies() {
+            self.compile_body(context, function_builder_context, id, body)?;
+        }
+        self.compile_init_constants(context, function_builder_context)?;
+        // Compiling them in a fixed order keeps the output deterministic.
+        for (builtin, id) in self
+            .builtin_wrappers
+            .clone()
+            .into_iter()
+            .sorted_by_key(|(_, id)| *id)
+        {
+            self.compile_builtin_wrapper(context, function_builder_context, builtin, id)?;
+        }
+        Ok(())
+    }
+
+    fn compile_body(
+        &mut self,
+        context: &mut Context,
+        function_builder_context: &mut FunctionBuilderContext,
+        id: BodyId,
+        body: &Body,
+    ) -> ModuleResult<()> {
+        let function_id = self.bodies[id.to_usize()];
+        context.func.signature =
+            candy_signature(&self.module, self.pointer_type, body.parameter_count());
+        context.func.name = UserFuncName::user(0, function_id.as_u32());
+        let mut builder = FunctionBuilder::new(&mut context.func, function_builder_context);
+        let entry = builder.create_block();
+        builder.append_block_params_for_function_params(entry);
+        builder.switch_to_block(entry);
+        builder.seal_block(entry);
+        let parameters = builder.block_params(entry).to_vec();
+
+        let id_count = body
+            .last_expression_id()
+            .unwrap_or_else(|| body.responsible_parameter_id())
+            .to_usize()
+            + 1;
+        for index in 0..id_count {
+            builder.declare_var(
+                Variable::from_u32(u32::try_from(index).unwrap()),
+                self.pointer_type,
+            );
+        }
+
+        let environment = *parameters.last().unwrap();
+        for (index, id) in body.captured_ids().enumerate() {
+            let value = builder.ins().load(
+                self.pointer_type,
+                MemFlags::trusted(),
+                environment,
+                self.offset(index + 1),
+            );
+            builder.def_var(variable(id), value);
+        }
+        for (id, value) in body
+            .parameter_ids()
+            .zip_eq(&parameters[..parameters.len() - 1])
+        {
+            builder.def_var(variable(id), *value);
+        }
+        // Responsibilities aren't tracked yet.
+        let null = builder.ins().iconst(self.pointer_type, 0);
+        builder.def_var(variable(body.responsible_parameter_id()), null);
+
+        for (id, expression) in body.ids_and_expressions() {
+            let value = self.compile_expression(&mut builder, body, expression)?;
+            builder.def_var(variable(id), value);
+        }
+
+        let return_value = match body.last_expression_id() {
+            Some(id) => builder.use_var(variable(id)),
+            None => builder.ins().iconst(self.pointer_type, 0),
+        };
+        builder.ins().return_(&[return_value]);
+        builder.finalize();
+        self.define_function(context, function_id)
+    }
+    fn compile_expression(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        body: &Body,
+        expression: &Expression,
+    ) -> ModuleResult<Value> {
+        let value = match expression {
+            Expression::CreateTag { symbol, value } => {
+                let symbol = self.string(builder, symbol)?;
+                let value = builder.use_var(variable(*value));
+                self.call_runtime(builder, "make_candy_tag", &[symbol, value])
+            }
+            Expression::CreateList(items) => {
+                let items = items
+                    .iter()
+                    .map(|it| builder.use_var(variable(*it)))
+                    .collect_vec();
+                self.create_list(builder, &items)
+            }
+            Expression::CreateStruct(fields) => {
+                let (keys, values): (Vec<_>, Vec<_>) = fields
+                    .iter()
+                    .map(|(key, value)| {
+                        (
+                            builder.use_var(variable(*key)),
+                            builder.use_var(variable(*value)),
+                        )
+                    })
+                    .unzip();
+                self.create_struct(builder, &keys, &values)
+            }
+            Expression::CreateFunction { captured, body_id } => {
+                let captured = captured
+                    .iter()
+                    .map(|it| builder.use_var(variable(*it)))
+                    .collect_vec();
+                let argument_count = self.lir.bodies().get(*body_id).parameter_count();
+                self.create_function(
+                    builder,
+                    self.bodies[body_id.to_usize()],
+                    argument_count,
+                    &captured,
+                )
+            }
+            Expression::Constant(id) => {
+                let address = self.data_address(builder, self.constants[id.to_usize()]);
+                builder
+                    .ins()
+                    .load(self.pointer_type, MemFlags::trusted(), address, 0)
+            }
+            Expression::Reference(id) => builder.use_var(variable(*id)),
+            Expression::Call {
+                function,
+                arguments,
+                responsible: _,
+            } => {
+                let arguments = arguments
+                    .iter()
+                    .map(|it| builder.use_var(variable(*it)))
+                    .collect_vec();
+                self.compile_call(builder, body, *function, arguments)?
+            }
+            Expression::Panic {
+                reason,
+                responsible: _,
+            } => {
+                let reason = builder.use_var(variable(*reason));
+                self.panic(builder, reason)
+            }
+            Expression::Dup { .. }
+            | Expression::Drop(_)
+            | Expression::TraceCallStarts { .. }
+            | Expression::TraceCallEnds { .. }
+            | Expression::TraceTailCall { .. }
+            | Expression::TraceExpressionEvaluated { .. }
+            | Expression::TraceFoundFuzzableFunction { .. } => {
+                builder.ins().iconst(self.pointer_type, 0)
+            }
+        };
+        Ok(value)
+    }
+    fn compile_call(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        body: &Body,
+        function: Id,
+        mut arguments: Vec<Value>,
+    ) -> ModuleResult<Value> {
+        // Calls of known functions don't need to go through the runtime.
+        if let Some(Expression::Constant(constant_id)) = body.expression(function) {
+            match self.lir.constants().get(*constant_id) {
+                Constant::Function(body_id) => {
+                    arguments.push(builder.ins().iconst(self.pointer_type, 0));
+                    let function = self.bodies[body_id.to_usize()];
+                    return Ok(self.call(builder, function, &arguments).unwrap());
+                }
+                Constant::Builtin(builtin) => {
+                    return self.call_builtin(builder, *builtin, &arguments);
+                }
+                _ => {}
+            }
+        }
+
+        let function = builder.use_var(variable(function));
+        Ok(self.call_function_value(builder, function, arguments))
+    }
+    fn call_function_value(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        function: Value,
+        mut arguments: Vec<Value>,
+    ) -> Value {
+        let pointer = self.call_runtime(builder, "get_candy_function_pointer", &[function]);
+        let environment = self.call_runtime(builder, "get_candy_function_environment", &[function]);
+        arguments.push(environment);
+        let signature = builder.import_signature(candy_signature(
+            &self.module,
+            self.pointer_type,
+            arguments.len() - 1,
+        ));
+        let call = builder.ins().call_indirect(signature, pointer, &arguments);
+        builder.inst_results(call)[0]
+    }
+    fn call_builtin(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        builtin: BuiltinFunction,
+        arguments: &[Value],
+    ) -> ModuleResult<Value> {
+        if let Some(value) = self.compile_inline_builtin(builder, builtin, arguments) {
+            return Ok(value);
+        }
+
+        let Some(name) = runtime_builtin_name(builtin) else {
+            let message = self.string(
+                builder,
+                &format!("The builtin `{builtin:?}` isn't supported by the native backend yet."),
+            )?;
+            let message = self.call_runtime(builder, "make_candy_text", &[message]);
+            return Ok(self.panic(builder, message));
+        };
+
+        let function = if let Some(function) = self.runtime_functions.get(name) {
+            *function
+        } else {
+            let mut signature = self.module.make_signature();
+            signature
+                .params
+                .extend((0..builtin.num_parameters()).map(|_| AbiParam::new(self.pointer_type)));
+            signature.returns.push(AbiParam::new(self.pointer_type));
+            let function = self
+                .module
+                .declare_function(name, Linkage::Import, &signature)?;
+            self.runtime_functions.insert(name, function);
+            function
+        };
+        Ok(self.call(builder, function, arguments).unwrap())
+    }
+    /// Builtins that the runtime doesn't implement, but that are simple enough
+    /// to generate directly.
+    fn compile_inline_builtin(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        builtin: BuiltinFunction,
+        arguments: &[Value],
+    ) -> Option<Value> {
+        let int = match builtin {
+            BuiltinFunction::GetArgumentCount => {
+                let environment =
+                    self.call_runtime(builder, "get_candy_function_environment", &[arguments[0]]);
+                builder
+                    .ins()
+                    .load(types::I64, MemFlags::trusted(), environment, 0)
+            }
+            BuiltinFunction::IntDivideTruncating
+            | BuiltinFunction::IntModulo
+            | BuiltinFunction::IntMultiply
+            | BuiltinFunction::IntRemainder
+            | BuiltinFunction::IntShiftLeft
+            | BuiltinFunction::IntShiftRight => {
+                let lhs = builder
+                    .ins()
+                    .load(types::I64, MemFlags::trusted(), arguments[0], 0);
+                let rhs = builder
+                    .ins()
+                    .load(types::I64, MemFlags::trusted(), arguments[1], 0);
+                match builtin {
+                    BuiltinFunction::IntDivideTruncating => builder.ins().sdiv(lhs, rhs),
+                    BuiltinFunction::IntModulo => {
+                        let remainder = builder.ins().srem(lhs, rhs);
+                        let rhs_abs = builder.ins().iabs(rhs);
+                        let adjusted = builder.ins().iadd(remainder, rhs_abs);
+                        let is_negative =
+                            builder.ins().icmp_imm(IntCC::SignedLessThan, remainder, 0);
+                        builder.ins().select(is_negative, adjusted, remainder)
+                    }
+                    BuiltinFunction::IntMultiply => builder.ins().imul(lhs, rhs),
+                    BuiltinFunction::IntRemainder => builder.ins().srem(lhs, rhs),
+                    BuiltinFunction::IntShiftLeft => builder.ins().ishl(lhs, rhs),
+                    BuiltinFunction::IntShiftRight => builder.ins().sshr(lhs, rhs),
+                    _ => unreachable!(),
+                }
+            }
+            _ => return None,
+        };
+        Some(self.call_runtime(builder, "make_candy_int", &[int]))
+    }
+    fn panic(&mut self, builder: &mut FunctionBuilder, reason: Value) -> Value {
+        self.call_runtime(builder, "candy_panic", &[reason]);
+        builder.ins().trap(TrapCode::UnreachableCodeReached);
+
+        // Code following a panic is unreachable, but we still have to put it
+        // somewhere.
+        let block = builder.create_block();
+        builder.switch_to_block(block);
+        builder.seal_block(block);
+        builder.ins().iconst(self.pointer_type, 0)
+    }
+
+    fn compile_init_constants(
+        &mut self,
+        context: &mut Context,
+        function_builder_context: &mut FunctionBuilderContext,
+    ) -> ModuleResult<()> {
+        context.func.signature = self.module.make_signature();
+        context.func.name = UserFuncName::user(0, self.init_constants.as_u32());
+        let mut builder = FunctionBuilder::new(&mut context.func, function_builder_context);
+        let entry = builder.create_block();
+        builder.switch_to_block(entry);
+        builder.seal_block(entry);
+
+        let mut values = FxHashMap::default();
+        for (id, _) in self.lir.constants().ids_and_constants() {
+            self.compile_constant(&mut builder, &mut values, id)?;
+        }
+
+        builder.ins().return_(&[]);
+        builder.finalize();
+        self.define_function(context, self.init_constants)
+    }
+    fn compile_constant(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        values: &mut FxHashMap<ConstantId, Value>,
+        id: ConstantId,
+    ) -> ModuleResult<Value> {
+        if let Some(value) = values.get(&id) {
+            return Ok(*value);
+        }
+
+        let value = match self.lir.constants().get(id) {
+            Constant::Int(int) => {
+                if let Ok(int) = i64::try_from(int) {
+                    let int = builder.ins().iconst(types::I64, int);
+                    self.call_runtime(builder, "make_candy_int", &[int])
+                } else {
+                    self.not_supported(builder, "Ints that don't fit into 64 bits")?
+                }
+            }
+            Constant::Float(_) => self.not_supported(builder, "Floats")?,
+            Constant::Text(text) => {
+                let text = self.string(builder, text)?;
+                self.call_runtime(builder, "make_candy_text", &[text])
+            }
+            Constant::Tag { symbol, value } => {
+                let symbol = self.string(builder, symbol)?;
+                let value = match value {
+                    Some(value) => self.compile_constant(builder, values, *value)?,
+                    None => builder.ins().iconst(self.pointer_type, 0),
+                };
+                self.call_runtime(builder, "make_candy_tag", &[symbol, value])
+            }
+            Constant::Builtin(builtin) => {
+                let wrapper = self.builtin_wrapper(*builtin)?;
+                self.create_function(builder, wrapper, builtin.num_parameters(), &[])
+            }
+            Constant::List(items) => {
+                let items = items
+                    .iter()
+                    .map(|it| self.compile_constant(builder, values, *it))
+                    .collect::<ModuleResult<Vec<_>>>()?;
+                self.create_list(builder, &items)
+            }
+            Constant::Struct(fields) => {
+                let mut keys = vec![];
+                let mut field_values = vec![];
+                for (key, value) in fields {
+                    keys.push(self.compile_constant(builder, values, *key)?);
+                    field_values.push(self.compile_constant(builder, values, *value)?);
+                }
+                self.create_struct(builder, &keys, &field_values)
+            }
+            Constant::HirId(id) => {
+                let id = self.string(builder, &id.to_string())?;
+                self.call_runtime(builder, "make_candy_text", &[id])
+            }
+            Constant::Function(body_id) => {
+                let argument_count = self.lir.bodies().get(*body_id).parameter_count();
+                self.create_function(
+                    builder,
+                    self.bodies[body_id.to_usize()],
+                    argument_count,
+                    &[],
+                )
+            }
+        };
+
+        let address = self.data_address(builder, self.constants[id.to_usize()]);
+        builder.ins().store(MemFlags::trusted(), value, address, 0);
+        values.insert(id, value);
+        Ok(value)
+    }
+    fn not_supported(&mut self, builder: &mut FunctionBuilder, what: &str) -> ModuleResult<Value> {
+        let symbol = self.string(builder, "NotSupported")?;
+        let what = self.string(builder, what)?;
+        let what = self.call_runtime(builder, "make_candy_text", &[what]);
+        Ok(self.call_runtime(builder, "make_candy_tag", &[symbol, what]))
+    }
+
+    /// Builtins that are used as values are wrapped in functions with the
+    /// calling convention of Candy functions.
+    fn builtin_wrapper(&mut self, builtin: BuiltinFunction) -> ModuleResult<FuncId> {
+        if let Some(id) = self.builtin_wrappers.get(&builtin) {
+            return Ok(*id);
+        }
+
+        let signature = candy_signature(&self.module, self.pointer_type, builtin.num_parameters());
+        let id = self.module.declare_function(
+            &format!("candy_builtin_wrapper_{builtin:?}"),
+            Linkage::Local,
+            &signature,
+        )?;
+        self.builtin_wrappers.insert(builtin, id);
+        Ok(id)
+    }
+    fn compile_builtin_wrapper(
+        &mut self,
+        context: &mut Context,
+        function_builder_context: &mut FunctionBuilderContext,
+        builtin: BuiltinFunction,
+        id: FuncId,
+    ) -> ModuleResult<()> {
+        context.func.signature =
+            candy_signature(&self.module, self.pointer_type, builtin.num_parameters());
+        context.func.name = UserFuncName::user(0, id.as_u32());
+        let mut builder = FunctionBuilder::new(&mut context.func, function_builder_context);
+        let entry = builder.create_block();
+        builder.append_block_params_for_function_params(entry);
+        builder.switch_to_block(entry);
+        builder.seal_block(entry);
+
+        let parameters = builder.block_params(entry).to_vec();
+        let arguments = &parameters[..parameters.len() - 1];
+        let return_value = self.call_builtin(&mut builder, builtin, arguments)?;
+        builder.ins().return_(&[return_value]);
+        builder.finalize();
+        self.define_function(context, id)
+    }
+
+    fn compile_main(
+        &mut self,
+        context: &mut Context,
+        function_builder_context: &mut FunctionBuilderContext,
+    ) -> ModuleResult<()> {
+        let mut signature = self.module.make_signature();
+        signature.returns.push(AbiParam::new(types::I32));
+        let main = self
+            .module
+            .declare_function("main", Linkage::Export, &signature)?;
+        context.func.signature = signature;
+        context.func.name = UserFuncName::user(0, main.as_u32());
+        let mut builder = FunctionBuilder::new(&mut context.func, function_builder_context);
+        let entry = builder.create_block();
+        builder.switch_to_block(entry);
+        builder.seal_block(entry);
+
+        self.call(&mut builder, self.init_constants, &[]);
+
+        // The module's body is compiled last and returns the `main` function.
+        let module_body = *self.bodies.last().unwrap();
+        let no_environment = builder.ins().iconst(self.pointer_type, 0);
+        let main_function = self
+            .call(&mut builder, module_body, &[no_environment])
+            .unwrap();
+
+        let environment = self.data_address(&mut builder, self.candy_environment);
+        let environment =
+            builder
+                .ins()
+                .load(self.pointer_type, MemFlags::trusted(), environment, 0);
+        self.call_function_value(&mut builder, main_function, vec![environment]);
+
+        let exit_code = builder.ins().iconst(types::I32, 0);
+        builder.ins().return_(&[exit_code]);
+        builder.finalize();
+        self.define_function(context, main)
+    }
+
+    fn compile_library_init(
+        &mut self,
+        context: &mut Context,
+        function_builder_context: &mut FunctionBuilderContext,
+        name: &str,
+    ) -> ModuleResult<()> {
+        let signature = self.module.make_signature();
+        let init =
+            self.module
+                .declare_function(&format!("{name}_init"), Linkage::Export, &signature)?;
+        context.func.signature = signature;
+        context.func.name = UserFuncName::user(0, init.as_u32());
+        let mut builder = FunctionBuilder::new(&mut context.func, function_builder_context);
+        let entry = builder.create_block();
+        builder.switch_to_block(entry);
+        builder.seal_block(entry);
+
+        self.call(&mut builder, self.init_constants, &[]);
+        let module_body = *self.bodies.last().unwrap();
+        let no_environment = builder.ins().iconst(self.pointer_type, 0);
+        self.call(&mut builder, module_body, &[no_environment]);
+
+        builder.ins().return_(&[]);
+        builder.finalize();
+        self.define_function(context, init)
+    }
+    /// Exported functions are constants, so they don't capture anything and
+    /// we can call their bodies directly.
+    fn compile_export(
+        &mut self,
+        context: &mut Context,
+        function_builder_context: &mut FunctionBuilderContext,
+        name: &str,
+        body_id: BodyId,
+    ) -> ModuleResult<()> {
+        let parameter_count = self.lir.bodies().get(body_id).parameter_count();
+        let mut signature = self.module.make_signature();
+        signature
+            .params
+            .extend((0..parameter_count).map(|_| AbiParam::new(self.pointer_type)));
+        signature.returns.push(AbiParam::new(self.pointer_type));
+        let export = self
+            .module
+            .declare_function(name, Linkage::Export, &signature)?;
+        context.func.signature = signature;
+        context.func.name = UserFuncName::user(0, export.as_u32());
+        let mut builder = FunctionBuilder::new(&mut context.func, function_builder_context);
+        let entry = builder.create_block();
+        builder.append_block_params_for_function_params(entry);
+        builder.switch_to_block(entry);
+        builder.seal_block(entry);
+
+        let mut arguments = builder.block_params(entry).to_vec();
+        arguments.push(builder.ins().iconst(self.pointer_type, 0));
+        let return_value = self
+            .call(&mut builder, self.bodies[body_id.to_usize()], &arguments)
+            .unwrap();
+        builder.ins().return_(&[return_value]);
+        builder.finalize();
+        self.define_function(context, export)
+    }
+    /// Returns the exports of the module that are constant functions, sorted
+    /// by name, and the names of all other exports.
+    fn exported_functions(&self) -> (Vec<(String, BodyId)>, Vec<String>) {
+        let (_, module_body) = self.lir.bodies().ids_and_bodies().last().unwrap();
+        let constant_of = |id: Id| {
+            let mut id = id;
+            loop {
+                match module_body.expression(id)? {
+                    Expression::Constant(constant_id) => return Some(*constant_id),
+                    Expression::Reference(referenced) => id = *referenced,
+                    _ => return None,
+                }
+            }
+        };
+        let Some(exports) = module_body.last_expression_id() else {
+            return (vec![], vec![]);
+        };
+        let entries = if let Some(exports) = constant_of(exports) {
+            let Constant::Struct(entries) = self.lir.constants().get(exports) else {
+                return (vec![], vec![]);
+            };
+            entries
+                .iter()
+                .map(|(key, value)| (Some(*key), Some(*value)))
+                .collect_vec()
+        } else if let Some(Expression::CreateStruct(entries)) = module_body.expression(exports) {
+            entries
+                .iter()
+                .map(|(key, value)| (constant_of(*key), constant_of(*value)))
+                .collect_vec()
+        } else {
+            return (vec![], vec![]);
+        };
+
+        let mut functions = vec![];
+        let mut skipped = vec![];
+        for (key, value) in entries {
+            let Some(name) = key.and_then(|key| match self.lir.constants().get(key) {
+                Constant::Tag {
+                    symbol,
+                    value: None,
+                } => Some(symbol.clone()),
+                Constant::Text(text) => Some(text.clone()),
+                _ => None,
+            }) else {
+                skipped.push("<unknown>".to_string());
+                continue;
+            };
+            match value.map(|value| self.lir.constants().get(value)) {
+                Some(Constant::Function(body_id)) => functions.push((name, *body_id)),
+                _ => skipped.push(name),
+            }
+        }
+        functions.sort();
+        skipped.sort();
+        (functions, skipped)
+    }
+
+    fn define_function(&mut self, context: &mut Context, id: FuncId) -> ModuleResult<()> {
+        if self.print_clif {
+            writeln!(self.clif, "{}", context.func.display()).unwrap();
+        }
+        self.module.define_function(id, context)?;
+        self.module.clear_context(context);
+        Ok(())
+    }
+
+    fn create_list(&mut self, builder: &mut FunctionBuilder, items: &[Value]) -> Value {
+        let items = self.create_null_terminated_array(builder, items);
+        self.call_runtime(builder, "make_candy_list", &[items])
+    }
+    fn create_struct(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        keys: &[Value],
+        values: &[Value],
+    ) -> Value {
+        let keys = self.create_null_terminated_array(builder, keys);
+        let values = self.create_null_terminated_array(builder, values);
+        self.call_runtime(builder, "make_candy_struct", &[keys, values])
+    }
+    /// The runtime doesn't store the number of arguments a function takes, so
+    /// we put it in front of the captured values in the function's
+    /// environment.
+    fn create_function(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        function: FuncId,
+        argument_count: usize,
+        captured: &[Value],
+    ) -> Value {
+        let function = self.module.declare_func_in_func(function, builder.func);
+        let function = builder.ins().func_addr(self.pointer_type, function);
+        let argument_count = builder
+            .ins()
+            .iconst(self.pointer_type, i64::try_from(argument_count).unwrap());
+        let mut environment = vec![argument_count];
+        environment.extend_from_slice(captured);
+        let environment_size = builder
+            .ins()
+            .iconst(types::I32, i64::try_from(environment.len()).unwrap());
+        let environment = self.create_array(builder, &environment);
+        self.call_runtime(
+            builder,
+            "make_candy_function",
+            &[function, environment, environment_size],
+        )
+    }
+    /// The runtime determines the length of lists and structs by looking for
+    /// a null pointer.
+    fn create_null_terminated_array(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        items: &[Value],
+    ) -> Value {
+        let null = builder.ins().iconst(self.pointer_type, 0);
+        let items = items.iter().copied().chain([null]).collect_vec();
+        self.create_array(builder, &items)
+    }
+    fn create_array(&mut self, builder: &mut FunctionBuilder, items: &[Value]) -> Value {
+        let size = builder
+            .ins()
+            .iconst(self.pointer_type, i64::from(self.offset(items.len())));
+        let array = self.call_runtime(builder, "malloc", &[size]);
+        for (index, item) in items.iter().enumerate() {
+            builder
+                .ins()
+                .store(MemFlags::trusted(), *item, array, self.offset(index));
+        }
+        array
+    }
+
+    /// Returns a pointer to a null-terminated copy of the string.
+    fn string(&mut self, builder: &mut FunctionBuilder, string: &str) -> ModuleResult<Value> {
+        let data = if let Some(data) = self.strings.get(string) {
+            *data
+        } else {
+            let data = self.module.declare_anonymous_data(false, false)?;
+            let mut bytes = string.as_bytes().to_vec();
+            bytes.push(0);
+            let mut description = DataDescription::new();
+            description.define(bytes.into_boxed_slice());
+            self.module.define_data(data, &description)?;
+            self.strings.insert(string.to_string(), data);
+            data
+        };
+        Ok(self.data_address(builder, data))
+    }
+    fn data_address(&mut self, builder: &mut FunctionBuilder, data: DataId) -> Value {
+        let global_value = self.module.declare_data_in_func(data, builder.func);
+        builder.ins().global_value(self.pointer_type, global_value)
+    }
+
+    fn call_runtime(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        name: &'static str,
+        arguments: &[Value],
+    ) -> Value {
+        let function = self.runtime_functions[name];
+        self.call(builder, function, arguments)
+            .unwrap_or_else(|| builder.ins().iconst(self.pointer_type, 0))
+    }
+    /// Returns the return value, if the function has one.
+    fn call(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        function: FuncId,
+        arguments: &[Value],
+    ) -> Option<Value> {
+        let function = self.module.declare_func_in_func(function, builder.func);
+        let call = builder.ins().call(function, arguments);
+        builder.inst_results(call).first().copied()
+    }
+
+    fn offset(&self, index: usize) -> i32 {
+        i32::try_from(index * self.pointer_type.bytes() as usize).unwrap()
+    }
+}
+
+/// Candy functions receive their arguments followed by a pointer to their
+/// captured values.
+fn candy_signature(module: &ObjectModule, pointer_type: Type, parameter_count: usize) -> Signature {
+    let mut signature = module.make_signature();
+    signature
+        .params
+        .extend((0..=parameter_count).map(|_| AbiParam::new(pointer_type)));
+    signature.returns.push(AbiParam::new(pointer_type));
+    signature
+}
+/// Converts names like `FooBar` and `foo bar` to `foo_bar`.
+fn c_identifier(name: &str) -> String {
+    let mut identifier = String::with_capacity(name.len());
+    for char in name.chars() {
+        if char.is_ascii_uppercase() {
+            if !identifier.is_empty() && !identifier.ends_with('_') {
+                identifier.push('_');
+            }
+            identifier.push(char.to_ascii_lowercase());
+        } else if char.is_ascii_alphanumeric() {
+            identifier.push(char);
+        } else if !identifier.ends_with('_') {
+            identifier.push('_');
+        }
+    }
+    if identifier.starts_with(|char: char| char.is_ascii_digit()) {
+        identifier.insert(0, '_');
+    }
+    identifier
+}
+fn variable(id: Id) -> Variable {
+    Variable::from_u32(u32::try_from(id.to_usize()).unwrap())
+}
+
+/// The name of the function implementing the builtin in the runtime.
+const fn runtime_builtin_name(builtin: BuiltinFunction) -> Option<&'static str> {
+    let name = match builtin {
+        BuiltinFunction::Equals => "candy_builtin_equals",
+        BuiltinFunction::IfElse => "candy_builtin_if_else",
+        BuiltinFunction::IntAdd => "candy_builtin_int_add",
+        BuiltinFunction::IntBitLength => "candy_builtin_int_bit_length",
+        BuiltinFunction::IntBitwiseAnd => "candy_builtin_int_bitwise_and",
+        BuiltinFunction::IntBitwiseOr => "candy_builtin_int_bitwise_or",
+        BuiltinFunction::IntBitwiseXor => "candy_builtin_int_bitwise_xor",
+        BuiltinFunction::IntCompareTo => "candy_builtin_int_compare_to",
+        BuiltinFunction::IntSubtract => "candy_builtin_int_subtract",
+        BuiltinFunction::ListLength => "candy_builtin_list_length",
+        BuiltinFunction::Print => "candy_builtin_print",
+        BuiltinFunction::StructGet => "candy_builtin_struct_get",
+        BuiltinFunction::StructGetKeys => "candy_builtin_struct_get_keys",
+        BuiltinFunction::StructHasKey => "candy_builtin_struct_has_key",
+        BuiltinFunction::TagGetValue => "candy_builtin_tag_get_value",
+        BuiltinFunction::TagHasValue => "candy_builtin_tag_has_value",
+        BuiltinFunction::TagWithoutValue => "candy_builtin_tag_without_value",
+        BuiltinFunction::TextConcatenate => "candy_builtin_text_concatenate",
+        BuiltinFunction::ToDebugText => "candy_builtin_to_debug_text",
+        BuiltinFunction::TypeOf => "candy_builtin_type_of",
+        _ => return None,
+    };
+    Some(name)
+}
+
+pub struct CandyObject {
+    clif: String,
+    product: ObjectProduct,
+}
+impl CandyObject {
+    /// The generated Cranelift IR if it was requested during compilation.
+    #[must_use]
+    pub fn clif(&self) -> &str {
+        &self.clif
+    }
+
+    /// Writes the object file to `{path}.o` and links it with the runtime to
+    /// an executable at `path`.
+    pub fn write_and_link(self, path: &str, build_runtime: bool, linker: &str) -> io::Result<()> {
+        let object_path = self.write(path, build_runtime)?;
+        run(Command::new(linker).args([
+            &object_path,
+            &format!("{RUNTIME_PATH}candy_runtime.a"),
+            "-o",
+            path,
+        ]))
+    }
+
+    /// Writes the object file to `{path}.o` and returns that path.
+    fn write(self, path: &str, build_runtime: bool) -> io::Result<String> {
+        let object_path = format!("{path}.o");
+        let object = self.product.emit().map_err(io::Error::other)?;
+        fs::write(&object_path, object)?;
+
+        if build_runtime {
+            Command::new("make")
+                .args(["-C", RUNTIME_PATH, "clean"])
+                .spawn()?
+                .wait()?;
+            Command::new("make")
+                .args(["-C", RUNTIME_PATH, "candy_runtime.a"])
+                .spawn()?
+                .wait()?;
+        }
+        Ok(object_path)
+    }
+}
+
+pub struct CandyLibrary {
+    name: String,
+    /// The names of the exported C functions and their parameter counts.
+    functions: Vec<(String, usize)>,
+    skipped_exports: Vec<String>,
+    object: CandyObject,
+}
+impl CandyLibrary {
+    /// The generated Cranelift IR if it was requested during compilation.
+    #[must_use]
+    pub fn clif(&self) -> &str {
+        self.object.clif()
+    }
+
+    /// Exports of the module that couldn't be exposed as C functions because
+    /// they aren't functions or aren't known at compile time.
+    #[must_use]
+    pub fn skipped_exports(&self) -> &[String] {
+        &self.skipped_exports
+    }
+
+    /// A C header declaring the exported functions.
+    ///
+    /// Values are passed as `candy_value_t *` and can be created and inspected
+    /// with the functions from `candy_runtime.h`.
+    #[must_use]
+    pub fn header(&self) -> String {
+        let guard = format!("__CANDY_{}_H", self.name.to_ascii_uppercase());
+        let mut header = format!(
+            "#ifndef {guard}\n#define {guard}\n\n#include \"candy_runtime.h\"\n\n\
+             // Runs the module's top-level code. Call this before any other function.\n\
+             void {}_init(void);\n",
+            self.name,
+        );
+        if !self.functions.is_empty() {
+            header.push('\n');
+        }
+        for (name, parameter_count) in &self.functions {
+            let parameters = if *parameter_count == 0 {
+                "void".to_string()
+            } else {
+                (0..*parameter_count)
+                    .map(|index| format!("candy_value_t *argument{index}"))
+                    .join(", ")
+            };
+            writeln!(header, "candy_value_t *{name}({parameters});").unwrap();
+        }
+        writeln!(header, "\n#endif").unwrap();
+        header
+    }
+
+    /// Writes the object file to `{path}.o`, a static library containing it
+    /// and the runtime to `lib{path}.a`, and the header to `{path}.h`.
+    pub fn write(self, path: &str, build_runtime: bool) -> io::Result<()> {
+        fs::write(format!("{path}.h"), self.header())?;
+
+        let object_path = self.object.write(path, build_runtime)?;
+        let library_path = format!("lib{path}.a");
+        fs::copy(format!("{RUNTIME_PATH}candy_runtime.a"), &library_path)?;
+        run(Command::new("ar").args(["rs", &library_path, &object_path]))
+    }
+}
+
+fn run(command: &mut Command) -> io::Result<()> {
+    let status = command.spawn()?.wait()?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "`{}` failed with {status}.",
+            command.get_program().to_string_lossy(),
+        )));
+    }
+    Ok(())
+}
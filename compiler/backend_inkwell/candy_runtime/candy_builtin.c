@@ -1,5 +1,6 @@
 #include <stdio.h>
 #include <stdint.h>
+#include <stdlib.h>
 #include <string.h>
 #include "candy_runtime.h"
 
@@ -15,6 +16,8 @@ const candy_value_t *candy_builtin_equals(candy_value_t *left, candy_value_t *ri
     case CANDY_TYPE_INT:
         return to_candy_bool(left->value.integer == right->value.integer);
         break;
+    case CANDY_TYPE_FLOAT:
+        return to_candy_bool(left->value.floating == right->value.floating);
     case CANDY_TYPE_TAG:
         return to_candy_bool(strcmp(left->value.text, right->value.text) == 0);
     default:
@@ -148,12 +151,38 @@ candy_value_t *candy_builtin_tag_without_value(candy_value_t *tag)
     return make_candy_tag(tag->value.tag.text, NULL);
 }
 
+candy_value_t *candy_builtin_text_concatenate(candy_value_t *left, candy_value_t *right)
+{
+    size_t left_length = strlen(left->value.text);
+    size_t right_length = strlen(right->value.text);
+    char *text = malloc(left_length + right_length + 1);
+    memcpy(text, left->value.text, left_length);
+    memcpy(text + left_length, right->value.text, right_length + 1);
+    candy_value_t *result = make_candy_text(text);
+    free(text);
+    return result;
+}
+
+candy_value_t *candy_builtin_to_debug_text(candy_value_t *value)
+{
+    char *text = NULL;
+    size_t length = 0;
+    FILE *stream = open_memstream(&text, &length);
+    fprint_candy_value(stream, value);
+    fclose(stream);
+    candy_value_t *result = make_candy_text(text);
+    free(text);
+    return result;
+}
+
 const candy_value_t *candy_builtin_type_of(candy_value_t *value)
 {
     switch (value->type)
     {
     case CANDY_TYPE_INT:
         return &__internal_int;
+    case CANDY_TYPE_FLOAT:
+        return &__internal_float;
     case CANDY_TYPE_TEXT:
         return &__internal_text;
     case CANDY_TYPE_TAG:
@@ -14,6 +14,8 @@ const candy_value_t *candy_builtin_print(candy_value_t *value);
 candy_value_t *candy_builtin_struct_get(candy_value_t *structure, candy_value_t *key);
 candy_value_t *candy_builtin_struct_get_keys(candy_value_t *structure);
 const candy_value_t *candy_builtin_tag_has_value(candy_value_t *tag);
+candy_value_t *candy_builtin_text_concatenate(candy_value_t *left, candy_value_t *right);
+candy_value_t *candy_builtin_to_debug_text(candy_value_t *value);
 candy_value_t *candy_builtin_tag_get_value(candy_value_t *tag);
 candy_value_t *candy_builtin_tag_without_value(candy_value_t *tag);
 const candy_value_t *candy_builtin_struct_has_key(candy_value_t *structure, candy_value_t *key);
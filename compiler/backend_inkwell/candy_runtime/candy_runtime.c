@@ -30,6 +30,7 @@ const candy_value_t __internal_equal = {
     .type = CANDY_TYPE_TAG};
 
 const candy_value_t __internal_int = {.value = {.text = "Int"}, .type = CANDY_TYPE_TAG};
+const candy_value_t __internal_float = {.value = {.text = "Float"}, .type = CANDY_TYPE_TAG};
 const candy_value_t __internal_text = {.value = {.text = "Text"}, .type = CANDY_TYPE_TAG};
 const candy_value_t __internal_tag = {.value = {.text = "Tag"}, .type = CANDY_TYPE_TAG};
 const candy_value_t __internal_list = {.value = {.text = "List"}, .type = CANDY_TYPE_TAG};
@@ -44,26 +45,29 @@ candy_value_t _candy_environment = {
 // Not particularly elegant, but this is a temporary solution anyway...
 candy_value_t *candy_environment = &_candy_environment;
 
-void print_candy_value(const candy_value_t *value)
+void fprint_candy_value(FILE *stream, const candy_value_t *value)
 {
     switch (value->type)
     {
     case CANDY_TYPE_INT:
-        printf("%ld", value->value.integer);
+        fprintf(stream, "%ld", value->value.integer);
+        break;
+    case CANDY_TYPE_FLOAT:
+        fprintf(stream, "%g", value->value.floating);
         break;
     case CANDY_TYPE_TEXT:
-        printf("%s", value->value.text);
+        fprintf(stream, "%s", value->value.text);
         break;
     case CANDY_TYPE_TAG:
-        printf("%s", value->value.tag.text);
+        fprintf(stream, "%s", value->value.tag.text);
         if (value->value.tag.value)
         {
-            printf(" ");
-            print_candy_value(value->value.tag.value);
+            fprintf(stream, " ");
+            fprint_candy_value(stream, value->value.tag.value);
         }
         break;
     case CANDY_TYPE_LIST:
-        printf("(");
+        fprintf(stream, "(");
         candy_value_t *length = candy_builtin_list_length(value);
         size_t list_length = length->value.integer;
         free_candy_value(length);
@@ -71,32 +75,37 @@ void print_candy_value(const candy_value_t *value)
         switch (list_length)
         {
         case 1:
-            print_candy_value(value->value.list[0]);
+            fprint_candy_value(stream, value->value.list[0]);
         case 0:
-            printf(",");
+            fprintf(stream, ",");
             break;
         default:
             for (size_t index = 0; index < list_length; index++)
             {
-                print_candy_value(value->value.list[index]);
+                fprint_candy_value(stream, value->value.list[index]);
                 if (index != list_length - 1)
                 {
-                    printf(", ");
+                    fprintf(stream, ", ");
                 }
             }
             break;
         }
-        printf(")");
+        fprintf(stream, ")");
         break;
     case CANDY_TYPE_FUNCTION:
-        printf("Function %p", value->value.function.function);
+        fprintf(stream, "Function %p", value->value.function.function);
         break;
     default:
-        printf("<unknown type %d>", value->type);
+        fprintf(stream, "<unknown type %d>", value->type);
         break;
     }
 }
 
+void print_candy_value(const candy_value_t *value)
+{
+    fprint_candy_value(stdout, value);
+}
+
 const candy_value_t *to_candy_bool(int value)
 {
     return value ? &__internal_true : &__internal_false;
@@ -129,6 +138,14 @@ candy_value_t *make_candy_int(int64_t value)
     return candy_value;
 }
 
+candy_value_t *make_candy_float(double value)
+{
+    candy_value_t *candy_value = malloc(sizeof(candy_value_t));
+    candy_value->value.floating = value;
+    candy_value->type = CANDY_TYPE_FLOAT;
+    return candy_value;
+}
+
 candy_value_t *make_candy_text(char *text)
 {
     candy_value_t *candy_value = malloc(sizeof(candy_value_t));
@@ -2,6 +2,7 @@
 #define __CANDY_RT_H
 
 #include <stdint.h>
+#include <stdio.h>
 
 typedef enum
 {
@@ -11,6 +12,7 @@ typedef enum
     CANDY_TYPE_LIST,
     CANDY_TYPE_STRUCT,
     CANDY_TYPE_FUNCTION,
+    CANDY_TYPE_FLOAT,
 } candy_type_t;
 
 typedef struct
@@ -36,6 +38,7 @@ typedef struct candy_value
     union
     {
         int64_t integer;
+        double floating;
         char *text;
         candy_tag_t tag;
         struct candy_value **list;
@@ -54,6 +57,7 @@ const extern candy_value_t __internal_less;
 const extern candy_value_t __internal_equal;
 const extern candy_value_t __internal_greater;
 const extern candy_value_t __internal_int;
+const extern candy_value_t __internal_float;
 const extern candy_value_t __internal_text;
 const extern candy_value_t __internal_tag;
 const extern candy_value_t __internal_list;
@@ -64,10 +68,12 @@ const extern candy_value_t __internal_platform;
 extern candy_value_t _candy_environment;
 extern candy_value_t *candy_environment;
 
+void fprint_candy_value(FILE *stream, const candy_value_t *value);
 void print_candy_value(const candy_value_t *value);
 const candy_value_t *to_candy_bool(int value);
 int candy_tag_to_bool(const candy_value_t *value);
 candy_value_t *make_candy_int(int64_t value);
+candy_value_t *make_candy_float(double value);
 candy_value_t *make_candy_text(char *text);
 candy_value_t *make_candy_tag(char *tag, candy_value_t *value);
 candy_value_t *make_candy_list(candy_value_t **values);
@@ -187,12 +187,18 @@ impl<'ctx> CodeGen<'ctx> {
         let i8_type = self.context.i8_type();
         let i32_type = self.context.i32_type();
         let i64_type = self.context.i64_type();
+        let f64_type = self.context.f64_type();
 
         self.add_function(
             "make_candy_int",
             &[i64_type.into()],
             self.candy_value_pointer_type,
         );
+        self.add_function(
+            "make_candy_float",
+            &[f64_type.into()],
+            self.candy_value_pointer_type,
+        );
         self.add_function(
             "make_candy_tag",
             &[
@@ -354,6 +360,21 @@ impl<'ctx> CodeGen<'ctx> {
 
                     Some(global.as_basic_value_enum())
                 }
+                Expression::Float(value) => {
+                    let f64_type = self.context.f64_type();
+                    let v = f64_type.const_float(value.into_inner());
+
+                    let make_candy_float = self.module.get_function("make_candy_float").unwrap();
+                    let call = self.builder.build_call(make_candy_float, &[v.into()], "");
+
+                    let global = self.create_global(
+                        &format!("float_{value}"),
+                        *id,
+                        call.try_as_basic_value().unwrap_left(),
+                    );
+
+                    Some(global.as_basic_value_enum())
+                }
                 Expression::Text(text) => {
                     let string = self.make_str_literal(text);
                     let make_candy_text = self.module.get_function("make_candy_text").unwrap();
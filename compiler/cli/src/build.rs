@@ -0,0 +1,326 @@
+use crate::{
+    bundle::{self, Bundle},
+    database::Database,
+    diagnostics::Diagnostic,
+    output::OutputFormat,
+    utils::{module_for_path, packages_path, parse_mir_passes},
+    Exit, ProgramResult,
+};
+#[cfg(feature = "cranelift")]
+use candy_backend_cranelift::CodeGen;
+#[cfg(feature = "cranelift")]
+use candy_frontend::lir::Lir;
+use candy_frontend::{
+    hir_to_mir::ExecutionTarget,
+    lir_optimize::OptimizeLir,
+    mir_optimize::MirPasses,
+    module,
+    timings::{self, Stage, Timings},
+    TracingConfig,
+};
+use clap::{Parser, ValueEnum, ValueHint};
+use itertools::Itertools;
+use serde_json::{json, Value};
+use std::{cmp::Reverse, path::PathBuf, time::Duration};
+#[cfg(feature = "cranelift")]
+use tracing::warn;
+use tracing::{error, info};
+
+/// Compile a Candy program ahead of time.
+///
+/// This command compiles the given file, or, if no file is provided, the package of
+/// your current working directory. For executables, the module should export a
+/// `main` function. This function is then called with an environment.
+///
+/// With `--emit bundle`, the program and all modules it uses are written to a
+/// single `<name>.candybundle` file that `candy run` can execute without the
+/// source tree.
+///
+/// The other outputs use the experimental Cranelift backend and are only
+/// available if the CLI is built with the `cranelift` feature. With
+/// `--emit staticlib`, the exported functions are made available to C code
+/// through a static library `lib<name>.a` and a header `<name>.h`. The header
+/// includes `candy_runtime.h` from `compiler/backend_inkwell/candy_runtime`.
+/// The Cranelift backend only supports a subset of Candy: ints, texts, tags,
+/// lists, structs, functions, and some builtins.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Parser, Debug)]
+pub struct Options {
+    /// What to generate.
+    #[cfg_attr(
+        feature = "cranelift",
+        arg(long, value_enum, default_value_t = Emit::Native)
+    )]
+    #[cfg_attr(
+        not(feature = "cranelift"),
+        arg(long, value_enum, default_value_t = Emit::Bundle)
+    )]
+    emit: Emit,
+
+    /// If enabled, build the Candy runtime from scratch.
+    #[cfg(feature = "cranelift")]
+    #[arg(long = "build-runtime", default_value_t = false)]
+    build_runtime: bool,
+
+    /// The program used for linking the executable with the runtime.
+    #[cfg(feature = "cranelift")]
+    #[arg(long, default_value = "cc")]
+    linker: String,
+
+    /// Report how much code each module contributes to the program.
+    ///
+    /// Imported modules are copied into each importing module, so this helps
+    /// to find modules that blow up the generated code.
+    #[arg(long)]
+    debug: bool,
+
+    /// Report how long each compiler stage and MIR optimization pass took and
+    /// which modules got compiled how often.
+    ///
+    /// Stages don't include the time spent in nested stages, e.g., the MIR
+    /// optimization of a module doesn't include compiling the modules it uses.
+    #[arg(long)]
+    timings: bool,
+
+    /// Enable or disable MIR optimization passes, e.g., `-inline,-cse`.
+    ///
+    /// The built-in passes are `reference-following`, `constant-folding`,
+    /// `inline`, `constant-lifting`, `after-panic`, `cse`, `call-tracing`, and
+    /// `tree-shaking`. All of them are enabled by default.
+    #[arg(long, value_name = "CHANGES", value_parser = parse_mir_passes)]
+    mir_passes: Option<MirPasses>,
+
+    /// Validate the MIR after each optimization pass. If a pass produces
+    /// invalid MIR, report the pass and how it changed the MIR.
+    ///
+    /// This makes compiling a lot slower.
+    #[arg(long)]
+    check_mir_passes: bool,
+
+    /// The file or package to compile. If none is provided, compile the package
+    /// of your current working directory.
+    #[arg(value_hint = ValueHint::FilePath)]
+    path: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Emit {
+    /// An executable next to the compiled file.
+    #[cfg(feature = "cranelift")]
+    Native,
+    /// A static library and a C header exposing the module's exported
+    /// functions.
+    #[cfg(feature = "cranelift")]
+    Staticlib,
+    /// The Cranelift IR, printed to stdout.
+    #[cfg(feature = "cranelift")]
+    Clif,
+    /// A bundle of the source code of all modules used by the program, which
+    /// can be run using `candy run`.
+    Bundle,
+}
+
+pub fn build(options: &Options, output: OutputFormat) -> ProgramResult {
+    let packages_path = packages_path();
+    let mut db = Database::new_with_file_system_module_provider(packages_path);
+    let mut mir_passes = options.mir_passes.clone().unwrap_or_default();
+    mir_passes.set_checked(options.check_mir_passes);
+    db.set_mir_passes(mir_passes);
+    let module = module_for_path(options.path.clone())?;
+    let name = options
+        .path
+        .as_ref()
+        .unwrap_or_else(|| match &module.package {
+            module::Package::User(user) => user,
+            module::Package::Managed(managed) => managed,
+            _ => unreachable!(),
+        })
+        .file_stem()
+        .map_or_else(
+            || "executable".to_string(),
+            |it| it.to_string_lossy().to_string(),
+        );
+
+    let target = match options.emit {
+        #[cfg(feature = "cranelift")]
+        Emit::Staticlib => ExecutionTarget::Module(module.clone()),
+        _ => ExecutionTarget::MainFunction(module.clone()),
+    };
+    if options.timings {
+        timings::start_recording();
+    }
+    let lir_result = db.optimized_lir(target, TracingConfig::off());
+    let timings = options.timings.then(timings::finish_recording);
+    let (lir, errors) = lir_result.map_err(|error| {
+        error!("Couldn't compile {module}: {error:?}");
+        Exit::FileNotFound
+    })?;
+    if let Some(timings) = &timings {
+        log_timings(timings);
+    }
+    let diagnostics = Diagnostic::from_compiler_errors(&db, errors.as_ref());
+    output.print_json(|| {
+        let diagnostics = diagnostics.iter().map(|it| it.to_json(&db)).collect_vec();
+        let mut result = json!({ "diagnostics": diagnostics });
+        if let Some(timings) = &timings {
+            result["timings"] = timings_to_json(timings);
+        }
+        result
+    });
+    if !diagnostics.is_empty() {
+        for diagnostic in &diagnostics {
+            error!("{}", diagnostic.render(&db));
+        }
+        return Err(Exit::CodeContainsErrors);
+    }
+
+    if options.debug {
+        info!("Code size by module:");
+        for (module, size) in lir
+            .code_size_by_module()
+            .into_iter()
+            .sorted_by_key(|(module, size)| (Reverse(size.expressions), module.clone()))
+        {
+            info!(
+                "  {module}: {} expressions in {} bodies ({} duplicated)",
+                size.expressions, size.bodies, size.duplicated_bodies,
+            );
+        }
+    }
+
+    match options.emit {
+        Emit::Bundle => {
+            let path = PathBuf::from(format!("{name}.{}", bundle::EXTENSION));
+            Bundle::new(&db, module).write(&path).map_err(|error| {
+                error!("Failed to write bundle: {error}");
+                Exit::ExternalError
+            })
+        }
+        #[cfg(feature = "cranelift")]
+        Emit::Native | Emit::Staticlib | Emit::Clif => build_with_cranelift(options, &lir, &name),
+    }
+}
+
+#[cfg(feature = "cranelift")]
+fn build_with_cranelift(options: &Options, lir: &Lir, name: &str) -> ProgramResult {
+    if matches!(options.emit, Emit::Staticlib) {
+        let library = CodeGen::new(lir, name)
+            .and_then(|codegen| codegen.compile_library(name, false))
+            .map_err(|error| {
+                error!("Failed to generate code: {error}");
+                Exit::ExternalError
+            })?;
+        for export in library.skipped_exports() {
+            warn!("The export `{export}` isn't a function known at compile time, so it's not available in the library.");
+        }
+        return library.write(name, options.build_runtime).map_err(|error| {
+            error!("Failed to write static library: {error}");
+            Exit::ExternalError
+        });
+    }
+
+    let print_clif = matches!(options.emit, Emit::Clif);
+    let object = CodeGen::new(lir, name)
+        .and_then(|codegen| codegen.compile(print_clif))
+        .map_err(|error| {
+            error!("Failed to generate code: {error}");
+            Exit::ExternalError
+        })?;
+    match options.emit {
+        Emit::Native => object
+            .write_and_link(name, options.build_runtime, &options.linker)
+            .map_err(|error| {
+                error!("Failed to compile and link executable: {error}");
+                Exit::ExternalError
+            }),
+        Emit::Clif => {
+            println!("{}", object.clif());
+            Ok(())
+        }
+        Emit::Staticlib | Emit::Bundle => unreachable!(),
+    }
+}
+
+fn log_timings(timings: &Timings) {
+    let total: Duration = timings.stages.values().sum::<Duration>()
+        + timings.passes.iter().map(|(_, it)| *it).sum::<Duration>();
+    let percentage = |duration: Duration| 100. * duration.as_secs_f64() / total.as_secs_f64();
+
+    info!("Compile time by stage:");
+    for (stage, duration) in timings.stages.iter().sorted_by_key(|(stage, _)| **stage) {
+        info!(
+            "  {stage:<28} {:>10.3?} {:>5.1} %",
+            duration,
+            percentage(*duration),
+        );
+        if *stage != Stage::MirOptimization {
+            continue;
+        }
+        for (pass, duration) in &timings.passes {
+            info!(
+                "    {pass:<26} {:>10.3?} {:>5.1} %",
+                duration,
+                percentage(*duration),
+            );
+        }
+    }
+    info!("  {:<28} {total:>10.3?}", "total");
+
+    info!("Compiled stages and folding cache hits by module:");
+    for (module, statistics) in timings
+        .modules
+        .iter()
+        .sorted_by_key(|(module, statistics)| {
+            (
+                Reverse(statistics.computed.values().sum::<usize>()),
+                (*module).clone(),
+            )
+        })
+    {
+        let computed = statistics
+            .computed
+            .iter()
+            .sorted()
+            .map(|(stage, count)| format!("{stage} ×{count}"))
+            .join(", ");
+        info!(
+            "  {module}: computed {computed}; folded {} times, {} cache hits",
+            statistics.folding_requests,
+            statistics.folding_cache_hits(),
+        );
+    }
+}
+fn timings_to_json(timings: &Timings) -> Value {
+    let stages = timings
+        .stages
+        .iter()
+        .sorted_by_key(|(stage, _)| **stage)
+        .map(|(stage, duration)| {
+            json!({ "stage": stage.to_string(), "seconds": duration.as_secs_f64() })
+        })
+        .collect_vec();
+    let passes = timings
+        .passes
+        .iter()
+        .map(|(pass, duration)| json!({ "pass": pass, "seconds": duration.as_secs_f64() }))
+        .collect_vec();
+    let modules = timings
+        .modules
+        .iter()
+        .sorted_by_key(|(module, _)| (*module).clone())
+        .map(|(module, statistics)| {
+            let computed = statistics
+                .computed
+                .iter()
+                .map(|(stage, count)| (stage.to_string(), json!(count)))
+                .collect::<serde_json::Map<_, _>>();
+            json!({
+                "module": module.to_string(),
+                "computed": computed,
+                "foldingRequests": statistics.folding_requests,
+                "foldingCacheHits": statistics.folding_cache_hits(),
+            })
+        })
+        .collect_vec();
+    json!({ "stages": stages, "mirOptimizationPasses": passes, "modules": modules })
+}
@@ -0,0 +1,75 @@
+//! Bundles contain a program together with all modules it uses, so they can be
+//! run without the source tree.
+//!
+//! A bundle stores the source code of the modules rather than compiled code:
+//! How a program gets compiled depends on options of `candy run`, such as
+//! which calls to trace, so it's compiled again when running the bundle.
+
+use crate::{database::Database, Exit};
+use candy_frontend::{
+    module::{BundleModuleProvider, Module, ModuleProviderOwner},
+    module_graph::module_graph,
+};
+use serde::{Deserialize, Serialize};
+use std::{fs, io, path::Path};
+use tracing::error;
+
+pub const EXTENSION: &str = "candybundle";
+
+/// Incremented whenever the format changes incompatibly.
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Bundle {
+    format_version: u32,
+    compiler_version: String,
+    pub entry: Module,
+    pub modules: BundleModuleProvider,
+}
+impl Bundle {
+    /// Bundles the `entry` module and all modules it uses, directly or
+    /// indirectly.
+    pub fn new(db: &Database, entry: Module) -> Self {
+        let provider = db.get_module_provider();
+        let mut modules = BundleModuleProvider::builder();
+        for node in module_graph(db, &entry).modules {
+            if let Some(content) = provider.get_content(&node.module) {
+                modules.add(node.module, content.as_ref().clone());
+            }
+        }
+        Self {
+            format_version: FORMAT_VERSION,
+            compiler_version: env!("CARGO_PKG_VERSION").to_string(),
+            entry,
+            modules: modules.build(),
+        }
+    }
+
+    #[must_use]
+    pub fn is_bundle(path: &Path) -> bool {
+        path.extension().is_some_and(|it| it == EXTENSION)
+    }
+
+    pub fn read(path: &Path) -> Result<Self, Exit> {
+        let content = fs::read(path).map_err(|error| {
+            error!("Couldn't read the bundle: {error}");
+            Exit::FileNotFound
+        })?;
+        let bundle: Self = serde_json::from_slice(&content).map_err(|error| {
+            error!("The bundle is invalid: {error}");
+            Exit::FileNotFound
+        })?;
+        if bundle.format_version != FORMAT_VERSION {
+            error!(
+                "The bundle was created by Candy {} using format version {}, but this version of Candy only supports format version {FORMAT_VERSION}.",
+                bundle.compiler_version, bundle.format_version,
+            );
+            return Err(Exit::FileNotFound);
+        }
+        Ok(bundle)
+    }
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, serde_json::to_vec(self).unwrap())
+    }
+}
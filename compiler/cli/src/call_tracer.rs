@@ -0,0 +1,124 @@
+use crate::database::Database;
+use candy_frontend::{
+    format::{MaxLength, Precedence},
+    module::PackagesPath,
+};
+use candy_vm::{
+    heap::{Heap, HirId, InlineObject, ToDebugText},
+    tracer::{stack_trace::Call, stack_trace::StackTracer, Tracer},
+    Panic,
+};
+use std::{env::current_dir, io::Write, path::PathBuf};
+
+const MAX_VALUE_LENGTH: MaxLength = MaxLength::Limited(60);
+
+/// Streams a human-readable call tree to a writer while the program runs.
+///
+/// Each call is printed on its own line, indented by its depth in the call
+/// stack and followed by the source location of the call site. Return values
+/// and evaluated expressions are printed one level deeper than the call they
+/// belong to. Tail calls replace the current call, so they are printed on the
+/// same level.
+pub struct CallTreeTracer<'a> {
+    db: &'a Database,
+    packages_path: &'a PackagesPath,
+    current_directory: Option<PathBuf>,
+    output: Box<dyn Write + 'a>,
+    depth: usize,
+}
+impl<'a> CallTreeTracer<'a> {
+    pub fn new(
+        db: &'a Database,
+        packages_path: &'a PackagesPath,
+        output: Box<dyn Write + 'a>,
+    ) -> Self {
+        Self {
+            db,
+            packages_path,
+            current_directory: current_dir().ok(),
+            output,
+            depth: 0,
+        }
+    }
+
+    fn print_call(
+        &mut self,
+        marker: &str,
+        call_site: HirId,
+        callee: InlineObject,
+        arguments: Vec<InlineObject>,
+        responsible: HirId,
+    ) {
+        let call = Call {
+            call_site,
+            callee,
+            arguments,
+            responsible,
+        };
+        let (location, call) = StackTracer::format_call(
+            self.db,
+            self.packages_path,
+            self.current_directory.as_deref(),
+            &call,
+            Some(MAX_VALUE_LENGTH),
+        );
+        self.print_line(&format!("{marker}{call}  ({location})"));
+    }
+    fn print_line(&mut self, line: &str) {
+        let indentation = "  ".repeat(self.depth);
+        // Tracing is best-effort: If the output is closed, the program should
+        // still run to completion.
+        let _ = writeln!(self.output, "{indentation}{line}");
+    }
+}
+impl Tracer for CallTreeTracer<'_> {
+    fn value_evaluated(&mut self, _heap: &mut Heap, expression: HirId, value: InlineObject) {
+        self.print_line(&format!(
+            "{} = {}",
+            expression.get(),
+            value.to_debug_text(Precedence::Low, MAX_VALUE_LENGTH),
+        ));
+    }
+
+    fn call_started(
+        &mut self,
+        _heap: &mut Heap,
+        call_site: HirId,
+        callee: InlineObject,
+        arguments: Vec<InlineObject>,
+        responsible: HirId,
+    ) {
+        self.print_call("", call_site, callee, arguments, responsible);
+        self.depth += 1;
+    }
+    fn call_ended(&mut self, _heap: &mut Heap, return_value: Option<InlineObject>) {
+        if let Some(return_value) = return_value {
+            self.print_line(&format!(
+                "→ {}",
+                return_value.to_debug_text(Precedence::Low, MAX_VALUE_LENGTH),
+            ));
+        }
+        self.depth = self.depth.saturating_sub(1);
+    }
+    fn tail_call(
+        &mut self,
+        _heap: &mut Heap,
+        call_site: HirId,
+        callee: InlineObject,
+        arguments: Vec<InlineObject>,
+        responsible: HirId,
+    ) {
+        self.depth = self.depth.saturating_sub(1);
+        self.print_call("↪ ", call_site, callee, arguments, responsible);
+        self.depth += 1;
+    }
+
+    fn panicked(&mut self, _heap: &mut Heap, panic: &Panic) {
+        self.print_line(&format!("💥 {}", panic.reason));
+    }
+}
+impl Drop for CallTreeTracer<'_> {
+    fn drop(&mut self) {
+        let _ = self.output.flush();
+    }
+}
@@ -1,17 +1,24 @@
 use crate::{
     database::Database,
+    diagnostics::{Diagnostic, Severity},
+    output::OutputFormat,
     utils::{module_for_path, packages_path},
     Exit, ProgramResult,
 };
-use candy_frontend::{ast_to_hir::AstToHir, hir::CollectErrors};
+use candy_frontend::{
+    ast_to_hir::AstToHir, hir::CollectErrors, lint::lint_module, static_panics::static_panics,
+};
 use clap::{arg, Parser, ValueHint};
+use itertools::Itertools;
+use serde_json::json;
 use std::path::PathBuf;
-use tracing::warn;
+use tracing::{error, info, warn};
 
 /// Check a Candy program for obvious errors.
 ///
-/// This command finds very obvious errors in your program. For more extensive
-/// error reporting, fuzzing the Candy program is recommended instead.
+/// This command finds very obvious errors in your program and runs the lints
+/// configured in your package's `_package.candy` file. For more extensive error
+/// reporting, fuzzing the Candy program is recommended instead.
 #[derive(Parser, Debug)]
 pub struct Options {
     /// The file or package to check. If none is provided, the package of your
@@ -20,7 +27,7 @@ pub struct Options {
     path: Option<PathBuf>,
 }
 
-pub fn check(options: Options) -> ProgramResult {
+pub fn check(options: Options, output: OutputFormat) -> ProgramResult {
     let packages_path = packages_path();
     let db = Database::new_with_file_system_module_provider(packages_path);
     let module = module_for_path(options.path)?;
@@ -29,15 +36,47 @@ pub fn check(options: Options) -> ProgramResult {
     // This will return a tuple containing the MIR and errors, even from
     // imported modules.
 
-    let (hir, _) = db.hir(module).unwrap();
+    let (hir, _) = db.hir(module.clone()).unwrap();
     let mut errors = vec![];
     hir.collect_errors(&mut errors);
-    let has_errors = !errors.is_empty();
+    let mut has_errors = !errors.is_empty();
+    let mut diagnostics = Diagnostic::from_compiler_errors(&db, &errors);
+    for diagnostic in &diagnostics {
+        warn!("{}", diagnostic.render(&db));
+    }
 
-    for error in errors {
-        warn!("{}", error.to_string_with_location(&db));
+    for violation in lint_module(&db, &module) {
+        let diagnostic = Diagnostic::from_lint_violation(violation);
+        match diagnostic.severity {
+            Severity::Info => info!("{}", diagnostic.render(&db)),
+            Severity::Warning => warn!("{}", diagnostic.render(&db)),
+            Severity::Error => {
+                error!("{}", diagnostic.render(&db));
+                has_errors = true;
+            }
+        }
+        diagnostics.push(diagnostic);
     }
 
+    for panic in static_panics(&db, &module) {
+        let Some(diagnostic) = Diagnostic::from_static_panic(&db, &panic) else {
+            continue;
+        };
+        match diagnostic.severity {
+            Severity::Error => {
+                error!("{}", diagnostic.render(&db));
+                has_errors = true;
+            }
+            Severity::Warning | Severity::Info => warn!("{}", diagnostic.render(&db)),
+        }
+        diagnostics.push(diagnostic);
+    }
+
+    output.print_json(|| {
+        let diagnostics = diagnostics.iter().map(|it| it.to_json(&db)).collect_vec();
+        json!({ "diagnostics": diagnostics })
+    });
+
     if has_errors {
         Err(Exit::CodeContainsErrors)
     } else {
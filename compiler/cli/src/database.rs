@@ -8,7 +8,7 @@ use candy_frontend::{
     hir::HirDbStorage,
     hir_to_mir::HirToMirStorage,
     lir_optimize::OptimizeLirStorage,
-    mir_optimize::OptimizeMirStorage,
+    mir_optimize::{MirPasses, MirPassesOwner, OptimizeMirStorage},
     mir_to_lir::MirToLirStorage,
     module::{
         FileSystemModuleProvider, GetModuleContentQuery, InMemoryModuleProvider, Module,
@@ -17,7 +17,7 @@ use candy_frontend::{
     },
     position::PositionConversionStorage,
     rcst_to_cst::RcstToCstStorage,
-    string_to_rcst::StringToRcstStorage,
+    string_to_rcst::{RcstCacheOwner, StringToRcstStorage},
 };
 
 #[cfg_attr(
@@ -60,6 +60,7 @@ use candy_frontend::{
 pub struct Database {
     storage: salsa::Storage<Self>,
     module_provider: OverlayModuleProvider<InMemoryModuleProvider, Box<dyn ModuleProvider + Send>>,
+    mir_passes: MirPasses,
 }
 impl salsa::Database for Database {}
 
@@ -74,8 +75,13 @@ impl Database {
                 InMemoryModuleProvider::default(),
                 module_provider,
             ),
+            mir_passes: MirPasses::new(),
         }
     }
+
+    pub fn set_mir_passes(&mut self, mir_passes: MirPasses) {
+        self.mir_passes = mir_passes;
+    }
 }
 
 impl ModuleProviderOwner for Database {
@@ -83,6 +89,12 @@ impl ModuleProviderOwner for Database {
         &self.module_provider
     }
 }
+impl RcstCacheOwner for Database {}
+impl MirPassesOwner for Database {
+    fn mir_passes(&self) -> &MirPasses {
+        &self.mir_passes
+    }
+}
 impl MutableModuleProviderOwner for Database {
     fn get_in_memory_module_provider(&mut self) -> &mut InMemoryModuleProvider {
         &mut self.module_provider.overlay
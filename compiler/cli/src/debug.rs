@@ -124,6 +124,9 @@ pub struct PathAndExecutionTargetAndTracing {
         require_equals(true)
     )]
     trace_evaluated_expressions: TracingMode,
+
+    #[arg(long)]
+    memoize_calls: bool,
 }
 impl PathAndExecutionTargetAndTracing {
     #[must_use]
@@ -132,6 +135,8 @@ impl PathAndExecutionTargetAndTracing {
             register_fuzzables: self.register_fuzzables,
             calls: self.trace_calls,
             evaluated_expressions: self.trace_evaluated_expressions,
+            memoize_calls: self.memoize_calls,
+            overrides: Vec::new(),
         }
     }
 }
@@ -189,7 +194,7 @@ pub fn debug(options: Options) -> ProgramResult {
             let module = module_for_path(options.path.clone())?;
             let execution_target = options.execution_target.resolve(module.clone());
             let tracing = options.to_tracing_config();
-            let mir = db.mir(execution_target, tracing);
+            let mir = db.mir(execution_target, tracing.clone());
             mir.ok()
                 .map(|(mir, _)| RichIr::for_mir(&module, &mir, tracing))
         }
@@ -197,7 +202,7 @@ pub fn debug(options: Options) -> ProgramResult {
             let module = module_for_path(options.path.clone())?;
             let execution_target = options.execution_target.resolve(module.clone());
             let tracing = options.to_tracing_config();
-            let mir = db.optimized_mir(execution_target, tracing);
+            let mir = db.optimized_mir(execution_target, tracing.clone());
             mir.ok()
                 .map(|(mir, _)| RichIr::for_optimized_mir(&module, &mir, tracing))
         }
@@ -205,7 +210,7 @@ pub fn debug(options: Options) -> ProgramResult {
             let module = module_for_path(options.path.clone())?;
             let execution_target = options.execution_target.resolve(module.clone());
             let tracing = options.to_tracing_config();
-            let lir = db.lir(execution_target, tracing);
+            let lir = db.lir(execution_target, tracing.clone());
             lir.ok()
                 .map(|(lir, _)| RichIr::for_lir(&module, &lir, tracing))
         }
@@ -213,7 +218,7 @@ pub fn debug(options: Options) -> ProgramResult {
             let module = module_for_path(options.path.clone())?;
             let execution_target = options.execution_target.resolve(module.clone());
             let tracing = options.to_tracing_config();
-            let lir = db.optimized_lir(execution_target, tracing);
+            let lir = db.optimized_lir(execution_target, tracing.clone());
             lir.ok()
                 .map(|(lir, _)| RichIr::for_optimized_lir(&module, &lir, tracing))
         }
@@ -221,8 +226,8 @@ pub fn debug(options: Options) -> ProgramResult {
             let module = module_for_path(options.path.clone())?;
             let execution_target = options.execution_target.resolve(module.clone());
             let tracing = options.to_tracing_config();
-            let (vm_byte_code, _) = compile_byte_code(&db, execution_target, tracing);
-            Some(RichIr::for_byte_code(&module, &vm_byte_code, tracing))
+            let (vm_byte_code, _) = compile_byte_code(&db, execution_target, tracing.clone());
+            Some(RichIr::for_byte_code(&module, &vm_byte_code, &tracing))
         }
         #[cfg(feature = "inkwell")]
         Options::LlvmIr(options) => {
@@ -261,7 +266,7 @@ pub fn debug(options: Options) -> ProgramResult {
                 TokenType::Function => Color::Blue,
                 TokenType::Comment => Color::Green,
                 TokenType::Text => Color::Cyan,
-                TokenType::Int => Color::Red,
+                TokenType::Int | TokenType::Float => Color::Red,
                 TokenType::Address => Color::BrightGreen,
                 TokenType::Constant => Color::BrightYellow,
             };
@@ -353,6 +358,8 @@ impl GoldOptions {
         register_fuzzables: TracingMode::Off,
         calls: CallTracingMode::OnlyForPanicTraces,
         evaluated_expressions: TracingMode::Off,
+        memoize_calls: false,
+        overrides: Vec::new(),
     };
 
     fn visit_irs(
@@ -437,7 +444,7 @@ impl GoldOptions {
             let (vm_byte_code, _) =
                 compile_byte_code(db, execution_target.clone(), Self::TRACING_CONFIG);
             let vm_byte_code_rich_ir =
-                RichIr::for_byte_code(&module, &vm_byte_code, Self::TRACING_CONFIG);
+                RichIr::for_byte_code(&module, &vm_byte_code, &Self::TRACING_CONFIG);
             visit(
                 "VM Byte Code",
                 Self::format_byte_code(&vm_byte_code, &vm_byte_code_rich_ir),
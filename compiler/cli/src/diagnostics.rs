@@ -0,0 +1,283 @@
+//! Renders compiler errors and lint violations together with the affected
+//! source code:
+//!
+//! ```text
+//! error[E0057]: `foo` is not in scope.
+//!   --> Examples:helloWorld:3:3
+//!    |
+//!  3 |   foo
+//!    |   ^^^
+//! For more information about this error, run `candy explain E0057`.
+//! ```
+
+use candy_frontend::{
+    ast_to_hir::AstToHir,
+    cst::CstDb,
+    error::CompilerError,
+    error_codes,
+    lint::{LintSeverity, LintViolation},
+    module::{Module, ModuleDb},
+    position::{Offset, Position, PositionConversionDb},
+    static_panics::{StaticPanic, StaticPanicKind},
+};
+use colored::{Color, Colorize};
+use itertools::Itertools;
+use serde_json::{json, Value};
+use std::{fmt::Write, ops::Range};
+
+/// Spans covering more lines only show the first and last few of them.
+const MAX_LINES_PER_SNIPPET: usize = 6;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+impl Severity {
+    const fn label(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+            Self::Info => "info",
+        }
+    }
+    const fn color(self) -> Color {
+        match self {
+            Self::Error => Color::Red,
+            Self::Warning => Color::Yellow,
+            Self::Info => Color::Blue,
+        }
+    }
+}
+
+pub struct Diagnostic {
+    pub module: Module,
+    pub span: Range<Offset>,
+    pub severity: Severity,
+    /// The code of the compiler error (e.g., `E0057`) or the name of the lint.
+    pub code: &'static str,
+    pub message: String,
+    pub notes: Vec<Note>,
+}
+/// Additional information pointing to related code.
+pub struct Note {
+    pub module: Module,
+    pub span: Range<Offset>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Sorted by their position.
+    pub fn from_compiler_errors<'a>(
+        db: &impl CstDb,
+        errors: impl IntoIterator<Item = &'a CompilerError>,
+    ) -> Vec<Self> {
+        errors
+            .into_iter()
+            .sorted_by_key(|it| (it.module.clone(), it.span.start, it.span.end))
+            .map(|it| Self::from_compiler_error(db, it))
+            .collect()
+    }
+    pub fn from_compiler_error(db: &impl CstDb, error: &CompilerError) -> Self {
+        let notes = error
+            .to_related_information()
+            .into_iter()
+            .map(|(module, cst_id, message)| Note {
+                span: db.find_cst(module.clone(), cst_id).display_span(),
+                module,
+                message,
+            })
+            .collect();
+        Self {
+            module: error.module.clone(),
+            span: error.span.clone(),
+            severity: Severity::Error,
+            code: error.payload.code(),
+            message: error.payload.to_string(),
+            notes,
+        }
+    }
+    #[must_use]
+    pub fn from_lint_violation(violation: LintViolation) -> Self {
+        let severity = match violation.severity {
+            LintSeverity::Off => unreachable!(),
+            LintSeverity::Info => Severity::Info,
+            LintSeverity::Warning => Severity::Warning,
+            LintSeverity::Error => Severity::Error,
+        };
+        Self {
+            module: violation.module,
+            span: violation.span,
+            severity,
+            code: violation.lint,
+            message: violation.message,
+            notes: vec![],
+        }
+    }
+
+    /// Code panicking whenever it runs is an error, functions panicking for
+    /// all arguments are a warning since they might never get called.
+    pub fn from_static_panic(db: &impl AstToHir, panic: &StaticPanic) -> Option<Self> {
+        let span = db.hir_id_to_display_span(&panic.responsible)?;
+        let (severity, message) = match panic.kind {
+            StaticPanicKind::Always => (Severity::Error, panic.reason.clone()),
+            StaticPanicKind::WhenCalled => (
+                Severity::Warning,
+                format!(
+                    "`{}` panics for all arguments: {}",
+                    panic.responsible.function_name(),
+                    panic.reason,
+                ),
+            ),
+        };
+        Some(Self {
+            module: panic.responsible.module.clone(),
+            span,
+            severity,
+            code: "static-panic",
+            message,
+            notes: vec![],
+        })
+    }
+
+    pub fn render<DB>(&self, db: &DB) -> String
+    where
+        DB: ModuleDb + PositionConversionDb,
+    {
+        let color = self.severity.color();
+        let mut rendered = format!(
+            "{} {}\n",
+            format!("{}[{}]:", self.severity.label(), self.code)
+                .color(color)
+                .bold(),
+            self.message.bold(),
+        );
+        render_snippet(&mut rendered, db, &self.module, &self.span, '^', color);
+        for note in &self.notes {
+            writeln!(rendered, "{} {}", "note:".bold(), note.message).unwrap();
+            render_snippet(
+                &mut rendered,
+                db,
+                &note.module,
+                &note.span,
+                '-',
+                Color::Blue,
+            );
+        }
+        if error_codes::explain(self.code).is_some() {
+            writeln!(
+                rendered,
+                "For more information about this error, run `candy explain {}`.",
+                self.code,
+            )
+            .unwrap();
+        }
+        rendered.truncate(rendered.trim_end().len());
+        rendered
+    }
+
+    pub fn to_json(&self, db: &impl PositionConversionDb) -> Value {
+        let notes = self
+            .notes
+            .iter()
+            .map(|note| {
+                json!({
+                    "module": note.module.to_string(),
+                    "range": range_to_json(db, &note.module, note.span.clone()),
+                    "message": note.message,
+                })
+            })
+            .collect_vec();
+        json!({
+            "module": self.module.to_string(),
+            "range": range_to_json(db, &self.module, self.span.clone()),
+            "severity": self.severity.label(),
+            "code": self.code,
+            "message": self.message,
+            "notes": notes,
+        })
+    }
+}
+
+fn render_snippet<DB>(
+    rendered: &mut String,
+    db: &DB,
+    module: &Module,
+    span: &Range<Offset>,
+    marker: char,
+    color: Color,
+) where
+    DB: ModuleDb + PositionConversionDb,
+{
+    let start = db.offset_to_position(module.clone(), span.start);
+    writeln!(rendered, "  {} {module}:{start}", "-->".blue().bold()).unwrap();
+    let Some(source) = db.get_module_content_as_string(module.clone()) else {
+        return;
+    };
+
+    let start = (*span.start).min(source.len());
+    let end = (*span.end).clamp(start, source.len());
+    let lines = source.split('\n').collect_vec();
+    let first_line = source[..start].matches('\n').count();
+    // The newline ending a line isn't shown, so a span ending right after it
+    // doesn't cover the next line.
+    let last_line = if end > start {
+        source[..end - 1].matches('\n').count()
+    } else {
+        first_line
+    };
+
+    let gutter_width = (last_line + 1).to_string().len();
+    let gutter = |line_number: Option<usize>| {
+        let line_number = line_number.map_or_else(String::new, |it| (it + 1).to_string());
+        format!("{line_number:>gutter_width$} |").blue().bold()
+    };
+    writeln!(rendered, "{}", gutter(None)).unwrap();
+
+    let mut line_start = lines[..first_line]
+        .iter()
+        .map(|it| it.len() + 1)
+        .sum::<usize>();
+    for (index, line) in lines
+        .iter()
+        .enumerate()
+        .take(last_line + 1)
+        .skip(first_line)
+    {
+        let is_shown = index < first_line + MAX_LINES_PER_SNIPPET / 2
+            || index + MAX_LINES_PER_SNIPPET / 2 > last_line;
+        if is_shown {
+            let line = line.trim_end_matches('\r');
+            let marker_start = start.saturating_sub(line_start).min(line.len());
+            let marker_end = end
+                .saturating_sub(line_start)
+                .clamp(marker_start, line.len());
+            let indentation = line[..marker_start].chars().count();
+            let width = line[marker_start..marker_end].chars().count().max(1);
+            writeln!(rendered, "{} {line}", gutter(Some(index))).unwrap();
+            writeln!(
+                rendered,
+                "{} {}{}",
+                gutter(None),
+                " ".repeat(indentation),
+                marker.to_string().repeat(width).color(color).bold(),
+            )
+            .unwrap();
+        } else if index == first_line + MAX_LINES_PER_SNIPPET / 2 {
+            writeln!(rendered, "{}", "...".blue().bold()).unwrap();
+        }
+        line_start += line.len() + 1;
+    }
+}
+
+fn range_to_json(db: &impl PositionConversionDb, module: &Module, span: Range<Offset>) -> Value {
+    let range = db.range_to_positions(module.clone(), span);
+    json!({
+        "start": position_to_json(range.start),
+        "end": position_to_json(range.end),
+    })
+}
+fn position_to_json(position: Position) -> Value {
+    json!({ "line": position.line, "character": position.character })
+}
@@ -0,0 +1,23 @@
+use crate::{Exit, ProgramResult};
+use candy_frontend::error_codes;
+use clap::Parser;
+use tracing::error;
+
+/// Explain a compiler error.
+///
+/// This command prints a longer description of the error with the given code,
+/// including examples of code causing it and how to fix it.
+#[derive(Parser, Debug)]
+pub struct Options {
+    /// The code of the error, e.g., `E0057`.
+    code: String,
+}
+
+pub fn explain(options: &Options) -> ProgramResult {
+    let Some(explanation) = error_codes::explain(&options.code) else {
+        error!("There's no error with the code `{}`.", options.code);
+        return Err(Exit::ErrorCodeNotFound);
+    };
+    println!("{explanation}");
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use crate::{utils::packages_path, Exit, ProgramResult};
+use candy_formatter::{Formatter, FormatterConfig, TrailingCommaPolicy};
+use candy_frontend::{rcst_to_cst::RcstsToCstsExt, string_to_rcst::parse_rcst};
+use clap::{arg, Parser, ValueHint};
+use std::{
+    env::current_dir,
+    fs,
+    path::{Path, PathBuf},
+};
+use tracing::{error, info};
+use walkdir::WalkDir;
+
+/// Format Candy files.
+#[derive(Parser, Debug)]
+pub struct Options {
+    /// The file or directory to format. If none is provided, the package of
+    /// your current working directory will be formatted.
+    #[arg(value_hint = ValueHint::AnyPath)]
+    path: Option<PathBuf>,
+
+    /// The maximum width of a line, including its indentation.
+    #[arg(long, default_value_t = FormatterConfig::default().max_line_width)]
+    max_line_width: usize,
+
+    /// The number of spaces per indentation level.
+    #[arg(long, default_value_t = FormatterConfig::default().indent_width)]
+    indent_width: usize,
+
+    /// When to add a comma after the last item of a list or struct: `always`,
+    /// `never`, or `multiline-only`.
+    #[arg(long, default_value = "multiline-only")]
+    trailing_comma: TrailingCommaPolicy,
+}
+
+pub fn format(options: Options) -> ProgramResult {
+    let config = FormatterConfig {
+        max_line_width: options.max_line_width,
+        indent_width: options.indent_width,
+        trailing_comma: options.trailing_comma,
+    };
+
+    let path = if let Some(path) = options.path {
+        if !path.exists() {
+            error!("The given file or directory doesn't exist.");
+            return Err(Exit::FileNotFound);
+        }
+        path
+    } else {
+        let packages_path = packages_path();
+        let Some(package) = packages_path.find_surrounding_package(&current_dir().unwrap()) else {
+            error!("You are not in a Candy package. Either navigate into a package or specify a Candy file or directory.");
+            return Err(Exit::NotInCandyPackage);
+        };
+        package.to_path(&packages_path).unwrap()
+    };
+
+    for file in WalkDir::new(path)
+        .into_iter()
+        .map(Result::unwrap)
+        .filter(|it| it.file_type().is_file())
+        .filter(|it| it.file_name().to_string_lossy().ends_with(".candy"))
+    {
+        format_file(file.path(), config);
+    }
+    Ok(())
+}
+
+fn format_file(path: &Path, config: FormatterConfig) {
+    let source = fs::read_to_string(path).unwrap();
+    let formatted = parse_rcst(&source)
+        .to_csts()
+        .format_to_string_with_config(config);
+    if formatted != source {
+        fs::write(path, formatted).unwrap();
+        info!("Formatted {}", path.display());
+    }
+}
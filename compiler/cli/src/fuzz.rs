@@ -1,12 +1,16 @@
 use crate::{
     database::Database,
     debug,
+    output::OutputFormat,
     utils::{module_for_path, packages_path},
     Exit, ProgramResult,
 };
+use candy_frontend::format::MaxLength;
+use candy_fuzzer::{FuzzingOptions, FuzzingReport, DEFAULT_INSTRUCTIONS_PER_FUNCTION};
 use clap::{Parser, ValueHint};
-use std::path::PathBuf;
-use tracing::{error, info};
+use serde_json::json;
+use std::{fs, path::PathBuf, time::Duration};
+use tracing::{error, info, warn};
 
 /// Fuzz a Candy module.
 ///
@@ -21,26 +25,100 @@ pub struct Options {
     /// current working directory will be fuzzed.
     #[arg(value_hint = ValueHint::FilePath)]
     path: Option<PathBuf>,
+
+    /// Only fuzz the function with this HIR path, e.g., `fibonacci:fibRec`.
+    #[arg(long, value_name = "PATH")]
+    function: Option<String>,
+
+    /// How many instructions to run per function before considering it fine.
+    #[arg(long, value_name = "INSTRUCTIONS", default_value_t = DEFAULT_INSTRUCTIONS_PER_FUNCTION)]
+    instructions_per_function: usize,
+
+    /// Stop fuzzing after this many seconds. Functions that weren't fuzzed by
+    /// then are reported as skipped.
+    #[arg(long, value_name = "SECONDS")]
+    time_limit: Option<u64>,
+
+    /// Write the results as JSON to this file, e.g., for CI.
+    #[arg(long, value_hint = ValueHint::FilePath)]
+    json_report: Option<PathBuf>,
 }
 
-pub fn fuzz(options: Options) -> ProgramResult {
+pub fn fuzz(options: Options, output: OutputFormat) -> ProgramResult {
     let db = Database::new_with_file_system_module_provider(packages_path());
     let module = module_for_path(options.path)?;
 
     debug!("Fuzzing `{module}`…");
-    let failing_cases = candy_fuzzer::fuzz(&db, module);
+    let fuzzing_options = FuzzingOptions {
+        function: options.function,
+        instructions_per_function: options.instructions_per_function,
+        time_limit: options.time_limit.map(Duration::from_secs),
+    };
+    let report = candy_fuzzer::fuzz(&db, module, &fuzzing_options);
+
+    if let Some(path) = &options.json_report {
+        let json = serde_json::to_string_pretty(&report_to_json(&report)).unwrap();
+        if let Err(error) = fs::write(path, json) {
+            error!("Couldn't write the JSON report: {error}");
+        }
+    }
+    output.print_json(|| report_to_json(&report));
 
-    if failing_cases.is_empty() {
+    if let Some(function) = &fuzzing_options.function {
+        if report.fuzzed_functions.is_empty() && report.skipped_functions.is_empty() {
+            error!("There's no fuzzable function `{function}`.");
+            return Err(Exit::FunctionNotFound);
+        }
+    }
+    if !report.skipped_functions.is_empty() {
+        warn!(
+            "The time limit was exceeded before {} functions could be fuzzed.",
+            report.skipped_functions.len(),
+        );
+    }
+
+    if report.failing_cases().next().is_none() {
         info!("All found fuzzable functions seem fine.");
         Ok(())
     } else {
         error!("");
         error!("Finished fuzzing.");
         error!("These are the failing cases:");
-        for case in failing_cases {
+        for case in report.failing_cases() {
             error!("");
             case.dump(&db);
         }
         Err(Exit::FuzzingFoundFailingCases)
     }
 }
+
+fn report_to_json(report: &FuzzingReport) -> serde_json::Value {
+    let fuzzed_functions = report
+        .fuzzed_functions
+        .iter()
+        .map(|function| {
+            let failing_case = function.failing_case.as_ref().map(|case| {
+                json!({
+                    "input": case.input().to_debug_text(MaxLength::Unlimited),
+                    "reason": case.panic().reason,
+                    "responsible": case.panic().responsible.to_string(),
+                })
+            });
+            json!({
+                "function": function.function.to_string(),
+                "instructions": function.instructions,
+                "coverage": function.coverage,
+                "failingCase": failing_case,
+            })
+        })
+        .collect::<Vec<_>>();
+    let skipped_functions = report
+        .skipped_functions
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    json!({
+        "fuzzedFunctions": fuzzed_functions,
+        "skippedFunctions": skipped_functions,
+    })
+}
@@ -0,0 +1,147 @@
+use crate::{
+    database::Database,
+    output::OutputFormat,
+    utils::{module_for_path, packages_path},
+    ProgramResult,
+};
+use candy_frontend::{
+    module::Module,
+    module_graph::{module_graph, Folding, ModuleGraph},
+};
+use clap::{Parser, ValueHint};
+use itertools::Itertools;
+use rustc_hash::FxHashSet;
+use serde_json::{json, Value};
+use std::{fmt::Write, path::PathBuf};
+use tracing::warn;
+
+/// Print the graph of modules used by a Candy program.
+///
+/// This command follows the `use`s starting at the given file or the package of
+/// your current working directory and prints the modules it finds as a DOT
+/// graph. With `--output json`, it prints a JSON object instead.
+///
+/// Each module is annotated with its number of expressions after optimization.
+/// Small modules get copied into every module using them, so modules that are
+/// used a lot should stay small or become big enough to be folded only once.
+#[derive(Parser, Debug)]
+pub struct Options {
+    /// The file or package to start at. If none is provided, the package of
+    /// your current working directory will be used.
+    #[arg(value_hint = ValueHint::FilePath)]
+    path: Option<PathBuf>,
+}
+
+pub fn graph(options: Options, output: OutputFormat) -> ProgramResult {
+    let packages_path = packages_path();
+    let db = Database::new_with_file_system_module_provider(packages_path);
+    let module = module_for_path(options.path)?;
+
+    let graph = module_graph(&db, &module);
+    for cycle in &graph.cycles {
+        warn!(
+            "There's a cycle of module uses: {}",
+            cycle
+                .iter()
+                .chain(cycle.first())
+                .map(ToString::to_string)
+                .join(" → "),
+        );
+    }
+
+    match output {
+        OutputFormat::Text => print!("{}", to_dot(&graph)),
+        OutputFormat::Json => output.print_json(|| to_json(&graph)),
+    }
+    Ok(())
+}
+
+fn to_dot(graph: &ModuleGraph) -> String {
+    let index_of = |module: &Module| {
+        graph
+            .modules
+            .iter()
+            .position(|it| &it.module == module)
+            .unwrap()
+    };
+    let cycle_edges = cycle_edges(graph);
+
+    let mut dot = "digraph modules {\n".to_string();
+    for (index, node) in graph.modules.iter().enumerate() {
+        let (size, style) = match (node.expressions, node.folding()) {
+            (Some(expressions), Some(Folding::Duplicated)) => {
+                (format!("{expressions} expressions"), "dashed")
+            }
+            (Some(expressions), _) => (format!("{expressions} expressions"), "solid"),
+            (None, _) => ("doesn't compile".to_string(), "dotted"),
+        };
+        writeln!(
+            dot,
+            "  m{index} [label={:?}, style={style}];",
+            format!("{}\n{size}", node.module),
+        )
+        .unwrap();
+    }
+    for module_use in &graph.uses {
+        let label = if module_use.count == 1 {
+            String::new()
+        } else {
+            format!(", label=\"{}×\"", module_use.count)
+        };
+        let color = if cycle_edges.contains(&(&module_use.user, &module_use.used)) {
+            ", color=red"
+        } else {
+            ""
+        };
+        writeln!(
+            dot,
+            "  m{} -> m{}{label}{color};",
+            index_of(&module_use.user),
+            index_of(&module_use.used),
+        )
+        .unwrap();
+    }
+    dot.push_str("}\n");
+    dot
+}
+fn cycle_edges(graph: &ModuleGraph) -> FxHashSet<(&Module, &Module)> {
+    graph
+        .cycles
+        .iter()
+        .flat_map(|cycle| cycle.iter().zip(cycle.iter().cycle().skip(1)).collect_vec())
+        .collect()
+}
+
+fn to_json(graph: &ModuleGraph) -> Value {
+    let modules = graph
+        .modules
+        .iter()
+        .map(|node| {
+            json!({
+                "module": node.module.to_string(),
+                "expressions": node.expressions,
+                "folding": node.folding().map(|folding| match folding {
+                    Folding::Duplicated => "duplicated",
+                    Folding::OncePerScope => "oncePerScope",
+                }),
+            })
+        })
+        .collect_vec();
+    let uses = graph
+        .uses
+        .iter()
+        .map(|module_use| {
+            json!({
+                "user": module_use.user.to_string(),
+                "used": module_use.used.to_string(),
+                "count": module_use.count,
+            })
+        })
+        .collect_vec();
+    let cycles = graph
+        .cycles
+        .iter()
+        .map(|cycle| cycle.iter().map(ToString::to_string).collect_vec())
+        .collect_vec();
+    json!({ "modules": modules, "uses": uses, "cycles": cycles })
+}
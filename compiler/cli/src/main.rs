@@ -10,9 +10,11 @@
     clippy::too_many_lines
 )]
 
+use candy_language_server::config::LogLevel;
 use candy_vm::CAN_USE_STDOUT;
-use clap::Parser;
-use std::sync::atomic::Ordering;
+use clap::{Parser, Subcommand};
+use output::OutputFormat;
+use std::{process::ExitCode, sync::atomic::Ordering};
 use tracing::{debug, Level, Metadata};
 use tracing_subscriber::{
     filter,
@@ -20,62 +22,113 @@ use tracing_subscriber::{
     prelude::*,
 };
 
+mod build;
+mod bundle;
+mod call_tracer;
 mod check;
 mod database;
 mod debug;
+mod diagnostics;
+mod explain;
+mod format;
 mod fuzz;
+mod graph;
 #[cfg(feature = "inkwell")]
 mod inkwell;
 mod lsp;
+mod output;
+mod repl;
 mod run;
 mod utils;
 
 #[derive(Parser, Debug)]
 #[command(name = "candy", about = "The 🍭 Candy CLI.")]
-enum CandyOptions {
+struct CandyOptions {
+    /// How to report results.
+    ///
+    /// With `json`, `check`, `run`, `fuzz`, `graph`, and `build` print a single
+    /// JSON object describing their result to stdout.
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
     Run(run::Options),
 
     Check(check::Options),
 
+    Format(format::Options),
+
     Fuzz(fuzz::Options),
 
+    Explain(explain::Options),
+
+    Graph(graph::Options),
+
+    Repl(repl::Options),
+
     #[command(subcommand)]
     Debug(debug::Options),
 
     /// Start a Language Server.
     Lsp,
 
+    Build(build::Options),
+
     #[cfg(feature = "inkwell")]
     Inkwell(inkwell::Options),
 }
 
 #[tokio::main]
-async fn main() -> ProgramResult {
-    let options = CandyOptions::parse();
+async fn main() -> ExitCode {
+    let CandyOptions { output, command } = CandyOptions::parse();
 
-    let should_log_to_stdout = !matches!(options, CandyOptions::Lsp);
+    // The LSP and the JSON output need stdout for themselves.
+    let should_log_to_stdout = !matches!(command, Command::Lsp) && output == OutputFormat::Text;
     init_logger(should_log_to_stdout);
     CAN_USE_STDOUT.store(should_log_to_stdout, Ordering::Relaxed);
 
-    match options {
-        CandyOptions::Run(options) => run::run(options),
-        CandyOptions::Check(options) => check::check(options),
-        CandyOptions::Fuzz(options) => fuzz::fuzz(options),
-        CandyOptions::Debug(options) => debug::debug(options),
-        CandyOptions::Lsp => lsp::lsp().await,
+    let result = match command {
+        Command::Run(options) => run::run(options, output),
+        Command::Check(options) => check::check(options, output),
+        Command::Format(options) => format::format(options),
+        Command::Fuzz(options) => fuzz::fuzz(options, output),
+        Command::Explain(options) => explain::explain(&options),
+        Command::Graph(options) => graph::graph(options, output),
+        Command::Debug(options) => debug::debug(options),
+        Command::Repl(options) => repl::repl(&options),
+        Command::Lsp => lsp::lsp().await,
+        Command::Build(options) => build::build(&options, output),
         #[cfg(feature = "inkwell")]
-        CandyOptions::Inkwell(options) => inkwell::compile(&options),
+        Command::Inkwell(options) => inkwell::compile(&options),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(exit) => {
+            debug!("Exiting because of {exit:?}.");
+            ExitCode::from(exit.code())
+        }
     }
 }
 
 pub type ProgramResult = Result<(), Exit>;
+/// Why the CLI exits unsuccessfully.
+///
+/// Each kind of failure has its own exit code so that scripts can react to
+/// them. Exit code 2 is used by clap for invalid arguments.
 #[derive(Debug)]
 pub enum Exit {
     CodePanicked,
     DirectoryNotFound,
-    #[cfg(feature = "inkwell")]
+    ErrorCodeNotFound,
+    ExecutionLimitExceeded,
     ExternalError,
     FileNotFound,
+    FunctionNotFound,
     FuzzingFoundFailingCases,
     NotInCandyPackage,
     CodeContainsErrors,
@@ -83,6 +136,25 @@ pub enum Exit {
     LlvmError(String),
     GoldOutdated,
 }
+impl Exit {
+    #[must_use]
+    pub const fn code(&self) -> u8 {
+        match self {
+            Self::ExternalError => 1,
+            #[cfg(feature = "inkwell")]
+            Self::LlvmError(_) => 1,
+            Self::CodeContainsErrors => 3,
+            Self::CodePanicked => 4,
+            Self::FuzzingFoundFailingCases | Self::GoldOutdated => 5,
+            Self::DirectoryNotFound
+            | Self::ErrorCodeNotFound
+            | Self::FileNotFound
+            | Self::FunctionNotFound
+            | Self::NotInCandyPackage => 6,
+            Self::ExecutionLimitExceeded => 7,
+        }
+    }
+}
 
 fn init_logger(use_stdout: bool) {
     let writer = if use_stdout {
@@ -124,6 +196,14 @@ fn init_logger(use_stdout: bool) {
             "candy_language_server::features_candy::analyzer::module_analyzer",
             Level::INFO,
         )))
+        .with_filter(filter::filter_fn(|metadata| {
+            // Configured by the language server's client.
+            !metadata
+                .module_path()
+                .unwrap_or_default()
+                .starts_with("candy_language_server")
+                || metadata.level() <= &LogLevel::current()
+        }))
         .with_filter(filter::filter_fn(level_for("candy_vm", Level::DEBUG)))
         .with_filter(filter::filter_fn(level_for("candy_vm::heap", Level::DEBUG)));
     tracing_subscriber::registry().with(console_log).init();
@@ -0,0 +1,21 @@
+use clap::ValueEnum;
+use serde_json::Value;
+
+/// How commands report their results.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable log messages.
+    #[default]
+    Text,
+    /// A single JSON object on stdout. Log messages and output of the Candy
+    /// program are written to stderr instead.
+    Json,
+}
+impl OutputFormat {
+    /// Prints the result of a command if the JSON output is enabled.
+    pub fn print_json(self, result: impl FnOnce() -> Value) {
+        if self == Self::Json {
+            println!("{}", serde_json::to_string_pretty(&result()).unwrap());
+        }
+    }
+}
@@ -0,0 +1,258 @@
+use crate::{database::Database, diagnostics::Diagnostic, utils::packages_path, ProgramResult};
+use candy_frontend::{
+    cst::{Cst, CstError, CstKind, UnwrapWhitespaceAndComment},
+    format::{MaxLength, Precedence},
+    hir_to_mir::ExecutionTarget,
+    module::{Module, ModuleKind, MutableModuleProviderOwner, Package},
+    string_to_rcst::parse_rcst,
+    TracingConfig,
+};
+use candy_vm::{
+    heap::{Data, InlineObject, ToDebugText},
+    lir_to_byte_code::compile_byte_code,
+    Runtime, RuntimeError,
+};
+use clap::Parser;
+use itertools::Itertools;
+use std::io::{self, Write};
+
+/// Evaluate Candy code interactively.
+///
+/// Each input is either a definition, such as `foo = 42` or `[int] = use
+/// "Core"`, which is available in all later inputs, or an expression, whose
+/// value is printed. If an input contains unclosed brackets or ends with an
+/// assignment sign, it continues on the next line, e.g., for functions
+/// spanning multiple lines.
+///
+/// Type `:help` to see the available commands.
+#[derive(Parser, Debug)]
+pub struct Options {}
+
+pub fn repl(_options: &Options) -> ProgramResult {
+    let mut repl = Repl::new();
+    println!("Welcome to the 🍭 Candy REPL! Type `:help` to see the available commands.");
+
+    loop {
+        let mut input = String::new();
+        let mut prompt = "» ";
+        loop {
+            print!("{prompt}");
+            io::stdout().flush().unwrap();
+            let mut line = String::new();
+            if io::stdin().read_line(&mut line).unwrap() == 0 {
+                return Ok(());
+            }
+            let line = line.trim_end_matches(['\n', '\r']);
+            if !input.is_empty() {
+                input.push('\n');
+            }
+            input.push_str(line);
+
+            // An empty line submits incomplete input, e.g., to see the
+            // resulting errors.
+            if line.trim().is_empty() || !is_incomplete(&input) {
+                break;
+            }
+            prompt = "… ";
+        }
+
+        if let Some(command) = input.trim().strip_prefix(':') {
+            let (command, argument) = command.split_once(' ').unwrap_or((command, ""));
+            match command {
+                "help" | "h" => print_help(),
+                "type" | "t" => repl.print_type(argument),
+                "definitions" | "d" => repl.print_definitions(),
+                "reset" => repl.reset(),
+                "quit" | "q" => return Ok(()),
+                _ => println!(
+                    "Unknown command `:{command}`. Type `:help` to see the available commands."
+                ),
+            }
+        } else {
+            repl.handle_input(&input);
+        }
+    }
+}
+
+fn print_help() {
+    println!("Enter a definition (e.g., `foo = 42`) or an expression to evaluate it.");
+    println!();
+    println!("Commands:");
+    println!("  :type <expression>  Show the type of the expression's value.");
+    println!("  :definitions        Show the definitions entered so far.");
+    println!("  :reset              Forget all definitions.");
+    println!("  :help               Show this help.");
+    println!("  :quit               Exit the REPL (or press Ctrl+D).");
+}
+
+/// The REPL keeps the definitions entered so far in a synthetic module.
+/// Evaluating an input compiles this module with the input appended. Salsa
+/// reuses the compilation results of unchanged parts, such as used packages.
+///
+/// Candy code doesn't have side effects outside of handles, which aren't
+/// available at the module level. So, re-running earlier definitions always
+/// results in the same values and we don't have to keep the VM around.
+struct Repl {
+    db: Database,
+    module: Module,
+    definitions: Vec<String>,
+}
+impl Repl {
+    /// The name of the export containing the value of an evaluated
+    /// expression.
+    const RESULT_NAME: &'static str = "replResult";
+
+    fn new() -> Self {
+        let mut db = Database::new_with_file_system_module_provider(packages_path());
+        let module = Module {
+            package: Package::Anonymous {
+                url: "repl".to_string(),
+            },
+            path: vec![],
+            kind: ModuleKind::Code,
+        };
+        db.did_open_module(&module, vec![]);
+        Self {
+            db,
+            module,
+            definitions: vec![],
+        }
+    }
+
+    fn handle_input(&mut self, input: &str) {
+        let rcsts = parse_rcst(input);
+        let mut parts = rcsts.iter().filter(|it| !it.is_whitespace_or_comment());
+        let Some(first) = parts.next() else {
+            return;
+        };
+        let is_definition = |cst: &Cst<()>| {
+            matches!(
+                cst.unwrap_whitespace_and_comment().kind,
+                CstKind::Assignment { .. },
+            )
+        };
+        if is_definition(first) && parts.all(is_definition) {
+            self.define(input);
+        } else if let Some((runtime, value)) = self.evaluate(input) {
+            println!(
+                "{}",
+                value.to_debug_text(Precedence::Low, MaxLength::Unlimited),
+            );
+            drop(runtime);
+        }
+    }
+
+    /// Keeps the definitions if they compile and evaluate successfully.
+    fn define(&mut self, definitions: &str) {
+        let source = self
+            .definitions
+            .iter()
+            .map(String::as_str)
+            .chain([definitions])
+            .join("\n");
+        if self.run(source).is_some() {
+            self.definitions.push(definitions.to_string());
+        }
+    }
+
+    /// Returns the runtime since the value lives on its heap.
+    fn evaluate(&mut self, expression: &str) -> Option<(Runtime, InlineObject)> {
+        let expression = expression
+            .lines()
+            .map(|line| format!("  {line}"))
+            .join("\n");
+        let source = self
+            .definitions
+            .iter()
+            .map(String::as_str)
+            .chain([format!("{} :=\n{expression}", Self::RESULT_NAME).as_str()])
+            .join("\n");
+        let mut runtime = self.run(source)?;
+        match runtime.exported_value(Self::RESULT_NAME) {
+            Ok(value) => Some((runtime, value)),
+            Err(error) => {
+                println!("{error}");
+                None
+            }
+        }
+    }
+
+    /// Compiles and runs the module with the given source code. Errors and
+    /// panics are printed.
+    fn run(&mut self, source: String) -> Option<Runtime> {
+        self.db.did_change_module(&self.module, source.into_bytes());
+        let (byte_code, errors) = compile_byte_code(
+            &self.db,
+            ExecutionTarget::Module(self.module.clone()),
+            TracingConfig::off(),
+        );
+        if !errors.is_empty() {
+            for diagnostic in Diagnostic::from_compiler_errors(&self.db, errors.iter()) {
+                println!("{}", diagnostic.render(&self.db));
+            }
+            return None;
+        }
+
+        let mut runtime = Runtime::new(byte_code);
+        match runtime.run_module() {
+            Ok(_) => Some(runtime),
+            Err(RuntimeError::Panicked { panic, .. }) => {
+                println!(
+                    "Panicked: {} ({} is responsible.)",
+                    panic.reason, panic.responsible
+                );
+                None
+            }
+            Err(error) => {
+                println!("{error}");
+                None
+            }
+        }
+    }
+
+    fn print_type(&mut self, expression: &str) {
+        if let Some((_runtime, value)) = self.evaluate(expression) {
+            let type_: &'static str = Data::from(value).into();
+            println!("{type_}");
+        }
+    }
+    fn print_definitions(&self) {
+        if self.definitions.is_empty() {
+            println!("There are no definitions yet.");
+        }
+        for definition in &self.definitions {
+            println!("{definition}");
+        }
+    }
+    fn reset(&mut self) {
+        self.definitions.clear();
+        println!("Forgot all definitions.");
+    }
+}
+
+/// Whether the input continues on the next line because it contains unclosed
+/// brackets or texts, or ends with an assignment sign or match.
+fn is_incomplete(input: &str) -> bool {
+    fn is_incomplete_cst(cst: &Cst<()>) -> bool {
+        match &cst.kind {
+            CstKind::Error { error, .. } => matches!(
+                error,
+                CstError::CurlyBraceNotClosed
+                    | CstError::ListNotClosed
+                    | CstError::MatchMissesCases
+                    | CstError::MatchCaseMissesBody
+                    | CstError::ParenthesisNotClosed
+                    | CstError::StructNotClosed
+                    | CstError::TextInterpolationNotClosed
+                    | CstError::TextNotClosed
+            ),
+            CstKind::Assignment { body, .. }
+                if body.iter().all(|it| it.is_whitespace_or_comment()) =>
+            {
+                true
+            }
+            _ => cst.children().into_iter().any(is_incomplete_cst),
+        }
+    }
+    parse_rcst(input).iter().any(is_incomplete_cst)
+}
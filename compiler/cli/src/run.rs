@@ -1,31 +1,191 @@
 use crate::{
+    bundle::Bundle,
+    call_tracer::CallTreeTracer,
     database::Database,
-    utils::{module_for_path, packages_path},
+    diagnostics::Diagnostic,
+    output::OutputFormat,
+    utils::{module_for_path, packages_path, parse_mir_passes},
     Exit, ProgramResult,
 };
 use candy_frontend::{
-    hir_to_mir::ExecutionTarget, tracing::CallTracingMode, TracingConfig, TracingMode,
+    format::{MaxLength, Precedence},
+    hir_to_mir::ExecutionTarget,
+    mir_optimize::MirPasses,
+    tracing::{CallTracingMode, TracingOverride},
+    TracingConfig, TracingMode,
 };
 use candy_vm::{
-    environment::DefaultEnvironment, heap::Heap, lir_to_byte_code::compile_byte_code,
-    tracer::stack_trace::StackTracer, Vm, VmFinished,
+    environment::{DefaultEnvironment, LogLevel, NondeterministicEvent},
+    heap::{Heap, HirId, InlineObject, ToDebugText},
+    lir_to_byte_code::compile_byte_code,
+    tracer::Tracer,
+    ExecutionLimits, Runtime, RuntimeError, DEFAULT_MAX_CALL_DEPTH,
 };
 use clap::{Parser, ValueHint};
+use itertools::Itertools;
+use serde_json::{json, Value};
 use std::{
+    fs::{self, File},
+    io::{stderr, BufWriter, Write},
     path::PathBuf,
     time::{Duration, Instant},
 };
-use tracing::{debug, error};
+use tracing::{debug, error, info, warn};
 
 /// Run a Candy program.
 ///
 /// This command runs the given file, or, if no file is provided, the package of
 /// your current working directory. The module should export a `main` function.
 /// This function is then called with an environment.
+///
+/// Instead of a file, you can also run a bundle created by
+/// `candy build --emit bundle`. It contains all modules the program uses, so
+/// it doesn't need the source tree.
+///
+/// With `--trace-calls` or `--trace-evaluated-expressions`, an indented call
+/// tree is printed to stderr (or the file given by `--trace-output`) while the
+/// program runs. To only trace specific modules, use `--trace-calls-in` and
+/// `--trace-evaluated-expressions-in`.
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Parser, Debug)]
 pub struct Options {
-    /// The file or package to run. If none is provided, the package of your
-    /// current working directory will be run.
+    /// Trace calls and print them as a call tree together with their arguments
+    /// and return values.
+    ///
+    /// `--trace-calls` only traces calls in the current module,
+    /// `--trace-calls=all` also traces calls in other modules.
+    #[arg(
+        long,
+        default_value("only-for-panic-traces"),
+        default_missing_value("only-current"),
+        num_args(0..=1),
+        require_equals(true)
+    )]
+    trace_calls: CallTracingMode,
+
+    /// Trace the values of evaluated expressions and print them in the call
+    /// tree.
+    ///
+    /// `--trace-evaluated-expressions` only traces expressions in the current
+    /// module, `--trace-evaluated-expressions=all` also traces expressions in
+    /// other modules.
+    #[arg(
+        long,
+        default_value("off"),
+        default_missing_value("only-current"),
+        num_args(0..=1),
+        require_equals(true)
+    )]
+    trace_evaluated_expressions: TracingMode,
+
+    /// Also trace calls in the modules matching this pattern, e.g., `foo/bar`
+    /// for the module `foo/bar` and its submodules, or `Examples:foo` for the
+    /// module `foo` of the package `Examples`.
+    ///
+    /// Can be given multiple times.
+    #[arg(long, value_name = "MODULES")]
+    trace_calls_in: Vec<String>,
+
+    /// Also trace evaluated expressions in the modules matching this pattern.
+    /// Patterns work like for `--trace-calls-in`.
+    ///
+    /// Can be given multiple times.
+    #[arg(long, value_name = "MODULES")]
+    trace_evaluated_expressions_in: Vec<String>,
+
+    /// The file to write traces to instead of stderr.
+    #[arg(long, value_hint = ValueHint::FilePath)]
+    trace_output: Option<PathBuf>,
+
+    /// Only print messages that the program logs using `environment.log` if
+    /// they have at least this level.
+    #[arg(long, value_name = "LEVEL", default_value("info"))]
+    log_level: LogLevel,
+
+    /// If the program panics, include the argument values of each call in the
+    /// stack trace.
+    #[arg(long)]
+    stack_trace_arguments: bool,
+
+    /// If the program panics, explain who's responsible for the panic: which
+    /// call passed the faulty value and through which calls the
+    /// responsibility was passed on, with the source code of each.
+    #[arg(long)]
+    explain_panic: bool,
+
+    /// After the program finished, report heap objects and handles that are
+    /// still alive.
+    ///
+    /// To see where leaked objects were allocated, build the CLI with the
+    /// `leak_tracking` feature.
+    #[arg(long)]
+    report_leaks: bool,
+
+    /// After the program finished, print statistics about heap allocations,
+    /// such as the number of allocated objects per kind and the peak memory
+    /// usage.
+    #[arg(long)]
+    heap_stats: bool,
+
+    /// While the program runs, also print a snapshot of the heap statistics
+    /// every this many milliseconds.
+    ///
+    /// Snapshots are taken at calls, so a long-running computation without
+    /// calls delays them.
+    #[arg(long, value_name = "MILLISECONDS", requires = "heap_stats")]
+    heap_stats_interval: Option<u64>,
+
+    /// Cache the results of calls to deterministic functions and reuse them
+    /// when such a function is called with the same arguments again.
+    ///
+    /// Traced functions are never deterministic, so this only has an effect
+    /// together with `--trace-calls=off`.
+    #[arg(long)]
+    memoize_calls: bool,
+
+    /// Enable or disable MIR optimization passes, e.g., `-inline,-cse`.
+    ///
+    /// The built-in passes are `reference-following`, `constant-folding`,
+    /// `inline`, `constant-lifting`, `after-panic`, `cse`, `call-tracing`, and
+    /// `tree-shaking`. All of them are enabled by default.
+    #[arg(long, value_name = "CHANGES", value_parser = parse_mir_passes)]
+    mir_passes: Option<MirPasses>,
+
+    /// Validate the MIR after each optimization pass. If a pass produces
+    /// invalid MIR, report the pass and how it changed the MIR.
+    ///
+    /// This makes compiling a lot slower.
+    #[arg(long)]
+    check_mir_passes: bool,
+
+    /// Panic with a stack overflow when calls are nested more than this many
+    /// levels deep.
+    #[arg(long, value_name = "DEPTH", default_value_t = DEFAULT_MAX_CALL_DEPTH)]
+    max_call_depth: usize,
+
+    /// Stop the program after it ran this many VM instructions.
+    #[arg(long, value_name = "INSTRUCTIONS")]
+    max_instructions: Option<usize>,
+
+    /// Stop the program after it ran for this many seconds.
+    ///
+    /// The time spent compiling the program doesn't count.
+    #[arg(long, value_name = "SECONDS", value_parser = parse_timeout)]
+    timeout: Option<Duration>,
+
+    /// Record the results of nondeterministic handles (the system clock,
+    /// random bytes, and stdin) to this file so that the run can be replayed
+    /// using `--replay`.
+    #[arg(long, value_hint = ValueHint::FilePath, conflicts_with = "replay")]
+    record: Option<PathBuf>,
+
+    /// Replay the results of nondeterministic handles from a file written by
+    /// `--record`.
+    #[arg(long, value_hint = ValueHint::FilePath)]
+    replay: Option<PathBuf>,
+
+    /// The file, package, or bundle to run. If none is provided, the package
+    /// of your current working directory will be run.
     #[arg(value_hint = ValueHint::FilePath)]
     path: Option<PathBuf>,
 
@@ -33,21 +193,86 @@ pub struct Options {
     arguments: Vec<String>,
 }
 
-pub fn run(options: Options) -> ProgramResult {
+pub fn run(options: Options, output: OutputFormat) -> ProgramResult {
     let packages_path = packages_path();
-    let db = Database::new_with_file_system_module_provider(packages_path.clone());
-    let module = module_for_path(options.path)?;
+    let (mut db, module) =
+        if let Some(path) = options.path.as_ref().filter(|path| Bundle::is_bundle(path)) {
+            let bundle = Bundle::read(path)?;
+            (Database::new(Box::new(bundle.modules)), bundle.entry)
+        } else {
+            (
+                Database::new_with_file_system_module_provider(packages_path.clone()),
+                module_for_path(options.path)?,
+            )
+        };
+    let mut mir_passes = options.mir_passes.unwrap_or_default();
+    mir_passes.set_checked(options.check_mir_passes);
+    db.set_mir_passes(mir_passes);
 
     let tracing = TracingConfig {
         register_fuzzables: TracingMode::Off,
-        calls: CallTracingMode::OnlyForPanicTraces,
-        evaluated_expressions: TracingMode::Off,
+        calls: options.trace_calls,
+        evaluated_expressions: options.trace_evaluated_expressions,
+        memoize_calls: options.memoize_calls,
+        overrides: options
+            .trace_calls_in
+            .iter()
+            .map(|modules| TracingOverride {
+                modules: modules.clone(),
+                calls: Some(CallTracingMode::OnlyCurrent),
+                evaluated_expressions: None,
+            })
+            .chain(
+                options
+                    .trace_evaluated_expressions_in
+                    .iter()
+                    .map(|modules| TracingOverride {
+                        modules: modules.clone(),
+                        calls: None,
+                        evaluated_expressions: Some(TracingMode::OnlyCurrent),
+                    }),
+            )
+            .collect(),
+    };
+    // Calls traced only for panic traces don't report return values, so they
+    // aren't worth printing.
+    let should_print_trace = matches!(
+        options.trace_calls,
+        CallTracingMode::OnlyCurrent | CallTracingMode::All,
+    ) || options.trace_evaluated_expressions.is_enabled()
+        || !tracing.overrides.is_empty();
+    let trace_output: Box<dyn Write> = match &options.trace_output {
+        Some(path) => Box::new(BufWriter::new(File::create(path).map_err(|error| {
+            error!("Couldn't create the trace output file: {error}");
+            Exit::FileNotFound
+        })?)),
+        None => Box::new(stderr()),
     };
 
+    let replayed_events = options
+        .replay
+        .as_ref()
+        .map(|path| {
+            let events = fs::read_to_string(path).map_err(|error| {
+                error!("Couldn't read the recording: {error}");
+                Exit::FileNotFound
+            })?;
+            serde_json::from_str::<Vec<NondeterministicEvent>>(&events).map_err(|error| {
+                error!("The recording is invalid: {error}");
+                Exit::FileNotFound
+            })
+        })
+        .transpose()?;
+
     debug!("Running {module}.");
 
     let compilation_start = Instant::now();
-    let byte_code = compile_byte_code(&db, ExecutionTarget::MainFunction(module), tracing).0;
+    let (byte_code, errors) = compile_byte_code(&db, ExecutionTarget::Module(module), tracing);
+    // Code containing errors still runs until it reaches an error.
+    let diagnostics = Diagnostic::from_compiler_errors(&db, errors.as_ref());
+    for diagnostic in &diagnostics {
+        warn!("{}", diagnostic.render(&db));
+    }
 
     let compilation_end = Instant::now();
     debug!(
@@ -56,31 +281,94 @@ pub fn run(options: Options) -> ProgramResult {
     );
 
     debug!("Running program.");
-    let mut heap = Heap::default();
-    let (environment_object, mut environment) =
-        DefaultEnvironment::new(&mut heap, &options.arguments);
-    let vm = Vm::for_main_function(
-        &byte_code,
-        &mut heap,
-        environment_object,
-        StackTracer::default(),
-    );
-    let VmFinished { result, tracer, .. } =
-        vm.run_forever_with_environment(&mut heap, &mut environment);
-    let result = match result {
+    let runtime = Runtime::with_environment(byte_code, |heap| {
+        let (environment_object, mut environment) =
+            DefaultEnvironment::new(heap, &options.arguments);
+        environment.set_log_level(options.log_level);
+        if options.record.is_some() {
+            environment.record();
+        }
+        if let Some(events) = replayed_events {
+            environment.replay(events);
+        }
+        (environment_object, environment)
+    });
+    let mut runtime = runtime
+        .with_tracer((
+            should_print_trace.then(|| CallTreeTracer::new(&db, &packages_path, trace_output)),
+            options
+                .heap_stats_interval
+                .map(|interval| HeapStatsTracer::new(Duration::from_millis(interval))),
+        ))
+        .with_max_call_depth(options.max_call_depth)
+        .with_execution_limits(ExecutionLimits {
+            max_instructions: options.max_instructions,
+            timeout: options.timeout,
+        });
+    let mut return_value_text = None;
+    let mut panic_json = Value::Null;
+    let result = match runtime.run_main() {
         Ok(return_value) => {
             debug!("The main function returned: {return_value:?}");
+            return_value_text =
+                Some(return_value.to_debug_text(Precedence::Low, MaxLength::Unlimited));
+            return_value.drop(runtime.heap_mut());
             Ok(())
         }
-        Err(panic) => {
+        Err(RuntimeError::UnknownExport(_)) => {
+            let reason = "The module doesn't export a main function.";
+            error!("{reason}");
+            panic_json = json!({ "reason": reason });
+            Err(Exit::CodePanicked)
+        }
+        Err(RuntimeError::NotConvertible(reason)) => {
+            error!("The module's `main` isn't a function: {reason}");
+            panic_json =
+                json!({ "reason": format!("The module's `main` isn't a function: {reason}") });
+            Err(Exit::CodePanicked)
+        }
+        Err(RuntimeError::Panicked {
+            panic,
+            stack_tracer: tracer,
+        }) => {
             error!("The program panicked: {}", panic.reason);
             error!("{} is responsible.", panic.responsible);
-            error!(
-                "This is the stack trace:\n{}",
-                tracer.format(&db, &packages_path),
-            );
+            let payload = panic
+                .payload
+                .map(|payload| payload.to_debug_text(Precedence::Low, MaxLength::Unlimited));
+            if let Some(payload) = &payload {
+                error!("Its payload is {payload}.");
+            }
+            let stack_trace = tracer.format(&db, &packages_path, options.stack_trace_arguments);
+            error!("This is the stack trace:\n{stack_trace}");
+            let responsibility_chain = options.explain_panic.then(|| {
+                tracer.format_responsibility_chain(&db, &packages_path, &panic.responsible)
+            });
+            if let Some(responsibility_chain) = &responsibility_chain {
+                error!("This is how the responsibility was passed on:\n{responsibility_chain}");
+            }
+            panic_json = json!({
+                "reason": panic.reason,
+                "responsible": panic.responsible.to_string(),
+                "payload": payload,
+                "stackTrace": stack_trace,
+                "responsibilityChain": responsibility_chain,
+            });
             Err(Exit::CodePanicked)
         }
+        Err(RuntimeError::LimitExceeded {
+            limit,
+            stack_tracer: tracer,
+        }) => {
+            error!("The program was stopped because it exceeded {limit}.");
+            let stack_trace = tracer.format(&db, &packages_path, options.stack_trace_arguments);
+            error!("This is where it was:\n{stack_trace}");
+            panic_json = json!({
+                "reason": format!("The program exceeded {limit}."),
+                "stackTrace": stack_trace,
+            });
+            Err(Exit::ExecutionLimitExceeded)
+        }
     };
     let execution_end = Instant::now();
     debug!(
@@ -88,10 +376,81 @@ pub fn run(options: Options) -> ProgramResult {
         format_duration(execution_end - compilation_end),
     );
 
-    drop(byte_code); // Make sure the byte code is kept around until here.
+    // Recordings are also written if the program panicked since that's when
+    // they're the most useful.
+    if let Some(path) = &options.record {
+        let events = runtime.environment().recorded_events().unwrap();
+        let events = serde_json::to_string_pretty(events).unwrap();
+        if let Err(error) = fs::write(path, events) {
+            error!("Couldn't write the recording: {error}");
+        }
+    }
+
+    if options.heap_stats {
+        info!("{}", runtime.heap().stats());
+    }
+
+    if options.report_leaks {
+        if result.is_ok() {
+            let report = runtime.tear_down();
+            if report.is_empty() {
+                info!("No leaks found.");
+            } else {
+                warn!("{report}");
+            }
+        } else {
+            // A panic leaves the VM's stack behind, so everything would show
+            // up as leaked.
+            warn!("Leaks are only reported if the program doesn't panic.");
+        }
+    }
+
+    output.print_json(|| {
+        let diagnostics = diagnostics.iter().map(|it| it.to_json(&db)).collect_vec();
+        json!({
+            "diagnostics": diagnostics,
+            "returnValue": return_value_text,
+            "panic": panic_json,
+        })
+    });
+
     result
 }
 
+/// Prints a snapshot of the heap statistics whenever the interval elapsed.
+struct HeapStatsTracer {
+    interval: Duration,
+    last_snapshot: Instant,
+}
+impl HeapStatsTracer {
+    fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_snapshot: Instant::now(),
+        }
+    }
+}
+impl Tracer for HeapStatsTracer {
+    fn call_started(
+        &mut self,
+        heap: &mut Heap,
+        _call_site: HirId,
+        _callee: InlineObject,
+        _arguments: Vec<InlineObject>,
+        _responsible: HirId,
+    ) {
+        if self.last_snapshot.elapsed() >= self.interval {
+            info!("{}", heap.stats());
+            self.last_snapshot = Instant::now();
+        }
+    }
+}
+
+fn parse_timeout(seconds: &str) -> Result<Duration, String> {
+    let seconds = seconds.parse::<f64>().map_err(|error| error.to_string())?;
+    Duration::try_from_secs_f64(seconds).map_err(|error| error.to_string())
+}
+
 fn format_duration(duration: Duration) -> String {
     if duration < Duration::from_millis(1) {
         format!("{} µs", duration.as_micros())
@@ -1,5 +1,8 @@
 use crate::Exit;
-use candy_frontend::module::{Module, ModuleFromPathError, ModuleKind, PackagesPath};
+use candy_frontend::{
+    mir_optimize::MirPasses,
+    module::{Module, ModuleFromPathError, ModuleKind, PackagesPath},
+};
 use std::{
     env::{current_dir, current_exe},
     path::PathBuf,
@@ -21,6 +24,10 @@ pub fn packages_path() -> PackagesPath {
 pub fn module_for_path(path: impl Into<Option<PathBuf>>) -> Result<Module, Exit> {
     let packages_path = packages_path();
     if let Some(file) = path.into() {
+        if !file.exists() {
+            error!("The given file doesn't exist.");
+            return Err(Exit::FileNotFound);
+        }
         Module::from_path(&packages_path, &file, ModuleKind::Code).map_err(|error| match error {
             ModuleFromPathError::NotFound(_) => {
                 error!("The given file doesn't exist.");
@@ -44,3 +51,11 @@ pub fn module_for_path(path: impl Into<Option<PathBuf>>) -> Result<Module, Exit>
         })
     }
 }
+
+pub fn parse_mir_passes(changes: &str) -> Result<MirPasses, String> {
+    let mut passes = MirPasses::new();
+    passes
+        .apply_changes(changes)
+        .map_err(|error| error.to_string())?;
+    Ok(passes)
+}
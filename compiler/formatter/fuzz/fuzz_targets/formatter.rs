@@ -14,7 +14,7 @@ use candy_frontend::{
     },
     position::Offset,
     rcst_to_cst::{RcstToCst, RcstToCstStorage},
-    string_to_rcst::StringToRcstStorage,
+    string_to_rcst::{RcstCacheOwner, StringToRcstStorage},
 };
 use lazy_static::lazy_static;
 use libfuzzer_sys::fuzz_target;
@@ -47,6 +47,7 @@ impl ModuleProviderOwner for Database {
         &self.module_provider
     }
 }
+impl RcstCacheOwner for Database {}
 
 fuzz_target!(|data: &[u8]| {
     let mut db = Database::default();
@@ -85,7 +86,7 @@ impl<T: NormalizeSpans> NormalizeSpans for [T] {
 impl NormalizeSpans for Ast {
     fn normalize_spans(&mut self) {
         match &mut self.kind {
-            AstKind::Int(_) => {}
+            AstKind::Int(_) | AstKind::Float(_) => {}
             AstKind::Text(Text(parts)) => parts.normalize_spans(),
             AstKind::TextPart(_) | AstKind::Identifier(_) | AstKind::Symbol(_) => {}
             AstKind::List(List(items)) => items.normalize_spans(),
@@ -107,7 +108,11 @@ impl NormalizeSpans for Ast {
                 receiver.normalize_spans();
                 arguments.normalize_spans();
             }
-            AstKind::Assignment(Assignment { is_public: _, body }) => match body {
+            AstKind::Assignment(Assignment {
+                is_public: _,
+                documentation: _,
+                body,
+            }) => match body {
                 AssignmentBody::Function { name: _, function } => function.normalize_spans(),
                 AssignmentBody::Body { pattern, body } => {
                     pattern.normalize_spans();
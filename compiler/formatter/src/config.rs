@@ -0,0 +1,46 @@
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct FormatterConfig {
+    /// The maximum width of a line, including its indentation.
+    pub max_line_width: usize,
+    /// The number of spaces per indentation level.
+    pub indent_width: usize,
+    pub trailing_comma: TrailingCommaPolicy,
+}
+impl Default for FormatterConfig {
+    fn default() -> Self {
+        Self {
+            max_line_width: 100,
+            indent_width: 2,
+            trailing_comma: TrailingCommaPolicy::default(),
+        }
+    }
+}
+
+/// When to add a comma after the last item of a list or struct.
+///
+/// Lists with a single item always keep their comma because it's required by
+/// the syntax.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum TrailingCommaPolicy {
+    Always,
+    Never,
+    /// Only add trailing commas if the items are split across multiple lines.
+    #[default]
+    MultilineOnly,
+}
+impl FromStr for TrailingCommaPolicy {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "always" => Ok(Self::Always),
+            "never" => Ok(Self::Never),
+            "multiline-only" => Ok(Self::MultilineOnly),
+            _ => Err(format!(
+                "Unknown trailing comma policy `{string}`, expected `always`, `never`, or `multiline-only`.",
+            )),
+        }
+    }
+}
@@ -12,11 +12,14 @@ use crate::{
     width::{Indentation, SinglelineWidth, StringWidth, Width},
 };
 use candy_frontend::{
-    cst::{Cst, CstError, CstKind, IntRadix, UnwrapWhitespaceAndComment},
+    cst::{
+        Associativity, BinaryOperator, Cst, CstError, CstKind, IntRadix, UnwrapWhitespaceAndComment,
+    },
     position::Offset,
 };
 use extension_trait::extension_trait;
 use itertools::Itertools;
+use std::{cmp::Ordering, iter};
 use traversal::dft_post_rev;
 
 #[derive(Clone, Default)]
@@ -189,6 +192,9 @@ pub fn format_cst<'a>(
         CstKind::SingleQuote | CstKind::DoubleQuote | CstKind::Percent | CstKind::Octothorpe => {
             SinglelineWidth::from(1).into()
         }
+        CstKind::IfKeyword => SinglelineWidth::from(2).into(),
+        CstKind::ThenKeyword | CstKind::ElseKeyword => SinglelineWidth::from(4).into(),
+        CstKind::BinaryOperator(operator) => SinglelineWidth::from(operator.as_str().len()).into(),
         CstKind::Whitespace(_) | CstKind::Newline(_) => {
             panic!("Whitespace and newlines should be handled separately.")
         }
@@ -212,29 +218,39 @@ pub fn format_cst<'a>(
             let child_width = child.into_empty_and_move_comments_to(edits, &mut whitespace);
             return FormattedCst::new(child_width, whitespace);
         }
-        CstKind::Identifier(string) | CstKind::Symbol(string) => string.width(),
+        CstKind::Identifier(string) | CstKind::Symbol(string) | CstKind::Float { string, .. } => {
+            string.width()
+        }
         CstKind::Int {
+            is_negative,
             radix_prefix,
             string,
             ..
         } => {
-            if let Some((radix, radix_string)) = radix_prefix {
-                let span_end = Offset(cst.data.span.start.0 + radix_string.len());
-                let span = cst.data.span.start..span_end;
-                match radix {
-                    IntRadix::Binary => edits.change(span, "0b"),
-                    IntRadix::Hexadecimal => {
-                        edits.change(span, "0x");
-                        edits.change(span_end..cst.data.span.end, string.to_uppercase());
-                    }
-                }
+            let sign_width = usize::from(*is_negative);
+            let radix_start = Offset(cst.data.span.start.0 + sign_width);
+            let digits_start = Offset(
+                *radix_start
+                    + radix_prefix
+                        .as_ref()
+                        .map(|(_, radix_string)| radix_string.len())
+                        .unwrap_or_default(),
+            );
+            if let Some((radix, _)) = radix_prefix {
+                // Radix prefixes always have a length of two.
+                edits.change(
+                    radix_start..digits_start,
+                    match radix {
+                        IntRadix::Binary => "0b",
+                        IntRadix::Hexadecimal => "0x",
+                    },
+                );
             }
 
-            radix_prefix
-                .as_ref()
-                .map(|(_, string)| string.width())
-                .unwrap_or_default()
-                + string.width()
+            let digits = canonicalize_int_digits(radix_prefix.as_ref().map(|(it, _)| it), string);
+            let width = Width::from(*digits_start - *cst.data.span.start) + digits.width();
+            edits.change(digits_start..cst.data.span.end, digits);
+            width
         }
         CstKind::OpeningText {
             opening_single_quotes,
@@ -339,6 +355,12 @@ pub fn format_cst<'a>(
                 .into_trailing(edits, TrailingWhitespace::Indentation(info.indentation))
         }
         CstKind::TextPart(text) => text.width(),
+        CstKind::TextEscape { value, .. } => {
+            let escape = canonicalize_text_escape(*value);
+            let width = escape.width();
+            edits.change(cst.data.span.clone(), escape);
+            width
+        }
         CstKind::TextInterpolation {
             opening_curly_braces,
             expression,
@@ -422,6 +444,61 @@ pub fn format_cst<'a>(
 
             return FormattedCst::new(left_width + bar_width + right_width, whitespace);
         }
+        CstKind::BinaryOperation {
+            left,
+            operator,
+            right,
+        } => {
+            let CstKind::BinaryOperator(operator_kind) =
+                operator.unwrap_whitespace_and_comment().kind
+            else {
+                panic!("The operator of a binary operation must be a binary operator.");
+            };
+
+            // Left
+            let mut left = format_operand(
+                edits,
+                previous_width,
+                left,
+                info,
+                operator_kind,
+                OperandSide::Left,
+            );
+
+            // Operator
+            // The parser requires operators on continuation lines to be indented.
+            let info_for_right_side = info.with_indent();
+            let width_for_right_side =
+                Width::multiline(None, info_for_right_side.indentation.width());
+            let operator_width = format_cst(edits, width_for_right_side, operator, info)
+                .into_space_and_move_comments_to(edits, &mut left.whitespace);
+            let left_min_width = left.min_width(info.indentation);
+
+            // Right
+            let (right_width, whitespace) = format_operand(
+                edits,
+                width_for_right_side + operator_width,
+                right,
+                &info_for_right_side,
+                operator_kind,
+                OperandSide::Right,
+            )
+            .split();
+
+            let left_width = if let Some(right_first_line_width) = right_width.first_line_width()
+                && (left_min_width
+                    + SinglelineWidth::SPACE
+                    + operator_width
+                    + right_first_line_width)
+                    .fits(info.indentation)
+            {
+                left.into_trailing_with_space(edits)
+            } else {
+                left.into_trailing_with_indentation(edits, info_for_right_side.indentation)
+            };
+
+            return FormattedCst::new(left_width + operator_width + right_width, whitespace);
+        }
         CstKind::Parenthesized { .. } => {
             // Whenever parentheses are necessary, they are handled by the parent. Hence, we try to
             // remove them here.
@@ -740,6 +817,77 @@ pub fn format_cst<'a>(
                 whitespace,
             );
         }
+        CstKind::If {
+            if_keyword,
+            condition,
+            then_keyword,
+            then,
+            else_keyword,
+            else_,
+        } => {
+            let previous_width_for_indented =
+                Width::multiline(None, info.indentation.with_indent().width());
+            let indented_info = info.with_indent();
+
+            let if_keyword = format_cst(edits, previous_width, if_keyword, info);
+            let mut condition = format_cst(
+                edits,
+                previous_width_for_indented,
+                condition,
+                &indented_info,
+            );
+            let if_keyword_width =
+                if_keyword.into_space_and_move_comments_to(edits, &mut condition.whitespace);
+
+            let then = format_if_branch(
+                edits,
+                previous_width_for_indented,
+                then_keyword,
+                then,
+                &indented_info,
+            );
+            let is_else_missing = else_keyword.kind.is_error();
+            let else_ = format_if_branch(
+                edits,
+                previous_width_for_indented,
+                else_keyword,
+                else_,
+                &indented_info,
+            );
+
+            // if foo then bar else baz
+            //
+            // if foo
+            //   then bar
+            //   else baz
+            let fits_in_one_line = !condition.whitespace.has_comments()
+                && !then.whitespace.has_comments()
+                && (previous_width
+                    + if_keyword_width
+                    + condition.child_width()
+                    + SinglelineWidth::SPACE
+                    + then.child_width()
+                    + SinglelineWidth::SPACE
+                    + else_.child_width())
+                .fits(info.indentation);
+            let trailing = if fits_in_one_line {
+                TrailingWhitespace::Space
+            } else {
+                TrailingWhitespace::Indentation(indented_info.indentation)
+            };
+
+            let condition_width = condition.into_trailing(edits, trailing);
+            let then_width = if is_else_missing {
+                then.into_empty_trailing(edits)
+            } else {
+                then.into_trailing(edits, trailing)
+            };
+            let (else_width, whitespace) = else_.split();
+            return FormattedCst::new(
+                if_keyword_width + condition_width + then_width + else_width,
+                whitespace,
+            );
+        }
         CstKind::MatchCase {
             pattern,
             arrow,
@@ -1028,6 +1176,34 @@ pub fn format_cst<'a>(
     FormattedCst::new(width, ExistingWhitespace::empty(cst.data.span.end))
 }
 
+/// Formats the `then …` or `else …` part of an if expression.
+fn format_if_branch<'a>(
+    edits: &mut TextEdits,
+    previous_width: Width,
+    keyword: &'a Cst,
+    branch: &'a Cst,
+    info: &FormattingInfo,
+) -> FormattedCst<'a> {
+    let keyword = format_cst(edits, previous_width, keyword, info);
+    let is_branch_missing = matches!(
+        &branch.unwrap_whitespace_and_comment().kind,
+        CstKind::Error { unparsable_input, .. } if unparsable_input.is_empty(),
+    );
+    let mut branch = format_cst(
+        edits,
+        previous_width + keyword.min_width(info.indentation) + SinglelineWidth::SPACE,
+        branch,
+        info,
+    );
+    let keyword_width = if is_branch_missing {
+        keyword.into_empty_and_move_comments_to(edits, &mut branch.whitespace)
+    } else {
+        keyword.into_space_and_move_comments_to(edits, &mut branch.whitespace)
+    };
+    let (branch_width, whitespace) = branch.split();
+    FormattedCst::new(keyword_width + branch_width, whitespace)
+}
+
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 enum ReceiverParent {
     BinaryBar,
@@ -1063,6 +1239,60 @@ fn format_receiver<'a>(
     }
 }
 
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum OperandSide {
+    Left,
+    Right,
+}
+fn format_operand<'a>(
+    edits: &mut TextEdits,
+    previous_width: Width,
+    operand: &'a Cst,
+    info: &FormattingInfo,
+    operator: BinaryOperator,
+    side: OperandSide,
+) -> FormattedCst<'a> {
+    let (operand, operand_parentheses) = ExistingParentheses::split_from(edits, operand);
+    let operand_needs_parentheses = match &operand.kind {
+        CstKind::BinaryOperation {
+            operator: operand_operator,
+            ..
+        } => {
+            let CstKind::BinaryOperator(operand_operator) =
+                operand_operator.unwrap_whitespace_and_comment().kind
+            else {
+                panic!("The operator of a binary operation must be a binary operator.");
+            };
+            match operand_operator.precedence().cmp(&operator.precedence()) {
+                Ordering::Less => true,
+                Ordering::Equal => {
+                    side == OperandSide::Right
+                        || operator.associativity() == Associativity::None
+                        || operand_parentheses.are_required_due_to_comments()
+                }
+                Ordering::Greater => operand_parentheses.are_required_due_to_comments(),
+            }
+        }
+        CstKind::Call { .. } => operand_parentheses.are_required_due_to_comments(),
+        _ => match operand.precedence() {
+            Some(PrecedenceCategory::High) => operand_parentheses.are_required_due_to_comments(),
+            Some(PrecedenceCategory::Low) | None => operand_parentheses.is_some(),
+        },
+    };
+    let previous_width_for_operand = if operand_needs_parentheses {
+        previous_width + SinglelineWidth::PARENTHESIS + SinglelineWidth::PARENTHESIS
+    } else {
+        previous_width
+    };
+    let operand = format_cst(edits, previous_width_for_operand, operand, info);
+
+    if operand_needs_parentheses {
+        operand_parentheses.into_some(edits, previous_width, operand, info)
+    } else {
+        operand_parentheses.into_none(edits, operand)
+    }
+}
+
 struct Argument<'a> {
     #[allow(clippy::struct_field_names)]
     argument: MaybeSandwichLikeArgument<'a>,
@@ -1226,19 +1456,27 @@ pub impl<D> CstExtension for Cst<D> {
             | CstKind::DoubleQuote
             | CstKind::Percent
             | CstKind::Octothorpe
+            | CstKind::IfKeyword
+            | CstKind::ThenKeyword
+            | CstKind::ElseKeyword
+            | CstKind::BinaryOperator(_)
             | CstKind::Whitespace(_)
             | CstKind::Newline(_)
             | CstKind::Comment { .. } => None,
             CstKind::TrailingWhitespace { child, .. } => child.precedence(),
-            CstKind::Identifier(_) | CstKind::Symbol(_) | CstKind::Int { .. } => {
-                Some(PrecedenceCategory::High)
-            }
+            CstKind::Identifier(_)
+            | CstKind::Symbol(_)
+            | CstKind::Int { .. }
+            | CstKind::Float { .. } => Some(PrecedenceCategory::High),
             CstKind::OpeningText { .. } | CstKind::ClosingText { .. } => None,
             CstKind::Text { .. } => Some(PrecedenceCategory::High),
-            CstKind::TextNewline(_) | CstKind::TextPart(_) | CstKind::TextInterpolation { .. } => {
-                None
+            CstKind::TextNewline(_)
+            | CstKind::TextPart(_)
+            | CstKind::TextEscape { .. }
+            | CstKind::TextInterpolation { .. } => None,
+            CstKind::BinaryBar { .. } | CstKind::BinaryOperation { .. } => {
+                Some(PrecedenceCategory::Low)
             }
-            CstKind::BinaryBar { .. } => Some(PrecedenceCategory::Low),
             CstKind::Parenthesized { .. } => Some(PrecedenceCategory::High),
             CstKind::Call { .. } => Some(PrecedenceCategory::Low),
             CstKind::List { .. } => Some(PrecedenceCategory::High),
@@ -1246,7 +1484,7 @@ pub impl<D> CstExtension for Cst<D> {
             CstKind::Struct { .. } => Some(PrecedenceCategory::High),
             CstKind::StructField { .. } => None,
             CstKind::StructAccess { .. } => Some(PrecedenceCategory::High),
-            CstKind::Match { .. } => Some(PrecedenceCategory::Low),
+            CstKind::If { .. } | CstKind::Match { .. } => Some(PrecedenceCategory::Low),
             CstKind::MatchCase { .. } => None,
             CstKind::Function { .. } => Some(PrecedenceCategory::High),
             CstKind::Assignment { .. } | CstKind::Error { .. } => None,
@@ -1254,9 +1492,47 @@ pub impl<D> CstExtension for Cst<D> {
     }
 }
 
+/// Hexadecimal digits are uppercased. If the int uses digit separators, they
+/// are regrouped into groups of three (decimal) or four (binary, hexadecimal)
+/// digits, starting from the right.
+fn canonicalize_int_digits(radix: Option<&IntRadix>, digits: &str) -> String {
+    let digits = match radix {
+        Some(IntRadix::Hexadecimal) => digits.to_uppercase(),
+        _ => digits.to_string(),
+    };
+    if !digits.contains('_') {
+        return digits;
+    }
+
+    let group_size = if radix.is_some() { 4 } else { 3 };
+    let digits = digits.chars().filter(|it| *it != '_').collect_vec();
+    let first_group_size = match digits.len() % group_size {
+        0 => group_size,
+        remainder => remainder,
+    };
+    let (first_group, rest) = digits.split_at(first_group_size);
+    let groups = iter::once(first_group)
+        .chain(rest.chunks(group_size))
+        .map(|group| group.iter().collect::<String>())
+        .join("_");
+    groups
+}
+
+/// Escapes are written in their short form if one exists and in the
+/// uppercase `\u{…}` form without leading zeros otherwise.
+fn canonicalize_text_escape(value: char) -> String {
+    match value {
+        '"' => r#"\""#.to_string(),
+        '\\' => r"\\".to_string(),
+        '\n' => r"\n".to_string(),
+        '\t' => r"\t".to_string(),
+        _ => format!("\\u{{{:X}}}", u32::from(value)),
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use crate::Formatter;
+    use crate::{Formatter, FormatterConfig, TrailingCommaPolicy};
     use candy_frontend::{rcst_to_cst::RcstsToCstsExt, string_to_rcst::parse_rcst};
     use itertools::Itertools;
 
@@ -1367,6 +1643,17 @@ mod test {
         // Hexadecimal
         test("0x123", "0x123\n");
         test("0XDEADc0de", "0xDEADC0DE\n");
+
+        // Negative
+        test("-1", "-1\n");
+        test("-0Xff", "-0xFF\n");
+
+        // Digit separators
+        test("1_000", "1_000\n");
+        test("1000_000", "1_000_000\n");
+        test("-12_34567", "-1_234_567\n");
+        test("0b1_0100101", "0b1010_0101\n");
+        test("0xdead_c0de", "0xDEAD_C0DE\n");
     }
     #[test]
     fn test_text() {
@@ -1397,6 +1684,12 @@ mod test {
         test("\"foo\n  bar\"", "\"\n  foo\n  bar\n\"\n");
         test("\"foo\n  bar\n\"", "\"\n  foo\n  bar\n\"\n");
         test("\"foo\n  {0}\n  bar\n\"", "\"\n  foo\n  {0}\n  bar\n\"\n");
+
+        // Escapes
+        test(r#""a\"b\\c""#, "\"a\\\"b\\\\c\"\n");
+        test(r#""a\u{a}b\u{9}""#, "\"a\\nb\\t\"\n");
+        test(r#""\u{00e9}\u{1f600}""#, "\"\\u{E9}\\u{1F600}\"\n");
+        test(r#"'"\u{a}"'"#, "'\"\\u{a}\"'\n");
     }
     #[test]
     fn test_binary_bar() {
@@ -1799,6 +2092,8 @@ mod test {
             "foo\n  .firstlooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooongArgument\n  .secondlooooooooooooooooooooooooooooooooongArgument\n",
         );
         test("(use \"Foo\").bar", "(use \"Foo\").bar\n");
+        test("foo.", "foo.\n");
+        test("foo . ", "foo.\n");
 
         // Comments
 
@@ -1845,6 +2140,62 @@ mod test {
             "foo %\n  Bar -> # abc\n    Baz\n",
         );
     }
+    #[test]
+    fn test_if() {
+        test("if foo then 1 else 2", "if foo then 1 else 2\n");
+        test("if  foo  then  1  else  2", "if foo then 1 else 2\n");
+        test("if foo\n  then 1\n  else 2", "if foo then 1 else 2\n");
+        test("bar = if foo then 1 else 2", "bar = if foo then 1 else 2\n");
+        test("if foo then", "if foo then\n");
+        // if foo
+        //   then loooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooong
+        //   else 2
+        test(
+            "if foo then loooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooong else 2",
+            "if foo\n  then loooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooong\n  else 2\n",
+        );
+
+        // Comments
+        // if foo # abc
+        //   then 1
+        //   else 2
+        test(
+            "if foo # abc\n  then 1 else 2",
+            "if foo # abc\n  then 1\n  else 2\n",
+        );
+    }
+
+    #[test]
+    fn test_binary_operation() {
+        test("1 + 2", "1 + 2\n");
+        test("1  +  2", "1 + 2\n");
+        test("1 +\n  2", "1 + 2\n");
+        test("1\n  + 2", "1 + 2\n");
+        test("foo bar + baz", "foo bar + baz\n");
+        test("(foo bar) + baz", "foo bar + baz\n");
+        test("foo | (bar + baz)", "foo | (bar + baz)\n");
+
+        // Precedence
+        test("1 + 2 * 3", "1 + 2 * 3\n");
+        test("1 + (2 * 3)", "1 + 2 * 3\n");
+        test("(1 + 2) * 3", "(1 + 2) * 3\n");
+        test("(1 + 2) + 3", "1 + 2 + 3\n");
+        test("1 + (2 + 3)", "1 + (2 + 3)\n");
+        test("1 - (2 - 3)", "1 - (2 - 3)\n");
+        test("(a < b) == c", "a < b == c\n");
+        test("(a == b) == c", "(a == b) == c\n");
+
+        // looooooooooooooooooooooooooooooooooooooooooooooongLeft
+        //   + looooooooooooooooooooooooooooooooooooooooooooooongRight
+        test(
+            "looooooooooooooooooooooooooooooooooooooooooooooongLeft + looooooooooooooooooooooooooooooooooooooooooooooongRight",
+            "looooooooooooooooooooooooooooooooooooooooooooooongLeft\n  + looooooooooooooooooooooooooooooooooooooooooooooongRight\n",
+        );
+
+        // Comments
+        test("1 # abc\n  + 2", "1 # abc\n  + 2\n");
+    }
+
     #[test]
     fn test_function() {
         // No parameters
@@ -2046,6 +2397,55 @@ mod test {
             "foo :=\n  # looooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooooongComment\n  Foo\n\n  Bar\n",
         );
     }
+    #[test]
+    fn test_config() {
+        let narrow = FormatterConfig {
+            max_line_width: 20,
+            ..FormatterConfig::default()
+        };
+        test_with_config("foo = (1, 2, 3)", narrow, "foo = (1, 2, 3)\n");
+        test_with_config(
+            "foo = (item1, item2, item3)",
+            narrow,
+            "foo = (\n  item1,\n  item2,\n  item3,\n)\n",
+        );
+
+        let wide_indentation = FormatterConfig {
+            indent_width: 4,
+            ..FormatterConfig::default()
+        };
+        test_with_config(
+            "foo =\n  bar\n  baz",
+            wide_indentation,
+            "foo =\n    bar\n    baz\n",
+        );
+        test_with_config(
+            "foo = { a ->\n  b = a\n  b\n}",
+            wide_indentation,
+            "foo = { a ->\n    b = a\n    b\n}\n",
+        );
+
+        let always = FormatterConfig {
+            trailing_comma: TrailingCommaPolicy::Always,
+            ..FormatterConfig::default()
+        };
+        test_with_config("(foo, bar)", always, "(foo, bar,)\n");
+        test_with_config("[Foo: foo]", always, "[Foo: foo,]\n");
+        test_with_config("(foo,)", always, "(foo,)\n");
+
+        let never = FormatterConfig {
+            trailing_comma: TrailingCommaPolicy::Never,
+            max_line_width: 20,
+            ..FormatterConfig::default()
+        };
+        test_with_config("(foo, bar,)", never, "(foo, bar)\n");
+        test_with_config("(foo,)", never, "(foo,)\n");
+        test_with_config(
+            "foo = (item1, item2, item3)",
+            never,
+            "foo = (\n  item1,\n  item2,\n  item3\n)\n",
+        );
+    }
 
     #[track_caller]
     fn test(source: &str, expected: &str) {
@@ -2055,4 +2455,12 @@ mod test {
         let formatted = csts.as_slice().format_to_string();
         assert_eq!(formatted, expected);
     }
+    #[track_caller]
+    fn test_with_config(source: &str, config: FormatterConfig, expected: &str) {
+        let csts = parse_rcst(source).to_csts();
+        assert_eq!(source, csts.iter().join(""));
+
+        let formatted = csts.as_slice().format_to_string_with_config(config);
+        assert_eq!(formatted, expected);
+    }
 }
@@ -1,4 +1,5 @@
 use crate::{
+    config::TrailingCommaPolicy,
     existing_whitespace::{ExistingWhitespace, TrailingWhitespace},
     format::{format_cst, CstExtension, FormattingInfo},
     formatted_cst::FormattedCst,
@@ -30,9 +31,13 @@ pub fn format_collection<'a>(
         &info,
     );
 
+    let max_line_width = info.indentation.max_line_width();
     let mut min_width = info.indentation.width()
         + opening_punctuation.min_width(info.indentation)
         + closing_punctuation.min_width(info.indentation);
+    if let Width::Singleline(width) = min_width {
+        min_width = Width::from_width_and_max(width, max_line_width);
+    }
     let previous_width_for_items = Width::multiline(None, info.indentation.with_indent().width());
     let item_info = info
         .with_indent()
@@ -48,15 +53,25 @@ pub fn format_collection<'a>(
                 is_single_item && is_comma_required_for_single_item;
             let is_comma_required =
                 is_comma_required_due_to_single_item || !is_last_item || item.has_comments();
-            let info = if !is_comma_required && let Width::Singleline(min_width) = min_width {
-                // We're looking at the last item and everything might fit in one line.
-                let max_width = Width::MAX - min_width;
-                assert!(!max_width.is_empty());
+            let info = match info.indentation.config().trailing_comma {
+                _ if is_comma_required => item_info.clone(),
+                TrailingCommaPolicy::Always => item_info.clone(),
+                TrailingCommaPolicy::Never => {
+                    item_info.with_trailing_comma_condition(TrailingCommaCondition::Never)
+                }
+                TrailingCommaPolicy::MultilineOnly => {
+                    if let Width::Singleline(min_width) = min_width {
+                        // We're looking at the last item and everything might fit in one line.
+                        let max_width = max_line_width - min_width;
+                        assert!(!max_width.is_empty());
 
-                item_info
-                    .with_trailing_comma_condition(TrailingCommaCondition::UnlessFitsIn(max_width))
-            } else {
-                item_info.clone()
+                        item_info.with_trailing_comma_condition(
+                            TrailingCommaCondition::UnlessFitsIn(max_width),
+                        )
+                    } else {
+                        item_info.clone()
+                    }
+                }
             };
             let item = format_cst(edits, previous_width_for_items, item, &info);
 
@@ -64,13 +79,13 @@ pub fn format_collection<'a>(
                 && let Width::Singleline(item_min_width) = item.min_width(info.indentation)
             {
                 let (item_min_width, max_width) = if is_last_item {
-                    (item_min_width, Width::MAX)
+                    (item_min_width, max_line_width)
                 } else {
                     // We need an additional column for the trailing space after the comma.
                     let item_min_width = item_min_width + SinglelineWidth::from(1);
 
                     // The last item needs at least one column of space.
-                    let max_width = Width::MAX - SinglelineWidth::from(1);
+                    let max_width = max_line_width - SinglelineWidth::from(1);
 
                     (item_min_width, max_width)
                 };
@@ -124,6 +139,7 @@ pub fn format_collection<'a>(
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum TrailingCommaCondition {
     Always,
+    Never,
 
     /// Add a trailing comma if the element fits in a single line and is at most
     /// this wide.
@@ -140,6 +156,7 @@ pub fn apply_trailing_comma_condition<'a>(
 ) -> (Width, ExistingWhitespace<'a>) {
     let should_have_comma = match info.trailing_comma_condition {
         Some(TrailingCommaCondition::Always) => true,
+        Some(TrailingCommaCondition::Never) => false,
         Some(TrailingCommaCondition::UnlessFitsIn(max_width)) => {
             !min_width_except_comma.fits_in(max_width)
         }
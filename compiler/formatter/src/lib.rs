@@ -13,18 +13,22 @@
 )]
 
 use candy_frontend::{cst::Cst, position::Offset};
+pub use config::{FormatterConfig, TrailingCommaPolicy};
 use existing_whitespace::{TrailingWithIndentationConfig, WhitespacePositionInBody};
 use extension_trait::extension_trait;
 use format::{format_csts, FormattingInfo};
 use itertools::Itertools;
+pub use new_line_indentation::new_line_indentation;
 use text_edits::TextEdits;
 use width::{Indentation, Width};
 
+mod config;
 mod existing_parentheses;
 mod existing_whitespace;
 mod format;
 mod format_collection;
 mod formatted_cst;
+mod new_line_indentation;
 mod text_edits;
 mod width;
 
@@ -33,8 +37,15 @@ pub impl<C: AsRef<[Cst]>> Formatter for C {
     fn format_to_string(&self) -> String {
         self.format_to_edits().apply()
     }
+    fn format_to_string_with_config(&self, config: FormatterConfig) -> String {
+        self.format_to_edits_with_config(config).apply()
+    }
     fn format_to_edits(&self) -> TextEdits {
+        self.format_to_edits_with_config(FormatterConfig::default())
+    }
+    fn format_to_edits_with_config(&self, config: FormatterConfig) -> TextEdits {
         let csts = self.as_ref();
+        let indentation = Indentation::new(config);
         // TOOD: Is there an elegant way to avoid stringifying the whole CST?
         let source = csts.iter().join("");
         let mut edits = TextEdits::new(source);
@@ -44,7 +55,10 @@ pub impl<C: AsRef<[Cst]>> Formatter for C {
             Width::default(),
             csts,
             Offset::default(),
-            &FormattingInfo::default(),
+            &FormattingInfo {
+                indentation,
+                ..FormattingInfo::default()
+            },
         );
         if formatted.child_width() == Width::default() && !formatted.whitespace.has_comments() {
             _ = formatted.into_empty_trailing(&mut edits);
@@ -55,7 +69,7 @@ pub impl<C: AsRef<[Cst]>> Formatter for C {
                 } else {
                     WhitespacePositionInBody::End
                 },
-                indentation: Indentation::default(),
+                indentation,
             };
             _ = formatted.into_trailing_with_indentation_detailed(&mut edits, &config);
         };
@@ -0,0 +1,229 @@
+use crate::{config::FormatterConfig, width::Indentation};
+use candy_frontend::{
+    cst::{Cst, CstKind, FindCstPath, UnwrapWhitespaceAndComment},
+    position::Offset,
+};
+
+/// Computes the indentation (as whitespace) of the line containing `offset`
+/// based on the code in front of it, e.g., after the user inserted a line
+/// break.
+///
+/// The `csts` must be parsed from the `source`.
+#[must_use]
+pub fn new_line_indentation(
+    source: &str,
+    csts: &[Cst],
+    offset: Offset,
+    config: FormatterConfig,
+) -> String {
+    let line_start = source[..*offset].rfind('\n').map_or(0, |it| it + 1);
+    let mut end_of_code = Offset(source[..line_start].trim_end().len());
+    // Comments don't influence the indentation.
+    while let Some(comment) = find_comment_ending_at(csts, end_of_code) {
+        end_of_code = Offset(source[..*comment.data.span.start].trim_end().len());
+    }
+    let csts = csts.iter().collect::<Vec<_>>();
+    let line = &source[line_start..];
+    let start_of_code =
+        Offset(line_start + line.len() - line.trim_start_matches([' ', '\t']).len());
+
+    let context = Context {
+        source,
+        config,
+        end_of_code,
+        start_of_code,
+    };
+    context
+        .indentation_in(&csts, Indentation::new(config))
+        .to_string()
+}
+
+fn find_comment_ending_at(csts: &[Cst], offset: Offset) -> Option<&Cst> {
+    csts.find_path_before_offset(offset)
+        .nodes()
+        .iter()
+        .find(|it| matches!(it.kind, CstKind::Comment { .. }) && it.data.span.end == offset)
+        .copied()
+}
+
+struct Context<'a> {
+    source: &'a str,
+    config: FormatterConfig,
+    /// The end of the code in front of the new line.
+    end_of_code: Offset,
+    /// The start of the code on the new line.
+    start_of_code: Offset,
+}
+impl Context<'_> {
+    fn indentation_in(&self, csts: &[&Cst], indentation: Indentation) -> Indentation {
+        let Some(cst) = csts.iter().find(|it| {
+            it.data.span.start < self.end_of_code && self.end_of_code <= it.data.span.end
+        }) else {
+            return indentation;
+        };
+
+        match &cst.kind {
+            CstKind::Parenthesized {
+                opening_parenthesis: opening,
+                closing_parenthesis: closing,
+                ..
+            }
+            | CstKind::List {
+                opening_parenthesis: opening,
+                closing_parenthesis: closing,
+                ..
+            }
+            | CstKind::Struct {
+                opening_bracket: opening,
+                closing_bracket: closing,
+                ..
+            }
+            | CstKind::Function {
+                opening_curly_brace: opening,
+                closing_curly_brace: closing,
+                ..
+            } => {
+                let closing = closing.unwrap_whitespace_and_comment();
+                if self.is_before_end_of_code(opening) || self.is_closed(&closing) {
+                    return self.indentation_in(&cst.kind.children(), indentation);
+                }
+                if !closing.kind.is_error() && closing.data.span.start == self.start_of_code {
+                    return self.line_indentation(cst);
+                }
+
+                let children = match &cst.kind {
+                    CstKind::Function { body, .. } => body.iter().collect(),
+                    kind => kind.children(),
+                };
+                self.indentation_in(&children, self.line_indentation(cst).with_indent())
+            }
+            CstKind::Match {
+                expression,
+                percent,
+                cases,
+            } => {
+                if self.is_before_end_of_code(percent) {
+                    return self.indentation_in(&[&**expression], indentation);
+                }
+                self.indentation_in(
+                    &cases.iter().collect::<Vec<_>>(),
+                    self.line_indentation(cst).with_indent(),
+                )
+            }
+            CstKind::If { else_, .. } => {
+                // Inside an `if` expression that isn't finished yet, `then`
+                // and `else` go on indented lines.
+                let indentation = if !else_.kind.is_error() && !self.is_before_end_of_code(else_) {
+                    indentation
+                } else {
+                    self.line_indentation(cst).with_indent()
+                };
+                self.indentation_in(&cst.kind.children(), indentation)
+            }
+            CstKind::Assignment {
+                assignment_sign: sign,
+                body,
+                ..
+            }
+            | CstKind::MatchCase {
+                arrow: sign, body, ..
+            } => {
+                if self.is_before_end_of_code(sign) {
+                    return self.indentation_in(&cst.kind.children(), indentation);
+                }
+
+                // A body that starts on the same line as the sign doesn't get
+                // indented, e.g., `foo = bar {` or `foo = 1`.
+                let sign_end = *sign.unwrap_whitespace_and_comment().data.span.end;
+                let is_multiline = body
+                    .iter()
+                    .find(|it| {
+                        !matches!(
+                            it.kind,
+                            CstKind::Whitespace(_)
+                                | CstKind::Newline(_)
+                                | CstKind::Comment { .. }
+                                | CstKind::Error { .. },
+                        )
+                    })
+                    .map_or(true, |first| {
+                        self.source[sign_end..*first.data.span.start].contains('\n')
+                    });
+                let body = body.iter().collect::<Vec<_>>();
+                if !is_multiline {
+                    return self.indentation_in(&body, indentation);
+                }
+                self.indentation_in(&body, self.line_indentation(cst).with_indent())
+            }
+            kind => self.indentation_in(&kind.children(), indentation),
+        }
+    }
+
+    /// Whether the `cst` doesn't end before the end of the code, i.e., the
+    /// code in front of the new line is still part of the `cst`.
+    fn is_before_end_of_code(&self, cst: &Cst) -> bool {
+        cst.unwrap_whitespace_and_comment().data.span.end > self.end_of_code
+    }
+    fn is_closed(&self, closing: &Cst) -> bool {
+        !closing.kind.is_error() && closing.data.span.end <= self.end_of_code
+    }
+
+    /// The indentation of the line on which the `cst` starts.
+    fn line_indentation(&self, cst: &Cst) -> Indentation {
+        let start = *cst.data.span.start;
+        let line_start = self.source[..start].rfind('\n').map_or(0, |it| it + 1);
+        Indentation::of_line(&self.source[line_start..start], self.config)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::new_line_indentation;
+    use crate::FormatterConfig;
+    use candy_frontend::{
+        position::Offset, rcst_to_cst::RcstsToCstsExt, string_to_rcst::parse_rcst,
+    };
+
+    #[test]
+    fn test_new_line_indentation() {
+        test("foo = 1\n$", "");
+        test("foo =\n$", "  ");
+        test("foo := \n$", "  ");
+        test("foo =\n  bar\n$", "  ");
+        test("foo =\n  bar\n$\nbaz = 1\n", "  ");
+        test("foo a b =\n$", "  ");
+        test("foo = { a ->\n$", "  ");
+        test("foo = { a ->\n$}", "");
+        test("foo = { a ->\n  a\n$}", "");
+        test("foo = { a -> a }\n$", "");
+        test("foo = bar {\n$", "  ");
+        test("foo =\n  bar | baz { it ->\n$", "    ");
+        test("foo = bar | baz { it ->\n  it\n}\n$", "");
+        test("foo = (\n$", "  ");
+        test("foo = (\n  1,\n$", "  ");
+        test("foo = (\n  1,\n$)", "");
+        test("foo = [\n$", "  ");
+        test("foo = [Foo: 1,\n$", "  ");
+        test("foo = bar %\n$", "  ");
+        test("foo = if bar\n$", "");
+        test("foo = if bar then 1\n$", "  ");
+        test("foo = if bar then 1 else 2\n$", "");
+        test("foo = bar %\n  Foo ->\n$", "    ");
+        test("foo = bar %\n  Foo -> 1\n$", "  ");
+        test("foo = # A comment.\n$", "  ");
+        test("foo =\n  # A comment.\n$", "  ");
+        test("foo = { a ->\n  a # A comment.\n$", "  ");
+    }
+
+    #[track_caller]
+    fn test(source: &str, expected: &str) {
+        let offset = source.find('$').unwrap();
+        let source = source.replacen('$', "", 1);
+        let csts = parse_rcst(&source).to_csts();
+        assert_eq!(
+            new_line_indentation(&source, &csts, Offset(offset), FormatterConfig::default()),
+            expected,
+            "Wrong indentation for {source:?} at {offset}.",
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::config::FormatterConfig;
 use derive_more::{Add, From, Sub};
 use extension_trait::extension_trait;
 use std::{
@@ -6,26 +7,63 @@ use std::{
     ops::{Add, AddAssign},
 };
 
-#[derive(Clone, Copy, Debug, Default, Eq, From, Hash, Ord, PartialEq, PartialOrd)]
-pub struct Indentation(usize);
+/// The indentation level together with the config, which determines how wide
+/// each level is and how much space is left in a line.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Indentation {
+    level: usize,
+    config: FormatterConfig,
+}
 impl Indentation {
+    pub const fn new(config: FormatterConfig) -> Self {
+        Self { level: 0, config }
+    }
+    /// The indentation of a line of source code, rounded down to full levels.
+    pub fn of_line(line: &str, config: FormatterConfig) -> Self {
+        let spaces = line.chars().take_while(|it| *it == ' ').count();
+        Self {
+            level: spaces / config.indent_width.max(1),
+            config,
+        }
+    }
+
+    pub const fn config(self) -> FormatterConfig {
+        self.config
+    }
     pub fn width(self) -> SinglelineWidth {
-        SinglelineWidth::from(self.0 * 2)
+        SinglelineWidth::from(self.level * self.config.indent_width)
+    }
+    pub fn max_line_width(self) -> SinglelineWidth {
+        SinglelineWidth::from(self.config.max_line_width)
     }
     pub const fn is_indented(self) -> bool {
-        self.0 > 0
+        self.level > 0
     }
 
     pub const fn with_indent(self) -> Self {
-        Self(self.0 + 1)
+        Self {
+            level: self.level + 1,
+            config: self.config,
+        }
     }
     pub const fn with_dedent(self) -> Self {
-        Self(self.0 - 1)
+        Self {
+            level: self.level - 1,
+            config: self.config,
+        }
+    }
+}
+impl From<usize> for Indentation {
+    fn from(level: usize) -> Self {
+        Self {
+            level,
+            config: FormatterConfig::default(),
+        }
     }
 }
 impl Display for Indentation {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", "  ".repeat(self.0))?;
+        write!(f, "{}", " ".repeat(self.level * self.config.indent_width))?;
         Ok(())
     }
 }
@@ -66,7 +104,6 @@ pub enum Width {
     },
 }
 impl Width {
-    pub const MAX: SinglelineWidth = SinglelineWidth::new_const(100);
     pub const NEWLINE: Self = Self::Multiline {
         first_line_width: Some(SinglelineWidth::new_const(0)),
         last_line_width: Some(SinglelineWidth::new_const(0)),
@@ -126,7 +163,10 @@ impl Width {
     }
 
     pub fn fits(&self, indentation: Indentation) -> bool {
-        self.fits_in(Self::MAX - indentation.width())
+        match self {
+            Self::Singleline(width) => indentation.width() + *width <= indentation.max_line_width(),
+            Self::Multiline { .. } => false,
+        }
     }
     pub fn fits_in(&self, max_width: SinglelineWidth) -> bool {
         match self {
@@ -140,11 +180,11 @@ impl Width {
         };
         match self {
             Self::Singleline(self_width) => {
-                indentation.width() + *self_width + extra_width <= Self::MAX
+                indentation.width() + *self_width + extra_width <= indentation.max_line_width()
             }
             Self::Multiline {
                 last_line_width, ..
-            } => last_line_width.unwrap() + extra_width <= Self::MAX,
+            } => last_line_width.unwrap() + extra_width <= indentation.max_line_width(),
         }
     }
 }
@@ -160,7 +200,7 @@ impl From<usize> for Width {
 }
 impl From<SinglelineWidth> for Width {
     fn from(width: SinglelineWidth) -> Self {
-        Self::from_width_and_max(width, Self::MAX)
+        Self::Singleline(width)
     }
 }
 
@@ -175,12 +215,7 @@ impl Add<Self> for Width {
             let (Some(lhs), Some(rhs)) = (lhs.into(), rhs.into()) else {
                 return None;
             };
-            let sum = lhs + rhs;
-            if sum <= Width::MAX {
-                Some(sum)
-            } else {
-                None
-            }
+            Some(lhs + rhs)
         }
 
         match (self, rhs) {
@@ -0,0 +1,209 @@
+//! A simple entry point for tools like linters or code search that want to
+//! inspect Candy code without the CLI.
+//!
+//! [`Analysis`] sets up the query database and module providers internally and
+//! exposes the results of the individual compiler stages (see the
+//! [README](https://github.com/candy-lang/candy/blob/main/compiler/frontend/src/README.md)
+//! for an overview).
+//!
+//! ```no_run
+//! # use candy_frontend::{analysis::Analysis, module::PackagesPath};
+//! # use std::path::Path;
+//! let packages_path = PackagesPath::try_from("packages").unwrap();
+//! let analysis = Analysis::new(packages_path, Path::new("packages/Examples/fibonacci.candy")).unwrap();
+//! for error in analysis.diagnostics() {
+//!     let range = analysis.positions(error.span.clone());
+//!     println!("{}: {}", range.start, error.payload);
+//! }
+//! ```
+
+use crate::{
+    ast::{Ast, AstDbStorage},
+    ast_to_hir::{AstToHir, AstToHirStorage},
+    cst::{Cst, CstDbStorage},
+    cst_to_ast::{CstToAst, CstToAstStorage},
+    error::CompilerError,
+    hir::{self, CollectErrors, HirDbStorage},
+    lint::{lint_module, LintViolation},
+    module::{
+        FileSystemModuleProvider, GetModuleContentQuery, InMemoryModuleProvider, Module,
+        ModuleDbStorage, ModuleFromPathError, ModuleKind, ModuleProvider, ModuleProviderOwner,
+        MutableModuleProviderOwner, OverlayModuleProvider, PackagesPath,
+    },
+    position::{Offset, Position, PositionConversionDb, PositionConversionStorage},
+    rcst_to_cst::{RcstToCst, RcstToCstStorage},
+    string_to_rcst::{ModuleError, RcstCacheOwner, StringToRcstStorage},
+};
+use std::{ops::Range, path::Path, sync::Arc};
+
+/// The analysis of a single Candy module.
+///
+/// Results are computed lazily and cached, so calling the same method twice is
+/// cheap.
+pub struct Analysis {
+    db: Database,
+    module: Module,
+}
+impl Analysis {
+    /// Analyzes the Candy file at `path`.
+    ///
+    /// Imports are resolved relative to the file's package or, for managed
+    /// packages like `Core`, inside `packages_path`.
+    pub fn new(packages_path: PackagesPath, path: &Path) -> Result<Self, ModuleFromPathError> {
+        // `Module::from_path(…)` panics for files that don't exist.
+        if !path.exists() {
+            return Err(ModuleFromPathError::NotFound(path.to_owned()));
+        }
+        let module = Module::from_path(&packages_path, path, ModuleKind::Code)?;
+        Ok(Self {
+            db: Database::new(packages_path),
+            module,
+        })
+    }
+
+    #[must_use]
+    pub const fn module(&self) -> &Module {
+        &self.module
+    }
+
+    /// Analyzes `source` instead of the content of the file on disk, e.g., for
+    /// unsaved changes in an editor.
+    pub fn set_source(&mut self, source: impl Into<String>) {
+        self.db
+            .did_change_module(&self.module, source.into().into_bytes());
+    }
+
+    pub fn cst(&self) -> Result<Arc<Vec<Cst>>, ModuleError> {
+        self.db.cst(self.module.clone())
+    }
+    pub fn ast(&self) -> Result<Arc<Vec<Ast>>, ModuleError> {
+        self.db.ast(self.module.clone()).map(|(ast, _)| ast)
+    }
+    pub fn hir(&self) -> Result<Arc<hir::Body>, ModuleError> {
+        self.db.hir(self.module.clone()).map(|(hir, _)| hir)
+    }
+
+    /// Errors in this module, not including errors of imported modules.
+    #[must_use]
+    pub fn diagnostics(&self) -> Vec<CompilerError> {
+        match self.hir() {
+            Ok(hir) => {
+                let mut errors = vec![];
+                hir.collect_errors(&mut errors);
+                errors
+            }
+            Err(error) => vec![CompilerError::for_whole_module(self.module.clone(), error)],
+        }
+    }
+
+    /// Results of the lints configured in the package's `_package.candy`.
+    #[must_use]
+    pub fn lints(&self) -> Vec<LintViolation> {
+        lint_module(&self.db, &self.module)
+    }
+
+    /// Converts a byte range (as used by [`Cst::data`] and [`CompilerError`])
+    /// to line and character positions.
+    #[must_use]
+    pub fn positions(&self, range: Range<Offset>) -> Range<Position> {
+        self.db.range_to_positions(self.module.clone(), range)
+    }
+    /// The source range of a HIR expression, e.g., to report lints.
+    #[must_use]
+    pub fn span_of(&self, id: &hir::Id) -> Option<Range<Offset>> {
+        self.db.hir_id_to_span(id)
+    }
+}
+
+#[salsa::database(
+    AstDbStorage,
+    AstToHirStorage,
+    CstDbStorage,
+    CstToAstStorage,
+    HirDbStorage,
+    ModuleDbStorage,
+    PositionConversionStorage,
+    RcstToCstStorage,
+    StringToRcstStorage
+)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    module_provider: OverlayModuleProvider<InMemoryModuleProvider, FileSystemModuleProvider>,
+}
+impl salsa::Database for Database {}
+
+impl Database {
+    fn new(packages_path: PackagesPath) -> Self {
+        Self {
+            storage: salsa::Storage::default(),
+            module_provider: OverlayModuleProvider::new(
+                InMemoryModuleProvider::default(),
+                FileSystemModuleProvider { packages_path },
+            ),
+        }
+    }
+}
+
+impl ModuleProviderOwner for Database {
+    fn get_module_provider(&self) -> &dyn ModuleProvider {
+        &self.module_provider
+    }
+}
+impl RcstCacheOwner for Database {}
+impl MutableModuleProviderOwner for Database {
+    fn get_in_memory_module_provider(&mut self) -> &mut InMemoryModuleProvider {
+        &mut self.module_provider.overlay
+    }
+    fn invalidate_module(&mut self, module: &Module) {
+        GetModuleContentQuery.in_db_mut(self).invalidate(module);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Analysis;
+    use crate::{
+        error::CompilerErrorPayload,
+        module::{ModuleFromPathError, PackagesPath},
+    };
+    use std::{fs, path::Path};
+
+    #[test]
+    fn analyzes_files_and_unsaved_changes() {
+        let packages_path = PackagesPath::try_from(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("../../packages")
+                .as_path(),
+        )
+        .unwrap();
+        let package = std::env::temp_dir().join("candy_analysis_test");
+        fs::create_dir_all(&package).unwrap();
+        fs::write(package.join("_package.candy"), "").unwrap();
+        let file = package.join("_.candy");
+        fs::write(&file, "foo = 1\n").unwrap();
+
+        let mut analysis = Analysis::new(packages_path.clone(), &file).unwrap();
+        assert_eq!(analysis.cst().unwrap().len(), 2);
+        assert!(analysis
+            .hir()
+            .unwrap()
+            .identifiers
+            .values()
+            .any(|it| it == "foo"));
+        assert!(analysis.diagnostics().is_empty());
+
+        analysis.set_source("foo = (\n");
+        let diagnostics = analysis.diagnostics();
+        assert!(!diagnostics.is_empty());
+        assert!(diagnostics
+            .iter()
+            .all(|it| !matches!(it.payload, CompilerErrorPayload::Module(_))));
+        let range = analysis.positions(diagnostics[0].span.clone());
+        assert!(range.start.line <= 1);
+
+        assert!(matches!(
+            Analysis::new(packages_path, &package.join("missing.candy")),
+            Err(ModuleFromPathError::NotFound(_)),
+        ));
+    }
+}
@@ -5,7 +5,8 @@ use crate::{
 };
 use derive_more::{Deref, From};
 use enumset::EnumSet;
-use num_bigint::{BigInt, BigUint};
+use num_bigint::BigInt;
+use ordered_float::OrderedFloat;
 use rustc_hash::FxHashMap;
 use std::{
     fmt::{self, Display, Formatter},
@@ -54,6 +55,7 @@ pub struct Ast {
 #[derive(Clone, Debug, EnumIs, Eq, From, Hash, PartialEq)]
 pub enum AstKind {
     Int(Int),
+    Float(Float),
     Text(Text),
     TextPart(TextPart),
     Identifier(Identifier),
@@ -71,7 +73,10 @@ pub enum AstKind {
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Hash)]
-pub struct Int(pub BigUint);
+pub struct Int(pub BigInt);
+
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct Float(pub OrderedFloat<f64>);
 
 #[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub struct Text(pub Vec<Ast>);
@@ -115,6 +120,9 @@ pub struct Call {
 #[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub struct Assignment {
     pub is_public: bool,
+    /// The comment lines directly above the assignment, without their leading
+    /// `#` or `##`.
+    pub documentation: Option<String>,
     pub body: AssignmentBody,
 }
 #[derive(Debug, PartialEq, Eq, Clone, Hash)]
@@ -145,6 +153,7 @@ pub struct AstString {
 
 #[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub enum AstError {
+    BinaryOperatorIsNotAssociative,
     ExpectedNameOrPatternInAssignment,
     ExpectedParameter,
     FunctionMissesClosingCurlyBrace,
@@ -182,6 +191,7 @@ impl FindAst for Ast {
 
         match &self.kind {
             AstKind::Int(_) => None,
+            AstKind::Float(_) => None,
             AstKind::Text(_) => None,
             AstKind::TextPart(_) => None,
             AstKind::Identifier(_) => None,
@@ -271,7 +281,7 @@ impl AstKind {
     }
     fn captured_identifiers_helper(&self, captured_identifiers: &mut FxHashMap<String, Vec<Id>>) {
         match self {
-            Self::Int(_) | Self::Text(_) | Self::TextPart(_) => {}
+            Self::Int(_) | Self::Float(_) | Self::Text(_) | Self::TextPart(_) => {}
             Self::Identifier(Identifier(identifier)) => {
                 let entry = captured_identifiers
                     .entry(identifier.value.clone())
@@ -315,6 +325,7 @@ impl CollectErrors for Ast {
     fn collect_errors(self, errors: &mut Vec<CompilerError>) {
         match self.kind {
             AstKind::Int(_) => {}
+            AstKind::Float(_) => {}
             AstKind::Text(Text(parts)) => parts.collect_errors(errors),
             AstKind::TextPart(_) => {}
             AstKind::Identifier(_) => {}
@@ -381,6 +392,7 @@ impl ToRichIr for Ast {
     fn build_rich_ir(&self, builder: &mut RichIrBuilder) {
         match &self.kind {
             AstKind::Int(int) => int.build_rich_ir(builder),
+            AstKind::Float(float) => float.build_rich_ir(builder),
             AstKind::Text(text) => text.build_rich_ir(builder),
             AstKind::TextPart(part) => part.build_rich_ir(builder),
             AstKind::Identifier(identifier) => identifier.build_rich_ir(builder),
@@ -404,7 +416,17 @@ impl ToRichIr for Ast {
 impl ToRichIr for Int {
     fn build_rich_ir(&self, builder: &mut RichIrBuilder) {
         let range = builder.push(format!("int {}", self.0), TokenType::Int, EnumSet::empty());
-        builder.push_reference(BigInt::from(self.0.clone()), range);
+        builder.push_reference(self.0.clone(), range);
+    }
+}
+impl ToRichIr for Float {
+    fn build_rich_ir(&self, builder: &mut RichIrBuilder) {
+        let range = builder.push(
+            format!("float {:?}", self.0),
+            TokenType::Float,
+            EnumSet::empty(),
+        );
+        builder.push_reference(self.0, range);
     }
 }
 impl ToRichIr for Text {
@@ -1,11 +1,11 @@
 use crate::{
     ast::{
-        self, Assignment, Ast, AstKind, AstString, Call, Identifier, Int, List, MatchCase,
+        self, Assignment, Ast, AstKind, AstString, Call, Float, Identifier, Int, List, MatchCase,
         OrPattern, Struct, StructAccess, Symbol, Text, TextPart,
     },
     builtin_functions::BuiltinFunction,
     cst::{self, CstDb},
-    cst_to_ast::CstToAst,
+    cst_to_ast::{CstToAst, SUGAR_BUILTINS_IDENTIFIER},
     error::{CompilerError, CompilerErrorPayload},
     hir::{
         self, Body, Expression, Function, FunctionKind, HirError, IdKey, Pattern,
@@ -15,6 +15,7 @@ use crate::{
     module::{Module, Package},
     position::Offset,
     string_to_rcst::ModuleError,
+    timings::{self, Stage},
     utils::AdjustCasingOfFirstLetter,
 };
 use itertools::Itertools;
@@ -32,6 +33,11 @@ pub trait AstToHir: CstDb + CstToAst {
     fn hir_id_to_span(&self, id: &hir::Id) -> Option<Range<Offset>>;
     #[salsa::transparent]
     fn hir_id_to_display_span(&self, id: &hir::Id) -> Option<Range<Offset>>;
+    /// Like [`hir_id_to_span`], but IDs generated by the compiler (e.g., during
+    /// desugaring or optimization) fall back to the span of their closest
+    /// ancestor that has one.
+    #[salsa::transparent]
+    fn hir_id_to_best_effort_span(&self, id: &hir::Id) -> Option<Range<Offset>>;
 
     #[salsa::transparent]
     fn ast_to_hir_ids(&self, id: &ast::Id) -> Vec<hir::Id>;
@@ -59,6 +65,18 @@ fn hir_to_cst_id(db: &dyn AstToHir, id: &hir::Id) -> Option<cst::Id> {
 fn hir_id_to_span(db: &dyn AstToHir, id: &hir::Id) -> Option<Range<Offset>> {
     db.ast_id_to_span(&db.hir_to_ast_id(id)?)
 }
+fn hir_id_to_best_effort_span(db: &dyn AstToHir, id: &hir::Id) -> Option<Range<Offset>> {
+    if id.module.package.is_tooling() {
+        return None;
+    }
+    let mut id = id.clone();
+    loop {
+        if let Some(span) = db.hir_id_to_span(&id) {
+            return Some(span);
+        }
+        id = id.parent()?;
+    }
+}
 fn hir_id_to_display_span(db: &dyn AstToHir, id: &hir::Id) -> Option<Range<Offset>> {
     let cst_id = db.hir_to_cst_id(id)?;
     Some(db.find_cst(id.module.clone(), cst_id).display_span())
@@ -88,10 +106,11 @@ fn cst_to_last_hir_id(db: &dyn AstToHir, module: Module, id: cst::Id) -> Option<
 }
 
 fn hir(db: &dyn AstToHir, module: Module) -> HirResult {
-    db.ast(module.clone()).map(|(ast, _)| {
+    let (ast, _) = db.ast(module.clone())?;
+    Ok(timings::record_stage(Stage::Hir, module.clone(), || {
         let (body, id_mapping) = compile_top_level(db, module, &ast);
         (Arc::new(body), Arc::new(id_mapping))
-    })
+    }))
 }
 
 fn compile_top_level(
@@ -109,8 +128,10 @@ fn compile_top_level(
         id_prefix: hir::Id::new(module, vec![]),
         identifiers: im::HashMap::new(),
         is_top_level: true,
+        current_definition: None,
         use_id: None,
         builtins_id: None,
+        known_arities: FxHashMap::default(),
     };
 
     context.generate_use();
@@ -139,8 +160,20 @@ struct Context<'a> {
     id_prefix: hir::Id,
     identifiers: im::HashMap<String, hir::Id>,
     is_top_level: bool,
+    /// The name of the top-level assignment being compiled. Anonymous
+    /// top-level expressions get IDs relative to it.
+    current_definition: Option<String>,
     use_id: Option<hir::Id>,
     builtins_id: Option<hir::Id>,
+    /// Functions whose number of parameters is statically known, including
+    /// references to them. Calls of these are checked for the right number of
+    /// arguments.
+    known_arities: FxHashMap<hir::Id, KnownArity>,
+}
+#[derive(Clone, Copy)]
+struct KnownArity {
+    parameters: usize,
+    definition: Option<cst::Id>,
 }
 
 impl Context<'_> {
@@ -198,6 +231,9 @@ impl Context<'_> {
     fn compile_single(&mut self, ast: &Ast) -> hir::Id {
         match &ast.kind {
             AstKind::Int(Int(int)) => self.push(ast.id.clone(), Expression::Int(int.clone()), None),
+            AstKind::Float(Float(float)) => {
+                self.push(ast.id.clone(), Expression::Float(*float), None)
+            }
             AstKind::Text(text) => self.lower_text(Some(ast.id.clone()), text),
             AstKind::TextPart(TextPart(string)) => {
                 self.push(ast.id.clone(), Expression::Text(string.value.clone()), None)
@@ -264,7 +300,11 @@ impl Context<'_> {
             }
             AstKind::Function(function) => self.compile_function(ast.id.clone(), function, None),
             AstKind::Call(call) => self.lower_call(Some(ast.id.clone()), call),
-            AstKind::Assignment(Assignment { is_public, body }) => {
+            AstKind::Assignment(Assignment {
+                is_public,
+                documentation,
+                body,
+            }) => {
                 // An assignment to a single identifier (i.e., no destructuring)
                 // gets converted to at least two HIR expressions:
                 //
@@ -277,6 +317,9 @@ impl Context<'_> {
                 let (names, body) = match body {
                     ast::AssignmentBody::Function { name, function } => {
                         let body = self.compile_function(ast.id.clone(), function, &***name);
+                        if let Some(arity) = self.known_arities.get_mut(&body) {
+                            arity.definition = self.db.ast_to_cst_id(&name.id);
+                        }
                         let name_id = self.push(
                             name.id.clone(),
                             Expression::Reference(body.clone()),
@@ -285,6 +328,14 @@ impl Context<'_> {
                         (vec![(name.value.clone(), name.id.clone(), name_id)], body)
                     }
                     ast::AssignmentBody::Body { pattern, body } => {
+                        let definition = match &pattern.kind {
+                            AstKind::Identifier(Identifier(name)) if self.is_top_level => {
+                                Some(name.value.clone())
+                            }
+                            _ => None,
+                        };
+                        let previous_definition =
+                            mem::replace(&mut self.current_definition, definition);
                         let body = self.with_non_top_level(|scope| scope.compile(body));
 
                         let names = if let AstKind::Identifier(Identifier(name)) = &pattern.kind {
@@ -330,10 +381,18 @@ impl Context<'_> {
                             Expression::Symbol("Nothing".to_string()),
                             None,
                         );
+                        self.current_definition = previous_definition;
 
                         (names, nothing_id)
                     }
                 };
+                if let Some(documentation) = documentation {
+                    for (_, _, id) in &names {
+                        self.body
+                            .documentation
+                            .insert(id.clone(), documentation.clone());
+                    }
+                }
                 if *is_public {
                     if self.is_top_level {
                         for (name, ast_id, id) in names {
@@ -530,7 +589,15 @@ impl Context<'_> {
         function: &ast::Function,
         identifier: impl Into<Option<&str>>,
     ) -> hir::Id {
+        let definition = self.db.ast_to_cst_id(&id);
         let function_id = self.create_next_id(id, identifier);
+        self.known_arities.insert(
+            function_id.clone(),
+            KnownArity {
+                parameters: function.parameters.len(),
+                definition,
+            },
+        );
         let (inner_body, parameters) = self.with_scope(function_id.clone(), |scope| {
             // TODO: Error on parameters with same name
             let mut parameters = Vec::with_capacity(function.parameters.len());
@@ -614,19 +681,38 @@ impl Context<'_> {
         };
 
         let struct_ = self.compile_single(&struct_access.struct_);
+        let key = struct_access.key.value.uppercase_first_letter();
         let key_id = self.push(
             struct_access.key.id.clone(),
-            Expression::Symbol(struct_access.key.value.uppercase_first_letter()),
+            Expression::Symbol(key.clone()),
             None,
         );
-        self.push(
+        let access_id = self.push(
             id,
             Expression::Call {
                 function: struct_get_id,
                 arguments: vec![struct_, key_id],
             },
             None,
-        )
+        );
+
+        // Builtin functions accessed via `✨.someBuiltin` or syntactic sugar
+        // have a known arity.
+        if let AstKind::Identifier(Identifier(struct_name)) = &struct_access.struct_.kind
+            && (struct_name.value == "✨" || struct_name.value == SUGAR_BUILTINS_IDENTIFIER)
+            && let Some(builtin) = BuiltinFunction::VARIANTS
+                .iter()
+                .find(|it| it.as_ref() == key)
+        {
+            self.known_arities.insert(
+                access_id.clone(),
+                KnownArity {
+                    parameters: builtin.num_parameters(),
+                    definition: None,
+                },
+            );
+        }
+        access_id
     }
 
     fn lower_call(&mut self, id: Option<ast::Id>, call: &Call) -> hir::Id {
@@ -647,6 +733,12 @@ impl Context<'_> {
                     [condition, reason] => Expression::Needs {
                         condition: condition.clone(),
                         reason: reason.clone(),
+                        context: None,
+                    },
+                    [condition, reason, context] => Expression::Needs {
+                        condition: condition.clone(),
+                        reason: reason.clone(),
+                        context: Some(context.clone()),
                     },
                     [condition] => Expression::Needs {
                         condition: condition.clone(),
@@ -666,6 +758,7 @@ impl Context<'_> {
                             }),
                             None,
                         ),
+                        context: None,
                     },
                     _ => {
                         return self.push_error(
@@ -682,6 +775,39 @@ impl Context<'_> {
             _ => self.compile_single(call.receiver.as_ref()),
         };
         arguments.extend(self.lower_call_arguments(uncompiled_arguments));
+        // Tags with a value like `Some 5` don't need the dynamic dispatch of
+        // calls. Calling a tag with more values still panics at runtime.
+        if let AstKind::Symbol(Symbol(symbol)) = &call.receiver.kind
+            && let [value] = arguments.as_slice()
+        {
+            return self.push(
+                id,
+                Expression::Tag {
+                    symbol: symbol.value.clone(),
+                    value: value.clone(),
+                },
+                None,
+            );
+        }
+        if let Some(arity) = self.known_arities.get(&function).copied()
+            && arity.parameters != arguments.len()
+        {
+            let receiver_span = self.db.ast_id_to_display_span(&call.receiver.id).unwrap();
+            let receiver = &self
+                .db
+                .get_module_content_as_string(self.module.clone())
+                .unwrap()[*receiver_span.start..*receiver_span.end];
+            return self.push_error(
+                id,
+                receiver_span,
+                HirError::CallWithWrongNumberOfArguments {
+                    function: Some(receiver.to_string()).filter(|it| !it.contains('\n')),
+                    expected: arity.parameters,
+                    actual: arguments.len(),
+                    definition: arity.definition,
+                },
+            );
+        }
         self.push(
             id,
             Expression::Call {
@@ -726,6 +852,11 @@ impl Context<'_> {
         identifier: impl Into<Option<String>>,
     ) -> hir::Id {
         let identifier = identifier.into();
+        if let Expression::Reference(target) = &expression
+            && let Some(arity) = self.known_arities.get(target).copied()
+        {
+            self.known_arities.insert(id.clone(), arity);
+        }
         self.body.push(id.clone(), expression, identifier.clone());
         if let Some(identifier) = identifier {
             self.identifiers.insert(identifier, id.clone());
@@ -757,9 +888,21 @@ impl Context<'_> {
         key: impl Into<Option<&str>>,
     ) -> hir::Id {
         let key = key.into();
+        let definition = self
+            .current_definition
+            .as_ref()
+            .filter(|_| self.id_prefix.is_root());
         for disambiguator in 0.. {
             let last_part = key.as_ref().map_or_else(
-                || disambiguator.into(),
+                || {
+                    definition.map_or_else(
+                        || disambiguator.into(),
+                        |definition| IdKey::InDefinition {
+                            definition: definition.clone(),
+                            index: disambiguator,
+                        },
+                    )
+                },
                 |key| {
                     if disambiguator == 0 {
                         (*key).to_string().into()
@@ -794,7 +937,9 @@ impl Context<'_> {
         }
 
         let sparkles_map = Expression::Struct(sparkles_map);
-        self.push(None, sparkles_map, "✨".to_string());
+        let sparkles_id = self.push(None, sparkles_map, "✨".to_string());
+        self.identifiers
+            .insert(SUGAR_BUILTINS_IDENTIFIER.to_string(), sparkles_id);
     }
 
     fn generate_use(&mut self) {
@@ -846,6 +991,8 @@ impl Context<'_> {
             },
             None,
         );
+        self.identifiers
+            .insert(SUGAR_BUILTINS_IDENTIFIER.to_string(), builtins_id.clone());
         self.builtins_id = Some(builtins_id);
     }
 
@@ -884,6 +1031,7 @@ impl<'a> PatternContext<'a> {
     fn compile_pattern(&mut self, ast: &Ast) -> Pattern {
         match &ast.kind {
             AstKind::Int(Int(int)) => Pattern::Int(int.clone()),
+            AstKind::Float(Float(float)) => Pattern::Float(*float),
             AstKind::Text(Text(text)) => Pattern::Text(
                 text.iter()
                     .map(|part| match &part.kind {
@@ -984,3 +1132,123 @@ impl<'a> PatternContext<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        analysis::Analysis,
+        error::CompilerErrorPayload,
+        hir::{Expression, HirError},
+        module::PackagesPath,
+    };
+    use std::{fs, path::Path};
+
+    fn analyze(package_name: &str, source: &str) -> Analysis {
+        let packages_path = PackagesPath::try_from(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("../../packages")
+                .as_path(),
+        )
+        .unwrap();
+        let package = std::env::temp_dir().join(package_name);
+        fs::create_dir_all(&package).unwrap();
+        fs::write(package.join("_package.candy"), "").unwrap();
+        let file = package.join("_.candy");
+        fs::write(&file, source).unwrap();
+        Analysis::new(packages_path, &file).unwrap()
+    }
+
+    #[test]
+    fn calls_of_known_functions_check_the_number_of_arguments() {
+        let analysis = analyze(
+            "candy_arity_test",
+            "foo a b = a\nbar = foo\nbaz = bar 1\nqux = 1 | foo 2\n",
+        );
+        let errors = analysis
+            .diagnostics()
+            .into_iter()
+            .map(|it| it.payload)
+            .collect::<Vec<_>>();
+        let [CompilerErrorPayload::Hir(HirError::CallWithWrongNumberOfArguments {
+            function,
+            expected,
+            actual,
+            definition,
+        })] = errors.as_slice()
+        else {
+            panic!("Expected exactly one arity error, got {errors:?}.");
+        };
+        assert_eq!(function.as_deref(), Some("bar"));
+        assert_eq!((*expected, *actual), (2, 1));
+        assert!(definition.is_some());
+    }
+
+    #[test]
+    fn placeholders_create_partial_applications() {
+        let analysis = analyze(
+            "candy_partial_application_test",
+            "foo a b = a\nbar = foo 1 _\nbaz = bar 2\nqux = bar 2 3\n",
+        );
+        let errors = analysis
+            .diagnostics()
+            .into_iter()
+            .map(|it| it.payload)
+            .collect::<Vec<_>>();
+        let [CompilerErrorPayload::Hir(HirError::CallWithWrongNumberOfArguments {
+            function,
+            expected,
+            actual,
+            ..
+        })] = errors.as_slice()
+        else {
+            panic!("Expected exactly one arity error, got {errors:?}.");
+        };
+        assert_eq!(function.as_deref(), Some("bar"));
+        assert_eq!((*expected, *actual), (1, 2));
+    }
+
+    #[test]
+    fn needs_accept_a_context() {
+        let analysis = analyze(
+            "candy_needs_context_test",
+            "x = 1\nneeds True \"Reason.\" x\nneeds True \"Reason.\" x x\n",
+        );
+        let hir = analysis.hir().unwrap();
+        let contexts = hir
+            .expressions
+            .values()
+            .filter_map(|expression| match expression {
+                Expression::Needs { context, .. } => Some(context.is_some()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(contexts, [true]);
+        assert!(matches!(
+            analysis
+                .diagnostics()
+                .into_iter()
+                .map(|it| it.payload)
+                .collect::<Vec<_>>()
+                .as_slice(),
+            [CompilerErrorPayload::Hir(
+                HirError::NeedsWithWrongNumberOfArguments { num_args: 4 }
+            )],
+        ));
+    }
+
+    #[test]
+    fn tags_with_a_value_are_lowered_statically() {
+        let analysis = analyze("candy_tag_test", "a = Some 5\nb = 5 | Some\nc = Some 1 2\n");
+        let hir = analysis.hir().unwrap();
+        let tags = hir
+            .expressions
+            .values()
+            .filter(|expression| matches!(expression, Expression::Tag { symbol, .. } if symbol == "Some"))
+            .count();
+        assert_eq!(tags, 2);
+        assert!(hir
+            .expressions
+            .values()
+            .any(|expression| matches!(expression, Expression::Call { arguments, .. } if arguments.len() == 2)));
+    }
+}
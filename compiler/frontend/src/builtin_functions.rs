@@ -25,9 +25,24 @@ use strum_macros::{AsRefStr, VariantArray};
 #[derive(AsRefStr, Clone, Copy, Debug, Eq, PartialEq, Hash, VariantArray)]
 #[strum(serialize_all = "snake_case")]
 pub enum BuiltinFunction {
+    BytesConcat,
+    BytesGet,
+    BytesLength,
+    BytesSlice,
+    CompareTo,
     Equals,
+    FloatAdd,
+    FloatCompareTo,
+    FloatDivide,
+    FloatFromInt,
+    FloatMultiply,
+    FloatSubtract,
+    FloatToText,
+    FloatTruncate,
     FunctionRun,
+    FunctionTry,
     GetArgumentCount,
+    HashValue,
     IfElse,
     IntAdd,
     IntBitLength,
@@ -36,23 +51,37 @@ pub enum BuiltinFunction {
     IntBitwiseXor,
     IntCompareTo,
     IntDivideTruncating,
+    IntGcd,
     IntModulo,
     IntMultiply,
     IntParse,
+    IntPow,
     IntRemainder,
     IntShiftLeft,
     IntShiftRight,
+    IntSqrt,
     IntSubtract,
+    ListConcatenate,
     ListFilled,
     ListGet,
     ListInsert,
     ListLength,
     ListRemoveAt,
     ListReplace,
+    ListSlice,
+    ListSort,
+    Panic,
     Print,
+    RegexCaptures,
+    RegexFind,
+    RegexIsMatch,
+    StructEntries,
     StructGet,
     StructGetKeys,
     StructHasKey,
+    StructInsert,
+    StructMerge,
+    StructRemove,
     TagGetValue,
     TagHasValue,
     TagWithoutValue,
@@ -63,11 +92,17 @@ pub enum BuiltinFunction {
     TextEndsWith,
     TextFromUtf8,
     TextGetRange,
+    TextIndexOf,
     TextIsEmpty,
     TextLength,
+    TextReplace,
+    TextSplit,
     TextStartsWith,
     TextTrimEnd,
     TextTrimStart,
+    TextToLower,
+    TextToUpper,
+    TextToUtf8Bytes,
     ToDebugText,
     TypeOf,
 }
@@ -76,9 +111,24 @@ impl BuiltinFunction {
     #[must_use]
     pub const fn is_pure(&self) -> bool {
         match self {
+            Self::BytesConcat => true,
+            Self::BytesGet => true,
+            Self::BytesLength => true,
+            Self::BytesSlice => true,
+            Self::CompareTo => true,
             Self::Equals => true,
+            Self::FloatAdd => true,
+            Self::FloatCompareTo => true,
+            Self::FloatDivide => true,
+            Self::FloatFromInt => true,
+            Self::FloatMultiply => true,
+            Self::FloatSubtract => true,
+            Self::FloatToText => true,
+            Self::FloatTruncate => true,
             Self::FunctionRun => false,
+            Self::FunctionTry => false,
             Self::GetArgumentCount => true,
+            Self::HashValue => true,
             Self::IfElse => false,
             Self::IntAdd => true,
             Self::IntBitLength => true,
@@ -87,23 +137,37 @@ impl BuiltinFunction {
             Self::IntBitwiseXor => true,
             Self::IntCompareTo => true,
             Self::IntDivideTruncating => true,
+            Self::IntGcd => true,
             Self::IntModulo => true,
             Self::IntMultiply => true,
             Self::IntParse => true,
+            Self::IntPow => true,
             Self::IntRemainder => true,
             Self::IntShiftLeft => true,
             Self::IntShiftRight => true,
+            Self::IntSqrt => true,
             Self::IntSubtract => true,
+            Self::ListConcatenate => true,
             Self::ListFilled => true,
             Self::ListGet => true,
             Self::ListInsert => true,
             Self::ListLength => true,
             Self::ListRemoveAt => true,
             Self::ListReplace => true,
+            Self::ListSlice => true,
+            Self::ListSort => false,
+            Self::Panic => false,
             Self::Print => false,
+            Self::RegexCaptures => true,
+            Self::RegexFind => true,
+            Self::RegexIsMatch => true,
+            Self::StructEntries => true,
             Self::StructGet => true,
             Self::StructGetKeys => true,
             Self::StructHasKey => true,
+            Self::StructInsert => true,
+            Self::StructMerge => true,
+            Self::StructRemove => true,
             Self::TagGetValue => true,
             Self::TagHasValue => true,
             Self::TagWithoutValue => true,
@@ -114,11 +178,17 @@ impl BuiltinFunction {
             Self::TextEndsWith => true,
             Self::TextFromUtf8 => true,
             Self::TextGetRange => true,
+            Self::TextIndexOf => true,
             Self::TextIsEmpty => true,
             Self::TextLength => true,
+            Self::TextReplace => true,
+            Self::TextSplit => true,
             Self::TextStartsWith => true,
             Self::TextTrimEnd => true,
             Self::TextTrimStart => true,
+            Self::TextToLower => true,
+            Self::TextToUpper => true,
+            Self::TextToUtf8Bytes => true,
             Self::ToDebugText => true,
             Self::TypeOf => true,
         }
@@ -126,52 +196,93 @@ impl BuiltinFunction {
 
     #[must_use]
     pub const fn num_parameters(&self) -> usize {
+        self.parameter_names().len()
+    }
+    /// The names of the parameters of the corresponding function in the
+    /// `Builtins` package.
+    #[must_use]
+    pub const fn parameter_names(&self) -> &'static [&'static str] {
         match self {
-            Self::Equals => 2,
-            Self::FunctionRun => 1,
-            Self::GetArgumentCount => 1,
-            Self::IfElse => 3,
-            Self::IntAdd => 2,
-            Self::IntBitLength => 1,
-            Self::IntBitwiseAnd => 2,
-            Self::IntBitwiseOr => 2,
-            Self::IntBitwiseXor => 2,
-            Self::IntCompareTo => 2,
-            Self::IntDivideTruncating => 2,
-            Self::IntModulo => 2,
-            Self::IntMultiply => 2,
-            Self::IntParse => 1,
-            Self::IntRemainder => 2,
-            Self::IntShiftLeft => 2,
-            Self::IntShiftRight => 2,
-            Self::IntSubtract => 2,
-            Self::ListFilled => 2,
-            Self::ListGet => 2,
-            Self::ListInsert => 3,
-            Self::ListLength => 1,
-            Self::ListRemoveAt => 2,
-            Self::ListReplace => 3,
-            Self::Print => 1,
-            Self::StructGet => 2,
-            Self::StructGetKeys => 1,
-            Self::StructHasKey => 2,
-            Self::TagGetValue => 1,
-            Self::TagHasValue => 1,
-            Self::TagWithoutValue => 1,
-            Self::TagWithValue => 2,
-            Self::TextCharacters => 1,
-            Self::TextConcatenate => 2,
-            Self::TextContains => 2,
-            Self::TextEndsWith => 2,
-            Self::TextFromUtf8 => 1,
-            Self::TextGetRange => 3,
-            Self::TextIsEmpty => 1,
-            Self::TextLength => 1,
-            Self::TextStartsWith => 2,
-            Self::TextTrimEnd => 1,
-            Self::TextTrimStart => 1,
-            Self::ToDebugText => 1,
-            Self::TypeOf => 1,
+            Self::BytesConcat => &["a", "b"],
+            Self::BytesGet => &["bytes", "index"],
+            Self::BytesLength => &["bytes"],
+            Self::BytesSlice => &["bytes", "startInclusive", "endExclusive"],
+            Self::CompareTo => &["a", "b"],
+            Self::Equals => &["a", "b"],
+            Self::FloatAdd => &["a", "b"],
+            Self::FloatCompareTo => &["a", "b"],
+            Self::FloatDivide => &["dividend", "divisor"],
+            Self::FloatFromInt => &["int"],
+            Self::FloatMultiply => &["factorA", "factorB"],
+            Self::FloatSubtract => &["minuend", "subtrahend"],
+            Self::FloatToText => &["float"],
+            Self::FloatTruncate => &["float"],
+            Self::FunctionRun => &["function"],
+            Self::FunctionTry => &["function"],
+            Self::GetArgumentCount => &["function"],
+            Self::HashValue => &["value"],
+            Self::IfElse => &["condition", "thenBody", "elseBody"],
+            Self::IntAdd => &["a", "b"],
+            Self::IntBitLength => &["value"],
+            Self::IntBitwiseAnd => &["a", "b"],
+            Self::IntBitwiseOr => &["a", "b"],
+            Self::IntBitwiseXor => &["a", "b"],
+            Self::IntCompareTo => &["a", "b"],
+            Self::IntDivideTruncating => &["dividend", "divisor"],
+            Self::IntGcd => &["a", "b"],
+            Self::IntModulo => &["dividend", "divisor"],
+            Self::IntMultiply => &["factorA", "factorB"],
+            Self::IntParse => &["text"],
+            Self::IntPow => &["base", "exponent"],
+            Self::IntRemainder => &["dividend", "divisor"],
+            Self::IntShiftLeft => &["value", "amount"],
+            Self::IntShiftRight => &["value", "amount"],
+            Self::IntSqrt => &["value"],
+            Self::IntSubtract => &["minuend", "subtrahend"],
+            Self::ListConcatenate => &["listA", "listB"],
+            Self::ListFilled => &["length", "item"],
+            Self::ListGet => &["list", "index"],
+            Self::ListInsert => &["list", "index", "item"],
+            Self::ListLength => &["list"],
+            Self::ListRemoveAt => &["list", "index"],
+            Self::ListReplace => &["list", "index", "newItem"],
+            Self::ListSlice => &["list", "startInclusive", "endExclusive"],
+            Self::ListSort => &["list", "compareTo"],
+            Self::Panic => &["reason", "payload"],
+            Self::Print => &["message"],
+            Self::RegexCaptures => &["text", "pattern"],
+            Self::RegexFind => &["text", "pattern"],
+            Self::RegexIsMatch => &["text", "pattern"],
+            Self::StructEntries => &["struct"],
+            Self::StructGet => &["struct", "key"],
+            Self::StructGetKeys => &["struct"],
+            Self::StructHasKey => &["struct", "key"],
+            Self::StructInsert => &["struct", "key", "value"],
+            Self::StructMerge => &["a", "b"],
+            Self::StructRemove => &["struct", "key"],
+            Self::TagGetValue => &["tag"],
+            Self::TagHasValue => &["tag"],
+            Self::TagWithoutValue => &["tag"],
+            Self::TagWithValue => &["tag", "value"],
+            Self::TextCharacters => &["text"],
+            Self::TextConcatenate => &["a", "b"],
+            Self::TextContains => &["text", "pattern"],
+            Self::TextEndsWith => &["text", "suffix"],
+            Self::TextFromUtf8 => &["bytes"],
+            Self::TextGetRange => &["text", "startInclusive", "endExclusive"],
+            Self::TextIndexOf => &["text", "pattern"],
+            Self::TextIsEmpty => &["text"],
+            Self::TextLength => &["text"],
+            Self::TextReplace => &["text", "pattern", "replacement"],
+            Self::TextSplit => &["text", "separator"],
+            Self::TextStartsWith => &["text", "prefix"],
+            Self::TextTrimEnd => &["text"],
+            Self::TextTrimStart => &["text"],
+            Self::TextToLower => &["text"],
+            Self::TextToUpper => &["text"],
+            Self::TextToUtf8Bytes => &["text"],
+            Self::ToDebugText => &["value"],
+            Self::TypeOf => &["value"],
         }
     }
 }
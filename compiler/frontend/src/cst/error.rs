@@ -1,8 +1,12 @@
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum CstError {
     BinaryBarMissesRight,
+    BinaryOperationMissesRight,
     CurlyBraceNotClosed,
+    FloatContainsNonDigits,
     IdentifierContainsNonAlphanumericAscii,
+    IfMissesBody,
+    IfMissesElse,
     IntContainsNonDigits,
     ListItemMissesValue,
     ListNotClosed,
@@ -12,15 +16,18 @@ pub enum CstError {
     OpeningParenthesisMissesExpression,
     OrPatternMissesRight,
     ParenthesisNotClosed,
+    StructAccessMissesKey,
     StructFieldMissesColon,
     StructFieldMissesKey,
     StructFieldMissesValue,
     StructNotClosed,
     SymbolContainsNonAlphanumericAscii,
+    TextEscapeIsUnknown,
     TextInterpolationMissesExpression,
     TextInterpolationNotClosed,
     TextNotClosed,
     TextNotSufficientlyIndented,
+    TextUnicodeEscapeIsInvalid,
     TooMuchWhitespace,
     UnexpectedCharacters,
     UnparsedRest,
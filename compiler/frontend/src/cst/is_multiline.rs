@@ -31,6 +31,10 @@ impl<D> IsMultiline for CstKind<D> {
             Self::DoubleQuote => false,
             Self::Percent => false,
             Self::Octothorpe => false,
+            Self::IfKeyword => false,
+            Self::ThenKeyword => false,
+            Self::ElseKeyword => false,
+            Self::BinaryOperator(_) => false,
             Self::Whitespace(_) => false,
             Self::Newline(_) => true,
             Self::Comment { .. } => false,
@@ -40,6 +44,7 @@ impl<D> IsMultiline for CstKind<D> {
             Self::Identifier(_) => false,
             Self::Symbol(_) => false,
             Self::Int { .. } => false,
+            Self::Float { .. } => false,
             Self::OpeningText { .. } => false,
             Self::ClosingText { .. } => false,
             Self::Text {
@@ -48,11 +53,16 @@ impl<D> IsMultiline for CstKind<D> {
                 closing,
             } => opening.is_multiline() || parts.is_multiline() || closing.is_multiline(),
             Self::TextNewline(_) => true,
-            Self::TextPart(_) => false,
+            Self::TextPart(_) | Self::TextEscape { .. } => false,
             Self::TextInterpolation { expression, .. } => expression.is_multiline(),
             Self::BinaryBar { left, bar, right } => {
                 left.is_multiline() || bar.is_multiline() || right.is_multiline()
             }
+            Self::BinaryOperation {
+                left,
+                operator,
+                right,
+            } => left.is_multiline() || operator.is_multiline() || right.is_multiline(),
             Self::Parenthesized {
                 opening_parenthesis,
                 inner,
@@ -110,6 +120,21 @@ impl<D> IsMultiline for CstKind<D> {
                 arrow,
                 body,
             } => pattern.is_multiline() || arrow.is_multiline() || body.is_multiline(),
+            Self::If {
+                if_keyword,
+                condition,
+                then_keyword,
+                then,
+                else_keyword,
+                else_,
+            } => {
+                if_keyword.is_multiline()
+                    || condition.is_multiline()
+                    || then_keyword.is_multiline()
+                    || then.is_multiline()
+                    || else_keyword.is_multiline()
+                    || else_.is_multiline()
+            }
             Self::Function {
                 opening_curly_brace,
                 parameters_and_arrow,
@@ -1,7 +1,8 @@
 use super::{Cst, CstData, CstError};
-use num_bigint::BigUint;
+use num_bigint::BigInt;
+use ordered_float::OrderedFloat;
 use std::fmt::{self, Display, Formatter};
-use strum_macros::EnumIs;
+use strum_macros::{EnumIs, VariantArray};
 
 #[derive(Clone, Debug, EnumIs, Eq, Hash, PartialEq)]
 pub enum CstKind<D = CstData> {
@@ -22,6 +23,10 @@ pub enum CstKind<D = CstData> {
     DoubleQuote,        // "
     Percent,            // %
     Octothorpe,         // #
+    IfKeyword,          // if
+    ThenKeyword,        // then
+    ElseKeyword,        // else
+    BinaryOperator(BinaryOperator),
     Whitespace(String), // contains only non-multiline whitespace
     Newline(String), // the associated `String` because some systems (such as Windows) have weird newlines
     Comment {
@@ -35,8 +40,13 @@ pub enum CstKind<D = CstData> {
     Identifier(String),
     Symbol(String),
     Int {
+        is_negative: bool,
         radix_prefix: Option<(IntRadix, String)>,
-        value: BigUint,
+        value: BigInt,
+        string: String,
+    },
+    Float {
+        value: OrderedFloat<f64>,
         string: String,
     },
     OpeningText {
@@ -54,6 +64,12 @@ pub enum CstKind<D = CstData> {
     },
     TextNewline(String), // special newline for text because line breaks have semantic meaning there
     TextPart(String),
+    /// An escape sequence like `\n` or `\u{1F600}` in a text without single
+    /// quotes.
+    TextEscape {
+        value: char,
+        string: String,
+    },
     TextInterpolation {
         opening_curly_braces: Vec<Cst<D>>,
         expression: Box<Cst<D>>,
@@ -64,6 +80,11 @@ pub enum CstKind<D = CstData> {
         bar: Box<Cst<D>>,
         right: Box<Cst<D>>,
     },
+    BinaryOperation {
+        left: Box<Cst<D>>,
+        operator: Box<Cst<D>>,
+        right: Box<Cst<D>>,
+    },
     Parenthesized {
         opening_parenthesis: Box<Cst<D>>,
         inner: Box<Cst<D>>,
@@ -107,6 +128,14 @@ pub enum CstKind<D = CstData> {
         arrow: Box<Cst<D>>,
         body: Vec<Cst<D>>,
     },
+    If {
+        if_keyword: Box<Cst<D>>,
+        condition: Box<Cst<D>>,
+        then_keyword: Box<Cst<D>>,
+        then: Box<Cst<D>>,
+        else_keyword: Box<Cst<D>>,
+        else_: Box<Cst<D>>,
+    },
     Function {
         opening_curly_brace: Box<Cst<D>>,
         parameters_and_arrow: Option<FunctionParametersAndArrow<D>>,
@@ -128,6 +157,83 @@ pub enum IntRadix {
     Binary,
     Hexadecimal,
 }
+
+/// Infix operators such as `+` and `==`.
+///
+/// | Operators                | Precedence | Associativity     |
+/// | ------------------------ | ---------- | ----------------- |
+/// | `*`, `/`                 | 4          | left              |
+/// | `+`, `-`                 | 3          | left              |
+/// | `<`, `<=`, `>`, `>=`     | 2          | none              |
+/// | `==`, `!=`               | 1          | none              |
+///
+/// All of them bind more tightly than the pipe (`|`) and less tightly than
+/// calls, so `foo a + b | bar` is `(foo a) + b | bar`.
+#[derive(Clone, Copy, Debug, EnumIs, Eq, Hash, PartialEq, VariantArray)]
+pub enum BinaryOperator {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+#[derive(Clone, Copy, Debug, EnumIs, Eq, Hash, PartialEq)]
+pub enum Associativity {
+    Left,
+    /// Chaining these operators without parentheses, e.g., `a < b < c`, is an
+    /// error.
+    None,
+}
+impl BinaryOperator {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Equal => "==",
+            Self::NotEqual => "!=",
+            Self::Less => "<",
+            Self::LessOrEqual => "<=",
+            Self::Greater => ">",
+            Self::GreaterOrEqual => ">=",
+            Self::Add => "+",
+            Self::Subtract => "-",
+            Self::Multiply => "*",
+            Self::Divide => "/",
+        }
+    }
+    /// Operators with a higher precedence bind more tightly.
+    #[must_use]
+    pub const fn precedence(self) -> u8 {
+        match self {
+            Self::Equal | Self::NotEqual => 1,
+            Self::Less | Self::LessOrEqual | Self::Greater | Self::GreaterOrEqual => 2,
+            Self::Add | Self::Subtract => 3,
+            Self::Multiply | Self::Divide => 4,
+        }
+    }
+    #[must_use]
+    pub const fn associativity(self) -> Associativity {
+        match self {
+            Self::Equal
+            | Self::NotEqual
+            | Self::Less
+            | Self::LessOrEqual
+            | Self::Greater
+            | Self::GreaterOrEqual => Associativity::None,
+            Self::Add | Self::Subtract | Self::Multiply | Self::Divide => Associativity::Left,
+        }
+    }
+}
+impl Display for BinaryOperator {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
 pub type FunctionParametersAndArrow<D> = (Vec<Cst<D>>, Box<Cst<D>>);
 
 impl<D> CstKind<D> {
@@ -160,6 +266,10 @@ impl<D> CstKind<D> {
             | Self::DoubleQuote
             | Self::Percent
             | Self::Octothorpe
+            | Self::IfKeyword
+            | Self::ThenKeyword
+            | Self::ElseKeyword
+            | Self::BinaryOperator(_)
             | Self::Whitespace(_)
             | Self::Newline(_) => vec![],
             Self::Comment { octothorpe, .. } => vec![octothorpe],
@@ -168,7 +278,9 @@ impl<D> CstKind<D> {
                 children.extend(whitespace);
                 children
             }
-            Self::Identifier(_) | Self::Symbol(_) | Self::Int { .. } => vec![],
+            Self::Identifier(_) | Self::Symbol(_) | Self::Int { .. } | Self::Float { .. } => {
+                vec![]
+            }
             Self::OpeningText {
                 opening_single_quotes,
                 opening_double_quote,
@@ -196,7 +308,7 @@ impl<D> CstKind<D> {
                 children.push(closing);
                 children
             }
-            Self::TextNewline(_) | Self::TextPart(_) => vec![],
+            Self::TextNewline(_) | Self::TextPart(_) | Self::TextEscape { .. } => vec![],
             Self::TextInterpolation {
                 opening_curly_braces,
                 expression,
@@ -214,6 +326,11 @@ impl<D> CstKind<D> {
                 children.push(right);
                 children
             }
+            Self::BinaryOperation {
+                left,
+                operator,
+                right,
+            } => vec![left.as_ref(), operator.as_ref(), right.as_ref()],
             Self::Parenthesized {
                 opening_parenthesis,
                 inner,
@@ -296,6 +413,21 @@ impl<D> CstKind<D> {
                 children.extend(body);
                 children
             }
+            Self::If {
+                if_keyword,
+                condition,
+                then_keyword,
+                then,
+                else_keyword,
+                else_,
+            } => vec![
+                if_keyword.as_ref(),
+                condition.as_ref(),
+                then_keyword.as_ref(),
+                then.as_ref(),
+                else_keyword.as_ref(),
+                else_.as_ref(),
+            ],
             Self::Function {
                 opening_curly_brace,
                 parameters_and_arrow,
@@ -346,6 +478,10 @@ impl<D> Display for CstKind<D> {
             Self::DoubleQuote => '"'.fmt(f),
             Self::Percent => '%'.fmt(f),
             Self::Octothorpe => '#'.fmt(f),
+            Self::IfKeyword => "if".fmt(f),
+            Self::ThenKeyword => "then".fmt(f),
+            Self::ElseKeyword => "else".fmt(f),
+            Self::BinaryOperator(operator) => operator.fmt(f),
             Self::Whitespace(whitespace) => whitespace.fmt(f),
             Self::Newline(newline) => newline.fmt(f),
             Self::Comment {
@@ -365,15 +501,20 @@ impl<D> Display for CstKind<D> {
             Self::Identifier(identifier) => identifier.fmt(f),
             Self::Symbol(symbol) => symbol.fmt(f),
             Self::Int {
+                is_negative,
                 radix_prefix,
                 value: _,
                 string,
             } => {
+                if *is_negative {
+                    '-'.fmt(f)?;
+                }
                 if let Some((_, radix_string)) = radix_prefix {
                     radix_string.fmt(f)?;
                 }
                 string.fmt(f)
             }
+            Self::Float { value: _, string } => string.fmt(f),
             Self::OpeningText {
                 opening_single_quotes,
                 opening_double_quote,
@@ -406,6 +547,7 @@ impl<D> Display for CstKind<D> {
             }
             Self::TextNewline(newline) => newline.fmt(f),
             Self::TextPart(literal) => literal.fmt(f),
+            Self::TextEscape { string, .. } => string.fmt(f),
             Self::TextInterpolation {
                 opening_curly_braces,
                 expression,
@@ -423,6 +565,11 @@ impl<D> Display for CstKind<D> {
             Self::BinaryBar { left, bar, right } => {
                 write!(f, "{}{}{}", left.kind, bar.kind, right.kind)
             }
+            Self::BinaryOperation {
+                left,
+                operator,
+                right,
+            } => write!(f, "{}{}{}", left.kind, operator.kind, right.kind),
             Self::Parenthesized {
                 opening_parenthesis,
                 inner,
@@ -515,6 +662,21 @@ impl<D> Display for CstKind<D> {
                 }
                 Ok(())
             }
+            Self::If {
+                if_keyword,
+                condition,
+                then_keyword,
+                then,
+                else_keyword,
+                else_,
+            } => {
+                if_keyword.fmt(f)?;
+                condition.fmt(f)?;
+                then_keyword.fmt(f)?;
+                then.fmt(f)?;
+                else_keyword.fmt(f)?;
+                else_.fmt(f)
+            }
             Self::Function {
                 opening_curly_brace,
                 parameters_and_arrow,
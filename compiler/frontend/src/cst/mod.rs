@@ -1,6 +1,10 @@
 use self::tree_with_ids::TreeWithIds;
 pub use self::{
-    error::CstError, id::Id, is_multiline::IsMultiline, kind::CstKind, kind::IntRadix,
+    error::CstError,
+    id::Id,
+    is_multiline::IsMultiline,
+    kind::{Associativity, BinaryOperator, CstKind, IntRadix},
+    path::{CstPath, FindCstPath},
     unwrap_whitespace_and_comment::UnwrapWhitespaceAndComment,
 };
 use crate::{module::Module, position::Offset, rcst_to_cst::RcstToCst};
@@ -14,6 +18,7 @@ mod error;
 mod id;
 mod is_multiline;
 mod kind;
+mod path;
 mod tree_with_ids;
 mod unwrap_whitespace_and_comment;
 
@@ -0,0 +1,157 @@
+use super::{Cst, CstKind, Id};
+use crate::position::Offset;
+use std::ops::Range;
+
+/// A CST node together with its ancestors.
+///
+/// CST nodes only know their children. A path is found by walking down from
+/// the top-level nodes of a module and records the parent links on the way,
+/// which queries like [`CstPath::parent`] and [`CstPath::preceding_siblings`]
+/// need.
+#[derive(Clone, Debug)]
+pub struct CstPath<'a> {
+    roots: &'a [Cst],
+    /// From the top-level node to the node itself.
+    nodes: Vec<&'a Cst>,
+}
+impl<'a> CstPath<'a> {
+    /// The innermost node or `None` if no node matched.
+    #[must_use]
+    pub fn node(&self) -> Option<&'a Cst> {
+        self.nodes.last().copied()
+    }
+    /// The node and its ancestors, starting with the top-level node.
+    #[must_use]
+    pub fn nodes(&self) -> &[&'a Cst] {
+        &self.nodes
+    }
+
+    #[must_use]
+    pub fn parent(&self) -> Option<&'a Cst> {
+        self.ancestors().next()
+    }
+    /// The ancestors of the node, starting with its parent.
+    #[must_use]
+    pub fn ancestors(&self) -> impl DoubleEndedIterator<Item = &'a Cst> + '_ {
+        self.nodes.iter().rev().skip(1).copied()
+    }
+
+    /// The siblings before the node, starting with the closest one.
+    #[must_use]
+    pub fn preceding_siblings(&self) -> Vec<&'a Cst> {
+        self.siblings_and_index()
+            .map_or_else(Vec::new, |(siblings, index)| {
+                siblings[..index].iter().rev().copied().collect()
+            })
+    }
+    /// The siblings after the node, starting with the closest one.
+    #[must_use]
+    pub fn following_siblings(&self) -> Vec<&'a Cst> {
+        self.siblings_and_index()
+            .map_or_else(Vec::new, |(siblings, index)| siblings[index + 1..].to_vec())
+    }
+    fn siblings_and_index(&self) -> Option<(Vec<&'a Cst>, usize)> {
+        let node = self.node()?;
+        let siblings = self.parent().map_or_else(
+            || self.roots.iter().collect(),
+            |parent| parent.kind.children(),
+        );
+        let index = siblings
+            .iter()
+            .position(|it| it.data.id == node.data.id)
+            .unwrap();
+        Some((siblings, index))
+    }
+
+    /// The innermost function containing the node: either a function literal
+    /// like `{ a -> … }` or an assignment defining a function like `foo a = …`.
+    #[must_use]
+    pub fn containing_function(&self) -> Option<&'a Cst> {
+        self.ancestors().find(|it| match &it.kind {
+            CstKind::Function { .. } => true,
+            CstKind::Assignment { left, .. } => {
+                matches!(unwrap_trailing_whitespace(left).kind, CstKind::Call { .. })
+            }
+            _ => false,
+        })
+    }
+}
+
+fn unwrap_trailing_whitespace(cst: &Cst) -> &Cst {
+    match &cst.kind {
+        CstKind::TrailingWhitespace { child, .. } => unwrap_trailing_whitespace(child),
+        _ => cst,
+    }
+}
+
+pub trait FindCstPath {
+    /// The path to the node with the given `id`.
+    fn find_path(&self, id: Id) -> CstPath<'_>;
+
+    /// The path to the innermost node at the `offset`.
+    ///
+    /// If the offset lies between two nodes, the one starting there is
+    /// preferred.
+    fn find_path_at_offset(&self, offset: Offset) -> CstPath<'_>;
+    /// The path to the innermost node that contains the code directly in
+    /// front of the `offset`, e.g., the code before the cursor.
+    fn find_path_before_offset(&self, offset: Offset) -> CstPath<'_>;
+    /// The path to the innermost node that contains the whole `range`.
+    fn find_path_enclosing(&self, range: &Range<Offset>) -> CstPath<'_>;
+}
+impl FindCstPath for [Cst] {
+    fn find_path(&self, id: Id) -> CstPath<'_> {
+        // IDs are assigned in pre-order, so the node with the ID is in the
+        // subtree of the last child whose ID isn't greater.
+        let path = find_path(self, |children| {
+            children.iter().rev().find(|it| it.data.id <= id).copied()
+        });
+        if path.node().is_some_and(|it| it.data.id == id) {
+            path
+        } else {
+            CstPath {
+                roots: self,
+                nodes: vec![],
+            }
+        }
+    }
+
+    fn find_path_at_offset(&self, offset: Offset) -> CstPath<'_> {
+        find_path(self, |children| {
+            children
+                .iter()
+                .find(|it| it.data.span.start <= offset && offset < it.data.span.end)
+                .or_else(|| children.iter().find(|it| it.data.span.end == offset))
+                .copied()
+        })
+    }
+    fn find_path_before_offset(&self, offset: Offset) -> CstPath<'_> {
+        find_path(self, |children| {
+            children
+                .iter()
+                .find(|it| it.data.span.start < offset && offset <= it.data.span.end)
+                .copied()
+        })
+    }
+    fn find_path_enclosing(&self, range: &Range<Offset>) -> CstPath<'_> {
+        find_path(self, |children| {
+            children
+                .iter()
+                .find(|it| it.data.span.start <= range.start && range.end <= it.data.span.end)
+                .copied()
+        })
+    }
+}
+
+fn find_path<'a>(
+    roots: &'a [Cst],
+    mut select_child: impl FnMut(&[&'a Cst]) -> Option<&'a Cst>,
+) -> CstPath<'a> {
+    let mut nodes = vec![];
+    let mut children = roots.iter().collect::<Vec<_>>();
+    while let Some(child) = select_child(&children) {
+        nodes.push(child);
+        children = child.kind.children();
+    }
+    CstPath { roots, nodes }
+}
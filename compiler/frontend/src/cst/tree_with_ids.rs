@@ -35,6 +35,10 @@ impl TreeWithIds for Cst {
             | CstKind::DoubleQuote
             | CstKind::Percent
             | CstKind::Octothorpe
+            | CstKind::IfKeyword
+            | CstKind::ThenKeyword
+            | CstKind::ElseKeyword
+            | CstKind::BinaryOperator(_)
             | CstKind::Whitespace(_)
             | CstKind::Newline(_) => None,
             CstKind::Comment {
@@ -47,9 +51,14 @@ impl TreeWithIds for Cst {
             CstKind::Identifier(_)
             | CstKind::Symbol(_)
             | CstKind::Int {
+                is_negative: _,
                 radix_prefix: _,
                 value: _,
                 string: _,
+            }
+            | CstKind::Float {
+                value: _,
+                string: _,
             } => None,
             CstKind::OpeningText {
                 opening_single_quotes,
@@ -71,7 +80,7 @@ impl TreeWithIds for Cst {
                 .find(id)
                 .or_else(|| parts.find(id))
                 .or_else(|| closing.find(id)),
-            CstKind::TextNewline(_) | CstKind::TextPart(_) => None,
+            CstKind::TextNewline(_) | CstKind::TextPart(_) | CstKind::TextEscape { .. } => None,
             CstKind::TextInterpolation {
                 opening_curly_braces,
                 expression,
@@ -84,6 +93,14 @@ impl TreeWithIds for Cst {
                 .find(id)
                 .or_else(|| bar.find(id))
                 .or_else(|| right.find(id)),
+            CstKind::BinaryOperation {
+                left,
+                operator,
+                right,
+            } => left
+                .find(id)
+                .or_else(|| operator.find(id))
+                .or_else(|| right.find(id)),
             CstKind::Parenthesized {
                 opening_parenthesis,
                 inner,
@@ -144,6 +161,20 @@ impl TreeWithIds for Cst {
                 .find(id)
                 .or_else(|| arrow.find(id))
                 .or_else(|| body.find(id)),
+            CstKind::If {
+                if_keyword,
+                condition,
+                then_keyword,
+                then,
+                else_keyword,
+                else_,
+            } => if_keyword
+                .find(id)
+                .or_else(|| condition.find(id))
+                .or_else(|| then_keyword.find(id))
+                .or_else(|| then.find(id))
+                .or_else(|| else_keyword.find(id))
+                .or_else(|| else_.find(id)),
             CstKind::Function {
                 opening_curly_brace,
                 parameters_and_arrow,
@@ -194,6 +225,10 @@ impl TreeWithIds for Cst {
             | CstKind::DoubleQuote
             | CstKind::Percent
             | CstKind::Octothorpe
+            | CstKind::IfKeyword
+            | CstKind::ThenKeyword
+            | CstKind::ElseKeyword
+            | CstKind::BinaryOperator(_)
             | CstKind::Whitespace(_)
             | CstKind::Newline(_) => (None, false),
             CstKind::Comment {
@@ -207,9 +242,14 @@ impl TreeWithIds for Cst {
             CstKind::Identifier(_)
             | CstKind::Symbol(_)
             | CstKind::Int {
+                is_negative: _,
                 radix_prefix: _,
                 value: _,
                 string: _,
+            }
+            | CstKind::Float {
+                value: _,
+                string: _,
             } => (None, true),
             CstKind::Text {
                 opening: _,
@@ -240,7 +280,8 @@ impl TreeWithIds for Cst {
                 closing_single_quotes: _,
             }
             | CstKind::TextNewline(_)
-            | CstKind::TextPart(_) => (None, false),
+            | CstKind::TextPart(_)
+            | CstKind::TextEscape { .. } => (None, false),
             CstKind::TextInterpolation {
                 opening_curly_braces: _,
                 expression,
@@ -252,6 +293,16 @@ impl TreeWithIds for Cst {
                     .or_else(|| right.find_by_offset(offset)),
                 false,
             ),
+            CstKind::BinaryOperation {
+                left,
+                operator,
+                right,
+            } => (
+                left.find_by_offset(offset)
+                    .or_else(|| operator.find_by_offset(offset))
+                    .or_else(|| right.find_by_offset(offset)),
+                false,
+            ),
             CstKind::Parenthesized {
                 opening_parenthesis: _,
                 inner,
@@ -338,6 +389,23 @@ impl TreeWithIds for Cst {
                     .or_else(|| body.find_by_offset(offset)),
                 false,
             ),
+            CstKind::If {
+                if_keyword,
+                condition,
+                then_keyword,
+                then,
+                else_keyword,
+                else_,
+            } => (
+                if_keyword
+                    .find_by_offset(offset)
+                    .or_else(|| condition.find_by_offset(offset))
+                    .or_else(|| then_keyword.find_by_offset(offset))
+                    .or_else(|| then.find_by_offset(offset))
+                    .or_else(|| else_keyword.find_by_offset(offset))
+                    .or_else(|| else_.find_by_offset(offset)),
+                false,
+            ),
             CstKind::Function {
                 opening_curly_brace,
                 parameters_and_arrow,
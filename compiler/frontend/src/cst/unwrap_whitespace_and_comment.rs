@@ -25,15 +25,20 @@ impl<D: Clone> UnwrapWhitespaceAndComment for Cst<D> {
             | CstKind::DoubleQuote
             | CstKind::Percent
             | CstKind::Octothorpe
+            | CstKind::IfKeyword
+            | CstKind::ThenKeyword
+            | CstKind::ElseKeyword
+            | CstKind::BinaryOperator(_)
             | CstKind::Whitespace(_)
             | CstKind::Newline(_)
             | CstKind::Comment { .. }) => kind.clone(),
             CstKind::TrailingWhitespace { box child, .. } => {
                 return child.unwrap_whitespace_and_comment()
             }
-            kind @ (CstKind::Identifier(_) | CstKind::Symbol(_) | CstKind::Int { .. }) => {
-                kind.clone()
-            }
+            kind @ (CstKind::Identifier(_)
+            | CstKind::Symbol(_)
+            | CstKind::Int { .. }
+            | CstKind::Float { .. }) => kind.clone(),
             CstKind::OpeningText {
                 opening_single_quotes,
                 opening_double_quote,
@@ -57,7 +62,10 @@ impl<D: Clone> UnwrapWhitespaceAndComment for Cst<D> {
                 parts: parts.unwrap_whitespace_and_comment(),
                 closing: closing.unwrap_whitespace_and_comment(),
             },
-            kind @ (CstKind::TextNewline(_) | CstKind::TextPart(_)) => kind.clone(),
+            kind
+            @ (CstKind::TextNewline(_) | CstKind::TextPart(_) | CstKind::TextEscape { .. }) => {
+                kind.clone()
+            }
             CstKind::TextInterpolation {
                 opening_curly_braces,
                 expression,
@@ -72,6 +80,15 @@ impl<D: Clone> UnwrapWhitespaceAndComment for Cst<D> {
                 bar: bar.unwrap_whitespace_and_comment(),
                 right: right.unwrap_whitespace_and_comment(),
             },
+            CstKind::BinaryOperation {
+                left,
+                operator,
+                right,
+            } => CstKind::BinaryOperation {
+                left: left.unwrap_whitespace_and_comment(),
+                operator: operator.unwrap_whitespace_and_comment(),
+                right: right.unwrap_whitespace_and_comment(),
+            },
             CstKind::Parenthesized {
                 opening_parenthesis,
                 inner,
@@ -151,6 +168,21 @@ impl<D: Clone> UnwrapWhitespaceAndComment for Cst<D> {
                 arrow: arrow.unwrap_whitespace_and_comment(),
                 body: body.unwrap_whitespace_and_comment(),
             },
+            CstKind::If {
+                if_keyword,
+                condition,
+                then_keyword,
+                then,
+                else_keyword,
+                else_,
+            } => CstKind::If {
+                if_keyword: if_keyword.unwrap_whitespace_and_comment(),
+                condition: condition.unwrap_whitespace_and_comment(),
+                then_keyword: then_keyword.unwrap_whitespace_and_comment(),
+                then: then.unwrap_whitespace_and_comment(),
+                else_keyword: else_keyword.unwrap_whitespace_and_comment(),
+                else_: else_.unwrap_whitespace_and_comment(),
+            },
             CstKind::Function {
                 opening_curly_brace,
                 parameters_and_arrow,
@@ -4,15 +4,16 @@ use rustc_hash::{FxHashMap, FxHashSet};
 use crate::{
     ast::{
         self, Assignment, AssignmentBody, Ast, AstError, AstKind, AstString, Call, CollectErrors,
-        Function, Identifier, Int, List, Match, MatchCase, OrPattern, Struct, StructAccess, Symbol,
-        Text, TextPart,
+        Float, Function, Identifier, Int, List, Match, MatchCase, OrPattern, Struct, StructAccess,
+        Symbol, Text, TextPart,
     },
-    cst::{self, Cst, CstDb, CstKind, UnwrapWhitespaceAndComment},
+    cst::{self, Associativity, BinaryOperator, Cst, CstDb, CstKind, UnwrapWhitespaceAndComment},
     error::{CompilerError, CompilerErrorPayload},
     module::Module,
     position::Offset,
     rcst_to_cst::RcstToCst,
     string_to_rcst::ModuleError,
+    timings::{self, Stage},
     utils::AdjustCasingOfFirstLetter,
 };
 use std::{collections::HashMap, ops::Range, sync::Arc};
@@ -29,11 +30,21 @@ pub trait CstToAst: CstDb + RcstToCst {
     #[salsa::transparent]
     fn cst_to_ast_ids(&self, module: Module, id: cst::Id) -> Vec<ast::Id>;
 
+    #[salsa::transparent]
+    fn module_documentation(&self, module: Module) -> Option<String>;
+
     fn ast(&self, module: Module) -> AstResult;
 }
 
 pub type AstResult = Result<(Arc<Vec<Ast>>, Arc<FxHashMap<ast::Id, cst::Id>>), ModuleError>;
 
+/// The identifier through which syntactic sugar calls builtins, e.g., `if`
+/// calls `ifElse`. It can't be written in code, so code can't shadow it.
+///
+/// In the Builtins package, it refers to `✨`. Elsewhere, it refers to the
+/// Builtins package so that the preconditions of its functions are checked.
+pub const SUGAR_BUILTINS_IDENTIFIER: &str = "#builtins";
+
 fn ast_to_cst_id(db: &dyn CstToAst, id: &ast::Id) -> Option<cst::Id> {
     let (_, ast_to_cst_id_mapping) = db.ast(id.module.clone()).ok()?;
     ast_to_cst_id_mapping.get(id).copied()
@@ -60,14 +71,86 @@ fn cst_to_ast_ids(db: &dyn CstToAst, module: Module, id: cst::Id) -> Vec<ast::Id
     }
 }
 
+/// A module's documentation consists of the comment lines at its beginning,
+/// unless they are directly above the first assignment and document that
+/// instead.
+fn module_documentation(db: &dyn CstToAst, module: Module) -> Option<String> {
+    let csts = db.cst(module).ok()?;
+    let mut comment_lines = vec![];
+    let mut newlines_since_comment = 0;
+    let mut is_followed_by_code = false;
+    for cst in csts.iter() {
+        match &cst.kind {
+            CstKind::Whitespace(_) => {}
+            CstKind::Newline(_) => newlines_since_comment += 1,
+            CstKind::Comment { comment, .. } if newlines_since_comment <= 1 => {
+                comment_lines.push(documentation_line(comment));
+                newlines_since_comment = 0;
+            }
+            _ => {
+                is_followed_by_code = true;
+                break;
+            }
+        }
+    }
+    (!comment_lines.is_empty() && (!is_followed_by_code || newlines_since_comment > 1))
+        .then(|| comment_lines.join("\n"))
+}
+
 fn ast(db: &dyn CstToAst, module: Module) -> AstResult {
-    let mut context = LoweringContext::new(module.clone());
+    let cst = db.cst(module.clone())?;
+    Ok(timings::record_stage(Stage::Ast, module.clone(), || {
+        let mut documentation = FxHashMap::default();
+        collect_documentation(cst.iter(), &mut documentation);
+        let mut context = LoweringContext::new(module, documentation);
 
-    db.cst(module).map(|cst| {
         let cst = cst.unwrap_whitespace_and_comment();
         let asts = context.lower_csts(&cst);
         (Arc::new(asts), Arc::new(context.id_mapping))
-    })
+    }))
+}
+
+/// Comments get removed before lowering, so we collect them beforehand.
+///
+/// An assignment's documentation consists of the comment lines directly above
+/// it. Doc comments can start with `##` to make them stand out, but a single
+/// `#` works as well.
+fn collect_documentation<'a>(
+    csts: impl IntoIterator<Item = &'a Cst>,
+    documentation: &mut FxHashMap<cst::Id, String>,
+) {
+    let mut comment_lines = vec![];
+    let mut newlines_since_comment = 0;
+    for cst in csts {
+        match &cst.kind {
+            CstKind::Whitespace(_) => {}
+            CstKind::Newline(_) => newlines_since_comment += 1,
+            CstKind::Comment { comment, .. } => {
+                // An empty line separates the comment from previous ones.
+                if newlines_since_comment > 1 {
+                    comment_lines.clear();
+                }
+                comment_lines.push(documentation_line(comment));
+                newlines_since_comment = 0;
+            }
+            kind => {
+                if matches!(kind, CstKind::Assignment { .. })
+                    && !comment_lines.is_empty()
+                    && newlines_since_comment == 1
+                {
+                    documentation.insert(cst.data.id, comment_lines.join("\n"));
+                }
+                comment_lines.clear();
+                collect_documentation(kind.children(), documentation);
+            }
+        }
+    }
+}
+
+/// Removes the second `#` of doc comments and the space after it.
+fn documentation_line(comment: &str) -> &str {
+    let line = comment.strip_prefix('#').unwrap_or(comment);
+    line.strip_prefix(' ').unwrap_or(line)
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
@@ -81,13 +164,15 @@ struct LoweringContext {
     module: Module,
     next_id: usize,
     id_mapping: FxHashMap<ast::Id, cst::Id>,
+    documentation: FxHashMap<cst::Id, String>,
 }
 impl LoweringContext {
-    fn new(module: Module) -> Self {
+    fn new(module: Module, documentation: FxHashMap<cst::Id, String>) -> Self {
         Self {
             module,
             next_id: 0,
             id_mapping: FxHashMap::default(),
+            documentation,
         }
     }
     fn lower_csts(&mut self, csts: &[Cst]) -> Vec<Ast> {
@@ -113,7 +198,11 @@ impl LoweringContext {
             | CstKind::SingleQuote
             | CstKind::DoubleQuote
             | CstKind::Percent
-            | CstKind::Octothorpe => self.create_error_ast(
+            | CstKind::Octothorpe
+            | CstKind::IfKeyword
+            | CstKind::ThenKeyword
+            | CstKind::ElseKeyword
+            | CstKind::BinaryOperator(_) => self.create_error_ast(
                 cst,
                 vec![self.create_error(cst, AstError::UnexpectedPunctuation)],
             ),
@@ -135,6 +224,7 @@ impl LoweringContext {
                 self.create_ast(cst.data.id, Symbol(string))
             }
             CstKind::Int { value, .. } => self.create_ast(cst.data.id, Int(value.clone())),
+            CstKind::Float { value, .. } => self.create_ast(cst.data.id, Float(*value)),
             CstKind::Text {
                 opening,
                 parts,
@@ -165,6 +255,14 @@ impl LoweringContext {
                             let string = self.create_string(part.data.id, text.clone());
                             Some(self.create_ast(part.data.id, AstKind::TextPart(TextPart(string))))
                         },
+                        CstKind::TextEscape { value, .. } => {
+                            let string = self.create_string(part.data.id, value.to_string());
+                            Some(self.create_ast(part.data.id, AstKind::TextPart(TextPart(string))))
+                        },
+                        CstKind::Error { error, .. } => {
+                            errors.push(self.create_error(part, *error));
+                            None
+                        },
                         CstKind::TextInterpolation {
                             opening_curly_braces,
                             expression,
@@ -227,6 +325,7 @@ impl LoweringContext {
             CstKind::ClosingText { .. } => panic!("ClosingText should only occur in Text."),
             CstKind::TextNewline(_) => panic!("TextNewline should only occur in Text."),
             CstKind::TextPart(_) => panic!("TextPart should only occur in Text."),
+            CstKind::TextEscape { .. } => panic!("TextEscape should only occur in Text."),
             CstKind::TextInterpolation { .. } => {
                 panic!("TextInterpolation should only occur in Text.")
             }
@@ -326,6 +425,36 @@ impl LoweringContext {
                     }
                 }
             }
+            CstKind::BinaryOperation {
+                left,
+                operator,
+                right,
+            } => {
+                if lowering_type != LoweringType::Expression {
+                    return self.create_ast_for_invalid_expression_in_pattern(cst);
+                };
+
+                let CstKind::BinaryOperator(operator) = operator.kind else {
+                    panic!("Expected an operator in a binary operation, but found {operator}.");
+                };
+
+                let mut errors = vec![];
+                if operator.associativity() == Associativity::None
+                    && let CstKind::BinaryOperation {
+                        operator: left_operator,
+                        ..
+                    } = &left.kind
+                    && let CstKind::BinaryOperator(left_operator) = left_operator.kind
+                    && left_operator.precedence() == operator.precedence()
+                {
+                    errors.push(self.create_error(cst, AstError::BinaryOperatorIsNotAssociative));
+                }
+
+                let left = self.lower_cst(left, LoweringType::Expression);
+                let right = self.lower_cst(right, LoweringType::Expression);
+                let ast = self.lower_binary_operation(cst.data.id, operator, left, right);
+                self.create_errors_or_use_ast(cst, errors, ast)
+            }
             CstKind::Parenthesized {
                 opening_parenthesis,
                 inner,
@@ -389,6 +518,10 @@ impl LoweringContext {
                     };
                 }
                 let receiver = self.lower_cst(receiver, LoweringType::Expression);
+                if lowering_type == LoweringType::Expression && arguments.iter().any(is_placeholder)
+                {
+                    return self.lower_partial_application(cst, receiver, arguments);
+                }
                 let arguments = self.lower_csts(arguments);
 
                 self.create_ast(
@@ -582,6 +715,56 @@ impl LoweringContext {
                     },
                 )
             }
+            CstKind::If {
+                if_keyword,
+                condition,
+                then_keyword,
+                then,
+                else_keyword,
+                else_,
+            } => {
+                if lowering_type != LoweringType::Expression {
+                    return self.create_ast_for_invalid_expression_in_pattern(cst);
+                };
+
+                assert!(
+                    if_keyword.kind.is_if_keyword(),
+                    "Expected an `if` at the beginning of an if expression, but found {if_keyword}.",
+                );
+                assert!(
+                    then_keyword.kind.is_then_keyword(),
+                    "Expected a `then` after the condition, but found {then_keyword}.",
+                );
+
+                // `if condition then a else b` is syntactic sugar for
+                // `builtins.ifElse condition { a } { b }`.
+                let condition = self.lower_cst(condition, LoweringType::Expression);
+                let then = self.lower_cst(then, LoweringType::Expression);
+                let else_ = if else_keyword.kind.is_else_keyword() {
+                    self.lower_cst(else_, LoweringType::Expression)
+                } else {
+                    // The missing `else` is the only error worth reporting.
+                    self.lower_cst(else_keyword, LoweringType::Expression)
+                };
+
+                let then = self.create_ast(
+                    then_keyword.data.id,
+                    Function {
+                        parameters: vec![],
+                        body: vec![then],
+                        fuzzable: false,
+                    },
+                );
+                let else_ = self.create_ast(
+                    else_keyword.data.id,
+                    Function {
+                        parameters: vec![],
+                        body: vec![else_],
+                        fuzzable: false,
+                    },
+                );
+                self.create_builtin_call(cst.data.id, "IfElse", vec![condition, then, else_])
+            }
             CstKind::MatchCase {
                 pattern,
                 arrow: _,
@@ -706,10 +889,12 @@ impl LoweringContext {
                     }
                 };
 
+                let documentation = self.documentation.remove(&cst.data.id);
                 self.create_ast(
                     cst.data.id,
                     Assignment {
                         is_public: assignment_sign.kind.is_colon_equals_sign(),
+                        documentation,
                         body,
                     },
                 )
@@ -748,6 +933,199 @@ impl LoweringContext {
         }
     }
 
+    /// Operators are syntactic sugar for calls of builtins, e.g., `a == b` for
+    /// `builtins.equals a b`.
+    fn lower_binary_operation(
+        &mut self,
+        id: cst::Id,
+        operator: BinaryOperator,
+        left: Ast,
+        right: Ast,
+    ) -> Ast {
+        match operator {
+            BinaryOperator::Equal => self.create_builtin_call(id, "Equals", vec![left, right]),
+            BinaryOperator::NotEqual => {
+                let equals = self.create_builtin_call(id, "Equals", vec![left, right]);
+                self.create_negation(id, equals)
+            }
+            BinaryOperator::Less | BinaryOperator::GreaterOrEqual => {
+                let ordering = self.create_builtin_call(id, "CompareTo", vec![left, right]);
+                let less = self.create_string(id, "Less".to_string());
+                let less = self.create_ast(id, Symbol(less));
+                let is_less = self.create_builtin_call(id, "Equals", vec![ordering, less]);
+                if operator == BinaryOperator::Less {
+                    is_less
+                } else {
+                    self.create_negation(id, is_less)
+                }
+            }
+            BinaryOperator::Greater | BinaryOperator::LessOrEqual => {
+                let ordering = self.create_builtin_call(id, "CompareTo", vec![left, right]);
+                let greater = self.create_string(id, "Greater".to_string());
+                let greater = self.create_ast(id, Symbol(greater));
+                let is_greater = self.create_builtin_call(id, "Equals", vec![ordering, greater]);
+                if operator == BinaryOperator::Greater {
+                    is_greater
+                } else {
+                    self.create_negation(id, is_greater)
+                }
+            }
+            BinaryOperator::Add => {
+                self.create_arithmetic_call(id, "IntAdd", "FloatAdd", left, right)
+            }
+            BinaryOperator::Subtract => {
+                self.create_arithmetic_call(id, "IntSubtract", "FloatSubtract", left, right)
+            }
+            BinaryOperator::Multiply => {
+                self.create_arithmetic_call(id, "IntMultiply", "FloatMultiply", left, right)
+            }
+            BinaryOperator::Divide => {
+                self.create_arithmetic_call(id, "IntDivideTruncating", "FloatDivide", left, right)
+            }
+        }
+    }
+    /// Arithmetic operators work on ints and floats, so they dispatch on the
+    /// type of the left operand:
+    ///
+    /// ```candy
+    /// { _#left _#right ->
+    ///   builtins.ifElse (builtins.equals (builtins.typeOf _#left) Float)
+    ///     { builtins.floatAdd _#left _#right }
+    ///     { builtins.intAdd _#left _#right }
+    /// } left right
+    /// ```
+    ///
+    /// The builtin then panics if the right operand has a different type.
+    fn create_arithmetic_call(
+        &mut self,
+        id: cst::Id,
+        int_builtin: &str,
+        float_builtin: &str,
+        left: Ast,
+        right: Ast,
+    ) -> Ast {
+        // Names containing `#` can't clash with identifiers in the code.
+        let create_identifier = |this: &mut Self, name: &str| {
+            let name = this.create_string(id, name.to_string());
+            this.create_ast(id, Identifier(name))
+        };
+        let parameters = vec![
+            create_identifier(self, "_#left"),
+            create_identifier(self, "_#right"),
+        ];
+
+        let left_reference = create_identifier(self, "_#left");
+        let type_ = self.create_builtin_call(id, "TypeOf", vec![left_reference]);
+        let float = self.create_string(id, "Float".to_string());
+        let float = self.create_ast(id, Symbol(float));
+        let is_float = self.create_builtin_call(id, "Equals", vec![type_, float]);
+
+        let create_branch = |this: &mut Self, builtin: &str| {
+            let arguments = vec![
+                create_identifier(this, "_#left"),
+                create_identifier(this, "_#right"),
+            ];
+            let call = this.create_builtin_call(id, builtin, arguments);
+            this.create_ast(
+                id,
+                Function {
+                    parameters: vec![],
+                    body: vec![call],
+                    fuzzable: false,
+                },
+            )
+        };
+        let float_branch = create_branch(self, float_builtin);
+        let int_branch = create_branch(self, int_builtin);
+        let dispatch =
+            self.create_builtin_call(id, "IfElse", vec![is_float, float_branch, int_branch]);
+
+        let function = self.create_ast(
+            id,
+            Function {
+                parameters,
+                body: vec![dispatch],
+                fuzzable: false,
+            },
+        );
+        self.create_ast(
+            id,
+            Call {
+                receiver: Box::new(function),
+                arguments: vec![left, right],
+                is_from_pipe: false,
+            },
+        )
+    }
+    fn create_negation(&mut self, id: cst::Id, value: Ast) -> Ast {
+        let false_ = self.create_string(id, "False".to_string());
+        let false_ = self.create_ast(id, Symbol(false_));
+        self.create_builtin_call(id, "Equals", vec![value, false_])
+    }
+    /// Creates a call of `builtins.<builtin>` for syntactic sugar.
+    fn create_builtin_call(&mut self, id: cst::Id, builtin: &str, arguments: Vec<Ast>) -> Ast {
+        let builtins = self.create_string(id, SUGAR_BUILTINS_IDENTIFIER.to_string());
+        let builtins = self.create_ast(id, Identifier(builtins));
+        let builtin = self.create_string(id, builtin.to_string());
+        let receiver = self.create_ast(
+            id,
+            StructAccess {
+                struct_: Box::new(builtins),
+                key: builtin,
+            },
+        );
+        self.create_ast(
+            id,
+            Call {
+                receiver: Box::new(receiver),
+                arguments,
+                is_from_pipe: false,
+            },
+        )
+    }
+
+    /// Lowers a call with `_` placeholders as arguments, e.g., `add 1 _`, to a
+    /// function taking one parameter per placeholder: `{ a -> add 1 a }`.
+    ///
+    /// Both the parameter and its reference point to the placeholder, so
+    /// tooling treats it like a parameter used in place.
+    fn lower_partial_application(&mut self, cst: &Cst, receiver: Ast, arguments: &[Cst]) -> Ast {
+        let mut parameters = vec![];
+        let mut lowered_arguments = vec![];
+        for argument in arguments {
+            if !is_placeholder(argument) {
+                lowered_arguments.push(self.lower_cst(argument, LoweringType::Expression));
+                continue;
+            }
+
+            // Names containing `#` can't clash with identifiers in the code.
+            // The leading underscore keeps lints from reporting nested
+            // partial applications as shadowing.
+            let name = format!("_#{}", parameters.len());
+            let parameter = self.create_string(argument.data.id, name.clone());
+            parameters.push(self.create_ast(argument.data.id, Identifier(parameter)));
+            let reference = self.create_string(argument.data.id, name);
+            lowered_arguments.push(self.create_ast(argument.data.id, Identifier(reference)));
+        }
+
+        let call = self.create_ast(
+            cst.data.id,
+            Call {
+                receiver: Box::new(receiver),
+                arguments: lowered_arguments,
+                is_from_pipe: false,
+            },
+        );
+        self.create_ast(
+            cst.data.id,
+            Function {
+                parameters,
+                body: vec![call],
+                fuzzable: false,
+            },
+        )
+    }
+
     fn lower_parameters(&mut self, csts: &[Cst], is_fuzzable: bool) -> Vec<Ast> {
         csts.iter()
             .enumerate()
@@ -835,3 +1213,9 @@ impl LoweringContext {
         }
     }
 }
+
+/// Whether the argument is an `_` that turns its call into a partial
+/// application.
+fn is_placeholder(argument: &Cst) -> bool {
+    matches!(&argument.kind, CstKind::Identifier(name) if name == "_")
+}
@@ -51,10 +51,18 @@ impl Display for CompilerErrorPayload {
             },
             Self::Cst(error) => match error {
                 CstError::BinaryBarMissesRight => "There should be a right side after this bar.",
+                CstError::BinaryOperationMissesRight => {
+                    "There should be a right side after this operator."
+                }
                 CstError::CurlyBraceNotClosed => "The curly brace is not closed.",
+                CstError::FloatContainsNonDigits => {
+                    "This float contains characters that are not digits."
+                }
                 CstError::IdentifierContainsNonAlphanumericAscii => {
                     "This identifier contains non-alphanumeric ASCII characters."
                 }
+                CstError::IfMissesBody => "This `then` or `else` misses an expression.",
+                CstError::IfMissesElse => "This `if` misses an `else`.",
                 CstError::IntContainsNonDigits => {
                     "This integer contains characters that are not digits."
                 }
@@ -68,6 +76,7 @@ impl Display for CompilerErrorPayload {
                 }
                 CstError::OrPatternMissesRight => "This or-pattern misses a right-hand side.",
                 CstError::ParenthesisNotClosed => "This parenthesis isn't closed.",
+                CstError::StructAccessMissesKey => "This struct access misses a key.",
                 CstError::StructFieldMissesColon => "This struct field misses a colon.",
                 CstError::StructFieldMissesKey => "This struct field misses a key.",
                 CstError::StructFieldMissesValue => "This struct field misses a value.",
@@ -75,12 +84,18 @@ impl Display for CompilerErrorPayload {
                 CstError::SymbolContainsNonAlphanumericAscii => {
                     "This symbol contains non-alphanumeric ASCII characters."
                 }
+                CstError::TextEscapeIsUnknown => {
+                    "This escape sequence is unknown. Supported ones are `\\\"`, `\\\\`, `\\n`, `\\t`, and `\\u{…}`."
+                }
                 CstError::TextNotClosed => "This text isn't closed.",
                 CstError::TextNotSufficientlyIndented => "This text isn't sufficiently indented.",
                 CstError::TextInterpolationNotClosed => "This text interpolation isn't closed.",
                 CstError::TextInterpolationMissesExpression => {
                     "Here's a start of a text interpolation without an expression after it."
                 }
+                CstError::TextUnicodeEscapeIsInvalid => {
+                    "This unicode escape sequence is invalid. It needs one to six hexadecimal digits in curly braces that form a valid code point, like `\\u{1F600}`."
+                }
                 CstError::TooMuchWhitespace => "There is too much whitespace here.",
                 CstError::UnexpectedCharacters => "This is an unexpected character.",
                 CstError::UnparsedRest => "The parser couldn't parse this rest.",
@@ -91,6 +106,9 @@ impl Display for CompilerErrorPayload {
             }
             .to_string(),
             Self::Ast(error) => match error {
+                AstError::BinaryOperatorIsNotAssociative => {
+                    "This operator can't be chained with the one before it. Add parentheses to clarify the order.".to_string()
+                }
                 AstError::ExpectedNameOrPatternInAssignment => {
                     "An assignment should have a name or pattern on the left side.".to_string()
                 }
@@ -151,8 +169,20 @@ impl Display for CompilerErrorPayload {
                 AstError::UnexpectedPunctuation => "This punctuation was unexpected.".to_string(),
             },
             Self::Hir(error) => match error {
+                HirError::CallWithWrongNumberOfArguments {
+                    function,
+                    expected,
+                    actual,
+                    ..
+                } => format!(
+                    "{} takes {expected} {}, but it's called with {actual}.",
+                    function
+                        .as_ref()
+                        .map_or_else(|| "This function".to_string(), |it| format!("`{it}`")),
+                    if *expected == 1 { "argument" } else { "arguments" },
+                ),
                 HirError::NeedsWithWrongNumberOfArguments { num_args } => {
-                    format!("`needs` accepts one or two arguments, but was called with {num_args} arguments. Its parameters are the `condition` and an optional `message`.")
+                    format!("`needs` accepts one to three arguments, but was called with {num_args} arguments. Its parameters are the `condition`, an optional `message`, and an optional `context` value that's shown if the condition isn't met.")
                 }
                 HirError::PatternContainsCall => "Calls in patterns are not allowed.".to_string(),
                 HirError::PublicAssignmentInNotTopLevel => {
@@ -205,6 +235,14 @@ impl CompilerError {
                     )
                 })
                 .collect(),
+            CompilerErrorPayload::Hir(HirError::CallWithWrongNumberOfArguments {
+                definition: Some(definition),
+                ..
+            }) => vec![(
+                self.module.clone(),
+                *definition,
+                "The function is defined here.".to_string(),
+            )],
             _ => vec![],
         }
     }
@@ -0,0 +1,1100 @@
+//! Stable codes for kinds of compiler errors (e.g., `E0053`) together with
+//! longer explanations that can be shown using `candy explain <code>`.
+//!
+//! Codes are never reused or reassigned, so new kinds of errors get a new code
+//! at the end.
+
+use crate::{
+    ast::AstError, cst::CstError, error::CompilerErrorPayload, hir::HirError, mir::MirError,
+    string_to_rcst::ModuleError,
+};
+
+impl CompilerErrorPayload {
+    #[must_use]
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::Module(error) => match error {
+                ModuleError::DoesNotExist => "E0001",
+                ModuleError::InvalidUtf8 => "E0002",
+                ModuleError::IsNotCandy => "E0003",
+                ModuleError::IsToolingModule => "E0004",
+            },
+            Self::Cst(error) => match error {
+                CstError::BinaryBarMissesRight => "E0005",
+                CstError::BinaryOperationMissesRight => "E0066",
+                CstError::CurlyBraceNotClosed => "E0006",
+                CstError::FloatContainsNonDigits => "E0007",
+                CstError::IdentifierContainsNonAlphanumericAscii => "E0008",
+                CstError::IfMissesBody => "E0064",
+                CstError::IfMissesElse => "E0065",
+                CstError::IntContainsNonDigits => "E0009",
+                CstError::ListItemMissesValue => "E0010",
+                CstError::ListNotClosed => "E0011",
+                CstError::MatchCaseMissesArrow => "E0012",
+                CstError::MatchCaseMissesBody => "E0013",
+                CstError::MatchMissesCases => "E0014",
+                CstError::OpeningParenthesisMissesExpression => "E0015",
+                CstError::OrPatternMissesRight => "E0016",
+                CstError::ParenthesisNotClosed => "E0017",
+                CstError::StructAccessMissesKey => "E0018",
+                CstError::StructFieldMissesColon => "E0019",
+                CstError::StructFieldMissesKey => "E0020",
+                CstError::StructFieldMissesValue => "E0021",
+                CstError::StructNotClosed => "E0022",
+                CstError::SymbolContainsNonAlphanumericAscii => "E0023",
+                CstError::TextEscapeIsUnknown => "E0068",
+                CstError::TextInterpolationMissesExpression => "E0024",
+                CstError::TextInterpolationNotClosed => "E0025",
+                CstError::TextNotClosed => "E0026",
+                CstError::TextNotSufficientlyIndented => "E0027",
+                CstError::TextUnicodeEscapeIsInvalid => "E0069",
+                CstError::TooMuchWhitespace => "E0028",
+                CstError::UnexpectedCharacters => "E0029",
+                CstError::UnparsedRest => "E0030",
+                CstError::WeirdWhitespace => "E0031",
+                CstError::WeirdWhitespaceInIndentation => "E0032",
+            },
+            Self::Ast(error) => match error {
+                AstError::BinaryOperatorIsNotAssociative => "E0067",
+                AstError::ExpectedNameOrPatternInAssignment => "E0033",
+                AstError::ExpectedParameter => "E0034",
+                AstError::FunctionMissesClosingCurlyBrace => "E0035",
+                AstError::ListItemMissesComma => "E0036",
+                AstError::ListMissesClosingParenthesis => "E0037",
+                AstError::ListWithNonListItem => "E0038",
+                AstError::OrPatternIsMissingIdentifiers { .. } => "E0039",
+                AstError::ParenthesizedInPattern => "E0040",
+                AstError::ParenthesizedMissesClosingParenthesis => "E0041",
+                AstError::PatternContainsInvalidExpression => "E0042",
+                AstError::PatternLiteralPartContainsInvalidExpression => "E0043",
+                AstError::PipeInPattern => "E0044",
+                AstError::StructKeyMissesColon => "E0045",
+                AstError::StructMissesClosingBrace => "E0046",
+                AstError::StructShorthandWithNotIdentifier => "E0047",
+                AstError::StructValueMissesComma => "E0048",
+                AstError::StructWithNonStructField => "E0049",
+                AstError::TextInterpolationMissesClosingCurlyBraces => "E0050",
+                AstError::TextMissesClosingQuote => "E0051",
+                AstError::UnexpectedPunctuation => "E0052",
+            },
+            Self::Hir(error) => match error {
+                HirError::CallWithWrongNumberOfArguments { .. } => "E0063",
+                HirError::NeedsWithWrongNumberOfArguments { .. } => "E0053",
+                HirError::PatternContainsCall => "E0054",
+                HirError::PublicAssignmentInNotTopLevel => "E0055",
+                HirError::PublicAssignmentWithSameName { .. } => "E0056",
+                HirError::UnknownReference { .. } => "E0057",
+            },
+            Self::Mir(error) => match error {
+                MirError::UseWithInvalidPath { .. } => "E0058",
+                MirError::UseHasTooManyParentNavigations { .. } => "E0059",
+                MirError::ModuleNotFound { .. } => "E0060",
+                MirError::UseNotStaticallyResolvable { .. } => "E0061",
+                MirError::ModuleHasCycle { .. } => "E0062",
+            },
+        }
+    }
+}
+
+/// Returns a longer description of the error with this code, including
+/// examples.
+#[must_use]
+pub fn explain(code: &str) -> Option<&'static str> {
+    let code = code.to_uppercase();
+    EXPLANATIONS
+        .iter()
+        .find(|(it, _)| *it == code)
+        .map(|(_, explanation)| explanation.trim())
+}
+
+/// Sorted by code.
+const EXPLANATIONS: &[(&str, &str)] = &[
+    (
+        "E0001",
+        r"
+The module doesn't exist.
+
+This usually happens when the file of a module is deleted or renamed while it's
+still being compiled.
+",
+    ),
+    (
+        "E0002",
+        r"
+The module's file contains bytes that are not valid UTF-8.
+
+Candy source files must be encoded as UTF-8. Re-save the file with this
+encoding in your editor.
+",
+    ),
+    (
+        "E0003",
+        r"
+The module is not a Candy file.
+
+Only files ending with `.candy` can be compiled or imported as code.
+",
+    ),
+    (
+        "E0004",
+        r"
+The module is a tooling module.
+
+Tooling modules are created by Candy's tools (e.g., for fuzzing or the language
+server) and can't be compiled as regular code.
+",
+    ),
+    (
+        "E0005",
+        r"
+A pipe (`|`) is missing the function to call on its right side.
+
+Erroneous code example:
+
+    result = value |
+
+A pipe passes the value on its left as the first argument to the function on
+its right:
+
+    result = value | int.add 1
+",
+    ),
+    (
+        "E0006",
+        r"
+A function's curly brace is not closed.
+
+Erroneous code example:
+
+    double = { a -> int.multiply a 2
+
+Add the closing curly brace:
+
+    double = { a -> int.multiply a 2 }
+",
+    ),
+    (
+        "E0007",
+        r"
+A float literal contains characters that are not digits.
+
+Erroneous code example:
+
+    pi = 3.14abc
+
+Floats consist of digits before and after the dot:
+
+    pi = 3.14
+",
+    ),
+    (
+        "E0008",
+        r"
+An identifier contains characters that are not alphanumeric ASCII characters.
+
+Erroneous code example:
+
+    größe = 5
+
+Identifiers start with a lowercase letter followed by ASCII letters and digits:
+
+    size = 5
+",
+    ),
+    (
+        "E0009",
+        r"
+An int literal contains characters that are not digits.
+
+Erroneous code example:
+
+    answer = 42abc
+
+Ints consist only of digits:
+
+    answer = 42
+",
+    ),
+    (
+        "E0010",
+        r"
+A list item is missing its value.
+
+Erroneous code example:
+
+    numbers = (1, , 3)
+
+Each item between commas needs a value:
+
+    numbers = (1, 2, 3)
+",
+    ),
+    (
+        "E0011",
+        r"
+A list is not closed.
+
+Erroneous code example:
+
+    numbers = (1, 2, 3
+
+Add the closing parenthesis:
+
+    numbers = (1, 2, 3)
+",
+    ),
+    (
+        "E0012",
+        r"
+A match case misses the arrow between its pattern and its body.
+
+Erroneous code example:
+
+    result = value %
+      Ok value value
+      Error _ -> 0
+
+Separate the pattern from the body using `->`:
+
+    result = value %
+      Ok value -> value
+      Error _ -> 0
+",
+    ),
+    (
+        "E0013",
+        r"
+A match case misses the body that runs when its pattern matches.
+
+Erroneous code example:
+
+    result = value %
+      Ok value ->
+      Error _ -> 0
+
+Add an expression after the arrow:
+
+    result = value %
+      Ok value -> value
+      Error _ -> 0
+",
+    ),
+    (
+        "E0014",
+        r"
+A match doesn't contain any cases.
+
+Erroneous code example:
+
+    result = value %
+
+Add indented cases after the `%`, each consisting of a pattern and a body:
+
+    result = value %
+      Ok value -> value
+      Error _ -> 0
+",
+    ),
+    (
+        "E0015",
+        r"
+An opening parenthesis is not followed by an expression.
+
+Erroneous code example:
+
+    result = (
+
+Put an expression inside the parentheses or remove them:
+
+    result = (int.add 1 2)
+",
+    ),
+    (
+        "E0016",
+        r"
+An or-pattern misses its right side.
+
+Erroneous code example:
+
+    result = value %
+      Foo | -> 1
+      _ -> 2
+
+Add another pattern after the bar:
+
+    result = value %
+      Foo | Bar -> 1
+      _ -> 2
+",
+    ),
+    (
+        "E0017",
+        r"
+A parenthesis is not closed.
+
+Erroneous code example:
+
+    result = (int.add 1 2
+
+Add the closing parenthesis:
+
+    result = (int.add 1 2)
+",
+    ),
+    (
+        "E0018",
+        r"
+A struct access misses the key after the dot.
+
+Erroneous code example:
+
+    name = person.
+
+Write the key to access after the dot:
+
+    name = person.name
+",
+    ),
+    (
+        "E0019",
+        r#"
+A struct field misses the colon between its key and its value.
+
+Erroneous code example:
+
+    person = [Name "Alice"]
+
+Separate keys and values using a colon:
+
+    person = [Name: "Alice"]
+"#,
+    ),
+    (
+        "E0020",
+        r#"
+A struct field misses its key.
+
+Erroneous code example:
+
+    person = [: "Alice"]
+
+Add a key before the colon:
+
+    person = [Name: "Alice"]
+"#,
+    ),
+    (
+        "E0021",
+        r#"
+A struct field misses its value.
+
+Erroneous code example:
+
+    person = [Name: ]
+
+Add a value after the colon:
+
+    person = [Name: "Alice"]
+"#,
+    ),
+    (
+        "E0022",
+        r#"
+A struct is not closed.
+
+Erroneous code example:
+
+    person = [Name: "Alice"
+
+Add the closing bracket:
+
+    person = [Name: "Alice"]
+"#,
+    ),
+    (
+        "E0023",
+        r"
+A symbol contains characters that are not alphanumeric ASCII characters.
+
+Erroneous code example:
+
+    color = Grün
+
+Symbols start with an uppercase letter followed by ASCII letters and digits:
+
+    color = Green
+",
+    ),
+    (
+        "E0024",
+        r#"
+A text interpolation doesn't contain an expression.
+
+Erroneous code example:
+
+    greeting = "Hello, {}!"
+
+Put the expression whose value should be inserted between the curly braces:
+
+    greeting = "Hello, {name}!"
+"#,
+    ),
+    (
+        "E0025",
+        r#"
+A text interpolation is not closed.
+
+Erroneous code example:
+
+    greeting = "Hello, {name!"
+
+Add the closing curly brace:
+
+    greeting = "Hello, {name}!"
+"#,
+    ),
+    (
+        "E0026",
+        r#"
+A text is not closed.
+
+Erroneous code example:
+
+    greeting = "Hello, world!
+
+Add the closing quote:
+
+    greeting = "Hello, world!"
+"#,
+    ),
+    (
+        "E0027",
+        r#"
+A line of a multiline text is not sufficiently indented.
+
+Erroneous code example:
+
+    poem = "
+    Roses are red
+      "
+
+The lines of a text spanning multiple lines must be indented more than the
+line containing the opening quote:
+
+    poem = "
+      Roses are red
+    "
+"#,
+    ),
+    (
+        "E0028",
+        r"
+There is too much whitespace here.
+
+Erroneous code example:
+
+    foo =
+        42
+
+Indentation always uses two spaces per level:
+
+    foo =
+      42
+",
+    ),
+    (
+        "E0029",
+        r"
+These characters are not valid Candy syntax.
+
+Erroneous code example:
+
+    result = 1 + 2
+
+Candy doesn't have operators like `+`. Use functions instead:
+
+    result = int.add 1 2
+",
+    ),
+    (
+        "E0030",
+        r"
+The parser couldn't make sense of the rest of the file.
+
+This usually follows another error that confused the parser, so fixing the
+earlier errors often resolves this one.
+",
+    ),
+    (
+        "E0031",
+        r"
+This whitespace character is not allowed.
+
+Candy only allows spaces and newlines as whitespace. Tabs and other special
+whitespace characters (e.g., non-breaking spaces) cause this error. Replace
+them with regular spaces.
+",
+    ),
+    (
+        "E0032",
+        r"
+The indentation contains whitespace characters other than spaces.
+
+Candy indents code using two spaces per level. Replace tabs and other special
+whitespace characters in the indentation with spaces.
+",
+    ),
+    (
+        "E0033",
+        r"
+An assignment misses a name or pattern on its left side.
+
+Erroneous code example:
+
+    = 42
+
+Write the name to assign the value to before the equals sign:
+
+    answer = 42
+",
+    ),
+    (
+        "E0034",
+        r"
+A parameter was expected here.
+
+Erroneous code example:
+
+    double = { 2 -> int.multiply 2 2 }
+
+Parameters of functions must be identifiers:
+
+    double = { a -> int.multiply a 2 }
+",
+    ),
+    (
+        "E0035",
+        r"
+A function misses its closing curly brace.
+
+Erroneous code example:
+
+    double = { a -> int.multiply a 2
+
+Add the closing curly brace:
+
+    double = { a -> int.multiply a 2 }
+",
+    ),
+    (
+        "E0036",
+        r"
+A list item is not followed by a comma.
+
+Erroneous code example:
+
+    numbers = (1, 2 3)
+
+Separate list items using commas:
+
+    numbers = (1, 2, 3)
+
+Lists with a single item require a trailing comma to distinguish them from
+parenthesized expressions:
+
+    numbers = (1,)
+",
+    ),
+    (
+        "E0037",
+        r"
+A list misses its closing parenthesis.
+
+Erroneous code example:
+
+    numbers = (1, 2, 3
+
+Add the closing parenthesis:
+
+    numbers = (1, 2, 3)
+",
+    ),
+    (
+        "E0038",
+        r"
+This is not a list item.
+
+Lists only contain items separated by commas. Make sure each item is followed
+by a comma and that the list is closed correctly.
+",
+    ),
+    (
+        "E0039",
+        r"
+A sub-pattern of an or-pattern doesn't bind all identifiers that the other
+sub-patterns bind.
+
+Erroneous code example:
+
+    result = value %
+      Ok a | Error -> a
+
+Whichever sub-pattern matches, the body can use all captured identifiers, so
+every sub-pattern must bind the same identifiers:
+
+    result = value %
+      Ok a | Error a -> a
+",
+    ),
+    (
+        "E0040",
+        r"
+Parentheses are not allowed in patterns.
+
+Erroneous code example:
+
+    result = value %
+      (Foo) -> 1
+
+Remove the parentheses:
+
+    result = value %
+      Foo -> 1
+",
+    ),
+    (
+        "E0041",
+        r"
+A parenthesized expression misses its closing parenthesis.
+
+Erroneous code example:
+
+    result = (int.add 1 2
+
+Add the closing parenthesis:
+
+    result = (int.add 1 2)
+",
+    ),
+    (
+        "E0042",
+        r"
+This kind of expression is not allowed in patterns.
+
+Erroneous code example:
+
+    result = value %
+      { a -> a } -> 1
+
+Patterns can contain identifiers to capture values, ints, texts, tags, lists,
+and structs, but no functions or other expressions.
+",
+    ),
+    (
+        "E0043",
+        r"
+This kind of expression is not allowed in this part of a pattern.
+
+Erroneous code example:
+
+    [Name: name] = person
+    [(name): value] = person
+
+Keys of struct patterns must be literals, such as tags, ints, or texts:
+
+    [Name: name] = person
+",
+    ),
+    (
+        "E0044",
+        r"
+Pipes are not allowed in patterns.
+
+Erroneous code example:
+
+    value | int.add 1 = 2
+
+Patterns describe the structure of a value but can't run code. Compute the
+value first and then destructure the result.
+",
+    ),
+    (
+        "E0045",
+        r#"
+A struct key is not followed by a colon.
+
+Erroneous code example:
+
+    person = [Name "Alice"]
+
+Separate keys and values using a colon:
+
+    person = [Name: "Alice"]
+"#,
+    ),
+    (
+        "E0046",
+        r#"
+A struct misses its closing bracket.
+
+Erroneous code example:
+
+    person = [Name: "Alice"
+
+Add the closing bracket:
+
+    person = [Name: "Alice"]
+"#,
+    ),
+    (
+        "E0047",
+        r"
+The shorthand syntax in structs only supports identifiers.
+
+Erroneous code example:
+
+    person = [getName person]
+
+The shorthand `[name]` is equivalent to `[Name: name]`, so it only works with
+identifiers. Write the key explicitly instead:
+
+    person = [Name: getName person]
+",
+    ),
+    (
+        "E0048",
+        r#"
+A struct value is not followed by a comma.
+
+Erroneous code example:
+
+    person = [Name: "Alice" Age: 42]
+
+Separate struct fields using commas:
+
+    person = [Name: "Alice", Age: 42]
+"#,
+    ),
+    (
+        "E0049",
+        r#"
+A struct contains something that is not a struct field.
+
+Structs only contain fields of the form `Key: value` (or the shorthand
+`identifier`) separated by commas:
+
+    person = [Name: "Alice", Age: 42]
+"#,
+    ),
+    (
+        "E0050",
+        r#"
+A text interpolation misses its closing curly brace.
+
+Erroneous code example:
+
+    greeting = "Hello, {name!"
+
+Add the closing curly brace:
+
+    greeting = "Hello, {name}!"
+"#,
+    ),
+    (
+        "E0051",
+        r#"
+A text misses its closing quote.
+
+Erroneous code example:
+
+    greeting = "Hello, world!
+
+Add the closing quote:
+
+    greeting = "Hello, world!"
+"#,
+    ),
+    (
+        "E0052",
+        r"
+This punctuation is not expected here.
+
+Erroneous code example:
+
+    result = int.add 1 2)
+
+Remove the punctuation or add the missing counterpart:
+
+    result = (int.add 1 2)
+",
+    ),
+    (
+        "E0053",
+        r#"
+`needs` was called with the wrong number of arguments.
+
+Erroneous code example:
+
+    needs
+
+`needs` takes a condition and, optionally, a message explaining the condition
+and a value that's shown together with the message if the condition isn't met:
+
+    needs (int.is value)
+    needs (int.is value) "The value must be an int."
+    needs (int.isNonNegative value) "The value must be non-negative." value
+"#,
+    ),
+    (
+        "E0054",
+        r"
+Calls are not allowed in patterns.
+
+Erroneous code example:
+
+    result = value %
+      Ok (int.add 1 2) -> 1
+
+Patterns can only check a value's structure. Compare values using `equals`
+after matching instead:
+
+    result = value %
+      Ok number -> number | equals (int.add 1 2)
+",
+    ),
+    (
+        "E0055",
+        r"
+Public assignments (`:=`) can only be used in top-level code.
+
+Erroneous code example:
+
+    main := { environment ->
+      answer := 42
+    }
+
+Only values assigned at the top level of a module can be exported. Use a
+regular assignment inside functions:
+
+    main := { environment ->
+      answer = 42
+    }
+",
+    ),
+    (
+        "E0056",
+        r"
+There already exists a public assignment with the same name.
+
+Erroneous code example:
+
+    answer := 42
+    answer := 43
+
+Each module exports a struct containing its public assignments, so their names
+must be unique. Rename one of them or make it private using `=`.
+",
+    ),
+    (
+        "E0057",
+        r#"
+This name is not in scope.
+
+Erroneous code example:
+
+    double a = int.multiply a 2
+
+Names must be defined before they are used. Check for typos, define the value,
+or import it from another module:
+
+    [int] = use "Core"
+
+    double a = int.multiply a 2
+"#,
+    ),
+    (
+        "E0058",
+        r#"
+A `use` refers to an invalid path.
+
+Erroneous code example:
+
+    foo = use ".foo-bar"
+
+Paths in `use` can only contain letters, digits, and dots. Relative paths start
+with a dot, followed by another dot for each parent navigation:
+
+    foo = use ".fooBar"
+"#,
+    ),
+    (
+        "E0059",
+        r#"
+A `use` navigates out of the current package.
+
+Erroneous code example:
+
+    other = use "....other"
+
+Relative imports can only refer to modules in the same package, which is the
+folder containing the `_package.candy` file. To use other packages, refer to
+them by name, e.g., `use "Core"`.
+"#,
+    ),
+    (
+        "E0060",
+        r#"
+The module referred to by a `use` doesn't exist.
+
+Erroneous code example:
+
+    foo = use ".doesNotExist"
+
+Check the name of the module and that its file exists.
+"#,
+    ),
+    (
+        "E0061",
+        r#"
+A `use` is not statically resolvable.
+
+Erroneous code example:
+
+    path = ".foo"
+    foo = use path
+
+The path passed to `use` must be a text literal so that the compiler can find
+the module:
+
+    foo = use ".foo"
+"#,
+    ),
+    (
+        "E0062",
+        r"
+There's a cycle in the used modules.
+
+For example, `a.candy` uses `b.candy` and `b.candy` uses `a.candy`. Modules are
+evaluated when they're used, so such a cycle would never finish. Move the code
+that both modules need into a third module.
+",
+    ),
+    (
+        "E0063",
+        r"
+A function is called with the wrong number of arguments.
+
+Erroneous code example:
+
+    add a b = int.add a b
+    three = add 1
+
+The compiler checks calls of functions it knows statically, such as functions
+defined in the same module. Calling a function with the wrong number of
+arguments panics at runtime, so pass all arguments:
+
+    three = add 1 2
+",
+    ),
+    (
+        "E0064",
+        r"
+A `then` or `else` of an `if` expression misses an expression.
+
+Erroneous code example:
+
+    foo = if condition then 1 else
+
+Add the value the `if` expression should evaluate to:
+
+    foo = if condition then 1 else 2
+",
+    ),
+    (
+        "E0065",
+        r"
+An `if` expression misses an `else`.
+
+Erroneous code example:
+
+    foo = if condition then 1
+
+`if` expressions always evaluate to a value, so they need an `else`:
+
+    foo = if condition then 1 else 2
+",
+    ),
+    (
+        "E0066",
+        r"
+A binary operator misses its right side.
+
+Erroneous code example:
+
+    foo = 1 +
+
+Add the right side:
+
+    foo = 1 + 2
+",
+    ),
+    (
+        "E0067",
+        r"
+Operators without associativity are chained.
+
+Erroneous code example:
+
+    isInRange = 0 < value < 10
+
+Comparison and equality operators don't have an associativity, so it's unclear
+which one should be evaluated first. Add parentheses or split the expression:
+
+    isInRange = bool.and (0 < value) (value < 10)
+",
+    ),
+    (
+        "E0068",
+        r#"
+A text contains an unknown escape sequence.
+
+Erroneous code example:
+
+    path = "C:\Users"
+
+Texts without single quotes support the escape sequences `\"`, `\\`, `\n`,
+`\t`, and `\u{…}`. Escape the backslash or use a meta text, in which
+backslashes have no special meaning:
+
+    path = "C:\\Users"
+    path = '"C:\Users"'
+"#,
+    ),
+    (
+        "E0069",
+        r#"
+A unicode escape sequence in a text is invalid.
+
+Erroneous code example:
+
+    smiley = "\u{1F60G}"
+
+Unicode escape sequences contain one to six hexadecimal digits in curly braces
+that form a valid Unicode scalar value:
+
+    smiley = "\u{1F600}"
+"#,
+    ),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explanations_are_sorted_and_contiguous() {
+        for (index, (code, _)) in EXPLANATIONS.iter().enumerate() {
+            assert_eq!(*code, format!("E{:04}", index + 1));
+        }
+    }
+}
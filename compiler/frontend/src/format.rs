@@ -6,11 +6,18 @@ use std::{borrow::Cow, ops::Sub};
 
 pub enum FormatValue<'a, T: Copy> {
     Int(Cow<'a, BigInt>),
-    Tag { symbol: &'a str, value: Option<T> },
+    Float(f64),
+    Tag {
+        symbol: &'a str,
+        value: Option<T>,
+    },
     Text(&'a str),
+    Bytes(&'a [u8]),
     List(&'a [T]),
     Struct(Cow<'a, Vec<(T, T)>>),
     Function,
+    /// A function with a known textual representation, e.g., `{ a -> a }`.
+    DescribedFunction(&'a str),
     SendPort,
     ReceivePort,
 }
@@ -51,6 +58,17 @@ impl Sub<usize> for MaxLength {
     }
 }
 
+/// The textual representation of floats is shared between `✨.floatToText`,
+/// debug texts, and constant folding.
+#[must_use]
+pub fn format_float(value: f64) -> String {
+    format!("{value:?}")
+}
+
+/// Values nested deeper than this are formatted as `…`. This keeps formatting
+/// deeply nested values (e.g., long linked lists) from overflowing the stack.
+const MAX_DEPTH: usize = 64;
+
 /// Formats the value, using the visitor to match across possible values.
 pub fn format_value<'a, T: 'a + Copy>(
     value: T,
@@ -58,6 +76,19 @@ pub fn format_value<'a, T: 'a + Copy>(
     max_length: MaxLength,
     visitor: &impl Fn(T) -> Option<FormatValue<'a, T>>,
 ) -> Option<String> {
+    format_value_at_depth(value, precedence, max_length, 0, visitor)
+}
+fn format_value_at_depth<'a, T: 'a + Copy>(
+    value: T,
+    precedence: Precedence,
+    max_length: MaxLength,
+    depth: usize,
+    visitor: &impl Fn(T) -> Option<FormatValue<'a, T>>,
+) -> Option<String> {
+    if depth >= MAX_DEPTH {
+        return Some("…".to_string());
+    }
+
     // For each case, the different alternatives of printing are listed.
     // Depending on the available space, the best is chosen.
     Some(match visitor(value)? {
@@ -72,6 +103,17 @@ pub fn format_value<'a, T: 'a + Copy>(
                 "…".to_string()
             }
         }
+        FormatValue::Float(float) => {
+            // - float
+            // - `…`
+
+            let string = format_float(float);
+            if max_length.fits(string.len()) {
+                string
+            } else {
+                "…".to_string()
+            }
+        }
         FormatValue::Tag { symbol, value } => {
             // - full: `Tag Value` or `(Tag Value)` or `Tag`
             // - only symbol: `Tag …` or `(Tag …)` or `Tag`
@@ -105,10 +147,11 @@ pub fn format_value<'a, T: 'a + Copy>(
             if let Some(value) = value {
                 string.push(' ');
                 if symbol_fits {
-                    string.push_str(&format_value(
+                    string.push_str(&format_value_at_depth(
                         value,
                         Precedence::High,
                         max_length - (length_needed_for_structure - 2 + symbol.len()),
+                        depth + 1,
                         visitor,
                     )?);
                 } else {
@@ -130,12 +173,42 @@ pub fn format_value<'a, T: 'a + Copy>(
                 "…".to_string()
             }
         }
+        FormatValue::Bytes(bytes) => {
+            // - all bytes in hexadecimal: `<48 65 79>`
+            // - no bytes shown: `<3 bytes>`
+            // - `…`
+
+            let full = format!("<{:02x}>", bytes.iter().format(" "));
+            if max_length.fits(full.len()) {
+                return Some(full);
+            }
+
+            let summary = format!("<{} bytes>", bytes.len());
+            if max_length.fits(summary.len()) {
+                summary
+            } else {
+                "…".to_string()
+            }
+        }
         FormatValue::Function => {
             // - `{ … }`
             // - `…`
 
             if max_length.fits(5) { "{ … }" } else { "…" }.to_string()
         }
+        FormatValue::DescribedFunction(description) => {
+            // - description
+            // - `{ … }`
+            // - `…`
+
+            if max_length.fits(description.len()) {
+                description.to_string()
+            } else if max_length.fits(5) {
+                "{ … }".to_string()
+            } else {
+                "…".to_string()
+            }
+        }
         FormatValue::List(list) => {
             // - all items: `(Foo, Bar, Baz)`
             // - some items: `(Foo, Bar, + 2 more)`
@@ -157,7 +230,13 @@ pub fn format_value<'a, T: 'a + Copy>(
             let list_len = list.len();
             if list_len == 1 {
                 let item = list[0];
-                let item = format_value(item, Precedence::Low, MaxLength::Unlimited, visitor)?;
+                let item = format_value_at_depth(
+                    item,
+                    Precedence::Low,
+                    MaxLength::Unlimited,
+                    depth + 1,
+                    visitor,
+                )?;
                 return if max_length.fits(item.len() + 3) {
                     Some(format!("({item},)"))
                 } else {
@@ -174,7 +253,13 @@ pub fn format_value<'a, T: 'a + Copy>(
                     break;
                 }
 
-                let item = format_value(*item, Precedence::Low, MaxLength::Unlimited, visitor)?;
+                let item = format_value_at_depth(
+                    *item,
+                    Precedence::Low,
+                    MaxLength::Unlimited,
+                    depth + 1,
+                    visitor,
+                )?;
                 total_item_length += item.len();
                 items.push(item);
             }
@@ -222,8 +307,14 @@ pub fn format_value<'a, T: 'a + Copy>(
             let mut entries = entries
                 .iter()
                 .map(|(key, value)| {
-                    format_value(*key, Precedence::Low, MaxLength::Unlimited, visitor)
-                        .map(|key| (key, value))
+                    format_value_at_depth(
+                        *key,
+                        Precedence::Low,
+                        MaxLength::Unlimited,
+                        depth + 1,
+                        visitor,
+                    )
+                    .map(|key| (key, value))
                 })
                 .collect::<Option<Vec<_>>>()?;
             entries.sort_by(|(key_a, _), (key_b, _)| key_a.cmp(key_b));
@@ -261,7 +352,13 @@ pub fn format_value<'a, T: 'a + Copy>(
             let mut values = Vec::with_capacity(num_entries);
             let mut total_values_length = num_entries; // dots for every value
             for (_, value) in &entries {
-                let value = format_value(**value, Precedence::Low, MaxLength::Unlimited, visitor)?;
+                let value = format_value_at_depth(
+                    **value,
+                    Precedence::Low,
+                    MaxLength::Unlimited,
+                    depth + 1,
+                    visitor,
+                )?;
                 total_values_length += value.len() - 1; // remove the dots, add the value
                 values.push(value);
 
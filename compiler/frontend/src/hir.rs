@@ -1,6 +1,7 @@
 use crate::{
     ast_to_hir::AstToHir,
     builtin_functions::BuiltinFunction,
+    cst,
     error::CompilerError,
     impl_countable_id, impl_display_via_richir,
     module::{Module, ModuleKind, Package},
@@ -10,8 +11,10 @@ use derive_more::From;
 use enumset::EnumSet;
 use itertools::Itertools;
 use linked_hash_map::LinkedHashMap;
-use num_bigint::BigUint;
+use num_bigint::BigInt;
+use ordered_float::OrderedFloat;
 use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
 use std::{
     fmt::{self, Debug, Display, Formatter},
     hash::{Hash, Hasher},
@@ -69,11 +72,15 @@ impl Expression {
     pub fn collect_all_ids(&self, ids: &mut Vec<Id>) {
         match self {
             Self::Int(_) => {}
+            Self::Float(_) => {}
             Self::Text(_) => {}
             Self::Reference(id) => {
                 ids.push(id.clone());
             }
             Self::Symbol(_) => {}
+            Self::Tag { value, .. } => {
+                ids.push(value.clone());
+            }
             Self::List(items) => {
                 ids.extend_from_slice(items);
             }
@@ -113,9 +120,14 @@ impl Expression {
                 ids.push(relative_path.clone());
             }
             Self::Builtin(_) => {}
-            Self::Needs { condition, reason } => {
+            Self::Needs {
+                condition,
+                reason,
+                context,
+            } => {
                 ids.push(condition.clone());
                 ids.push(reason.clone());
+                ids.extend(context.clone());
             }
             Self::Error { .. } => {}
         }
@@ -130,15 +142,27 @@ impl Body {
     }
 }
 
-#[derive(Clone, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub struct Id {
     pub module: Module,
     pub keys: Vec<IdKey>,
 }
-#[derive(Clone, Eq, From, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Deserialize, Eq, From, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub enum IdKey {
-    Named { name: String, disambiguator: usize },
+    Named {
+        name: String,
+        disambiguator: usize,
+    },
     Positional(usize),
+    /// An anonymous expression on the right side of a top-level assignment.
+    ///
+    /// The index only counts the anonymous expressions of this definition, so
+    /// the ID stays the same when other definitions are inserted or removed.
+    #[from(ignore)]
+    InDefinition {
+        definition: String,
+        index: usize,
+    },
 }
 impl Id {
     #[must_use]
@@ -239,6 +263,7 @@ impl Id {
             .iter()
             .map(|it| match it {
                 IdKey::Positional(index) => format!("<anonymous {index}>"),
+                IdKey::InDefinition { .. } => format!("<anonymous {it}>"),
                 IdKey::Named { name, .. } => name.to_string(),
             })
             .join(" → ")
@@ -278,6 +303,7 @@ impl Debug for IdKey {
                 Ok(())
             }
             Self::Positional(index) => write!(f, "{index}"),
+            Self::InDefinition { definition, index } => write!(f, "{definition}.{index}"),
         }
     }
 }
@@ -302,10 +328,18 @@ impl From<&str> for IdKey {
 
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub enum Expression {
-    Int(BigUint),
+    Int(BigInt),
+    Float(OrderedFloat<f64>),
     Text(String),
     Reference(Id),
     Symbol(String),
+    /// A tag with a value, e.g., `Some 5`.
+    ///
+    /// Tags without a value are [`Expression::Symbol`]s.
+    Tag {
+        symbol: String,
+        value: Id,
+    },
     List(Vec<Id>),
     Struct(FxHashMap<Id, Id>),
     Destructure {
@@ -333,6 +367,9 @@ pub enum Expression {
     Needs {
         condition: Id,
         reason: Id,
+        /// A value that gets appended to the reason if the condition is
+        /// `False`.
+        context: Option<Id>,
     },
     Error {
         errors: Vec<CompilerError>,
@@ -374,7 +411,8 @@ impl ToRichIr for PatternIdentifierId {
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub enum Pattern {
     NewIdentifier(PatternIdentifierId),
-    Int(BigUint),
+    Int(BigInt),
+    Float(OrderedFloat<f64>),
     Text(String),
     Tag {
         symbol: String,
@@ -398,7 +436,7 @@ impl Pattern {
     pub fn contains_captured_identifiers(&self) -> bool {
         match self {
             Self::NewIdentifier(_) => true,
-            Self::Int(_) | Self::Text(_) => false,
+            Self::Int(_) | Self::Float(_) | Self::Text(_) => false,
             Self::Tag { value, .. } => value
                 .as_ref()
                 .is_some_and(|value| value.contains_captured_identifiers()),
@@ -413,7 +451,7 @@ impl Pattern {
     pub fn captured_identifier_count(&self) -> usize {
         match self {
             Self::NewIdentifier(_) => 1,
-            Self::Int(_) | Self::Text(_) => 0,
+            Self::Int(_) | Self::Float(_) | Self::Text(_) => 0,
             Self::Tag { value, .. } => value
                 .as_ref()
                 .map(|value| value.captured_identifier_count())
@@ -444,7 +482,7 @@ impl Pattern {
     fn collect_captured_identifiers(&self, ids: &mut Vec<PatternIdentifierId>) {
         match self {
             Self::NewIdentifier(identifier_id) => ids.push(*identifier_id),
-            Self::Int(_) | Self::Text(_) => {}
+            Self::Int(_) | Self::Float(_) | Self::Text(_) => {}
             Self::Tag { value, .. } => {
                 if let Some(value) = value {
                     value.collect_captured_identifiers(ids);
@@ -515,6 +553,9 @@ impl FunctionKind {
 pub struct Body {
     pub expressions: LinkedHashMap<Id, Expression>,
     pub identifiers: FxHashMap<Id, String>,
+    /// Documentation of assigned identifiers, keyed by the ID that the
+    /// identifier refers to.
+    pub documentation: FxHashMap<Id, String>,
 }
 #[allow(clippy::derived_hash_with_manual_eq)]
 impl Hash for Body {
@@ -525,11 +566,25 @@ impl Hash for Body {
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum HirError {
-    NeedsWithWrongNumberOfArguments { num_args: usize },
+    /// The callee is statically known, e.g., a function defined in the same
+    /// module. `function` is its name if the call refers to it by name.
+    CallWithWrongNumberOfArguments {
+        function: Option<String>,
+        expected: usize,
+        actual: usize,
+        definition: Option<cst::Id>,
+    },
+    NeedsWithWrongNumberOfArguments {
+        num_args: usize,
+    },
     PatternContainsCall,
     PublicAssignmentInNotTopLevel,
-    PublicAssignmentWithSameName { name: String },
-    UnknownReference { name: String },
+    PublicAssignmentWithSameName {
+        name: String,
+    },
+    UnknownReference {
+        name: String,
+    },
 }
 
 impl Body {
@@ -548,6 +603,9 @@ impl ToRichIr for Expression {
             Self::Int(int) => {
                 int.build_rich_ir(builder);
             }
+            Self::Float(float) => {
+                float.build_rich_ir(builder);
+            }
             Self::Text(text) => {
                 let range = builder.push(format!(r#""{text}""#), TokenType::Text, EnumSet::empty());
                 builder.push_reference(text.clone(), range);
@@ -559,6 +617,12 @@ impl ToRichIr for Expression {
                 let range = builder.push(symbol, TokenType::Symbol, EnumSet::empty());
                 builder.push_reference(ReferenceKey::Symbol(symbol.clone()), range);
             }
+            Self::Tag { symbol, value } => {
+                let range = builder.push(symbol, TokenType::Symbol, EnumSet::empty());
+                builder.push_reference(ReferenceKey::Symbol(symbol.clone()), range);
+                builder.push(" ", None, EnumSet::empty());
+                value.build_rich_ir(builder);
+            }
             Self::List(items) => {
                 builder.push("(", None, EnumSet::empty());
                 builder.push_children(items, ", ");
@@ -641,11 +705,19 @@ impl ToRichIr for Expression {
                 builder.push(", use ", None, EnumSet::empty());
                 relative_path.build_rich_ir(builder);
             }
-            Self::Needs { condition, reason } => {
+            Self::Needs {
+                condition,
+                reason,
+                context,
+            } => {
                 builder.push("needs ", None, EnumSet::empty());
                 condition.build_rich_ir(builder);
                 builder.push(" with reason ", None, EnumSet::empty());
                 reason.build_rich_ir(builder);
+                if let Some(context) = context {
+                    builder.push(" and context ", None, EnumSet::empty());
+                    context.build_rich_ir(builder);
+                }
             }
             Self::Error { errors } => {
                 build_errors_rich_ir(builder, errors);
@@ -659,6 +731,9 @@ impl ToRichIr for Pattern {
             Self::Int(int) => {
                 builder.push(format!("{int}"), TokenType::Int, EnumSet::empty());
             }
+            Self::Float(float) => {
+                builder.push(format!("{float:?}"), TokenType::Float, EnumSet::empty());
+            }
             Self::Text(text) => {
                 builder.push(format!(r#""{text}""#), TokenType::Text, EnumSet::empty());
             }
@@ -757,9 +832,11 @@ impl Expression {
     fn find(&self, id: &Id) -> Option<&Self> {
         match self {
             Self::Int { .. } => None,
+            Self::Float { .. } => None,
             Self::Text { .. } => None,
             Self::Reference { .. } => None,
             Self::Symbol { .. } => None,
+            Self::Tag { .. } => None,
             Self::List(_) => None,
             Self::Struct(_) => None,
             Self::Destructure { .. } => None,
@@ -798,9 +875,11 @@ impl CollectErrors for Expression {
     fn collect_errors(&self, errors: &mut Vec<CompilerError>) {
         match self {
             Self::Int(_)
+            | Self::Float(_)
             | Self::Text(_)
             | Self::Reference(_)
             | Self::Symbol(_)
+            | Self::Tag { .. }
             | Self::List(_)
             | Self::Struct(_)
             | Self::PatternIdentifierReference { .. } => {}
@@ -824,7 +903,7 @@ impl CollectErrors for Expression {
 impl CollectErrors for Pattern {
     fn collect_errors(&self, errors: &mut Vec<CompilerError>) {
         match self {
-            Self::NewIdentifier(_) | Self::Int(_) | Self::Text(_) => {}
+            Self::NewIdentifier(_) | Self::Int(_) | Self::Float(_) | Self::Text(_) => {}
             Self::Tag { value, .. } => {
                 if let Some(value) = value {
                     value.collect_errors(errors);
@@ -11,6 +11,7 @@ use crate::{
     module::{Module, ModuleKind},
     position::PositionConversionDb,
     string_to_rcst::ModuleError,
+    timings::{self, Stage},
     tracing::CallTracingMode,
 };
 use itertools::Itertools;
@@ -53,13 +54,16 @@ fn mir(db: &dyn HirToMir, target: ExecutionTarget, tracing: TracingConfig) -> Mi
         ModuleKind::Code => {
             let (hir, _) = db.hir(module.clone())?;
             let mut errors = FxHashSet::default();
-            let mir = LoweringContext::compile_module(
-                module,
-                target_is_main_function,
-                &hir,
-                tracing,
-                &mut errors,
-            );
+            let tracing = tracing.for_module(&module);
+            let mir = timings::record_stage(Stage::Mir, module.clone(), || {
+                LoweringContext::compile_module(
+                    module.clone(),
+                    target_is_main_function,
+                    &hir,
+                    tracing,
+                    &mut errors,
+                )
+            });
             (mir, errors)
         }
         ModuleKind::Asset => {
@@ -233,6 +237,48 @@ impl<'a> LoweringContext<'a> {
             }
         })
     }
+    /// Appends the debug text of the `context` to the `reason` of a `needs`.
+    ///
+    /// Rendering the context can be expensive, so this only happens if the
+    /// condition is `False`, i.e., right before the `needs` panics.
+    fn compile_reason_with_context(
+        body: &mut BodyBuilder,
+        hir_id: &hir::Id,
+        condition: Id,
+        reason: Id,
+        context: Id,
+        responsible: Id,
+    ) -> Id {
+        let builtin_equals = body.push_builtin(BuiltinFunction::Equals);
+        let false_tag = body.push_bool(false);
+        let is_condition_false =
+            body.push_call(builtin_equals, vec![condition, false_tag], responsible);
+        body.push_if_else(
+            &hir_id.child("reasonWithContext"),
+            is_condition_false,
+            |body| {
+                let builtin_to_debug_text = body.push_builtin(BuiltinFunction::ToDebugText);
+                let context_text =
+                    body.push_call(builtin_to_debug_text, vec![context], responsible);
+                let builtin_text_concatenate = body.push_builtin(BuiltinFunction::TextConcatenate);
+                let separator = body.push_text(" Context: ".to_string());
+                let reason_with_separator = body.push_call(
+                    builtin_text_concatenate,
+                    vec![reason, separator],
+                    responsible,
+                );
+                body.push_call(
+                    builtin_text_concatenate,
+                    vec![reason_with_separator, context_text],
+                    responsible,
+                );
+            },
+            |body| {
+                body.push_reference(reason);
+            },
+            responsible,
+        )
+    }
     fn compile_get_main_function_from_export_struct(
         body: &mut BodyBuilder,
         module_hir_id: &hir::Id,
@@ -316,9 +362,13 @@ impl<'a> LoweringContext<'a> {
     ) {
         let id = match expression {
             hir::Expression::Int(int) => body.push_int(int.clone()),
+            hir::Expression::Float(float) => body.push_float(*float),
             hir::Expression::Text(text) => body.push_text(text.clone()),
             hir::Expression::Reference(reference) => body.push_reference(self.mapping[reference]),
             hir::Expression::Symbol(symbol) => body.push_tag(symbol.clone(), None),
+            hir::Expression::Tag { symbol, value } => {
+                body.push_tag(symbol.clone(), Some(self.mapping[value]))
+            }
             hir::Expression::Builtin(builtin) => body.push_builtin(*builtin),
             hir::Expression::List(items) => {
                 body.push_list(items.iter().map(|item| self.mapping[item]).collect())
@@ -351,6 +401,7 @@ impl<'a> LoweringContext<'a> {
                         hir_id.clone(),
                         responsible,
                         expression,
+                        None,
                         pattern,
                     );
                     self.ongoing_destructuring = Some(OngoingDestructuring {
@@ -367,7 +418,11 @@ impl<'a> LoweringContext<'a> {
                             body.push_reference(nothing);
                         },
                         |body| {
-                            let reason = body.push_text("The value doesn't match the pattern on the left side of the destructuring.".to_string());
+                            // The `NoMatch` tag contains the reason why the
+                            // value doesn't match the pattern.
+                            let tag_get_value = body.push_builtin(BuiltinFunction::TagGetValue);
+                            let reason =
+                                body.push_call(tag_get_value, vec![pattern_result], responsible);
                             body.push_panic(reason, responsible);
                         },
                         responsible,
@@ -580,17 +635,29 @@ impl<'a> LoweringContext<'a> {
                 // `needs`.
                 responsible: responsible_for_needs,
             }),
-            hir::Expression::Needs { condition, reason } => {
+            hir::Expression::Needs {
+                condition,
+                reason,
+                context,
+            } => {
                 let responsible = body.push_hir_id(hir_id.clone());
+                let condition = self.mapping[condition];
+                let mut reason = self.mapping[reason];
+                if let Some(context) = context {
+                    reason = Self::compile_reason_with_context(
+                        body,
+                        hir_id,
+                        condition,
+                        reason,
+                        self.mapping[context],
+                        responsible,
+                    );
+                }
                 self.push_call(
                     body,
                     hir_id,
                     self.needs_function,
-                    vec![
-                        self.mapping[condition],
-                        self.mapping[reason],
-                        responsible_for_needs,
-                    ],
+                    vec![condition, reason, responsible_for_needs],
                     responsible,
                 )
             }
@@ -612,6 +679,12 @@ impl<'a> LoweringContext<'a> {
         }
     }
 
+    /// Compiles a match to a decision tree.
+    ///
+    /// Cases are tried in order, but the tree checks the type of the matched
+    /// value only once for consecutive cases that require different types.
+    /// E.g., for cases matching an int, a list, and an int again, the value's
+    /// type is checked first, and the list pattern is only tried for lists.
     fn compile_match(
         &mut self,
         hir_id: hir::Id,
@@ -621,14 +694,19 @@ impl<'a> LoweringContext<'a> {
         responsible_for_needs: Id,
         responsible_for_match: Id,
     ) -> Id {
+        let cases = cases
+            .iter()
+            .enumerate()
+            .map(|(index, (pattern, body))| (index, pattern, body))
+            .collect_vec();
         self.compile_match_rec(
             hir_id,
             body,
             expression,
-            cases,
+            &cases,
+            None,
             responsible_for_needs,
             responsible_for_match,
-            0,
         )
     }
     #[allow(clippy::too_many_arguments)]
@@ -637,10 +715,10 @@ impl<'a> LoweringContext<'a> {
         hir_id: hir::Id,
         body: &mut BodyBuilder,
         expression: Id,
-        cases: &[(hir::Pattern, hir::Body)],
+        cases: &[(usize, &hir::Pattern, &hir::Body)],
+        known_type: Option<&'static str>,
         responsible_for_needs: Id,
         responsible_for_match: Id,
-        case_index: usize,
     ) -> Id {
         match cases {
             [] => {
@@ -648,12 +726,30 @@ impl<'a> LoweringContext<'a> {
                 // TODO: concat reasons
                 body.push_panic(reason, responsible_for_match)
             }
-            [(case_pattern, case_body), rest @ ..] => {
+            [(case_index, case_pattern, case_body), rest @ ..] => {
+                if known_type.is_none()
+                    && let Some(type_) = case_pattern.required_type()
+                    && rest.iter().any(|(_, pattern, _)| {
+                        pattern.required_type().is_some_and(|it| it != type_)
+                    })
+                {
+                    return self.compile_match_split_by_type(
+                        hir_id,
+                        body,
+                        expression,
+                        cases,
+                        type_,
+                        responsible_for_needs,
+                        responsible_for_match,
+                    );
+                }
+
                 let pattern_result = PatternLoweringContext::check_pattern(
                     body,
                     hir_id.clone(),
                     responsible_for_match,
                     expression,
+                    known_type,
                     case_pattern,
                 );
 
@@ -674,9 +770,9 @@ impl<'a> LoweringContext<'a> {
                         body,
                         expression,
                         rest,
+                        known_type,
                         responsible_for_needs,
                         responsible_for_match,
-                        case_index + 1,
                     );
                 });
                 body.push_call(
@@ -688,6 +784,72 @@ impl<'a> LoweringContext<'a> {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
+    fn compile_match_split_by_type(
+        &mut self,
+        hir_id: hir::Id,
+        body: &mut BodyBuilder,
+        expression: Id,
+        cases: &[(usize, &hir::Pattern, &hir::Body)],
+        type_: &'static str,
+        responsible_for_needs: Id,
+        responsible_for_match: Id,
+    ) -> Id {
+        // Cases requiring a different type can't match in the first
+        // branch, and cases requiring this type can't match in the
+        // second one.
+        let cases_with_type = cases
+            .iter()
+            .filter(|(_, pattern, _)| pattern.required_type().map_or(true, |it| it == type_))
+            .copied()
+            .collect_vec();
+        let cases_without_type = cases
+            .iter()
+            .filter(|(_, pattern, _)| pattern.required_type().map_or(true, |it| it != type_))
+            .copied()
+            .collect_vec();
+
+        let builtin_type_of = body.push_builtin(BuiltinFunction::TypeOf);
+        let actual_type = body.push_call(builtin_type_of, vec![expression], responsible_for_match);
+        let expected_type = body.push_tag(type_.to_string(), None);
+        let builtin_equals = body.push_builtin(BuiltinFunction::Equals);
+        let has_type = body.push_call(
+            builtin_equals,
+            vec![actual_type, expected_type],
+            responsible_for_match,
+        );
+
+        let type_id = hir_id.child(format!("is{type_}"));
+        let builtin_if_else = body.push_builtin(BuiltinFunction::IfElse);
+        let then_function = body.push_function(type_id.child("then"), |body, _| {
+            self.compile_match_rec(
+                hir_id.clone(),
+                body,
+                expression,
+                &cases_with_type,
+                Some(type_),
+                responsible_for_needs,
+                responsible_for_match,
+            );
+        });
+        let else_function = body.push_function(type_id.child("else"), |body, _| {
+            self.compile_match_rec(
+                hir_id,
+                body,
+                expression,
+                &cases_without_type,
+                None,
+                responsible_for_needs,
+                responsible_for_match,
+            );
+        });
+        body.push_call(
+            builtin_if_else,
+            vec![has_type, then_function, else_function],
+            responsible_for_match,
+        )
+    }
+
     fn push_call(
         &self,
         body: &mut BodyBuilder,
@@ -726,6 +888,7 @@ impl hir::Pattern {
         match self {
             Self::NewIdentifier(_) => false,
             Self::Int(_) => true,
+            Self::Float(_) => true,
             Self::Text(_) => true,
             Self::Tag { symbol: _, value } => value.as_ref().map_or(true, |val| val.is_exact()),
             Self::List(items) => items.iter().all(Self::is_exact),
@@ -734,6 +897,27 @@ impl hir::Pattern {
             Self::Error { .. } => true,
         }
     }
+
+    /// The type (as returned by `✨.typeOf`) a value must have to match this
+    /// pattern, if there is one.
+    fn required_type(&self) -> Option<&'static str> {
+        match self {
+            Self::NewIdentifier(_) | Self::Error { .. } => None,
+            Self::Int(_) => Some("Int"),
+            Self::Float(_) => Some("Float"),
+            Self::Text(_) => Some("Text"),
+            Self::Tag { .. } => Some("Tag"),
+            Self::List(_) => Some("List"),
+            Self::Struct(_) => Some("Struct"),
+            Self::Or(patterns) => {
+                let (first, rest) = patterns.split_first()?;
+                let type_ = first.required_type()?;
+                rest.iter()
+                    .all(|pattern| pattern.required_type() == Some(type_))
+                    .then_some(type_)
+            }
+        }
+    }
 }
 
 struct PatternLoweringContext {
@@ -741,15 +925,18 @@ struct PatternLoweringContext {
     match_tag: Id,
     no_match_tag: Id,
     responsible: Id,
+    /// The matched value and its type if a parent already checked it.
+    known_type: Option<(Id, &'static str)>,
 }
 impl PatternLoweringContext {
     /// Checks a pattern and returns an expression of type
-    /// `Match (variable0, …, variableN) | NoMatch`.
+    /// `Match (variable0, …, variableN) | NoMatch reason`.
     fn check_pattern(
         body: &mut BodyBuilder,
         hir_id: hir::Id,
         responsible: Id,
         expression: Id,
+        known_type: Option<&'static str>,
         pattern: &hir::Pattern,
     ) -> Id {
         let match_tag = body.push_match_tag();
@@ -759,6 +946,7 @@ impl PatternLoweringContext {
             match_tag,
             no_match_tag,
             responsible,
+            known_type: known_type.map(|type_| (expression, type_)),
         };
         context.check(body, expression, pattern)
     }
@@ -773,6 +961,7 @@ impl PatternLoweringContext {
             // The unreachable cases will be caught be the pattern.is_exact()
             // check above.
             hir::Pattern::Int(_) => unreachable!(),
+            hir::Pattern::Float(_) => unreachable!(),
             hir::Pattern::Text(_) => unreachable!(),
             hir::Pattern::Tag {
                 symbol: _,
@@ -789,21 +978,41 @@ impl PatternLoweringContext {
                     self.responsible,
                 );
                 let expected_symbol = body.push_tag(symbol.clone(), None);
-                self.check_equals(body, expected_symbol, actual_symbol, |body| {
-                    let builtin_tag_has_value = body.push_builtin(BuiltinFunction::TagHasValue);
-                    let actual_has_value =
-                        body.push_call(builtin_tag_has_value, vec![expression], self.responsible);
-                    let expected_has_value = body.push_bool(true);
-                    self.check_equals(body, expected_has_value, actual_has_value, |body| {
-                        let builtin_tag_get_value = body.push_builtin(BuiltinFunction::TagGetValue);
-                        let actual_value = body.push_call(
-                            builtin_tag_get_value,
+                self.check_equals(
+                    body,
+                    expected_symbol,
+                    actual_symbol,
+                    |body| {
+                        let builtin_tag_has_value = body.push_builtin(BuiltinFunction::TagHasValue);
+                        let actual_has_value = body.push_call(
+                            builtin_tag_has_value,
                             vec![expression],
                             self.responsible,
                         );
-                        self.check(body, actual_value, value);
-                    });
-                });
+                        let expected_has_value = body.push_bool(true);
+                        self.check_equals(
+                            body,
+                            expected_has_value,
+                            actual_has_value,
+                            |body| {
+                                let builtin_tag_get_value =
+                                    body.push_builtin(BuiltinFunction::TagGetValue);
+                                let actual_value = body.push_call(
+                                    builtin_tag_get_value,
+                                    vec![expression],
+                                    self.responsible,
+                                );
+                                self.check(body, actual_value, value);
+                            },
+                            |body| {
+                                body.push_text(format!(
+                                    "Expected the tag `{symbol}` to have a value."
+                                ))
+                            },
+                        );
+                    },
+                    |body| self.push_mismatch_reason(body, expected_symbol, actual_symbol),
+                );
             }),
             hir::Pattern::List(list) => {
                 // Check that it's a list.
@@ -813,27 +1022,43 @@ impl PatternLoweringContext {
                     let builtin_list_length = body.push_builtin(BuiltinFunction::ListLength);
                     let actual_length =
                         body.push_call(builtin_list_length, vec![expression], self.responsible);
-                    self.check_equals(body, expected, actual_length, |body| {
-                        // Destructure the items.
-                        let builtin_list_get = body.push_builtin(BuiltinFunction::ListGet);
-                        let condition_builders = list
-                            .iter()
-                            .enumerate()
-                            .map(|(index, item_pattern)| {
-                                move |body: &mut BodyBuilder| {
-                                    let index = body.push_int(index);
-                                    let item = body.push_call(
-                                        builtin_list_get,
-                                        vec![expression, index],
-                                        self.responsible,
-                                    );
-                                    let result = self.check(body, item, item_pattern);
-                                    (result, item_pattern.captured_identifier_count())
-                                }
-                            })
-                            .collect_vec();
-                        self.check_all(body, condition_builders);
-                    });
+                    let length_reason = |body: &mut BodyBuilder| {
+                        let expected = body.push_text(format!(
+                            "Expected {} {}, got ",
+                            list.len(),
+                            if list.len() == 1 { "item" } else { "items" },
+                        ));
+                        let actual = body.push_to_debug_text(actual_length, self.responsible);
+                        let end = body.push_text(".".to_string());
+                        body.push_text_concatenation(&[expected, actual, end], self.responsible)
+                    };
+                    self.check_equals(
+                        body,
+                        expected,
+                        actual_length,
+                        |body| {
+                            // Destructure the items.
+                            let builtin_list_get = body.push_builtin(BuiltinFunction::ListGet);
+                            let condition_builders = list
+                                .iter()
+                                .enumerate()
+                                .map(|(index, item_pattern)| {
+                                    move |body: &mut BodyBuilder| {
+                                        let index = body.push_int(index);
+                                        let item = body.push_call(
+                                            builtin_list_get,
+                                            vec![expression, index],
+                                            self.responsible,
+                                        );
+                                        let result = self.check(body, item, item_pattern);
+                                        (result, item_pattern.captured_identifier_count())
+                                    }
+                                })
+                                .collect_vec();
+                            self.check_all(body, condition_builders);
+                        },
+                        length_reason,
+                    );
                 })
             }
             hir::Pattern::Struct(struct_) => {
@@ -865,7 +1090,16 @@ impl PatternLoweringContext {
                                         self.check(body, value, value_pattern);
                                     },
                                     |body| {
-                                        self.push_no_match(body);
+                                        let start = body.push_text(
+                                            "Expected the struct to contain the key `".to_string(),
+                                        );
+                                        let key = body.push_to_debug_text(key, self.responsible);
+                                        let end = body.push_text("`.".to_string());
+                                        let reason = body.push_text_concatenation(
+                                            &[start, key, end],
+                                            self.responsible,
+                                        );
+                                        self.push_no_match(body, reason);
                                     },
                                     self.responsible,
                                 );
@@ -937,9 +1171,15 @@ impl PatternLoweringContext {
         expression: Id,
         exact_value: Id,
     ) -> Id {
-        self.check_equals(body, exact_value, expression, |body| {
-            self.push_match(body, vec![]);
-        })
+        self.check_equals(
+            body,
+            exact_value,
+            expression,
+            |body| {
+                self.push_match(body, vec![]);
+            },
+            |body| self.push_mismatch_reason(body, exact_value, expression),
+        )
     }
 
     fn check_type(
@@ -949,18 +1189,36 @@ impl PatternLoweringContext {
         expected_type: String,
         then_builder: impl FnOnce(&mut BodyBuilder),
     ) -> Id {
+        if self
+            .known_type
+            .is_some_and(|(id, type_)| id == expression && type_ == expected_type)
+        {
+            then_builder(body);
+            return body.current_return_value();
+        }
+
+        let reason_start = format!("Expected a {expected_type}, got `");
+        let reason = |body: &mut BodyBuilder| {
+            let start = body.push_text(reason_start);
+            let actual = body.push_to_debug_text(expression, self.responsible);
+            let end = body.push_text("`.".to_string());
+            body.push_text_concatenation(&[start, actual, end], self.responsible)
+        };
         let expected_type = body.push_tag(expected_type, None);
         let builtin_type_of = body.push_builtin(BuiltinFunction::TypeOf);
         let type_ = body.push_call(builtin_type_of, vec![expression], self.responsible);
-        self.check_equals(body, expected_type, type_, then_builder)
+        self.check_equals(body, expected_type, type_, then_builder, reason)
     }
 
+    /// If `expected` and `actual` are not equal, the resulting `NoMatch` tag
+    /// contains the text returned by `reason_builder`.
     fn check_equals(
         &self,
         body: &mut BodyBuilder,
         expected: Id,
         actual: Id,
         then_builder: impl FnOnce(&mut BodyBuilder),
+        reason_builder: impl FnOnce(&mut BodyBuilder) -> Id,
     ) -> Id {
         let builtin_equals = body.push_builtin(BuiltinFunction::Equals);
         let equals = body.push_call(builtin_equals, vec![expected, actual], self.responsible);
@@ -970,17 +1228,28 @@ impl PatternLoweringContext {
             equals,
             then_builder,
             |body| {
-                self.push_no_match(body);
+                let reason = reason_builder(body);
+                self.push_no_match(body, reason);
             },
             self.responsible,
         )
     }
+    /// Compiles to a text like ``Expected `Foo`, got `Bar`.``
+    fn push_mismatch_reason(&self, body: &mut BodyBuilder, expected: Id, actual: Id) -> Id {
+        let start = body.push_text("Expected `".to_string());
+        let expected = body.push_to_debug_text(expected, self.responsible);
+        let middle = body.push_text("`, got `".to_string());
+        let actual = body.push_to_debug_text(actual, self.responsible);
+        let end = body.push_text("`.".to_string());
+        body.push_text_concatenation(&[start, expected, middle, actual, end], self.responsible)
+    }
 
     fn compile_pattern(&self, body: &mut BodyBuilder, pattern: &hir::Pattern) -> Id {
         assert!(pattern.is_exact());
         match pattern {
             hir::Pattern::NewIdentifier(_) => unreachable!(),
             hir::Pattern::Int(int) => body.push_int(int.clone()),
+            hir::Pattern::Float(float) => body.push_float(*float),
             hir::Pattern::Text(text) => body.push_text(text.clone()),
             hir::Pattern::Tag { symbol, value } => {
                 let value = value
@@ -1050,8 +1319,8 @@ impl PatternLoweringContext {
         let captured = body.push_list(captured_identifiers);
         body.push_call(self.match_tag, vec![captured], self.responsible)
     }
-    fn push_no_match(&self, body: &mut BodyBuilder) -> Id {
-        body.push_reference(self.no_match_tag)
+    fn push_no_match(&self, body: &mut BodyBuilder, reason: Id) -> Id {
+        body.push_call(self.no_match_tag, vec![reason], self.responsible)
     }
 }
 
@@ -1063,8 +1332,23 @@ impl BodyBuilder {
         self.push_tag("NoMatch".to_string(), None)
     }
 
-    /// Compiles to code taking a `Match (…,)` or `NoMatch` and returning a
-    /// boolean.
+    fn push_to_debug_text(&mut self, value: Id, responsible: Id) -> Id {
+        let builtin_to_debug_text = self.push_builtin(BuiltinFunction::ToDebugText);
+        self.push_call(builtin_to_debug_text, vec![value], responsible)
+    }
+    fn push_text_concatenation(&mut self, parts: &[Id], responsible: Id) -> Id {
+        let builtin_text_concatenate = self.push_builtin(BuiltinFunction::TextConcatenate);
+        parts
+            .iter()
+            .copied()
+            .reduce(|result, part| {
+                self.push_call(builtin_text_concatenate, vec![result, part], responsible)
+            })
+            .unwrap()
+    }
+
+    /// Compiles to code taking a `Match (…,)` or `NoMatch reason` and
+    /// returning a boolean.
     fn push_is_match(&mut self, match_or_no_match: Id, responsible: Id) -> Id {
         let tag_without_value = self.push_builtin(BuiltinFunction::TagWithoutValue);
         let match_or_no_match_symbol =
@@ -21,6 +21,7 @@
 
 pub use self::tracing::{CallTracingMode, TracingConfig, TracingMode};
 
+pub mod analysis;
 pub mod ast;
 pub mod ast_to_hir;
 pub mod builtin_functions;
@@ -28,20 +29,25 @@ pub mod comment;
 pub mod cst;
 pub mod cst_to_ast;
 pub mod error;
+pub mod error_codes;
 pub mod format;
 pub mod hir;
 pub mod hir_to_mir;
 pub mod id;
+pub mod lint;
 pub mod lir;
 pub mod lir_optimize;
 pub mod mir;
 pub mod mir_optimize;
 pub mod mir_to_lir;
 pub mod module;
+pub mod module_graph;
 pub mod position;
 pub mod rcst;
 pub mod rcst_to_cst;
 pub mod rich_ir;
+pub mod static_panics;
 pub mod string_to_rcst;
+pub mod timings;
 pub mod tracing;
 pub mod utils;
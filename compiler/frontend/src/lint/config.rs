@@ -0,0 +1,139 @@
+use super::{Lint, LintSeverity};
+use crate::{
+    ast::{Assignment, AssignmentBody, Ast, AstKind, Identifier, Int, Struct, Symbol},
+    cst_to_ast::CstToAst,
+    module::{Module, ModuleKind, Package},
+};
+use rustc_hash::FxHashMap;
+use tracing::warn;
+
+/// The lint configuration of a package.
+///
+/// Packages configure lints with a `lints` struct in their `_package.candy`
+/// file. Each lint maps to either a severity (`Off`, `Info`, `Warning`, or
+/// `Error`) or a struct containing the `Severity` and lint-specific options:
+///
+/// ```candy
+/// lints := [
+///   UnusedParameter: Off,
+///   DeepNesting: [Severity: Error, MaxDepth: 3],
+/// ]
+/// ```
+#[derive(Debug, Default)]
+pub struct LintConfig {
+    lints: FxHashMap<String, LintRuleConfig>,
+}
+#[derive(Debug, Default)]
+struct LintRuleConfig {
+    severity: Option<LintSeverity>,
+    options: FxHashMap<String, usize>,
+}
+
+impl LintConfig {
+    #[must_use]
+    pub fn for_package<DB: CstToAst + ?Sized>(db: &DB, package: &Package) -> Self {
+        // Other packages don't have a `_package.candy` file we could read.
+        if !matches!(package, Package::User(_) | Package::Managed(_)) {
+            return Self::default();
+        }
+        let manifest = Module {
+            package: package.clone(),
+            path: vec!["_package".to_string()],
+            kind: ModuleKind::Code,
+        };
+        let Ok((asts, _)) = db.ast(manifest) else {
+            return Self::default();
+        };
+
+        let lints = asts.iter().rev().find_map(|ast| {
+            let AstKind::Assignment(Assignment {
+                body: AssignmentBody::Body { pattern, body },
+                ..
+            }) = &ast.kind
+            else {
+                return None;
+            };
+            let AstKind::Identifier(Identifier(name)) = &pattern.kind else {
+                return None;
+            };
+            if name.value != "lints" {
+                return None;
+            }
+            let AstKind::Struct(Struct { fields }) = &body.last()?.kind else {
+                warn!("The `lints` in `{package}` should be a struct.");
+                return None;
+            };
+            Some(fields)
+        });
+        let Some(lints) = lints else {
+            return Self::default();
+        };
+
+        let mut config = Self::default();
+        for (name, value) in lints {
+            let Some(name) = name.as_ref().and_then(symbol) else {
+                warn!("The `lints` in `{package}` should only contain symbol keys.");
+                continue;
+            };
+            let rule = match &value.kind {
+                AstKind::Struct(Struct { fields }) => LintRuleConfig::from_fields(fields, package),
+                _ => LintRuleConfig {
+                    severity: parse_severity(value, package),
+                    options: FxHashMap::default(),
+                },
+            };
+            config.lints.insert(name.to_string(), rule);
+        }
+        config
+    }
+
+    #[must_use]
+    pub fn severity(&self, lint: &dyn Lint) -> LintSeverity {
+        self.lints
+            .get(lint.name())
+            .and_then(|it| it.severity)
+            .unwrap_or_else(|| lint.default_severity())
+    }
+    #[must_use]
+    pub fn option(&self, lint: &dyn Lint, name: &str) -> Option<usize> {
+        self.lints.get(lint.name())?.options.get(name).copied()
+    }
+}
+
+impl LintRuleConfig {
+    fn from_fields(fields: &[(Option<Ast>, Ast)], package: &Package) -> Self {
+        let mut config = Self::default();
+        for (key, value) in fields {
+            match key.as_ref().and_then(symbol) {
+                Some("Severity") => config.severity = parse_severity(value, package),
+                Some(key) => {
+                    let AstKind::Int(Int(value)) = &value.kind else {
+                        warn!("The lint option `{key}` in `{package}` should be an integer.");
+                        continue;
+                    };
+                    let Ok(value) = usize::try_from(value) else {
+                        warn!("The lint option `{key}` in `{package}` is out of range.");
+                        continue;
+                    };
+                    config.options.insert(key.to_string(), value);
+                }
+                None => warn!("The lint options in `{package}` should only contain symbol keys."),
+            }
+        }
+        config
+    }
+}
+
+fn symbol(ast: &Ast) -> Option<&str> {
+    match &ast.kind {
+        AstKind::Symbol(Symbol(symbol)) => Some(&symbol.value),
+        _ => None,
+    }
+}
+fn parse_severity(ast: &Ast, package: &Package) -> Option<LintSeverity> {
+    let severity = symbol(ast).and_then(LintSeverity::from_symbol);
+    if severity.is_none() {
+        warn!("Lint severities in `{package}` should be `Off`, `Info`, `Warning`, or `Error`.");
+    }
+    severity
+}
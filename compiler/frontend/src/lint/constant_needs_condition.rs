@@ -0,0 +1,53 @@
+use super::{visit_expressions, Lint, LintContext, LintSeverity};
+use crate::hir::Expression;
+
+/// `needs` with a literal condition other than `False`.
+///
+/// `needs True` never panics, and conditions that aren't booleans always
+/// panic. `needs False` is fine because it's the usual way to panic.
+pub struct ConstantNeedsCondition;
+impl Lint for ConstantNeedsCondition {
+    fn name(&self) -> &'static str {
+        "ConstantNeedsCondition"
+    }
+    fn default_severity(&self) -> LintSeverity {
+        LintSeverity::Warning
+    }
+
+    fn check(&self, context: &mut LintContext) {
+        let mut reports = vec![];
+        visit_expressions(context.hir, &mut |expression| {
+            let Expression::Needs { condition, .. } = expression else {
+                return;
+            };
+
+            let mut value = condition;
+            let message =
+                loop {
+                    match context.hir.find(value) {
+                    Some(Expression::Reference(target)) => value = target,
+                    Some(Expression::Symbol(symbol)) if symbol == "True" => {
+                        break "This `needs` is always satisfied.";
+                    }
+                    Some(Expression::Symbol(symbol)) if symbol == "False" => return,
+                    Some(
+                        Expression::Int(_)
+                        | Expression::Float(_)
+                        | Expression::Text(_)
+                        | Expression::Symbol(_)
+                        | Expression::Tag { .. }
+                        | Expression::List(_)
+                        | Expression::Struct(_)
+                        | Expression::Function(_),
+                    ) => break "This `needs` always panics because its condition is not a boolean.",
+                    _ => return,
+                }
+                };
+            reports.push((condition.clone(), message));
+        });
+
+        for (id, message) in reports {
+            context.report_hir(&id, message);
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use super::{Lint, LintContext, LintSeverity};
+use crate::hir::{self, Body, Expression, FunctionKind};
+
+/// Functions and matches that are nested more deeply than the `MaxDepth`
+/// option allows.
+pub struct DeepNesting;
+impl DeepNesting {
+    const DEFAULT_MAX_DEPTH: usize = 5;
+}
+impl Lint for DeepNesting {
+    fn name(&self) -> &'static str {
+        "DeepNesting"
+    }
+    fn default_severity(&self) -> LintSeverity {
+        LintSeverity::Warning
+    }
+
+    fn check(&self, context: &mut LintContext) {
+        let max_depth = context
+            .option("MaxDepth")
+            .unwrap_or(Self::DEFAULT_MAX_DEPTH);
+        let mut too_deep = vec![];
+        check_body(context.hir, 0, max_depth, &mut too_deep);
+        for id in too_deep {
+            context.report_hir(
+                &id,
+                format!("This is nested more than {max_depth} levels deep. Consider extracting it into a separate function."),
+            );
+        }
+    }
+}
+
+fn check_body(body: &Body, depth: usize, max_depth: usize, too_deep: &mut Vec<hir::Id>) {
+    for (id, expression) in &body.expressions {
+        let bodies = match expression {
+            Expression::Match { cases, .. } => cases.iter().map(|(_, body)| body).collect(),
+            Expression::Function(function) if function.kind != FunctionKind::Use => {
+                vec![&function.body]
+            }
+            _ => continue,
+        };
+        if depth == max_depth {
+            // Only report the outermost expression that's too deep.
+            too_deep.push(id.clone());
+            continue;
+        }
+        for body in bodies {
+            check_body(body, depth + 1, max_depth, too_deep);
+        }
+    }
+}
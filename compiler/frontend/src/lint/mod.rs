@@ -0,0 +1,255 @@
+//! Lints find code that is valid but likely not what the author intended, such
+//! as unused parameters.
+//!
+//! Each lint implements [`Lint`] and runs over the AST or HIR of a module.
+//! Packages can
+//! configure the lints in their `_package.candy` file (see [`LintConfig`]).
+
+pub use self::config::LintConfig;
+use self::{
+    constant_needs_condition::ConstantNeedsCondition, deep_nesting::DeepNesting,
+    shadowed_binding::ShadowedBinding, unreachable_match_case::UnreachableMatchCase,
+    unused_parameter::UnusedParameter,
+};
+use crate::{
+    ast::{self, Ast},
+    ast_to_hir::AstToHir,
+    hir::{self, Body, Expression},
+    module::Module,
+    position::Offset,
+};
+use std::{
+    fmt::{self, Display, Formatter},
+    ops::Range,
+};
+
+mod config;
+mod constant_needs_condition;
+mod deep_nesting;
+mod shadowed_binding;
+mod unreachable_match_case;
+mod unused_parameter;
+
+pub const LINTS: &[&dyn Lint] = &[
+    &UnusedParameter,
+    &ShadowedBinding,
+    &ConstantNeedsCondition,
+    &DeepNesting,
+    &UnreachableMatchCase,
+];
+
+pub trait Lint: Sync {
+    /// The name used to refer to this lint in the configuration, e.g.,
+    /// `UnusedParameter`.
+    fn name(&self) -> &'static str;
+    fn default_severity(&self) -> LintSeverity;
+    fn check(&self, context: &mut LintContext);
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum LintSeverity {
+    Off,
+    Info,
+    Warning,
+    Error,
+}
+impl LintSeverity {
+    #[must_use]
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        match symbol {
+            "Off" => Some(Self::Off),
+            "Info" => Some(Self::Info),
+            "Warning" => Some(Self::Warning),
+            "Error" => Some(Self::Error),
+            _ => None,
+        }
+    }
+}
+impl Display for LintSeverity {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Off => write!(f, "off"),
+            Self::Info => write!(f, "info"),
+            Self::Warning => write!(f, "warning"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+pub struct LintContext<'a> {
+    pub ast: &'a [Ast],
+    pub hir: &'a Body,
+    db: &'a dyn AstToHir,
+    lint: &'a dyn Lint,
+    config: &'a LintConfig,
+    reports: Vec<(Range<Offset>, String)>,
+}
+impl<'a> LintContext<'a> {
+    /// Returns an option of the current lint from the configuration, e.g.,
+    /// `MaxDepth` of `DeepNesting`.
+    #[must_use]
+    pub fn option(&self, name: &str) -> Option<usize> {
+        self.config.option(self.lint, name)
+    }
+
+    pub fn report_ast(&mut self, id: &ast::Id, message: impl Into<String>) {
+        if let Some(span) = self.db.ast_id_to_display_span(id) {
+            self.reports.push((span, message.into()));
+        }
+    }
+    pub fn report_hir(&mut self, id: &hir::Id, message: impl Into<String>) {
+        if let Some(span) = self.db.hir_id_to_display_span(id) {
+            self.reports.push((span, message.into()));
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct LintViolation {
+    pub lint: &'static str,
+    pub severity: LintSeverity,
+    pub module: Module,
+    pub span: Range<Offset>,
+    pub message: String,
+}
+
+/// Runs all enabled lints on the module, sorted by their position.
+#[must_use]
+pub fn lint_module(db: &dyn AstToHir, module: &Module) -> Vec<LintViolation> {
+    let (Ok((ast, _)), Ok((hir, _))) = (db.ast(module.clone()), db.hir(module.clone())) else {
+        return vec![];
+    };
+    let config = LintConfig::for_package(db, &module.package);
+
+    let mut violations = vec![];
+    for lint in LINTS {
+        let severity = config.severity(*lint);
+        if severity == LintSeverity::Off {
+            continue;
+        }
+
+        let mut context = LintContext {
+            ast: &ast,
+            hir: &hir,
+            db,
+            lint: *lint,
+            config: &config,
+            reports: vec![],
+        };
+        lint.check(&mut context);
+        violations.extend(
+            context
+                .reports
+                .into_iter()
+                .map(|(span, message)| LintViolation {
+                    lint: lint.name(),
+                    severity,
+                    module: module.clone(),
+                    span,
+                    message,
+                }),
+        );
+    }
+    violations.sort_by_key(|it| (it.span.start, it.span.end));
+    violations
+}
+
+/// Calls `visit` for all expressions in `body` and the bodies nested inside
+/// it, except for the generated `use` function.
+fn visit_expressions(body: &Body, visit: &mut impl FnMut(&Expression)) {
+    for expression in body.expressions.values() {
+        visit(expression);
+        match expression {
+            Expression::Match { cases, .. } => {
+                for (_, body) in cases {
+                    visit_expressions(body, visit);
+                }
+            }
+            Expression::Function(function) if function.kind != hir::FunctionKind::Use => {
+                visit_expressions(&function.body, visit);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LintSeverity;
+    use crate::{analysis::Analysis, module::PackagesPath};
+    use std::{fs, path::Path};
+
+    #[test]
+    fn lints_respect_package_configuration() {
+        let packages_path = PackagesPath::try_from(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("../../packages")
+                .as_path(),
+        )
+        .unwrap();
+        let package = std::env::temp_dir().join("candy_lint_test");
+        fs::create_dir_all(&package).unwrap();
+        fs::write(
+            package.join("_package.candy"),
+            "lints := [\n  ShadowedBinding: Error,\n  UnusedParameter: Off,\n  DeepNesting: [Severity: Info, MaxDepth: 1],\n]\n",
+        )
+        .unwrap();
+        let file = package.join("_.candy");
+        fs::write(&file, "foo a b =\n  needs True\n  c = { a -> a }\n  c b\n").unwrap();
+
+        let analysis = Analysis::new(packages_path, &file).unwrap();
+        let lints = analysis
+            .lints()
+            .into_iter()
+            .map(|it| (it.lint, it.severity))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lints,
+            vec![
+                ("ConstantNeedsCondition", LintSeverity::Warning),
+                ("DeepNesting", LintSeverity::Info),
+                ("ShadowedBinding", LintSeverity::Error),
+            ],
+        );
+    }
+
+    #[test]
+    fn reports_unreachable_match_cases() {
+        let packages_path = PackagesPath::try_from(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("../../packages")
+                .as_path(),
+        )
+        .unwrap();
+        let package = std::env::temp_dir().join("candy_unreachable_match_case_test");
+        fs::create_dir_all(&package).unwrap();
+        let file = package.join("_.candy");
+        fs::write(
+            &file,
+            "foo value = value %\n  1 -> A\n  1 -> B\n  2 | 3 -> C\n  x -> x\n  4 -> D\n",
+        )
+        .unwrap();
+
+        let analysis = Analysis::new(packages_path, &file).unwrap();
+        let lints = analysis
+            .lints()
+            .into_iter()
+            .map(|it| (it.lint, it.message))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lints,
+            vec![
+                (
+                    "UnreachableMatchCase",
+                    "This case is unreachable because earlier cases match the same values."
+                        .to_string(),
+                ),
+                (
+                    "UnreachableMatchCase",
+                    "This case is unreachable because an earlier case matches all values."
+                        .to_string(),
+                ),
+            ],
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use super::{Lint, LintContext, LintSeverity};
+use crate::ast::{
+    self, Assignment, AssignmentBody, Ast, AstKind, Call, Function, Identifier, List, Match,
+    MatchCase, OrPattern, Struct, StructAccess, Text,
+};
+use rustc_hash::FxHashSet;
+
+/// Bindings (assignments, parameters, and identifiers in patterns) that have
+/// the same name as an earlier binding that's still visible.
+///
+/// Names starting with an underscore are not reported.
+pub struct ShadowedBinding;
+impl Lint for ShadowedBinding {
+    fn name(&self) -> &'static str {
+        "ShadowedBinding"
+    }
+    fn default_severity(&self) -> LintSeverity {
+        // Shadowing, e.g., a module by a parameter of the same name is common.
+        LintSeverity::Info
+    }
+
+    fn check(&self, context: &mut LintContext) {
+        let mut shadowing = vec![];
+        check_body(context.ast, FxHashSet::default(), &mut shadowing);
+        for (id, name) in shadowing {
+            context.report_ast(&id, format!("`{name}` shadows an earlier binding."));
+        }
+    }
+}
+
+type Shadowing = Vec<(ast::Id, String)>;
+
+fn check_body(asts: &[Ast], mut visible: FxHashSet<String>, shadowing: &mut Shadowing) {
+    for ast in asts {
+        check_expression(ast, &mut visible, shadowing);
+    }
+}
+fn check_expression(ast: &Ast, visible: &mut FxHashSet<String>, shadowing: &mut Shadowing) {
+    match &ast.kind {
+        AstKind::Int(_)
+        | AstKind::Float(_)
+        | AstKind::TextPart(_)
+        | AstKind::Identifier(_)
+        | AstKind::Symbol(_)
+        | AstKind::MatchCase(_)
+        | AstKind::OrPattern(_)
+        | AstKind::Error { .. } => {}
+        AstKind::Text(Text(parts)) => {
+            for part in parts {
+                check_expression(part, visible, shadowing);
+            }
+        }
+        AstKind::List(List(items)) => {
+            for item in items {
+                check_expression(item, visible, shadowing);
+            }
+        }
+        AstKind::Struct(Struct { fields }) => {
+            for (key, value) in fields {
+                if let Some(key) = key {
+                    check_expression(key, visible, shadowing);
+                }
+                check_expression(value, visible, shadowing);
+            }
+        }
+        AstKind::StructAccess(StructAccess { struct_, .. }) => {
+            check_expression(struct_, visible, shadowing);
+        }
+        AstKind::Function(function) => check_function(function, visible.clone(), shadowing),
+        AstKind::Call(Call {
+            receiver,
+            arguments,
+            ..
+        }) => {
+            check_expression(receiver, visible, shadowing);
+            for argument in arguments {
+                check_expression(argument, visible, shadowing);
+            }
+        }
+        AstKind::Assignment(Assignment { body, .. }) => match body {
+            AssignmentBody::Function { name, function } => {
+                bind(&name.id, name, visible, shadowing);
+                check_function(function, visible.clone(), shadowing);
+            }
+            AssignmentBody::Body { pattern, body } => {
+                check_body(body, visible.clone(), shadowing);
+                bind_pattern(pattern, visible, shadowing);
+            }
+        },
+        AstKind::Match(Match { expression, cases }) => {
+            check_expression(expression, visible, shadowing);
+            for case in cases {
+                let AstKind::MatchCase(MatchCase { pattern, body }) = &case.kind else {
+                    continue;
+                };
+                let mut inner_visible = visible.clone();
+                bind_pattern(pattern, &mut inner_visible, shadowing);
+                check_body(body, inner_visible, shadowing);
+            }
+        }
+    }
+}
+fn check_function(function: &Function, mut visible: FxHashSet<String>, shadowing: &mut Shadowing) {
+    for parameter in &function.parameters {
+        bind_pattern(parameter, &mut visible, shadowing);
+    }
+    check_body(&function.body, visible, shadowing);
+}
+
+fn bind_pattern(pattern: &Ast, visible: &mut FxHashSet<String>, shadowing: &mut Shadowing) {
+    match &pattern.kind {
+        AstKind::Identifier(Identifier(name)) => bind(&pattern.id, name, visible, shadowing),
+        AstKind::List(List(items)) => {
+            for item in items {
+                bind_pattern(item, visible, shadowing);
+            }
+        }
+        AstKind::Struct(Struct { fields }) => {
+            for (_, value) in fields {
+                bind_pattern(value, visible, shadowing);
+            }
+        }
+        AstKind::Call(Call { arguments, .. }) => {
+            for argument in arguments {
+                bind_pattern(argument, visible, shadowing);
+            }
+        }
+        // All alternatives bind the same identifiers.
+        AstKind::OrPattern(OrPattern(patterns)) => {
+            if let Some(pattern) = patterns.first() {
+                bind_pattern(pattern, visible, shadowing);
+            }
+        }
+        _ => {}
+    }
+}
+fn bind(id: &ast::Id, name: &str, visible: &mut FxHashSet<String>, shadowing: &mut Shadowing) {
+    if !visible.insert(name.to_string()) && !name.starts_with('_') {
+        shadowing.push((id.clone(), name.to_string()));
+    }
+}
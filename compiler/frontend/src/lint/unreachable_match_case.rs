@@ -0,0 +1,155 @@
+use super::{Lint, LintContext, LintSeverity};
+use crate::ast::{
+    self, Assignment, AssignmentBody, Ast, AstKind, Call, Function, Int, List, Match, MatchCase,
+    OrPattern, Struct, StructAccess, Symbol, Text, TextPart,
+};
+use itertools::Itertools;
+use num_bigint::BigInt;
+use rustc_hash::FxHashSet;
+use std::slice;
+
+/// Match cases that can never match because an earlier case matches all
+/// values or the same literal.
+///
+/// Whether a match handles all values can only be determined if the matched
+/// value is known at compile time, in which case the optimized code panics
+/// statically.
+pub struct UnreachableMatchCase;
+impl Lint for UnreachableMatchCase {
+    fn name(&self) -> &'static str {
+        "UnreachableMatchCase"
+    }
+    fn default_severity(&self) -> LintSeverity {
+        LintSeverity::Warning
+    }
+
+    fn check(&self, context: &mut LintContext) {
+        let mut unreachable = vec![];
+        check_body(context.ast, &mut unreachable);
+        for (id, message) in unreachable {
+            context.report_ast(&id, message);
+        }
+    }
+}
+
+type Unreachable = Vec<(ast::Id, &'static str)>;
+
+fn check_body(asts: &[Ast], unreachable: &mut Unreachable) {
+    for ast in asts {
+        check_expression(ast, unreachable);
+    }
+}
+fn check_expression(ast: &Ast, unreachable: &mut Unreachable) {
+    match &ast.kind {
+        AstKind::Int(_)
+        | AstKind::Float(_)
+        | AstKind::TextPart(_)
+        | AstKind::Identifier(_)
+        | AstKind::Symbol(_)
+        | AstKind::MatchCase(_)
+        | AstKind::OrPattern(_)
+        | AstKind::Error { .. } => {}
+        AstKind::Text(Text(parts)) => check_body(parts, unreachable),
+        AstKind::List(List(items)) => check_body(items, unreachable),
+        AstKind::Struct(Struct { fields }) => {
+            for (key, value) in fields {
+                if let Some(key) = key {
+                    check_expression(key, unreachable);
+                }
+                check_expression(value, unreachable);
+            }
+        }
+        AstKind::StructAccess(StructAccess { struct_, .. }) => {
+            check_expression(struct_, unreachable);
+        }
+        AstKind::Function(Function { body, .. }) => check_body(body, unreachable),
+        AstKind::Call(Call {
+            receiver,
+            arguments,
+            ..
+        }) => {
+            check_expression(receiver, unreachable);
+            check_body(arguments, unreachable);
+        }
+        AstKind::Assignment(Assignment { body, .. }) => match body {
+            AssignmentBody::Function { function, .. } => check_body(&function.body, unreachable),
+            AssignmentBody::Body { body, .. } => check_body(body, unreachable),
+        },
+        AstKind::Match(Match { expression, cases }) => {
+            check_expression(expression, unreachable);
+            check_cases(cases, unreachable);
+            for case in cases {
+                if let AstKind::MatchCase(MatchCase { body, .. }) = &case.kind {
+                    check_body(body, unreachable);
+                }
+            }
+        }
+    }
+}
+
+fn check_cases(cases: &[Ast], unreachable: &mut Unreachable) {
+    let mut matched_literals = FxHashSet::default();
+    let mut all_values_matched = false;
+    for case in cases {
+        let AstKind::MatchCase(MatchCase { pattern, .. }) = &case.kind else {
+            continue;
+        };
+
+        if all_values_matched {
+            unreachable.push((
+                pattern.id.clone(),
+                "This case is unreachable because an earlier case matches all values.",
+            ));
+            continue;
+        }
+
+        let alternatives = match &pattern.kind {
+            AstKind::OrPattern(OrPattern(alternatives)) => alternatives.as_slice(),
+            _ => slice::from_ref(&**pattern),
+        };
+        if alternatives
+            .iter()
+            .any(|it| matches!(it.kind, AstKind::Identifier(_)))
+        {
+            all_values_matched = true;
+            continue;
+        }
+
+        let literals = alternatives.iter().map(Literal::from_pattern).collect_vec();
+        if literals
+            .iter()
+            .all(|it| it.as_ref().is_some_and(|it| matched_literals.contains(it)))
+        {
+            unreachable.push((
+                pattern.id.clone(),
+                "This case is unreachable because earlier cases match the same values.",
+            ));
+            continue;
+        }
+        matched_literals.extend(literals.into_iter().flatten());
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+enum Literal {
+    Int(BigInt),
+    Text(String),
+    Symbol(String),
+}
+impl Literal {
+    fn from_pattern(pattern: &Ast) -> Option<Self> {
+        match &pattern.kind {
+            AstKind::Int(Int(int)) => Some(Self::Int(int.clone())),
+            AstKind::Text(Text(parts)) => parts
+                .iter()
+                .map(|part| match &part.kind {
+                    AstKind::TextPart(TextPart(text)) => Some(text.value.as_str()),
+                    _ => None,
+                })
+                .collect::<Option<String>>()
+                .map(Self::Text),
+            AstKind::Symbol(Symbol(symbol)) => Some(Self::Symbol(symbol.value.clone())),
+            _ => None,
+        }
+    }
+}
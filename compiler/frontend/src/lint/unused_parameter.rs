@@ -0,0 +1,51 @@
+use super::{visit_expressions, Lint, LintContext, LintSeverity};
+use crate::hir::{Expression, Function};
+use rustc_hash::FxHashSet;
+
+/// Parameters that the function body never references.
+///
+/// Parameters that destructure their argument using a pattern and parameters
+/// starting with an underscore are not reported.
+pub struct UnusedParameter;
+impl Lint for UnusedParameter {
+    fn name(&self) -> &'static str {
+        "UnusedParameter"
+    }
+    fn default_severity(&self) -> LintSeverity {
+        LintSeverity::Warning
+    }
+
+    fn check(&self, context: &mut LintContext) {
+        let mut unused = vec![];
+        visit_expressions(context.hir, &mut |expression| {
+            let Expression::Function(Function {
+                parameters, body, ..
+            }) = expression
+            else {
+                return;
+            };
+
+            let mut referenced = vec![];
+            for expression in body.expressions.values() {
+                expression.collect_all_ids(&mut referenced);
+            }
+            let referenced = referenced.into_iter().collect::<FxHashSet<_>>();
+            for parameter in parameters {
+                if referenced.contains(parameter) {
+                    continue;
+                }
+                let Some(name) = body.identifiers.get(parameter) else {
+                    continue;
+                };
+                if name.starts_with('_') {
+                    continue;
+                }
+                unused.push((parameter.clone(), name.clone()));
+            }
+        });
+
+        for (parameter, name) in unused {
+            context.report_hir(&parameter, format!("The parameter `{name}` is never used."));
+        }
+    }
+}
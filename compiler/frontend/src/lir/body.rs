@@ -122,7 +122,15 @@ pub struct Body {
     original_hirs: FxHashSet<hir::Id>,
     captured_count: usize,
     parameter_count: usize,
+    /// Whether the VM may cache results of calls to this function.
+    is_memoized: bool,
     expressions: Vec<Expression>,
+    /// For each expression, the HIR ID of the source code it originates from
+    /// (if known).
+    ///
+    /// This is best-effort: Optimizations can merge code from different
+    /// places, so an expression's origin is only a hint for tooling.
+    origins: Vec<Option<hir::Id>>,
 }
 impl Body {
     #[must_use]
@@ -130,12 +138,15 @@ impl Body {
         original_hirs: FxHashSet<hir::Id>,
         captured_count: usize,
         parameter_count: usize,
+        is_memoized: bool,
     ) -> Self {
         Self {
             original_hirs,
             captured_count,
             parameter_count,
+            is_memoized,
             expressions: vec![],
+            origins: vec![],
         }
     }
 
@@ -165,6 +176,11 @@ impl Body {
         Id::from_usize(self.captured_count + self.parameter_count)
     }
 
+    #[must_use]
+    pub const fn is_memoized(&self) -> bool {
+        self.is_memoized
+    }
+
     #[must_use]
     pub fn expressions(&self) -> &[Expression] {
         &self.expressions
@@ -201,8 +217,19 @@ impl Body {
         self.captured_count + self.parameter_count + 1
     }
 
+    /// The HIR ID of the source code that the expression originates from.
+    #[must_use]
+    pub fn origin(&self, id: Id) -> Option<&hir::Id> {
+        let index = id.to_usize().checked_sub(self.expression_id_offset())?;
+        self.origins.get(index)?.as_ref()
+    }
+
     pub fn push(&mut self, expression: Expression) -> Id {
+        self.push_with_origin(expression, None)
+    }
+    pub fn push_with_origin(&mut self, expression: Expression, origin: Option<hir::Id>) -> Id {
         self.expressions.push(expression);
+        self.origins.push(origin);
         self.last_expression_id().unwrap()
     }
 
@@ -238,6 +265,11 @@ impl Body {
         }
         builder.push_newline();
 
+        if self.is_memoized {
+            builder.push("# Memoized", TokenType::Comment, EnumSet::empty());
+            builder.push_newline();
+        }
+
         builder.push_custom_multiline(self.ids_and_expressions(), |builder, (id, expression)| {
             let range = builder.push(id.to_string(), TokenType::Variable, EnumSet::empty());
             builder.push_definition(*id, range);
@@ -10,8 +10,13 @@ use derive_more::{From, TryInto};
 use enumset::EnumSet;
 use itertools::Itertools;
 use num_bigint::BigInt;
+use ordered_float::OrderedFloat;
 use rustc_hash::FxHashMap;
-use std::fmt::{self, Debug, Display, Formatter};
+use std::{
+    fmt::{self, Debug, Display, Formatter},
+    hash::{Hash, Hasher},
+    mem,
+};
 use strum_macros::EnumIs;
 
 // ID
@@ -56,22 +61,33 @@ impl ToRichIr for ConstantId {
 
 // Constants
 
+/// Identical constants are only stored once, even if they originate from
+/// different modules.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
-pub struct Constants(Vec<Constant>);
+pub struct Constants {
+    constants: Vec<Constant>,
+    ids: FxHashMap<Constant, ConstantId>,
+}
 
 impl Constants {
     #[must_use]
     pub fn get(&self, id: ConstantId) -> &Constant {
-        &self.0[id.to_usize()]
+        &self.constants[id.to_usize()]
     }
+    /// Returns the ID of an identical constant if there already is one.
     pub fn push(&mut self, constant: impl Into<Constant>) -> ConstantId {
-        let id = ConstantId::from_usize(self.0.len());
-        self.0.push(constant.into());
-        id
+        *self
+            .ids
+            .entry(constant.into())
+            .or_insert_with_key(|constant| {
+                let id = ConstantId::from_usize(self.constants.len());
+                self.constants.push(constant.clone());
+                id
+            })
     }
 
     pub fn ids_and_constants(&self) -> impl Iterator<Item = (ConstantId, &Constant)> {
-        self.0
+        self.constants
             .iter()
             .enumerate()
             .map(|(index, it)| (ConstantId(index), it))
@@ -90,10 +106,10 @@ impl ToRichIr for Constants {
 
 // Constant
 
-// TODO: `impl Hash for Constant`
 #[derive(Clone, Debug, EnumIs, Eq, From, PartialEq, TryInto)]
 pub enum Constant {
     Int(BigInt),
+    Float(OrderedFloat<f64>),
     Text(String),
     Tag {
         symbol: String,
@@ -106,6 +122,32 @@ pub enum Constant {
     Function(BodyId),
 }
 
+impl Hash for Constant {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+        match self {
+            Self::Int(int) => int.hash(state),
+            Self::Float(float) => float.hash(state),
+            Self::Text(text) => text.hash(state),
+            Self::Tag { symbol, value } => {
+                symbol.hash(state);
+                value.hash(state);
+            }
+            Self::Builtin(builtin) => builtin.hash(state),
+            Self::List(items) => items.hash(state),
+            Self::Struct(fields) => {
+                // The iteration order of hash maps is arbitrary.
+                fields.len().hash(state);
+                for field in fields.iter().sorted() {
+                    field.hash(state);
+                }
+            }
+            Self::HirId(id) => id.hash(state),
+            Self::Function(body_id) => body_id.hash(state),
+        }
+    }
+}
+
 impl Constant {
     pub fn build_rich_ir_with_constants(
         &self,
@@ -117,6 +159,9 @@ impl Constant {
             Self::Int(int) => {
                 int.build_rich_ir(builder);
             }
+            Self::Float(float) => {
+                float.build_rich_ir(builder);
+            }
             Self::Text(text) => {
                 let range = builder.push(format!(r#""{text}""#), TokenType::Text, EnumSet::empty());
                 builder.push_reference(text.clone(), range);
@@ -146,8 +191,9 @@ impl Constant {
             }
             Self::Struct(fields) => {
                 builder.push("[", None, EnumSet::empty());
+                // The iteration order of hash maps is arbitrary.
                 builder.push_children_custom(
-                    fields.iter().collect_vec(),
+                    fields.iter().sorted().collect_vec(),
                     |builder, (key, value)| {
                         key.build_rich_ir_with_constants(builder, constants);
                         builder.push(": ", None, EnumSet::empty());
@@ -176,3 +222,24 @@ impl ToRichIr for Constant {
         self.build_rich_ir_with_constants(builder, None);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{Constant, Constants};
+    use num_bigint::BigInt;
+    use std::iter;
+
+    #[test]
+    fn identical_constants_are_deduplicated() {
+        let mut constants = Constants::default();
+        let one = constants.push(BigInt::from(1));
+        let foo = constants.push("Foo".to_string());
+        assert_eq!(constants.push(BigInt::from(1)), one);
+        assert_ne!(constants.push(BigInt::from(2)), one);
+
+        let first = constants.push(Constant::Struct(iter::once((foo, one)).collect()));
+        let second = constants.push(Constant::Struct(iter::once((foo, one)).collect()));
+        assert_eq!(first, second);
+        assert_eq!(constants.ids_and_constants().count(), 4);
+    }
+}
@@ -24,8 +24,13 @@
 //! reference counted anyways.
 
 pub use self::{body::*, constant::*, expression::*, id::*};
-use crate::rich_ir::{RichIrBuilder, ToRichIr, TokenType};
+use crate::{
+    module::Module,
+    rich_ir::{RichIrBuilder, ToRichIr, TokenType},
+};
 use enumset::EnumSet;
+use itertools::Itertools;
+use rustc_hash::FxHashMap;
 
 mod body;
 mod constant;
@@ -53,6 +58,42 @@ impl Lir {
     pub const fn bodies(&self) -> &Bodies {
         &self.bodies
     }
+
+    /// How much code originates from each module, based on the original HIR
+    /// IDs of the bodies. A body that originates from several modules counts
+    /// towards each of them.
+    #[must_use]
+    pub fn code_size_by_module(&self) -> FxHashMap<Module, ModuleCodeSize> {
+        let mut bodies_by_origin = FxHashMap::<_, usize>::default();
+        for (_, body) in self.bodies.ids_and_bodies() {
+            let origin = body.original_hirs().iter().sorted().collect_vec();
+            *bodies_by_origin.entry(origin).or_default() += 1;
+        }
+
+        let mut sizes = FxHashMap::<Module, ModuleCodeSize>::default();
+        for (_, body) in self.bodies.ids_and_bodies() {
+            let origin = body.original_hirs().iter().sorted().collect_vec();
+            let is_duplicated = bodies_by_origin[&origin] > 1;
+            for module in origin.iter().map(|id| &id.module).unique() {
+                let size = sizes.entry(module.clone()).or_default();
+                size.bodies += 1;
+                if is_duplicated {
+                    size.duplicated_bodies += 1;
+                }
+                size.expressions += body.expressions().len();
+            }
+        }
+        sizes
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ModuleCodeSize {
+    pub bodies: usize,
+    /// Bodies with the same original HIR IDs as another body, e.g., because
+    /// the same function was inlined in several places.
+    pub duplicated_bodies: usize,
+    pub expressions: usize,
 }
 
 impl ToRichIr for Lir {
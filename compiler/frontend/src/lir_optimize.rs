@@ -1,17 +1,31 @@
 use crate::{
     hir_to_mir::ExecutionTarget,
-    lir::{Bodies, Body, Expression, Id, Lir},
+    lir::{Bodies, Body, BodyId, Expression, Id, Lir},
     mir_to_lir::{LirResult, MirToLir},
+    module::Module,
+    position::Offset,
+    timings::{self, Stage},
     utils::{HashMapExtension, HashSetExtension},
     TracingConfig,
 };
 use itertools::Itertools;
 use rustc_hash::{FxHashMap, FxHashSet};
-use std::{collections::hash_map::Entry, sync::Arc};
+use std::{collections::hash_map::Entry, ops::Range, sync::Arc};
 
 #[salsa::query_group(OptimizeLirStorage)]
 pub trait OptimizeLir: MirToLir {
     fn optimized_lir(&self, target: ExecutionTarget, tracing: TracingConfig) -> LirResult;
+
+    /// A best-effort source span of an expression in the optimized LIR, e.g.,
+    /// for showing where a panic originates.
+    #[salsa::transparent]
+    fn lir_expression_span(
+        &self,
+        target: ExecutionTarget,
+        tracing: TracingConfig,
+        body_id: BodyId,
+        id: Id,
+    ) -> Option<(Module, Range<Offset>)>;
 }
 
 #[allow(clippy::needless_pass_by_value)]
@@ -20,24 +34,46 @@ fn optimized_lir(
     target: ExecutionTarget,
     tracing: TracingConfig,
 ) -> LirResult {
-    let (lir, errors) = db.lir(target, tracing)?;
-
-    let mut bodies = Bodies::default();
-    for (id, body) in lir.bodies().ids_and_bodies() {
-        let new_id = bodies.push(body.optimize());
-        assert_eq!(id, new_id);
-    }
+    let (lir, errors) = db.lir(target.clone(), tracing)?;
 
-    let optimized_lir = Lir::new(lir.constants().clone(), bodies);
+    let optimized_lir =
+        timings::record_stage(Stage::LirOptimization, target.module().clone(), || {
+            let mut bodies = Bodies::default();
+            for (id, body) in lir.bodies().ids_and_bodies() {
+                let new_id = bodies.push(body.optimize());
+                assert_eq!(id, new_id);
+            }
+            Lir::new(lir.constants().clone(), bodies)
+        });
     Ok((Arc::new(optimized_lir), errors))
 }
 
+#[allow(clippy::needless_pass_by_value)]
+fn lir_expression_span(
+    db: &dyn OptimizeLir,
+    target: ExecutionTarget,
+    tracing: TracingConfig,
+    body_id: BodyId,
+    id: Id,
+) -> Option<(Module, Range<Offset>)> {
+    let (lir, _) = db.optimized_lir(target, tracing).ok()?;
+    let body = lir.bodies().get(body_id);
+    // Expressions without a known origin, such as reference counting, belong
+    // to the function as a whole.
+    let hir_id = body
+        .origin(id)
+        .or_else(|| body.original_hirs().iter().min())?;
+    let span = db.hir_id_to_best_effort_span(hir_id)?;
+    Some((hir_id.module.clone(), span))
+}
+
 impl Body {
     fn optimize(&self) -> Self {
         let mut new_body = Self::new(
             self.original_hirs().clone(),
             self.captured_count(),
             self.parameter_count(),
+            self.is_memoized(),
         );
         let mut id_mapping = FxHashMap::default();
 
@@ -86,7 +122,7 @@ impl Body {
 
             let mut new_expression = old_expression.clone();
             new_expression.replace_ids(|id| self.get_new_id(&id_mapping, id));
-            let id = new_body.push(new_expression);
+            let id = new_body.push_with_origin(new_expression, self.origin(old_id).cloned());
             id_mapping.force_insert(old_id, id);
             new_body.maybe_dup(&mut to_dup, old_id, &id_mapping);
         }
@@ -100,7 +136,7 @@ impl Body {
         // Returned expression
         let mut new_expression = self.expression(return_expression_id).unwrap().clone();
         new_expression.replace_ids(|id| self.get_new_id(&id_mapping, id));
-        new_body.push(new_expression);
+        new_body.push_with_origin(new_expression, self.origin(return_expression_id).cloned());
 
         new_body
     }
@@ -153,3 +189,96 @@ impl Body {
         (to_dup, to_drop)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast::AstDbStorage,
+        ast_to_hir::AstToHirStorage,
+        cst::CstDbStorage,
+        cst_to_ast::CstToAstStorage,
+        hir::HirDbStorage,
+        hir_to_mir::HirToMirStorage,
+        mir_optimize::{MirPassesOwner, OptimizeMir, OptimizeMirStorage},
+        mir_to_lir::MirToLirStorage,
+        module::{
+            GetModuleContentQuery, InMemoryModuleProvider, ModuleDbStorage, ModuleKind,
+            ModuleProvider, ModuleProviderOwner, MutableModuleProviderOwner, Package,
+        },
+        position::PositionConversionStorage,
+        rcst_to_cst::RcstToCstStorage,
+        rich_ir::ToRichIr,
+        string_to_rcst::{RcstCacheOwner, StringToRcstStorage},
+    };
+    use std::path::PathBuf;
+
+    #[salsa::database(
+        AstDbStorage,
+        AstToHirStorage,
+        CstDbStorage,
+        CstToAstStorage,
+        HirDbStorage,
+        HirToMirStorage,
+        MirToLirStorage,
+        ModuleDbStorage,
+        OptimizeLirStorage,
+        OptimizeMirStorage,
+        PositionConversionStorage,
+        RcstToCstStorage,
+        StringToRcstStorage
+    )]
+    #[derive(Default)]
+    struct Database {
+        storage: salsa::Storage<Self>,
+        module_provider: InMemoryModuleProvider,
+    }
+    impl salsa::Database for Database {}
+    impl ModuleProviderOwner for Database {
+        fn get_module_provider(&self) -> &dyn ModuleProvider {
+            &self.module_provider
+        }
+    }
+    impl RcstCacheOwner for Database {}
+    impl MirPassesOwner for Database {}
+    impl MutableModuleProviderOwner for Database {
+        fn get_in_memory_module_provider(&mut self) -> &mut InMemoryModuleProvider {
+            &mut self.module_provider
+        }
+        fn invalidate_module(&mut self, module: &Module) {
+            GetModuleContentQuery.in_db_mut(self).invalidate(module);
+        }
+    }
+
+    #[test]
+    fn compiling_twice_produces_identical_output() {
+        let module = |name: &str| Module {
+            package: Package::User(PathBuf::from("/non/existent")),
+            path: vec![name.to_string()],
+            kind: ModuleKind::Code,
+        };
+        let compile = || {
+            let mut db = Database::default();
+            db.did_open_module(
+                &module("main"),
+                b"helper = use \".helper\"
+config = [Name: \"Candy\", Version: 1, Flags: [Debug: True, Verbose: False]]
+wrap := { value -> [Value: value, Config: config, Helper: helper.double] }"
+                    .to_vec(),
+            );
+            db.did_open_module(
+                &module("helper"),
+                b"double := { a -> [First: a, Second: a] }".to_vec(),
+            );
+
+            let target = ExecutionTarget::Module(module("main"));
+            let (mir, _) = db
+                .optimized_mir(target.clone(), TracingConfig::off())
+                .unwrap();
+            let (lir, _) = db.optimized_lir(target, TracingConfig::off()).unwrap();
+            (mir.to_rich_ir(true).text, lir.to_rich_ir(true).text)
+        };
+
+        assert_eq!(compile(), compile());
+    }
+}
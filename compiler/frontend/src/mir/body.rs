@@ -10,6 +10,7 @@ use derive_more::{Deref, DerefMut};
 use enumset::EnumSet;
 use itertools::Itertools;
 use num_bigint::BigInt;
+use ordered_float::OrderedFloat;
 use rustc_hash::FxHashMap;
 use std::{
     cmp::Ordering,
@@ -338,6 +339,9 @@ impl BodyBuilder {
     pub fn push_int(&mut self, value: impl Into<BigInt>) -> Id {
         self.push(Expression::Int(value.into()))
     }
+    pub fn push_float(&mut self, value: impl Into<OrderedFloat<f64>>) -> Id {
+        self.push(Expression::Float(value.into()))
+    }
     pub fn push_text(&mut self, value: String) -> Id {
         self.push(Expression::Text(value))
     }
@@ -0,0 +1,1139 @@
+//! A small interpreter that evaluates MIR code at compile-time.
+//!
+//! Constant folding in the optimizer only evaluates single builtin calls and
+//! relies on inlining for everything else, which doesn't work for recursive
+//! functions. This evaluator runs arbitrary MIR code as long as it only depends
+//! on compile-time known values and doesn't have side effects.
+//!
+//! Evaluation is gated by fuel: Each evaluated expression and call consumes one
+//! unit, and creating collections consumes one unit per item. Once the fuel is
+//! used up, evaluation stops, so compile-time evaluation can't hang the
+//! compiler.
+
+use super::{Body, Expression, Id, VisibleExpressions};
+use crate::{
+    builtin_functions::BuiltinFunction,
+    format::{format_float, format_value, FormatValue, MaxLength, Precedence},
+    hir,
+    id::IdGenerator,
+    utils::GraphemeAwareSearch,
+};
+use itertools::Itertools;
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{FromPrimitive, Signed, ToPrimitive, Zero};
+use ordered_float::OrderedFloat;
+use rustc_hash::FxHashMap;
+use std::{borrow::Cow, cmp::Ordering, str::FromStr};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Deeper recursion is treated like running out of fuel so that evaluation
+/// doesn't overflow the stack.
+const MAX_CALL_DEPTH: usize = 200;
+
+pub struct Evaluator<'a> {
+    visible: &'a VisibleExpressions,
+    values: Vec<Value<'a>>,
+    /// Values of visible expressions that were already evaluated.
+    visible_values: FxHashMap<Id, ValueId>,
+    fuel: usize,
+    call_depth: usize,
+    /// Values of `TraceExpressionEvaluated` expressions. These are only
+    /// recorded when evaluating partially. Otherwise, tracing is a side effect
+    /// that prevents evaluation.
+    evaluated_expressions: Option<FxHashMap<hir::Id, ValueId>>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ValueId(usize);
+
+#[derive(Clone, Debug)]
+enum Value<'a> {
+    Int(BigInt),
+    Float(OrderedFloat<f64>),
+    Text(String),
+    Tag {
+        symbol: String,
+        value: Option<ValueId>,
+    },
+    Builtin(BuiltinFunction),
+    List(Vec<ValueId>),
+    Struct(Vec<(ValueId, ValueId)>),
+    HirId(hir::Id),
+    Function {
+        parameters: &'a [Id],
+        body: &'a Body,
+        environment: Environment,
+    },
+}
+type Environment = im::HashMap<Id, ValueId>;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EvaluationError {
+    /// The code depends on values that aren't known at compile-time or has
+    /// side effects.
+    Unsupported,
+    Panicked,
+    OutOfFuel,
+}
+pub type EvaluationResult<T> = Result<T, EvaluationError>;
+
+impl<'a> Evaluator<'a> {
+    #[must_use]
+    pub fn new(visible: &'a VisibleExpressions, fuel: usize) -> Self {
+        Self {
+            visible,
+            values: vec![],
+            visible_values: FxHashMap::default(),
+            fuel,
+            call_depth: 0,
+            evaluated_expressions: None,
+        }
+    }
+
+    /// Calls a function. The function and arguments are looked up in the
+    /// visible expressions.
+    ///
+    /// Responsibilities are ignored: They only matter when the code panics, in
+    /// which case the evaluation fails anyway.
+    pub fn call(&mut self, function: Id, arguments: &[Id]) -> EvaluationResult<ValueId> {
+        let environment = Environment::new();
+        let function = self.lookup(&environment, function)?;
+        let arguments = arguments
+            .iter()
+            .map(|it| self.lookup(&environment, *it))
+            .try_collect()?;
+        self.call_value(function, arguments)
+    }
+
+    /// Evaluates the expressions of a body one after another and returns the
+    /// values of `TraceExpressionEvaluated` expressions.
+    ///
+    /// Expressions that can't be evaluated are skipped, as are expressions
+    /// that depend on them. Evaluation stops once the code panics or the fuel
+    /// runs out.
+    pub fn evaluate_partially(&mut self, body: &'a Body) -> FxHashMap<hir::Id, ValueId> {
+        self.evaluated_expressions = Some(FxHashMap::default());
+        let mut environment = Environment::new();
+        for (id, expression) in body.iter() {
+            match self.evaluate_expression(&environment, expression) {
+                Ok(value) => {
+                    environment.insert(id, value);
+                }
+                Err(EvaluationError::Unsupported) => {}
+                Err(EvaluationError::Panicked | EvaluationError::OutOfFuel) => break,
+            }
+        }
+        self.evaluated_expressions.take().unwrap()
+    }
+
+    #[must_use]
+    pub fn format(
+        &self,
+        value: ValueId,
+        precedence: Precedence,
+        max_length: MaxLength,
+    ) -> Option<String> {
+        format_value(value, precedence, max_length, &|value| {
+            Some(match self.get(value) {
+                Value::Int(int) => FormatValue::Int(Cow::Borrowed(int)),
+                Value::Float(float) => FormatValue::Float(**float),
+                Value::Text(text) => FormatValue::Text(text),
+                Value::Tag { symbol, value } => FormatValue::Tag {
+                    symbol,
+                    value: *value,
+                },
+                Value::Builtin(_) | Value::Function { .. } => FormatValue::Function,
+                Value::List(items) => FormatValue::List(items),
+                Value::Struct(fields) => FormatValue::Struct(Cow::Borrowed(fields)),
+                Value::HirId(_) => return None,
+            })
+        })
+    }
+
+    /// Converts the value back to MIR expressions.
+    ///
+    /// Returns `None` if the value contains functions since they may capture
+    /// values that only exist during evaluation.
+    pub fn to_body(&self, value: ValueId, id_generator: &mut IdGenerator<Id>) -> Option<Body> {
+        let mut body = Body::default();
+        self.push_to_body(value, &mut body, id_generator, &mut FxHashMap::default())?;
+        Some(body)
+    }
+    fn push_to_body(
+        &self,
+        value: ValueId,
+        body: &mut Body,
+        id_generator: &mut IdGenerator<Id>,
+        pushed: &mut FxHashMap<ValueId, Id>,
+    ) -> Option<Id> {
+        if let Some(id) = pushed.get(&value) {
+            return Some(*id);
+        }
+
+        let mut push = |value| self.push_to_body(value, body, id_generator, pushed);
+        let expression = match self.get(value) {
+            Value::Int(int) => Expression::Int(int.clone()),
+            Value::Float(float) => Expression::Float(*float),
+            Value::Text(text) => Expression::Text(text.clone()),
+            Value::Tag { symbol, value } => Expression::Tag {
+                symbol: symbol.clone(),
+                value: match value {
+                    Some(value) => Some(push(*value)?),
+                    None => None,
+                },
+            },
+            Value::Builtin(builtin) => Expression::Builtin(*builtin),
+            Value::List(items) => {
+                Expression::List(items.iter().map(|it| push(*it)).collect::<Option<_>>()?)
+            }
+            Value::Struct(fields) => Expression::Struct(
+                fields
+                    .iter()
+                    .map(|(key, value)| Some((push(*key)?, push(*value)?)))
+                    .collect::<Option<_>>()?,
+            ),
+            Value::HirId(id) => Expression::HirId(id.clone()),
+            Value::Function { .. } => return None,
+        };
+        let id = body.push_with_new_id(id_generator, expression);
+        pushed.insert(value, id);
+        Some(id)
+    }
+
+    fn get(&self, value: ValueId) -> &Value<'a> {
+        &self.values[value.0]
+    }
+    fn push(&mut self, value: Value<'a>) -> ValueId {
+        self.values.push(value);
+        ValueId(self.values.len() - 1)
+    }
+    fn consume_fuel(&mut self, amount: usize) -> EvaluationResult<()> {
+        if let Some(remaining) = self.fuel.checked_sub(amount) {
+            self.fuel = remaining;
+            Ok(())
+        } else {
+            self.fuel = 0;
+            Err(EvaluationError::OutOfFuel)
+        }
+    }
+
+    fn lookup(&mut self, environment: &Environment, id: Id) -> EvaluationResult<ValueId> {
+        if let Some(value) = environment.get(&id) {
+            return Ok(*value);
+        }
+        if let Some(value) = self.visible_values.get(&id) {
+            return Ok(*value);
+        }
+        if !self.visible.contains(id) {
+            return Err(EvaluationError::Unsupported);
+        }
+
+        let visible = self.visible;
+        let value = self.evaluate_expression(&Environment::new(), visible.get(id))?;
+        self.visible_values.insert(id, value);
+        Ok(value)
+    }
+
+    fn evaluate_body(
+        &mut self,
+        mut environment: Environment,
+        body: &'a Body,
+    ) -> EvaluationResult<ValueId> {
+        for (id, expression) in body.iter() {
+            let value = self.evaluate_expression(&environment, expression)?;
+            environment.insert(id, value);
+        }
+        Ok(*environment.get(&body.return_value()).unwrap())
+    }
+    fn evaluate_expression(
+        &mut self,
+        environment: &Environment,
+        expression: &'a Expression,
+    ) -> EvaluationResult<ValueId> {
+        self.consume_fuel(1)?;
+        let value = match expression {
+            Expression::Int(int) => Value::Int(int.clone()),
+            Expression::Float(float) => Value::Float(*float),
+            Expression::Text(text) => Value::Text(text.clone()),
+            Expression::Tag { symbol, value } => Value::Tag {
+                symbol: symbol.clone(),
+                value: value.map(|it| self.lookup(environment, it)).transpose()?,
+            },
+            Expression::Builtin(builtin) => Value::Builtin(*builtin),
+            Expression::List(items) => Value::List(
+                items
+                    .iter()
+                    .map(|it| self.lookup(environment, *it))
+                    .try_collect()?,
+            ),
+            Expression::Struct(fields) => Value::Struct(
+                fields
+                    .iter()
+                    .map(|(key, value)| {
+                        Ok((
+                            self.lookup(environment, *key)?,
+                            self.lookup(environment, *value)?,
+                        ))
+                    })
+                    .try_collect()?,
+            ),
+            Expression::Reference(id) => return self.lookup(environment, *id),
+            Expression::HirId(id) => Value::HirId(id.clone()),
+            Expression::Function {
+                parameters, body, ..
+            } => Value::Function {
+                parameters,
+                body,
+                environment: environment.clone(),
+            },
+            Expression::Call {
+                function,
+                arguments,
+                ..
+            } => {
+                let function = self.lookup(environment, *function)?;
+                let arguments = arguments
+                    .iter()
+                    .map(|it| self.lookup(environment, *it))
+                    .try_collect()?;
+                return self.call_value(function, arguments);
+            }
+            Expression::Panic { .. } => return Err(EvaluationError::Panicked),
+            Expression::TraceExpressionEvaluated {
+                hir_expression,
+                value,
+            } if self.evaluated_expressions.is_some() => {
+                let hir_expression = self.lookup(environment, *hir_expression)?;
+                let value = self.lookup(environment, *value)?;
+                if let Value::HirId(hir_expression) = self.get(hir_expression) {
+                    let hir_expression = hir_expression.clone();
+                    self.evaluated_expressions
+                        .as_mut()
+                        .unwrap()
+                        .insert(hir_expression, value);
+                }
+                nothing()
+            }
+            Expression::Parameter
+            | Expression::UseModule { .. }
+            | Expression::TraceCallStarts { .. }
+            | Expression::TraceCallEnds { .. }
+            | Expression::TraceTailCall { .. }
+            | Expression::TraceExpressionEvaluated { .. }
+            | Expression::TraceFoundFuzzableFunction { .. } => {
+                return Err(EvaluationError::Unsupported);
+            }
+        };
+        Ok(self.push(value))
+    }
+
+    fn call_value(
+        &mut self,
+        function: ValueId,
+        arguments: Vec<ValueId>,
+    ) -> EvaluationResult<ValueId> {
+        self.consume_fuel(1)?;
+        if self.call_depth >= MAX_CALL_DEPTH {
+            return Err(EvaluationError::OutOfFuel);
+        }
+
+        match self.get(function).clone() {
+            Value::Function {
+                parameters,
+                body,
+                mut environment,
+            } => {
+                if parameters.len() != arguments.len() {
+                    return Err(EvaluationError::Panicked);
+                }
+                environment.extend(parameters.iter().copied().zip(arguments));
+
+                self.call_depth += 1;
+                let result = self.evaluate_body(environment, body);
+                self.call_depth -= 1;
+                result
+            }
+            Value::Builtin(builtin) => {
+                if builtin.num_parameters() != arguments.len() {
+                    return Err(EvaluationError::Panicked);
+                }
+                self.run_builtin(builtin, &arguments)
+            }
+            Value::Tag {
+                symbol,
+                value: None,
+            } if arguments.len() == 1 => Ok(self.push(Value::Tag {
+                symbol,
+                value: Some(arguments[0]),
+            })),
+            _ => Err(EvaluationError::Panicked),
+        }
+    }
+
+    #[allow(clippy::too_many_lines)]
+    fn run_builtin(
+        &mut self,
+        builtin: BuiltinFunction,
+        arguments: &[ValueId],
+    ) -> EvaluationResult<ValueId> {
+        let value = match builtin {
+            BuiltinFunction::BytesConcat
+            | BuiltinFunction::BytesGet
+            | BuiltinFunction::BytesLength
+            | BuiltinFunction::BytesSlice
+            | BuiltinFunction::FunctionTry
+            // The hash is computed by the VM.
+            | BuiltinFunction::HashValue
+            | BuiltinFunction::ListSort
+            | BuiltinFunction::Print
+            | BuiltinFunction::RegexCaptures
+            | BuiltinFunction::RegexFind
+            | BuiltinFunction::RegexIsMatch
+            // The order of keys depends on the VM's hashing and comparisons.
+            | BuiltinFunction::StructEntries
+            | BuiltinFunction::StructGetKeys
+            | BuiltinFunction::TextToUtf8Bytes => return Err(EvaluationError::Unsupported),
+            BuiltinFunction::CompareTo => {
+                let [a, b] = arguments else { unreachable!() };
+                ordering(self.compare(*a, *b).ok_or(EvaluationError::Unsupported)?)
+            }
+            BuiltinFunction::Equals => {
+                let [a, b] = arguments else { unreachable!() };
+                bool(self.equals(*a, *b).ok_or(EvaluationError::Unsupported)?)
+            }
+            BuiltinFunction::FloatAdd => {
+                let [a, b] = arguments else { unreachable!() };
+                Value::Float(self.float(*a)? + self.float(*b)?)
+            }
+            BuiltinFunction::FloatCompareTo => {
+                let [a, b] = arguments else { unreachable!() };
+                ordering(self.float(*a)?.cmp(&self.float(*b)?))
+            }
+            BuiltinFunction::FloatDivide => {
+                let [dividend, divisor] = arguments else {
+                    unreachable!()
+                };
+                Value::Float(self.float(*dividend)? / self.float(*divisor)?)
+            }
+            BuiltinFunction::FloatFromInt => {
+                let [int] = arguments else { unreachable!() };
+                let float = self.int(*int)?.to_f64();
+                Value::Float(float.ok_or(EvaluationError::Unsupported)?.into())
+            }
+            BuiltinFunction::FloatMultiply => {
+                let [factor_a, factor_b] = arguments else {
+                    unreachable!()
+                };
+                Value::Float(self.float(*factor_a)? * self.float(*factor_b)?)
+            }
+            BuiltinFunction::FloatSubtract => {
+                let [minuend, subtrahend] = arguments else {
+                    unreachable!()
+                };
+                Value::Float(self.float(*minuend)? - self.float(*subtrahend)?)
+            }
+            BuiltinFunction::FloatToText => {
+                let [float] = arguments else { unreachable!() };
+                Value::Text(format_float(*self.float(*float)?))
+            }
+            BuiltinFunction::FloatTruncate => {
+                let [float] = arguments else { unreachable!() };
+                let int = BigInt::from_f64(self.float(*float)?.trunc());
+                Value::Int(int.ok_or(EvaluationError::Unsupported)?)
+            }
+            BuiltinFunction::FunctionRun => {
+                let [function] = arguments else {
+                    unreachable!()
+                };
+                return self.call_value(*function, vec![]);
+            }
+            BuiltinFunction::GetArgumentCount => {
+                let [function] = arguments else {
+                    unreachable!()
+                };
+                let count = match self.get(*function) {
+                    Value::Builtin(builtin) => builtin.num_parameters(),
+                    Value::Function { parameters, .. } => parameters.len(),
+                    _ => return Err(EvaluationError::Panicked),
+                };
+                Value::Int(count.into())
+            }
+            BuiltinFunction::IfElse => {
+                let [condition, then, else_] = arguments else {
+                    unreachable!()
+                };
+                let function = if self.bool(*condition)? { then } else { else_ };
+                return self.call_value(*function, vec![]);
+            }
+            BuiltinFunction::IntAdd => {
+                let [a, b] = arguments else { unreachable!() };
+                Value::Int(self.int(*a)? + self.int(*b)?)
+            }
+            BuiltinFunction::IntBitLength => {
+                let [a] = arguments else { unreachable!() };
+                Value::Int(self.int(*a)?.bits().into())
+            }
+            BuiltinFunction::IntBitwiseAnd => {
+                let [a, b] = arguments else { unreachable!() };
+                Value::Int(self.int(*a)? & self.int(*b)?)
+            }
+            BuiltinFunction::IntBitwiseOr => {
+                let [a, b] = arguments else { unreachable!() };
+                Value::Int(self.int(*a)? | self.int(*b)?)
+            }
+            BuiltinFunction::IntBitwiseXor => {
+                let [a, b] = arguments else { unreachable!() };
+                Value::Int(self.int(*a)? ^ self.int(*b)?)
+            }
+            BuiltinFunction::IntCompareTo => {
+                let [a, b] = arguments else { unreachable!() };
+                ordering(self.int(*a)?.cmp(self.int(*b)?))
+            }
+            BuiltinFunction::IntDivideTruncating => {
+                let [dividend, divisor] = arguments else {
+                    unreachable!()
+                };
+                Value::Int(self.int(*dividend)? / self.non_zero_int(*divisor)?)
+            }
+            BuiltinFunction::IntGcd => {
+                let [a, b] = arguments else { unreachable!() };
+                Value::Int(self.int(*a)?.gcd(self.int(*b)?))
+            }
+            BuiltinFunction::IntModulo => {
+                let [dividend, divisor] = arguments else {
+                    unreachable!()
+                };
+                Value::Int(self.int(*dividend)?.mod_floor(self.non_zero_int(*divisor)?))
+            }
+            BuiltinFunction::IntMultiply => {
+                let [factor_a, factor_b] = arguments else {
+                    unreachable!()
+                };
+                Value::Int(self.int(*factor_a)? * self.int(*factor_b)?)
+            }
+            BuiltinFunction::IntParse => {
+                let [text] = arguments else { unreachable!() };
+                let result = match BigInt::from_str(self.text(*text)?) {
+                    Ok(int) => Ok(Value::Int(int)),
+                    Err(error) => Err(Value::Text(error.to_string())),
+                };
+                self.result(result)
+            }
+            BuiltinFunction::IntPow => {
+                let [base, exponent] = arguments else {
+                    unreachable!()
+                };
+                let exponent = self.int(*exponent)?;
+                if exponent.is_negative() {
+                    return Err(EvaluationError::Panicked);
+                }
+                // Big exponents would create huge ints.
+                let exponent = exponent.to_u16().ok_or(EvaluationError::Unsupported)?;
+                Value::Int(self.int(*base)?.pow(exponent.into()))
+            }
+            BuiltinFunction::IntRemainder => {
+                let [dividend, divisor] = arguments else {
+                    unreachable!()
+                };
+                Value::Int(self.int(*dividend)? % self.non_zero_int(*divisor)?)
+            }
+            BuiltinFunction::IntShiftLeft => {
+                let [value, amount] = arguments else {
+                    unreachable!()
+                };
+                // Big shift amounts would create huge ints.
+                let amount = self.int(*amount)?.to_u16();
+                Value::Int(self.int(*value)? << amount.ok_or(EvaluationError::Unsupported)?)
+            }
+            BuiltinFunction::IntShiftRight => {
+                let [value, amount] = arguments else {
+                    unreachable!()
+                };
+                let amount = self.int(*amount)?.to_u16();
+                Value::Int(self.int(*value)? >> amount.ok_or(EvaluationError::Unsupported)?)
+            }
+            BuiltinFunction::IntSqrt => {
+                let [value] = arguments else { unreachable!() };
+                let value = self.int(*value)?;
+                if value.is_negative() {
+                    return Err(EvaluationError::Panicked);
+                }
+                Value::Int(value.sqrt())
+            }
+            BuiltinFunction::IntSubtract => {
+                let [minuend, subtrahend] = arguments else {
+                    unreachable!()
+                };
+                Value::Int(self.int(*minuend)? - self.int(*subtrahend)?)
+            }
+            BuiltinFunction::ListConcatenate => {
+                let [a, b] = arguments else { unreachable!() };
+                let items = self
+                    .list(*a)?
+                    .iter()
+                    .chain(self.list(*b)?)
+                    .copied()
+                    .collect_vec();
+                self.consume_fuel(items.len())?;
+                Value::List(items)
+            }
+            BuiltinFunction::ListFilled => {
+                let [length, item] = arguments else {
+                    unreachable!()
+                };
+                let length = self.int(*length)?.to_usize();
+                let length = length.ok_or(EvaluationError::Unsupported)?;
+                self.consume_fuel(length)?;
+                Value::List(vec![*item; length])
+            }
+            BuiltinFunction::ListGet => {
+                let [list, index] = arguments else {
+                    unreachable!()
+                };
+                let list = self.list(*list)?;
+                let index = self.index(*index, list.len())?;
+                return Ok(list[index]);
+            }
+            BuiltinFunction::ListInsert => {
+                let [list, index, item] = arguments else {
+                    unreachable!()
+                };
+                let mut items = self.list(*list)?.clone();
+                let index = self.index(*index, items.len() + 1)?;
+                self.consume_fuel(items.len())?;
+                items.insert(index, *item);
+                Value::List(items)
+            }
+            BuiltinFunction::ListLength => {
+                let [list] = arguments else { unreachable!() };
+                Value::Int(self.list(*list)?.len().into())
+            }
+            BuiltinFunction::ListRemoveAt => {
+                let [list, index] = arguments else {
+                    unreachable!()
+                };
+                let mut items = self.list(*list)?.clone();
+                let index = self.index(*index, items.len())?;
+                self.consume_fuel(items.len())?;
+                items.remove(index);
+                Value::List(items)
+            }
+            BuiltinFunction::ListReplace => {
+                let [list, index, new_item] = arguments else {
+                    unreachable!()
+                };
+                let mut items = self.list(*list)?.clone();
+                let index = self.index(*index, items.len())?;
+                self.consume_fuel(items.len())?;
+                items[index] = *new_item;
+                Value::List(items)
+            }
+            BuiltinFunction::ListSlice => {
+                let [list, start_inclusive, end_exclusive] = arguments else {
+                    unreachable!()
+                };
+                let length = self.list(*list)?.len();
+                let end_exclusive = self.index(*end_exclusive, length + 1)?;
+                let start_inclusive = self.index(*start_inclusive, end_exclusive + 1)?;
+                let items = self.list(*list)?[start_inclusive..end_exclusive].to_vec();
+                self.consume_fuel(items.len())?;
+                Value::List(items)
+            }
+            BuiltinFunction::Panic => return Err(EvaluationError::Panicked),
+            BuiltinFunction::StructGet => {
+                let [struct_, key] = arguments else {
+                    unreachable!()
+                };
+                let fields = self.struct_(*struct_)?;
+                return self
+                    .find_field(fields, *key)?
+                    .ok_or(EvaluationError::Panicked);
+            }
+            BuiltinFunction::StructHasKey => {
+                let [struct_, key] = arguments else {
+                    unreachable!()
+                };
+                let fields = self.struct_(*struct_)?;
+                bool(self.find_field(fields, *key)?.is_some())
+            }
+            BuiltinFunction::StructInsert => {
+                let [struct_, key, value] = arguments else {
+                    unreachable!()
+                };
+                let mut fields = self.fields_without_key(*struct_, *key)?;
+                fields.push((*key, *value));
+                Value::Struct(fields)
+            }
+            BuiltinFunction::StructMerge => {
+                let [a, b] = arguments else { unreachable!() };
+                let b_fields = self.struct_(*b)?.clone();
+                let mut fields = self.struct_(*a)?.clone();
+                for (key, _) in &b_fields {
+                    let mut remaining_fields = vec![];
+                    for (field_key, field_value) in fields {
+                        if !self
+                            .equals(field_key, *key)
+                            .ok_or(EvaluationError::Unsupported)?
+                        {
+                            remaining_fields.push((field_key, field_value));
+                        }
+                    }
+                    fields = remaining_fields;
+                }
+                fields.extend(b_fields);
+                self.consume_fuel(fields.len())?;
+                Value::Struct(fields)
+            }
+            BuiltinFunction::StructRemove => {
+                let [struct_, key] = arguments else {
+                    unreachable!()
+                };
+                Value::Struct(self.fields_without_key(*struct_, *key)?)
+            }
+            BuiltinFunction::TagGetValue => {
+                let [tag] = arguments else { unreachable!() };
+                let Value::Tag { value, .. } = self.get(*tag) else {
+                    return Err(EvaluationError::Panicked);
+                };
+                return value.ok_or(EvaluationError::Panicked);
+            }
+            BuiltinFunction::TagHasValue => {
+                let [tag] = arguments else { unreachable!() };
+                let Value::Tag { value, .. } = self.get(*tag) else {
+                    return Err(EvaluationError::Panicked);
+                };
+                bool(value.is_some())
+            }
+            BuiltinFunction::TagWithoutValue => {
+                let [tag] = arguments else { unreachable!() };
+                let Value::Tag { symbol, .. } = self.get(*tag) else {
+                    return Err(EvaluationError::Panicked);
+                };
+                Value::Tag {
+                    symbol: symbol.clone(),
+                    value: None,
+                }
+            }
+            BuiltinFunction::TagWithValue => {
+                let [tag, value] = arguments else {
+                    unreachable!()
+                };
+                let Value::Tag { symbol, .. } = self.get(*tag) else {
+                    return Err(EvaluationError::Panicked);
+                };
+                Value::Tag {
+                    symbol: symbol.clone(),
+                    value: Some(*value),
+                }
+            }
+            BuiltinFunction::TextCharacters => {
+                let [text] = arguments else { unreachable!() };
+                let characters = self
+                    .text(*text)?
+                    .graphemes(true)
+                    .map(ToString::to_string)
+                    .collect_vec();
+                self.consume_fuel(characters.len())?;
+                let characters = characters
+                    .into_iter()
+                    .map(|it| self.push(Value::Text(it)))
+                    .collect();
+                Value::List(characters)
+            }
+            BuiltinFunction::TextConcatenate => {
+                let [a, b] = arguments else { unreachable!() };
+                Value::Text(format!("{}{}", self.text(*a)?, self.text(*b)?))
+            }
+            BuiltinFunction::TextContains => {
+                let [text, pattern] = arguments else {
+                    unreachable!()
+                };
+                bool(self.text(*text)?.contains(self.text(*pattern)?))
+            }
+            BuiltinFunction::TextEndsWith => {
+                let [text, suffix] = arguments else {
+                    unreachable!()
+                };
+                bool(self.text(*text)?.ends_with(self.text(*suffix)?))
+            }
+            BuiltinFunction::TextFromUtf8 => {
+                let [bytes] = arguments else { unreachable!() };
+                let bytes: Vec<u8> = self
+                    .list(*bytes)?
+                    .iter()
+                    .map(|it| self.int(*it)?.to_u8().ok_or(EvaluationError::Panicked))
+                    .try_collect()?;
+                let result = String::from_utf8(bytes)
+                    .map(Value::Text)
+                    .map_err(|_| Value::Text("Invalid UTF-8.".to_string()));
+                self.result(result)
+            }
+            BuiltinFunction::TextGetRange => {
+                let [text, start_inclusive, end_exclusive] = arguments else {
+                    unreachable!()
+                };
+                let text = self.text(*text)?;
+                let length = text.graphemes(true).count();
+                let end_exclusive = self.index(*end_exclusive, length + 1)?;
+                let start_inclusive = self.index(*start_inclusive, end_exclusive + 1)?;
+                Value::Text(
+                    text.graphemes(true)
+                        .skip(start_inclusive)
+                        .take(end_exclusive - start_inclusive)
+                        .collect(),
+                )
+            }
+            BuiltinFunction::TextIndexOf => {
+                let [text, pattern] = arguments else {
+                    unreachable!()
+                };
+                let index = self.text(*text)?.grapheme_index_of(self.text(*pattern)?);
+                index.map_or_else(
+                    || tag("NotFound"),
+                    |index| Value::Tag {
+                        symbol: "FoundAt".to_string(),
+                        value: Some(self.push(Value::Int(index.into()))),
+                    },
+                )
+            }
+            BuiltinFunction::TextIsEmpty => {
+                let [text] = arguments else { unreachable!() };
+                bool(self.text(*text)?.is_empty())
+            }
+            BuiltinFunction::TextLength => {
+                let [text] = arguments else { unreachable!() };
+                Value::Int(self.text(*text)?.graphemes(true).count().into())
+            }
+            BuiltinFunction::TextReplace => {
+                let [text, pattern, replacement] = arguments else {
+                    unreachable!()
+                };
+                Value::Text(
+                    self.text(*text)?
+                        .grapheme_replace(self.text(*pattern)?, self.text(*replacement)?),
+                )
+            }
+            BuiltinFunction::TextSplit => {
+                let [text, separator] = arguments else {
+                    unreachable!()
+                };
+                let parts = self
+                    .text(*text)?
+                    .grapheme_split(self.text(*separator)?)
+                    .into_iter()
+                    .map(ToString::to_string)
+                    .collect_vec();
+                self.consume_fuel(parts.len())?;
+                let parts = parts
+                    .into_iter()
+                    .map(|it| self.push(Value::Text(it)))
+                    .collect();
+                Value::List(parts)
+            }
+            BuiltinFunction::TextStartsWith => {
+                let [text, prefix] = arguments else {
+                    unreachable!()
+                };
+                bool(self.text(*text)?.starts_with(self.text(*prefix)?))
+            }
+            BuiltinFunction::TextTrimEnd => {
+                let [text] = arguments else { unreachable!() };
+                Value::Text(self.text(*text)?.trim_end().to_string())
+            }
+            BuiltinFunction::TextTrimStart => {
+                let [text] = arguments else { unreachable!() };
+                Value::Text(self.text(*text)?.trim_start().to_string())
+            }
+            BuiltinFunction::TextToLower => {
+                let [text] = arguments else { unreachable!() };
+                Value::Text(self.text(*text)?.to_lowercase())
+            }
+            BuiltinFunction::TextToUpper => {
+                let [text] = arguments else { unreachable!() };
+                Value::Text(self.text(*text)?.to_uppercase())
+            }
+            BuiltinFunction::ToDebugText => {
+                let [value] = arguments else { unreachable!() };
+                let text = self.format(*value, Precedence::Low, MaxLength::Unlimited);
+                Value::Text(text.ok_or(EvaluationError::Unsupported)?)
+            }
+            BuiltinFunction::TypeOf => {
+                let [value] = arguments else { unreachable!() };
+                tag(match self.get(*value) {
+                    Value::Int(_) => "Int",
+                    Value::Float(_) => "Float",
+                    Value::Text(_) => "Text",
+                    Value::Tag { .. } => "Tag",
+                    Value::Builtin(_) => "Builtin",
+                    Value::List(_) => "List",
+                    Value::Struct(_) => "Struct",
+                    Value::Function { .. } => "Function",
+                    Value::HirId(_) => return Err(EvaluationError::Unsupported),
+                })
+            }
+        };
+        Ok(self.push(value))
+    }
+
+    fn int(&self, value: ValueId) -> EvaluationResult<&BigInt> {
+        match self.get(value) {
+            Value::Int(int) => Ok(int),
+            _ => Err(EvaluationError::Panicked),
+        }
+    }
+    fn non_zero_int(&self, value: ValueId) -> EvaluationResult<&BigInt> {
+        let int = self.int(value)?;
+        if int.is_zero() {
+            return Err(EvaluationError::Panicked);
+        }
+        Ok(int)
+    }
+    /// Returns the index if it's less than `length`.
+    fn index(&self, value: ValueId, length: usize) -> EvaluationResult<usize> {
+        self.int(value)?
+            .to_usize()
+            .filter(|it| *it < length)
+            .ok_or(EvaluationError::Panicked)
+    }
+    fn float(&self, value: ValueId) -> EvaluationResult<OrderedFloat<f64>> {
+        match self.get(value) {
+            Value::Float(float) => Ok(*float),
+            _ => Err(EvaluationError::Panicked),
+        }
+    }
+    fn text(&self, value: ValueId) -> EvaluationResult<&str> {
+        match self.get(value) {
+            Value::Text(text) => Ok(text),
+            _ => Err(EvaluationError::Panicked),
+        }
+    }
+    fn bool(&self, value: ValueId) -> EvaluationResult<bool> {
+        match self.get(value) {
+            Value::Tag {
+                symbol,
+                value: None,
+            } if symbol == "True" => Ok(true),
+            Value::Tag {
+                symbol,
+                value: None,
+            } if symbol == "False" => Ok(false),
+            _ => Err(EvaluationError::Panicked),
+        }
+    }
+    fn list(&self, value: ValueId) -> EvaluationResult<&Vec<ValueId>> {
+        match self.get(value) {
+            Value::List(items) => Ok(items),
+            _ => Err(EvaluationError::Panicked),
+        }
+    }
+    fn struct_(&self, value: ValueId) -> EvaluationResult<&Vec<(ValueId, ValueId)>> {
+        match self.get(value) {
+            Value::Struct(fields) => Ok(fields),
+            _ => Err(EvaluationError::Panicked),
+        }
+    }
+    fn result(&mut self, result: Result<Value<'a>, Value<'a>>) -> Value<'a> {
+        let (symbol, value) = match result {
+            Ok(value) => ("Ok", value),
+            Err(value) => ("Error", value),
+        };
+        Value::Tag {
+            symbol: symbol.to_string(),
+            value: Some(self.push(value)),
+        }
+    }
+
+    /// Returns `None` if it's unknown whether the values are equal, e.g.,
+    /// because both are functions.
+    fn equals(&self, a: ValueId, b: ValueId) -> Option<bool> {
+        if a == b {
+            return Some(true);
+        }
+
+        Some(match (self.get(a), self.get(b)) {
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::Float(a), Value::Float(b)) => a == b,
+            (Value::Text(a), Value::Text(b)) => a == b,
+            (
+                Value::Tag {
+                    symbol: symbol_a,
+                    value: value_a,
+                },
+                Value::Tag {
+                    symbol: symbol_b,
+                    value: value_b,
+                },
+            ) => {
+                symbol_a == symbol_b
+                    && match (value_a, value_b) {
+                        (None, None) => true,
+                        (Some(a), Some(b)) => self.equals(*a, *b)?,
+                        _ => false,
+                    }
+            }
+            (Value::Builtin(a), Value::Builtin(b)) => a == b,
+            (Value::List(a), Value::List(b)) => {
+                if a.len() != b.len() {
+                    return Some(false);
+                }
+                for (a, b) in a.iter().zip_eq(b) {
+                    if !self.equals(*a, *b)? {
+                        return Some(false);
+                    }
+                }
+                true
+            }
+            (Value::Struct(a), Value::Struct(b)) => {
+                if a.len() != b.len() {
+                    return Some(false);
+                }
+                for (key, value_a) in a {
+                    let Some(value_b) = self.find_field(b, *key).ok()? else {
+                        return Some(false);
+                    };
+                    if !self.equals(*value_a, value_b)? {
+                        return Some(false);
+                    }
+                }
+                true
+            }
+            (Value::HirId(a), Value::HirId(b)) => a == b,
+            (Value::Function { .. }, Value::Function { .. }) => return None,
+            _ => false,
+        })
+    }
+    /// Returns `None` if the order of the values isn't known at compile time,
+    /// e.g., because they have different kinds.
+    fn compare(&self, a: ValueId, b: ValueId) -> Option<Ordering> {
+        if a == b {
+            return Some(Ordering::Equal);
+        }
+
+        Some(match (self.get(a), self.get(b)) {
+            (Value::Int(a), Value::Int(b)) => a.cmp(b),
+            (Value::Float(a), Value::Float(b)) => a.cmp(b),
+            (Value::Text(a), Value::Text(b)) => a.cmp(b),
+            (Value::List(a), Value::List(b)) => {
+                for (a, b) in a.iter().zip(b) {
+                    let ordering = self.compare(*a, *b)?;
+                    if ordering != Ordering::Equal {
+                        return Some(ordering);
+                    }
+                }
+                a.len().cmp(&b.len())
+            }
+            _ => return None,
+        })
+    }
+    fn find_field(
+        &self,
+        fields: &[(ValueId, ValueId)],
+        key: ValueId,
+    ) -> EvaluationResult<Option<ValueId>> {
+        for (field_key, field_value) in fields.iter().rev() {
+            if self
+                .equals(*field_key, key)
+                .ok_or(EvaluationError::Unsupported)?
+            {
+                return Ok(Some(*field_value));
+            }
+        }
+        Ok(None)
+    }
+    fn fields_without_key(
+        &mut self,
+        struct_: ValueId,
+        key: ValueId,
+    ) -> EvaluationResult<Vec<(ValueId, ValueId)>> {
+        let fields = self.struct_(struct_)?;
+        let mut remaining_fields = vec![];
+        for (field_key, field_value) in fields {
+            if !self
+                .equals(*field_key, key)
+                .ok_or(EvaluationError::Unsupported)?
+            {
+                remaining_fields.push((*field_key, *field_value));
+            }
+        }
+        self.consume_fuel(remaining_fields.len())?;
+        Ok(remaining_fields)
+    }
+}
+
+fn tag<'a>(symbol: &str) -> Value<'a> {
+    Value::Tag {
+        symbol: symbol.to_string(),
+        value: None,
+    }
+}
+fn nothing<'a>() -> Value<'a> {
+    tag("Nothing")
+}
+fn bool<'a>(value: bool) -> Value<'a> {
+    tag(if value { "True" } else { "False" })
+}
+fn ordering<'a>(ordering: Ordering) -> Value<'a> {
+    tag(match ordering {
+        Ordering::Less => "Less",
+        Ordering::Equal => "Equal",
+        Ordering::Greater => "Greater",
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mir::Mir;
+
+    #[test]
+    fn evaluates_recursive_functions() {
+        // sum self n = if n == 0 { 0 } else { n + self self (n - 1) }
+        let hir_id = hir::Id::dummy();
+        let mir = Mir::build(|body| {
+            body.push_function(hir_id.clone(), |body, responsible| {
+                let self_ = body.new_parameter();
+                let n = body.new_parameter();
+                let zero = body.push_int(0);
+                let equals = body.push_builtin(BuiltinFunction::Equals);
+                let is_zero = body.push_call(equals, vec![n, zero], responsible);
+                body.push_if_else(
+                    &hir_id,
+                    is_zero,
+                    |body| {
+                        body.push_reference(zero);
+                    },
+                    |body| {
+                        let one = body.push_int(1);
+                        let subtract = body.push_builtin(BuiltinFunction::IntSubtract);
+                        let n_minus_one = body.push_call(subtract, vec![n, one], responsible);
+                        let rest = body.push_call(self_, vec![self_, n_minus_one], responsible);
+                        let add = body.push_builtin(BuiltinFunction::IntAdd);
+                        body.push_call(add, vec![n, rest], responsible);
+                    },
+                    responsible,
+                );
+            });
+            body.push_int(10);
+        });
+        let (sum, _) = mir.body.iter().next().unwrap();
+        let ten = mir.body.return_value();
+        let mut visible = VisibleExpressions::none_visible();
+        for (id, expression) in mir.body.iter() {
+            visible.insert(id, expression.clone());
+        }
+
+        let mut evaluator = Evaluator::new(&visible, 1000);
+        let result = evaluator.call(sum, &[sum, ten]).unwrap();
+        assert_eq!(
+            evaluator.format(result, Precedence::Low, MaxLength::Unlimited),
+            Some("55".to_string()),
+        );
+
+        let mut evaluator = Evaluator::new(&visible, 10);
+        assert_eq!(
+            evaluator.call(sum, &[sum, ten]),
+            Err(EvaluationError::OutOfFuel),
+        );
+    }
+}
@@ -10,6 +10,7 @@ use derive_more::{From, TryInto};
 use enumset::EnumSet;
 use itertools::Itertools;
 use num_bigint::BigInt;
+use ordered_float::OrderedFloat;
 use rustc_hash::{FxHashSet, FxHasher};
 use std::{
     cmp::Ordering,
@@ -23,6 +24,10 @@ pub enum Expression {
     #[try_into]
     Int(BigInt),
 
+    #[from]
+    #[try_into]
+    Float(OrderedFloat<f64>),
+
     #[from]
     #[try_into]
     Text(String),
@@ -168,6 +173,22 @@ impl<'a> TryInto<&'a BigInt> for &'a Expression {
         Ok(int)
     }
 }
+// Float
+impl From<f64> for Expression {
+    fn from(value: f64) -> Self {
+        Self::Float(value.into())
+    }
+}
+impl<'a> TryInto<&'a OrderedFloat<f64>> for &'a Expression {
+    type Error = ();
+
+    fn try_into(self) -> Result<&'a OrderedFloat<f64>, ()> {
+        let Expression::Float(float) = self else {
+            return Err(());
+        };
+        Ok(float)
+    }
+}
 // Text
 impl<'a> From<&'a str> for Expression {
     fn from(value: &'a str) -> Self {
@@ -243,6 +264,7 @@ impl Hash for Expression {
         mem::discriminant(self).hash(state);
         match self {
             Self::Int(int) => int.hash(state),
+            Self::Float(float) => float.hash(state),
             Self::Text(text) => text.hash(state),
             Self::Tag { symbol, value } => {
                 symbol.hash(state);
@@ -340,6 +362,9 @@ impl ToRichIr for Expression {
             Self::Int(int) => {
                 int.build_rich_ir(builder);
             }
+            Self::Float(float) => {
+                float.build_rich_ir(builder);
+            }
             Self::Text(text) => {
                 let range = builder.push(format!(r#""{text}""#), TokenType::Text, EnumSet::empty());
                 builder.push_reference(text.clone(), range);
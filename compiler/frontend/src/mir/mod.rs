@@ -1,4 +1,4 @@
-pub use self::{body::*, error::*, expression::*, id::*};
+pub use self::{body::*, error::*, evaluator::*, expression::*, id::*};
 use crate::{
     id::IdGenerator,
     impl_debug_via_richir, impl_display_via_richir,
@@ -7,6 +7,7 @@ use crate::{
 
 mod body;
 mod error;
+mod evaluator;
 mod expression;
 mod id;
 
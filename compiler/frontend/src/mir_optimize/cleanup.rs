@@ -55,8 +55,9 @@ impl Mir {
                     Expression::Builtin(_) => 1,
                     Expression::Tag { value: None, .. } => 2,
                     Expression::Int(_) => 3,
-                    Expression::Text(_) => 4,
-                    _ => 5,
+                    Expression::Float(_) => 4,
+                    Expression::Text(_) => 5,
+                    _ => 6,
                 }
             }
             match (a, b) {
@@ -75,6 +76,7 @@ impl Mir {
                     },
                 ) => a.cmp(b),
                 (Expression::Int(a), Expression::Int(b)) => a.cmp(b),
+                (Expression::Float(a), Expression::Float(b)) => a.cmp(b),
                 (Expression::Text(a), Expression::Text(b)) => a.cmp(b),
                 _ => order_score(a).cmp(&order_score(b)),
             }
@@ -29,6 +29,7 @@ use crate::{
 use impl_trait_for_tuples::impl_for_tuples;
 use itertools::Itertools;
 use num_bigint::BigInt;
+use ordered_float::OrderedFloat;
 use rustc_hash::{FxHashMap, FxHashSet, FxHasher};
 use std::{
     collections::hash_map::Entry,
@@ -244,7 +245,15 @@ macro_rules! impl_default_normalized_comparison {
         $(impl_default_normalized_comparison!($type);)*
     };
 }
-impl_default_normalized_comparison!(BigInt, BuiltinFunction, hir::Id, Module, String, usize);
+impl_default_normalized_comparison!(
+    BigInt,
+    BuiltinFunction,
+    hir::Id,
+    Module,
+    OrderedFloat<f64>,
+    String,
+    usize
+);
 impl<T: NormalizedComparison> NormalizedComparison for Option<T> {
     fn equals_normalized(
         &self,
@@ -358,6 +367,9 @@ impl NormalizedComparison for Expression {
             (Self::Int(self_int), Self::Int(other_int)) => {
                 self_int.equals_normalized(self_normalization, other_int, other_normalization)
             }
+            (Self::Float(self_float), Self::Float(other_float)) => {
+                self_float.equals_normalized(self_normalization, other_float, other_normalization)
+            }
             (Self::Text(self_text), Self::Text(other_text)) => {
                 self_text.equals_normalized(self_normalization, other_text, other_normalization)
             }
@@ -587,6 +599,7 @@ impl NormalizedComparison for Expression {
         mem::discriminant(self).hash(state);
         match self {
             Self::Int(int) => int.hash_normalized(normalization, state),
+            Self::Float(float) => float.hash_normalized(normalization, state),
             Self::Text(text) => text.hash_normalized(normalization, state),
             Self::Tag { symbol, value } => {
                 symbol.hash_normalized(normalization, state);
@@ -26,6 +26,10 @@
 //! errors from previous compilation stages can possibly also be executed at
 //! compile-time.
 //!
+//! Calls of functions with only constant arguments are run by the
+//! [`Evaluator`]. This also covers recursive functions, which inlining can't
+//! handle.
+//!
 //! [tree shaking]: super::tree_shaking
 
 use super::{
@@ -34,14 +38,16 @@ use super::{
 };
 use crate::{
     builtin_functions::BuiltinFunction,
-    format::{format_value, FormatValue, MaxLength, Precedence},
+    format::{format_float, format_value, FormatValue, MaxLength, Precedence},
     id::IdGenerator,
-    mir::{Body, Expression, Id, VisibleExpressions},
+    mir::{Body, Evaluator, Expression, Id, VisibleExpressions},
+    utils::GraphemeAwareSearch,
 };
 use itertools::Itertools;
 use num_bigint::BigInt;
 use num_integer::Integer;
-use num_traits::{ToPrimitive, Zero};
+use num_traits::{FromPrimitive, Signed, ToPrimitive, Zero};
+use ordered_float::OrderedFloat;
 use std::{
     borrow::Cow,
     cmp::Ordering,
@@ -50,6 +56,9 @@ use std::{
 use tracing::warn;
 use unicode_segmentation::UnicodeSegmentation;
 
+/// How much work the [`Evaluator`] may do for a single call.
+const EVALUATION_FUEL: usize = 10_000;
+
 pub fn fold_constants(context: &mut Context, expression: &mut CurrentExpression) {
     let Expression::Call {
         function,
@@ -89,6 +98,25 @@ pub fn fold_constants(context: &mut Context, expression: &mut CurrentExpression)
             };
             expression.replace_with(result, context.pureness);
         }
+        Expression::Function { .. } => {
+            let is_const = |id: &Id| {
+                context
+                    .pureness
+                    .is_definition_const(context.visible.get(*id))
+            };
+            if !is_const(function) || !arguments.iter().all(is_const) {
+                return;
+            }
+
+            let mut evaluator = Evaluator::new(context.visible, EVALUATION_FUEL);
+            let Ok(result) = evaluator.call(*function, arguments) else {
+                return;
+            };
+            let Some(body) = evaluator.to_body(result, context.id_generator) else {
+                return;
+            };
+            expression.replace_with_multiple(body, context.pureness);
+        }
         _ => {}
     }
 }
@@ -113,10 +141,83 @@ fn run_builtin(
     );
 
     let result = match builtin {
+        BuiltinFunction::BytesConcat => return None,
+        BuiltinFunction::BytesGet => return None,
+        BuiltinFunction::BytesLength => return None,
+        BuiltinFunction::BytesSlice => return None,
+        BuiltinFunction::CompareTo => {
+            let [a, b] = arguments else { unreachable!() };
+            if a.semantically_equals(*b, visible, pureness) == Some(true) {
+                return Some(Ordering::Equal.into());
+            }
+
+            // Values of different kinds are ordered by their kind in the VM. We
+            // only fold comparisons of values of the same kind.
+            match (visible.get(*a), visible.get(*b)) {
+                (Expression::Int(a), Expression::Int(b)) => a.cmp(b).into(),
+                (Expression::Text(a), Expression::Text(b)) => a.cmp(b).into(),
+                _ => return None,
+            }
+        }
         BuiltinFunction::Equals => {
             let [a, b] = arguments else { unreachable!() };
             a.semantically_equals(*b, visible, pureness)?.into()
         }
+        BuiltinFunction::FloatAdd => {
+            let [a, b] = arguments else { unreachable!() };
+            let a: &OrderedFloat<f64> = visible.get(*a).try_into().ok()?;
+            let b: &OrderedFloat<f64> = visible.get(*b).try_into().ok()?;
+            (a + b).into()
+        }
+        BuiltinFunction::FloatCompareTo => {
+            let [a, b] = arguments else { unreachable!() };
+            if a.semantically_equals(*b, visible, pureness) == Some(true) {
+                return Some(Ordering::Equal.into());
+            }
+
+            let a: &OrderedFloat<f64> = visible.get(*a).try_into().ok()?;
+            let b: &OrderedFloat<f64> = visible.get(*b).try_into().ok()?;
+            a.cmp(b).into()
+        }
+        BuiltinFunction::FloatDivide => {
+            let [dividend, divisor] = arguments else {
+                unreachable!()
+            };
+            let dividend: &OrderedFloat<f64> = visible.get(*dividend).try_into().ok()?;
+            let divisor: &OrderedFloat<f64> = visible.get(*divisor).try_into().ok()?;
+            (dividend / divisor).into()
+        }
+        BuiltinFunction::FloatFromInt => {
+            let [int] = arguments else { unreachable!() };
+            let int: &BigInt = visible.get(*int).try_into().ok()?;
+            int.to_f64()?.into()
+        }
+        BuiltinFunction::FloatMultiply => {
+            let [factor_a, factor_b] = arguments else {
+                unreachable!()
+            };
+            let factor_a: &OrderedFloat<f64> = visible.get(*factor_a).try_into().ok()?;
+            let factor_b: &OrderedFloat<f64> = visible.get(*factor_b).try_into().ok()?;
+            (factor_a * factor_b).into()
+        }
+        BuiltinFunction::FloatSubtract => {
+            let [minuend, subtrahend] = arguments else {
+                unreachable!()
+            };
+            let minuend: &OrderedFloat<f64> = visible.get(*minuend).try_into().ok()?;
+            let subtrahend: &OrderedFloat<f64> = visible.get(*subtrahend).try_into().ok()?;
+            (minuend - subtrahend).into()
+        }
+        BuiltinFunction::FloatToText => {
+            let [float] = arguments else { unreachable!() };
+            let float: &OrderedFloat<f64> = visible.get(*float).try_into().ok()?;
+            format_float(**float).into()
+        }
+        BuiltinFunction::FloatTruncate => {
+            let [float] = arguments else { unreachable!() };
+            let float: &OrderedFloat<f64> = visible.get(*float).try_into().ok()?;
+            BigInt::from_f64(float.trunc())?.into()
+        }
         BuiltinFunction::FunctionRun => {
             let [function] = arguments else {
                 unreachable!()
@@ -127,6 +228,7 @@ fn run_builtin(
                 responsible,
             }
         }
+        BuiltinFunction::FunctionTry => return None,
         BuiltinFunction::GetArgumentCount => {
             let [function] = arguments else {
                 unreachable!()
@@ -137,6 +239,8 @@ fn run_builtin(
                 _ => return None,
             }
         }
+        // The hash is computed by the VM.
+        BuiltinFunction::HashValue => return None,
         BuiltinFunction::IfElse => {
             let [condition, then, else_] = arguments else {
                 unreachable!()
@@ -232,6 +336,12 @@ fn run_builtin(
             let divisor: &BigInt = visible.get(*divisor).try_into().ok()?;
             (dividend / divisor).into()
         }
+        BuiltinFunction::IntGcd => {
+            let [a, b] = arguments else { unreachable!() };
+            let a: &BigInt = visible.get(*a).try_into().ok()?;
+            let b: &BigInt = visible.get(*b).try_into().ok()?;
+            a.gcd(b).into()
+        }
         BuiltinFunction::IntModulo => {
             let [dividend, divisor] = arguments else {
                 unreachable!()
@@ -264,6 +374,20 @@ fn run_builtin(
             expression.replace_with_multiple(body, pureness);
             return None;
         }
+        BuiltinFunction::IntPow => {
+            let [base, exponent] = arguments else {
+                unreachable!()
+            };
+            let base: &BigInt = visible.get(*base).try_into().ok()?;
+            let exponent: &BigInt = visible.get(*exponent).try_into().ok()?;
+            // Negative exponents panic at runtime and big results would bloat
+            // the byte code.
+            let exponent = exponent.to_u32()?;
+            if base.bits() * u64::from(exponent) > 1 << 16 {
+                return None;
+            }
+            base.pow(exponent).into()
+        }
         BuiltinFunction::IntRemainder => {
             let [dividend, divisor] = arguments else {
                 unreachable!()
@@ -281,8 +405,8 @@ fn run_builtin(
                 unreachable!()
             };
             let amount: &BigInt = visible.get(*amount).try_into().ok()?;
-            // TODO: Support larger shift amounts.
-            let amount: u128 = amount.try_into().unwrap();
+            // Larger shifts panic in the VM.
+            let amount: u32 = amount.try_into().ok()?;
             if amount == 0 {
                 return Some(value.into());
             }
@@ -296,7 +420,7 @@ fn run_builtin(
             };
             let amount: &BigInt = visible.get(*amount).try_into().ok()?;
             // TODO: Support larger shift amounts.
-            let amount: u128 = amount.try_into().unwrap();
+            let amount: u128 = amount.try_into().ok()?;
             if amount == 0 {
                 return Some(value.into());
             }
@@ -304,6 +428,14 @@ fn run_builtin(
             let value: &BigInt = visible.get(*value).try_into().ok()?;
             (value >> amount).into()
         }
+        BuiltinFunction::IntSqrt => {
+            let [value] = arguments else { unreachable!() };
+            let value: &BigInt = visible.get(*value).try_into().ok()?;
+            if value.is_negative() {
+                return None;
+            }
+            value.sqrt().into()
+        }
         BuiltinFunction::IntSubtract => {
             let [minuend, subtrahend] = arguments else {
                 unreachable!()
@@ -316,6 +448,16 @@ fn run_builtin(
             let subtrahend: &BigInt = visible.get(*subtrahend).try_into().ok()?;
             (minuend - subtrahend).into()
         }
+        BuiltinFunction::ListConcatenate => {
+            let [a, b] = arguments else { unreachable!() };
+            let Expression::List(a) = visible.get(*a) else {
+                return None;
+            };
+            let Expression::List(b) = visible.get(*b) else {
+                return None;
+            };
+            a.iter().chain(b).copied().collect_vec().into()
+        }
         BuiltinFunction::ListFilled => {
             let [length, item] = arguments else {
                 unreachable!()
@@ -323,8 +465,8 @@ fn run_builtin(
             let Expression::Int(length) = visible.get(*length) else {
                 return None;
             };
-            // TODO: Support lists longer than `usize::MAX`.
-            vec![*item; length.to_usize().unwrap()].into()
+            // Invalid lengths panic in the VM.
+            vec![*item; length.to_usize()?].into()
         }
         BuiltinFunction::ListGet => {
             let [list, index] = arguments else {
@@ -349,7 +491,30 @@ fn run_builtin(
         }
         BuiltinFunction::ListRemoveAt => return None,
         BuiltinFunction::ListReplace => return None,
+        BuiltinFunction::ListSlice => {
+            let [list, start_inclusive, end_exclusive] = arguments else {
+                unreachable!()
+            };
+            let Expression::List(list) = visible.get(*list) else {
+                return None;
+            };
+            let Expression::Int(start_inclusive) = visible.get(*start_inclusive) else {
+                return None;
+            };
+            let Expression::Int(end_exclusive) = visible.get(*end_exclusive) else {
+                return None;
+            };
+            // TODO: Support lists longer than `usize::MAX`.
+            list.get(start_inclusive.to_usize()?..end_exclusive.to_usize()?)?
+                .to_vec()
+                .into()
+        }
+        BuiltinFunction::ListSort => return None,
+        BuiltinFunction::Panic => return None,
         BuiltinFunction::Print => return None,
+        BuiltinFunction::RegexCaptures => return None,
+        BuiltinFunction::RegexFind => return None,
+        BuiltinFunction::RegexIsMatch => return None,
         BuiltinFunction::StructGet => {
             let [struct_, key] = arguments else {
                 unreachable!()
@@ -390,7 +555,8 @@ fn run_builtin(
                 return None;
             }
         }
-        BuiltinFunction::StructGetKeys => return None,
+        // The order of entries depends on how the VM compares keys.
+        BuiltinFunction::StructEntries | BuiltinFunction::StructGetKeys => return None,
         BuiltinFunction::StructHasKey => {
             let [struct_, key] = arguments else {
                 unreachable!()
@@ -418,6 +584,41 @@ fn run_builtin(
 
             is_contained?.into()
         }
+        BuiltinFunction::StructInsert => {
+            let [struct_, key, value] = arguments else {
+                unreachable!()
+            };
+            let mut fields = fields_without_key(visible.get(*struct_), *key, visible, pureness)?;
+            fields.push((*key, *value));
+            Expression::Struct(fields)
+        }
+        BuiltinFunction::StructMerge => {
+            let [a, b] = arguments else { unreachable!() };
+            let Expression::Struct(b_fields) = visible.get(*b) else {
+                return None;
+            };
+
+            let mut fields = visible.get(*a).clone();
+            for (key, _) in b_fields {
+                fields = Expression::Struct(fields_without_key(&fields, *key, visible, pureness)?);
+            }
+            let Expression::Struct(mut fields) = fields else {
+                unreachable!()
+            };
+            fields.extend(b_fields.iter().copied());
+            Expression::Struct(fields)
+        }
+        BuiltinFunction::StructRemove => {
+            let [struct_, key] = arguments else {
+                unreachable!()
+            };
+            Expression::Struct(fields_without_key(
+                visible.get(*struct_),
+                *key,
+                visible,
+                pureness,
+            )?)
+        }
         BuiltinFunction::TagGetValue => {
             let [tag] = arguments else { unreachable!() };
             let Expression::Tag {
@@ -591,6 +792,31 @@ fn run_builtin(
                 .collect::<String>()
                 .into()
         }
+        BuiltinFunction::TextIndexOf => {
+            let [text, pattern] = arguments else {
+                unreachable!()
+            };
+            let Expression::Text(text) = visible.get(*text) else {
+                return None;
+            };
+            let Expression::Text(pattern) = visible.get(*pattern) else {
+                return None;
+            };
+            let Some(index) = text.grapheme_index_of(pattern) else {
+                return Some(Expression::tag("NotFound".to_string()));
+            };
+            let mut body = Body::default();
+            let index = body.push_with_new_id(id_generator, index);
+            body.push_with_new_id(
+                id_generator,
+                Expression::Tag {
+                    symbol: "FoundAt".to_string(),
+                    value: Some(index),
+                },
+            );
+            expression.replace_with_multiple(body, pureness);
+            return None;
+        }
         BuiltinFunction::TextIsEmpty => {
             let [text] = arguments else { unreachable!() };
             let Expression::Text(text) = visible.get(*text) else {
@@ -605,6 +831,41 @@ fn run_builtin(
             };
             text.graphemes(true).count().into()
         }
+        BuiltinFunction::TextReplace => {
+            let [text, pattern, replacement] = arguments else {
+                unreachable!()
+            };
+            let Expression::Text(text) = visible.get(*text) else {
+                return None;
+            };
+            let Expression::Text(pattern) = visible.get(*pattern) else {
+                return None;
+            };
+            let Expression::Text(replacement) = visible.get(*replacement) else {
+                return None;
+            };
+            Expression::Text(text.grapheme_replace(pattern, replacement))
+        }
+        BuiltinFunction::TextSplit => {
+            let [text, separator] = arguments else {
+                unreachable!()
+            };
+            let Expression::Text(text) = visible.get(*text) else {
+                return None;
+            };
+            let Expression::Text(separator) = visible.get(*separator) else {
+                return None;
+            };
+            let mut body = Body::default();
+            let parts = text
+                .grapheme_split(separator)
+                .into_iter()
+                .map(|it| body.push_with_new_id(id_generator, it))
+                .collect_vec();
+            body.push_with_new_id(id_generator, parts);
+            expression.replace_with_multiple(body, pureness);
+            return None;
+        }
         BuiltinFunction::TextStartsWith => {
             let [text, suffix] = arguments else {
                 unreachable!()
@@ -635,6 +896,21 @@ fn run_builtin(
             };
             text.trim_start().into()
         }
+        BuiltinFunction::TextToLower => {
+            let [text] = arguments else { unreachable!() };
+            let Expression::Text(text) = visible.get(*text) else {
+                return None;
+            };
+            Expression::Text(text.to_lowercase())
+        }
+        BuiltinFunction::TextToUpper => {
+            let [text] = arguments else { unreachable!() };
+            let Expression::Text(text) = visible.get(*text) else {
+                return None;
+            };
+            Expression::Text(text.to_uppercase())
+        }
+        BuiltinFunction::TextToUtf8Bytes => return None,
         BuiltinFunction::ToDebugText => {
             let [argument] = arguments else {
                 unreachable!()
@@ -643,6 +919,7 @@ fn run_builtin(
                 format_value(*argument, Precedence::Low, MaxLength::Unlimited, &|id| {
                     Some(match visible.get(id) {
                         Expression::Int(int) => FormatValue::Int(Cow::Borrowed(int)),
+                        Expression::Float(float) => FormatValue::Float(**float),
                         Expression::Text(text) => FormatValue::Text(text),
                         Expression::Tag { symbol, value } => FormatValue::Tag {
                             symbol,
@@ -660,6 +937,7 @@ fn run_builtin(
         BuiltinFunction::TypeOf => Expression::tag(
             match visible.get(arguments[0]) {
                 Expression::Int(_) => "Int",
+                Expression::Float(_) => "Float",
                 Expression::Text(_) => "Text",
                 Expression::Tag { .. } => "Tag",
                 Expression::Builtin(_) => "Function",
@@ -675,9 +953,24 @@ fn run_builtin(
                         return None;
                     };
                     match builtin {
+                        BuiltinFunction::BytesConcat => "Bytes",
+                        BuiltinFunction::BytesGet => "Int",
+                        BuiltinFunction::BytesLength => "Int",
+                        BuiltinFunction::BytesSlice => "Bytes",
+                        BuiltinFunction::CompareTo => "Tag",
                         BuiltinFunction::Equals => "Tag",
+                        BuiltinFunction::FloatAdd => "Float",
+                        BuiltinFunction::FloatCompareTo => "Tag",
+                        BuiltinFunction::FloatDivide => "Float",
+                        BuiltinFunction::FloatFromInt => "Float",
+                        BuiltinFunction::FloatMultiply => "Float",
+                        BuiltinFunction::FloatSubtract => "Float",
+                        BuiltinFunction::FloatToText => "Text",
+                        BuiltinFunction::FloatTruncate => "Int",
                         BuiltinFunction::GetArgumentCount => "Int",
+                        BuiltinFunction::HashValue => "Int",
                         BuiltinFunction::FunctionRun => return None,
+                        BuiltinFunction::FunctionTry => "Tag",
                         BuiltinFunction::IfElse => return None,
                         BuiltinFunction::IntAdd => "Int",
                         BuiltinFunction::IntBitLength => "Int",
@@ -686,23 +979,37 @@ fn run_builtin(
                         BuiltinFunction::IntBitwiseXor => "Int",
                         BuiltinFunction::IntCompareTo => "Tag",
                         BuiltinFunction::IntDivideTruncating => "Int",
+                        BuiltinFunction::IntGcd => "Int",
                         BuiltinFunction::IntModulo => "Int",
                         BuiltinFunction::IntMultiply => "Int",
                         BuiltinFunction::IntParse => "Struct",
+                        BuiltinFunction::IntPow => "Int",
                         BuiltinFunction::IntRemainder => "Int",
                         BuiltinFunction::IntShiftLeft => "Int",
                         BuiltinFunction::IntShiftRight => "Int",
+                        BuiltinFunction::IntSqrt => "Int",
                         BuiltinFunction::IntSubtract => "Int",
+                        BuiltinFunction::ListConcatenate => "List",
                         BuiltinFunction::ListFilled => "List",
                         BuiltinFunction::ListGet => return None,
                         BuiltinFunction::ListInsert => "List",
                         BuiltinFunction::ListLength => "Int",
                         BuiltinFunction::ListRemoveAt => "List",
                         BuiltinFunction::ListReplace => "List",
+                        BuiltinFunction::ListSlice => "List",
+                        BuiltinFunction::ListSort => "List",
+                        BuiltinFunction::Panic => return None,
                         BuiltinFunction::Print => "Tag",
+                        BuiltinFunction::RegexCaptures => "Tag",
+                        BuiltinFunction::RegexFind => "Tag",
+                        BuiltinFunction::RegexIsMatch => "Tag",
+                        BuiltinFunction::StructEntries => "List",
                         BuiltinFunction::StructGet => return None,
                         BuiltinFunction::StructGetKeys => "List",
                         BuiltinFunction::StructHasKey => "Tag",
+                        BuiltinFunction::StructInsert => "Struct",
+                        BuiltinFunction::StructMerge => "Struct",
+                        BuiltinFunction::StructRemove => "Struct",
                         BuiltinFunction::TagGetValue => return None,
                         BuiltinFunction::TagHasValue => "Tag",
                         BuiltinFunction::TagWithoutValue => "Tag",
@@ -713,11 +1020,17 @@ fn run_builtin(
                         BuiltinFunction::TextEndsWith => "Tag",
                         BuiltinFunction::TextFromUtf8 => "Struct",
                         BuiltinFunction::TextGetRange => "Text",
+                        BuiltinFunction::TextIndexOf => "Tag",
                         BuiltinFunction::TextIsEmpty => "Tag",
                         BuiltinFunction::TextLength => "Int",
+                        BuiltinFunction::TextReplace => "Text",
+                        BuiltinFunction::TextSplit => "List",
                         BuiltinFunction::TextStartsWith => "Tag",
                         BuiltinFunction::TextTrimEnd => "Text",
                         BuiltinFunction::TextTrimStart => "Text",
+                        BuiltinFunction::TextToLower => "Text",
+                        BuiltinFunction::TextToUpper => "Text",
+                        BuiltinFunction::TextToUtf8Bytes => "Bytes",
                         BuiltinFunction::ToDebugText => "Text",
                         BuiltinFunction::TypeOf => "Tag",
                     }
@@ -735,3 +1048,26 @@ fn run_builtin(
     };
     Some(result)
 }
+
+/// Returns the fields of a statically known struct without the given key.
+///
+/// Returns `None` if the struct is unknown or if it's unclear whether one of
+/// its keys equals the given key.
+fn fields_without_key(
+    struct_: &Expression,
+    key: Id,
+    visible: &VisibleExpressions,
+    pureness: &PurenessInsights,
+) -> Option<Vec<(Id, Id)>> {
+    let Expression::Struct(fields) = struct_ else {
+        return None;
+    };
+
+    let mut remaining_fields = vec![];
+    for (field_key, field_value) in fields {
+        if !field_key.semantically_equals(key, visible, pureness)? {
+            remaining_fields.push((*field_key, *field_value));
+        }
+    }
+    Some(remaining_fields)
+}
@@ -3,18 +3,27 @@ use crate::{
     error::CompilerError,
     id::IdGenerator,
     mir::{Body, Expression, Id, VisibleExpressions},
+    module::Module,
+    tracing::CallTracingMode,
     TracingConfig,
 };
-use rustc_hash::FxHashSet;
+use rustc_hash::{FxHashMap, FxHashSet};
 use std::ops::Deref;
 
 pub struct Context<'a> {
     pub db: &'a dyn OptimizeMir,
+    /// The config that the module is compiled with, without applying the
+    /// module's overrides, so that imported modules inherit the right modes.
     pub tracing: &'a TracingConfig,
+    /// The call tracing mode of the module, including its overrides.
+    pub call_tracing_mode: CallTracingMode,
     pub errors: &'a mut FxHashSet<CompilerError>,
     pub visible: &'a mut VisibleExpressions,
     pub id_generator: &'a mut IdGenerator<Id>,
     pub pureness: &'a mut PurenessInsights,
+    /// Large modules that were already folded, mapped to the ID of their
+    /// result. See [`super::module_folding`].
+    pub folded_modules: FxHashMap<Module, Id>,
 }
 
 pub struct CurrentExpression<'a> {
@@ -44,6 +44,7 @@
 
 use self::{
     current_expression::{Context, CurrentExpression},
+    passes::BodyPass,
     pure::PurenessInsights,
 };
 use super::{hir, hir_to_mir::HirToMir, mir::Mir, tracing::TracingConfig};
@@ -51,12 +52,20 @@ use crate::{
     error::CompilerError,
     hir_to_mir::ExecutionTarget,
     mir::{Body, Expression, MirError, VisibleExpressions},
+    module::Module,
+    rich_ir::ToRichIr,
     string_to_rcst::ModuleError,
+    timings::{self, Stage},
     utils::DoHash,
 };
-use rustc_hash::FxHashSet;
+use rustc_hash::{FxHashMap, FxHashSet};
 use std::{mem, sync::Arc};
-use tracing::debug;
+use tracing::{debug, error};
+
+pub use self::{
+    module_folding::MAX_DUPLICATED_MODULE_EXPRESSIONS,
+    passes::{BuiltinMirPass, CustomMirPass, MirPassError, MirPasses, MirPassesOwner},
+};
 
 mod after_panic;
 mod call_tracing;
@@ -68,6 +77,7 @@ mod constant_lifting;
 mod current_expression;
 mod inlining;
 mod module_folding;
+mod passes;
 mod pure;
 mod reference_following;
 mod tail_calls;
@@ -76,7 +86,7 @@ mod utils;
 mod validate;
 
 #[salsa::query_group(OptimizeMirStorage)]
-pub trait OptimizeMir: HirToMir {
+pub trait OptimizeMir: HirToMir + MirPassesOwner {
     fn optimized_mir(&self, target: ExecutionTarget, tracing: TracingConfig) -> OptimizedMirResult;
 
     #[salsa::cycle(recover_from_cycle)]
@@ -107,7 +117,9 @@ fn optimized_mir(
     let (mir, _, errors) = db.optimized_mir_without_tail_calls(target, tracing)?;
     let mut mir = (*mir).clone();
 
-    tail_calls::simplify_tail_call_tracing(&mut mir);
+    timings::record_pass("tail-calls", || {
+        tail_calls::simplify_tail_call_tracing(&mut mir);
+    });
 
     Ok((Arc::new(mir), errors))
 }
@@ -120,13 +132,15 @@ fn optimized_mir_without_tail_calls(
 ) -> OptimizedMirWithoutTailCallsResult {
     let module = target.module();
     debug!("{module}: Compiling.");
-    let (mir, errors) = db.mir(target.clone(), tracing)?;
+    let (mir, errors) = db.mir(target.clone(), tracing.clone())?;
     let mut mir = (*mir).clone();
     let mut pureness = PurenessInsights::default();
     let mut errors = (*errors).clone();
 
     let complexity_before = mir.complexity();
-    mir.optimize(db, &tracing, &mut pureness, &mut errors);
+    timings::record_stage(Stage::MirOptimization, module.clone(), || {
+        mir.optimize(db, module, &tracing, &mut pureness, &mut errors);
+    });
     let complexity_after = mir.complexity();
 
     debug!("{module}: Done. Optimized from {complexity_before} to {complexity_after}");
@@ -137,6 +151,7 @@ impl Mir {
     pub fn optimize(
         &mut self,
         db: &dyn OptimizeMir,
+        module: &Module,
         tracing: &TracingConfig,
         pureness: &mut PurenessInsights,
         errors: &mut FxHashSet<CompilerError>,
@@ -144,16 +159,25 @@ impl Mir {
         let mut context = Context {
             db,
             tracing,
+            call_tracing_mode: tracing.for_module(module).calls,
             errors,
             visible: &mut VisibleExpressions::none_visible(),
             id_generator: &mut self.id_generator,
             pureness,
+            folded_modules: FxHashMap::default(),
         };
         context.optimize_body(&mut self.body);
         if cfg!(debug_assertions) {
             self.validate();
         }
-        self.cleanup(pureness);
+
+        let before = db.mir_passes().is_checked().then(|| self.clone());
+        timings::record_pass("cleanup", || self.cleanup(pureness));
+        if let Some(before) = before
+            && let Err(error) = self.check()
+        {
+            report_invalid_mir("cleanup", "", &error, &before, self);
+        }
     }
 }
 
@@ -171,7 +195,12 @@ impl Context<'_> {
             }
             self.pureness.visit_optimized(expression.id(), &expression);
 
-            module_folding::apply(self, &mut expression);
+            self.run_expression_pass(
+                BuiltinMirPass::ModuleFolding,
+                &mut expression,
+                None,
+                module_folding::apply,
+            );
 
             let new_id = expression.id();
             index = expression.index() + 1;
@@ -183,36 +212,134 @@ impl Context<'_> {
             *expression = self.visible.remove(*id);
         }
 
-        after_panic::remove_expressions_after_panic(body, self.pureness);
-        common_subtree_elimination::eliminate_common_subtrees(body, self.pureness);
-        {
-            // Reference following
-            let mut index = 0;
-            while index < body.expressions.len() {
-                // Thoroughly optimize the expression.
-                let mut expression = CurrentExpression::new(body, index);
-                reference_following::follow_references(self, &mut expression);
-                if cfg!(debug_assertions) {
-                    expression.validate(self.visible);
-                }
-                self.pureness.visit_optimized(expression.id(), &expression);
+        let db = self.db;
+        for pass in db.mir_passes().body_passes() {
+            match pass {
+                BodyPass::Builtin(pass) => self.run_builtin_body_pass(pass, body),
+                BodyPass::Custom(pass) => self.run_body_pass(pass.name(), body, |context, body| {
+                    pass.optimize_body(body, context.id_generator);
+                }),
+            }
+        }
+        if self.is_enabled(BuiltinMirPass::ReferenceFollowing) {
+            self.run_body_pass(
+                BuiltinMirPass::ReferenceFollowing.as_ref(),
+                body,
+                |context, body| {
+                    reference_following::remove_redundant_return_references(body, context.pureness);
+                },
+            );
+        }
+    }
+    fn run_builtin_body_pass(&mut self, pass: BuiltinMirPass, body: &mut Body) {
+        if !self.is_enabled(pass) {
+            return;
+        }
+        self.run_body_pass(pass.as_ref(), body, |context, body| match pass {
+            BuiltinMirPass::AfterPanic => {
+                after_panic::remove_expressions_after_panic(body, context.pureness);
+            }
+            BuiltinMirPass::CommonSubtreeElimination => {
+                common_subtree_elimination::eliminate_common_subtrees(body, context.pureness);
+            }
+            BuiltinMirPass::ReferenceFollowing => {
+                let mut index = 0;
+                while index < body.expressions.len() {
+                    let mut expression = CurrentExpression::new(body, index);
+                    reference_following::follow_references(context, &mut expression);
+                    if cfg!(debug_assertions) {
+                        expression.validate(context.visible);
+                    }
+                    context
+                        .pureness
+                        .visit_optimized(expression.id(), &expression);
 
-                let new_id = expression.id();
-                index = expression.index() + 1;
-                let expression =
-                    mem::replace(expression.get_mut_carefully(), Expression::Parameter);
-                self.visible.insert(new_id, expression);
+                    let new_id = expression.id();
+                    index = expression.index() + 1;
+                    let expression =
+                        mem::replace(expression.get_mut_carefully(), Expression::Parameter);
+                    context.visible.insert(new_id, expression);
+                }
+                for (id, expression) in &mut body.expressions {
+                    *expression = context.visible.remove(*id);
+                }
+            }
+            BuiltinMirPass::CallTracing => {
+                call_tracing::remove_unnecessary_call_tracing(
+                    body,
+                    context.pureness,
+                    context.call_tracing_mode,
+                );
             }
-            for (id, expression) in &mut body.expressions {
-                *expression = self.visible.remove(*id);
+            BuiltinMirPass::TreeShaking => tree_shaking::tree_shake(body, context.pureness),
+            BuiltinMirPass::ConstantFolding
+            | BuiltinMirPass::Inlining
+            | BuiltinMirPass::ConstantLifting
+            | BuiltinMirPass::ModuleFolding => {
+                unreachable!("`{}` isn't a body pass.", pass.as_ref())
             }
+        });
+    }
+
+    fn is_enabled(&self, pass: BuiltinMirPass) -> bool {
+        self.db.mir_passes().is_enabled(pass.as_ref())
+    }
+    /// Runs a pass on a body and, in checked mode, validates the body
+    /// afterwards.
+    fn run_body_pass(
+        &mut self,
+        name: &str,
+        body: &mut Body,
+        run: impl FnOnce(&mut Self, &mut Body),
+    ) {
+        let before = self.db.mir_passes().is_checked().then(|| body.clone());
+        timings::record_pass(name, || run(self, body));
+        if let Some(before) = before
+            && let Err(error) =
+                body.check(&mut FxHashSet::default(), im::HashSet::new(), self.visible)
+        {
+            report_invalid_mir(name, "", &error, &before, body);
+        }
+    }
+    /// Runs a built-in pass on an expression if the pass is enabled and, in
+    /// checked mode, validates the expression afterwards.
+    ///
+    /// `iteration` is the iteration of the fixed-point loop in which the pass
+    /// runs, if any.
+    fn run_expression_pass(
+        &mut self,
+        pass: BuiltinMirPass,
+        expression: &mut CurrentExpression,
+        iteration: Option<usize>,
+        run: impl FnOnce(&mut Self, &mut CurrentExpression),
+    ) {
+        if !self.is_enabled(pass) {
+            return;
+        }
+        let before = self
+            .db
+            .mir_passes()
+            .is_checked()
+            .then(|| (**expression).clone());
+        timings::record_pass(pass.as_ref(), || run(self, expression));
+        if let Some(before) = before
+            && let Err(error) = expression.check(self.visible)
+        {
+            let location = iteration.map_or_else(
+                || format!(" for {}", expression.id()),
+                |iteration| {
+                    format!(
+                        " for {} in iteration {iteration} of the fixed-point loop",
+                        expression.id(),
+                    )
+                },
+            );
+            report_invalid_mir(pass.as_ref(), &location, &error, &before, &**expression);
         }
-        call_tracing::remove_unnecessary_call_tracing(body, self.pureness, self.tracing.calls);
-        tree_shaking::tree_shake(body, self.pureness);
-        reference_following::remove_redundant_return_references(body, self.pureness);
     }
 
     fn optimize_expression(&mut self, expression: &mut CurrentExpression) {
+        let mut iteration = 0;
         'outer: loop {
             if let Expression::Function {
                 parameters,
@@ -238,16 +365,34 @@ impl Context<'_> {
             }
 
             loop {
+                iteration += 1;
                 let hashcode_before = expression.do_hash();
 
-                reference_following::follow_references(self, expression);
-                constant_folding::fold_constants(self, expression);
+                self.run_expression_pass(
+                    BuiltinMirPass::ReferenceFollowing,
+                    expression,
+                    Some(iteration),
+                    reference_following::follow_references,
+                );
+                self.run_expression_pass(
+                    BuiltinMirPass::ConstantFolding,
+                    expression,
+                    Some(iteration),
+                    constant_folding::fold_constants,
+                );
 
                 let is_call = matches!(**expression, Expression::Call { .. });
-                inlining::inline_tiny_functions(self, expression);
-                inlining::inline_needs_function(self, expression);
-                inlining::inline_functions_containing_use(self, expression);
-                inlining::inline_calls_with_constant_arguments(self, expression);
+                self.run_expression_pass(
+                    BuiltinMirPass::Inlining,
+                    expression,
+                    Some(iteration),
+                    |context, expression| {
+                        inlining::inline_tiny_functions(context, expression);
+                        inlining::inline_needs_function(context, expression);
+                        inlining::inline_functions_containing_use(context, expression);
+                        inlining::inline_calls_with_constant_arguments(context, expression);
+                    },
+                );
                 if is_call && matches!(**expression, Expression::Function { .. }) {
                     // We inlined a function call and the resulting code starts with
                     // a function definition. We need to visit that first before
@@ -255,7 +400,12 @@ impl Context<'_> {
                     continue 'outer;
                 }
 
-                constant_lifting::lift_constants(self, expression);
+                self.run_expression_pass(
+                    BuiltinMirPass::ConstantLifting,
+                    expression,
+                    Some(iteration),
+                    constant_lifting::lift_constants,
+                );
 
                 if expression.do_hash() == hashcode_before {
                     break 'outer;
@@ -265,6 +415,23 @@ impl Context<'_> {
     }
 }
 
+fn report_invalid_mir(
+    pass: &str,
+    location: &str,
+    error: &str,
+    before: &impl ToRichIr,
+    after: &impl ToRichIr,
+) -> ! {
+    let before = before.to_rich_ir(true).text;
+    let after = after.to_rich_ir(true).text;
+    error!("The `{pass}` pass produced invalid MIR{location}: {error}");
+    error!(
+        "This is how the pass changed the MIR:\n{}",
+        diffy::create_patch(&before, &after),
+    );
+    panic!("The `{pass}` pass produced invalid MIR.");
+}
+
 #[allow(clippy::trivially_copy_pass_by_ref, clippy::unnecessary_wraps)]
 fn recover_from_cycle(
     _db: &dyn OptimizeMir,
@@ -24,6 +24,12 @@
 //! and compiling other modules. Module folding is a necessity for building
 //! binaries that don't include the Candy compiler itself.
 //!
+//! Because each import copies the imported module's code, importing a large
+//! module several times can blow up the generated code. Modules with more than
+//! [`MAX_DUPLICATED_MODULE_EXPRESSIONS`] expressions are therefore only folded
+//! once per scope: Later imports of the same module reference the result of the
+//! earlier import if it's still visible.
+//!
 //! [constant folding]: super::constant_folding
 //! [inlining]: super::inlining
 
@@ -34,10 +40,16 @@ use crate::{
     id::IdGenerator,
     mir::{Body, BodyBuilder, Expression, Id, MirError},
     module::{Module, UsePath},
+    timings,
 };
+use itertools::Itertools;
 use rustc_hash::FxHashMap;
 use std::mem;
 
+/// Modules bigger than this are not duplicated if an earlier import of the same
+/// module is visible.
+pub const MAX_DUPLICATED_MODULE_EXPRESSIONS: usize = 100;
+
 pub fn apply(context: &mut Context, expression: &mut CurrentExpression) {
     let Expression::UseModule {
         current_module,
@@ -100,6 +112,14 @@ pub fn apply(context: &mut Context, expression: &mut CurrentExpression) {
         }
     };
 
+    if let Some(result) = context.folded_modules.get(&module_to_import)
+        && context.visible.contains(*result)
+    {
+        expression.replace_with(Expression::Reference(*result), context.pureness);
+        return;
+    }
+
+    timings::record_folding_request(&module_to_import);
     match context.db.optimized_mir_without_tail_calls(
         ExecutionTarget::Module(module_to_import.clone()),
         context.tracing.for_child_module(),
@@ -107,10 +127,12 @@ pub fn apply(context: &mut Context, expression: &mut CurrentExpression) {
         Ok((mir, other_pureness, more_errors)) => {
             context.errors.extend(more_errors.iter().cloned());
 
+            // Generating IDs in a fixed order keeps the output deterministic.
             let mapping: FxHashMap<Id, Id> = mir
                 .body
                 .all_ids()
                 .into_iter()
+                .sorted()
                 .map(|id| (id, context.id_generator.generate()))
                 .collect();
 
@@ -127,10 +149,11 @@ pub fn apply(context: &mut Context, expression: &mut CurrentExpression) {
                     (mapping[&id], expression)
                 }),
             );
-            expression.replace_with(
-                Expression::Reference(mapping[&mir.body.return_value()]),
-                context.pureness,
-            );
+            let result = mapping[&mir.body.return_value()];
+            expression.replace_with(Expression::Reference(result), context.pureness);
+            if mir.complexity().expressions > MAX_DUPLICATED_MODULE_EXPRESSIONS {
+                context.folded_modules.insert(module_to_import, result);
+            }
         }
         Err(error) => {
             context
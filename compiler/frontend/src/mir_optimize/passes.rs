@@ -0,0 +1,334 @@
+//! The optimization passes that run on the MIR.
+//!
+//! All built-in passes are enabled by default, but each of them can be
+//! disabled, e.g., to find out which pass causes a miscompilation. Embedders
+//! can also register their own passes, which run on each body after the
+//! built-in body passes they depend on.
+//!
+//! In checked mode, the MIR is validated after each pass. If a pass produces
+//! invalid MIR, the compiler panics with the name of the pass and a diff of
+//! the code before and after the pass. Passes that run repeatedly on an
+//! expression also report in which iteration of the loop the MIR broke.
+//!
+//! Databases provide the passes to use through [`MirPassesOwner`]. Already
+//! optimized MIR isn't invalidated when the passes change, so they should be
+//! configured before compiling anything.
+
+use crate::{
+    id::IdGenerator,
+    mir::{Body, Id},
+};
+use itertools::Itertools;
+use std::{
+    fmt::{self, Debug, Display, Formatter},
+    sync::Arc,
+};
+use strum_macros::{AsRefStr, VariantArray};
+
+#[derive(AsRefStr, Clone, Copy, Debug, Eq, Hash, PartialEq, VariantArray)]
+#[strum(serialize_all = "kebab-case")]
+pub enum BuiltinMirPass {
+    // These run repeatedly on each expression until it no longer changes.
+    ReferenceFollowing,
+    ConstantFolding,
+    #[strum(serialize = "inline")]
+    Inlining,
+    ConstantLifting,
+
+    // This runs once on each expression after it has been optimized. It can't
+    // be disabled because the LIR can't represent module uses.
+    ModuleFolding,
+
+    // These run once on each body after all of its expressions have been
+    // optimized.
+    AfterPanic,
+    #[strum(serialize = "cse")]
+    CommonSubtreeElimination,
+    CallTracing,
+    TreeShaking,
+}
+impl BuiltinMirPass {
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        <Self as strum::VariantArray>::VARIANTS
+            .iter()
+            .find(|pass| pass.as_ref() == name)
+            .copied()
+    }
+
+    /// Whether the compiler can't produce code without this pass.
+    #[must_use]
+    pub const fn is_required(self) -> bool {
+        matches!(self, Self::ModuleFolding)
+    }
+}
+
+/// A pass defined outside of the compiler.
+pub trait CustomMirPass: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// The names of built-in or previously registered passes that have to run
+    /// before this pass.
+    fn run_after(&self) -> Vec<String> {
+        vec![]
+    }
+
+    fn optimize_body(&self, body: &mut Body, id_generator: &mut IdGenerator<Id>);
+}
+
+#[derive(Clone, Default)]
+pub struct MirPasses {
+    disabled: Vec<String>,
+    is_checked: bool,
+    /// Custom passes in the order in which they were registered.
+    custom: Vec<Arc<dyn CustomMirPass>>,
+}
+impl MirPasses {
+    /// All built-in passes, but no custom ones.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            disabled: vec![],
+            is_checked: false,
+            custom: vec![],
+        }
+    }
+
+    #[must_use]
+    pub fn is_enabled(&self, name: &str) -> bool {
+        !self.disabled.iter().any(|it| it == name)
+    }
+    pub fn enable(&mut self, name: &str) -> Result<(), MirPassError> {
+        self.check_exists(name)?;
+        self.disabled.retain(|it| it != name);
+        Ok(())
+    }
+    pub fn disable(&mut self, name: &str) -> Result<(), MirPassError> {
+        self.check_exists(name)?;
+        if BuiltinMirPass::from_name(name).is_some_and(BuiltinMirPass::is_required) {
+            return Err(MirPassError::RequiredPass(name.to_string()));
+        }
+        if self.is_enabled(name) {
+            self.disabled.push(name.to_string());
+        }
+        Ok(())
+    }
+    #[must_use]
+    pub const fn is_checked(&self) -> bool {
+        self.is_checked
+    }
+    pub fn set_checked(&mut self, is_checked: bool) {
+        self.is_checked = is_checked;
+    }
+
+    /// Applies changes in the format `+inline,-cse`, i.e., a comma-separated
+    /// list of pass names prefixed with `+` to enable or `-` to disable them.
+    pub fn apply_changes(&mut self, changes: &str) -> Result<(), MirPassError> {
+        for change in changes
+            .split(',')
+            .map(str::trim)
+            .filter(|it| !it.is_empty())
+        {
+            if let Some(name) = change.strip_prefix('+') {
+                self.enable(name)?;
+            } else if let Some(name) = change.strip_prefix('-') {
+                self.disable(name)?;
+            } else {
+                return Err(MirPassError::InvalidChange(change.to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn register(&mut self, pass: Arc<dyn CustomMirPass>) -> Result<(), MirPassError> {
+        let name = pass.name();
+        if self.exists(name) {
+            return Err(MirPassError::DuplicatePass(name.to_string()));
+        }
+        if let Some(dependency) = pass.run_after().into_iter().find(|it| !self.exists(it)) {
+            return Err(MirPassError::UnknownPass(dependency));
+        }
+        self.custom.push(pass);
+        Ok(())
+    }
+
+    fn exists(&self, name: &str) -> bool {
+        BuiltinMirPass::from_name(name).is_some() || self.custom.iter().any(|it| it.name() == name)
+    }
+    fn check_exists(&self, name: &str) -> Result<(), MirPassError> {
+        if self.exists(name) {
+            Ok(())
+        } else {
+            Err(MirPassError::UnknownPass(name.to_string()))
+        }
+    }
+
+    /// The enabled passes that run once on each body, in order.
+    ///
+    /// Custom passes run directly after the last pass they depend on or after
+    /// all built-in passes if they don't depend on any pass.
+    pub(super) fn body_passes(&self) -> Vec<BodyPass> {
+        let mut passes = [
+            BuiltinMirPass::AfterPanic,
+            BuiltinMirPass::CommonSubtreeElimination,
+            BuiltinMirPass::ReferenceFollowing,
+            BuiltinMirPass::CallTracing,
+            BuiltinMirPass::TreeShaking,
+        ]
+        .into_iter()
+        .map(BodyPass::Builtin)
+        .collect_vec();
+        for pass in &self.custom {
+            let index = pass
+                .run_after()
+                .iter()
+                .filter_map(|dependency| passes.iter().position(|it| it.name() == dependency))
+                .max()
+                .map_or(passes.len(), |index| index + 1);
+            passes.insert(index, BodyPass::Custom(pass.clone()));
+        }
+        passes.retain(|pass| self.is_enabled(pass.name()));
+        passes
+    }
+}
+impl Debug for MirPasses {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MirPasses")
+            .field("disabled", &self.disabled)
+            .field("is_checked", &self.is_checked)
+            .field(
+                "custom",
+                &self.custom.iter().map(|it| it.name()).collect_vec(),
+            )
+            .finish()
+    }
+}
+
+pub(super) enum BodyPass {
+    Builtin(BuiltinMirPass),
+    Custom(Arc<dyn CustomMirPass>),
+}
+impl BodyPass {
+    pub(super) fn name(&self) -> &str {
+        match self {
+            Self::Builtin(pass) => pass.as_ref(),
+            Self::Custom(pass) => pass.name(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MirPassError {
+    DuplicatePass(String),
+    InvalidChange(String),
+    RequiredPass(String),
+    UnknownPass(String),
+}
+impl Display for MirPassError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuplicatePass(name) => write!(f, "There's already a pass named `{name}`."),
+            Self::InvalidChange(change) => write!(
+                f,
+                "`{change}` should start with `+` to enable or `-` to disable a pass.",
+            ),
+            Self::RequiredPass(name) => {
+                write!(f, "The pass `{name}` is required and can't be disabled.")
+            }
+            Self::UnknownPass(name) => write!(f, "There's no pass named `{name}`."),
+        }
+    }
+}
+
+/// Databases can provide [`MirPasses`] to configure the MIR optimization.
+pub trait MirPassesOwner {
+    fn mir_passes(&self) -> &MirPasses {
+        static DEFAULT: MirPasses = MirPasses::new();
+        &DEFAULT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct NoOpPass {
+        name: &'static str,
+        run_after: Vec<String>,
+    }
+    impl CustomMirPass for NoOpPass {
+        fn name(&self) -> &str {
+            self.name
+        }
+        fn run_after(&self) -> Vec<String> {
+            self.run_after.clone()
+        }
+        fn optimize_body(&self, _body: &mut Body, _id_generator: &mut IdGenerator<Id>) {}
+    }
+
+    fn body_pass_names(passes: &MirPasses) -> Vec<String> {
+        passes
+            .body_passes()
+            .iter()
+            .map(|it| it.name().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn changes_enable_and_disable_passes() {
+        let mut passes = MirPasses::new();
+        passes.apply_changes("-inline,-cse,+inline").unwrap();
+        assert!(passes.is_enabled("inline"));
+        assert!(!passes.is_enabled("cse"));
+
+        assert_eq!(
+            passes.apply_changes("-foo"),
+            Err(MirPassError::UnknownPass("foo".to_string())),
+        );
+        assert_eq!(
+            passes.apply_changes("-module-folding"),
+            Err(MirPassError::RequiredPass("module-folding".to_string())),
+        );
+        assert_eq!(
+            passes.apply_changes("inline"),
+            Err(MirPassError::InvalidChange("inline".to_string())),
+        );
+    }
+
+    #[test]
+    fn custom_passes_run_after_their_dependencies() {
+        let mut passes = MirPasses::new();
+        passes
+            .register(Arc::new(NoOpPass {
+                name: "last",
+                run_after: vec![],
+            }))
+            .unwrap();
+        passes
+            .register(Arc::new(NoOpPass {
+                name: "early",
+                run_after: vec!["after-panic".to_string()],
+            }))
+            .unwrap();
+        passes.disable("call-tracing").unwrap();
+
+        assert_eq!(
+            body_pass_names(&passes),
+            [
+                "after-panic",
+                "early",
+                "cse",
+                "reference-following",
+                "tree-shaking",
+                "last",
+            ],
+        );
+        assert_eq!(
+            passes.register(Arc::new(NoOpPass {
+                name: "unordered",
+                run_after: vec!["unknown".to_string()],
+            })),
+            Err(MirPassError::UnknownPass("unknown".to_string())),
+        );
+    }
+}
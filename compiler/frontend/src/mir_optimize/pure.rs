@@ -56,12 +56,16 @@ pub struct PurenessInsights {
     pure_definitions: IdSet,
     pure_functions: IdSet,
     const_definitions: IdSet,
+    /// Definitions of `✨.ifElse`. Its calls are deterministic if both
+    /// branches are.
+    if_else_builtins: IdSet,
 }
 impl PurenessInsights {
     #[must_use]
     pub fn is_definition_deterministic(&self, expression: &Expression) -> bool {
         match expression {
             Expression::Int(_)
+            | Expression::Float(_)
             | Expression::Text(_)
             | Expression::Tag { .. }
             | Expression::Builtin(_)
@@ -72,7 +76,20 @@ impl PurenessInsights {
             | Expression::Function { .. }
             | Expression::Parameter
             | Expression::Panic { .. } => true,
-            Expression::Call { function, .. } => self.deterministic_functions.contains(*function),
+            Expression::Call {
+                function,
+                arguments,
+                ..
+            } => {
+                self.deterministic_functions.contains(*function)
+                    || (self.if_else_builtins.contains(*function)
+                        && matches!(
+                            arguments.as_slice(),
+                            [_, then, else_]
+                                if self.deterministic_functions.contains(*then)
+                                    && self.deterministic_functions.contains(*else_),
+                        ))
+            }
             Expression::UseModule { .. }
             | Expression::TraceCallStarts { .. }
             | Expression::TraceCallEnds { .. }
@@ -85,8 +102,22 @@ impl PurenessInsights {
     pub fn is_function_deterministic(&self, expression: &Expression) -> bool {
         match expression {
             Expression::Builtin(builtin) => match builtin {
-                BuiltinFunction::Equals
+                BuiltinFunction::BytesConcat
+                | BuiltinFunction::BytesGet
+                | BuiltinFunction::BytesLength
+                | BuiltinFunction::BytesSlice
+                | BuiltinFunction::CompareTo
+                | BuiltinFunction::Equals
+                | BuiltinFunction::FloatAdd
+                | BuiltinFunction::FloatCompareTo
+                | BuiltinFunction::FloatDivide
+                | BuiltinFunction::FloatFromInt
+                | BuiltinFunction::FloatMultiply
+                | BuiltinFunction::FloatSubtract
+                | BuiltinFunction::FloatToText
+                | BuiltinFunction::FloatTruncate
                 | BuiltinFunction::GetArgumentCount
+                | BuiltinFunction::HashValue
                 | BuiltinFunction::IntAdd
                 | BuiltinFunction::IntBitLength
                 | BuiltinFunction::IntBitwiseAnd
@@ -94,22 +125,34 @@ impl PurenessInsights {
                 | BuiltinFunction::IntBitwiseXor
                 | BuiltinFunction::IntCompareTo
                 | BuiltinFunction::IntDivideTruncating
+                | BuiltinFunction::IntGcd
                 | BuiltinFunction::IntModulo
                 | BuiltinFunction::IntMultiply
                 | BuiltinFunction::IntParse
+                | BuiltinFunction::IntPow
                 | BuiltinFunction::IntRemainder
                 | BuiltinFunction::IntShiftLeft
                 | BuiltinFunction::IntShiftRight
+                | BuiltinFunction::IntSqrt
                 | BuiltinFunction::IntSubtract
+                | BuiltinFunction::ListConcatenate
                 | BuiltinFunction::ListFilled
                 | BuiltinFunction::ListGet
                 | BuiltinFunction::ListInsert
                 | BuiltinFunction::ListLength
                 | BuiltinFunction::ListRemoveAt
                 | BuiltinFunction::ListReplace
+                | BuiltinFunction::ListSlice
+                | BuiltinFunction::RegexCaptures
+                | BuiltinFunction::RegexFind
+                | BuiltinFunction::RegexIsMatch
+                | BuiltinFunction::StructEntries
                 | BuiltinFunction::StructGet
                 | BuiltinFunction::StructGetKeys
                 | BuiltinFunction::StructHasKey
+                | BuiltinFunction::StructInsert
+                | BuiltinFunction::StructMerge
+                | BuiltinFunction::StructRemove
                 | BuiltinFunction::TagGetValue
                 | BuiltinFunction::TagHasValue
                 | BuiltinFunction::TagWithoutValue
@@ -120,22 +163,32 @@ impl PurenessInsights {
                 | BuiltinFunction::TextEndsWith
                 | BuiltinFunction::TextFromUtf8
                 | BuiltinFunction::TextGetRange
+                | BuiltinFunction::TextIndexOf
                 | BuiltinFunction::TextIsEmpty
                 | BuiltinFunction::TextLength
+                | BuiltinFunction::TextReplace
+                | BuiltinFunction::TextSplit
                 | BuiltinFunction::TextStartsWith
                 | BuiltinFunction::TextTrimEnd
                 | BuiltinFunction::TextTrimStart
+                | BuiltinFunction::TextToLower
+                | BuiltinFunction::TextToUpper
+                | BuiltinFunction::TextToUtf8Bytes
                 | BuiltinFunction::ToDebugText
                 | BuiltinFunction::TypeOf => true,
-                BuiltinFunction::FunctionRun | BuiltinFunction::IfElse | BuiltinFunction::Print => {
-                    false
-                }
+                BuiltinFunction::FunctionRun
+                | BuiltinFunction::FunctionTry
+                | BuiltinFunction::IfElse
+                | BuiltinFunction::ListSort
+                | BuiltinFunction::Panic
+                | BuiltinFunction::Print => false,
             },
             Expression::Function { body, .. } => body
                 .iter()
                 .all(|(_, expression)| self.is_definition_deterministic(expression)),
             Expression::Tag { .. } => true, // either works or panics
             Expression::Int(_)
+            | Expression::Float(_)
             | Expression::Text(_)
             | Expression::List(_)
             | Expression::Struct(_)
@@ -153,6 +206,11 @@ impl PurenessInsights {
         }
     }
 
+    #[must_use]
+    pub const fn deterministic_functions(&self) -> &IdSet {
+        &self.deterministic_functions
+    }
+
     #[must_use]
     pub const fn pure_definitions(&self) -> &IdSet {
         &self.pure_definitions
@@ -161,6 +219,7 @@ impl PurenessInsights {
     pub fn is_definition_pure(&self, expression: &Expression) -> bool {
         match expression {
             Expression::Int(_)
+            | Expression::Float(_)
             | Expression::Text(_)
             | Expression::Tag { .. }
             | Expression::Builtin(_)
@@ -184,8 +243,22 @@ impl PurenessInsights {
     pub fn is_function_pure(&self, expression: &Expression) -> bool {
         match expression {
             Expression::Builtin(builtin) => match builtin {
-                BuiltinFunction::Equals
+                BuiltinFunction::BytesConcat
+                | BuiltinFunction::BytesGet
+                | BuiltinFunction::BytesLength
+                | BuiltinFunction::BytesSlice
+                | BuiltinFunction::CompareTo
+                | BuiltinFunction::Equals
+                | BuiltinFunction::FloatAdd
+                | BuiltinFunction::FloatCompareTo
+                | BuiltinFunction::FloatDivide
+                | BuiltinFunction::FloatFromInt
+                | BuiltinFunction::FloatMultiply
+                | BuiltinFunction::FloatSubtract
+                | BuiltinFunction::FloatToText
+                | BuiltinFunction::FloatTruncate
                 | BuiltinFunction::GetArgumentCount
+                | BuiltinFunction::HashValue
                 | BuiltinFunction::IntAdd
                 | BuiltinFunction::IntBitLength
                 | BuiltinFunction::IntBitwiseAnd
@@ -193,22 +266,34 @@ impl PurenessInsights {
                 | BuiltinFunction::IntBitwiseXor
                 | BuiltinFunction::IntCompareTo
                 | BuiltinFunction::IntDivideTruncating
+                | BuiltinFunction::IntGcd
                 | BuiltinFunction::IntModulo
                 | BuiltinFunction::IntMultiply
                 | BuiltinFunction::IntParse
+                | BuiltinFunction::IntPow
                 | BuiltinFunction::IntRemainder
                 | BuiltinFunction::IntShiftLeft
                 | BuiltinFunction::IntShiftRight
+                | BuiltinFunction::IntSqrt
                 | BuiltinFunction::IntSubtract
+                | BuiltinFunction::ListConcatenate
                 | BuiltinFunction::ListFilled
                 | BuiltinFunction::ListGet
                 | BuiltinFunction::ListInsert
                 | BuiltinFunction::ListLength
                 | BuiltinFunction::ListRemoveAt
                 | BuiltinFunction::ListReplace
+                | BuiltinFunction::ListSlice
+                | BuiltinFunction::RegexCaptures
+                | BuiltinFunction::RegexFind
+                | BuiltinFunction::RegexIsMatch
+                | BuiltinFunction::StructEntries
                 | BuiltinFunction::StructGet
                 | BuiltinFunction::StructGetKeys
                 | BuiltinFunction::StructHasKey
+                | BuiltinFunction::StructInsert
+                | BuiltinFunction::StructMerge
+                | BuiltinFunction::StructRemove
                 | BuiltinFunction::TagGetValue
                 | BuiltinFunction::TagHasValue
                 | BuiltinFunction::TagWithoutValue
@@ -219,16 +304,25 @@ impl PurenessInsights {
                 | BuiltinFunction::TextEndsWith
                 | BuiltinFunction::TextFromUtf8
                 | BuiltinFunction::TextGetRange
+                | BuiltinFunction::TextIndexOf
                 | BuiltinFunction::TextIsEmpty
                 | BuiltinFunction::TextLength
+                | BuiltinFunction::TextReplace
+                | BuiltinFunction::TextSplit
                 | BuiltinFunction::TextStartsWith
                 | BuiltinFunction::TextTrimEnd
                 | BuiltinFunction::TextTrimStart
+                | BuiltinFunction::TextToLower
+                | BuiltinFunction::TextToUpper
+                | BuiltinFunction::TextToUtf8Bytes
                 | BuiltinFunction::ToDebugText
                 | BuiltinFunction::TypeOf => true,
-                BuiltinFunction::FunctionRun | BuiltinFunction::IfElse | BuiltinFunction::Print => {
-                    false
-                }
+                BuiltinFunction::FunctionRun
+                | BuiltinFunction::FunctionTry
+                | BuiltinFunction::IfElse
+                | BuiltinFunction::ListSort
+                | BuiltinFunction::Panic
+                | BuiltinFunction::Print => false,
             },
             Expression::Function { body, .. } => body
                 .iter()
@@ -266,6 +360,10 @@ impl PurenessInsights {
             self.const_definitions.insert(id);
         }
 
+        if matches!(expression, Expression::Builtin(BuiltinFunction::IfElse)) {
+            self.if_else_builtins.insert(id);
+        }
+
         // TODO: Don't optimize lifted constants again.
         // Then, we can also add asserts here about not visiting them twice.
     }
@@ -290,6 +388,7 @@ impl PurenessInsights {
         update(&mut self.pure_definitions, mapping);
         update(&mut self.pure_functions, mapping);
         update(&mut self.const_definitions, mapping);
+        update(&mut self.if_else_builtins, mapping);
     }
     pub(super) fn on_remove(&mut self, id: Id) {
         let Self {
@@ -298,12 +397,14 @@ impl PurenessInsights {
             pure_definitions,
             pure_functions,
             const_definitions,
+            if_else_builtins,
         } = self;
         deterministic_definitions.remove(id);
         deterministic_functions.remove(id);
         pure_definitions.remove(id);
         pure_functions.remove(id);
         const_definitions.remove(id);
+        if_else_builtins.remove(id);
     }
     pub(super) fn include(&mut self, other: &Self, mapping: &FxHashMap<Id, Id>) {
         fn insert(source: &IdSet, mapping: &FxHashMap<Id, Id>, target: &mut IdSet) {
@@ -333,6 +434,7 @@ impl PurenessInsights {
             mapping,
             &mut self.const_definitions,
         );
+        insert(&other.if_else_builtins, mapping, &mut self.if_else_builtins);
     }
 }
 
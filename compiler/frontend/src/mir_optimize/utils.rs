@@ -56,7 +56,7 @@ impl Expression {
     }
     fn collect_referenced_ids(&self, referenced: &mut FxHashSet<Id>) {
         match self {
-            Self::Int(_) | Self::Text(_) | Self::Builtin(_) | Self::HirId(_) => {}
+            Self::Int(_) | Self::Float(_) | Self::Text(_) | Self::Builtin(_) | Self::HirId(_) => {}
             Self::Tag { value, .. } => {
                 if let Some(value) = value {
                     referenced.insert(*value);
@@ -199,6 +199,7 @@ impl Id {
 
         match (self_expr, other_expr) {
             (Expression::Int(a), Expression::Int(b)) => Some(a == b),
+            (Expression::Float(a), Expression::Float(b)) => Some(a == b),
             (Expression::Text(a), Expression::Text(b)) => Some(a == b),
             (
                 Expression::Tag {
@@ -240,12 +241,14 @@ impl Id {
             // Expressions have different types.
             (
                 Expression::Int(_)
+                | Expression::Float(_)
                 | Expression::Text(_)
                 | Expression::Tag { .. }
                 | Expression::Builtin(_)
                 | Expression::List(_)
                 | Expression::Struct(_),
                 Expression::Int(_)
+                | Expression::Float(_)
                 | Expression::Text(_)
                 | Expression::Tag { .. }
                 | Expression::Builtin(_)
@@ -262,7 +265,7 @@ impl Expression {
     /// this expression.
     pub fn replace_id_references(&mut self, replacer: &mut impl FnMut(&mut Id)) {
         match self {
-            Self::Int(_) | Self::Text(_) | Self::Builtin(_) | Self::HirId(_) => {}
+            Self::Int(_) | Self::Float(_) | Self::Text(_) | Self::Builtin(_) | Self::HirId(_) => {}
             Self::Tag { value, .. } => {
                 if let Some(value) = value {
                     replacer(value);
@@ -4,24 +4,52 @@ use tracing::error;
 
 impl Mir {
     pub fn validate(&self) {
-        self.body
-            .validate(&mut FxHashSet::default(), im::HashSet::new());
+        if let Err(error) = self.check() {
+            error!("{error}");
+            error!("This is the MIR:\n{self}");
+            panic!("MIR is invalid!");
+        }
+    }
+    pub fn check(&self) -> Result<(), String> {
+        self.body.check(
+            &mut FxHashSet::default(),
+            im::HashSet::new(),
+            &VisibleExpressions::none_visible(),
+        )
     }
 }
 
 impl Body {
-    pub fn validate(&self, defined_ids: &mut FxHashSet<Id>, mut visible: im::HashSet<Id>) {
-        if self.expressions.is_empty() {
-            error!("A body of a function is empty! Functions should have at least a return value.");
+    pub fn validate(&self, defined_ids: &mut FxHashSet<Id>, visible: im::HashSet<Id>) {
+        if let Err(error) = self.check(defined_ids, visible, &VisibleExpressions::none_visible()) {
+            error!("{error}");
             error!("This is the MIR:\n{self}");
             panic!("MIR is invalid!");
         }
+    }
+    /// Checks that the body isn't empty, only captures visible IDs, and defines
+    /// each ID only once.
+    ///
+    /// IDs are visible if they're in `visible` or `outer`, or if they're
+    /// defined earlier in the body.
+    pub fn check(
+        &self,
+        defined_ids: &mut FxHashSet<Id>,
+        mut visible: im::HashSet<Id>,
+        outer: &VisibleExpressions,
+    ) -> Result<(), String> {
+        if self.expressions.is_empty() {
+            return Err(
+                "A body of a function is empty! Functions should have at least a return value."
+                    .to_string(),
+            );
+        }
         for (id, expression) in self.iter() {
             for captured in expression.captured_ids() {
-                if !visible.contains(&captured) {
-                    error!("MIR is invalid! {id} captures {captured}, but that's not visible.");
-                    error!("This is the MIR:\n{self}");
-                    panic!("MIR is invalid!");
+                if !visible.contains(&captured) && !outer.contains(captured) {
+                    return Err(format!(
+                        "MIR is invalid! {id} captures {captured}, but that's not visible.",
+                    ));
                 }
             }
             if let Expression::Function {
@@ -34,29 +62,36 @@ impl Body {
                 let mut inner_visible = visible.clone();
                 inner_visible.extend(parameters.iter().copied());
                 inner_visible.insert(*responsible_parameter);
-                body.validate(defined_ids, inner_visible);
+                body.check(defined_ids, inner_visible, outer)?;
             }
 
             if defined_ids.contains(&id) {
-                error!("ID {id} exists twice.");
-                error!("This is the MIR:\n{self}");
-                panic!("MIR is invalid!");
+                return Err(format!("ID {id} exists twice."));
             }
             defined_ids.insert(id);
 
             visible.insert(id);
         }
+        Ok(())
     }
 }
 
 impl Expression {
     pub fn validate(&self, visible: &VisibleExpressions) {
+        if let Err(error) = self.check(visible) {
+            println!("{error}");
+            println!("{self}");
+            panic!("Expression references ID that is not in its scope.");
+        }
+    }
+    pub fn check(&self, visible: &VisibleExpressions) -> Result<(), String> {
         for id in self.captured_ids() {
             if !visible.contains(id) {
-                println!("Expression references ID {id:?}, but that ID is not visible:");
-                println!("{self}");
-                panic!("Expression references ID that is not in its scope.");
+                return Err(format!(
+                    "Expression references ID {id:?}, but that ID is not visible.",
+                ));
             }
         }
+        Ok(())
     }
 }
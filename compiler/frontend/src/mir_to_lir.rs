@@ -7,6 +7,7 @@ use crate::{
     mir::{self},
     mir_optimize::OptimizeMir,
     string_to_rcst::ModuleError,
+    timings::{self, Stage},
     utils::{HashMapExtension, HashSetExtension},
     TracingConfig,
 };
@@ -23,17 +24,24 @@ pub type LirResult = Result<(Arc<Lir>, Arc<FxHashSet<CompilerError>>), ModuleErr
 
 fn lir(db: &dyn MirToLir, target: ExecutionTarget, tracing: TracingConfig) -> LirResult {
     let module = target.module().clone();
-    let (mir, errors) = db.optimized_mir(target, tracing)?;
+    let (mir, errors) = db.optimized_mir(target.clone(), tracing.clone())?;
 
     let mut context = LoweringContext::default();
-    context.compile_function(
-        FxHashSet::from_iter([hir::Id::new(module, vec![])]),
-        &[],
-        &[],
-        mir::Id::from_usize(0),
-        &mir.body,
-    );
-    let lir = Lir::new(context.constants, context.bodies);
+    if tracing.memoize_calls {
+        let (_, pureness, _) = db.optimized_mir_without_tail_calls(target, tracing)?;
+        context.deterministic_functions = pureness.deterministic_functions().iter().collect();
+    }
+    let lir = timings::record_stage(Stage::Lir, module.clone(), || {
+        context.compile_function(
+            FxHashSet::from_iter([hir::Id::new(module.clone(), vec![])]),
+            &[],
+            &[],
+            mir::Id::from_usize(0),
+            &mir.body,
+            false,
+        );
+        Lir::new(context.constants, context.bodies)
+    });
 
     Ok((Arc::new(lir), errors))
 }
@@ -43,11 +51,18 @@ struct LoweringContext {
     constants: lir::Constants,
     constant_mapping: FxHashMap<mir::Id, lir::ConstantId>,
     bodies: lir::Bodies,
+    deterministic_functions: FxHashSet<mir::Id>,
 }
 impl LoweringContext {
     fn constant_for(&self, id: mir::Id) -> Option<lir::ConstantId> {
         self.constant_mapping.get(&id).copied()
     }
+    fn hir_id_for(&self, id: mir::Id) -> Option<hir::Id> {
+        match self.constants.get(self.constant_for(id)?) {
+            lir::Constant::HirId(hir_id) => Some(hir_id.clone()),
+            _ => None,
+        }
+    }
 
     fn compile_function(
         &mut self,
@@ -56,6 +71,7 @@ impl LoweringContext {
         parameters: &[mir::Id],
         responsible_parameter: mir::Id,
         body: &mir::Body,
+        is_memoized: bool,
     ) -> lir::BodyId {
         let body = CurrentBody::compile_function(
             self,
@@ -64,6 +80,7 @@ impl LoweringContext {
             parameters,
             responsible_parameter,
             body,
+            is_memoized,
         );
         self.bodies.push(body)
     }
@@ -74,6 +91,8 @@ struct CurrentBody {
     id_mapping: FxHashMap<mir::Id, lir::Id>,
     body: lir::Body,
     current_constant: Option<mir::Id>,
+    /// The origin of the MIR expression that is currently being compiled.
+    current_origin: Option<hir::Id>,
     ids_to_drop: FxHashSet<lir::Id>,
 }
 impl CurrentBody {
@@ -84,10 +103,18 @@ impl CurrentBody {
         parameters: &[mir::Id],
         responsible_parameter: mir::Id,
         body: &mir::Body,
+        is_memoized: bool,
     ) -> lir::Body {
-        let mut lir_body = Self::new(original_hirs, captured, parameters, responsible_parameter);
+        let mut lir_body = Self::new(
+            original_hirs,
+            captured,
+            parameters,
+            responsible_parameter,
+            is_memoized,
+        );
         for (id, expression) in body.iter() {
             lir_body.current_constant = None;
+            lir_body.current_origin = Self::origin_of(context, expression);
             lir_body.compile_expression(context, id, expression);
         }
         lir_body.finish(&context.constant_mapping)
@@ -98,8 +125,9 @@ impl CurrentBody {
         captured: &[mir::Id],
         parameters: &[mir::Id],
         responsible_parameter: mir::Id,
+        is_memoized: bool,
     ) -> Self {
-        let body = lir::Body::new(original_hirs, captured.len(), parameters.len());
+        let body = lir::Body::new(original_hirs, captured.len(), parameters.len(), is_memoized);
         let id_mapping: FxHashMap<_, _> = captured
             .iter()
             .chain(parameters.iter())
@@ -131,10 +159,33 @@ impl CurrentBody {
             id_mapping,
             body,
             current_constant: None,
+            current_origin: None,
             ids_to_drop,
         }
     }
 
+    /// The HIR ID of the source code that a MIR expression originates from.
+    ///
+    /// MIR expressions don't store their origin, but calls and panics are
+    /// responsible for their call site and trace expressions reference the
+    /// traced HIR expression.
+    fn origin_of(context: &LoweringContext, expression: &mir::Expression) -> Option<hir::Id> {
+        match expression {
+            mir::Expression::Function { original_hirs, .. } => original_hirs.iter().min().cloned(),
+            mir::Expression::Call { responsible, .. }
+            | mir::Expression::Panic { responsible, .. } => context.hir_id_for(*responsible),
+            mir::Expression::TraceCallStarts { hir_call, .. }
+            | mir::Expression::TraceTailCall { hir_call, .. } => context.hir_id_for(*hir_call),
+            mir::Expression::TraceExpressionEvaluated { hir_expression, .. } => {
+                context.hir_id_for(*hir_expression)
+            }
+            mir::Expression::TraceFoundFuzzableFunction { hir_definition, .. } => {
+                context.hir_id_for(*hir_definition)
+            }
+            _ => None,
+        }
+    }
+
     fn compile_expression(
         &mut self,
         context: &mut LoweringContext,
@@ -143,6 +194,7 @@ impl CurrentBody {
     ) {
         match expression {
             mir::Expression::Int(int) => self.push_constant(context, id, int.clone()),
+            mir::Expression::Float(float) => self.push_constant(context, id, *float),
             mir::Expression::Text(text) => self.push_constant(context, id, text.clone()),
             mir::Expression::Tag { symbol, value } => {
                 if let Some(value) = value {
@@ -240,12 +292,21 @@ impl CurrentBody {
                     .sorted()
                     .collect_vec();
 
+                // Memoizing only pays off for functions that do some work, i.e.,
+                // that call other functions.
+                let is_memoized = context.deterministic_functions.contains(&id)
+                    && !parameters.is_empty()
+                    && body
+                        .iter()
+                        .any(|(_, it)| matches!(it, mir::Expression::Call { .. }));
+
                 let body_id = context.compile_function(
                     original_hirs.clone(),
                     &captured,
                     parameters,
                     *responsible_parameter,
                     body,
+                    is_memoized,
                 );
                 if captured.is_empty() {
                     self.push_constant(context, id, body_id);
@@ -396,7 +457,9 @@ impl CurrentBody {
     fn push(&mut self, mir_id: mir::Id, expression: impl Into<lir::Expression>) -> lir::Id {
         let expression = expression.into();
         let is_constant = matches!(expression, lir::Expression::Constant(_));
-        let id = self.body.push(expression);
+        let id = self
+            .body
+            .push_with_origin(expression, self.current_origin.clone());
         self.id_mapping.force_insert(mir_id, id);
         if !is_constant {
             self.ids_to_drop.force_insert(id);
@@ -406,7 +469,8 @@ impl CurrentBody {
     /// Push an expression that doesn't produce a return value, i.e., a trace
     /// expression.
     fn push_without_value(&mut self, expression: impl Into<lir::Expression>) {
-        self.body.push(expression.into());
+        self.body
+            .push_with_origin(expression.into(), self.current_origin.clone());
     }
 
     fn maybe_dup(&mut self, id: lir::Id) {
@@ -430,7 +494,10 @@ impl CurrentBody {
             return;
         }
 
-        self.body.push(lir::Expression::Dup { id, amount: 1 });
+        self.body.push_with_origin(
+            lir::Expression::Dup { id, amount: 1 },
+            self.current_origin.clone(),
+        );
     }
     fn finish(mut self, constant_mapping: &FxHashMap<mir::Id, lir::ConstantId>) -> lir::Body {
         if let Some(current_constant) = self.current_constant {
@@ -1,7 +1,8 @@
 pub use self::{
     module::{Module, ModuleFromPathError, ModuleKind},
     module_provider::{
-        FileSystemModuleProvider, InMemoryModuleProvider, ModuleProvider, OverlayModuleProvider,
+        BundleModuleProvider, BundleModuleProviderBuilder, FileSystemModuleProvider,
+        InMemoryModuleProvider, ModuleProvider, OverlayModuleProvider,
     },
     module_provider_owner::{ModuleProviderOwner, MutableModuleProviderOwner},
     package::{Package, PackagesPath},
@@ -5,6 +5,7 @@ use crate::{
 };
 use enumset::EnumSet;
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 use std::{
     fmt::{self, Display, Formatter},
     fs,
@@ -13,13 +14,13 @@ use std::{
 };
 use tracing::{error, warn};
 
-#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub struct Module {
     pub package: Package,
     pub path: Vec<String>,
     pub kind: ModuleKind,
 }
-#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub enum ModuleKind {
     Code,
     Asset,
@@ -118,7 +119,13 @@ impl Module {
             match path.try_exists() {
                 Ok(true) => return Some(path),
                 Ok(false) => {}
-                Err(error) if matches!(error.kind(), std::io::ErrorKind::NotFound) => {}
+                // For a module `foo`, `foo/_.candy` can't exist if `foo` is a
+                // file.
+                Err(error)
+                    if matches!(
+                        error.kind(),
+                        std::io::ErrorKind::NotFound | std::io::ErrorKind::NotADirectory,
+                    ) => {}
                 Err(error) => error!("Unexpected error when reading file {path:?}: {error}."),
             }
         }
@@ -1,16 +1,39 @@
-use super::{module::Module, package::PackagesPath};
-use rustc_hash::FxHashMap;
+//! Where the source code of modules comes from.
+//!
+//! Providers can be layered: An [`OverlayModuleProvider`] answers from its
+//! overlay first and only falls back to the next layer for modules that the
+//! overlay doesn't know. A typical stack is an [`InMemoryModuleProvider`] with
+//! the files opened in an editor on top of a [`FileSystemModuleProvider`].
+//! Tooling that shouldn't touch the disk, e.g., when running a bundle or test
+//! fixtures, can use a [`BundleModuleProvider`] or an
+//! [`InMemoryModuleProvider`] as the bottom layer instead.
+
+use super::{
+    module::{Module, ModuleKind},
+    package::{Package, PackagesPath},
+};
+use itertools::Itertools;
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::{Deserialize, Serialize};
 use std::{fs, io, sync::Arc};
 use tracing::error;
 
 pub trait ModuleProvider {
     fn get_content(&self, module: &Module) -> Option<Arc<Vec<u8>>>;
+
+    /// The code modules of the `package` that this provider knows about.
+    ///
+    /// The order is unspecified.
+    fn modules_of_package(&self, package: &Package) -> Vec<Module>;
 }
 
 impl<M: ModuleProvider + ?Sized> ModuleProvider for Box<M> {
     fn get_content(&self, module: &Module) -> Option<Arc<Vec<u8>>> {
         self.as_ref().get_content(module)
     }
+    fn modules_of_package(&self, package: &Package) -> Vec<Module> {
+        self.as_ref().modules_of_package(package)
+    }
 }
 
 #[derive(Default)]
@@ -47,6 +70,9 @@ impl ModuleProvider for InMemoryModuleProvider {
     fn get_content(&self, module: &Module) -> Option<Arc<Vec<u8>>> {
         self.modules.get(module).cloned()
     }
+    fn modules_of_package(&self, package: &Package) -> Vec<Module> {
+        code_modules_of_package(self.modules.keys(), package)
+    }
 }
 
 pub struct FileSystemModuleProvider {
@@ -72,6 +98,36 @@ impl ModuleProvider for FileSystemModuleProvider {
         }
         None
     }
+    fn modules_of_package(&self, package: &Package) -> Vec<Module> {
+        let Some(path) = package.to_path(&self.packages_path) else {
+            return vec![];
+        };
+
+        let mut modules = vec![];
+        let mut directories = vec![path];
+        while let Some(directory) = directories.pop() {
+            let Ok(entries) = fs::read_dir(&directory) else {
+                continue;
+            };
+            for entry in entries.filter_map(Result::ok) {
+                let path = entry.path();
+                if path.is_dir() {
+                    directories.push(path);
+                } else if path.extension().is_some_and(|it| it == "candy")
+                    && path.file_name().is_some_and(|it| it != "_package.candy")
+                    && let Ok(module) = Module::from_package_and_path(
+                        &self.packages_path,
+                        package.clone(),
+                        &path,
+                        ModuleKind::Code,
+                    )
+                {
+                    modules.push(module);
+                }
+            }
+        }
+        modules
+    }
 }
 
 pub struct OverlayModuleProvider<O: ModuleProvider, F: ModuleProvider> {
@@ -89,4 +145,177 @@ impl<O: ModuleProvider, F: ModuleProvider> ModuleProvider for OverlayModuleProvi
             .get_content(module)
             .or_else(|| self.fallback.get_content(module))
     }
+    fn modules_of_package(&self, package: &Package) -> Vec<Module> {
+        let mut modules = self
+            .overlay
+            .modules_of_package(package)
+            .into_iter()
+            .collect::<FxHashSet<_>>();
+        modules.extend(self.fallback.modules_of_package(package));
+        modules.into_iter().collect()
+    }
+}
+
+/// A read-only set of modules, e.g., loaded from a bundle.
+///
+/// Contents are stored by content, so modules with identical source code
+/// share a single copy.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct BundleModuleProvider {
+    modules: FxHashMap<Module, usize>,
+    contents: Vec<Arc<Vec<u8>>>,
+}
+impl BundleModuleProvider {
+    #[must_use]
+    pub fn builder() -> BundleModuleProviderBuilder {
+        BundleModuleProviderBuilder::default()
+    }
+
+    pub fn modules(&self) -> impl Iterator<Item = &Module> {
+        self.modules.keys()
+    }
+    /// The number of distinct contents, which can be lower than the number of
+    /// modules.
+    #[must_use]
+    pub fn number_of_contents(&self) -> usize {
+        self.contents.len()
+    }
+}
+impl ModuleProvider for BundleModuleProvider {
+    fn get_content(&self, module: &Module) -> Option<Arc<Vec<u8>>> {
+        self.modules
+            .get(module)
+            .map(|index| self.contents[*index].clone())
+    }
+    fn modules_of_package(&self, package: &Package) -> Vec<Module> {
+        code_modules_of_package(self.modules.keys(), package)
+    }
+}
+
+#[derive(Default)]
+pub struct BundleModuleProviderBuilder {
+    modules: FxHashMap<Module, usize>,
+    contents: Vec<Arc<Vec<u8>>>,
+    indices_by_content: FxHashMap<Arc<Vec<u8>>, usize>,
+}
+impl BundleModuleProviderBuilder {
+    /// Adds a module, replacing a previously added module with the same name.
+    pub fn add(&mut self, module: Module, content: Vec<u8>) -> &mut Self {
+        let content = Arc::new(content);
+        let index = *self
+            .indices_by_content
+            .entry(content.clone())
+            .or_insert_with(|| {
+                self.contents.push(content);
+                self.contents.len() - 1
+            });
+        self.modules.insert(module, index);
+        self
+    }
+    /// Adds all modules of `provider` in the given `packages`.
+    pub fn add_packages_from(
+        &mut self,
+        provider: &dyn ModuleProvider,
+        packages: &[Package],
+    ) -> &mut Self {
+        for module in packages
+            .iter()
+            .flat_map(|package| provider.modules_of_package(package))
+            .sorted()
+        {
+            if let Some(content) = provider.get_content(&module) {
+                self.add(module, content.as_ref().clone());
+            }
+        }
+        self
+    }
+
+    #[must_use]
+    pub fn build(self) -> BundleModuleProvider {
+        BundleModuleProvider {
+            modules: self.modules,
+            contents: self.contents,
+        }
+    }
+}
+
+fn code_modules_of_package<'a>(
+    modules: impl Iterator<Item = &'a Module>,
+    package: &Package,
+) -> Vec<Module> {
+    modules
+        .filter(|it| &it.package == package && it.kind == ModuleKind::Code)
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn module(package: &Package, name: &str) -> Module {
+        Module {
+            package: package.clone(),
+            path: vec![name.to_string()],
+            kind: ModuleKind::Code,
+        }
+    }
+
+    #[test]
+    fn bundles_deduplicate_identical_contents() {
+        let package = Package::User(PathBuf::from("/non/existent"));
+        let mut builder = BundleModuleProvider::builder();
+        builder
+            .add(module(&package, "a"), b"x = 1".to_vec())
+            .add(module(&package, "b"), b"x = 1".to_vec())
+            .add(module(&package, "c"), b"x = 2".to_vec());
+        let bundle = builder.build();
+
+        assert_eq!(bundle.number_of_contents(), 2);
+        assert_eq!(
+            bundle
+                .get_content(&module(&package, "b"))
+                .unwrap()
+                .as_slice(),
+            b"x = 1",
+        );
+        assert_eq!(bundle.get_content(&module(&package, "d")), None);
+    }
+
+    #[test]
+    fn overlays_take_precedence_and_list_modules_of_all_layers() {
+        let package = Package::User(PathBuf::from("/non/existent"));
+        let mut builder = BundleModuleProvider::builder();
+        builder
+            .add(module(&package, "a"), b"x = 1".to_vec())
+            .add(module(&package, "b"), b"x = 2".to_vec());
+        let mut overlay = InMemoryModuleProvider::default();
+        overlay.add_str(&module(&package, "b"), "x = 3");
+        overlay.add_str(&module(&package, "c"), "x = 4");
+        let provider = OverlayModuleProvider::new(overlay, builder.build());
+
+        assert_eq!(
+            provider
+                .get_content(&module(&package, "b"))
+                .unwrap()
+                .as_slice(),
+            b"x = 3",
+        );
+        assert_eq!(
+            provider
+                .modules_of_package(&package)
+                .into_iter()
+                .sorted()
+                .collect_vec(),
+            [
+                module(&package, "a"),
+                module(&package, "b"),
+                module(&package, "c"),
+            ],
+        );
+        assert!(provider
+            .modules_of_package(&Package::User(PathBuf::from("/other")))
+            .is_empty());
+    }
 }
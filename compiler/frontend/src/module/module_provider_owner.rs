@@ -43,11 +43,11 @@ mod test {
         cst_to_ast::CstToAstStorage,
         hir::HirDbStorage,
         hir_to_mir::HirToMirStorage,
-        mir_optimize::OptimizeMirStorage,
+        mir_optimize::{MirPassesOwner, OptimizeMirStorage},
         module::{GetModuleContentQuery, ModuleDb, ModuleDbStorage, ModuleKind, Package},
         position::PositionConversionStorage,
         rcst_to_cst::RcstToCstStorage,
-        string_to_rcst::{StringToRcst, StringToRcstStorage},
+        string_to_rcst::{RcstCacheOwner, StringToRcst, StringToRcstStorage},
     };
 
     #[salsa::database(
@@ -74,6 +74,8 @@ mod test {
             &self.module_provider
         }
     }
+    impl RcstCacheOwner for Database {}
+    impl MirPassesOwner for Database {}
     impl MutableModuleProviderOwner for Database {
         fn get_in_memory_module_provider(&mut self) -> &mut InMemoryModuleProvider {
             &mut self.module_provider
@@ -102,6 +104,7 @@ mod test {
         assert_eq!(
             db.rcst(module.clone()).unwrap().as_ref().clone(),
             vec![CstKind::Int {
+                is_negative: false,
                 radix_prefix: None,
                 value: 123u8.into(),
                 string: "123".to_string(),
@@ -120,6 +123,7 @@ mod test {
         assert_eq!(
             db.rcst(module.clone()).unwrap().as_ref().clone(),
             vec![CstKind::Int {
+                is_negative: false,
                 radix_prefix: None,
                 value: 456u16.into(),
                 string: "456".to_string(),
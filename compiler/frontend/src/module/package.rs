@@ -1,5 +1,6 @@
 use derive_more::Deref;
 use rustc_hash::FxHashSet;
+use serde::{Deserialize, Serialize};
 use shellexpand::tilde;
 use std::{
     ffi::OsStr,
@@ -92,7 +93,7 @@ impl TryFrom<&Path> for PackagesPath {
     }
 }
 
-#[derive(Clone, Debug, Eq, EnumIs, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Deserialize, EnumIs, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub enum Package {
     /// A package written by the user.
     User(PathBuf),
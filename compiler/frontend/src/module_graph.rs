@@ -0,0 +1,289 @@
+//! The graph of modules that a module uses, directly or indirectly.
+//!
+//! [Module folding] copies the code of each used module into the using
+//! module. Small modules are copied into every place that uses them, which can
+//! blow up the code of modules that are used often. This graph shows which
+//! modules get folded into which and how big they are.
+//!
+//! [Module folding]: crate::mir_optimize::module_folding
+
+use crate::{
+    ast_to_hir::AstToHir,
+    hir::{Body, Expression, FunctionKind, Id},
+    hir_to_mir::ExecutionTarget,
+    mir_optimize::{OptimizeMir, MAX_DUPLICATED_MODULE_EXPRESSIONS},
+    module::{Module, UsePath},
+    TracingConfig,
+};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ModuleGraph {
+    /// All modules reachable from the entry module, in the order in which
+    /// they're first used. The entry module comes first.
+    pub modules: Vec<ModuleNode>,
+    pub uses: Vec<ModuleUse>,
+    /// Each cycle lists the modules in the order in which they use each other.
+    /// The last module uses the first one again.
+    pub cycles: Vec<Vec<Module>>,
+}
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ModuleNode {
+    pub module: Module,
+    /// The number of expressions in the optimized module, including the
+    /// modules folded into it.
+    ///
+    /// This is `None` if the module can't be compiled, e.g., because it
+    /// doesn't exist.
+    pub expressions: Option<usize>,
+}
+impl ModuleNode {
+    #[must_use]
+    pub fn folding(&self) -> Option<Folding> {
+        self.expressions.map(|expressions| {
+            if expressions > MAX_DUPLICATED_MODULE_EXPRESSIONS {
+                Folding::OncePerScope
+            } else {
+                Folding::Duplicated
+            }
+        })
+    }
+}
+/// How a module's code gets folded into the modules using it.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Folding {
+    /// Each `use` copies the module's code.
+    Duplicated,
+    /// Later `use`s of the module reference the result of an earlier one if
+    /// it's still visible.
+    OncePerScope,
+}
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ModuleUse {
+    pub user: Module,
+    pub used: Module,
+    /// How many `use` expressions in `user` refer to `used`.
+    pub count: usize,
+}
+
+#[must_use]
+pub fn module_graph<DB: AstToHir + OptimizeMir>(db: &DB, entry: &Module) -> ModuleGraph {
+    let mut builder = GraphBuilder {
+        db,
+        graph: ModuleGraph::default(),
+        visited: FxHashSet::default(),
+        stack: vec![],
+    };
+    builder.visit(entry);
+    builder.graph
+}
+
+struct GraphBuilder<'a, DB: AstToHir + OptimizeMir> {
+    db: &'a DB,
+    graph: ModuleGraph,
+    visited: FxHashSet<Module>,
+    /// The modules whose uses are currently being visited.
+    stack: Vec<Module>,
+}
+impl<'a, DB: AstToHir + OptimizeMir> GraphBuilder<'a, DB> {
+    fn visit(&mut self, module: &Module) {
+        self.visited.insert(module.clone());
+        self.stack.push(module.clone());
+
+        // Module folding compiles used modules with the same tracing config,
+        // so this reuses the cached results.
+        let expressions = self
+            .db
+            .optimized_mir_without_tail_calls(
+                ExecutionTarget::Module(module.clone()),
+                TracingConfig::off(),
+            )
+            .ok()
+            .map(|(mir, _, _)| mir.complexity().expressions);
+        self.graph.modules.push(ModuleNode {
+            module: module.clone(),
+            expressions,
+        });
+
+        for (used, count) in used_modules(self.db, module) {
+            self.graph.uses.push(ModuleUse {
+                user: module.clone(),
+                used: used.clone(),
+                count,
+            });
+            if let Some(index) = self.stack.iter().position(|it| it == &used) {
+                self.graph.cycles.push(self.stack[index..].to_vec());
+            } else if !self.visited.contains(&used) {
+                self.visit(&used);
+            }
+        }
+
+        self.stack.pop();
+    }
+}
+
+/// The modules used by `use` calls with a text literal as the path, in the
+/// order of their first `use`. Invalid paths are already reported as errors
+/// when compiling the module, so they're ignored here.
+fn used_modules(db: &dyn AstToHir, module: &Module) -> Vec<(Module, usize)> {
+    let Ok((hir, _)) = db.hir(module.clone()) else {
+        return vec![];
+    };
+    let Some(use_id) = hir.expressions.iter().find_map(|(id, expression)| {
+        matches!(expression, Expression::Function(function) if function.kind == FunctionKind::Use)
+            .then_some(id)
+    }) else {
+        return vec![];
+    };
+
+    // Code refers to `use` and to texts through references, so these track
+    // all IDs that are known to evaluate to them.
+    let mut use_ids = FxHashSet::from_iter([use_id.clone()]);
+    let mut texts = FxHashMap::default();
+    let mut paths = vec![];
+    visit_expressions(&hir, &mut |id, expression| match expression {
+        Expression::Text(text) => {
+            texts.insert(id.clone(), text.clone());
+        }
+        Expression::Reference(target) => {
+            if use_ids.contains(target) {
+                use_ids.insert(id.clone());
+            } else if let Some(text) = texts.get(target) {
+                texts.insert(id.clone(), text.clone());
+            }
+        }
+        Expression::Call {
+            function,
+            arguments,
+        } if use_ids.contains(function) => {
+            if let [argument] = arguments.as_slice()
+                && let Some(path) = texts.get(argument)
+            {
+                paths.push(path.clone());
+            }
+        }
+        _ => {}
+    });
+
+    let mut used_modules: Vec<(Module, usize)> = vec![];
+    for path in paths {
+        let Ok(used) =
+            UsePath::parse(&path).and_then(|path| path.resolve_relative_to(module.clone()))
+        else {
+            continue;
+        };
+        if let Some((_, count)) = used_modules.iter_mut().find(|(it, _)| it == &used) {
+            *count += 1;
+        } else {
+            used_modules.push((used, 1));
+        }
+    }
+    used_modules
+}
+fn visit_expressions(body: &Body, visit: &mut impl FnMut(&Id, &Expression)) {
+    for (id, expression) in &body.expressions {
+        visit(id, expression);
+        match expression {
+            Expression::Match { cases, .. } => {
+                for (_, body) in cases {
+                    visit_expressions(body, visit);
+                }
+            }
+            Expression::Function(function) if function.kind != FunctionKind::Use => {
+                visit_expressions(&function.body, visit);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast::AstDbStorage,
+        ast_to_hir::AstToHirStorage,
+        cst::CstDbStorage,
+        cst_to_ast::CstToAstStorage,
+        hir::HirDbStorage,
+        hir_to_mir::HirToMirStorage,
+        mir_optimize::{MirPassesOwner, OptimizeMirStorage},
+        module::{
+            GetModuleContentQuery, InMemoryModuleProvider, ModuleDbStorage, ModuleKind,
+            ModuleProvider, ModuleProviderOwner, MutableModuleProviderOwner, Package,
+        },
+        position::PositionConversionStorage,
+        rcst_to_cst::RcstToCstStorage,
+        string_to_rcst::{RcstCacheOwner, StringToRcstStorage},
+    };
+    use std::path::PathBuf;
+
+    #[salsa::database(
+        AstDbStorage,
+        AstToHirStorage,
+        CstDbStorage,
+        CstToAstStorage,
+        HirDbStorage,
+        HirToMirStorage,
+        ModuleDbStorage,
+        OptimizeMirStorage,
+        PositionConversionStorage,
+        RcstToCstStorage,
+        StringToRcstStorage
+    )]
+    #[derive(Default)]
+    struct Database {
+        storage: salsa::Storage<Self>,
+        module_provider: InMemoryModuleProvider,
+    }
+    impl salsa::Database for Database {}
+    impl ModuleProviderOwner for Database {
+        fn get_module_provider(&self) -> &dyn ModuleProvider {
+            &self.module_provider
+        }
+    }
+    impl RcstCacheOwner for Database {}
+    impl MirPassesOwner for Database {}
+    impl MutableModuleProviderOwner for Database {
+        fn get_in_memory_module_provider(&mut self) -> &mut InMemoryModuleProvider {
+            &mut self.module_provider
+        }
+        fn invalidate_module(&mut self, module: &Module) {
+            GetModuleContentQuery.in_db_mut(self).invalidate(module);
+        }
+    }
+
+    #[test]
+    fn finds_uses_and_cycles() {
+        let module = |name: &str| Module {
+            package: Package::User(PathBuf::from("/non/existent")),
+            path: vec![name.to_string()],
+            kind: ModuleKind::Code,
+        };
+        let mut db = Database::default();
+        db.did_open_module(
+            &module("main"),
+            b"a = use \"..a\"\nb = use \"..b\"\nc = use \"..a\"".to_vec(),
+        );
+        db.did_open_module(&module("a"), b"b = use \"..b\"".to_vec());
+        db.did_open_module(&module("b"), b"a = use \"..a\"".to_vec());
+
+        let graph = module_graph(&db, &module("main"));
+        let users_and_used = graph
+            .uses
+            .iter()
+            .filter(|it| it.used.package != Package::builtins())
+            .map(|it| (it.user.clone(), it.used.clone(), it.count))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            users_and_used,
+            vec![
+                (module("main"), module("a"), 2),
+                (module("a"), module("b"), 1),
+                (module("b"), module("a"), 1),
+                (module("main"), module("b"), 1),
+            ],
+        );
+        assert_eq!(graph.cycles, vec![vec![module("a"), module("b")]]);
+    }
+}
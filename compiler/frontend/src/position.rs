@@ -45,7 +45,7 @@ pub trait PositionConversionDb: ModuleDb {
     #[salsa::transparent]
     fn offset_to_position(&self, module: Module, position: Offset) -> Position;
 
-    fn line_start_offsets(&self, module: Module) -> Arc<Vec<Offset>>;
+    fn line_index(&self, module: Module) -> Arc<LineIndex>;
 }
 
 fn range_to_positions(
@@ -57,11 +57,7 @@ fn range_to_positions(
     let end = db.offset_to_position(module, range.end);
     start..end
 }
-fn offset_to_position(
-    db: &dyn PositionConversionDb,
-    module: Module,
-    mut offset: Offset,
-) -> Position {
+fn offset_to_position(db: &dyn PositionConversionDb, module: Module, offset: Offset) -> Position {
     let Some(text) = db.get_module_content_as_string(module.clone()) else {
         assert_eq!(*offset, 0);
         return Position {
@@ -69,33 +65,187 @@ fn offset_to_position(
             character: 0,
         };
     };
-    if *offset > text.len() {
-        *offset = text.len();
-    }
-    let line_start_offsets = db.line_start_offsets(module);
-
-    let line = line_start_offsets
-        .binary_search(&offset)
-        .unwrap_or_else(|i| i - 1);
+    let line_index = db.line_index(module);
 
-    let character = text[*line_start_offsets[line]..*offset]
+    let offset = line_index.clamp(offset);
+    let line = line_index.line_of(offset);
+    let character = text[*line_index.line_start(line)..*offset]
         .graphemes(true)
         .count();
     Position { line, character }
 }
 
-fn line_start_offsets(db: &dyn PositionConversionDb, module: Module) -> Arc<Vec<Offset>> {
+fn line_index(db: &dyn PositionConversionDb, module: Module) -> Arc<LineIndex> {
     let text = db.get_module_content_as_string(module).unwrap();
-    Arc::new(line_start_offsets_raw(&*text))
+    Arc::new(LineIndex::new(&text))
 }
-pub fn line_start_offsets_raw<S: AsRef<str>>(text: S) -> Vec<Offset> {
-    let mut offsets = vec![Offset(0)];
-    offsets.extend(
-        text.as_ref()
-            .bytes()
-            .enumerate()
-            .filter(|(_, it)| it == &b'\n')
-            .map(|(index, _)| Offset(index + 1)),
-    );
-    offsets
+
+/// The line structure of a text, which allows converting between offsets and
+/// line-based positions without scanning the text again.
+///
+/// Besides the line starts, this stores all characters that take up more than
+/// one byte in UTF-8. That's enough to convert to and from positions counting
+/// UTF-16 code units, which the Language Server Protocol uses.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LineIndex {
+    text_length: usize,
+    line_start_offsets: Vec<Offset>,
+    /// Sorted by offset.
+    wide_characters: Vec<WideCharacter>,
+}
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct WideCharacter {
+    offset: Offset,
+    utf8_length: usize,
+    utf16_length: usize,
+}
+impl WideCharacter {
+    fn end(self) -> usize {
+        *self.offset + self.utf8_length
+    }
+}
+
+impl LineIndex {
+    #[must_use]
+    pub fn new(text: &str) -> Self {
+        let mut line_start_offsets = vec![Offset(0)];
+        let mut wide_characters = vec![];
+        for (index, character) in text.char_indices() {
+            if character == '\n' {
+                line_start_offsets.push(Offset(index + 1));
+            } else if !character.is_ascii() {
+                wide_characters.push(WideCharacter {
+                    offset: Offset(index),
+                    utf8_length: character.len_utf8(),
+                    utf16_length: character.len_utf16(),
+                });
+            }
+        }
+        Self {
+            text_length: text.len(),
+            line_start_offsets,
+            wide_characters,
+        }
+    }
+
+    #[must_use]
+    pub fn line_count(&self) -> usize {
+        self.line_start_offsets.len()
+    }
+    #[must_use]
+    pub fn line_start(&self, line: usize) -> Offset {
+        self.line_start_offsets[line]
+    }
+    /// The offset of the end of the line, excluding the newline.
+    #[must_use]
+    pub fn line_end(&self, line: usize) -> Offset {
+        self.line_start_offsets
+            .get(line + 1)
+            .map_or(Offset(self.text_length), |it| Offset(**it - 1))
+    }
+    /// The zero-based line containing the offset.
+    #[must_use]
+    pub fn line_of(&self, offset: Offset) -> usize {
+        self.line_start_offsets.partition_point(|it| *it <= offset) - 1
+    }
+
+    /// Moves the offset to the end of the text if it's beyond it and to the
+    /// start of the surrounding character if it's inside one.
+    #[must_use]
+    pub fn clamp(&self, offset: Offset) -> Offset {
+        let offset = Offset((*offset).min(self.text_length));
+        let index = self
+            .wide_characters
+            .partition_point(|it| it.offset <= offset);
+        match index
+            .checked_sub(1)
+            .map(|index| self.wide_characters[index])
+        {
+            Some(character) if *offset < character.end() => character.offset,
+            _ => offset,
+        }
+    }
+
+    /// The number of UTF-16 code units in the range, which must start and end
+    /// at character boundaries.
+    #[must_use]
+    pub fn utf16_length(&self, range: Range<Offset>) -> usize {
+        let start = self
+            .wide_characters
+            .partition_point(|it| it.offset < range.start);
+        let end = self
+            .wide_characters
+            .partition_point(|it| it.offset < range.end);
+        let excess: usize = self.wide_characters[start..end]
+            .iter()
+            .map(|it| it.utf8_length - it.utf16_length)
+            .sum();
+        *range.end - *range.start - excess
+    }
+    /// Converts an offset to its zero-based line and the number of UTF-16
+    /// code units preceding it in that line.
+    #[must_use]
+    pub fn offset_to_utf16_position(&self, offset: Offset) -> (usize, usize) {
+        let offset = self.clamp(offset);
+        let line = self.line_of(offset);
+        (line, self.utf16_length(self.line_start(line)..offset))
+    }
+    /// Converts a zero-based line and a number of UTF-16 code units in that
+    /// line to an offset.
+    ///
+    /// Positions beyond the end of a line resolve to the line's end and
+    /// positions inside a character resolve to the character's start.
+    #[must_use]
+    pub fn utf16_position_to_offset(&self, line: usize, character: usize) -> Offset {
+        if line >= self.line_count() {
+            return Offset(self.text_length);
+        }
+
+        let line_start = self.line_start(line);
+        let line_end = self.line_end(line);
+        let start = self
+            .wide_characters
+            .partition_point(|it| it.offset < line_start);
+        let mut offset = *line_start + character;
+        for wide_character in &self.wide_characters[start..] {
+            if wide_character.offset >= line_end || *wide_character.offset >= offset {
+                break;
+            }
+            offset += wide_character.utf8_length - wide_character.utf16_length;
+        }
+        self.clamp(Offset(offset.min(*line_end)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_line_index() {
+        let line_index = LineIndex::new("ab\nä😀c\n");
+        assert_eq!(line_index.line_count(), 3);
+        assert_eq!(line_index.line_end(0), Offset(2));
+        assert_eq!(line_index.line_of(Offset(3)), 1);
+        assert_eq!(line_index.line_of(Offset(13)), 2);
+
+        assert_eq!(line_index.offset_to_utf16_position(Offset(2)), (0, 2));
+        assert_eq!(line_index.offset_to_utf16_position(Offset(5)), (1, 1));
+        assert_eq!(line_index.offset_to_utf16_position(Offset(9)), (1, 3));
+        assert_eq!(line_index.offset_to_utf16_position(Offset(10)), (1, 4));
+        // Inside the emoji
+        assert_eq!(line_index.offset_to_utf16_position(Offset(7)), (1, 1));
+        // Beyond the end
+        assert_eq!(line_index.offset_to_utf16_position(Offset(42)), (2, 0));
+
+        assert_eq!(line_index.utf16_position_to_offset(0, 1), Offset(1));
+        assert_eq!(line_index.utf16_position_to_offset(1, 1), Offset(5));
+        assert_eq!(line_index.utf16_position_to_offset(1, 3), Offset(9));
+        assert_eq!(line_index.utf16_position_to_offset(1, 4), Offset(10));
+        // Inside the emoji's surrogate pair
+        assert_eq!(line_index.utf16_position_to_offset(1, 2), Offset(5));
+        // Beyond the end of the line and the text
+        assert_eq!(line_index.utf16_position_to_offset(0, 10), Offset(2));
+        assert_eq!(line_index.utf16_position_to_offset(5, 0), Offset(11));
+    }
 }
@@ -8,6 +8,7 @@ use crate::{
     id::IdGenerator,
     module::Module,
     position::Offset,
+    timings::{self, Stage},
 };
 use extension_trait::extension_trait;
 use std::sync::Arc;
@@ -20,8 +21,10 @@ pub trait RcstToCst: StringToRcst {
 pub type CstResult = Result<Arc<Vec<Cst>>, ModuleError>;
 
 fn cst(db: &dyn RcstToCst, module: Module) -> Result<Arc<Vec<Cst>>, ModuleError> {
-    let rcsts = db.rcst(module)?;
-    Ok(Arc::new(rcsts.to_csts()))
+    let rcsts = db.rcst(module.clone())?;
+    Ok(timings::record_stage(Stage::Cst, module, || {
+        Arc::new(rcsts.to_csts())
+    }))
 }
 
 #[derive(Default)]
@@ -114,6 +117,22 @@ impl Rcst {
                 *state.offset += 1;
                 CstKind::Octothorpe
             }
+            CstKind::IfKeyword => {
+                *state.offset += 2;
+                CstKind::IfKeyword
+            }
+            CstKind::ThenKeyword => {
+                *state.offset += 4;
+                CstKind::ThenKeyword
+            }
+            CstKind::ElseKeyword => {
+                *state.offset += 4;
+                CstKind::ElseKeyword
+            }
+            CstKind::BinaryOperator(operator) => {
+                *state.offset += operator.as_str().len();
+                CstKind::BinaryOperator(*operator)
+            }
             CstKind::Whitespace(whitespace) => {
                 *state.offset += whitespace.len();
                 CstKind::Whitespace(whitespace.clone())
@@ -146,21 +165,33 @@ impl Rcst {
                 CstKind::Symbol(symbol.clone())
             }
             CstKind::Int {
+                is_negative,
                 radix_prefix,
                 value,
                 string,
             } => {
+                if *is_negative {
+                    *state.offset += 1;
+                }
                 *state.offset += radix_prefix
                     .as_ref()
                     .map(|(_, radix_string)| radix_string.len())
                     .unwrap_or_default();
                 *state.offset += string.len();
                 CstKind::Int {
+                    is_negative: *is_negative,
                     radix_prefix: radix_prefix.clone(),
                     value: value.clone(),
                     string: string.clone(),
                 }
             }
+            CstKind::Float { value, string } => {
+                *state.offset += string.len();
+                CstKind::Float {
+                    value: *value,
+                    string: string.clone(),
+                }
+            }
             CstKind::OpeningText {
                 opening_single_quotes,
                 opening_double_quote,
@@ -192,6 +223,13 @@ impl Rcst {
                 *state.offset += text.len();
                 CstKind::TextPart(text.clone())
             }
+            CstKind::TextEscape { value, string } => {
+                *state.offset += string.len();
+                CstKind::TextEscape {
+                    value: *value,
+                    string: string.clone(),
+                }
+            }
             CstKind::TextInterpolation {
                 opening_curly_braces,
                 expression,
@@ -264,11 +302,35 @@ impl Rcst {
                 arrow: Box::new(arrow.to_cst(state)),
                 body: body.to_csts_helper(state),
             },
+            CstKind::If {
+                if_keyword,
+                condition,
+                then_keyword,
+                then,
+                else_keyword,
+                else_,
+            } => CstKind::If {
+                if_keyword: Box::new(if_keyword.to_cst(state)),
+                condition: Box::new(condition.to_cst(state)),
+                then_keyword: Box::new(then_keyword.to_cst(state)),
+                then: Box::new(then.to_cst(state)),
+                else_keyword: Box::new(else_keyword.to_cst(state)),
+                else_: Box::new(else_.to_cst(state)),
+            },
             CstKind::BinaryBar { left, bar, right } => CstKind::BinaryBar {
                 left: Box::new(left.to_cst(state)),
                 bar: Box::new(bar.to_cst(state)),
                 right: Box::new(right.to_cst(state)),
             },
+            CstKind::BinaryOperation {
+                left,
+                operator,
+                right,
+            } => CstKind::BinaryOperation {
+                left: Box::new(left.to_cst(state)),
+                operator: Box::new(operator.to_cst(state)),
+                right: Box::new(right.to_cst(state)),
+            },
             CstKind::Parenthesized {
                 opening_parenthesis,
                 inner,
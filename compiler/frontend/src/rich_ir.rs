@@ -15,6 +15,7 @@ use derive_more::From;
 use enumset::{EnumSet, EnumSetType};
 use itertools::Itertools;
 use num_bigint::{BigInt, BigUint};
+use ordered_float::OrderedFloat;
 use rustc_hash::FxHashMap;
 use std::{
     fmt::{self, Display, Formatter},
@@ -38,6 +39,7 @@ impl Display for RichIr {
 #[derive(Clone, Debug, Eq, From, Hash, PartialEq)]
 pub enum ReferenceKey {
     Int(BigInt),
+    Float(OrderedFloat<f64>),
     Text(String),
     #[from(ignore)]
     Symbol(String),
@@ -74,6 +76,7 @@ pub enum TokenType {
     Comment,
     Text,
     Int,
+    Float,
     Address,
     Constant,
 }
@@ -126,6 +129,12 @@ impl ToRichIr for BigUint {
         builder.push_reference(BigInt::from(self.clone()), range);
     }
 }
+impl ToRichIr for OrderedFloat<f64> {
+    fn build_rich_ir(&self, builder: &mut RichIrBuilder) {
+        let range = builder.push(format!("{self:?}"), TokenType::Float, EnumSet::empty());
+        builder.push_reference(*self, range);
+    }
+}
 
 #[macro_export]
 macro_rules! impl_debug_via_richir {
@@ -295,7 +304,7 @@ impl RichIrBuilder {
             .push(range);
     }
 
-    pub fn push_tracing_config(&mut self, tracing_config: TracingConfig) {
+    pub fn push_tracing_config(&mut self, tracing_config: &TracingConfig) {
         fn push_mode(builder: &mut RichIrBuilder, title: &str, mode: TracingMode) {
             builder.push_comment_line(format!(
                 "• {title} {}",
@@ -329,6 +338,14 @@ impl RichIrBuilder {
             "Include tracing of evaluated expressions?",
             tracing_config.evaluated_expressions,
         );
+        for tracing_override in &tracing_config.overrides {
+            self.push_comment_line(format!(
+                "• Override for `{}`: calls {:?}, evaluated expressions {:?}",
+                tracing_override.modules,
+                tracing_override.calls,
+                tracing_override.evaluated_expressions,
+            ));
+        }
     }
 
     #[must_use]
@@ -419,7 +436,7 @@ impl RichIr {
         let mut builder = RichIrBuilder::default();
         builder.push_comment_line(format!("{ir_name} for module {module}"));
         if let Some(tracing_config) = tracing_config.into() {
-            builder.push_tracing_config(tracing_config);
+            builder.push_tracing_config(&tracing_config);
             builder.push_newline();
         }
 
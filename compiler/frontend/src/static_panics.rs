@@ -0,0 +1,219 @@
+//! Finds code that panics for all inputs without running it.
+//!
+//! The optimizations already abstractly interpret the MIR: Constant folding
+//! and inlining evaluate code whose inputs are known at compile-time. For
+//! example, both `needs False` and accessing a key that a struct literal
+//! doesn't contain turn into an [`Expression::Panic`]. This module collects
+//! these panics from the optimized MIR.
+//!
+//! Because MIR bodies don't branch (conditionals call functions instead), a
+//! panic in a body happens every time that body runs. If the caller of a
+//! function is responsible for such a panic, the function panics for all
+//! arguments.
+
+use crate::{
+    hir::{self, HirDb},
+    hir_to_mir::ExecutionTarget,
+    mir::{Body, Expression, Id, Mir, VisibleExpressions},
+    mir_optimize::OptimizeMir,
+    module::Module,
+    tracing::CallTracingMode,
+    TracingConfig, TracingMode,
+};
+use itertools::Itertools;
+use std::mem;
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct StaticPanic {
+    pub kind: StaticPanicKind,
+    pub reason: String,
+    /// For [`StaticPanicKind::Always`], the code at fault. For
+    /// [`StaticPanicKind::WhenCalled`], the function.
+    pub responsible: hir::Id,
+}
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum StaticPanicKind {
+    /// The code panics whenever it runs, e.g., `needs False` in a module.
+    Always,
+    /// The function panics for all arguments. Whether that's a problem
+    /// depends on whether it's called at all.
+    WhenCalled,
+}
+
+/// The static panics whose responsible code is in the given module.
+///
+/// Compiler errors also panic when they're evaluated, but they're already
+/// reported as such, so they're not included.
+#[must_use]
+pub fn static_panics<DB: HirDb + OptimizeMir>(db: &DB, module: &Module) -> Vec<StaticPanic> {
+    let tracing = TracingConfig {
+        register_fuzzables: TracingMode::OnlyCurrent,
+        calls: CallTracingMode::Off,
+        evaluated_expressions: TracingMode::Off,
+        memoize_calls: false,
+        overrides: vec![],
+    };
+    let Ok((mir, _)) = db.optimized_mir(ExecutionTarget::Module(module.clone()), tracing) else {
+        return vec![];
+    };
+    let mut mir = (*mir).clone();
+    let mut panics = mir.static_panics();
+    panics.retain(|panic| {
+        &panic.responsible.module == module
+            && !matches!(
+                db.find_expression(panic.responsible.clone()),
+                Some(hir::Expression::Error { .. }),
+            )
+    });
+    panics
+}
+
+impl Mir {
+    #[must_use]
+    pub fn static_panics(&mut self) -> Vec<StaticPanic> {
+        let mut panics = vec![];
+        self.body.collect_static_panics(
+            &mut VisibleExpressions::none_visible(),
+            None,
+            true,
+            &mut panics,
+        );
+        panics.into_iter().unique().collect()
+    }
+}
+
+/// The function whose body is currently analyzed.
+struct CurrentFunction<'a> {
+    original_hirs: Vec<&'a hir::Id>,
+    responsible_parameter: Id,
+}
+
+impl Body {
+    fn collect_static_panics(
+        &mut self,
+        visible: &mut VisibleExpressions,
+        function: Option<&CurrentFunction>,
+        is_fuzzable: bool,
+        panics: &mut Vec<StaticPanic>,
+    ) {
+        for (id, expression) in &mut self.expressions {
+            let mut expression = mem::replace(expression, Expression::Parameter);
+            expression.collect_static_panics(visible, function, is_fuzzable, panics);
+            visible.insert(*id, expression);
+        }
+
+        for (id, expression) in &mut self.expressions {
+            *expression = visible.remove(*id);
+        }
+    }
+}
+
+impl Expression {
+    fn collect_static_panics(
+        &mut self,
+        visible: &mut VisibleExpressions,
+        function: Option<&CurrentFunction>,
+        is_fuzzable: bool,
+        panics: &mut Vec<StaticPanic>,
+    ) {
+        let referenced = self.referenced_ids();
+        match self {
+            Self::Function {
+                original_hirs,
+                parameters,
+                responsible_parameter,
+                body,
+            } => {
+                let is_fuzzable = referenced.contains(responsible_parameter);
+
+                for parameter in &*parameters {
+                    visible.insert(*parameter, Self::Parameter);
+                }
+                visible.insert(*responsible_parameter, Self::Parameter);
+
+                let function = CurrentFunction {
+                    original_hirs: original_hirs.iter().sorted().collect(),
+                    responsible_parameter: *responsible_parameter,
+                };
+                body.collect_static_panics(visible, Some(&function), is_fuzzable, panics);
+
+                for parameter in &*parameters {
+                    visible.remove(*parameter);
+                }
+                visible.remove(*responsible_parameter);
+            }
+            Self::Panic {
+                reason,
+                responsible,
+            } if is_fuzzable => {
+                let Self::Text(reason) = visible.get(*reason) else {
+                    return;
+                };
+                match visible.get(*responsible) {
+                    Self::HirId(responsible) => panics.push(StaticPanic {
+                        kind: StaticPanicKind::Always,
+                        reason: reason.to_string(),
+                        responsible: responsible.clone(),
+                    }),
+                    // The caller is responsible, but no matter the arguments,
+                    // the function panics.
+                    Self::Parameter => {
+                        if let Some(function) = function
+                            && *responsible == function.responsible_parameter
+                        {
+                            panics.extend(function.original_hirs.iter().map(|id| StaticPanic {
+                                kind: StaticPanicKind::WhenCalled,
+                                reason: reason.to_string(),
+                                responsible: (*id).clone(),
+                            }));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_panics_at_the_top_level() {
+        let mut mir = Mir::build(|body| {
+            let reason = body.push_text("Oh no.".to_string());
+            let responsible = body.push_hir_id(hir::Id::dummy());
+            body.push_panic(reason, responsible);
+        });
+        assert_eq!(
+            mir.static_panics(),
+            vec![StaticPanic {
+                kind: StaticPanicKind::Always,
+                reason: "Oh no.".to_string(),
+                responsible: hir::Id::dummy(),
+            }],
+        );
+    }
+
+    #[test]
+    fn finds_functions_panicking_for_all_arguments() {
+        let function_id = hir::Id::user();
+        let mut mir = Mir::build(|body| {
+            let reason = body.push_text("Never call me.".to_string());
+            body.push_function(function_id.clone(), |body, responsible| {
+                body.new_parameter();
+                body.push_panic(reason, responsible);
+            });
+        });
+        assert_eq!(
+            mir.static_panics(),
+            vec![StaticPanic {
+                kind: StaticPanicKind::WhenCalled,
+                reason: "Never call me.".to_string(),
+                responsible: function_id,
+            }],
+        );
+    }
+}
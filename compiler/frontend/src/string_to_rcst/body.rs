@@ -1,8 +1,12 @@
 use super::{
     expression::{expression, ExpressionParsingOptions},
-    literal::{arrow, closing_bracket, closing_curly_brace, closing_parenthesis, colon, comma},
+    literal::{
+        arrow, binary_operator, closing_bracket, closing_curly_brace, closing_parenthesis, colon,
+        comma,
+    },
     utils::whitespace_indentation_score,
     whitespace::{single_line_whitespace, whitespaces_and_newlines},
+    word::keyword,
 };
 use crate::{
     cst::{CstError, CstKind},
@@ -13,71 +17,83 @@ use tracing::instrument;
 #[instrument(level = "trace")]
 pub fn body(mut input: &str, indentation: usize) -> (&str, Vec<Rcst>) {
     let mut expressions = vec![];
-
-    loop {
-        let num_expressions_before = expressions.len();
-
-        let (new_input, mut whitespace) = whitespaces_and_newlines(input, indentation, true);
+    while let Some((new_input, mut new_expressions)) = body_item(input, indentation) {
         input = new_input;
-        expressions.append(&mut whitespace);
+        expressions.append(&mut new_expressions);
+    }
+    (input, expressions)
+}
 
-        let mut indentation = indentation;
-        if let Some((new_input, unexpected_whitespace)) = single_line_whitespace(input) {
-            input = new_input;
-            indentation += match &unexpected_whitespace.kind {
-                CstKind::Whitespace(whitespace)
-                | CstKind::Error {
-                    unparsable_input: whitespace,
-                    error: CstError::WeirdWhitespace,
-                } => whitespace_indentation_score(whitespace) / 2,
-                _ => panic!(
-                    "single_line_whitespace returned something other than Whitespace or Error."
-                ),
-            };
-            expressions.push(
-                CstKind::Error {
-                    unparsable_input: unexpected_whitespace.to_string(),
-                    error: CstError::TooMuchWhitespace,
-                }
-                .into(),
-            );
-        }
+/// Parses whitespace followed by a single expression of a body.
+///
+/// The result only depends on the `input` and `indentation`, which allows
+/// reparsing bodies incrementally.
+pub fn body_item(mut input: &str, indentation: usize) -> Option<(&str, Vec<Rcst>)> {
+    let mut expressions = vec![];
 
-        let parsed_expression = expression(
-            input,
-            indentation,
-            ExpressionParsingOptions {
-                allow_assignment: true,
-                allow_call: true,
-                allow_bar: true,
-                allow_function: true,
-            },
-        );
-        if let Some((new_input, expression)) = parsed_expression {
-            input = new_input;
+    let (new_input, mut whitespace) = whitespaces_and_newlines(input, indentation, true);
+    input = new_input;
+    expressions.append(&mut whitespace);
 
-            let (mut whitespace, expression) = expression.split_outer_trailing_whitespace();
-            expressions.push(expression);
-            expressions.append(&mut whitespace);
-        } else {
-            let fallback = colon(new_input)
-                .or_else(|| comma(new_input))
-                .or_else(|| closing_parenthesis(new_input))
-                .or_else(|| closing_bracket(new_input))
-                .or_else(|| closing_curly_brace(new_input))
-                .or_else(|| arrow(new_input));
-            if let Some((new_input, cst)) = fallback {
-                input = new_input;
-                expressions.push(cst);
+    let mut indentation = indentation;
+    if let Some((new_input, unexpected_whitespace)) = single_line_whitespace(input) {
+        input = new_input;
+        indentation += match &unexpected_whitespace.kind {
+            CstKind::Whitespace(whitespace)
+            | CstKind::Error {
+                unparsable_input: whitespace,
+                error: CstError::WeirdWhitespace,
+            } => whitespace_indentation_score(whitespace) / 2,
+            _ => {
+                panic!("single_line_whitespace returned something other than Whitespace or Error.")
             }
-        }
+        };
+        expressions.push(
+            CstKind::Error {
+                unparsable_input: unexpected_whitespace.to_string(),
+                error: CstError::TooMuchWhitespace,
+            }
+            .into(),
+        );
+    }
 
-        let num_expressions_after = expressions.len();
-        if num_expressions_before == num_expressions_after {
-            break;
+    let parsed_expression = expression(
+        input,
+        indentation,
+        ExpressionParsingOptions {
+            allow_assignment: true,
+            allow_call: true,
+            allow_binary_operation: true,
+            allow_bar: true,
+            allow_function: true,
+        },
+    );
+    if let Some((new_input, expression)) = parsed_expression {
+        input = new_input;
+
+        let (mut whitespace, expression) = expression.split_outer_trailing_whitespace();
+        expressions.push(expression);
+        expressions.append(&mut whitespace);
+    } else {
+        let fallback = colon(new_input)
+            .or_else(|| comma(new_input))
+            .or_else(|| closing_parenthesis(new_input))
+            .or_else(|| closing_bracket(new_input))
+            .or_else(|| closing_curly_brace(new_input))
+            .or_else(|| arrow(new_input))
+            .or_else(|| binary_operator(new_input))
+            .or_else(|| keyword(new_input, "then", CstKind::ThenKeyword))
+            .or_else(|| keyword(new_input, "else", CstKind::ElseKeyword));
+        if let Some((new_input, cst)) = fallback {
+            input = new_input;
+            expressions.push(cst);
         }
     }
-    (input, expressions)
+    if expressions.is_empty() {
+        None
+    } else {
+        Some((input, expressions))
+    }
 }
 
 #[cfg(test)]
@@ -1,19 +1,21 @@
 use super::{
     body::body,
+    float::float,
     function::function,
+    if_::if_,
     int::int,
     list::list,
     literal::{
-        arrow, bar, closing_bracket, closing_curly_brace, closing_parenthesis, colon_equals_sign,
-        dot, equals_sign, percent,
+        arrow, bar, binary_operator, closing_bracket, closing_curly_brace, closing_parenthesis,
+        colon_equals_sign, dot, equals_sign, percent,
     },
     struct_::struct_,
     text::text,
     whitespace::{comment, single_line_whitespace, whitespaces_and_newlines},
-    word::{identifier, symbol, word},
+    word::{identifier, is_reserved_keyword, symbol, word},
 };
 use crate::{
-    cst::{CstError, CstKind, IsMultiline},
+    cst::{BinaryOperator, CstError, CstKind, IsMultiline},
     rcst::{Rcst, SplitOuterTrailingWhitespace},
 };
 use tracing::instrument;
@@ -23,6 +25,7 @@ use tracing::instrument;
 pub struct ExpressionParsingOptions {
     pub allow_assignment: bool,
     pub allow_call: bool,
+    pub allow_binary_operation: bool,
     pub allow_bar: bool,
     pub allow_function: bool,
 }
@@ -36,6 +39,7 @@ pub fn expression(
     // If we start the call list with `if … else …`, the formatting looks weird.
     // Hence, we start with a single `None`.
     let (mut input, mut result) = None
+        .or_else(|| float(input))
         .or_else(|| int(input))
         .or_else(|| text(input, indentation))
         .or_else(|| symbol(input))
@@ -48,18 +52,34 @@ pub fn expression(
                 None
             }
         })
+        .or_else(|| {
+            // Like calls, `if` expressions need parentheses when used as an
+            // argument.
+            if options.allow_call {
+                if_(input, indentation)
+            } else {
+                None
+            }
+        })
         .or_else(|| identifier(input))
         .or_else(|| {
-            word(input).map(|(input, word)| {
-                (
-                    input,
-                    CstKind::Error {
-                        unparsable_input: word,
-                        error: CstError::UnexpectedCharacters,
-                    }
-                    .into(),
-                )
-            })
+            // Keywords and operators end the expression before them, e.g., the
+            // condition of an `if` expression.
+            if binary_operator(input).is_some() {
+                return None;
+            }
+            word(input)
+                .filter(|(_, word)| !is_reserved_keyword(word))
+                .map(|(input, word)| {
+                    (
+                        input,
+                        CstKind::Error {
+                            unparsable_input: word,
+                            error: CstError::UnexpectedCharacters,
+                        }
+                        .into(),
+                    )
+                })
         })?;
 
     loop {
@@ -91,6 +111,14 @@ pub fn expression(
             did_make_progress |=
                 parse_suffix(&mut input, indentation, &mut result, expression_suffix_call);
         }
+        if options.allow_binary_operation {
+            did_make_progress |= parse_suffix(
+                &mut input,
+                indentation,
+                &mut result,
+                expression_suffix_binary_operation,
+            );
+        }
         if options.allow_bar {
             did_make_progress |=
                 parse_suffix(&mut input, indentation, &mut result, expression_suffix_bar);
@@ -130,7 +158,28 @@ fn expression_suffix_struct_access<'a>(
     let (new_input, whitespace_after_dot) = whitespaces_and_newlines(input, indentation + 1, true);
     let dot = dot.wrap_in_whitespace(whitespace_after_dot);
 
-    let (input, key) = identifier(new_input)?;
+    // While typing `foo.bar`, the key is missing for a moment. We still
+    // produce a struct access so that the IDE keeps working.
+    let (input, key) = identifier(new_input)
+        .or_else(|| {
+            word(new_input).map(|(input, word)| {
+                (
+                    input,
+                    CstKind::Error {
+                        unparsable_input: word,
+                        error: CstError::StructAccessMissesKey,
+                    }
+                    .into(),
+                )
+            })
+        })
+        .unwrap_or_else(|| {
+            let error = CstKind::Error {
+                unparsable_input: String::new(),
+                error: CstError::StructAccessMissesKey,
+            };
+            (new_input, error.into())
+        });
 
     Some((
         input,
@@ -169,6 +218,7 @@ fn expression_suffix_call<'a>(
             ExpressionParsingOptions {
                 allow_assignment: false,
                 allow_call: has_multiline_whitespace,
+                allow_binary_operation: has_multiline_whitespace,
                 allow_bar: has_multiline_whitespace,
                 allow_function: true,
             },
@@ -235,6 +285,7 @@ fn expression_suffix_bar<'a>(
         ExpressionParsingOptions {
             allow_assignment: false,
             allow_call: true,
+            allow_binary_operation: true,
             allow_bar: false,
             allow_function: true,
         },
@@ -262,6 +313,93 @@ fn expression_suffix_bar<'a>(
     ))
 }
 
+/// Parses a chain of binary operations, e.g., `+ b * c`, and arranges them
+/// according to the precedence of the operators.
+#[instrument(level = "trace")]
+fn expression_suffix_binary_operation<'a>(
+    mut input: &'a str,
+    current: &Rcst,
+    indentation: usize,
+) -> Option<(&'a str, Rcst)> {
+    let mut operands = vec![current.clone()];
+    let mut operators: Vec<(BinaryOperator, Rcst)> = vec![];
+    loop {
+        let (i, whitespace_after_left) = whitespaces_and_newlines(input, indentation + 1, true);
+        let Some((i, operator)) = binary_operator(i) else {
+            break;
+        };
+        let CstKind::BinaryOperator(operator_kind) = operator.kind else {
+            unreachable!("`binary_operator` always returns a binary operator.");
+        };
+        let left = operands.pop().unwrap();
+        operands.push(left.wrap_in_whitespace(whitespace_after_left));
+
+        let (i, whitespace_after_operator) = whitespaces_and_newlines(i, indentation + 1, true);
+        let operator = operator.wrap_in_whitespace(whitespace_after_operator);
+
+        let indentation = if operator.is_multiline() {
+            indentation + 1
+        } else {
+            indentation
+        };
+        let (i, right) = expression(
+            i,
+            indentation,
+            ExpressionParsingOptions {
+                allow_assignment: false,
+                allow_call: true,
+                allow_binary_operation: false,
+                allow_bar: false,
+                allow_function: true,
+            },
+        )
+        .unwrap_or_else(|| {
+            let error = CstKind::Error {
+                unparsable_input: String::new(),
+                error: CstError::BinaryOperationMissesRight,
+            };
+            (i, error.into())
+        });
+        input = i;
+
+        // Operators with a higher or equal precedence on the left are applied
+        // first. Operators without associativity are also grouped to the left
+        // and reported when lowering to the AST.
+        while let Some((previous, _)) = operators.last()
+            && previous.precedence() >= operator_kind.precedence()
+        {
+            reduce_binary_operation(&mut operands, &mut operators);
+        }
+        operators.push((operator_kind, operator));
+        operands.push(right);
+    }
+    if operators.is_empty() {
+        return None;
+    }
+
+    while !operators.is_empty() {
+        reduce_binary_operation(&mut operands, &mut operators);
+    }
+    assert_eq!(operands.len(), 1);
+    Some((input, operands.pop().unwrap()))
+}
+fn reduce_binary_operation(operands: &mut Vec<Rcst>, operators: &mut Vec<(BinaryOperator, Rcst)>) {
+    let (_, operator) = operators.pop().unwrap();
+    let right = operands.pop().unwrap();
+    let left = operands.pop().unwrap();
+
+    // The whitespace after the right operand comes after the whole operation.
+    let (whitespace, right) = right.split_outer_trailing_whitespace();
+    operands.push(
+        CstKind::BinaryOperation {
+            left: Box::new(left),
+            operator: Box::new(operator),
+            right: Box::new(right),
+        }
+        .wrap_in_whitespace(whitespace),
+    );
+}
+
 #[instrument(level = "trace")]
 fn expression_suffix_match<'a>(
     input: &'a str,
@@ -352,6 +490,7 @@ fn expression_suffix_assignment<'a>(
             ExpressionParsingOptions {
                 allow_assignment: false,
                 allow_call: true,
+                allow_binary_operation: true,
                 allow_bar: true,
                 allow_function: true,
             },
@@ -400,6 +539,7 @@ fn match_case(input: &str, indentation: usize) -> Option<(&str, Rcst)> {
         ExpressionParsingOptions {
             allow_assignment: false,
             allow_call: true,
+            allow_binary_operation: true,
             allow_bar: true,
             allow_function: true,
         },
@@ -453,6 +593,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: true,
                     allow_call: true,
+                    allow_binary_operation: true,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -466,6 +607,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: false,
                     allow_call: false,
+                    allow_binary_operation: false,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -495,6 +637,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: true,
                     allow_call: true,
+                    allow_binary_operation: true,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -512,6 +655,64 @@ mod test {
                 .into(),
             )),
         );
+        // foo.
+        assert_eq!(
+            expression(
+                "foo.",
+                0,
+                ExpressionParsingOptions {
+                    allow_assignment: true,
+                    allow_call: true,
+                    allow_binary_operation: true,
+                    allow_bar: true,
+                    allow_function: true
+                }
+            ),
+            Some((
+                "",
+                CstKind::StructAccess {
+                    struct_: Box::new(build_identifier("foo")),
+                    dot: Box::new(CstKind::Dot.into()),
+                    key: Box::new(
+                        CstKind::Error {
+                            unparsable_input: String::new(),
+                            error: CstError::StructAccessMissesKey,
+                        }
+                        .into()
+                    ),
+                }
+                .into(),
+            )),
+        );
+        // foo.Bar
+        assert_eq!(
+            expression(
+                "foo.Bar",
+                0,
+                ExpressionParsingOptions {
+                    allow_assignment: true,
+                    allow_call: true,
+                    allow_binary_operation: true,
+                    allow_bar: true,
+                    allow_function: true
+                }
+            ),
+            Some((
+                "",
+                CstKind::StructAccess {
+                    struct_: Box::new(build_identifier("foo")),
+                    dot: Box::new(CstKind::Dot.into()),
+                    key: Box::new(
+                        CstKind::Error {
+                            unparsable_input: "Bar".to_string(),
+                            error: CstError::StructAccessMissesKey,
+                        }
+                        .into()
+                    ),
+                }
+                .into(),
+            )),
+        );
         // foo
         // .bar
         assert_eq!(
@@ -521,6 +722,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: true,
                     allow_call: true,
+                    allow_binary_operation: true,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -536,6 +738,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: true,
                     allow_call: true,
+                    allow_binary_operation: true,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -562,6 +765,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: true,
                     allow_call: true,
+                    allow_binary_operation: true,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -594,6 +798,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: true,
                     allow_call: true,
+                    allow_binary_operation: true,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -623,6 +828,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: false,
                     allow_call: true,
+                    allow_binary_operation: true,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -680,6 +886,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: false,
                     allow_call: true,
+                    allow_binary_operation: true,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -700,6 +907,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: false,
                     allow_call: true,
+                    allow_binary_operation: true,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -727,6 +935,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: false,
                     allow_call: true,
+                    allow_binary_operation: true,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -760,6 +969,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: false,
                     allow_call: true,
+                    allow_binary_operation: true,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -793,6 +1003,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: true,
                     allow_call: true,
+                    allow_binary_operation: true,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -821,6 +1032,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: false,
                     allow_call: true,
+                    allow_binary_operation: true,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -860,6 +1072,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: false,
                     allow_call: true,
+                    allow_binary_operation: true,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -880,6 +1093,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: true,
                     allow_call: true,
+                    allow_binary_operation: true,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -901,6 +1115,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: true,
                     allow_call: true,
+                    allow_binary_operation: true,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -925,6 +1140,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: true,
                     allow_call: true,
+                    allow_binary_operation: true,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -945,6 +1161,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: false,
                     allow_call: false,
+                    allow_binary_operation: false,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -970,6 +1187,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: false,
                     allow_call: false,
+                    allow_binary_operation: false,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -998,6 +1216,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: false,
                     allow_call: false,
+                    allow_binary_operation: false,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -1030,6 +1249,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: true,
                     allow_call: true,
+                    allow_binary_operation: true,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -1063,6 +1283,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: true,
                     allow_call: true,
+                    allow_binary_operation: true,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -1101,6 +1322,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: true,
                     allow_call: true,
+                    allow_binary_operation: true,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -1136,6 +1358,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: true,
                     allow_call: true,
+                    allow_binary_operation: true,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -1157,6 +1380,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: true,
                     allow_call: true,
+                    allow_binary_operation: true,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -1178,6 +1402,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: true,
                     allow_call: true,
+                    allow_binary_operation: true,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -1206,6 +1431,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: true,
                     allow_call: true,
+                    allow_binary_operation: true,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -1234,6 +1460,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: true,
                     allow_call: true,
+                    allow_binary_operation: true,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -1263,6 +1490,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: true,
                     allow_call: true,
+                    allow_binary_operation: true,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -1318,6 +1546,7 @@ mod test {
                 ExpressionParsingOptions {
                     allow_assignment: true,
                     allow_call: true,
+                    allow_binary_operation: true,
                     allow_bar: true,
                     allow_function: true
                 }
@@ -1348,4 +1577,95 @@ mod test {
             )),
         );
     }
+
+    #[test]
+    fn test_binary_operation() {
+        let options = ExpressionParsingOptions {
+            allow_assignment: true,
+            allow_call: true,
+            allow_binary_operation: true,
+            allow_bar: true,
+            allow_function: true,
+        };
+        let operator = |operator| CstKind::BinaryOperator(operator).with_trailing_space();
+
+        // a + b * c
+        assert_eq!(
+            expression("a + b * c", 0, options),
+            Some((
+                "",
+                CstKind::BinaryOperation {
+                    left: Box::new(build_identifier("a").with_trailing_space()),
+                    operator: Box::new(operator(BinaryOperator::Add)),
+                    right: Box::new(
+                        CstKind::BinaryOperation {
+                            left: Box::new(build_identifier("b").with_trailing_space()),
+                            operator: Box::new(operator(BinaryOperator::Multiply)),
+                            right: Box::new(build_identifier("c")),
+                        }
+                        .into(),
+                    ),
+                }
+                .into(),
+            )),
+        );
+        // a - b - c
+        assert_eq!(
+            expression("a - b - c", 0, options),
+            Some((
+                "",
+                CstKind::BinaryOperation {
+                    left: Box::new(
+                        CstKind::BinaryOperation {
+                            left: Box::new(build_identifier("a").with_trailing_space()),
+                            operator: Box::new(operator(BinaryOperator::Subtract)),
+                            right: Box::new(build_identifier("b")),
+                        }
+                        .with_trailing_space(),
+                    ),
+                    operator: Box::new(operator(BinaryOperator::Subtract)),
+                    right: Box::new(build_identifier("c")),
+                }
+                .into(),
+            )),
+        );
+        // foo a == b
+        assert_eq!(
+            expression("foo a == b", 0, options),
+            Some((
+                "",
+                CstKind::BinaryOperation {
+                    left: Box::new(
+                        CstKind::Call {
+                            receiver: Box::new(build_identifier("foo").with_trailing_space()),
+                            arguments: vec![build_identifier("a")],
+                        }
+                        .with_trailing_space(),
+                    ),
+                    operator: Box::new(operator(BinaryOperator::Equal)),
+                    right: Box::new(build_identifier("b")),
+                }
+                .into(),
+            )),
+        );
+        // a +
+        assert_eq!(
+            expression("a +", 0, options),
+            Some((
+                "",
+                CstKind::BinaryOperation {
+                    left: Box::new(build_identifier("a").with_trailing_space()),
+                    operator: Box::new(CstKind::BinaryOperator(BinaryOperator::Add).into()),
+                    right: Box::new(
+                        CstKind::Error {
+                            unparsable_input: String::new(),
+                            error: CstError::BinaryOperationMissesRight,
+                        }
+                        .into(),
+                    ),
+                }
+                .into(),
+            )),
+        );
+    }
 }
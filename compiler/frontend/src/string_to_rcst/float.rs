@@ -0,0 +1,134 @@
+use super::word::word;
+use crate::{
+    cst::{CstError, CstKind},
+    rcst::Rcst,
+};
+use ordered_float::OrderedFloat;
+use tracing::instrument;
+
+/// Floats are decimal numbers with a fractional part (e.g., `1.5`) and an
+/// optional exponent (e.g., `1.5e3` or `1.5e-3`). Without the dot, the input is
+/// parsed as an int instead.
+#[instrument(level = "trace")]
+pub fn float(input: &str) -> Option<(&str, Rcst)> {
+    let (input, is_negative) = match input.strip_prefix('-') {
+        Some(rest) if rest.starts_with(|c: char| c.is_ascii_digit()) => (rest, true),
+        _ => (input, false),
+    };
+
+    let (input, integer_part) = word(input)?;
+    if !integer_part.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let input = input.strip_prefix('.')?;
+    let (mut input, mut fractional_part) = word(input)?;
+    if !fractional_part.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+
+    // The minus of a negative exponent ends the word, so we have to continue
+    // parsing after it.
+    if fractional_part.ends_with(['e', 'E'])
+        && let Some(rest) = input.strip_prefix('-')
+        && let Some((rest, exponent)) = word(rest)
+    {
+        fractional_part.push('-');
+        fractional_part.push_str(&exponent);
+        input = rest;
+    }
+
+    let string = format!(
+        "{}{integer_part}.{fractional_part}",
+        if is_negative { "-" } else { "" },
+    );
+    let (fraction, exponent) = match fractional_part.split_once(['e', 'E']) {
+        Some((fraction, exponent)) => (fraction, Some(exponent)),
+        None => (fractional_part.as_str(), None),
+    };
+    let rcst = if are_decimal_digits(&integer_part)
+        && are_decimal_digits(fraction)
+        && exponent.map_or(true, |exponent| {
+            are_decimal_digits(exponent.strip_prefix('-').unwrap_or(exponent))
+        }) {
+        let value = string
+            .replace('_', "")
+            .parse()
+            .expect("Couldn't parse float.");
+        CstKind::Float {
+            value: OrderedFloat(value),
+            string,
+        }
+        .into()
+    } else {
+        CstKind::Error {
+            unparsable_input: string,
+            error: CstError::FloatContainsNonDigits,
+        }
+        .into()
+    };
+    Some((input, rcst))
+}
+
+/// Digits may be separated by underscores, but they may neither start nor end
+/// with an underscore.
+fn are_decimal_digits(digits: &str) -> bool {
+    !digits.is_empty()
+        && !digits.starts_with('_')
+        && !digits.ends_with('_')
+        && digits.chars().all(|c| c == '_' || c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_float() {
+        assert_eq!(float("1.5 "), Some((" ", build_float(1.5, "1.5"))),);
+        assert_eq!(float("-0.25"), Some(("", build_float(-0.25, "-0.25"))),);
+        assert_eq!(
+            float("1_000.000_1"),
+            Some(("", build_float(1_000.000_1, "1_000.000_1"))),
+        );
+        assert_eq!(float("1.5e3"), Some(("", build_float(1.5e3, "1.5e3"))),);
+        assert_eq!(
+            float("1.5E-3, 2"),
+            Some((", 2", build_float(1.5e-3, "1.5E-3"))),
+        );
+        assert_eq!(float("1"), None);
+        assert_eq!(float("1.foo"), None);
+        assert_eq!(float("foo.5"), None);
+        assert_eq!(float("-.5"), None);
+        assert_eq!(
+            float("1.5x"),
+            Some((
+                "",
+                CstKind::Error {
+                    unparsable_input: "1.5x".to_string(),
+                    error: CstError::FloatContainsNonDigits,
+                }
+                .into(),
+            )),
+        );
+        assert_eq!(
+            float("1_.5"),
+            Some((
+                "",
+                CstKind::Error {
+                    unparsable_input: "1_.5".to_string(),
+                    error: CstError::FloatContainsNonDigits,
+                }
+                .into(),
+            )),
+        );
+    }
+
+    fn build_float(value: f64, string: &str) -> Rcst {
+        CstKind::Float {
+            value: OrderedFloat(value),
+            string: string.to_string(),
+        }
+        .into()
+    }
+}
@@ -35,6 +35,7 @@ pub fn function(input: &str, indentation: usize) -> Option<(&str, Rcst)> {
                 ExpressionParsingOptions {
                     allow_assignment: false,
                     allow_call: false,
+                    allow_binary_operation: false,
                     allow_bar: false,
                     allow_function: false,
                 },
@@ -71,6 +72,7 @@ pub fn function(input: &str, indentation: usize) -> Option<(&str, Rcst)> {
             ExpressionParsingOptions {
                 allow_assignment: true,
                 allow_call: true,
+                allow_binary_operation: true,
                 allow_bar: true,
                 allow_function: true,
             },
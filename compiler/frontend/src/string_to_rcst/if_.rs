@@ -0,0 +1,245 @@
+use super::{
+    expression::{expression, ExpressionParsingOptions},
+    whitespace::whitespaces_and_newlines,
+    word::keyword,
+};
+use crate::{
+    cst::{CstError, CstKind},
+    rcst::Rcst,
+};
+use tracing::instrument;
+
+/// Parses `if condition then a else b`. The condition, `then`, and `else` may
+/// be on separate, indented lines.
+///
+/// Because `if` is not a reserved keyword, this only returns an `if`
+/// expression if a condition followed by `then` exists. Otherwise, `if` is
+/// parsed as a normal identifier.
+#[instrument(level = "trace")]
+pub fn if_(input: &str, indentation: usize) -> Option<(&str, Rcst)> {
+    let (input, if_keyword) = keyword(input, "if", CstKind::IfKeyword)?;
+    let (input, whitespace) = whitespaces_and_newlines(input, indentation + 1, true);
+    let if_keyword = if_keyword.wrap_in_whitespace(whitespace);
+
+    let (input, condition) = expression(input, indentation + 1, branch_options())?;
+    let (input, whitespace) = whitespaces_and_newlines(input, indentation + 1, true);
+    let condition = condition.wrap_in_whitespace(whitespace);
+
+    let (input, then_keyword) = keyword(input, "then", CstKind::ThenKeyword)?;
+    let (input, then_keyword, then) = branch(input, indentation, then_keyword);
+
+    let (input, then, else_keyword, else_) = {
+        let (input_after_whitespace, whitespace) =
+            whitespaces_and_newlines(input, indentation + 1, true);
+        match keyword(input_after_whitespace, "else", CstKind::ElseKeyword) {
+            Some((input, else_keyword)) => {
+                let (input, else_keyword, else_) = branch(input, indentation, else_keyword);
+                (
+                    input,
+                    then.wrap_in_whitespace(whitespace),
+                    else_keyword,
+                    else_,
+                )
+            }
+            None => (
+                input,
+                then,
+                CstKind::Error {
+                    unparsable_input: String::new(),
+                    error: CstError::IfMissesElse,
+                }
+                .into(),
+                missing_branch(),
+            ),
+        }
+    };
+
+    Some((
+        input,
+        CstKind::If {
+            if_keyword: Box::new(if_keyword),
+            condition: Box::new(condition),
+            then_keyword: Box::new(then_keyword),
+            then: Box::new(then),
+            else_keyword: Box::new(else_keyword),
+            else_: Box::new(else_),
+        }
+        .into(),
+    ))
+}
+
+/// Parses the expression after `then` or `else`.
+fn branch(input: &str, indentation: usize, keyword: Rcst) -> (&str, Rcst, Rcst) {
+    let (input_after_whitespace, whitespace) =
+        whitespaces_and_newlines(input, indentation + 1, true);
+    match expression(input_after_whitespace, indentation + 1, branch_options()) {
+        Some((input, expression)) => (input, keyword.wrap_in_whitespace(whitespace), expression),
+        None => (input, keyword, missing_branch()),
+    }
+}
+fn missing_branch() -> Rcst {
+    CstKind::Error {
+        unparsable_input: String::new(),
+        error: CstError::IfMissesBody,
+    }
+    .into()
+}
+
+const fn branch_options() -> ExpressionParsingOptions {
+    ExpressionParsingOptions {
+        allow_assignment: false,
+        allow_call: true,
+        allow_binary_operation: true,
+        allow_bar: true,
+        allow_function: true,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::string_to_rcst::utils::{build_identifier, build_simple_int};
+
+    #[test]
+    fn test_if() {
+        assert_eq!(if_("foo", 0), None);
+        assert_eq!(if_("if", 0), None);
+        // `if` without `then` is a normal call of the `if` function.
+        assert_eq!(if_("if foo { 1 }", 0), None);
+        assert_eq!(
+            if_("if foo then 1 else 2", 0),
+            Some((
+                "",
+                CstKind::If {
+                    if_keyword: Box::new(CstKind::IfKeyword.with_trailing_space()),
+                    condition: Box::new(build_identifier("foo").with_trailing_space()),
+                    then_keyword: Box::new(CstKind::ThenKeyword.with_trailing_space()),
+                    then: Box::new(build_simple_int(1).with_trailing_space()),
+                    else_keyword: Box::new(CstKind::ElseKeyword.with_trailing_space()),
+                    else_: Box::new(build_simple_int(2)),
+                }
+                .into(),
+            )),
+        );
+        assert_eq!(
+            if_("if foo bar then 1 else 2", 0),
+            Some((
+                "",
+                CstKind::If {
+                    if_keyword: Box::new(CstKind::IfKeyword.with_trailing_space()),
+                    condition: Box::new(
+                        CstKind::Call {
+                            receiver: Box::new(build_identifier("foo").with_trailing_space()),
+                            arguments: vec![build_identifier("bar")],
+                        }
+                        .with_trailing_space(),
+                    ),
+                    then_keyword: Box::new(CstKind::ThenKeyword.with_trailing_space()),
+                    then: Box::new(build_simple_int(1).with_trailing_space()),
+                    else_keyword: Box::new(CstKind::ElseKeyword.with_trailing_space()),
+                    else_: Box::new(build_simple_int(2)),
+                }
+                .into(),
+            )),
+        );
+        // if foo
+        //   then 1
+        //   else 2
+        assert_eq!(
+            if_("if foo\n  then 1\n  else 2", 0),
+            Some((
+                "",
+                CstKind::If {
+                    if_keyword: Box::new(CstKind::IfKeyword.with_trailing_space()),
+                    condition: Box::new(build_identifier("foo").with_trailing_whitespace(vec![
+                        CstKind::Newline("\n".to_string()),
+                        CstKind::Whitespace("  ".to_string()),
+                    ])),
+                    then_keyword: Box::new(CstKind::ThenKeyword.with_trailing_space()),
+                    then: Box::new(build_simple_int(1).with_trailing_whitespace(vec![
+                        CstKind::Newline("\n".to_string()),
+                        CstKind::Whitespace("  ".to_string()),
+                    ])),
+                    else_keyword: Box::new(CstKind::ElseKeyword.with_trailing_space()),
+                    else_: Box::new(build_simple_int(2)),
+                }
+                .into(),
+            )),
+        );
+        assert_eq!(
+            if_("if foo then 1\nbar", 0),
+            Some((
+                "\nbar",
+                CstKind::If {
+                    if_keyword: Box::new(CstKind::IfKeyword.with_trailing_space()),
+                    condition: Box::new(build_identifier("foo").with_trailing_space()),
+                    then_keyword: Box::new(CstKind::ThenKeyword.with_trailing_space()),
+                    then: Box::new(build_simple_int(1)),
+                    else_keyword: Box::new(
+                        CstKind::Error {
+                            unparsable_input: String::new(),
+                            error: CstError::IfMissesElse,
+                        }
+                        .into(),
+                    ),
+                    else_: Box::new(
+                        CstKind::Error {
+                            unparsable_input: String::new(),
+                            error: CstError::IfMissesBody,
+                        }
+                        .into(),
+                    ),
+                }
+                .into(),
+            )),
+        );
+        assert_eq!(
+            if_("if foo then else", 0),
+            Some((
+                "",
+                CstKind::If {
+                    if_keyword: Box::new(CstKind::IfKeyword.with_trailing_space()),
+                    condition: Box::new(build_identifier("foo").with_trailing_space()),
+                    then_keyword: Box::new(CstKind::ThenKeyword.into()),
+                    then: Box::new(
+                        CstKind::Error {
+                            unparsable_input: String::new(),
+                            error: CstError::IfMissesBody,
+                        }
+                        .with_trailing_space(),
+                    ),
+                    else_keyword: Box::new(CstKind::ElseKeyword.into()),
+                    else_: Box::new(
+                        CstKind::Error {
+                            unparsable_input: String::new(),
+                            error: CstError::IfMissesBody,
+                        }
+                        .into(),
+                    ),
+                }
+                .into(),
+            )),
+        );
+        assert_eq!(
+            if_("if foo then bar baz else 2", 0),
+            Some((
+                "",
+                CstKind::If {
+                    if_keyword: Box::new(CstKind::IfKeyword.with_trailing_space()),
+                    condition: Box::new(build_identifier("foo").with_trailing_space()),
+                    then_keyword: Box::new(CstKind::ThenKeyword.with_trailing_space()),
+                    then: Box::new(
+                        CstKind::Call {
+                            receiver: Box::new(build_identifier("bar").with_trailing_space()),
+                            arguments: vec![build_identifier("baz")],
+                        }
+                        .with_trailing_space(),
+                    ),
+                    else_keyword: Box::new(CstKind::ElseKeyword.with_trailing_space()),
+                    else_: Box::new(build_simple_int(2)),
+                }
+                .into(),
+            )),
+        );
+    }
+}
@@ -0,0 +1,244 @@
+//! Reparsing a module after a change only needs to parse the top-level
+//! expressions around the change.
+//!
+//! The top-level body is parsed by calling [`body_item`] repeatedly, and each
+//! call only depends on the remaining source code. We remember where each of
+//! these items starts. After a change, we keep the items in front of the
+//! change and start parsing again shortly before it. As soon as the parser
+//! reaches the start of an old item behind the change, the remaining source
+//! code is identical, so the remaining old items can be reused.
+
+use super::{body::body_item, parse_rest};
+use crate::{module::Module, rcst::Rcst};
+use rustc_hash::FxHashMap;
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RcstItems(Vec<RcstItem>);
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct RcstItem {
+    /// Byte offset of this item in the source code.
+    offset: usize,
+    rcsts: Vec<Rcst>,
+}
+
+impl RcstItems {
+    #[must_use]
+    pub fn parse(source: &str) -> Self {
+        Self(parse_items_from(source, 0, |_| None))
+    }
+
+    /// Parses the `new_source`, reusing the items of `self` which were parsed
+    /// from the `old_source`.
+    #[must_use]
+    pub fn reparse(&self, old_source: &str, new_source: &str) -> Self {
+        let (old_range, new_end) = changed_range(old_source, new_source);
+
+        // Items may look a bit beyond where they end (e.g., to check the
+        // indentation of the next line), so we also reparse the item in front
+        // of the one containing the change.
+        let first_changed_index = self
+            .0
+            .iter()
+            .position(|item| item.offset >= old_range.start)
+            .unwrap_or(self.0.len());
+        let first_reparsed_index = first_changed_index.saturating_sub(2);
+        let reparse_offset = self.0.get(first_reparsed_index).map_or(0, |it| it.offset);
+
+        let mut items = self.0[..first_reparsed_index].to_vec();
+        items.extend(parse_items_from(new_source, reparse_offset, |offset| {
+            if offset < new_end {
+                return None;
+            }
+
+            let old_offset = offset + old_range.end - new_end;
+            let index = self
+                .0
+                .binary_search_by_key(&old_offset, |it| it.offset)
+                .ok()?;
+            Some(
+                self.0[index..]
+                    .iter()
+                    .map(|item| RcstItem {
+                        offset: item.offset + new_end - old_range.end,
+                        rcsts: item.rcsts.clone(),
+                    })
+                    .collect(),
+            )
+        }));
+        Self(items)
+    }
+
+    #[must_use]
+    pub fn to_rcsts(&self) -> Vec<Rcst> {
+        self.0
+            .iter()
+            .flat_map(|item| item.rcsts.iter().cloned())
+            .collect()
+    }
+}
+
+/// Parses items starting at `offset`.
+///
+/// Before parsing each item, `reuse` is called with its offset and can return
+/// the remaining items instead.
+fn parse_items_from(
+    source: &str,
+    mut offset: usize,
+    mut reuse: impl FnMut(usize) -> Option<Vec<RcstItem>>,
+) -> Vec<RcstItem> {
+    let mut items = vec![];
+    loop {
+        if let Some(mut reused_items) = reuse(offset) {
+            items.append(&mut reused_items);
+            return items;
+        }
+
+        let Some((rest, rcsts)) = body_item(&source[offset..], 0) else {
+            break;
+        };
+        items.push(RcstItem { offset, rcsts });
+        offset = source.len() - rest.len();
+    }
+
+    let rcsts = parse_rest(&source[offset..]);
+    if !rcsts.is_empty() {
+        items.push(RcstItem { offset, rcsts });
+    }
+    items
+}
+
+/// Returns the range in the old source that changed and the end of the
+/// replacement in the new source.
+fn changed_range(old_source: &str, new_source: &str) -> (std::ops::Range<usize>, usize) {
+    let mut prefix_length = old_source
+        .bytes()
+        .zip(new_source.bytes())
+        .take_while(|(old, new)| old == new)
+        .count();
+    while !old_source.is_char_boundary(prefix_length) {
+        prefix_length -= 1;
+    }
+
+    let max_suffix_length = old_source.len().min(new_source.len()) - prefix_length;
+    let mut suffix_length = old_source
+        .bytes()
+        .rev()
+        .zip(new_source.bytes().rev())
+        .take(max_suffix_length)
+        .take_while(|(old, new)| old == new)
+        .count();
+    while !old_source.is_char_boundary(old_source.len() - suffix_length) {
+        suffix_length -= 1;
+    }
+
+    (
+        prefix_length..old_source.len() - suffix_length,
+        new_source.len() - suffix_length,
+    )
+}
+
+/// Remembers the last parsed version of each module so that modules can be
+/// reparsed incrementally after they changed.
+///
+/// Salsa only stores the latest RCST and throws it away when the module's
+/// content changes. Reparsing incrementally always produces the same RCST as
+/// parsing from scratch, so this cache is invisible to salsa.
+#[derive(Debug, Default)]
+pub struct RcstCache(Mutex<FxHashMap<Module, CachedRcst>>);
+#[derive(Debug)]
+struct CachedRcst {
+    source: Arc<String>,
+    items: Arc<RcstItems>,
+}
+impl RcstCache {
+    #[must_use]
+    pub fn parse(&self, module: &Module, source: Arc<String>) -> Arc<RcstItems> {
+        let mut cache = self.0.lock().unwrap();
+        let items = if let Some(cached) = cache.get(module) {
+            if cached.source == source {
+                return cached.items.clone();
+            }
+            cached.items.reparse(&cached.source, &source)
+        } else {
+            RcstItems::parse(&source)
+        };
+        let items = Arc::new(items);
+        cache.insert(
+            module.clone(),
+            CachedRcst {
+                source,
+                items: items.clone(),
+            },
+        );
+        items
+    }
+}
+
+/// Databases for editors can provide an [`RcstCache`] so that changed modules
+/// are reparsed incrementally.
+pub trait RcstCacheOwner {
+    fn rcst_cache(&self) -> Option<&RcstCache> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::string_to_rcst::parse_rcst;
+
+    const SOURCE: &str = r#"[foo, bar] = use ".foo"
+
+# A comment.
+baz a b :=
+  needs (int.is a)
+  c = [Foo: a, Bar: (b, 2)]
+  c.foo | bar { it ->
+    "text {it}"
+  }
+
+text := "
+  multiline
+"
+list := (
+  1,
+  2,
+)
+"#;
+
+    fn check_reparse(old_source: &str, new_source: &str) {
+        let old_items = RcstItems::parse(old_source);
+        assert_eq!(old_items.to_rcsts(), parse_rcst(old_source));
+        assert_eq!(
+            old_items.reparse(old_source, new_source).to_rcsts(),
+            parse_rcst(new_source),
+            "Reparsing after changing {old_source:?} to {new_source:?} differs from parsing from scratch.",
+        );
+    }
+
+    #[test]
+    fn test_reparse_after_deletions() {
+        for (index, _) in SOURCE.char_indices() {
+            let mut new_source = SOURCE.to_string();
+            new_source.remove(index);
+            check_reparse(SOURCE, &new_source);
+        }
+    }
+    #[test]
+    fn test_reparse_after_insertions() {
+        for (index, _) in SOURCE.char_indices() {
+            for inserted in ["\n", " ", "\"", "[", "x", "#", "{ a ->"] {
+                let mut new_source = SOURCE.to_string();
+                new_source.insert_str(index, inserted);
+                check_reparse(SOURCE, &new_source);
+            }
+        }
+    }
+    #[test]
+    fn test_reparse_after_replacing_everything() {
+        check_reparse(SOURCE, "foo = 1\n");
+        check_reparse("", SOURCE);
+        check_reparse(SOURCE, "");
+    }
+}
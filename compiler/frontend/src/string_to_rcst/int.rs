@@ -3,54 +3,60 @@ use crate::{
     cst::{CstError, CstKind, IntRadix},
     rcst::Rcst,
 };
-use num_bigint::BigUint;
+use num_bigint::{BigInt, BigUint};
 use num_traits::Num;
 use tracing::instrument;
 
 #[instrument(level = "trace")]
 pub fn int(input: &str) -> Option<(&str, Rcst)> {
+    // A minus is only part of the int if it's directly followed by a digit.
+    // Otherwise, it might be the start of an arrow.
+    let (input, is_negative) = match input.strip_prefix('-') {
+        Some(rest) if rest.starts_with(|c: char| c.is_ascii_digit()) => (rest, true),
+        _ => (input, false),
+    };
+
     let (input, string) = word(input)?;
     if !string.chars().next().unwrap().is_ascii_digit() {
         return None;
     }
 
-    let rcst = if (string.starts_with("0b") || string.starts_with("0B"))
-        && string.len() >= 3
-        && string.chars().skip(2).all(|c| c == '0' || c == '1')
-    {
-        // Binary
-        let value = BigUint::from_str_radix(&string[2..], 2).expect("Couldn't parse binary int.");
-        CstKind::Int {
-            radix_prefix: Some((IntRadix::Binary, string[..2].to_string())),
-            value,
-            string: string[2..].to_string(),
-        }
-        .into()
-    } else if (string.starts_with("0x") || string.starts_with("0X"))
-        && string.len() >= 3
-        && string.chars().skip(2).all(|c| c.is_ascii_hexdigit())
+    let (radix_prefix, digits, radix) = if string.starts_with("0b") || string.starts_with("0B") {
+        (Some(IntRadix::Binary), &string[2..], 2)
+    } else if string.starts_with("0x") || string.starts_with("0X") {
+        (Some(IntRadix::Hexadecimal), &string[2..], 16)
+    } else {
+        (None, string.as_str(), 10)
+    };
+
+    // Digits may be separated by underscores (e.g., `1_000_000`), but an int
+    // may neither start nor end with an underscore.
+    let rcst = if !digits.is_empty()
+        && !digits.starts_with('_')
+        && !digits.ends_with('_')
+        && digits.chars().all(|c| c == '_' || c.is_digit(radix))
     {
-        // Hexadecimal
         let value =
-            BigUint::from_str_radix(&string[2..], 16).expect("Couldn't parse hexadecimal int.");
-        CstKind::Int {
-            radix_prefix: Some((IntRadix::Hexadecimal, string[..2].to_string())),
-            value,
-            string: string[2..].to_string(),
-        }
-        .into()
-    } else if string.chars().all(|c| c.is_ascii_digit()) {
-        // Decimal
-        let value = str::parse(&string).expect("Couldn't parse decimal int.");
+            BigUint::from_str_radix(&digits.replace('_', ""), radix).expect("Couldn't parse int.");
+        let value = if is_negative {
+            -BigInt::from(value)
+        } else {
+            value.into()
+        };
         CstKind::Int {
-            radix_prefix: None,
+            is_negative,
+            radix_prefix: radix_prefix.map(|radix| (radix, string[..2].to_string())),
             value,
-            string,
+            string: digits.to_string(),
         }
         .into()
     } else {
         CstKind::Error {
-            unparsable_input: string,
+            unparsable_input: if is_negative {
+                format!("-{string}")
+            } else {
+                string
+            },
             error: CstError::IntContainsNonDigits,
         }
         .into()
@@ -71,6 +77,7 @@ mod test {
             Some((
                 "",
                 CstKind::Int {
+                    is_negative: false,
                     radix_prefix: Some((IntRadix::Binary, "0b".to_string())),
                     value: 0b10u8.into(),
                     string: "10".to_string()
@@ -83,6 +90,7 @@ mod test {
             Some((
                 "",
                 CstKind::Int {
+                    is_negative: false,
                     radix_prefix: Some((IntRadix::Binary, "0B".to_string())),
                     value: 0b101u8.into(),
                     string: "101".to_string()
@@ -95,6 +103,7 @@ mod test {
             Some((
                 "",
                 CstKind::Int {
+                    is_negative: false,
                     radix_prefix: Some((IntRadix::Binary, "0b".to_string())),
                     value: 0b1010_0101u32.into(),
                     string: "10100101".to_string()
@@ -109,6 +118,7 @@ mod test {
             Some((
                 "",
                 CstKind::Int {
+                    is_negative: false,
                     radix_prefix: None,
                     value: 12u8.into(),
                     string: "012".to_string()
@@ -122,6 +132,7 @@ mod test {
             Some((
                 "",
                 CstKind::Int {
+                    is_negative: false,
                     radix_prefix: Some((IntRadix::Hexadecimal, "0x".to_string())),
                     value: 0x12u8.into(),
                     string: "12".to_string()
@@ -134,6 +145,7 @@ mod test {
             Some((
                 "",
                 CstKind::Int {
+                    is_negative: false,
                     radix_prefix: Some((IntRadix::Hexadecimal, "0X".to_string())),
                     value: 0x12u8.into(),
                     string: "012".to_string()
@@ -146,6 +158,7 @@ mod test {
             Some((
                 "",
                 CstKind::Int {
+                    is_negative: false,
                     radix_prefix: Some((IntRadix::Hexadecimal, "0x".to_string())),
                     value: 0xDEAD_C0DEu32.into(),
                     string: "DEADc0de".to_string()
@@ -156,6 +169,8 @@ mod test {
 
         assert_eq!(int("123 years"), Some((" years", build_simple_int(123))));
         assert_eq!(int("foo"), None);
+        assert_eq!(int("->"), None);
+        assert_eq!(int("- 1"), None);
         assert_eq!(
             int("3D"),
             Some((
@@ -168,4 +183,97 @@ mod test {
             )),
         );
     }
+
+    #[test]
+    fn test_negative_int() {
+        assert_eq!(
+            int("-42 "),
+            Some((
+                " ",
+                CstKind::Int {
+                    is_negative: true,
+                    radix_prefix: None,
+                    value: (-42).into(),
+                    string: "42".to_string()
+                }
+                .into(),
+            )),
+        );
+        assert_eq!(
+            int("-0xFF"),
+            Some((
+                "",
+                CstKind::Int {
+                    is_negative: true,
+                    radix_prefix: Some((IntRadix::Hexadecimal, "0x".to_string())),
+                    value: (-0xFF).into(),
+                    string: "FF".to_string()
+                }
+                .into(),
+            )),
+        );
+        assert_eq!(
+            int("-1a"),
+            Some((
+                "",
+                CstKind::Error {
+                    unparsable_input: "-1a".to_string(),
+                    error: CstError::IntContainsNonDigits,
+                }
+                .into(),
+            )),
+        );
+    }
+
+    #[test]
+    fn test_int_with_digit_separators() {
+        assert_eq!(
+            int("1_000_000"),
+            Some((
+                "",
+                CstKind::Int {
+                    is_negative: false,
+                    radix_prefix: None,
+                    value: 1_000_000.into(),
+                    string: "1_000_000".to_string()
+                }
+                .into(),
+            )),
+        );
+        assert_eq!(
+            int("0b1010_0101"),
+            Some((
+                "",
+                CstKind::Int {
+                    is_negative: false,
+                    radix_prefix: Some((IntRadix::Binary, "0b".to_string())),
+                    value: 0b1010_0101.into(),
+                    string: "1010_0101".to_string()
+                }
+                .into(),
+            )),
+        );
+        assert_eq!(
+            int("1_"),
+            Some((
+                "",
+                CstKind::Error {
+                    unparsable_input: "1_".to_string(),
+                    error: CstError::IntContainsNonDigits,
+                }
+                .into(),
+            )),
+        );
+        assert_eq!(
+            int("0x_1"),
+            Some((
+                "",
+                CstKind::Error {
+                    unparsable_input: "0x_1".to_string(),
+                    error: CstError::IntContainsNonDigits,
+                }
+                .into(),
+            )),
+        );
+    }
 }
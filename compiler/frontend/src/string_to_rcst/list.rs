@@ -37,6 +37,7 @@ pub fn list(input: &str, indentation: usize) -> Option<(&str, Rcst)> {
             ExpressionParsingOptions {
                 allow_assignment: false,
                 allow_call: true,
+                allow_binary_operation: true,
                 allow_bar: true,
                 allow_function: true,
             },
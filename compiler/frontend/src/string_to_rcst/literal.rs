@@ -1,4 +1,9 @@
-use crate::{cst::CstKind, rcst::Rcst};
+use super::utils::SUPPORTED_WHITESPACE;
+use crate::{
+    cst::{BinaryOperator, CstKind},
+    rcst::Rcst,
+};
+use strum::VariantArray;
 use tracing::instrument;
 
 #[instrument(level = "trace")]
@@ -33,6 +38,23 @@ define_literal!(double_quote, "\"", CstKind::DoubleQuote);
 define_literal!(percent, "%", CstKind::Percent);
 define_literal!(octothorpe, "#", CstKind::Octothorpe);
 
+/// Binary operators have to be followed by whitespace. Otherwise, `-1` would be
+/// ambiguous: It could be a negative int or the start of a subtraction.
+#[instrument(level = "trace")]
+pub fn binary_operator(input: &str) -> Option<(&str, Rcst)> {
+    let length = input
+        .find(|c| !"+-*/<>=!".contains(c))
+        .unwrap_or(input.len());
+    let (operator, rest) = input.split_at(length);
+    let operator = BinaryOperator::VARIANTS
+        .iter()
+        .find(|it| it.as_str() == operator)?;
+    if !rest.is_empty() && !rest.starts_with(|c| SUPPORTED_WHITESPACE.contains(c)) {
+        return None;
+    }
+    Some((rest, CstKind::BinaryOperator(*operator).into()))
+}
+
 #[instrument(level = "trace")]
 pub fn newline(input: &str) -> Option<(&str, Rcst)> {
     let newlines = vec!["\n", "\r\n"];
@@ -53,4 +75,27 @@ mod test {
         assert_eq!(literal("hello, world", "hello"), Some(", world"));
         assert_eq!(literal("hello, world", "hi"), None);
     }
+
+    #[test]
+    fn test_binary_operator() {
+        assert_eq!(
+            binary_operator("+ 1"),
+            Some((" 1", CstKind::BinaryOperator(BinaryOperator::Add).into())),
+        );
+        assert_eq!(
+            binary_operator("<=\n"),
+            Some((
+                "\n",
+                CstKind::BinaryOperator(BinaryOperator::LessOrEqual).into()
+            )),
+        );
+        assert_eq!(
+            binary_operator("=="),
+            Some(("", CstKind::BinaryOperator(BinaryOperator::Equal).into())),
+        );
+        assert_eq!(binary_operator("-1"), None);
+        assert_eq!(binary_operator("-> foo"), None);
+        assert_eq!(binary_operator("= foo"), None);
+        assert_eq!(binary_operator("foo"), None);
+    }
 }
@@ -8,7 +8,10 @@
 
 mod body;
 mod expression;
+mod float;
 mod function;
+mod if_;
+mod incremental;
 mod int;
 mod list;
 mod literal;
@@ -23,18 +26,24 @@ use crate::{
     module::{Module, ModuleDb, ModuleKind, Package},
     rcst::Rcst,
     rich_ir::{RichIrBuilder, ToRichIr, TokenType},
+    timings::{self, Stage},
 };
 use enumset::EnumSet;
+pub use incremental::{RcstCache, RcstCacheOwner, RcstItems};
 use std::{str, sync::Arc};
 
 #[salsa::query_group(StringToRcstStorage)]
-pub trait StringToRcst: ModuleDb {
+pub trait StringToRcst: ModuleDb + RcstCacheOwner {
     fn rcst(&self, module: Module) -> RcstResult;
 }
 
 pub type RcstResult = Result<Arc<Vec<Rcst>>, ModuleError>;
 
+#[allow(clippy::needless_pass_by_value)]
 fn rcst(db: &dyn StringToRcst, module: Module) -> RcstResult {
+    timings::record_stage(Stage::Rcst, module.clone(), || rcst_uncached(db, &module))
+}
+fn rcst_uncached(db: &dyn StringToRcst, module: &Module) -> RcstResult {
     if module.kind != ModuleKind::Code {
         return Err(ModuleError::IsNotCandy);
     }
@@ -43,16 +52,27 @@ fn rcst(db: &dyn StringToRcst, module: Module) -> RcstResult {
         return Err(ModuleError::IsToolingModule);
     }
     let source = db
-        .get_module_content(module)
+        .get_module_content(module.clone())
         .ok_or(ModuleError::DoesNotExist)?;
     let Ok(source) = str::from_utf8(source.as_slice()) else {
         return Err(ModuleError::InvalidUtf8);
     };
+    if let Some(cache) = db.rcst_cache() {
+        return Ok(Arc::new(
+            cache.parse(module, Arc::new(source.to_string())).to_rcsts(),
+        ));
+    }
     Ok(Arc::new(parse_rcst(source)))
 }
 #[must_use]
 pub fn parse_rcst(source: &str) -> Vec<Rcst> {
-    let (mut rest, mut rcsts) = body::body(source, 0);
+    let (rest, mut rcsts) = body::body(source, 0);
+    rcsts.extend(parse_rest(rest));
+    rcsts
+}
+/// Wraps input that couldn't be parsed in an error.
+fn parse_rest(mut rest: &str) -> Vec<Rcst> {
+    let mut rcsts = vec![];
     if !rest.is_empty() {
         let trailing_newline = if rest.ends_with("\r\n") {
             let (_, newline) = literal::newline(&rest[rest.len() - 2..]).unwrap();
@@ -38,6 +38,7 @@ pub fn struct_(input: &str, indentation: usize, allow_function: bool) -> Option<
             ExpressionParsingOptions {
                 allow_assignment: false,
                 allow_call: true,
+                allow_binary_operation: true,
                 allow_bar: true,
                 allow_function,
             },
@@ -83,6 +84,7 @@ pub fn struct_(input: &str, indentation: usize, allow_function: bool) -> Option<
             ExpressionParsingOptions {
                 allow_assignment: false,
                 allow_call: true,
+                allow_binary_operation: true,
                 allow_bar: true,
                 allow_function,
             },
@@ -42,6 +42,11 @@ pub fn text(input: &str, indentation: usize) -> Option<(&str, Rcst)> {
         {
             input = input_after_interpolation;
             parts.push(interpolation);
+        } else if opening_single_quotes.is_empty()
+            && let Some((input_after_escape, escape)) = text_escape(input)
+        {
+            input = input_after_escape;
+            parts.push(escape);
         } else if let Some((input_after_part, part)) = text_part(input, opening_single_quotes.len())
         {
             input = input_after_part;
@@ -167,6 +172,7 @@ fn text_interpolation(
         ExpressionParsingOptions {
             allow_assignment: false,
             allow_call: true,
+            allow_binary_operation: true,
             allow_bar: true,
             allow_function: true,
         },
@@ -204,6 +210,95 @@ fn text_interpolation(
     ))
 }
 
+/// Parses an escape sequence like `\n` or `\u{1F600}`.
+///
+/// Escape sequences are only supported in texts without single quotes. Texts
+/// with single quotes are raw, which is useful for regular expressions.
+#[instrument(level = "trace")]
+fn text_escape(input: &str) -> Option<(&str, Rcst)> {
+    let input_after_backslash = input.strip_prefix('\\')?;
+    let Some(escaped) = input_after_backslash.chars().next() else {
+        return Some((
+            input_after_backslash,
+            invalid_escape(input, input_after_backslash, CstError::TextEscapeIsUnknown),
+        ));
+    };
+    let (input_after_escape, value) = match escaped {
+        '"' => (&input_after_backslash[1..], '"'),
+        '\\' => (&input_after_backslash[1..], '\\'),
+        'n' => (&input_after_backslash[1..], '\n'),
+        't' => (&input_after_backslash[1..], '\t'),
+        'u' => {
+            let input_after_u = &input_after_backslash[1..];
+            let Some(input_after_opening_curly_brace) = input_after_u.strip_prefix('{') else {
+                return Some((
+                    input_after_u,
+                    invalid_escape(input, input_after_u, CstError::TextUnicodeEscapeIsInvalid),
+                ));
+            };
+            let digits_length = input_after_opening_curly_brace
+                .find(|c: char| !c.is_ascii_hexdigit())
+                .unwrap_or(input_after_opening_curly_brace.len());
+            let (digits, input_after_digits) =
+                input_after_opening_curly_brace.split_at(digits_length);
+            let Some(input_after_closing_curly_brace) = input_after_digits.strip_prefix('}') else {
+                return Some((
+                    input_after_digits,
+                    invalid_escape(
+                        input,
+                        input_after_digits,
+                        CstError::TextUnicodeEscapeIsInvalid,
+                    ),
+                ));
+            };
+            let value = if (1..=6).contains(&digits.len()) {
+                u32::from_str_radix(digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+            } else {
+                None
+            };
+            let Some(value) = value else {
+                return Some((
+                    input_after_closing_curly_brace,
+                    invalid_escape(
+                        input,
+                        input_after_closing_curly_brace,
+                        CstError::TextUnicodeEscapeIsInvalid,
+                    ),
+                ));
+            };
+            (input_after_closing_curly_brace, value)
+        }
+        _ if newline(input_after_backslash).is_some() => {
+            return Some((
+                input_after_backslash,
+                invalid_escape(input, input_after_backslash, CstError::TextEscapeIsUnknown),
+            ));
+        }
+        other => {
+            let input_after_escape = &input_after_backslash[other.len_utf8()..];
+            return Some((
+                input_after_escape,
+                invalid_escape(input, input_after_escape, CstError::TextEscapeIsUnknown),
+            ));
+        }
+    };
+    let string = input[..input.len() - input_after_escape.len()].to_string();
+    Some((
+        input_after_escape,
+        CstKind::TextEscape { value, string }.into(),
+    ))
+}
+
+fn invalid_escape(input: &str, input_after_escape: &str, error: CstError) -> Rcst {
+    CstKind::Error {
+        unparsable_input: input[..input.len() - input_after_escape.len()].to_string(),
+        error,
+    }
+    .into()
+}
+
 #[instrument(level = "trace")]
 fn text_part(mut input: &str, single_quotes_count: usize) -> Option<(&str, Rcst)> {
     let mut text_part = vec![];
@@ -212,6 +307,7 @@ fn text_part(mut input: &str, single_quotes_count: usize) -> Option<(&str, Rcst)
         // TODO Optimize this somehow
         if next_char.is_none()
             || newline(input).is_some()
+            || (single_quotes_count == 0 && next_char == Some('\\'))
             || parse_multiple(
                 input,
                 opening_curly_brace,
@@ -661,4 +757,89 @@ mod test {
             )),
         );
     }
+
+    #[test]
+    fn test_text_escape() {
+        assert_eq!(text_escape("foo"), None);
+        assert_eq!(
+            text_escape(r"\n foo"),
+            Some((
+                " foo",
+                CstKind::TextEscape {
+                    value: '\n',
+                    string: r"\n".to_string(),
+                }
+                .into(),
+            )),
+        );
+        assert_eq!(
+            text_escape(r"\u{1F600}"),
+            Some((
+                "",
+                CstKind::TextEscape {
+                    value: '😀',
+                    string: r"\u{1F600}".to_string(),
+                }
+                .into(),
+            )),
+        );
+        assert_eq!(
+            text_escape(r"\d+"),
+            Some((
+                "+",
+                CstKind::Error {
+                    unparsable_input: r"\d".to_string(),
+                    error: CstError::TextEscapeIsUnknown,
+                }
+                .into(),
+            )),
+        );
+        assert_eq!(
+            text_escape(r"\u{D800}"),
+            Some((
+                "",
+                CstKind::Error {
+                    unparsable_input: r"\u{D800}".to_string(),
+                    error: CstError::TextUnicodeEscapeIsInvalid,
+                }
+                .into(),
+            )),
+        );
+        assert_eq!(
+            text_escape("\\u{41\""),
+            Some((
+                "\"",
+                CstKind::Error {
+                    unparsable_input: r"\u{41".to_string(),
+                    error: CstError::TextUnicodeEscapeIsInvalid,
+                }
+                .into(),
+            )),
+        );
+        assert_eq!(
+            text(r#""a\"b" c"#, 0),
+            Some((
+                " c",
+                build_text(
+                    0,
+                    vec![
+                        CstKind::TextPart("a".to_string()).into(),
+                        CstKind::TextEscape {
+                            value: '"',
+                            string: r#"\""#.to_string(),
+                        }
+                        .into(),
+                        CstKind::TextPart("b".to_string()).into(),
+                    ]
+                )
+            )),
+        );
+        assert_eq!(
+            text(r#"'"a\nb"'"#, 0),
+            Some((
+                "",
+                build_text(1, vec![CstKind::TextPart(r"a\nb".to_string()).into()])
+            )),
+        );
+    }
 }
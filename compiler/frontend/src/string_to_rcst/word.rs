@@ -27,9 +27,31 @@ pub fn word(mut input: &str) -> Option<(&str, String)> {
     }
 }
 
+/// Words that separate the parts of an `if` expression, so they can't be used
+/// as identifiers.
+///
+/// `if` itself is not reserved because the `Core` package has a function named
+/// `if`. It only starts an `if` expression if it's followed by a condition and
+/// `then`.
+const RESERVED_KEYWORDS: [&str; 2] = ["then", "else"];
+
+#[must_use]
+pub fn is_reserved_keyword(word: &str) -> bool {
+    RESERVED_KEYWORDS.contains(&word)
+}
+
+#[instrument(level = "trace")]
+pub fn keyword<'a>(input: &'a str, keyword: &str, kind: CstKind<()>) -> Option<(&'a str, Rcst)> {
+    let (input, w) = word(input)?;
+    (w == keyword).then(|| (input, kind.into()))
+}
+
 #[instrument(level = "trace")]
 pub fn identifier(input: &str) -> Option<(&str, Rcst)> {
     let (input, w) = word(input)?;
+    if is_reserved_keyword(&w) {
+        return None;
+    }
     if w == "✨" {
         return Some((input, CstKind::Identifier(w).into()));
     }
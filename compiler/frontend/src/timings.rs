@@ -0,0 +1,202 @@
+//! Measuring where the compiler spends its time.
+//!
+//! Compiler stages call each other through salsa queries. For example,
+//! computing the HIR of a module first computes its AST, and optimizing the MIR
+//! of a module compiles all modules it uses. To attribute the time correctly,
+//! each stage and optimization pass only counts the time it spends itself,
+//! excluding nested stages and passes.
+//!
+//! Recording is off by default and only enabled for the current thread by
+//! [`start_recording`].
+
+use crate::module::Module;
+use rustc_hash::FxHashMap;
+use std::{
+    cell::RefCell,
+    fmt::{self, Display, Formatter},
+    time::{Duration, Instant},
+};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Stage {
+    Rcst,
+    Cst,
+    Ast,
+    Hir,
+    Mir,
+    MirOptimization,
+    Lir,
+    LirOptimization,
+}
+impl Display for Stage {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Rcst => "rcst",
+            Self::Cst => "cst",
+            Self::Ast => "ast",
+            Self::Hir => "hir",
+            Self::Mir => "mir",
+            Self::MirOptimization => "mir optimization",
+            Self::Lir => "lir",
+            Self::LirOptimization => "lir optimization",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Timings {
+    /// The time spent in each stage, excluding its optimization passes.
+    pub stages: FxHashMap<Stage, Duration>,
+    /// The time spent in each MIR optimization pass, in the order in which
+    /// the passes first ran.
+    pub passes: Vec<(String, Duration)>,
+    pub modules: FxHashMap<Module, ModuleCacheStatistics>,
+}
+#[derive(Clone, Debug, Default)]
+pub struct ModuleCacheStatistics {
+    /// The stages that ran for this module, i.e., salsa cache misses.
+    ///
+    /// The same stage can run several times for a module if it's compiled for
+    /// different execution targets or tracing configs.
+    pub computed: FxHashMap<Stage, usize>,
+    /// How often module folding requested the optimized MIR of this module.
+    pub folding_requests: usize,
+}
+impl ModuleCacheStatistics {
+    /// How often module folding reused an already optimized MIR of this
+    /// module, i.e., salsa cache hits.
+    #[must_use]
+    pub fn folding_cache_hits(&self) -> usize {
+        let computed = self
+            .computed
+            .get(&Stage::MirOptimization)
+            .copied()
+            .unwrap_or_default();
+        self.folding_requests.saturating_sub(computed)
+    }
+}
+
+thread_local! {
+    static RECORDER: RefCell<Option<Recorder>> = const { RefCell::new(None) };
+}
+
+#[derive(Default)]
+struct Recorder {
+    timings: Timings,
+    /// The stages and passes currently running, innermost last.
+    running: Vec<RunningMeasurement>,
+}
+struct RunningMeasurement {
+    start: Instant,
+    nested: Duration,
+}
+
+pub fn start_recording() {
+    RECORDER.with_borrow_mut(|recorder| *recorder = Some(Recorder::default()));
+}
+#[must_use]
+pub fn finish_recording() -> Timings {
+    RECORDER
+        .with_borrow_mut(Option::take)
+        .map(|recorder| recorder.timings)
+        .unwrap_or_default()
+}
+
+/// Runs a stage for a module, i.e., the body of a salsa query.
+pub fn record_stage<T>(stage: Stage, module: Module, run: impl FnOnce() -> T) -> T {
+    measure(run, |timings, duration| {
+        *timings.stages.entry(stage).or_default() += duration;
+        *timings
+            .modules
+            .entry(module)
+            .or_default()
+            .computed
+            .entry(stage)
+            .or_default() += 1;
+    })
+}
+pub fn record_pass<T>(pass: &str, run: impl FnOnce() -> T) -> T {
+    measure(run, |timings, duration| {
+        if let Some((_, total)) = timings.passes.iter_mut().find(|(it, _)| *it == pass) {
+            *total += duration;
+        } else {
+            timings.passes.push((pass.to_string(), duration));
+        }
+    })
+}
+pub fn record_folding_request(module: &Module) {
+    RECORDER.with_borrow_mut(|recorder| {
+        if let Some(recorder) = recorder {
+            recorder
+                .timings
+                .modules
+                .entry(module.clone())
+                .or_default()
+                .folding_requests += 1;
+        }
+    });
+}
+
+fn measure<T>(run: impl FnOnce() -> T, add: impl FnOnce(&mut Timings, Duration)) -> T {
+    let is_recording = RECORDER.with_borrow_mut(|recorder| {
+        let Some(recorder) = recorder else {
+            return false;
+        };
+        recorder.running.push(RunningMeasurement {
+            start: Instant::now(),
+            nested: Duration::ZERO,
+        });
+        true
+    });
+    if !is_recording {
+        return run();
+    }
+
+    let result = run();
+
+    RECORDER.with_borrow_mut(|recorder| {
+        let Some(recorder) = recorder else {
+            return;
+        };
+        let measurement = recorder.running.pop().unwrap();
+        let total = measurement.start.elapsed();
+        if let Some(parent) = recorder.running.last_mut() {
+            parent.nested += total;
+        }
+        add(
+            &mut recorder.timings,
+            total.saturating_sub(measurement.nested),
+        );
+    });
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module::{ModuleKind, Package};
+    use std::{path::PathBuf, thread::sleep};
+
+    #[test]
+    fn nested_stages_only_count_their_own_time() {
+        let module = Module {
+            package: Package::User(PathBuf::from("/non/existent")),
+            path: vec![],
+            kind: ModuleKind::Code,
+        };
+
+        start_recording();
+        record_stage(Stage::Hir, module.clone(), || {
+            record_stage(Stage::Ast, module.clone(), || {
+                sleep(Duration::from_millis(20));
+            });
+        });
+        let timings = finish_recording();
+
+        assert!(timings.stages[&Stage::Ast] >= Duration::from_millis(20));
+        assert!(timings.stages[&Stage::Hir] < Duration::from_millis(20));
+        assert_eq!(timings.modules[&module].computed[&Stage::Hir], 1);
+        assert_eq!(timings.modules[&module].computed[&Stage::Ast], 1);
+    }
+}
@@ -1,12 +1,23 @@
+use crate::module::{Module, Package};
 use clap::ValueEnum;
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TracingConfig {
     pub register_fuzzables: TracingMode,
     pub calls: CallTracingMode,
     pub evaluated_expressions: TracingMode,
+
+    /// Cache results of calls to deterministic functions in the VM and reuse
+    /// them for later calls with the same arguments.
+    pub memoize_calls: bool,
+
+    /// Tracing modes for specific modules. Later overrides take precedence
+    /// over earlier ones.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<TracingOverride>,
 }
 impl TracingConfig {
     #[must_use]
@@ -15,16 +26,71 @@ impl TracingConfig {
             register_fuzzables: TracingMode::Off,
             calls: CallTracingMode::Off,
             evaluated_expressions: TracingMode::Off,
+            memoize_calls: false,
+            overrides: vec![],
         }
     }
 
     #[must_use]
-    pub const fn for_child_module(&self) -> Self {
+    pub fn for_child_module(&self) -> Self {
         Self {
             register_fuzzables: self.register_fuzzables.for_child_module(),
             calls: self.calls.for_child_module(),
             evaluated_expressions: self.evaluated_expressions.for_child_module(),
+            memoize_calls: self.memoize_calls,
+            overrides: self.overrides.clone(),
+        }
+    }
+
+    /// The config to compile `module` with, i.e., with the overrides matching
+    /// `module` applied.
+    #[must_use]
+    pub fn for_module(&self, module: &Module) -> Self {
+        let mut config = self.clone();
+        for tracing_override in self.overrides.iter().filter(|it| it.matches(module)) {
+            if let Some(calls) = tracing_override.calls {
+                config.calls = calls;
+            }
+            if let Some(evaluated_expressions) = tracing_override.evaluated_expressions {
+                config.evaluated_expressions = evaluated_expressions;
+            }
         }
+        config
+    }
+}
+
+/// Overrides the tracing modes of the modules matching a pattern.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TracingOverride {
+    /// A module path like `foo/bar`, which matches the module `foo/bar` and
+    /// its submodules (e.g., `foo/bar/baz`) in any package.
+    ///
+    /// To only match modules of a specific package, prefix the path with the
+    /// package's name, e.g., `Examples:foo/bar`.
+    pub modules: String,
+    pub calls: Option<CallTracingMode>,
+    pub evaluated_expressions: Option<TracingMode>,
+}
+impl TracingOverride {
+    #[must_use]
+    pub fn matches(&self, module: &Module) -> bool {
+        let path = match self.modules.split_once(':') {
+            Some((package, path)) => {
+                let (Package::User(package_path) | Package::Managed(package_path)) =
+                    &module.package
+                else {
+                    return false;
+                };
+                if package_path.file_name().and_then(|it| it.to_str()) != Some(package) {
+                    return false;
+                }
+                path
+            }
+            None => &self.modules,
+        };
+        let pattern = path.split('/').filter(|it| !it.is_empty()).collect_vec();
+        pattern.len() <= module.path.len() && pattern.iter().zip(&module.path).all(|(a, b)| a == b)
     }
 }
 
@@ -109,3 +175,66 @@ impl CallTracingMode {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module::ModuleKind;
+    use std::path::PathBuf;
+
+    fn module(package: &str, path: &[&str]) -> Module {
+        Module {
+            package: Package::User(PathBuf::from("/packages").join(package)),
+            path: path.iter().map(ToString::to_string).collect(),
+            kind: ModuleKind::Code,
+        }
+    }
+    fn override_for(modules: &str) -> TracingOverride {
+        TracingOverride {
+            modules: modules.to_string(),
+            calls: Some(CallTracingMode::OnlyCurrent),
+            evaluated_expressions: None,
+        }
+    }
+
+    #[test]
+    fn overrides_match_modules_and_submodules() {
+        let foo_bar = override_for("foo/bar");
+        assert!(foo_bar.matches(&module("Examples", &["foo", "bar"])));
+        assert!(foo_bar.matches(&module("Examples", &["foo", "bar", "baz"])));
+        assert!(!foo_bar.matches(&module("Examples", &["foo"])));
+        assert!(!foo_bar.matches(&module("Examples", &["foo", "barbaz"])));
+
+        let examples_foo = override_for("Examples:foo");
+        assert!(examples_foo.matches(&module("Examples", &["foo"])));
+        assert!(!examples_foo.matches(&module("Core", &["foo"])));
+    }
+
+    #[test]
+    fn later_overrides_take_precedence() {
+        let config = TracingConfig {
+            overrides: vec![
+                override_for("foo"),
+                TracingOverride {
+                    calls: Some(CallTracingMode::Off),
+                    ..override_for("foo/bar")
+                },
+            ],
+            ..TracingConfig::off()
+        };
+        assert_eq!(
+            config.for_module(&module("Examples", &["foo"])).calls,
+            CallTracingMode::OnlyCurrent,
+        );
+        assert_eq!(
+            config
+                .for_module(&module("Examples", &["foo", "bar"]))
+                .calls,
+            CallTracingMode::Off,
+        );
+        assert_eq!(
+            config.for_module(&module("Examples", &["baz"])).calls,
+            CallTracingMode::Off,
+        );
+    }
+}
@@ -3,7 +3,9 @@ use rustc_hash::FxHasher;
 use std::{
     collections::{HashMap, HashSet},
     hash::{BuildHasher, Hash, Hasher},
+    ops::Range,
 };
+use unicode_segmentation::UnicodeSegmentation;
 
 #[extension_trait]
 pub impl AdjustCasingOfFirstLetter for str {
@@ -22,6 +24,52 @@ pub impl AdjustCasingOfFirstLetter for str {
     }
 }
 
+/// Text operations that only match patterns at grapheme cluster boundaries,
+/// consistent with how Candy indexes texts.
+#[extension_trait]
+pub impl GraphemeAwareSearch for str {
+    /// Byte ranges of the non-overlapping occurrences of `pattern`, from left
+    /// to right. An empty pattern matches at every grapheme cluster boundary.
+    fn grapheme_matches(&self, pattern: &str) -> Vec<Range<usize>> {
+        let boundaries = self
+            .grapheme_indices(true)
+            .map(|(index, _)| index)
+            .chain([self.len()])
+            .collect::<Vec<_>>();
+
+        let mut matches: Vec<Range<usize>> = vec![];
+        for &start in &boundaries {
+            if matches.last().is_some_and(|last| start < last.end) {
+                continue;
+            }
+            let end = start + pattern.len();
+            if self[start..].starts_with(pattern) && boundaries.binary_search(&end).is_ok() {
+                matches.push(start..end);
+            }
+        }
+        matches
+    }
+
+    /// The grapheme cluster index of the first occurrence of `pattern`.
+    fn grapheme_index_of(&self, pattern: &str) -> Option<usize> {
+        let start = self.grapheme_matches(pattern).first()?.start;
+        Some(self[..start].graphemes(true).count())
+    }
+    fn grapheme_split(&self, separator: &str) -> Vec<&str> {
+        let mut parts = vec![];
+        let mut part_start = 0;
+        for separator_range in self.grapheme_matches(separator) {
+            parts.push(&self[part_start..separator_range.start]);
+            part_start = separator_range.end;
+        }
+        parts.push(&self[part_start..]);
+        parts
+    }
+    fn grapheme_replace(&self, pattern: &str, replacement: &str) -> String {
+        self.grapheme_split(pattern).join(replacement)
+    }
+}
+
 #[extension_trait]
 pub impl<T: Hash> DoHash for T {
     fn do_hash(&self) -> u64 {
@@ -64,3 +112,22 @@ where
         assert!(self.insert(k, v).is_none());
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::GraphemeAwareSearch;
+
+    #[test]
+    fn grapheme_aware_search() {
+        assert_eq!("a,b,,c".grapheme_split(","), ["a", "b", "", "c"]);
+        assert_eq!("ab".grapheme_split(""), ["", "a", "b", ""]);
+        assert_eq!("🍔🍟".grapheme_index_of("🍟"), Some(1));
+        assert_eq!("abc".grapheme_index_of("d"), None);
+        assert_eq!("aaa".grapheme_replace("aa", "b"), "ba");
+
+        // The "e" is part of the grapheme cluster "é", so it doesn't match.
+        let text = "e\u{301}e";
+        assert_eq!(text.grapheme_index_of("e"), Some(1));
+        assert_eq!(text.grapheme_replace("e", "x"), "e\u{301}x");
+    }
+}
@@ -25,6 +25,8 @@ pub struct Fuzzer {
     pub function_id: Id,
     pool: InputPool,
     status: Option<Status>, // only `None` during transitions
+    /// The number of instructions run while fuzzing, across all inputs.
+    total_instructions: usize,
 }
 
 // TODO: Decrease enum variant sizes and size differences
@@ -35,7 +37,20 @@ pub enum Status {
         input: Input,
         runner: Runner<Rc<ByteCode>>,
     },
-    // TODO: In the future, also add a state for trying to simplify the input.
+    /// We found an input that panics and now try to find a simpler one that
+    /// still causes the same panic.
+    Shrinking {
+        /// The simplest panicking input found so far.
+        input: Input,
+        panic: Panic,
+        heap: Heap,
+        tracer: StackTracer,
+        /// Simpler variants of `input` that we didn't try yet. They live in
+        /// `heap` and are tried from the end.
+        candidates: Vec<Input>,
+        /// Runs the candidate that we're currently trying.
+        runner: Runner<Rc<ByteCode>>,
+    },
     FoundPanic {
         input: Input,
         panic: Panic,
@@ -76,6 +91,10 @@ impl Fuzzer {
             collect_symbols_in_heap(&persistent_heap)
                 .into_iter()
                 .collect_vec(),
+            byte_code
+                .fuzzing_callbacks
+                .clone()
+                .expect("Fuzzing requires byte code compiled with fuzzables being registered."),
         );
 
         let input = pool.generate_new_input(&mut persistent_heap);
@@ -95,6 +114,7 @@ impl Fuzzer {
                 input,
                 runner,
             }),
+            total_instructions: 0,
         }
     }
 
@@ -124,7 +144,14 @@ impl Fuzzer {
                     runner,
                 }
             }
-            Status::FoundPanic {
+            Status::Shrinking {
+                input,
+                panic,
+                heap,
+                tracer,
+                ..
+            }
+            | Status::FoundPanic {
                 heap,
                 input,
                 panic,
@@ -142,23 +169,44 @@ impl Fuzzer {
     pub const fn input_pool(&self) -> &InputPool {
         &self.pool
     }
+    #[must_use]
+    pub const fn total_instructions(&self) -> usize {
+        self.total_instructions
+    }
 
     pub fn run(&mut self, max_instructions: usize) {
         let mut status = self.status.take().unwrap();
         let mut instructions_left = max_instructions;
 
-        while matches!(status, Status::StillFuzzing { .. }) && instructions_left > 0 {
+        while !matches!(status, Status::FoundPanic { .. }) && instructions_left > 0 {
             status = match status {
                 Status::StillFuzzing {
                     total_coverage,
                     input,
                     runner,
                 } => self.continue_fuzzing(&mut instructions_left, total_coverage, input, runner),
+                Status::Shrinking {
+                    input,
+                    panic,
+                    heap,
+                    tracer,
+                    candidates,
+                    runner,
+                } => self.continue_shrinking(
+                    &mut instructions_left,
+                    input,
+                    panic,
+                    heap,
+                    tracer,
+                    candidates,
+                    runner,
+                ),
                 // We already found some arguments that caused the function to panic,
                 // so there's nothing more to do.
                 status @ Status::FoundPanic { .. } => status,
             };
         }
+        self.total_instructions += max_instructions - instructions_left;
         self.status = Some(status);
     }
 
@@ -209,12 +257,7 @@ impl Fuzzer {
                 heap,
                 tracer,
                 panic,
-            } => Status::FoundPanic {
-                heap,
-                input: runner.input,
-                panic,
-                tracer,
-            },
+            } => self.start_shrinking(runner.input, panic, heap, tracer),
         }
     }
     fn create_new_fuzzing_case(&mut self, total_coverage: Coverage) -> Status {
@@ -226,4 +269,91 @@ impl Fuzzer {
             runner,
         }
     }
+
+    /// Starts trying simpler variants of a panicking `input` that lives in
+    /// `heap`.
+    fn start_shrinking(
+        &self,
+        input: Input,
+        panic: Panic,
+        mut heap: Heap,
+        tracer: StackTracer,
+    ) -> Status {
+        let mut candidates = input.shrunk(&mut heap);
+        // Candidates are popped from the end, but the first ones are the
+        // simplest.
+        candidates.reverse();
+        self.try_next_candidate(input, panic, heap, tracer, candidates)
+    }
+    fn try_next_candidate(
+        &self,
+        input: Input,
+        panic: Panic,
+        heap: Heap,
+        tracer: StackTracer,
+        mut candidates: Vec<Input>,
+    ) -> Status {
+        let Some(candidate) = candidates.pop() else {
+            return Status::FoundPanic {
+                input,
+                panic,
+                heap,
+                tracer,
+            };
+        };
+
+        let runner = Runner::new(self.byte_code.clone(), self.function, &candidate);
+        candidates.push(candidate);
+        Status::Shrinking {
+            input,
+            panic,
+            heap,
+            tracer,
+            candidates,
+            runner,
+        }
+    }
+    #[allow(clippy::too_many_arguments)]
+    fn continue_shrinking(
+        &self,
+        instructions_left: &mut usize,
+        input: Input,
+        panic: Panic,
+        mut heap: Heap,
+        tracer: StackTracer,
+        mut candidates: Vec<Input>,
+        mut runner: Runner<Rc<ByteCode>>,
+    ) -> Status {
+        runner.run(instructions_left);
+        let Some(result) = runner.take_result() else {
+            return Status::Shrinking {
+                input,
+                panic,
+                heap,
+                tracer,
+                candidates,
+                runner,
+            };
+        };
+
+        let candidate = candidates.pop().unwrap();
+        let call_string = format!("`{} {}`", self.function_id.function_name(), candidate);
+        debug!("Shrinking: {}", result.to_string(&call_string));
+        match result {
+            // The simpler input causes the same panic, so we continue with it.
+            // The old input and remaining candidates are freed together with
+            // their heap.
+            RunResult::Panicked {
+                heap: new_heap,
+                tracer: new_tracer,
+                panic: new_panic,
+            } if new_panic.responsible == panic.responsible => {
+                self.start_shrinking(runner.input, new_panic, new_heap, new_tracer)
+            }
+            _ => {
+                candidate.drop(&mut heap);
+                self.try_next_candidate(input, panic, heap, tracer, candidates)
+            }
+        }
+    }
 }
@@ -1,3 +1,4 @@
+use crate::values::describe_callback;
 use candy_frontend::format::{MaxLength, Precedence};
 use candy_vm::heap::{Heap, HeapObject, InlineObject, ToDebugText};
 use itertools::Itertools;
@@ -47,15 +48,24 @@ impl Input {
     }
 }
 
+impl Input {
+    /// Formats the arguments, each shortened to `max_length`.
+    #[must_use]
+    pub fn to_debug_text(&self, max_length: MaxLength) -> String {
+        self.arguments
+            .iter()
+            .map(|argument| {
+                argument.to_debug_text_describing_functions(
+                    Precedence::High,
+                    max_length,
+                    &describe_callback,
+                )
+            })
+            .join(" ")
+    }
+}
 impl Display for Input {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        write!(
-            f,
-            "{}",
-            self.arguments
-                .iter()
-                .map(|argument| argument.to_debug_text(Precedence::High, MaxLength::Limited(40)))
-                .join(" "),
-        )
+        write!(f, "{}", self.to_debug_text(MaxLength::Limited(40)))
     }
 }
@@ -1,6 +1,9 @@
 use super::input::Input;
-use crate::runner::RunResult;
-use candy_vm::heap::{Heap, Text};
+use crate::{parameter_constraints::ParameterConstraints, runner::RunResult};
+use candy_vm::{
+    fuzzing_callbacks::FuzzingCallbacks,
+    heap::{Data, DataDiscriminants, Heap, Text},
+};
 use itertools::Itertools;
 use rand::{rngs::ThreadRng, seq::SliceRandom, Rng};
 use rustc_hash::FxHashMap;
@@ -8,17 +11,21 @@ use rustc_hash::FxHashMap;
 pub type Score = f64;
 
 pub struct InputPool {
-    num_args: usize,
+    constraints: Vec<ParameterConstraints>,
     symbols: Vec<Text>,
+    callbacks: FuzzingCallbacks,
     results_and_scores: FxHashMap<Input, (RunResult, Score)>,
 }
 
 impl InputPool {
     #[must_use]
-    pub fn new(num_args: usize, symbols: Vec<Text>) -> Self {
+    pub fn new(num_args: usize, symbols: Vec<Text>, callbacks: FuzzingCallbacks) -> Self {
         Self {
-            num_args,
+            constraints: (0..num_args)
+                .map(|_| ParameterConstraints::default())
+                .collect(),
             symbols,
+            callbacks,
             results_and_scores: FxHashMap::default(),
         }
     }
@@ -40,7 +47,7 @@ impl InputPool {
         let mut rng = ThreadRng::default();
 
         if rng.gen_bool(0.1) || self.results_and_scores.len() < 20 {
-            return Input::generate(heap, self.num_args, &self.symbols);
+            return Input::generate(heap, &self.constraints, &self.symbols, &self.callbacks);
         }
 
         let inputs_and_scores = self
@@ -51,12 +58,48 @@ impl InputPool {
         let (input, _) = inputs_and_scores
             .choose_weighted(&mut rng, |(_, score)| *score)
             .unwrap();
-        input.mutated(heap, &mut rng, &self.symbols)
+        input.mutated(
+            heap,
+            &mut rng,
+            &self.constraints,
+            &self.symbols,
+            &self.callbacks,
+        )
     }
 
     pub fn add(&mut self, input: Input, result: RunResult, score: Score) {
+        self.learn_constraints(&input, &result);
         self.results_and_scores.insert(input, (result, score));
     }
+    fn learn_constraints(&mut self, input: &Input, result: &RunResult) {
+        let kinds = input
+            .arguments()
+            .iter()
+            .map(|argument| DataDiscriminants::from(Data::from(*argument)));
+        match result {
+            RunResult::Timeout => {}
+            RunResult::Done { .. } | RunResult::Panicked { .. } => {
+                for (constraints, kind) in self.constraints.iter_mut().zip(kinds) {
+                    constraints.accept(kind);
+                }
+            }
+            RunResult::NeedsUnfulfilled { .. } => {
+                // We can only blame a kind if it's the only one that isn't
+                // known to be accepted. Otherwise, the `needs` could have
+                // rejected any of them (or the values themselves).
+                let mut unknown = self
+                    .constraints
+                    .iter_mut()
+                    .zip(kinds)
+                    .filter(|(constraints, kind)| !constraints.is_known_to_accept(*kind));
+                if let Some((constraints, kind)) = unknown.next()
+                    && unknown.next().is_none()
+                {
+                    constraints.reject(kind);
+                }
+            }
+        }
+    }
 
     #[must_use]
     pub fn interesting_inputs(&self) -> Vec<Input> {
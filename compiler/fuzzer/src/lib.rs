@@ -6,13 +6,14 @@ mod coverage;
 mod fuzzer;
 mod input;
 mod input_pool;
+mod parameter_constraints;
 mod runner;
 mod utils;
 mod values;
 
-use self::input::Input;
 pub use self::{
     fuzzer::{Fuzzer, Status},
+    input::Input,
     input_pool::InputPool,
     runner::RunResult,
     utils::FuzzablesFinder,
@@ -32,10 +33,69 @@ use candy_vm::{
     heap::Heap, lir_to_byte_code::compile_byte_code, tracer::stack_trace::StackTracer, Panic, Vm,
     VmFinished,
 };
-use std::rc::Rc;
-use tracing::{debug, error, info};
+use itertools::Itertools;
+use std::{
+    rc::Rc,
+    time::{Duration, Instant},
+};
+use tracing::{debug, error, info, warn};
+
+pub const DEFAULT_INSTRUCTIONS_PER_FUNCTION: usize = 100_000;
+
+/// How often (in instructions) we check whether the time limit is exceeded.
+const INSTRUCTIONS_PER_TIME_CHECK: usize = 10_000;
+
+#[derive(Clone, Debug)]
+pub struct FuzzingOptions {
+    /// Only fuzz the function with this HIR path. The path can either be a
+    /// full HIR ID (e.g., `Examples:fibonacci:fibRec`) or the part after the
+    /// module (e.g., `fibonacci:fibRec`).
+    pub function: Option<String>,
+    /// How many instructions to run per function before we consider it fine.
+    /// Shrinking a found panicking input doesn't count towards this budget.
+    pub instructions_per_function: usize,
+    /// Functions that weren't fuzzed when this time ran out are reported as
+    /// skipped.
+    pub time_limit: Option<Duration>,
+}
+impl Default for FuzzingOptions {
+    fn default() -> Self {
+        Self {
+            function: None,
+            instructions_per_function: DEFAULT_INSTRUCTIONS_PER_FUNCTION,
+            time_limit: None,
+        }
+    }
+}
+
+pub struct FuzzingReport {
+    pub fuzzed_functions: Vec<FuzzedFunction>,
+    /// Functions that weren't fuzzed because the time limit was exceeded.
+    pub skipped_functions: Vec<Id>,
+}
+impl FuzzingReport {
+    pub fn failing_cases(&self) -> impl Iterator<Item = &FailingFuzzCase> {
+        self.fuzzed_functions
+            .iter()
+            .filter_map(|it| it.failing_case.as_ref())
+    }
+}
+
+pub struct FuzzedFunction {
+    pub function: Id,
+    pub instructions: usize,
+    /// The share of the function's instructions that were executed. This is
+    /// only available if no panic was found.
+    pub coverage: Option<f64>,
+    pub failing_case: Option<FailingFuzzCase>,
+}
+
+#[must_use]
+pub fn matches_function_path(id: &Id, path: &str) -> bool {
+    id.to_string() == path || id.keys.iter().join(":") == path
+}
 
-pub fn fuzz<DB>(db: &DB, module: Module) -> Vec<FailingFuzzCase>
+pub fn fuzz<DB>(db: &DB, module: Module, options: &FuzzingOptions) -> FuzzingReport
 where
     DB: AstToHir + CstDb + OptimizeLir + PositionConversionDb,
 {
@@ -43,6 +103,8 @@ where
         register_fuzzables: TracingMode::OnlyCurrent,
         calls: CallTracingMode::Off,
         evaluated_expressions: TracingMode::Off,
+        memoize_calls: false,
+        overrides: vec![],
     };
     let (byte_code, _) = compile_byte_code(db, ExecutionTarget::Module(module), tracing);
     let byte_code = Rc::new(byte_code);
@@ -54,24 +116,63 @@ where
     } = Vm::for_module(byte_code.clone(), &mut heap, FuzzablesFinder::default())
         .run_forever_without_handles(&mut heap);
 
+    let mut fuzzables = fuzzables.into_iter().collect_vec();
+    fuzzables.sort_by(|(a, _), (b, _)| a.cmp(b));
+    if let Some(path) = &options.function {
+        fuzzables.retain(|(id, _)| matches_function_path(id, path));
+    }
+
     info!(
-        "Now, the fuzzing begins. We have {} functions to fuzz: {fuzzables:?}.",
+        "Now, the fuzzing begins. We have {} functions to fuzz: {:?}.",
         fuzzables.len(),
+        fuzzables.iter().map(|(id, _)| id).collect_vec(),
     );
 
-    let mut failing_cases = vec![];
+    let deadline = options.time_limit.map(|limit| Instant::now() + limit);
+    let is_out_of_time = || deadline.is_some_and(|deadline| Instant::now() >= deadline);
+    let mut report = FuzzingReport {
+        fuzzed_functions: vec![],
+        skipped_functions: vec![],
+    };
 
     for (id, function) in fuzzables {
+        if is_out_of_time() {
+            warn!("Skipping {id} because the time limit is exceeded.");
+            report.skipped_functions.push(id);
+            continue;
+        }
+
         info!("Fuzzing {id}.");
         let mut fuzzer = Fuzzer::new(byte_code.clone(), function, id.clone());
-        fuzzer.run(100_000);
+        loop {
+            let instructions = match fuzzer.status() {
+                Status::StillFuzzing { .. } => options
+                    .instructions_per_function
+                    .saturating_sub(fuzzer.total_instructions()),
+                // Once we found a panic, we shrink the input as far as possible
+                // before reporting it, regardless of the fuzzing budget.
+                Status::Shrinking { .. } => INSTRUCTIONS_PER_TIME_CHECK,
+                Status::FoundPanic { .. } => 0,
+            };
+            if instructions == 0 || is_out_of_time() {
+                break;
+            }
+            fuzzer.run(instructions.min(INSTRUCTIONS_PER_TIME_CHECK));
+        }
 
+        let instructions = fuzzer.total_instructions();
         match fuzzer.into_result() {
             FuzzerResult::StillFuzzing { total_coverage, .. } => {
                 let coverage = total_coverage
                     .in_range(&byte_code.range_of_function(&id))
                     .relative_coverage();
                 debug!("Achieved a coverage of {:.1} %.", coverage * 100.0);
+                report.fuzzed_functions.push(FuzzedFunction {
+                    function: id,
+                    instructions,
+                    coverage: Some(coverage),
+                    failing_case: None,
+                });
             }
             FuzzerResult::FoundPanic {
                 input,
@@ -81,19 +182,24 @@ where
             } => {
                 error!("The fuzzer discovered an input that crashes {id}:");
                 let case = FailingFuzzCase {
-                    function: id,
+                    function: id.clone(),
                     input,
                     panic,
                     heap,
                     tracer,
                 };
                 case.dump(db);
-                failing_cases.push(case);
+                report.fuzzed_functions.push(FuzzedFunction {
+                    function: id,
+                    instructions,
+                    coverage: None,
+                    failing_case: Some(case),
+                });
             }
         }
     }
 
-    failing_cases
+    report
 }
 
 pub struct FailingFuzzCase {
@@ -107,6 +213,19 @@ pub struct FailingFuzzCase {
 }
 
 impl FailingFuzzCase {
+    #[must_use]
+    pub const fn function(&self) -> &Id {
+        &self.function
+    }
+    #[must_use]
+    pub const fn input(&self) -> &Input {
+        &self.input
+    }
+    #[must_use]
+    pub const fn panic(&self) -> &Panic {
+        &self.panic
+    }
+
     #[allow(unused_variables)]
     pub fn dump<DB>(&self, db: &DB)
     where
@@ -0,0 +1,66 @@
+use candy_vm::heap::DataDiscriminants;
+use rand::{rngs::ThreadRng, seq::IteratorRandom, Rng};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+/// Kinds of values that we generate for arguments.
+pub const GENERATED_KINDS: [DataDiscriminants; 8] = [
+    DataDiscriminants::Int,
+    DataDiscriminants::Text,
+    DataDiscriminants::Tag,
+    DataDiscriminants::List,
+    DataDiscriminants::Struct,
+    DataDiscriminants::Float,
+    DataDiscriminants::Bytes,
+    DataDiscriminants::Function,
+];
+
+/// After the `needs` of a function rejected a kind of value for a parameter
+/// this many times (and never accepted it), we stop generating that kind.
+const REJECTIONS_UNTIL_AVOIDED: usize = 3;
+
+/// What we learned about the kinds of values a parameter accepts, based on
+/// whether the function's `needs` were fulfilled for previous inputs.
+///
+/// Most functions check the kinds of their arguments first (e.g., using
+/// `int.is`), so this lets us focus on inputs that get past these checks.
+#[derive(Debug, Default)]
+pub struct ParameterConstraints {
+    accepted: FxHashSet<DataDiscriminants>,
+    rejections: FxHashMap<DataDiscriminants, usize>,
+}
+impl ParameterConstraints {
+    /// Usually chooses one of the accepted kinds, but still explores other
+    /// kinds that weren't rejected repeatedly.
+    pub fn kind_to_generate(&self, rng: &mut ThreadRng) -> DataDiscriminants {
+        if !self.accepted.is_empty() && rng.gen_bool(0.9) {
+            return *self.accepted.iter().choose(rng).unwrap();
+        }
+
+        GENERATED_KINDS
+            .into_iter()
+            .filter(|kind| self.may_accept(*kind))
+            .choose(rng)
+            .unwrap_or_else(|| *GENERATED_KINDS.iter().choose(rng).unwrap())
+    }
+    /// Whether the kind wasn't rejected repeatedly.
+    #[must_use]
+    pub fn may_accept(&self, kind: DataDiscriminants) -> bool {
+        self.accepted.contains(&kind)
+            || self.rejections.get(&kind).copied().unwrap_or_default() < REJECTIONS_UNTIL_AVOIDED
+    }
+
+    /// Records that the function's `needs` were fulfilled for a value of this
+    /// kind.
+    pub fn accept(&mut self, kind: DataDiscriminants) {
+        self.accepted.insert(kind);
+    }
+    #[must_use]
+    pub fn is_known_to_accept(&self, kind: DataDiscriminants) -> bool {
+        self.accepted.contains(&kind)
+    }
+    /// Records that the function's `needs` were not fulfilled, most likely
+    /// because of a value of this kind.
+    pub fn reject(&mut self, kind: DataDiscriminants) {
+        *self.rejections.entry(kind).or_default() += 1;
+    }
+}
@@ -1,38 +1,106 @@
 use super::input::Input;
-use candy_frontend::builtin_functions::BuiltinFunction;
-use candy_vm::heap::{Data, Heap, I64BitLength, InlineObject, Int, List, Struct, Tag, Text};
+use crate::parameter_constraints::{ParameterConstraints, GENERATED_KINDS};
+use candy_frontend::{
+    builtin_functions::BuiltinFunction,
+    format::{MaxLength, Precedence},
+    hir::Id,
+};
+use candy_vm::{
+    fuzzing_callbacks::{FuzzingCallbackKind, FuzzingCallbacks},
+    heap::{
+        Bytes, Data, DataDiscriminants, Float, Function, Heap, HirId, I64BitLength, InlineObject,
+        Int, List, Struct, Tag, Text, ToDebugText,
+    },
+};
 use extension_trait::extension_trait;
 use itertools::Itertools;
-use num_bigint::RandBigInt;
+use num_bigint::{BigInt, RandBigInt, Sign};
 use rand::{
     prelude::ThreadRng,
     seq::{IteratorRandom, SliceRandom},
     Rng,
 };
 use rustc_hash::FxHashMap;
-use std::collections::hash_map;
+use std::{collections::hash_map, hash::Hash};
 use strum::VariantArray;
 
 impl Input {
-    pub fn generate(heap: &mut Heap, num_args: usize, symbols: &[Text]) -> Self {
-        let arguments = (0..num_args)
-            .map(|_| InlineObject::generate(heap, &mut rand::thread_rng(), 5.0, symbols))
+    /// Generates an argument for each parameter, preferring kinds of values
+    /// that the parameter accepted before.
+    pub fn generate(
+        heap: &mut Heap,
+        constraints: &[ParameterConstraints],
+        symbols: &[Text],
+        callbacks: &FuzzingCallbacks,
+    ) -> Self {
+        let rng = &mut rand::thread_rng();
+        let arguments = constraints
+            .iter()
+            .map(|constraints| {
+                let kind = constraints.kind_to_generate(rng);
+                InlineObject::generate_of_kind(heap, rng, kind, 5.0, symbols, callbacks)
+            })
             .collect();
         Self::new(arguments)
     }
-    pub fn mutated(&self, heap: &mut Heap, rng: &mut ThreadRng, symbols: &[Text]) -> Self {
+    #[must_use]
+    pub fn mutated(
+        &self,
+        heap: &mut Heap,
+        rng: &mut ThreadRng,
+        constraints: &[ParameterConstraints],
+        symbols: &[Text],
+        callbacks: &FuzzingCallbacks,
+    ) -> Self {
         let mut arguments = self.arguments().to_owned();
 
         let index_to_mutate = rng.gen_range(0..arguments.len());
         for (index, argument) in arguments.iter_mut().enumerate() {
             if index == index_to_mutate {
-                *argument = argument.generate_mutated(heap, rng, symbols);
+                let mut mutated = argument.generate_mutated(heap, rng, symbols, callbacks);
+                let constraints = &constraints[index];
+                if !constraints.may_accept(Data::from(mutated).into()) {
+                    mutated.drop(heap);
+                    let kind = constraints.kind_to_generate(rng);
+                    mutated =
+                        InlineObject::generate_of_kind(heap, rng, kind, 5.0, symbols, callbacks);
+                }
+                *argument = mutated;
             } else {
                 argument.dup(heap);
             }
         }
         Self::new(arguments)
     }
+    /// Inputs that are simpler than this one, starting with the ones that
+    /// simplify it the most.
+    ///
+    /// Each returned input has a lower [complexity](Self::complexity), so
+    /// repeatedly shrinking an input eventually ends.
+    pub fn shrunk(&self, heap: &mut Heap) -> Vec<Self> {
+        let complexity = self.complexity();
+        let mut candidates = vec![];
+        for (index_to_shrink, argument) in self.arguments().iter().enumerate() {
+            for shrunk_argument in argument.shrunk(heap) {
+                let mut arguments = self.arguments().to_owned();
+                for (index, argument) in arguments.iter_mut().enumerate() {
+                    if index == index_to_shrink {
+                        *argument = shrunk_argument;
+                    } else {
+                        argument.dup(heap);
+                    }
+                }
+                let candidate = Self::new(arguments);
+                if candidate.complexity() < complexity {
+                    candidates.push(candidate);
+                } else {
+                    candidate.drop(heap);
+                }
+            }
+        }
+        candidates
+    }
+    #[must_use]
     pub fn complexity(&self) -> usize {
         self.arguments()
             .iter()
@@ -46,15 +114,29 @@ impl InlineObjectGeneration for InlineObject {
     fn generate(
         heap: &mut Heap,
         rng: &mut ThreadRng,
+        complexity: f32,
+        symbols: &[Text],
+        callbacks: &FuzzingCallbacks,
+    ) -> InlineObject {
+        let kind = *GENERATED_KINDS.choose(rng).unwrap();
+        Self::generate_of_kind(heap, rng, kind, complexity, symbols, callbacks)
+    }
+    fn generate_of_kind(
+        heap: &mut Heap,
+        rng: &mut ThreadRng,
+        kind: DataDiscriminants,
         mut complexity: f32,
         symbols: &[Text],
+        callbacks: &FuzzingCallbacks,
     ) -> InlineObject {
-        match rng.gen_range(1..=5) {
-            1 => Int::create_from_bigint(heap, true, rng.gen_bigint(10)).into(),
-            2 => Text::create(heap, true, "test").into(),
-            3 => {
+        match kind {
+            DataDiscriminants::Int => {
+                Int::create_from_bigint(heap, true, rng.gen_bigint(10)).into()
+            }
+            DataDiscriminants::Text => Text::create(heap, true, "test").into(),
+            DataDiscriminants::Tag => {
                 if rng.gen_bool(0.2) {
-                    let value = Self::generate(heap, rng, complexity - 10.0, symbols);
+                    let value = Self::generate(heap, rng, complexity - 10.0, symbols, callbacks);
                     Tag::create_with_value(heap, true, *symbols.choose(rng).unwrap(), value).into()
                 } else {
                     let symbol = *symbols.choose(rng).unwrap();
@@ -62,40 +144,52 @@ impl InlineObjectGeneration for InlineObject {
                     Tag::create(symbol).into()
                 }
             }
-            4 => {
+            DataDiscriminants::List => {
                 complexity -= 1.0;
                 let mut items = vec![];
                 while complexity > 10.0 {
-                    let item = Self::generate(heap, rng, 10.0, symbols);
+                    let item = Self::generate(heap, rng, 10.0, symbols, callbacks);
                     items.push(item);
                     complexity -= 10.0;
                 }
                 List::create(heap, true, &items).into()
             }
-            5 => {
+            DataDiscriminants::Struct => {
                 complexity -= 1.0;
                 let mut fields = FxHashMap::default();
                 while complexity > 20.0 {
                     // Generate a key that is not already in the struct
                     let entry = loop {
-                        let key = Self::generate(heap, rng, 10.0, symbols);
+                        let key = Self::generate(heap, rng, 10.0, symbols, callbacks);
                         match fields.entry(key) {
                             hash_map::Entry::Occupied(_) => key.drop(heap),
                             hash_map::Entry::Vacant(entry) => break entry,
                         }
                     };
 
-                    let value = Self::generate(heap, rng, 10.0, symbols);
+                    let value = Self::generate(heap, rng, 10.0, symbols, callbacks);
                     entry.insert(value);
                     complexity -= 20.0;
                 }
                 Struct::create(heap, true, &fields).into()
             }
-            6 => {
+            DataDiscriminants::Float => {
+                Float::create(heap, true, rng.gen_range(-1000.0..1000.0)).into()
+            }
+            DataDiscriminants::Bytes => {
+                let bytes: Vec<u8> = (0..rng.gen_range(0..10)).map(|_| rng.gen()).collect();
+                Bytes::create(heap, true, &bytes).into()
+            }
+            DataDiscriminants::Function => {
+                generate_callback(heap, rng, complexity, symbols, callbacks)
+            }
+            DataDiscriminants::Builtin => {
                 // No `dup()` necessary since these are inline.
                 (*BuiltinFunction::VARIANTS.choose(rng).unwrap()).into()
             }
-            _ => unreachable!(),
+            DataDiscriminants::HirId | DataDiscriminants::Handle => {
+                panic!("Can't be created for fuzzing.")
+            }
         }
     }
     #[allow(clippy::too_many_lines)]
@@ -104,9 +198,10 @@ impl InlineObjectGeneration for InlineObject {
         heap: &mut Heap,
         rng: &mut ThreadRng,
         symbols: &[Text],
+        callbacks: &FuzzingCallbacks,
     ) -> InlineObject {
         if rng.gen_bool(0.1) {
-            return Self::generate(heap, rng, 100.0, symbols);
+            return Self::generate(heap, rng, 100.0, symbols, callbacks);
         }
 
         match self.into() {
@@ -114,6 +209,9 @@ impl InlineObjectGeneration for InlineObject {
                 Int::create_from_bigint(heap, true, int.get().as_ref() + rng.gen_range(-10..10))
                     .into()
             }
+            Data::Float(float) => {
+                Float::create(heap, true, *float.get() + rng.gen_range(-10.0..10.0)).into()
+            }
             Data::Text(text) => {
                 let mut string = text.get().to_string();
                 mutate_string(rng, &mut string);
@@ -134,7 +232,7 @@ impl InlineObjectGeneration for InlineObject {
                     tag.symbol().dup();
                     if rng.gen_bool(0.9) {
                         // Keep symbol, mutate value
-                        let value = value.generate_mutated(heap, rng, symbols);
+                        let value = value.generate_mutated(heap, rng, symbols, callbacks);
                         Tag::create_with_value(heap, true, tag.symbol(), value).into()
                     } else {
                         // Keep symbol, remove value
@@ -143,10 +241,22 @@ impl InlineObjectGeneration for InlineObject {
                 } else {
                     // Keep symbol, add value
                     tag.symbol().dup();
-                    let value = Self::generate(heap, rng, 100.0, symbols);
+                    let value = Self::generate(heap, rng, 100.0, symbols, callbacks);
                     Tag::create_with_value(heap, true, tag.symbol(), value).into()
                 }
             }
+            Data::Bytes(bytes) => {
+                let mut bytes = bytes.get().to_vec();
+                if !bytes.is_empty() && rng.gen_bool(0.5) {
+                    // Replace byte
+                    let index = rng.gen_range(0..bytes.len());
+                    bytes[index] = rng.gen();
+                } else {
+                    // Add byte
+                    bytes.insert(rng.gen_range(0..=bytes.len()), rng.gen());
+                }
+                Bytes::create(heap, true, &bytes).into()
+            }
             Data::List(list) => {
                 let len = list.len();
                 if len > 0 && rng.gen_bool(0.9) {
@@ -154,7 +264,7 @@ impl InlineObjectGeneration for InlineObject {
                     let index_to_mutate = rng.gen_range(0..len);
                     let new_item = list
                         .get(index_to_mutate)
-                        .generate_mutated(heap, rng, symbols);
+                        .generate_mutated(heap, rng, symbols, callbacks);
                     for (index, item) in list.items().iter().enumerate() {
                         if index != index_to_mutate {
                             item.dup(heap);
@@ -173,7 +283,7 @@ impl InlineObjectGeneration for InlineObject {
                     for item in list.items() {
                         item.dup(heap);
                     }
-                    let new_item = Self::generate(heap, rng, 100.0, symbols);
+                    let new_item = Self::generate(heap, rng, 100.0, symbols, callbacks);
                     list.insert(heap, rng.gen_range(0..=len), new_item).into()
                 }
             }
@@ -181,63 +291,152 @@ impl InlineObjectGeneration for InlineObject {
                 let len = struct_.len();
                 if rng.gen_bool(0.9) && len > 0 {
                     // Mutate value
-                    let index_to_mutate = rng.gen_range(0..len);
-                    for key in struct_.keys() {
-                        key.dup(heap);
-                    }
-                    for (index, value) in struct_.values().iter().enumerate() {
-                        if index != index_to_mutate {
-                            value.dup(heap);
-                        }
-                    }
-                    let value =
-                        struct_.values()[index_to_mutate].generate_mutated(heap, rng, symbols);
-                    struct_
-                        .replace_at_index(heap, index_to_mutate, value)
-                        .into()
-                // TODO: Support removing value from a struct
-                // } else if rng.gen_bool(0.5) && len > 0 {
-                //     struct_
-                //         .remove(rng.gen_range(0..len));
+                    let (_, key, value) = struct_.iter().nth(rng.gen_range(0..len)).unwrap();
+                    let value = value.generate_mutated(heap, rng, symbols, callbacks);
+                    let new_struct = struct_.insert(heap, key, value);
+                    value.drop(heap);
+                    new_struct.into()
+                } else if rng.gen_bool(0.5) && len > 0 {
+                    // Remove entry
+                    let (_, key_to_remove, _) = struct_.iter().nth(rng.gen_range(0..len)).unwrap();
+                    struct_.remove(heap, key_to_remove).into()
                 } else {
                     // Add entry
-                    for key in struct_.keys() {
-                        key.dup(heap);
-                    }
-                    for value in struct_.values() {
-                        value.dup(heap);
-                    }
-
                     // Generate a key that is not already in the struct
                     let key = loop {
-                        let key = Self::generate(heap, rng, 10.0, symbols);
+                        let key = Self::generate(heap, rng, 10.0, symbols, callbacks);
                         if struct_.contains(key) {
                             key.drop(heap);
                         } else {
                             break key;
                         }
                     };
-                    let value = Self::generate(heap, rng, 100.0, symbols);
-                    struct_.insert(heap, key, value).into()
+                    let value = Self::generate(heap, rng, 100.0, symbols, callbacks);
+                    let new_struct = struct_.insert(heap, key, value);
+                    key.drop(heap);
+                    value.drop(heap);
+                    new_struct.into()
                 }
             }
             Data::Builtin(_) => {
                 // No `dup()` necessary since these are inline.
                 (*BuiltinFunction::VARIANTS.choose(rng).unwrap()).into()
             }
-            Data::HirId(_) | Data::Function(_) | Data::Handle(_) => {
+            Data::Function(_) => generate_callback(heap, rng, 100.0, symbols, callbacks),
+            Data::HirId(_) | Data::Handle(_) => {
                 panic!("Couldn't have been created for fuzzing.")
             }
         }
     }
 
+    /// Simpler variants of this value, e.g., lists with fewer items or ints
+    /// closer to zero.
+    #[allow(clippy::too_many_lines)]
+    fn shrunk(self, heap: &mut Heap) -> Vec<InlineObject> {
+        match self.into() {
+            Data::Int(int) => {
+                let value = int.get();
+                let value = value.as_ref();
+                let closer_to_zero = match value.sign() {
+                    Sign::Minus => value + 1,
+                    Sign::NoSign => return vec![],
+                    Sign::Plus => value - 1,
+                };
+                [BigInt::default(), value / 2, closer_to_zero]
+                    .into_iter()
+                    .unique()
+                    .map(|value| Int::create_from_bigint(heap, true, value).into())
+                    .collect()
+            }
+            Data::Text(text) => {
+                let characters = text.get().chars().collect_vec();
+                shrunk_sequences(&characters)
+                    .into_iter()
+                    .map(|characters| {
+                        let string = characters.into_iter().collect::<String>();
+                        Text::create(heap, true, &string).into()
+                    })
+                    .collect()
+            }
+            Data::Bytes(bytes) => shrunk_sequences(bytes.get())
+                .into_iter()
+                .map(|bytes| Bytes::create(heap, true, &bytes).into())
+                .collect(),
+            Data::Tag(tag) => {
+                let Some(value) = tag.value() else {
+                    return vec![];
+                };
+
+                tag.symbol().dup();
+                let mut candidates = vec![tag.without_value().into()];
+                for shrunk_value in value.shrunk(heap) {
+                    tag.symbol().dup();
+                    candidates.push(
+                        Tag::create_with_value(heap, true, tag.symbol(), shrunk_value).into(),
+                    );
+                }
+                candidates
+            }
+            Data::List(list) => {
+                if list.len() == 0 {
+                    return vec![];
+                }
+
+                let mut candidates = vec![List::create(heap, true, &[]).into()];
+                for index_to_remove in 0..list.len() {
+                    let new_list = list.remove(heap, index_to_remove);
+                    for item in new_list.items() {
+                        item.dup(heap);
+                    }
+                    candidates.push(new_list.into());
+                }
+                for (index_to_shrink, item) in list.items().iter().enumerate() {
+                    for shrunk_item in item.shrunk(heap) {
+                        for (index, item) in list.items().iter().enumerate() {
+                            if index != index_to_shrink {
+                                item.dup(heap);
+                            }
+                        }
+                        candidates.push(list.replace(heap, index_to_shrink, shrunk_item).into());
+                    }
+                }
+                candidates
+            }
+            Data::Struct(struct_) => {
+                if struct_.len() == 0 {
+                    return vec![];
+                }
+
+                let mut candidates = vec![Struct::create(heap, true, &FxHashMap::default()).into()];
+                for (_, key_to_remove, _) in struct_.iter() {
+                    candidates.push(struct_.remove(heap, key_to_remove).into());
+                }
+                for (_, key, value) in struct_.iter() {
+                    for shrunk_value in value.shrunk(heap) {
+                        candidates.push(struct_.insert(heap, key, shrunk_value).into());
+                        shrunk_value.drop(heap);
+                    }
+                }
+                candidates
+            }
+            // All floats have the same complexity.
+            Data::Float(_)
+            | Data::HirId(_)
+            | Data::Function(_)
+            | Data::Builtin(_)
+            | Data::Handle(_) => vec![],
+        }
+    }
+
     fn complexity(self) -> usize {
         match self.into() {
             Data::Int(int) => match int {
                 Int::Inline(int) => int.get().abs().bit_length() as usize,
                 Int::Heap(int) => int.get().bits().try_into().unwrap_or(usize::MAX),
             },
+            Data::Float(_) => 8,
             Data::Text(text) => text.byte_len() + 1,
+            Data::Bytes(bytes) => bytes.len() + 1,
             Data::Tag(tag) => {
                 1 + tag
                     .value()
@@ -263,6 +462,89 @@ impl InlineObjectGeneration for InlineObject {
     }
 }
 
+/// Creates a function that the fuzzed function can call. Its first captured
+/// value describes it for [`describe_callback`].
+fn generate_callback(
+    heap: &mut Heap,
+    rng: &mut ThreadRng,
+    complexity: f32,
+    symbols: &[Text],
+    callbacks: &FuzzingCallbacks,
+) -> InlineObject {
+    const PARAMETER_NAMES: [&str; FuzzingCallbacks::MAX_ARGUMENT_COUNT] = ["a", "b", "c"];
+
+    let kind = *FuzzingCallbackKind::VARIANTS.choose(rng).unwrap();
+    let argument_count = rng.gen_range(kind.argument_counts());
+    let parameters = &PARAMETER_NAMES[..argument_count];
+    let mut captured = vec![];
+    let (parameters, body) = match kind {
+        FuzzingCallbackKind::Constant => {
+            let value = InlineObject::generate(heap, rng, complexity - 10.0, symbols, callbacks);
+            captured.push(value);
+            let value = value.to_debug_text_describing_functions(
+                Precedence::Low,
+                MaxLength::Limited(20),
+                &describe_callback,
+            );
+            (vec!["_"; argument_count], value)
+        }
+        FuzzingCallbackKind::Identity => {
+            let mut parameters = vec!["_"; argument_count];
+            parameters[0] = PARAMETER_NAMES[0];
+            (parameters, PARAMETER_NAMES[0].to_string())
+        }
+        FuzzingCallbackKind::Panicking => {
+            captured.push(
+                Text::create(heap, true, "A callback created by the fuzzer panicked.").into(),
+            );
+            captured.push(HirId::create(heap, true, Id::fuzzer()).into());
+            (vec!["_"; argument_count], "panic".to_string())
+        }
+        FuzzingCallbackKind::RecordingArguments => {
+            let list = match parameters {
+                [] => "(,)".to_string(),
+                [parameter] => format!("({parameter},)"),
+                parameters => format!("({})", parameters.join(", ")),
+            };
+            (parameters.to_vec(), list)
+        }
+    };
+    let description = if parameters.is_empty() {
+        format!("{{ {body} }}")
+    } else {
+        format!("{{ {} -> {body} }}", parameters.join(" "))
+    };
+    captured.insert(0, Text::create(heap, true, &description).into());
+
+    let body = callbacks.body(kind, argument_count);
+    Function::create(heap, true, &captured, argument_count, body).into()
+}
+/// Functions in inputs are always created by [`generate_callback`].
+pub fn describe_callback<'a>(function: Function) -> Option<&'a str> {
+    Some(Text::try_from(function.captured()[0]).ok()?.get())
+}
+
+/// Shorter variants of a non-empty sequence: an empty one, both halves, and
+/// one without the last element.
+fn shrunk_sequences<T: Clone + Eq + Hash>(items: &[T]) -> Vec<Vec<T>> {
+    if items.is_empty() {
+        return vec![];
+    }
+
+    let middle = items.len() / 2;
+    [
+        &[][..],
+        &items[..middle],
+        &items[middle..],
+        &items[..items.len() - 1],
+    ]
+    .into_iter()
+    .filter(|it| it.len() < items.len())
+    .unique()
+    .map(<[T]>::to_vec)
+    .collect()
+}
+
 fn mutate_string(rng: &mut ThreadRng, string: &mut String) {
     if rng.gen_bool(0.5) && !string.is_empty() {
         let start = string.floor_char_boundary(rng.gen_range(0..string.len()));
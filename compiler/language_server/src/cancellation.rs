@@ -0,0 +1,50 @@
+use crate::database::Database;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use tokio::{sync::Mutex, task};
+
+/// Long-running features check this token regularly and return early once it
+/// is cancelled.
+///
+/// Salsa only cancels queries when there are pending writes to the database,
+/// which can't happen while a request holds the database's lock. Instead, the
+/// token is cancelled when the client sends a `$/cancelRequest`.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Runs `compute` with the locked database on a separate thread.
+///
+/// tower-lsp handles `$/cancelRequest` by dropping the future of the request,
+/// but synchronous code can't be interrupted that way. So when the returned
+/// future is dropped, we cancel the token passed to `compute`.
+pub async fn run_cancellable<T, F>(db: &Arc<Mutex<Database>>, compute: F) -> T
+where
+    T: Send + 'static,
+    F: FnOnce(&Database, &CancellationToken) -> T + Send + 'static,
+{
+    struct CancelOnDrop(CancellationToken);
+    impl Drop for CancelOnDrop {
+        fn drop(&mut self) {
+            self.0.cancel();
+        }
+    }
+
+    let db = db.clone().lock_owned().await;
+    let token = CancellationToken::default();
+    let _cancel_on_drop = CancelOnDrop(token.clone());
+    match task::spawn_blocking(move || compute(&db, &token)).await {
+        Ok(result) => result,
+        Err(error) => std::panic::resume_unwind(error.into_panic()),
+    }
+}
@@ -0,0 +1,90 @@
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::atomic::{AtomicU8, Ordering};
+use tracing::{level_filters::LevelFilter, warn};
+
+/// Settings of the language server.
+///
+/// Clients pass them in the `initializationOptions` and update them via
+/// `workspace/didChangeConfiguration`. The settings can be nested in a `candy`
+/// object, which is how VS Code sends them. Missing settings use their
+/// default.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ServerConfig {
+    pub hints: HintsConfig,
+    pub fuzzing: FuzzingConfig,
+    pub formatter: FormatterSettings,
+    pub log_level: LogLevel,
+}
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct HintsConfig {
+    pub enabled: bool,
+}
+impl Default for HintsConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct FuzzingConfig {
+    /// How many instructions to spend on fuzzing a single function. `None`
+    /// means that functions are fuzzed until a panic is found.
+    pub budget_per_function: Option<usize>,
+}
+/// Defaults for the formatter. Formatting requests can override them with
+/// custom properties.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct FormatterSettings {
+    pub max_line_width: Option<usize>,
+}
+
+/// Verbosity of the language server's own logs.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    #[default]
+    Trace,
+}
+
+impl ServerConfig {
+    #[must_use]
+    pub fn from_json(settings: &Value) -> Self {
+        let settings = settings.get("candy").unwrap_or(settings);
+        if settings.is_null() {
+            return Self::default();
+        }
+        serde_json::from_value(settings.clone()).unwrap_or_else(|error| {
+            warn!("Invalid language server settings: {error}");
+            Self::default()
+        })
+    }
+}
+
+static LOG_LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Trace as u8);
+impl LogLevel {
+    /// The currently configured log level, which the logger of the CLI uses to
+    /// filter the language server's logs.
+    #[must_use]
+    pub fn current() -> LevelFilter {
+        match LOG_LEVEL.load(Ordering::Relaxed) {
+            0 => LevelFilter::OFF,
+            1 => LevelFilter::ERROR,
+            2 => LevelFilter::WARN,
+            3 => LevelFilter::INFO,
+            4 => LevelFilter::DEBUG,
+            _ => LevelFilter::TRACE,
+        }
+    }
+    pub fn apply(self) {
+        LOG_LEVEL.store(self as u8, Ordering::Relaxed);
+    }
+}
@@ -1,3 +1,7 @@
+use crate::features_candy::references::{
+    ImportedDefinitionsQuery, ModulesOfPackageQuery, PersistedImports, PersistedImportsOwner,
+    ReferencesIndexStorage,
+};
 #[cfg(feature = "inkwell")]
 use candy_backend_inkwell::LlvmIrStorage;
 use candy_frontend::{
@@ -8,17 +12,18 @@ use candy_frontend::{
     hir::HirDbStorage,
     hir_to_mir::HirToMirStorage,
     lir_optimize::OptimizeLirStorage,
-    mir_optimize::OptimizeMirStorage,
+    mir_optimize::{MirPassesOwner, OptimizeMirStorage},
     mir_to_lir::MirToLirStorage,
     module::{
         FileSystemModuleProvider, GetModuleContentQuery, InMemoryModuleProvider, Module,
         ModuleDbStorage, ModuleProvider, ModuleProviderOwner, MutableModuleProviderOwner,
-        OverlayModuleProvider, PackagesPath,
+        OverlayModuleProvider, Package, PackagesPath,
     },
     position::PositionConversionStorage,
     rcst_to_cst::RcstToCstStorage,
-    string_to_rcst::StringToRcstStorage,
+    string_to_rcst::{RcstCache, RcstCacheOwner, StringToRcstStorage},
 };
+use rustc_hash::FxHashMap;
 
 #[cfg_attr(
     feature = "inkwell",
@@ -36,6 +41,7 @@ use candy_frontend::{
         OptimizeMirStorage,
         PositionConversionStorage,
         RcstToCstStorage,
+        ReferencesIndexStorage,
         StringToRcstStorage
     )
 )]
@@ -54,6 +60,7 @@ use candy_frontend::{
         OptimizeMirStorage,
         PositionConversionStorage,
         RcstToCstStorage,
+        ReferencesIndexStorage,
         StringToRcstStorage
     )
 )]
@@ -61,6 +68,8 @@ pub struct Database {
     storage: salsa::Storage<Self>,
     pub packages_path: PackagesPath,
     module_provider: OverlayModuleProvider<InMemoryModuleProvider, Box<dyn ModuleProvider + Send>>,
+    rcst_cache: RcstCache,
+    persisted_imported_definitions: FxHashMap<Module, PersistedImports>,
 }
 impl salsa::Database for Database {}
 
@@ -85,8 +94,26 @@ impl Database {
                 InMemoryModuleProvider::default(),
                 module_provider,
             ),
+            rcst_cache: RcstCache::default(),
+            persisted_imported_definitions: FxHashMap::default(),
         }
     }
+
+    /// Imported definitions from a previous session of the language server
+    /// that are used as long as their modules didn't change.
+    pub fn set_persisted_imported_definitions(
+        &mut self,
+        definitions: FxHashMap<Module, PersistedImports>,
+    ) {
+        self.persisted_imported_definitions = definitions;
+        ImportedDefinitionsQuery.in_db(self).purge();
+    }
+
+    /// Modules of the `package` were created or deleted, either on disk or by
+    /// opening or closing modules that aren't saved to disk.
+    pub fn invalidate_modules_of_package(&mut self, package: &Package) {
+        ModulesOfPackageQuery.in_db_mut(self).invalidate(package);
+    }
 }
 
 impl ModuleProviderOwner for Database {
@@ -102,3 +129,14 @@ impl MutableModuleProviderOwner for Database {
         GetModuleContentQuery.in_db_mut(self).invalidate(module);
     }
 }
+impl RcstCacheOwner for Database {
+    fn rcst_cache(&self) -> Option<&RcstCache> {
+        Some(&self.rcst_cache)
+    }
+}
+impl PersistedImportsOwner for Database {
+    fn persisted_imported_definitions(&self, module: &Module) -> Option<&PersistedImports> {
+        self.persisted_imported_definitions.get(module)
+    }
+}
+impl MirPassesOwner for Database {}
@@ -221,10 +221,8 @@ impl PausedState {
                         count = count.saturating_sub(1);
 
                         let fields = struct_
-                            .keys()
                             .iter()
-                            .copied()
-                            .zip_eq(struct_.values().iter().copied())
+                            .map(|(_, key, value)| (key, value))
                             .sorted()
                             .collect_vec();
                         variables.extend(fields.into_iter().skip(start).take(count).map(
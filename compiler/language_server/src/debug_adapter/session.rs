@@ -189,6 +189,8 @@ impl DebugSession {
                     register_fuzzables: TracingMode::Off,
                     calls: CallTracingMode::All,
                     evaluated_expressions: TracingMode::All,
+                    memoize_calls: false,
+                    overrides: vec![],
                 };
                 let byte_code = compile_byte_code(
                     &self.db,
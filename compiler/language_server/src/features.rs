@@ -1,10 +1,11 @@
-use crate::database::Database;
+use crate::{config::ServerConfig, database::Database};
 use async_trait::async_trait;
 use lsp_types::{
-    FoldingRange, LocationLink, SemanticToken, TextDocumentContentChangeEvent, TextEdit, Url,
+    CodeLens, FoldingRange, FormattingOptions, Hover, LinkedEditingRanges, LocationLink,
+    SelectionRange, SemanticToken, SignatureHelp, TextDocumentContentChangeEvent, TextEdit, Url,
 };
 use rustc_hash::FxHashMap;
-use std::collections::HashMap;
+use std::{collections::HashMap, path::Path, sync::Arc};
 use tokio::sync::Mutex;
 
 #[async_trait]
@@ -15,8 +16,9 @@ pub trait LanguageFeatures: Send + Sync {
     #[must_use]
     fn supported_url_schemes(&self) -> Vec<&'static str>;
 
-    async fn initialize(&self) {}
+    async fn initialize(&self, _db: &Mutex<Database>, _workspace_root: Option<&Path>) {}
     async fn shutdown(&self) {}
+    async fn update_config(&self, _config: &ServerConfig) {}
 
     fn supports_did_open(&self) -> bool {
         false
@@ -46,7 +48,7 @@ pub trait LanguageFeatures: Send + Sync {
         false
     }
     #[must_use]
-    async fn folding_ranges(&self, _db: &Mutex<Database>, _uri: Url) -> Vec<FoldingRange> {
+    async fn folding_ranges(&self, _db: &Arc<Mutex<Database>>, _uri: Url) -> Vec<FoldingRange> {
         unimplemented!()
     }
 
@@ -54,7 +56,27 @@ pub trait LanguageFeatures: Send + Sync {
         false
     }
     #[must_use]
-    async fn format(&self, _db: &Mutex<Database>, _uri: Url) -> Vec<TextEdit> {
+    async fn format(
+        &self,
+        _db: &Mutex<Database>,
+        _uri: Url,
+        _options: FormattingOptions,
+    ) -> Vec<TextEdit> {
+        unimplemented!()
+    }
+
+    fn supports_format_on_type(&self) -> bool {
+        false
+    }
+    /// Called after the user inserted a line break.
+    #[must_use]
+    async fn format_on_type(
+        &self,
+        _db: &Mutex<Database>,
+        _uri: Url,
+        _position: lsp_types::Position,
+        _options: FormattingOptions,
+    ) -> Vec<TextEdit> {
         unimplemented!()
     }
 
@@ -71,6 +93,32 @@ pub trait LanguageFeatures: Send + Sync {
         unimplemented!()
     }
 
+    fn supports_hover(&self) -> bool {
+        false
+    }
+    #[must_use]
+    async fn hover(
+        &self,
+        _db: &Mutex<Database>,
+        _uri: Url,
+        _position: lsp_types::Position,
+    ) -> Option<Hover> {
+        unimplemented!()
+    }
+
+    fn supports_selection_ranges(&self) -> bool {
+        false
+    }
+    #[must_use]
+    async fn selection_ranges(
+        &self,
+        _db: &Mutex<Database>,
+        _uri: Url,
+        _positions: Vec<lsp_types::Position>,
+    ) -> Vec<SelectionRange> {
+        unimplemented!()
+    }
+
     fn supports_references(&self) -> bool {
         false
     }
@@ -78,7 +126,7 @@ pub trait LanguageFeatures: Send + Sync {
     #[must_use]
     async fn references(
         &self,
-        _db: &Mutex<Database>,
+        _db: &Arc<Mutex<Database>>,
         _uri: Url,
         _position: lsp_types::Position,
         _only_in_same_document: bool,
@@ -87,6 +135,21 @@ pub trait LanguageFeatures: Send + Sync {
         unimplemented!()
     }
 
+    fn supports_linked_editing_ranges(&self) -> bool {
+        false
+    }
+    /// Ranges that have the same content and are renamed together while
+    /// typing.
+    #[must_use]
+    async fn linked_editing_ranges(
+        &self,
+        _db: &Mutex<Database>,
+        _uri: Url,
+        _position: lsp_types::Position,
+    ) -> Option<LinkedEditingRanges> {
+        unimplemented!()
+    }
+
     fn supports_rename(&self) -> bool {
         false
     }
@@ -110,11 +173,32 @@ pub trait LanguageFeatures: Send + Sync {
         unimplemented!()
     }
 
+    fn supports_signature_help(&self) -> bool {
+        false
+    }
+    #[must_use]
+    async fn signature_help(
+        &self,
+        _db: &Mutex<Database>,
+        _uri: Url,
+        _position: lsp_types::Position,
+    ) -> Option<SignatureHelp> {
+        unimplemented!()
+    }
+
     fn supports_semantic_tokens(&self) -> bool {
         false
     }
     #[must_use]
-    async fn semantic_tokens(&self, _db: &Mutex<Database>, _uri: Url) -> Vec<SemanticToken> {
+    async fn semantic_tokens(&self, _db: &Arc<Mutex<Database>>, _uri: Url) -> Vec<SemanticToken> {
+        unimplemented!()
+    }
+
+    fn supports_code_lenses(&self) -> bool {
+        false
+    }
+    #[must_use]
+    async fn code_lenses(&self, _db: &Mutex<Database>, _uri: Url) -> Vec<CodeLens> {
         unimplemented!()
     }
 }
@@ -0,0 +1,184 @@
+use candy_frontend::{
+    cst::{Cst, CstKind, UnwrapWhitespaceAndComment},
+    hir::{Body, Expression, Id},
+};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+/// A change to a module that only modified the bodies of top-level functions
+/// or added new ones, none of which are called while evaluating the module
+/// itself.
+///
+/// For such changes, the evaluated constants are still valid and fuzzers of
+/// functions that don't depend on the changed ones can keep running.
+pub struct FunctionBodyChange {
+    /// Top-level definitions whose behavior might have changed: The changed
+    /// functions and all definitions referencing them (transitively).
+    affected: FxHashSet<Id>,
+}
+impl FunctionBodyChange {
+    /// Returns `None` if the change affects more than just function bodies,
+    /// e.g., because a constant was modified or a changed function is called
+    /// at the top level of the module.
+    ///
+    /// The `hir` must belong to the `new_csts`.
+    pub fn detect(old_csts: &[Cst], new_csts: &[Cst], hir: &Body) -> Option<Self> {
+        let changed_names = changed_function_definitions(old_csts, new_csts)?;
+
+        // Curly brace functions are stored in anonymous IDs that the named
+        // definition refers to.
+        let mut changed = FxHashSet::default();
+        let mut unresolved = hir
+            .identifiers
+            .iter()
+            .filter(|(id, name)| id.keys.len() == 1 && changed_names.contains(*name))
+            .map(|(id, _)| id.clone())
+            .collect::<Vec<_>>();
+        while let Some(id) = unresolved.pop() {
+            if !changed.insert(id.clone()) {
+                continue;
+            }
+            match hir.expressions.get(&id)? {
+                Expression::Function(_) => {}
+                Expression::Reference(target) if target.keys.len() == 1 => {
+                    unresolved.push(target.clone());
+                }
+                _ => return None,
+            }
+        }
+
+        let dependents = dependents(hir);
+        let mut affected = FxHashSet::default();
+        let mut pending = changed.into_iter().collect::<Vec<_>>();
+        while let Some(id) = pending.pop() {
+            if !affected.insert(id.clone()) {
+                continue;
+            }
+            // These may run code of the changed functions during the
+            // evaluation of the module.
+            if matches!(
+                hir.expressions.get(&id),
+                Some(Expression::Call { .. } | Expression::Match { .. }),
+            ) {
+                return None;
+            }
+            pending.extend(dependents.get(&id).into_iter().flatten().cloned());
+        }
+        Some(Self { affected })
+    }
+
+    /// Whether the expression with this `id` might behave differently after
+    /// the change.
+    pub fn is_affected(&self, id: &Id) -> bool {
+        top_level_definition(id).map_or(true, |it| self.affected.contains(&it))
+    }
+}
+
+/// Compares the top-level CSTs and returns the names of function definitions
+/// whose bodies changed or that were inserted, or `None` if anything else
+/// changed.
+///
+/// Because IDs of expressions inside definitions are relative to the
+/// definition's name, inserting a definition doesn't change the IDs of the
+/// other ones.
+fn changed_function_definitions(old_csts: &[Cst], new_csts: &[Cst]) -> Option<FxHashSet<String>> {
+    const fn is_trivia(cst: &Cst) -> bool {
+        matches!(
+            cst.kind,
+            CstKind::Whitespace(_) | CstKind::Newline(_) | CstKind::Comment { .. },
+        )
+    }
+    let old_csts = old_csts
+        .iter()
+        .filter(|it| !is_trivia(it))
+        .collect::<Vec<_>>();
+    let old_function_names = old_csts
+        .iter()
+        .filter_map(|it| function_definition(it))
+        .map(|(name, _)| name)
+        .collect::<FxHashSet<_>>();
+
+    let mut names = FxHashSet::default();
+    let mut old_csts = old_csts.into_iter().peekable();
+    for new in new_csts.iter().filter(|it| !is_trivia(it)) {
+        if let Some(old) = old_csts.peek()
+            && old.to_string() == new.to_string()
+        {
+            old_csts.next();
+            continue;
+        }
+
+        let (new_name, new_signature) = function_definition(new)?;
+        if !old_function_names.contains(&new_name) {
+            names.insert(new_name);
+            continue;
+        }
+        let (old_name, old_signature) = function_definition(old_csts.next()?)?;
+        if old_name != new_name || old_signature != new_signature {
+            return None;
+        }
+        names.insert(new_name);
+    }
+    if old_csts.next().is_some() {
+        return None;
+    }
+    Some(names)
+}
+/// Returns the name and signature (everything in front of the body) if the
+/// `cst` is a function definition like `foo a := …` or `foo = { a -> … }`.
+fn function_definition(cst: &Cst) -> Option<(String, String)> {
+    let CstKind::Assignment {
+        left,
+        assignment_sign,
+        body,
+    } = &cst.unwrap_whitespace_and_comment().kind
+    else {
+        return None;
+    };
+
+    let name = match &left.unwrap_whitespace_and_comment().kind {
+        CstKind::Call { receiver, .. } => receiver.unwrap_whitespace_and_comment(),
+        CstKind::Identifier(_) => {
+            let mut body = body.iter().filter(|it| {
+                !matches!(
+                    it.kind,
+                    CstKind::Whitespace(_) | CstKind::Newline(_) | CstKind::Comment { .. },
+                )
+            });
+            match (body.next(), body.next()) {
+                (Some(function), None)
+                    if matches!(
+                        function.unwrap_whitespace_and_comment().kind,
+                        CstKind::Function { .. },
+                    ) => {}
+                _ => return None,
+            }
+            left.unwrap_whitespace_and_comment()
+        }
+        _ => return None,
+    };
+    let CstKind::Identifier(name) = &name.kind else {
+        return None;
+    };
+    Some((name.clone(), format!("{left}{assignment_sign}")))
+}
+
+/// Maps each top-level definition to the top-level definitions referencing it.
+fn dependents(hir: &Body) -> FxHashMap<Id, FxHashSet<Id>> {
+    let mut dependents: FxHashMap<Id, FxHashSet<Id>> = FxHashMap::default();
+    for (id, expression) in &hir.expressions {
+        let mut ids = vec![];
+        expression.collect_all_ids(&mut ids);
+        for dependency in ids
+            .iter()
+            .filter(|it| it.module == id.module)
+            .filter_map(top_level_definition)
+        {
+            dependents.entry(dependency).or_default().insert(id.clone());
+        }
+    }
+    dependents
+}
+fn top_level_definition(id: &Id) -> Option<Id> {
+    let key = id.keys.first()?;
+    Some(Id::new(id.module.clone(), vec![key.clone()]))
+}
@@ -5,16 +5,16 @@ use candy_frontend::{
     ast_to_hir::AstToHir,
     format::{MaxLength, Precedence},
     hir::{Expression, HirDb, Id},
+    lint::{LintSeverity, LintViolation},
     module::Module,
+    static_panics::{StaticPanic, StaticPanicKind},
 };
 use candy_fuzzer::{Fuzzer, RunResult, Status};
-use candy_vm::{
-    heap::{InlineObject, ToDebugText},
-    Panic,
-};
+use candy_vm::heap::{InlineObject, ToDebugText};
 use extension_trait::extension_trait;
-use lsp_types::{Diagnostic, DiagnosticSeverity, Position, Range};
+use lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Position, Range};
 use serde::{Deserialize, Serialize};
+use tracing::debug;
 
 #[derive(Debug)]
 pub enum Insight {
@@ -41,6 +41,13 @@ pub enum HintKind {
 
 impl Insight {
     pub fn for_value(db: &Database, id: Id, value: InlineObject) -> Option<Self> {
+        Self::for_formatted_value(
+            db,
+            id,
+            &value.to_debug_text(Precedence::Low, MaxLength::Limited(60)),
+        )
+    }
+    pub fn for_formatted_value(db: &Database, id: Id, value: &str) -> Option<Self> {
         let hir = db.find_expression(id.clone())?;
         let text = match hir {
             Expression::Reference(_) => {
@@ -60,15 +67,12 @@ impl Insight {
                     return None;
                 }
 
-                value.to_debug_text(Precedence::Low, MaxLength::Limited(60))
+                value.to_string()
             }
             Expression::PatternIdentifierReference { .. } => {
                 let body = db.containing_body_of(id.clone());
                 let name = body.identifiers.get(&id).unwrap();
-                format!(
-                    "{name} = {}",
-                    value.to_debug_text(Precedence::Low, MaxLength::Limited(60)),
-                )
+                format!("{name} = {value}")
             }
             _ => return None,
         };
@@ -98,7 +102,7 @@ impl Insight {
                 let function_coverage = total_coverage.in_range(&function_range);
                 function_coverage.relative_coverage()
             }
-            Status::FoundPanic { .. } => 1., // TODO: not correct
+            Status::Shrinking { .. } | Status::FoundPanic { .. } => 1., // TODO: not correct
         };
         let function_name = id.function_name();
         let interesting_inputs = fuzzer.input_pool().interesting_inputs();
@@ -108,7 +112,8 @@ impl Insight {
             text: format!("{:.0} % fuzzed", 100. * coverage),
         }));
 
-        if let Status::FoundPanic { input, .. } = fuzzer.status() {
+        if let Status::Shrinking { input, .. } | Status::FoundPanic { input, .. } = fuzzer.status()
+        {
             insights.push(Self::Hint(Hint {
                 kind: HintKind::SampleInputPanickingWithInternalCodeResponsible,
                 position: end_of_line,
@@ -140,16 +145,48 @@ impl Insight {
         insights
     }
 
-    pub fn for_static_panic(db: &Database, module: Module, panic: &Panic) -> Self {
-        let call_span = db
-            .hir_id_to_display_span(&panic.responsible)
-            .unwrap_or_else(|| panic!("Can't resolve responsible ID for panic: {panic:?}"));
-        let call_span = db.range_to_lsp_range(module, call_span);
+    /// Returns `None` if the responsible ID doesn't have a span, e.g., when
+    /// the module itself is responsible for a `needs` at the top level.
+    pub fn for_static_panic(db: &Database, module: Module, panic: &StaticPanic) -> Option<Self> {
+        let Some(span) = db.hir_id_to_display_span(&panic.responsible) else {
+            debug!("Can't resolve responsible ID for panic: {panic:?}");
+            return None;
+        };
+        let range = db.range_to_lsp_range(module, span);
 
-        Self::Diagnostic(Diagnostic::error(
-            call_span,
-            ToString::to_string(&panic.reason),
-        ))
+        // Functions panicking for all arguments might never get called.
+        let (severity, message) = match panic.kind {
+            StaticPanicKind::Always => (DiagnosticSeverity::ERROR, panic.reason.clone()),
+            StaticPanicKind::WhenCalled => (
+                DiagnosticSeverity::WARNING,
+                format!(
+                    "`{}` panics for all arguments: {}",
+                    panic.responsible.function_name(),
+                    panic.reason,
+                ),
+            ),
+        };
+        Some(Self::Diagnostic(Diagnostic {
+            severity: Some(severity),
+            ..Diagnostic::error(range, message)
+        }))
+    }
+
+    pub fn for_lint(db: &Database, violation: &LintViolation) -> Self {
+        let severity = match violation.severity {
+            LintSeverity::Off => unreachable!(),
+            LintSeverity::Info => DiagnosticSeverity::INFORMATION,
+            LintSeverity::Warning => DiagnosticSeverity::WARNING,
+            LintSeverity::Error => DiagnosticSeverity::ERROR,
+        };
+        Self::Diagnostic(Diagnostic {
+            severity: Some(severity),
+            code: Some(NumberOrString::String(violation.lint.to_string())),
+            ..Diagnostic::error(
+                db.range_to_lsp_range(violation.module.clone(), violation.span.clone()),
+                violation.message.clone(),
+            )
+        })
     }
 }
 
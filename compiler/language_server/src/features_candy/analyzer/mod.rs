@@ -8,14 +8,22 @@
 //!
 //! While doing all that, we can pause regularly between executing instructions
 //! so that we don't occupy a single CPU at 100 %.
+//!
+//! Additionally, the server reports compiler errors for modules that aren't
+//! open but belong to the same package as an open module.
 
 use self::{
     insights::{Hint, Insight},
     module_analyzer::ModuleAnalyzer,
+    persisted_index::PersistedIndex,
+    project_analyzer::ProjectAnalyzer,
 };
 use super::AnalyzerClient;
-use crate::database::Database;
-use candy_frontend::module::{Module, MutableModuleProviderOwner, PackagesPath};
+use crate::{config::ServerConfig, database::Database};
+use candy_frontend::{
+    hir,
+    module::{Module, MutableModuleProviderOwner, PackagesPath},
+};
 use itertools::{Either, Itertools};
 use lsp_types::{notification::Notification, Url};
 use rand::{seq::IteratorRandom, thread_rng};
@@ -23,20 +31,31 @@ use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
 use std::{fmt, future::Future, time::Duration, vec};
 use tokio::{
-    sync::mpsc::{self, error::TryRecvError},
+    sync::{
+        mpsc::{self, error::TryRecvError},
+        oneshot,
+    },
     time::sleep,
 };
 use tracing::debug;
 
+mod hot_reload;
 pub mod insights;
 mod module_analyzer;
-mod static_panics;
+pub mod persisted_index;
+mod project_analyzer;
 mod utils;
 
 #[derive(Debug)]
 pub enum Message {
+    /// Replaces the persisted index once the workspace is known.
+    SetIndex(PersistedIndex),
     UpdateModule(Module, Vec<u8>),
     CloseModule(Module),
+    UpdateConfig(ServerConfig),
+    /// Responds with the formatted value of the expression if the module's
+    /// analyzer already evaluated it.
+    GetEvaluatedValue(hir::Id, oneshot::Sender<Option<String>>),
     Shutdown,
 }
 
@@ -60,6 +79,8 @@ pub async fn run_server(
 ) {
     let mut db = Database::new_with_file_system_module_provider(packages_path);
     let mut analyzers: FxHashMap<Module, ModuleAnalyzer> = FxHashMap::default();
+    let mut project_analyzer = ProjectAnalyzer::new(PersistedIndex::default());
+    let mut config = ServerConfig::default();
     let client_ref = &client;
     let mut outgoing_diagnostics = OutgoingCache::new(move |module, diagnostics| {
         client_ref.update_diagnostics(module, diagnostics)
@@ -77,31 +98,61 @@ pub async fn run_server(
                 Err(TryRecvError::Disconnected) => break 'server_loop,
             };
             match event {
+                Message::SetIndex(index) => {
+                    project_analyzer = ProjectAnalyzer::new(index);
+                }
                 Message::UpdateModule(module, content) => {
                     db.did_change_module(&module, content);
+                    project_analyzer.module_changed(&module);
                     outgoing_hints.send(module.clone(), vec![]).await;
                     analyzers
                         .entry(module.clone())
-                        .and_modify(ModuleAnalyzer::module_changed)
-                        .or_insert_with(|| ModuleAnalyzer::for_module(module.clone()));
+                        .and_modify(|analyzer| analyzer.module_changed(&db))
+                        .or_insert_with(|| ModuleAnalyzer::for_module(&db, module.clone()));
                 }
                 Message::CloseModule(module) => {
                     db.did_close_module(&module);
+                    project_analyzer.module_changed(&module);
                     analyzers.remove(&module);
                 }
+                Message::UpdateConfig(new_config) => {
+                    if !new_config.hints.enabled {
+                        for module in analyzers.keys() {
+                            outgoing_hints.send(module.clone(), vec![]).await;
+                        }
+                    }
+                    config = new_config;
+                }
+                Message::GetEvaluatedValue(id, response) => {
+                    let value = analyzers
+                        .get(&id.module)
+                        .and_then(|analyzer| analyzer.evaluated_value(&id));
+                    // The requester might have given up already.
+                    let _ = response.send(value);
+                }
                 Message::Shutdown => {
                     incoming_events.close();
                 }
             }
         }
 
+        if let Some(diagnostics) = project_analyzer.run(&db, &client).await {
+            for (module, diagnostics) in diagnostics {
+                // Open modules are analyzed more thoroughly by their own
+                // analyzer.
+                if !analyzers.contains_key(&module) {
+                    outgoing_diagnostics.send(module, diagnostics).await;
+                }
+            }
+        }
+
         let Some(module) = analyzers.keys().choose(&mut thread_rng()).cloned() else {
             client.update_status(None);
             continue;
         };
         let analyzer = analyzers.get_mut(&module).unwrap();
 
-        analyzer.run(&db, &client).await;
+        analyzer.run(&db, &client, &config.fuzzing).await;
 
         let insights = analyzer.insights(&db);
         let (diagnostics, mut hints): (Vec<_>, Vec<_>) =
@@ -109,11 +160,16 @@ pub async fn run_server(
                 Insight::Diagnostic(diagnostic) => Either::Left(diagnostic),
                 Insight::Hint(hint) => Either::Right(hint),
             });
+        if !config.hints.enabled {
+            hints.clear();
+        }
         hints.sort_by_key(|hint| hint.position);
 
         outgoing_diagnostics.send(module.clone(), diagnostics).await;
         outgoing_hints.send(module, hints).await;
     }
+
+    project_analyzer.save_index();
 }
 
 struct OutgoingCache<T, R: Fn(Module, T) -> F, F: Future> {
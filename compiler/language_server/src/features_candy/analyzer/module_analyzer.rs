@@ -1,44 +1,72 @@
-use super::{insights::Insight, static_panics::StaticPanicsOfMir};
+use super::{hot_reload::FunctionBodyChange, insights::Insight};
 use crate::{
-    database::Database, features_candy::analyzer::insights::ErrorDiagnostic,
+    config::FuzzingConfig, database::Database, features_candy::analyzer::insights::ErrorDiagnostic,
     server::AnalyzerClient, utils::LspPositionConversion,
 };
 use candy_frontend::{
     ast_to_hir::AstToHir,
+    cst::Cst,
     format::{MaxLength, Precedence},
+    hir,
     hir_to_mir::ExecutionTarget,
+    lint::lint_module,
+    mir::{Evaluator, VisibleExpressions},
     mir_optimize::OptimizeMir,
     module::Module,
+    rcst_to_cst::RcstToCst,
+    static_panics::{static_panics, StaticPanic},
     tracing::CallTracingMode,
     TracingConfig, TracingMode,
 };
 use candy_fuzzer::{FuzzablesFinder, Fuzzer, Status};
 use candy_vm::{
     byte_code::ByteCode,
-    environment::StateAfterRunWithoutHandles,
+    environment::{EmptyEnvironment, StateAfterRunWithoutHandles},
+    execution_controller::{
+        ExecutionController, RunLimitedNumberOfInstructions, WallClockController,
+    },
     heap::{Heap, ToDebugText},
     lir_to_byte_code::compile_byte_code,
     tracer::{evaluated_values::EvaluatedValuesTracer, stack_trace::StackTracer},
-    Panic, Vm, VmFinished,
+    Vm, VmFinished,
 };
 use extension_trait::extension_trait;
 use itertools::Itertools;
 use lsp_types::Diagnostic;
 use rand::{prelude::SliceRandom, thread_rng};
-use std::rc::Rc;
+use rustc_hash::FxHashMap;
+use std::{mem, rc::Rc, sync::Arc, time::Duration};
 use tracing::debug;
 
+/// How much work the compile-time evaluation for instant hints may do.
+const INSTANT_EVALUATION_FUEL: usize = 100_000;
+
+/// How long the VM may run in a single step of the analyzer before handing
+/// control back to the hints server. Slow instructions, such as builtins
+/// working on huge values, are limited by the wall-clock time.
+fn evaluation_step_controller() -> impl ExecutionController {
+    RunLimitedNumberOfInstructions::new(500)
+        .and(WallClockController::new(Duration::from_millis(10)))
+}
+
 /// A hints finder is responsible for finding hints for a single module.
 pub struct ModuleAnalyzer {
     module: Module,
+    /// The CSTs of the module's current content, used to detect which parts
+    /// changed.
+    csts: Option<Arc<Vec<Cst>>>,
     state: Option<State>, // only None during state transition
 }
 enum State {
     Initial,
     /// First, we run the module with tracing of evaluated expressions enabled.
     /// This enables us to show hints for constants.
+    ///
+    /// Until the VM finishes, we show the values that the compile-time
+    /// evaluator could determine instantly.
     EvaluateConstants {
-        static_panics: Vec<Panic>,
+        static_panics: Vec<StaticPanic>,
+        instant_values: Vec<(hir::Id, String)>,
         byte_code: Rc<ByteCode>,
         heap: Heap,
         vm: Vm<Rc<ByteCode>, (StackTracer, EvaluatedValuesTracer)>,
@@ -48,7 +76,7 @@ enum State {
     /// fuzzable functions. Thus, the found functions to fuzz have the most
     /// efficient byte code possible.
     FindFuzzables {
-        static_panics: Vec<Panic>,
+        static_panics: Vec<StaticPanic>,
         heap_for_constants: Heap,
         stack_tracer: StackTracer,
         /// We need to keep a reference to this byte code for its constant heap
@@ -58,11 +86,25 @@ enum State {
         byte_code: Rc<ByteCode>,
         heap: Heap,
         vm: Vm<Rc<ByteCode>, FuzzablesFinder>,
+        /// Fuzzers from before a hot reload that are reused for the same
+        /// functions instead of starting from scratch.
+        reusable_fuzzers: Vec<Fuzzer>,
+    },
+    /// When only function bodies changed or functions were added (see
+    /// [FunctionBodyChange]), the evaluated constants are still valid, so we
+    /// skip evaluating them again and directly continue with finding
+    /// fuzzables.
+    HotReload {
+        heap_for_constants: Heap,
+        stack_tracer: StackTracer,
+        evaluated_values_byte_code: Rc<ByteCode>,
+        evaluated_values: EvaluatedValuesTracer,
+        reusable_fuzzers: Vec<Fuzzer>,
     },
     /// Then, the functions are actually fuzzed.
     Fuzz {
         byte_code: Rc<ByteCode>,
-        static_panics: Vec<Panic>,
+        static_panics: Vec<StaticPanic>,
         heap_for_constants: Heap,
         stack_tracer: StackTracer,
         evaluated_values_byte_code: Rc<ByteCode>,
@@ -73,47 +115,118 @@ enum State {
 }
 
 impl ModuleAnalyzer {
-    pub const fn for_module(module: Module) -> Self {
+    pub fn for_module(db: &Database, module: Module) -> Self {
         Self {
+            csts: db.cst(module.clone()).ok(),
             module,
             state: Some(State::Initial),
         }
     }
-    pub fn module_changed(&mut self) {
-        // PERF: Save some incremental state.
-        self.state = Some(State::Initial);
+    pub fn module_changed(&mut self, db: &Database) {
+        let old_csts = mem::replace(&mut self.csts, db.cst(self.module.clone()).ok());
+        let state = self.state.take().unwrap();
+        let state = self
+            .hot_reload(db, old_csts, state)
+            .unwrap_or(State::Initial);
+        self.state = Some(state);
     }
+    /// Keeps the evaluated constants and unaffected fuzzers if the change only
+    /// affects function bodies.
+    fn hot_reload(
+        &self,
+        db: &Database,
+        old_csts: Option<Arc<Vec<Cst>>>,
+        state: State,
+    ) -> Option<State> {
+        let (old_csts, new_csts) = (old_csts?, self.csts.as_ref()?);
+        let (hir, _) = db.hir(self.module.clone()).ok()?;
+        let change = FunctionBodyChange::detect(&old_csts, new_csts, &hir)?;
 
-    pub async fn run(&mut self, db: &Database, client: &AnalyzerClient) {
+        let (
+            mut heap_for_constants,
+            stack_tracer,
+            evaluated_values_byte_code,
+            mut evaluated_values,
+            fuzzers,
+        ) = match state {
+            State::Initial | State::EvaluateConstants { .. } => return None,
+            State::FindFuzzables {
+                heap_for_constants,
+                stack_tracer,
+                evaluated_values_byte_code,
+                evaluated_values,
+                reusable_fuzzers,
+                ..
+            }
+            | State::HotReload {
+                heap_for_constants,
+                stack_tracer,
+                evaluated_values_byte_code,
+                evaluated_values,
+                reusable_fuzzers,
+            } => (
+                heap_for_constants,
+                stack_tracer,
+                evaluated_values_byte_code,
+                evaluated_values,
+                reusable_fuzzers,
+            ),
+            State::Fuzz {
+                heap_for_constants,
+                stack_tracer,
+                evaluated_values_byte_code,
+                evaluated_values,
+                fuzzers,
+                ..
+            } => (
+                heap_for_constants,
+                stack_tracer,
+                evaluated_values_byte_code,
+                evaluated_values,
+                fuzzers,
+            ),
+        };
+        evaluated_values.retain(&mut heap_for_constants, |id| !change.is_affected(id));
+        let reusable_fuzzers = fuzzers
+            .into_iter()
+            .filter(|fuzzer| !change.is_affected(&fuzzer.function_id))
+            .collect();
+        Some(State::HotReload {
+            heap_for_constants,
+            stack_tracer,
+            evaluated_values_byte_code,
+            evaluated_values,
+            reusable_fuzzers,
+        })
+    }
+
+    pub async fn run(&mut self, db: &Database, client: &AnalyzerClient, fuzzing: &FuzzingConfig) {
         let state = self.state.take().unwrap();
-        let state = self.update_state(db, client, state).await;
+        let state = self.update_state(db, client, fuzzing, state).await;
         self.state = Some(state);
     }
-    async fn update_state(&self, db: &Database, client: &AnalyzerClient, state: State) -> State {
+    async fn update_state(
+        &self,
+        db: &Database,
+        client: &AnalyzerClient,
+        fuzzing: &FuzzingConfig,
+        state: State,
+    ) -> State {
         match state {
             State::Initial => {
                 client
                     .update_status(Some(format!("Compiling {}", self.module)))
                     .await;
 
-                let (mir, _) = db
-                    .optimized_mir(
-                        ExecutionTarget::Module(self.module.clone()),
-                        TracingConfig {
-                            register_fuzzables: TracingMode::OnlyCurrent,
-                            calls: CallTracingMode::Off,
-                            evaluated_expressions: TracingMode::Off,
-                        },
-                    )
-                    .unwrap();
-                let mut mir = (*mir).clone();
-                let mut static_panics = mir.static_panics();
-                static_panics.retain(|panic| panic.responsible.module == self.module);
+                let static_panics = self.static_panics(db);
+                let instant_values = self.instant_values(db);
 
                 let tracing = TracingConfig {
                     register_fuzzables: TracingMode::Off,
                     calls: CallTracingMode::Off,
                     evaluated_expressions: TracingMode::OnlyCurrent,
+                    memoize_calls: false,
+                    overrides: vec![],
                 };
                 let (byte_code, _) =
                     compile_byte_code(db, ExecutionTarget::Module(self.module.clone()), tracing);
@@ -128,6 +241,7 @@ impl ModuleAnalyzer {
 
                 State::EvaluateConstants {
                     static_panics,
+                    instant_values,
                     byte_code,
                     heap,
                     vm,
@@ -135,6 +249,7 @@ impl ModuleAnalyzer {
             }
             State::EvaluateConstants {
                 static_panics,
+                instant_values,
                 byte_code,
                 heap: mut heap_for_constants,
                 vm,
@@ -143,10 +258,18 @@ impl ModuleAnalyzer {
                     .update_status(Some(format!("Evaluating {}", self.module)))
                     .await;
 
-                let tracer = match vm.run_n_without_handles(&mut heap_for_constants, 500) {
+                let tracer = match vm
+                    .run_until_yield(
+                        &mut heap_for_constants,
+                        &mut EmptyEnvironment,
+                        &mut evaluation_step_controller(),
+                    )
+                    .await
+                {
                     StateAfterRunWithoutHandles::Running(vm) => {
                         return State::EvaluateConstants {
                             static_panics,
+                            instant_values,
                             byte_code,
                             heap: heap_for_constants,
                             vm,
@@ -156,21 +279,7 @@ impl ModuleAnalyzer {
                 };
                 let (stack_tracer, evaluated_values) = tracer;
 
-                let tracing = TracingConfig {
-                    register_fuzzables: TracingMode::OnlyCurrent,
-                    calls: CallTracingMode::Off,
-                    evaluated_expressions: TracingMode::Off,
-                };
-                let (fuzzing_byte_code, _) =
-                    compile_byte_code(db, ExecutionTarget::Module(self.module.clone()), tracing);
-                let fuzzing_byte_code = Rc::new(fuzzing_byte_code);
-
-                let mut heap = Heap::default();
-                let vm = Vm::for_module(
-                    fuzzing_byte_code.clone(),
-                    &mut heap,
-                    FuzzablesFinder::default(),
-                );
+                let (fuzzing_byte_code, heap, vm) = self.find_fuzzables(db);
                 State::FindFuzzables {
                     static_panics,
                     heap_for_constants,
@@ -180,6 +289,32 @@ impl ModuleAnalyzer {
                     byte_code: fuzzing_byte_code,
                     heap,
                     vm,
+                    reusable_fuzzers: vec![],
+                }
+            }
+            State::HotReload {
+                heap_for_constants,
+                stack_tracer,
+                evaluated_values_byte_code,
+                evaluated_values,
+                reusable_fuzzers,
+            } => {
+                client
+                    .update_status(Some(format!("Compiling {}", self.module)))
+                    .await;
+
+                let static_panics = self.static_panics(db);
+                let (byte_code, heap, vm) = self.find_fuzzables(db);
+                State::FindFuzzables {
+                    static_panics,
+                    heap_for_constants,
+                    stack_tracer,
+                    evaluated_values_byte_code,
+                    evaluated_values,
+                    byte_code,
+                    heap,
+                    vm,
+                    reusable_fuzzers,
                 }
             }
             State::FindFuzzables {
@@ -191,12 +326,20 @@ impl ModuleAnalyzer {
                 byte_code,
                 mut heap,
                 vm,
+                reusable_fuzzers,
             } => {
                 client
                     .update_status(Some(format!("Evaluating {}", self.module)))
                     .await;
 
-                let (heap, tracer) = match vm.run_n_without_handles(&mut heap, 500) {
+                let (heap, tracer) = match vm
+                    .run_until_yield(
+                        &mut heap,
+                        &mut EmptyEnvironment,
+                        &mut evaluation_step_controller(),
+                    )
+                    .await
+                {
                     StateAfterRunWithoutHandles::Running(vm) => {
                         return State::FindFuzzables {
                             static_panics,
@@ -207,6 +350,7 @@ impl ModuleAnalyzer {
                             byte_code,
                             heap,
                             vm,
+                            reusable_fuzzers,
                         }
                     }
                     StateAfterRunWithoutHandles::Finished(VmFinished { tracer, .. }) => {
@@ -214,10 +358,18 @@ impl ModuleAnalyzer {
                     }
                 };
 
+                let mut reusable_fuzzers: FxHashMap<_, _> = reusable_fuzzers
+                    .into_iter()
+                    .map(|fuzzer| (fuzzer.function_id.clone(), fuzzer))
+                    .collect();
                 let fuzzers = tracer
                     .fuzzables
                     .iter()
-                    .map(|(id, function)| Fuzzer::new(byte_code.clone(), *function, id.clone()))
+                    .map(|(id, function)| {
+                        reusable_fuzzers.remove(id).unwrap_or_else(|| {
+                            Fuzzer::new(byte_code.clone(), *function, id.clone())
+                        })
+                    })
                     .collect();
                 State::Fuzz {
                     byte_code,
@@ -242,7 +394,14 @@ impl ModuleAnalyzer {
             } => {
                 let mut running_fuzzers = fuzzers
                     .iter_mut()
-                    .filter(|fuzzer| matches!(fuzzer.status(), Status::StillFuzzing { .. }))
+                    .filter(|fuzzer| {
+                        matches!(
+                            fuzzer.status(),
+                            Status::StillFuzzing { .. } | Status::Shrinking { .. },
+                        ) && fuzzing
+                            .budget_per_function
+                            .map_or(true, |budget| fuzzer.total_instructions() < budget)
+                    })
                     .collect_vec();
                 let Some(fuzzer) = running_fuzzers.choose_mut(&mut thread_rng()) else {
                     client.update_status(None).await;
@@ -278,14 +437,103 @@ impl ModuleAnalyzer {
         }
     }
 
+    fn static_panics(&self, db: &Database) -> Vec<StaticPanic> {
+        static_panics(db, &self.module)
+    }
+    /// Evaluates the module's optimized MIR at compile-time. This is much
+    /// faster than running the VM, but can't evaluate everything.
+    fn instant_values(&self, db: &Database) -> Vec<(hir::Id, String)> {
+        let (mir, _) = db
+            .optimized_mir(
+                ExecutionTarget::Module(self.module.clone()),
+                TracingConfig {
+                    register_fuzzables: TracingMode::Off,
+                    calls: CallTracingMode::Off,
+                    evaluated_expressions: TracingMode::OnlyCurrent,
+                    memoize_calls: false,
+                    overrides: vec![],
+                },
+            )
+            .unwrap();
+        let visible = VisibleExpressions::none_visible();
+        let mut evaluator = Evaluator::new(&visible, INSTANT_EVALUATION_FUEL);
+        evaluator
+            .evaluate_partially(&mir.body)
+            .into_iter()
+            .filter_map(|(id, value)| {
+                let text = evaluator.format(value, Precedence::Low, MaxLength::Limited(60))?;
+                Some((id, text))
+            })
+            .collect()
+    }
+    fn find_fuzzables(
+        &self,
+        db: &Database,
+    ) -> (Rc<ByteCode>, Heap, Vm<Rc<ByteCode>, FuzzablesFinder>) {
+        let tracing = TracingConfig {
+            register_fuzzables: TracingMode::OnlyCurrent,
+            calls: CallTracingMode::Off,
+            evaluated_expressions: TracingMode::Off,
+            memoize_calls: false,
+            overrides: vec![],
+        };
+        let (byte_code, _) =
+            compile_byte_code(db, ExecutionTarget::Module(self.module.clone()), tracing);
+        let byte_code = Rc::new(byte_code);
+
+        let mut heap = Heap::default();
+        let vm = Vm::for_module(byte_code.clone(), &mut heap, FuzzablesFinder::default());
+        (byte_code, heap, vm)
+    }
+
+    /// The value that the expression evaluated to when running the module,
+    /// if it was evaluated yet.
+    pub fn evaluated_value(&self, id: &hir::Id) -> Option<String> {
+        let evaluated_values = match self.state.as_ref().unwrap() {
+            State::Initial | State::EvaluateConstants { .. } => return None,
+            State::FindFuzzables {
+                evaluated_values, ..
+            }
+            | State::HotReload {
+                evaluated_values, ..
+            }
+            | State::Fuzz {
+                evaluated_values, ..
+            } => evaluated_values,
+        };
+        let value = evaluated_values.values().get(id)?;
+        Some(value.to_debug_text(Precedence::Low, MaxLength::Unlimited))
+    }
+
     pub fn insights(&self, db: &Database) -> Vec<Insight> {
-        let mut insights = vec![];
+        let mut insights = lint_module(db, &self.module)
+            .iter()
+            .map(|it| Insight::for_lint(db, it))
+            .collect_vec();
 
         match self.state.as_ref().unwrap() {
             State::Initial => {}
-            State::EvaluateConstants { static_panics, .. } => {
-                // TODO: Show incremental constant evaluation hints.
+            State::EvaluateConstants {
+                static_panics,
+                instant_values,
+                ..
+            } => {
                 insights.extend(static_panics.to_insights(db, &self.module));
+                insights.extend(
+                    instant_values.iter().filter_map(|(id, value)| {
+                        Insight::for_formatted_value(db, id.clone(), value)
+                    }),
+                );
+            }
+            State::HotReload {
+                evaluated_values, ..
+            } => {
+                insights.extend(
+                    evaluated_values
+                        .values()
+                        .iter()
+                        .filter_map(|(id, value)| Insight::for_value(db, id.clone(), *value)),
+                );
             }
             State::FindFuzzables {
                 static_panics,
@@ -317,7 +565,11 @@ impl ModuleAnalyzer {
                 for fuzzer in fuzzers {
                     insights.append(&mut Insight::for_fuzzer_status(db, fuzzer));
 
-                    let Status::FoundPanic { input, panic, .. } = fuzzer.status() else {
+                    // While shrinking, we already report the simplest panicking
+                    // input found so far.
+                    let (Status::Shrinking { input, panic, .. }
+                    | Status::FoundPanic { input, panic, .. }) = fuzzer.status()
+                    else {
                         continue;
                     };
 
@@ -343,19 +595,15 @@ impl ModuleAnalyzer {
                     // TODO: In the future, re-run only the failing case with
                     // tracing enabled and also show the arguments to the failing
                     // function in the hint.
-                    let call_span = db
-                        .hir_id_to_display_span(&panic.responsible)
+                    let call_span = panic
+                        .display_span(db)
                         .unwrap_or_else(|| panic!("Couldn't find the span for {panic:?}."));
                     insights.push(Insight::Diagnostic(Diagnostic::error(
                         db.range_to_lsp_range(self.module.clone(), call_span),
                         format!(
                             "For `{} {}`, this call panics: {}",
                             fuzzer.function_id.function_name(),
-                            input
-                                .arguments()
-                                .iter()
-                                .map(|it| it.to_debug_text(Precedence::High, MaxLength::Unlimited))
-                                .join(" "),
+                            input.to_debug_text(MaxLength::Unlimited),
                             panic.reason,
                         ),
                     )));
@@ -370,10 +618,10 @@ impl ModuleAnalyzer {
 }
 
 #[extension_trait]
-pub impl StaticPanics for Vec<Panic> {
+pub impl StaticPanics for Vec<StaticPanic> {
     fn to_insights(&self, db: &Database, module: &Module) -> Vec<Insight> {
         self.iter()
-            .map(|panic| Insight::for_static_panic(db, module.clone(), panic))
+            .filter_map(|panic| Insight::for_static_panic(db, module.clone(), panic))
             .collect_vec()
     }
 }
@@ -0,0 +1,262 @@
+//! Compiling all packages with open modules can take a while, so the project
+//! diagnostics and the definitions of imported identifiers are persisted
+//! between sessions of the language server.
+//!
+//! Together with the diagnostics, we store a hash of the content and the used
+//! modules of every module that the root of a package depends on. When the
+//! server starts again, diagnostics of a package are reused if none of these
+//! modules changed in the meantime.
+//!
+//! For each of these modules, we also store which definitions in other
+//! modules its imported identifiers refer to. Searching references across a
+//! package uses these instead of compiling modules whose content hash didn't
+//! change.
+//!
+//! Each workspace has its own index file, so that language servers running
+//! for different workspaces don't overwrite each other's index.
+
+use crate::{
+    database::Database,
+    features_candy::{
+        find_definition::find_used_module,
+        references::{PersistedImports, ReferencesIndexDb},
+    },
+};
+use candy_frontend::{
+    ast_to_hir::AstToHir,
+    hir::{self, Body, Expression},
+    module::{Module, ModuleDb},
+};
+use lsp_types::Diagnostic;
+use rustc_hash::{FxHashMap, FxHashSet, FxHasher};
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tracing::{debug, warn};
+
+#[derive(Debug, Default)]
+pub struct PersistedIndex {
+    /// `None` if the index isn't persisted, e.g., because there's no cache
+    /// directory.
+    path: Option<PathBuf>,
+    modules: FxHashMap<Module, ModuleEntry>,
+    /// Diagnostics of all modules reachable from a package's root module.
+    roots: FxHashMap<Module, FxHashMap<Module, Vec<Diagnostic>>>,
+}
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct ModuleEntry {
+    /// `None` if the module doesn't exist.
+    content_hash: Option<u64>,
+    used_modules: Vec<Module>,
+    /// Identifiers that the module imports from other modules and their
+    /// definitions in those modules.
+    imported_definitions: Vec<(hir::Id, hir::Id)>,
+}
+
+/// The format on disk. JSON only supports strings as keys of maps, so we store
+/// lists of pairs instead.
+#[derive(Deserialize, Serialize)]
+struct IndexFile {
+    version: u32,
+    /// Diagnostics depend on the compiler, so they're only reused by the same
+    /// version of it.
+    compiler_version: String,
+    modules: Vec<(Module, ModuleEntry)>,
+    roots: Vec<(Module, Vec<ModuleDiagnostics>)>,
+}
+type ModuleDiagnostics = (Module, Vec<Diagnostic>);
+impl IndexFile {
+    /// Increase this when changing the format or the meaning of entries.
+    const VERSION: u32 = 3;
+    const COMPILER_VERSION: &'static str = env!("CARGO_PKG_VERSION");
+}
+
+impl PersistedIndex {
+    fn path(workspace_root: &Path) -> Option<PathBuf> {
+        let cache_directory = env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("LOCALAPPDATA").map(PathBuf::from))
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+        let mut hasher = FxHasher::default();
+        workspace_root.hash(&mut hasher);
+        let workspace_hash = hasher.finish();
+        Some(
+            cache_directory
+                .join("candy")
+                .join(format!("language_server_index-{workspace_hash:016x}.json")),
+        )
+    }
+
+    #[must_use]
+    pub fn load(workspace_root: &Path) -> Self {
+        let Some(path) = Self::path(workspace_root) else {
+            return Self::default();
+        };
+        let empty = Self {
+            path: Some(path.clone()),
+            ..Self::default()
+        };
+        let Ok(content) = fs::read(&path) else {
+            return empty;
+        };
+        let file: IndexFile = match serde_json::from_slice(&content) {
+            Ok(file) => file,
+            Err(error) => {
+                warn!("Couldn't parse the persisted index at {path:?}: {error}");
+                return empty;
+            }
+        };
+        if file.version != IndexFile::VERSION
+            || file.compiler_version != IndexFile::COMPILER_VERSION
+        {
+            return empty;
+        }
+
+        Self {
+            path: Some(path),
+            modules: file.modules.into_iter().collect(),
+            roots: file
+                .roots
+                .into_iter()
+                .map(|(root, diagnostics)| (root, diagnostics.into_iter().collect()))
+                .collect(),
+        }
+    }
+    pub fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let file = IndexFile {
+            version: IndexFile::VERSION,
+            compiler_version: IndexFile::COMPILER_VERSION.to_string(),
+            modules: self
+                .modules
+                .iter()
+                .map(|(module, entry)| (module.clone(), entry.clone()))
+                .collect(),
+            roots: self
+                .roots
+                .iter()
+                .map(|(root, diagnostics)| {
+                    let diagnostics = diagnostics
+                        .iter()
+                        .map(|(module, diagnostics)| (module.clone(), diagnostics.clone()))
+                        .collect();
+                    (root.clone(), diagnostics)
+                })
+                .collect(),
+        };
+        let content = serde_json::to_vec(&file).unwrap();
+        if let Err(error) =
+            fs::create_dir_all(path.parent().unwrap()).and_then(|()| fs::write(path, content))
+        {
+            warn!("Couldn't save the persisted index to {path:?}: {error}");
+        }
+    }
+
+    /// The persisted imported definitions of all existing modules, keyed by
+    /// module.
+    #[must_use]
+    pub fn imported_definitions(&self) -> FxHashMap<Module, PersistedImports> {
+        self.modules
+            .iter()
+            .filter_map(|(module, entry)| {
+                let imports = PersistedImports {
+                    content_hash: entry.content_hash?,
+                    definitions: Arc::new(entry.imported_definitions.iter().cloned().collect()),
+                };
+                Some((module.clone(), imports))
+            })
+            .collect()
+    }
+
+    /// Returns the persisted diagnostics of the modules reachable from `root`
+    /// if none of these modules changed.
+    #[must_use]
+    pub fn diagnostics(
+        &self,
+        db: &Database,
+        root: &Module,
+    ) -> Option<FxHashMap<Module, Vec<Diagnostic>>> {
+        let diagnostics = self.roots.get(root)?;
+
+        let mut visited = FxHashSet::default();
+        let mut pending = vec![root.clone()];
+        while let Some(module) = pending.pop() {
+            if !visited.insert(module.clone()) {
+                continue;
+            }
+            let entry = self.modules.get(&module)?;
+            if entry.content_hash != content_hash(db, &module) {
+                debug!("{module} changed since its diagnostics were persisted.");
+                return None;
+            }
+            pending.extend(entry.used_modules.iter().cloned());
+        }
+        Some(diagnostics.clone())
+    }
+    /// Stores the `diagnostics` of the modules reachable from `root`, which
+    /// have to be computed using the current content of the modules.
+    pub fn update(
+        &mut self,
+        db: &Database,
+        root: &Module,
+        diagnostics: FxHashMap<Module, Vec<Diagnostic>>,
+    ) {
+        let mut visited = FxHashSet::default();
+        let mut pending = vec![root.clone()];
+        while let Some(module) = pending.pop() {
+            if !visited.insert(module.clone()) {
+                continue;
+            }
+            let used_modules = used_modules(db, &module);
+            pending.extend(used_modules.iter().cloned());
+            let entry = ModuleEntry {
+                content_hash: content_hash(db, &module),
+                used_modules,
+                imported_definitions: db
+                    .imported_definitions(module.clone())
+                    .iter()
+                    .map(|(import, definition)| (import.clone(), definition.clone()))
+                    .collect(),
+            };
+            self.modules.insert(module, entry);
+        }
+        self.roots.insert(root.clone(), diagnostics);
+    }
+}
+
+pub fn content_hash<DB: ModuleDb + ?Sized>(db: &DB, module: &Module) -> Option<u64> {
+    let content = db.get_module_content(module.clone())?;
+    let mut hasher = FxHasher::default();
+    content.hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+fn used_modules(db: &Database, module: &Module) -> Vec<Module> {
+    fn collect(db: &Database, body: &Body, modules: &mut FxHashSet<Module>) {
+        for (id, expression) in &body.expressions {
+            match expression {
+                Expression::Call { .. } => modules.extend(find_used_module(db, id)),
+                Expression::Match { cases, .. } => {
+                    for (_, body) in cases {
+                        collect(db, body, modules);
+                    }
+                }
+                Expression::Function(function) => collect(db, &function.body, modules),
+                _ => {}
+            }
+        }
+    }
+
+    let Ok((hir, _)) = db.hir(module.clone()) else {
+        return vec![];
+    };
+    let mut modules = FxHashSet::default();
+    collect(db, &hir, &mut modules);
+    modules.into_iter().collect()
+}
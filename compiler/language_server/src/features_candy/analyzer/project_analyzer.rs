@@ -0,0 +1,137 @@
+use super::{insights::Insight, persisted_index::PersistedIndex};
+use crate::{database::Database, server::AnalyzerClient};
+use candy_frontend::{
+    hir_to_mir::ExecutionTarget,
+    mir_optimize::OptimizeMir,
+    module::{Module, ModuleKind, Package},
+    TracingConfig,
+};
+use lsp_types::Diagnostic;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::mem;
+use tracing::debug;
+
+/// A project analyzer finds diagnostics for all modules of the packages that
+/// contain open modules – even for modules that aren't open themselves.
+///
+/// Compiling the root module of a package also compiles all modules that are
+/// reachable from it. Thanks to module folding, compiler errors of those
+/// modules end up as static panics in the root module's MIR, so we only have
+/// to compile each package once. If none of the reachable modules changed
+/// since a previous session, we don't even do that and reuse the diagnostics
+/// from the [`PersistedIndex`].
+pub struct ProjectAnalyzer {
+    roots: FxHashSet<Module>,
+    is_outdated: bool,
+    modules_with_diagnostics: FxHashSet<Module>,
+    index: PersistedIndex,
+}
+
+impl ProjectAnalyzer {
+    #[must_use]
+    pub fn new(index: PersistedIndex) -> Self {
+        Self {
+            roots: FxHashSet::default(),
+            is_outdated: false,
+            modules_with_diagnostics: FxHashSet::default(),
+            index,
+        }
+    }
+    pub fn save_index(&self) {
+        self.index.save();
+    }
+
+    pub fn module_changed(&mut self, module: &Module) {
+        if matches!(module.package, Package::User(_) | Package::Managed(_)) {
+            self.roots.insert(Module {
+                package: module.package.clone(),
+                path: vec![],
+                kind: ModuleKind::Code,
+            });
+        }
+        self.is_outdated = true;
+    }
+
+    /// Returns the diagnostics of all modules if something changed since the
+    /// last call.
+    ///
+    /// Modules that had diagnostics before but don't have any now are
+    /// contained with an empty list so that their diagnostics get cleared.
+    pub async fn run(
+        &mut self,
+        db: &Database,
+        client: &AnalyzerClient,
+    ) -> Option<FxHashMap<Module, Vec<Diagnostic>>> {
+        if !mem::take(&mut self.is_outdated) {
+            return None;
+        }
+
+        let mut diagnostics: FxHashMap<Module, Vec<Diagnostic>> = FxHashMap::default();
+        let mut progress = None;
+        for (index, root) in self.roots.iter().enumerate() {
+            let root_diagnostics = if let Some(root_diagnostics) = self.index.diagnostics(db, root)
+            {
+                debug!("Reusing persisted diagnostics of {root}.");
+                root_diagnostics
+            } else {
+                let message = format!("Compiling {}", root.package);
+                client.update_status(Some(message.clone())).await;
+                if progress.is_none() {
+                    progress = Some(client.begin_progress("Analyzing project").await);
+                }
+                let percentage = 100 * index / self.roots.len();
+                progress
+                    .as_ref()
+                    .unwrap()
+                    .report(message, percentage.try_into().unwrap())
+                    .await;
+                let Some(root_diagnostics) = Self::compile(db, root) else {
+                    debug!("Couldn't compile {root} for project diagnostics.");
+                    continue;
+                };
+                self.index.update(db, root, root_diagnostics.clone());
+                root_diagnostics
+            };
+            for (module, root_diagnostics) in root_diagnostics {
+                diagnostics
+                    .entry(module)
+                    .or_default()
+                    .extend(root_diagnostics);
+            }
+        }
+        if let Some(progress) = progress {
+            progress.end().await;
+        }
+
+        let previous_modules = mem::replace(
+            &mut self.modules_with_diagnostics,
+            diagnostics.keys().cloned().collect(),
+        );
+        for module in previous_modules {
+            diagnostics.entry(module).or_default();
+        }
+        Some(diagnostics)
+    }
+
+    fn compile(db: &Database, root: &Module) -> Option<FxHashMap<Module, Vec<Diagnostic>>> {
+        let (mir, _) = db
+            .optimized_mir(ExecutionTarget::Module(root.clone()), TracingConfig::off())
+            .ok()?;
+
+        let mut diagnostics: FxHashMap<Module, Vec<Diagnostic>> = FxHashMap::default();
+        for panic in (*mir).clone().static_panics() {
+            let module = panic.responsible.module.clone();
+            if module.package != root.package {
+                continue;
+            }
+
+            let Some(Insight::Diagnostic(diagnostic)) =
+                Insight::for_static_panic(db, module.clone(), &panic)
+            else {
+                continue;
+            };
+            diagnostics.entry(module).or_default().push(diagnostic);
+        }
+        Some(diagnostics)
+    }
+}
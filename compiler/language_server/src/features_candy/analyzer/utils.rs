@@ -15,12 +15,7 @@ where
         let line = self
             .offset_to_lsp_position(id.module.clone(), span.start)
             .line;
-        let line_start_offsets = self.line_start_offsets(id.module.clone());
-        let last_characer_of_line = if line as usize == line_start_offsets.len() - 1 {
-            self.get_module_content(id.module.clone()).unwrap().len()
-        } else {
-            *line_start_offsets[(line + 1) as usize] - 1
-        };
-        Some(self.offset_to_lsp_position(id.module, last_characer_of_line.into()))
+        let last_characer_of_line = self.line_index(id.module.clone()).line_end(line as usize);
+        Some(self.offset_to_lsp_position(id.module, last_characer_of_line))
     }
 }
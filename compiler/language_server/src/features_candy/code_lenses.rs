@@ -0,0 +1,61 @@
+use crate::{
+    features_candy::execution::{FUZZ_FUNCTION_COMMAND, RUN_MAIN_COMMAND},
+    utils::LspPositionConversion,
+};
+use candy_frontend::{
+    ast_to_hir::AstToHir,
+    hir::{Expression, Function},
+    module::Module,
+    position::PositionConversionDb,
+};
+use itertools::Itertools;
+use lsp_types::{CodeLens, Command, Url};
+use serde_json::json;
+
+/// Lenses for running the `main` function and fuzzing the top-level fuzzable
+/// functions of a module.
+pub fn code_lenses<DB>(db: &DB, module: &Module, uri: &Url) -> Vec<CodeLens>
+where
+    DB: AstToHir + PositionConversionDb,
+{
+    let Ok((body, _)) = db.hir(module.clone()) else {
+        return vec![];
+    };
+
+    let mut lenses = vec![];
+    for (id, expression) in &body.expressions {
+        let Some(name) = body.identifiers.get(id) else {
+            continue;
+        };
+        let Some(span) = db.hir_id_to_span(id) else {
+            continue;
+        };
+        let range = db.range_to_lsp_range(module.clone(), span);
+
+        if name == "main" {
+            lenses.push(CodeLens {
+                range,
+                command: Some(Command {
+                    title: "▶ Run".to_string(),
+                    command: RUN_MAIN_COMMAND.to_string(),
+                    arguments: Some(vec![json!(uri)]),
+                }),
+                data: None,
+            });
+        }
+        if let Expression::Function(Function { kind, .. }) = expression
+            && kind.is_fuzzable()
+        {
+            lenses.push(CodeLens {
+                range,
+                command: Some(Command {
+                    title: "🧪 Fuzz".to_string(),
+                    command: FUZZ_FUNCTION_COMMAND.to_string(),
+                    arguments: Some(vec![json!(uri), json!(id.keys.iter().join(":"))]),
+                }),
+                data: None,
+            });
+        }
+    }
+    lenses
+}
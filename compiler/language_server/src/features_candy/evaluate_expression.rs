@@ -0,0 +1,167 @@
+//! Evaluates the selected expression for the client's "Evaluate selection"
+//! action.
+//!
+//! If the hints server already evaluated the expression while analyzing the
+//! module, we reuse its value. Otherwise, we run the module on a separate
+//! thread with tracing of evaluated expressions enabled.
+
+use crate::{
+    database::Database,
+    server::Server,
+    utils::{lsp_range_to_range_raw, module_from_url},
+};
+use candy_frontend::{
+    ast_to_hir::AstToHir,
+    cst::FindCstPath,
+    format::{MaxLength, Precedence},
+    hir,
+    hir_to_mir::ExecutionTarget,
+    module::{Module, ModuleDb, ModuleKind, MutableModuleProviderOwner},
+    position::Offset,
+    rcst_to_cst::RcstToCst,
+    tracing::CallTracingMode,
+    TracingConfig, TracingMode,
+};
+use candy_vm::{
+    environment::{EmptyEnvironment, StateAfterRunWithoutHandles},
+    execution_controller::WallClockController,
+    heap::{Heap, ToDebugText},
+    lir_to_byte_code::compile_byte_code,
+    tracer::evaluated_values::EvaluatedValuesTracer,
+    Vm, VmFinished,
+};
+use lsp_types::TextDocumentIdentifier;
+use serde::{Deserialize, Serialize};
+use std::{ops::Range, time::Duration};
+use tokio::task;
+use tower_lsp::jsonrpc;
+
+/// How long evaluating the module may take if the hints server didn't
+/// evaluate the expression yet.
+const EVALUATION_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvaluateExpressionParams {
+    pub text_document: TextDocumentIdentifier,
+    pub range: lsp_types::Range,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum EvaluateExpressionResult {
+    Value { text: String },
+    Panic { reason: String, responsible: String },
+    NotEvaluated { reason: String },
+}
+
+impl Server {
+    pub async fn candy_evaluate_expression(
+        &self,
+        params: EvaluateExpressionParams,
+    ) -> jsonrpc::Result<EvaluateExpressionResult> {
+        let uri = params.text_document.uri;
+        let packages_path = self.require_running_state().await.packages_path.clone();
+        let module = module_from_url(&uri, ModuleKind::Code, &packages_path)
+            .map_err(jsonrpc::Error::invalid_params)?;
+
+        let (id, content) = {
+            let db = self.db.lock().await;
+            let Some(content) = db.get_module_content(module.clone()) else {
+                return Err(jsonrpc::Error::invalid_params(
+                    "The document doesn't exist.",
+                ));
+            };
+            let text = String::from_utf8_lossy(&content);
+            let range = lsp_range_to_range_raw(&text, params.range);
+            let id = find_expression(&db, &module, &text, range);
+            (id, (*content).clone())
+        };
+        let Some(id) = id else {
+            return Ok(EvaluateExpressionResult::NotEvaluated {
+                reason: "The selection isn't an expression.".to_string(),
+            });
+        };
+
+        let cached_value = self
+            .require_features()
+            .await
+            .candy
+            .evaluated_value(id.clone())
+            .await;
+        if let Some(text) = cached_value {
+            return Ok(EvaluateExpressionResult::Value { text });
+        }
+
+        task::spawn_blocking(move || {
+            let mut db = Database::new_with_file_system_module_provider(packages_path);
+            db.did_open_module(&module, content);
+            evaluate(&db, module, &id)
+        })
+        .await
+        .map_err(|_| jsonrpc::Error::internal_error())
+    }
+}
+
+/// Finds the innermost expression containing the selection, ignoring
+/// surrounding whitespace.
+fn find_expression(
+    db: &Database,
+    module: &Module,
+    text: &str,
+    range: Range<Offset>,
+) -> Option<hir::Id> {
+    let selection = &text[*range.start..*range.end];
+    let start = *range.start + (selection.len() - selection.trim_start().len());
+    let end = start.max(*range.end - (selection.len() - selection.trim_end().len()));
+    let range = Offset(start)..Offset(end);
+
+    let csts = db.cst(module.clone()).ok()?;
+    csts.find_path_enclosing(&range)
+        .nodes()
+        .iter()
+        .rev()
+        .find_map(|cst| db.cst_to_last_hir_id(module.clone(), cst.data.id))
+}
+
+fn evaluate(db: &Database, module: Module, id: &hir::Id) -> EvaluateExpressionResult {
+    let tracing = TracingConfig {
+        register_fuzzables: TracingMode::Off,
+        calls: CallTracingMode::Off,
+        evaluated_expressions: TracingMode::OnlyCurrent,
+        memoize_calls: false,
+        overrides: vec![],
+    };
+    let (byte_code, _) = compile_byte_code(db, ExecutionTarget::Module(module.clone()), tracing);
+
+    let mut heap = Heap::default();
+    let vm = Vm::for_module(&byte_code, &mut heap, EvaluatedValuesTracer::new(module));
+    let mut controller = WallClockController::new(EVALUATION_TIMEOUT);
+    let VmFinished { tracer, result } =
+        match vm.run_with_controller(&mut heap, &mut EmptyEnvironment, &mut controller) {
+            StateAfterRunWithoutHandles::Running(_) => {
+                return EvaluateExpressionResult::NotEvaluated {
+                    reason: format!(
+                        "Evaluating the module took longer than {} seconds.",
+                        EVALUATION_TIMEOUT.as_secs(),
+                    ),
+                }
+            }
+            StateAfterRunWithoutHandles::Finished(finished) => finished,
+        };
+
+    if let Some(value) = tracer.values().get(id) {
+        return EvaluateExpressionResult::Value {
+            text: value.to_debug_text(Precedence::Low, MaxLength::Unlimited),
+        };
+    }
+    match result {
+        Err(panic) => EvaluateExpressionResult::Panic {
+            reason: panic.reason,
+            responsible: panic.responsible.to_string(),
+        },
+        Ok(_) => EvaluateExpressionResult::NotEvaluated {
+            reason: "The expression wasn't evaluated when running the module, e.g., because it's in a function that isn't called.".to_string(),
+        },
+    }
+}
@@ -0,0 +1,251 @@
+//! Runs `main` functions and fuzzes single functions on behalf of the client,
+//! e.g., when the user clicks on a code lens.
+//!
+//! Executions run on a separate thread with their own database. They stream
+//! their output and result back to the client using notifications containing
+//! the execution ID that `workspace/executeCommand` returned.
+
+use crate::{database::Database, utils::module_from_url};
+use candy_frontend::{
+    format::{MaxLength, Precedence},
+    hir_to_mir::ExecutionTarget,
+    module::{Module, ModuleDb, ModuleKind, MutableModuleProviderOwner},
+    TracingConfig,
+};
+use candy_fuzzer::FuzzingOptions;
+use candy_vm::{
+    byte_code::ByteCode,
+    environment::{Environment, LogHandles},
+    heap::{Handle, Heap, Struct, Tag, ToDebugText},
+    lir_to_byte_code::compile_byte_code,
+    tracer::Tracer,
+    Runtime, RuntimeError, Vm, VmHandleCall,
+};
+use lsp_types::{notification::Notification, ExecuteCommandParams, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    borrow::Borrow,
+    sync::atomic::{AtomicU32, Ordering},
+};
+use tokio::{runtime::Handle as RuntimeHandle, task};
+use tower_lsp::Client;
+
+pub const RUN_MAIN_COMMAND: &str = "candy.runMain";
+pub const FUZZ_FUNCTION_COMMAND: &str = "candy.fuzzFunction";
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionOutputNotification {
+    pub execution_id: u32,
+    pub text: String,
+}
+impl Notification for ExecutionOutputNotification {
+    const METHOD: &'static str = "candy/execution/output";
+
+    type Params = Self;
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionFinishedNotification {
+    pub execution_id: u32,
+    pub succeeded: bool,
+    pub message: String,
+}
+impl Notification for ExecutionFinishedNotification {
+    const METHOD: &'static str = "candy/execution/finished";
+
+    type Params = Self;
+}
+
+#[derive(Debug)]
+pub enum Execution {
+    RunMain {
+        uri: Url,
+    },
+    /// `function` is the function's HIR ID without the module, e.g.,
+    /// `foo:bar`.
+    FuzzFunction {
+        uri: Url,
+        function: String,
+    },
+}
+impl Execution {
+    pub fn from_command(params: ExecuteCommandParams) -> Result<Self, String> {
+        let mut arguments = params.arguments.into_iter();
+        let mut next_argument = |name: &str| match arguments.next() {
+            Some(Value::String(argument)) => Ok(argument),
+            _ => Err(format!("`{}` expects a {name}.", params.command)),
+        };
+        let uri = next_argument("document URI")?;
+        let uri = Url::parse(&uri).map_err(|error| format!("Invalid document URI: {error}"))?;
+        match params.command.as_str() {
+            RUN_MAIN_COMMAND => Ok(Self::RunMain { uri }),
+            FUZZ_FUNCTION_COMMAND => Ok(Self::FuzzFunction {
+                uri,
+                function: next_argument("function")?,
+            }),
+            command => Err(format!("Unknown command `{command}`.")),
+        }
+    }
+
+    const fn uri(&self) -> &Url {
+        match self {
+            Self::RunMain { uri } | Self::FuzzFunction { uri, .. } => uri,
+        }
+    }
+
+    /// Starts the execution in the background and returns its ID.
+    ///
+    /// The execution uses the module's current content from `db`, which might
+    /// not be saved yet.
+    pub fn start(self, db: &Database, client: Client) -> Result<u32, String> {
+        static NEXT_EXECUTION_ID: AtomicU32 = AtomicU32::new(0);
+
+        let packages_path = db.packages_path.clone();
+        let module = module_from_url(self.uri(), ModuleKind::Code, &packages_path)?;
+        let content = db.get_module_content(module.clone());
+        let execution_id = NEXT_EXECUTION_ID.fetch_add(1, Ordering::Relaxed);
+        let runtime = RuntimeHandle::current();
+        task::spawn_blocking(move || {
+            let mut db = Database::new_with_file_system_module_provider(packages_path);
+            if let Some(content) = content {
+                db.did_open_module(&module, (*content).clone());
+            }
+
+            let send_output = |text: String| {
+                runtime.block_on(client.send_notification::<ExecutionOutputNotification>(
+                    ExecutionOutputNotification { execution_id, text },
+                ));
+            };
+            let (succeeded, message) = match self {
+                Self::RunMain { .. } => run_main(&db, module, send_output),
+                Self::FuzzFunction { function, .. } => fuzz_function(&db, module, &function),
+            };
+            runtime.block_on(client.send_notification::<ExecutionFinishedNotification>(
+                ExecutionFinishedNotification {
+                    execution_id,
+                    succeeded,
+                    message,
+                },
+            ));
+        });
+        Ok(execution_id)
+    }
+}
+
+fn run_main(db: &Database, module: Module, send_output: impl FnMut(String)) -> (bool, String) {
+    let (byte_code, _) =
+        compile_byte_code(db, ExecutionTarget::Module(module), TracingConfig::off());
+    let mut runtime = Runtime::with_environment(byte_code, |heap| {
+        LanguageServerEnvironment::new(heap, Box::new(send_output))
+    });
+    match runtime.run_main() {
+        Ok(return_value) => {
+            let message = format!(
+                "`main` returned {}.",
+                return_value.to_debug_text(Precedence::Low, MaxLength::Limited(100)),
+            );
+            return_value.drop(runtime.heap_mut());
+            (true, message)
+        }
+        Err(RuntimeError::UnknownExport(_)) => (
+            false,
+            "The module doesn't export a main function.".to_string(),
+        ),
+        Err(RuntimeError::NotConvertible(reason)) => (
+            false,
+            format!("The module's `main` isn't a function: {reason}"),
+        ),
+        Err(error @ RuntimeError::LimitExceeded { .. }) => (false, error.to_string()),
+        Err(RuntimeError::Panicked { panic, .. }) => (
+            false,
+            format!(
+                "The program panicked: {} ({} is responsible.)",
+                panic.reason, panic.responsible,
+            ),
+        ),
+    }
+}
+
+fn fuzz_function(db: &Database, module: Module, function: &str) -> (bool, String) {
+    let options = FuzzingOptions {
+        function: Some(function.to_string()),
+        ..FuzzingOptions::default()
+    };
+    let report = candy_fuzzer::fuzz(db, module, &options);
+    let Some(fuzzed) = report.fuzzed_functions.first() else {
+        return (false, format!("`{function}` isn't a fuzzable function."));
+    };
+    let Some(case) = &fuzzed.failing_case else {
+        return (
+            true,
+            format!(
+                "No panics found after running {} instructions ({:.1} % coverage).",
+                fuzzed.instructions,
+                fuzzed.coverage.unwrap_or_default() * 100.0,
+            ),
+        );
+    };
+    (
+        false,
+        format!(
+            "Calling `{function} {}` panics: {} ({} is responsible.)",
+            case.input().to_debug_text(MaxLength::Limited(100)),
+            case.panic().reason,
+            case.panic().responsible,
+        ),
+    )
+}
+
+/// Only provides `stdout` and `log` (sending their output to the client) and
+/// empty `arguments`. Other handles, such as `stdin`, would interfere with the
+/// language server's communication.
+struct LanguageServerEnvironment<'a> {
+    stdout_handle: Handle,
+    log_handles: LogHandles,
+    send_output: Box<dyn FnMut(String) + 'a>,
+}
+impl<'a> LanguageServerEnvironment<'a> {
+    fn new(heap: &mut Heap, send_output: Box<dyn FnMut(String) + 'a>) -> (Struct, Self) {
+        let arguments = candy_vm::heap::List::create(heap, true, &[]);
+        let stdout_handle = Handle::new(heap, 1);
+        let (log_object, log_handles) = LogHandles::new(heap);
+        let environment_object = Struct::create_with_symbol_keys(
+            heap,
+            true,
+            [
+                (heap.default_symbols().arguments, arguments.into()),
+                (heap.default_symbols().stdout, **stdout_handle),
+                (heap.default_symbols().log, log_object.into()),
+            ],
+        );
+        let environment = Self {
+            stdout_handle,
+            log_handles,
+            send_output,
+        };
+        (environment_object, environment)
+    }
+}
+impl Environment for LanguageServerEnvironment<'_> {
+    fn handle<B: Borrow<ByteCode>, T: Tracer>(
+        &mut self,
+        heap: &mut Heap,
+        call: VmHandleCall<B, T>,
+    ) -> Vm<B, T> {
+        let [message] = call.arguments.as_slice() else {
+            unreachable!()
+        };
+        let message = LogHandles::message_to_string(*message);
+        let text = if let Some(level) = self.log_handles.level_of(call.handle) {
+            format!("[{level}] {message}")
+        } else {
+            assert_eq!(call.handle, self.stdout_handle);
+            message
+        };
+        (self.send_output)(text);
+        call.complete(heap, Tag::create_nothing(heap))
+    }
+}
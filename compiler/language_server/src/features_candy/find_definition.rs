@@ -5,8 +5,8 @@ use crate::{
 use candy_frontend::{
     ast_to_hir::AstToHir,
     cst::{CstDb, CstKind},
-    hir::{Expression, HirDb},
-    module::Module,
+    hir::{Expression, HirDb, Id, Pattern},
+    module::{Module, ModuleKind, UsePath},
     position::Offset,
 };
 use lsp_types::LocationLink;
@@ -26,14 +26,129 @@ pub fn find_definition(db: &Database, module: Module, offset: Offset) -> Option<
     let Expression::Reference(target_hir_id) = origin_expression else {
         return None;
     };
+    let origin_selection_range = Some(db.range_to_lsp_range(module, origin_cst.data.span));
+
+    if let Some(used_module) = find_used_module(db, &target_hir_id) {
+        // Modules don't have a definition, so we jump to their beginning.
+        let start = db.range_to_lsp_range(used_module.clone(), Offset(0)..Offset(0));
+        return Some(LocationLink {
+            origin_selection_range,
+            target_uri: module_to_url(&used_module, &db.packages_path)?,
+            target_range: start,
+            target_selection_range: start,
+        });
+    }
+
+    let target_hir_id = find_imported_definition(db, &target_hir_id).unwrap_or(target_hir_id);
+    let target_module = target_hir_id.module.clone();
     let target_cst_id = db.hir_to_cst_id(&target_hir_id)?;
-    let target_cst = db.find_cst(module.clone(), target_cst_id);
+    let target_cst = db.find_cst(target_module.clone(), target_cst_id);
     debug!("Target CST: {target_cst:?}");
 
     Some(LocationLink {
-        origin_selection_range: Some(db.range_to_lsp_range(module.clone(), origin_cst.data.span)),
-        target_uri: module_to_url(&module, &db.packages_path).unwrap(),
-        target_range: db.range_to_lsp_range(module.clone(), target_cst.data.span.clone()),
-        target_selection_range: db.range_to_lsp_range(module, target_cst.display_span()),
+        origin_selection_range,
+        target_uri: module_to_url(&target_module, &db.packages_path)?,
+        target_range: db.range_to_lsp_range(target_module.clone(), target_cst.data.span.clone()),
+        target_selection_range: db.range_to_lsp_range(target_module, target_cst.display_span()),
+    })
+}
+
+/// If the `id` refers to the result of a `use` call (like `foo` in
+/// `foo = use "..foo"`), returns the used module.
+pub fn find_used_module<DB: HirDb + ?Sized>(db: &DB, id: &Id) -> Option<Module> {
+    let mut id = id.clone();
+    loop {
+        match db.find_expression(id.clone())? {
+            Expression::Reference(target) => id = target,
+            Expression::Call {
+                function,
+                arguments,
+            } => {
+                let [path] = arguments.as_slice() else {
+                    return None;
+                };
+                let Expression::Text(path) = db.find_expression(path.clone())? else {
+                    return None;
+                };
+                let current_module = find_use_function_module(db, function)?;
+                let module = UsePath::parse(&path)
+                    .ok()?
+                    .resolve_relative_to(current_module)
+                    .ok()?;
+                return (module.kind == ModuleKind::Code).then_some(module);
+            }
+            _ => return None,
+        }
+    }
+}
+/// The `use` function is generated for every module and contains an
+/// [`Expression::UseModule`] that knows the module from which other modules
+/// are resolved.
+fn find_use_function_module<DB: HirDb + ?Sized>(db: &DB, mut function: Id) -> Option<Module> {
+    loop {
+        match db.find_expression(function.clone())? {
+            Expression::Reference(target) => function = target,
+            Expression::Function(function) => {
+                return function.body.expressions.values().find_map(
+                    |expression| match expression {
+                        Expression::UseModule { current_module, .. } => {
+                            Some(current_module.clone())
+                        }
+                        _ => None,
+                    },
+                );
+            }
+            _ => return None,
+        }
+    }
+}
+
+/// If the `id` refers to an identifier that's destructured from a used module
+/// (like `foo` in `[foo] = use "..bar"`), returns the ID of its public
+/// definition in that module.
+pub fn find_imported_definition<DB: HirDb + ?Sized>(db: &DB, id: &Id) -> Option<Id> {
+    let Expression::PatternIdentifierReference(identifier_id) = db.find_expression(id.clone())?
+    else {
+        return None;
+    };
+
+    let body = db.containing_body_of(id.clone());
+    let (used_module, symbol) =
+        body.expressions
+            .values()
+            .find_map(|expression| match expression {
+                Expression::Destructure {
+                    expression,
+                    pattern: Pattern::Struct(fields),
+                } => fields.iter().find_map(|(key, value)| match (key, value) {
+                    (
+                        Pattern::Tag {
+                            symbol,
+                            value: None,
+                        },
+                        Pattern::NewIdentifier(it),
+                    ) if *it == identifier_id => {
+                        Some((find_used_module(db, expression)?, symbol.clone()))
+                    }
+                    _ => None,
+                }),
+                _ => None,
+            })?;
+    find_exported_definition(db, used_module, &symbol)
+}
+/// Returns the ID of the definition that `module` exports as `symbol`.
+pub fn find_exported_definition<DB: HirDb + ?Sized>(
+    db: &DB,
+    module: Module,
+    symbol: &str,
+) -> Option<Id> {
+    // The last expression of a module is the struct containing its exports.
+    let (body, _) = db.hir(module).ok()?;
+    let Some((_, Expression::Struct(exports))) = body.expressions.iter().last() else {
+        return None;
+    };
+    exports.iter().find_map(|(key, value)| {
+        matches!(body.find(key), Some(Expression::Symbol(it)) if it == symbol)
+            .then(|| value.clone())
     })
 }
@@ -1,25 +1,46 @@
 use std::ops::Range;
 
 use candy_frontend::{
-    cst::{Cst, CstKind, UnwrapWhitespaceAndComment},
+    cst::{Cst, CstKind, IsMultiline, UnwrapWhitespaceAndComment},
     module::{Module, ModuleDb},
     position::{Offset, PositionConversionDb},
     rcst_to_cst::RcstToCst,
 };
 use lsp_types::{FoldingRange, FoldingRangeKind};
 
-use crate::utils::LspPositionConversion;
+use crate::{cancellation::CancellationToken, utils::LspPositionConversion};
 
 pub fn folding_ranges<DB: ModuleDb + PositionConversionDb + RcstToCst>(
     db: &DB,
     module: Module,
+    cancellation: &CancellationToken,
 ) -> Vec<FoldingRange> {
     let mut context = Context::new(db, module.clone());
     let cst = db.cst(module).unwrap();
-    context.visit_csts(&cst);
+    for cst in cst.iter() {
+        if cancellation.is_cancelled() {
+            break;
+        }
+        context.visit_cst(cst);
+    }
+
+    let mut comments = vec![];
+    collect_comments(&cst.iter().collect::<Vec<_>>(), &mut comments);
+    context.push_comments(&comments);
+
     context.ranges
 }
 
+fn collect_comments<'a>(csts: &[&'a Cst], comments: &mut Vec<(Range<Offset>, &'a str)>) {
+    for cst in csts {
+        if let CstKind::Comment { comment, .. } = &cst.kind {
+            comments.push((cst.data.span.clone(), comment));
+        } else {
+            collect_comments(&cst.kind.children(), comments);
+        }
+    }
+}
+
 struct Context<'a, DB: ModuleDb + PositionConversionDb + ?Sized> {
     db: &'a DB,
     module: Module,
@@ -61,24 +82,55 @@ where
             | CstKind::DoubleQuote
             | CstKind::Percent
             | CstKind::Octothorpe
+            | CstKind::IfKeyword
+            | CstKind::ThenKeyword
+            | CstKind::ElseKeyword
+            | CstKind::BinaryOperator(_)
             | CstKind::Whitespace(_)
             | CstKind::Newline(_) => {}
-            // TODO: support folding ranges for comments
+            // Comments are folded separately since they can appear in
+            // whitespace anywhere.
             CstKind::Comment { .. } => {}
             CstKind::TrailingWhitespace { child, .. } => self.visit_cst(child),
-            CstKind::Identifier(_) | CstKind::Symbol(_) | CstKind::Int { .. } => {}
-            // TODO: support folding ranges for multiline texts
+            CstKind::Identifier(_)
+            | CstKind::Symbol(_)
+            | CstKind::Int { .. }
+            | CstKind::Float { .. } => {}
             CstKind::OpeningText { .. }
             | CstKind::ClosingText { .. }
-            | CstKind::Text { .. }
             | CstKind::TextNewline(_)
             | CstKind::TextPart(_)
-            | CstKind::TextInterpolation { .. } => {}
+            | CstKind::TextEscape { .. } => {}
+            CstKind::Text {
+                opening,
+                parts,
+                closing,
+            } => {
+                if cst.is_multiline() {
+                    let opening = opening.unwrap_whitespace_and_comment();
+                    let closing = closing.unwrap_whitespace_and_comment();
+                    self.push(
+                        opening.data.span.end..closing.data.span.start,
+                        FoldingRangeKind::Region,
+                    );
+                }
+                self.visit_csts(parts);
+            }
+            CstKind::TextInterpolation { expression, .. } => self.visit_cst(expression),
             CstKind::BinaryBar { left, bar, right } => {
                 self.visit_cst(left);
                 self.visit_cst(bar);
                 self.visit_cst(right);
             }
+            CstKind::BinaryOperation {
+                left,
+                operator,
+                right,
+            } => {
+                self.visit_cst(left);
+                self.visit_cst(operator);
+                self.visit_cst(right);
+            }
             CstKind::Parenthesized { inner, .. } => self.visit_cst(inner),
             CstKind::Call {
                 receiver,
@@ -135,6 +187,21 @@ where
 
                 self.visit_csts(cases);
             }
+            CstKind::If {
+                if_keyword,
+                condition,
+                then_keyword,
+                then,
+                else_keyword,
+                else_,
+            } => {
+                self.visit_cst(if_keyword);
+                self.visit_cst(condition);
+                self.visit_cst(then_keyword);
+                self.visit_cst(then);
+                self.visit_cst(else_keyword);
+                self.visit_cst(else_);
+            }
             CstKind::MatchCase {
                 pattern,
                 arrow,
@@ -199,6 +266,57 @@ where
         }
     }
 
+    /// Folds blocks of consecutive comment lines and the code between
+    /// `# region` and `# endregion` markers.
+    fn push_comments(&mut self, comments: &[(Range<Offset>, &str)]) {
+        let text = self
+            .db
+            .get_module_content_as_string(self.module.clone())
+            .unwrap();
+        let mut region_starts = vec![];
+        let mut block: Option<Range<Offset>> = None;
+        for (span, content) in comments {
+            let span = span.clone();
+            let line_start = text[..*span.start].rfind('\n').map_or(0, |it| it + 1);
+            let is_on_own_line = text[line_start..*span.start].trim().is_empty();
+
+            let marker = RegionMarker::parse(content);
+            let continues_block = block.as_ref().is_some_and(|block| {
+                let between = &text[*block.end..*span.start];
+                between.trim().is_empty() && between.matches('\n').count() == 1
+            });
+            if marker.is_none() && is_on_own_line && continues_block {
+                block.as_mut().unwrap().end = span.end;
+                continue;
+            }
+            if let Some(block) = block.take() {
+                self.push_comment_block(block);
+            }
+
+            match marker {
+                Some(RegionMarker::Start) => region_starts.push(span.start),
+                Some(RegionMarker::End) => {
+                    if let Some(start) = region_starts.pop() {
+                        self.push(start..span.end, FoldingRangeKind::Region);
+                    }
+                }
+                None if is_on_own_line => block = Some(span),
+                None => {}
+            }
+        }
+        if let Some(block) = block {
+            self.push_comment_block(block);
+        }
+    }
+    fn push_comment_block(&mut self, block: Range<Offset>) {
+        let range = self
+            .db
+            .range_to_lsp_range(self.module.clone(), block.clone());
+        if range.start.line != range.end.line {
+            self.push(block, FoldingRangeKind::Comment);
+        }
+    }
+
     fn push(&mut self, range: Range<Offset>, kind: FoldingRangeKind) {
         let range = self.db.range_to_lsp_range(self.module.clone(), range);
         self.ranges.push(FoldingRange {
@@ -212,3 +330,22 @@ where
         });
     }
 }
+
+enum RegionMarker {
+    Start,
+    End,
+}
+impl RegionMarker {
+    /// Parses comments like `# region Helpers` and `# endregion`.
+    fn parse(comment: &str) -> Option<Self> {
+        let comment = comment.trim_start();
+        let word = comment
+            .split_once(char::is_whitespace)
+            .map_or(comment, |(word, _)| word);
+        match word {
+            "region" => Some(Self::Start),
+            "endregion" => Some(Self::End),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use super::{
+    find_definition::{find_imported_definition, find_used_module},
+    signature_help::{find_parameter_names, find_struct_access_target},
+};
+use crate::{database::Database, utils::LspPositionConversion};
+use candy_frontend::{
+    ast_to_hir::AstToHir,
+    cst::{CstDb, CstKind, FindCstPath, UnwrapWhitespaceAndComment},
+    cst_to_ast::CstToAst,
+    hir::{Expression, HirDb, Id},
+    module::Module,
+    position::Offset,
+    rcst_to_cst::RcstToCst,
+};
+use itertools::Itertools;
+use lsp_types::{Hover, HoverContents, MarkupContent, MarkupKind};
+use std::iter;
+use tracing::debug;
+
+/// Shows the signature and documentation of the hovered identifier, or the
+/// documentation of the module it refers to.
+pub fn hover(db: &Database, module: Module, offset: Offset) -> Option<Hover> {
+    let cst = db.find_cst_by_offset(module.clone(), offset);
+    let CstKind::Identifier(name) = &cst.kind else {
+        return None;
+    };
+    debug!("Hovering over {name}");
+
+    // For struct accesses like `foo.bar`, the key is lowered to a symbol, so we
+    // look at the whole access instead.
+    let csts = db.cst(module.clone()).ok()?;
+    let cst_id = csts
+        .find_path(cst.data.id)
+        .ancestors()
+        .find(|it| match &it.kind {
+            CstKind::StructAccess { key, .. } => {
+                key.unwrap_whitespace_and_comment().data.id == cst.data.id
+            }
+            _ => false,
+        })
+        .map_or(cst.data.id, |it| it.data.id);
+    let id = db.cst_to_last_hir_id(module.clone(), cst_id)?;
+    let mut sections = vec![];
+    if let Some(used_module) = find_used_module(db, &id) {
+        sections.push(format!("```\n{used_module}\n```"));
+        sections.extend(db.module_documentation(used_module));
+    } else {
+        if let Some(parameters) = find_parameter_names(db, id.clone()) {
+            let signature = iter::once(name.clone()).chain(parameters).join(" ");
+            sections.push(format!("```candy\n{signature}\n```"));
+        }
+        sections.extend(find_documentation(db, id));
+    }
+    if sections.is_empty() {
+        return None;
+    }
+
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: sections.join("\n\n"),
+        }),
+        range: Some(db.range_to_lsp_range(module, cst.data.span)),
+    })
+}
+
+/// Follows references, imports, and struct accesses until reaching a
+/// documented definition.
+pub fn find_documentation(db: &Database, mut id: Id) -> Option<String> {
+    loop {
+        if let Some(documentation) = db.containing_body_of(id.clone()).documentation.get(&id) {
+            return Some(documentation.clone());
+        }
+        id = match db.find_expression(id.clone())? {
+            Expression::Reference(target) => target,
+            Expression::PatternIdentifierReference(_) => find_imported_definition(db, &id)?,
+            Expression::Call {
+                function,
+                arguments,
+            } => find_struct_access_target(db, &function, &arguments)?,
+            _ => return None,
+        };
+    }
+}
@@ -1,36 +1,55 @@
 use self::{
+    analyzer::persisted_index::PersistedIndex,
+    code_lenses::code_lenses,
     find_definition::find_definition,
     folding_ranges::folding_ranges,
-    references::{reference_query_for_offset, references, ReferenceQuery},
+    hover::hover,
+    references::{reference_query_for_offset, references, ReferenceQuery, ReferencesIndexDb},
+    selection_ranges::selection_range,
     semantic_tokens::semantic_tokens,
+    signature_help::signature_help,
 };
 use crate::{
+    cancellation::{run_cancellable, CancellationToken},
+    config::{FormatterSettings, ServerConfig},
     database::Database,
     features::{LanguageFeatures, Reference, RenameError},
     server::AnalyzerClient,
-    utils::{lsp_range_to_range_raw, module_from_url, LspPositionConversion},
+    utils::{lsp_range_to_range_raw, module_from_url, module_to_url, LspPositionConversion},
 };
 use async_trait::async_trait;
-use candy_formatter::Formatter;
+use candy_formatter::{new_line_indentation, Formatter, FormatterConfig};
 use candy_frontend::{
+    hir,
     module::{Module, ModuleDb, ModuleKind, MutableModuleProviderOwner, PackagesPath},
+    position::Offset,
     rcst_to_cst::RcstToCst,
 };
 use lsp_types::{
-    notification::Notification, FoldingRange, LocationLink, SemanticToken,
+    notification::Notification, CodeLens, FoldingRange, FormattingOptions, FormattingProperty,
+    Hover, LinkedEditingRanges, LocationLink, SelectionRange, SemanticToken, SignatureHelp,
     TextDocumentContentChangeEvent, TextEdit, Url,
 };
 use regex::Regex;
 use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, thread};
-use tokio::sync::{mpsc::Sender, Mutex};
+use std::{collections::HashMap, path::Path, sync::Arc, thread};
+use tokio::sync::{mpsc::Sender, oneshot, Mutex, RwLock};
+use tracing::warn;
 
 pub mod analyzer;
+pub mod code_lenses;
+pub mod evaluate_expression;
+pub mod execution;
 pub mod find_definition;
 pub mod folding_ranges;
+pub mod hover;
 pub mod references;
+pub mod selection_ranges;
 pub mod semantic_tokens;
+pub mod signature_help;
+
+const IDENTIFIER_PATTERN: &str = "[a-z][A-Za-z0-9_]*";
 
 #[derive(Serialize, Deserialize)]
 pub struct ServerStatusNotification {
@@ -45,6 +64,7 @@ impl Notification for ServerStatusNotification {
 #[derive(Debug)]
 pub struct CandyFeatures {
     hints_events_sender: Sender<analyzer::Message>,
+    formatter_settings: RwLock<FormatterSettings>,
 }
 impl CandyFeatures {
     #[must_use]
@@ -55,6 +75,7 @@ impl CandyFeatures {
         });
         Self {
             hints_events_sender,
+            formatter_settings: RwLock::default(),
         }
     }
 
@@ -64,6 +85,13 @@ impl CandyFeatures {
             Err(error) => panic!("Couldn't send message to hints server: {error:?}."),
         }
     }
+    /// Asks the hints server for the value that the expression evaluated to.
+    pub async fn evaluated_value(&self, id: hir::Id) -> Option<String> {
+        let (sender, receiver) = oneshot::channel();
+        self.send_to_analyzer(analyzer::Message::GetEvaluatedValue(id, sender))
+            .await;
+        receiver.await.ok().flatten()
+    }
 }
 
 #[async_trait]
@@ -75,10 +103,24 @@ impl LanguageFeatures for CandyFeatures {
         vec!["file", "untitled"]
     }
 
-    async fn initialize(&self) {}
+    async fn initialize(&self, db: &Mutex<Database>, workspace_root: Option<&Path>) {
+        if let Some(workspace_root) = workspace_root {
+            let index = PersistedIndex::load(workspace_root);
+            db.lock()
+                .await
+                .set_persisted_imported_definitions(index.imported_definitions());
+            self.send_to_analyzer(analyzer::Message::SetIndex(index))
+                .await;
+        }
+    }
     async fn shutdown(&self) {
         self.send_to_analyzer(analyzer::Message::Shutdown).await;
     }
+    async fn update_config(&self, config: &ServerConfig) {
+        *self.formatter_settings.write().await = config.formatter.clone();
+        self.send_to_analyzer(analyzer::Message::UpdateConfig(config.clone()))
+            .await;
+    }
 
     fn supports_did_open(&self) -> bool {
         true
@@ -88,6 +130,7 @@ impl LanguageFeatures for CandyFeatures {
             let mut db = db.lock().await;
             let module = decode_module(&uri, &db.packages_path);
             db.did_open_module(&module, content.clone());
+            db.invalidate_modules_of_package(&module.package);
             module
         };
         self.send_to_analyzer(analyzer::Message::UpdateModule(module, content))
@@ -120,6 +163,7 @@ impl LanguageFeatures for CandyFeatures {
             let mut db = db.lock().await;
             let module = decode_module(&uri, &db.packages_path);
             db.did_close_module(&module);
+            db.invalidate_modules_of_package(&module.package);
             module
         };
         self.send_to_analyzer(analyzer::Message::CloseModule(module))
@@ -129,23 +173,31 @@ impl LanguageFeatures for CandyFeatures {
     fn supports_folding_ranges(&self) -> bool {
         true
     }
-    async fn folding_ranges(&self, db: &Mutex<Database>, uri: Url) -> Vec<FoldingRange> {
-        let db = db.lock().await;
-        let module = decode_module(&uri, &db.packages_path);
-        folding_ranges(&*db, module)
+    async fn folding_ranges(&self, db: &Arc<Mutex<Database>>, uri: Url) -> Vec<FoldingRange> {
+        run_cancellable(db, move |db, cancellation| {
+            let module = decode_module(&uri, &db.packages_path);
+            folding_ranges(db, module, cancellation)
+        })
+        .await
     }
 
     fn supports_format(&self) -> bool {
         true
     }
-    async fn format(&self, db: &Mutex<Database>, uri: Url) -> Vec<TextEdit> {
+    async fn format(
+        &self,
+        db: &Mutex<Database>,
+        uri: Url,
+        options: FormattingOptions,
+    ) -> Vec<TextEdit> {
         let db = db.lock().await;
         let module = decode_module(&uri, &db.packages_path);
         let Ok(cst) = db.cst(module.clone()) else {
             return vec![];
         };
 
-        cst.format_to_edits()
+        let config = formatter_config(&*self.formatter_settings.read().await, &options);
+        cst.format_to_edits_with_config(config)
             .finish()
             .into_iter()
             .map(|it| TextEdit {
@@ -154,6 +206,40 @@ impl LanguageFeatures for CandyFeatures {
             })
             .collect()
     }
+    fn supports_format_on_type(&self) -> bool {
+        true
+    }
+    async fn format_on_type(
+        &self,
+        db: &Mutex<Database>,
+        uri: Url,
+        position: lsp_types::Position,
+        options: FormattingOptions,
+    ) -> Vec<TextEdit> {
+        let db = db.lock().await;
+        let module = decode_module(&uri, &db.packages_path);
+        let (Some(text), Ok(csts)) = (
+            db.get_module_content_as_string(module.clone()),
+            db.cst(module.clone()),
+        ) else {
+            return vec![];
+        };
+        let offset = db.lsp_position_to_offset(module.clone(), position);
+
+        let line_start = text[..*offset].rfind('\n').map_or(0, |it| it + 1);
+        let line = &text[line_start..];
+        let indentation_end = line_start + line.len() - line.trim_start_matches([' ', '\t']).len();
+        let config = formatter_config(&*self.formatter_settings.read().await, &options);
+        let new_text = new_line_indentation(&text, &csts, offset, config);
+        if text[line_start..indentation_end] == new_text {
+            return vec![];
+        }
+
+        vec![TextEdit {
+            range: db.range_to_lsp_range(module, Offset(line_start)..Offset(indentation_end)),
+            new_text,
+        }]
+    }
 
     fn supports_find_definition(&self) -> bool {
         true
@@ -170,28 +256,104 @@ impl LanguageFeatures for CandyFeatures {
         find_definition(&db, module, offset)
     }
 
+    fn supports_hover(&self) -> bool {
+        true
+    }
+    async fn hover(
+        &self,
+        db: &Mutex<Database>,
+        uri: Url,
+        position: lsp_types::Position,
+    ) -> Option<Hover> {
+        let db = db.lock().await;
+        let module = decode_module(&uri, &db.packages_path);
+        let offset = db.lsp_position_to_offset(module.clone(), position);
+        hover(&db, module, offset)
+    }
+
+    fn supports_selection_ranges(&self) -> bool {
+        true
+    }
+    async fn selection_ranges(
+        &self,
+        db: &Mutex<Database>,
+        uri: Url,
+        positions: Vec<lsp_types::Position>,
+    ) -> Vec<SelectionRange> {
+        let db = db.lock().await;
+        let module = decode_module(&uri, &db.packages_path);
+        positions
+            .into_iter()
+            .map(|position| {
+                let offset = db.lsp_position_to_offset(module.clone(), position);
+                selection_range(&*db, &module, offset)
+            })
+            .collect()
+    }
+
     fn supports_references(&self) -> bool {
         true
     }
     async fn references(
         &self,
-        db: &Mutex<Database>,
+        db: &Arc<Mutex<Database>>,
         uri: Url,
         position: lsp_types::Position,
-        _only_in_same_document: bool,
+        only_in_same_document: bool,
         include_declaration: bool,
     ) -> FxHashMap<Url, Vec<Reference>> {
+        run_cancellable(db, move |db, cancellation| {
+            references_by_url(
+                db,
+                &uri,
+                position,
+                only_in_same_document,
+                include_declaration,
+                cancellation,
+            )
+        })
+        .await
+    }
+
+    fn supports_linked_editing_ranges(&self) -> bool {
+        true
+    }
+    async fn linked_editing_ranges(
+        &self,
+        db: &Mutex<Database>,
+        uri: Url,
+        position: lsp_types::Position,
+    ) -> Option<LinkedEditingRanges> {
         let db = db.lock().await;
         let module = decode_module(&uri, &db.packages_path);
         let offset = db.lsp_position_to_offset(module.clone(), position);
 
-        let mut all_references = FxHashMap::default();
-        let references = references(&*db, module, offset, include_declaration);
-        // TODO: Look for references in all modules
-        if !references.is_empty() {
-            all_references.insert(uri, references);
+        let (ReferenceQuery::Id(id), _) = reference_query_for_offset(&*db, module.clone(), offset)?
+        else {
+            return None;
+        };
+        // Top-level definitions may be used by other modules, so we only link
+        // identifiers that are local to a function.
+        if id.keys.len() < 2 {
+            return None;
         }
-        all_references
+
+        let ranges = references(
+            &*db,
+            module.clone(),
+            offset,
+            true,
+            &[],
+            &CancellationToken::default(),
+        )
+        .remove(&module)?
+        .into_iter()
+        .map(|it| it.range)
+        .collect();
+        Some(LinkedEditingRanges {
+            ranges,
+            word_pattern: Some(IDENTIFIER_PATTERN.to_string()),
+        })
     }
 
     fn supports_rename(&self) -> bool {
@@ -223,29 +385,31 @@ impl LanguageFeatures for CandyFeatures {
         position: lsp_types::Position,
         new_name: String,
     ) -> Result<HashMap<Url, Vec<TextEdit>>, RenameError> {
-        {
-            let db = db.lock().await;
-            let module = decode_module(&uri, &db.packages_path);
-            let offset = db.lsp_position_to_offset(module.clone(), position);
-
-            let regex =
-                match reference_query_for_offset(&*db, module, offset).map(|(query, _)| query) {
-                    Some(ReferenceQuery::Id(_)) => Regex::new(r"^[a-z][A-Za-z0-9_]*$").unwrap(),
-                    Some(
-                        ReferenceQuery::Symbol(_, _)
-                        | ReferenceQuery::Int(_, _)
-                        | ReferenceQuery::Needs(_),
-                    )
-                    | None => {
-                        panic!("Renaming is not supported at this position.")
-                    }
-                };
-            if !regex.is_match(&new_name) {
-                return Err(RenameError::NewNameInvalid);
+        let db = db.lock().await;
+        let module = decode_module(&uri, &db.packages_path);
+        let offset = db.lsp_position_to_offset(module.clone(), position);
+
+        let regex = match reference_query_for_offset(&*db, module, offset).map(|(query, _)| query) {
+            Some(ReferenceQuery::Id(_)) => Regex::new(&format!("^{IDENTIFIER_PATTERN}$")).unwrap(),
+            Some(
+                ReferenceQuery::Symbol(_, _) | ReferenceQuery::Int(_, _) | ReferenceQuery::Needs(_),
+            )
+            | None => {
+                panic!("Renaming is not supported at this position.")
             }
+        };
+        if !regex.is_match(&new_name) {
+            return Err(RenameError::NewNameInvalid);
         }
 
-        let references = self.references(db, uri, position, false, true).await;
+        let references = references_by_url(
+            &db,
+            &uri,
+            position,
+            false,
+            true,
+            &CancellationToken::default(),
+        );
         assert!(!references.is_empty());
         let changes = references
             .into_iter()
@@ -263,14 +427,98 @@ impl LanguageFeatures for CandyFeatures {
         Ok(changes)
     }
 
+    fn supports_signature_help(&self) -> bool {
+        true
+    }
+    async fn signature_help(
+        &self,
+        db: &Mutex<Database>,
+        uri: Url,
+        position: lsp_types::Position,
+    ) -> Option<SignatureHelp> {
+        let db = db.lock().await;
+        let module = decode_module(&uri, &db.packages_path);
+        let offset = db.lsp_position_to_offset(module.clone(), position);
+        signature_help(&db, module, offset)
+    }
+
     fn supports_semantic_tokens(&self) -> bool {
         true
     }
-    async fn semantic_tokens(&self, db: &Mutex<Database>, uri: Url) -> Vec<SemanticToken> {
+    async fn semantic_tokens(&self, db: &Arc<Mutex<Database>>, uri: Url) -> Vec<SemanticToken> {
+        run_cancellable(db, move |db, cancellation| {
+            let module = decode_module(&uri, &db.packages_path);
+            semantic_tokens(db, module, cancellation)
+        })
+        .await
+    }
+
+    fn supports_code_lenses(&self) -> bool {
+        true
+    }
+    async fn code_lenses(&self, db: &Mutex<Database>, uri: Url) -> Vec<CodeLens> {
         let db = db.lock().await;
         let module = decode_module(&uri, &db.packages_path);
-        semantic_tokens(&*db, module)
+        code_lenses(&*db, &module, &uri)
+    }
+}
+
+fn references_by_url(
+    db: &Database,
+    uri: &Url,
+    position: lsp_types::Position,
+    only_in_same_document: bool,
+    include_declaration: bool,
+    cancellation: &CancellationToken,
+) -> FxHashMap<Url, Vec<Reference>> {
+    let module = decode_module(uri, &db.packages_path);
+    let offset = db.lsp_position_to_offset(module.clone(), position);
+
+    let other_modules = if only_in_same_document {
+        Arc::default()
+    } else {
+        db.modules_of_package(module.package.clone())
+    };
+    references(
+        db,
+        module,
+        offset,
+        include_declaration,
+        &other_modules,
+        cancellation,
+    )
+    .into_iter()
+    .filter_map(|(module, references)| {
+        Some((module_to_url(&module, &db.packages_path)?, references))
+    })
+    .collect()
+}
+
+/// Besides the indentation width, clients can pass the custom properties
+/// `maxLineWidth` (a number) and `trailingComma` (`always`, `never`, or
+/// `multiline-only`). These take precedence over the server's `settings`.
+fn formatter_config(settings: &FormatterSettings, options: &FormattingOptions) -> FormatterConfig {
+    let mut config = FormatterConfig {
+        indent_width: options.tab_size.try_into().unwrap(),
+        ..FormatterConfig::default()
+    };
+    if let Some(max_line_width) = settings.max_line_width {
+        config.max_line_width = max_line_width;
+    }
+    if let Some(FormattingProperty::Number(max_line_width)) = options.properties.get("maxLineWidth")
+        && let Ok(max_line_width) = (*max_line_width).try_into()
+    {
+        config.max_line_width = max_line_width;
+    }
+    if let Some(FormattingProperty::String(trailing_comma)) =
+        options.properties.get("trailingComma")
+    {
+        match trailing_comma.parse() {
+            Ok(trailing_comma) => config.trailing_comma = trailing_comma,
+            Err(error) => warn!("{error}"),
+        }
     }
+    config
 }
 
 fn decode_module(uri: &Url, packages_path: &PackagesPath) -> Module {
@@ -1,29 +1,96 @@
-use crate::{features::Reference, utils::LspPositionConversion};
+use super::{analyzer::persisted_index::content_hash, find_definition::find_imported_definition};
+use crate::{cancellation::CancellationToken, features::Reference, utils::LspPositionConversion};
 use candy_frontend::{
-    ast_to_hir::AstToHir,
     cst::{CstDb, CstKind},
     hir::{self, Body, Expression, Function, HirDb},
-    module::{Module, ModuleDb},
+    module::{Module, ModuleDb, Package},
     position::{Offset, PositionConversionDb},
 };
-use num_bigint::BigUint;
-use rustc_hash::FxHashSet;
-use std::ops::Range;
+use num_bigint::BigInt;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::{ops::Range, sync::Arc};
 use tracing::{debug, info};
 
+#[salsa::query_group(ReferencesIndexStorage)]
+pub trait ReferencesIndexDb: HirDb + PersistedImportsOwner {
+    /// Maps identifiers that a module imports from other modules (like `foo`
+    /// in `[foo] = use "..bar"`) to their definitions in those modules.
+    ///
+    /// This is cached per module, so searching references across a package
+    /// only has to look at modules that changed since the last search.
+    fn imported_definitions(&self, module: Module) -> Arc<FxHashMap<hir::Id, hir::Id>>;
+
+    /// All code modules of the `package`, including open modules that are not
+    /// saved to disk yet.
+    ///
+    /// Finding them walks the package's directory, so this is cached until
+    /// the database learns about created or deleted modules.
+    fn modules_of_package(&self, package: Package) -> Arc<Vec<Module>>;
+}
+#[allow(clippy::needless_pass_by_value)]
+fn imported_definitions(
+    db: &dyn ReferencesIndexDb,
+    module: Module,
+) -> Arc<FxHashMap<hir::Id, hir::Id>> {
+    if let Some(persisted) = db.persisted_imported_definitions(&module)
+        && content_hash(db, &module) == Some(persisted.content_hash)
+    {
+        return persisted.definitions.clone();
+    }
+
+    let Ok((hir, _)) = db.hir(module) else {
+        return Arc::default();
+    };
+    let imports = hir
+        .expressions
+        .iter()
+        .filter(|(_, expression)| matches!(expression, Expression::PatternIdentifierReference(_)))
+        .filter_map(|(id, _)| Some((id.clone(), find_imported_definition(db, id)?)))
+        .collect();
+    Arc::new(imports)
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn modules_of_package(db: &dyn ReferencesIndexDb, package: Package) -> Arc<Vec<Module>> {
+    Arc::new(db.get_module_provider().modules_of_package(&package))
+}
+
+/// Databases can provide the imported definitions of a previous session of
+/// the language server so that the first search for references doesn't have
+/// to compile all modules of a package.
+pub trait PersistedImportsOwner {
+    fn persisted_imported_definitions(&self, _module: &Module) -> Option<&PersistedImports> {
+        None
+    }
+}
+#[derive(Clone, Debug)]
+pub struct PersistedImports {
+    /// The hash of the module content from which the `definitions` were
+    /// computed. They're only used if the module didn't change since then.
+    pub content_hash: u64,
+    pub definitions: Arc<FxHashMap<hir::Id, hir::Id>>,
+}
+
+/// Finds references in `module` and, for identifiers, in the `other_modules`
+/// that import them.
+///
+/// If the `cancellation` token is cancelled, the references found so far are
+/// returned.
 pub fn references<DB>(
     db: &DB,
     module: Module,
     offset: Offset,
     include_declaration: bool,
-) -> Vec<Reference>
+    other_modules: &[Module],
+    cancellation: &CancellationToken,
+) -> FxHashMap<Module, Vec<Reference>>
 where
-    DB: HirDb + ModuleDb + PositionConversionDb,
+    DB: ReferencesIndexDb + ModuleDb + PositionConversionDb,
 {
     let Some((query, _)) = reference_query_for_offset(db, module, offset) else {
-        return vec![];
+        return FxHashMap::default();
     };
-    find_references(db, query, include_declaration)
+    find_references(db, query, include_declaration, other_modules, cancellation)
 }
 
 pub fn reference_query_for_offset<DB>(
@@ -53,7 +120,7 @@ where
                         // An intermediate reference. Find references to its target.
                         match hir_expr {
                             Expression::Reference(target_id) => Some(target_id),
-                            Expression::Symbol(_) => {
+                            Expression::Symbol(_) | Expression::Tag { .. } => {
                                 // TODO: Handle struct access
                                 None
                             }
@@ -76,22 +143,88 @@ where
     query
 }
 
-fn find_references<DB>(db: &DB, query: ReferenceQuery, include_declaration: bool) -> Vec<Reference>
+fn find_references<DB>(
+    db: &DB,
+    query: ReferenceQuery,
+    include_declaration: bool,
+    other_modules: &[Module],
+    cancellation: &CancellationToken,
+) -> FxHashMap<Module, Vec<Reference>>
 where
-    DB: AstToHir + HirDb + PositionConversionDb,
+    DB: ReferencesIndexDb + PositionConversionDb,
 {
-    // TODO: search all files
-    let module = match &query {
-        ReferenceQuery::Id(id) => id.module.clone(),
-        ReferenceQuery::Int(module, _) => module.clone(),
-        ReferenceQuery::Symbol(module, _) => module.clone(),
-        ReferenceQuery::Needs(module) => module.clone(),
+    let mut id = match query {
+        ReferenceQuery::Id(id) => id,
+        ReferenceQuery::Int(ref module, _)
+        | ReferenceQuery::Symbol(ref module, _)
+        | ReferenceQuery::Needs(ref module) => {
+            let module = module.clone();
+            let (hir, _) = db.hir(module.clone()).unwrap();
+            let mut context = Context::new(db, query, include_declaration);
+            context.visit_body(hir.as_ref());
+            return FxHashMap::from_iter([(module, context.references)]);
+        }
     };
-    let (hir, _) = db.hir(module).unwrap();
 
-    let mut context = Context::new(db, query, include_declaration);
-    context.visit_body(hir.as_ref());
-    context.references
+    // Start searching at the original definition of imported identifiers.
+    while let Some(definition) = db.imported_definitions(id.module.clone()).get(&id) {
+        id = definition.clone();
+    }
+
+    let mut references: FxHashMap<Module, Vec<Reference>> = FxHashMap::default();
+    let mut visited = FxHashSet::default();
+    let mut targets = vec![id.clone()];
+    while let Some(target) = targets.pop() {
+        if cancellation.is_cancelled() {
+            break;
+        }
+        if !visited.insert(target.clone()) {
+            continue;
+        }
+        let Ok((hir, _)) = db.hir(target.module.clone()) else {
+            continue;
+        };
+
+        // Imports and re-exports of the original definition share its name,
+        // so their declarations are references as well.
+        let mut context = Context::new(
+            db,
+            ReferenceQuery::Id(target.clone()),
+            include_declaration || target != id,
+        );
+        context.visit_body(hir.as_ref());
+
+        // Top-level definitions like `foo := bar` re-export `bar`.
+        targets.extend(
+            context
+                .discovered_references
+                .iter()
+                .filter(|it| {
+                    it.keys.len() == 1
+                        && hir.identifiers.contains_key(it)
+                        && matches!(hir.expressions.get(*it), Some(Expression::Reference(_)))
+                })
+                .cloned(),
+        );
+        references
+            .entry(target.module.clone())
+            .or_default()
+            .extend(context.references);
+
+        for module in other_modules.iter().chain([&target.module]) {
+            if cancellation.is_cancelled() {
+                break;
+            }
+            targets.extend(
+                db.imported_definitions(module.clone())
+                    .iter()
+                    .filter(|(_, definition)| **definition == target)
+                    .map(|(import, _)| import.clone()),
+            );
+        }
+    }
+    references.retain(|_, references| !references.is_empty());
+    references
 }
 
 struct Context<'a, DB: PositionConversionDb + ?Sized> {
@@ -104,7 +237,7 @@ struct Context<'a, DB: PositionConversionDb + ?Sized> {
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum ReferenceQuery {
     Id(hir::Id),
-    Int(Module, BigUint),
+    Int(Module, BigInt),
     Symbol(Module, String),
     Needs(Module),
 }
@@ -153,7 +286,7 @@ where
                     self.add_reference(id, false);
                 }
             }
-            Expression::Text(_) => {}
+            Expression::Float(_) | Expression::Text(_) => {}
             Expression::Reference(target) => {
                 if let ReferenceQuery::Id(target_id) = &self.query
                     && target == target_id
@@ -168,6 +301,14 @@ where
                     self.add_reference(id, false);
                 }
             }
+            Expression::Tag { symbol, value } => {
+                if let ReferenceQuery::Symbol(_, target) = &self.query
+                    && symbol == target
+                {
+                    self.add_reference(id, false);
+                }
+                self.visit_id(value.clone());
+            }
             Expression::List(_)
             | Expression::Struct(_)
             | Expression::Destructure { .. }
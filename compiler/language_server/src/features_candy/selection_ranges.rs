@@ -0,0 +1,49 @@
+use std::iter;
+
+use candy_frontend::{
+    cst::{CstKind, FindCstPath},
+    module::{Module, ModuleDb},
+    position::{Offset, PositionConversionDb},
+    rcst_to_cst::RcstToCst,
+};
+use lsp_types::SelectionRange;
+
+use crate::utils::LspPositionConversion;
+
+/// Selection ranges follow the CST from the innermost node at the offset to
+/// the whole module, e.g., identifier → call → assignment → module.
+pub fn selection_range<DB: ModuleDb + PositionConversionDb + RcstToCst>(
+    db: &DB,
+    module: &Module,
+    offset: Offset,
+) -> SelectionRange {
+    let text = db.get_module_content_as_string(module.clone()).unwrap();
+    let csts = db.cst(module.clone()).unwrap();
+
+    let spans = csts
+        .find_path_at_offset(offset)
+        .nodes()
+        .iter()
+        // These don't add a meaningful selection on their own.
+        .filter(|it| {
+            !matches!(
+                it.kind,
+                CstKind::TrailingWhitespace { .. } | CstKind::Whitespace(_) | CstKind::Newline(_),
+            )
+        })
+        .map(|it| it.data.span.clone())
+        .collect::<Vec<_>>();
+
+    let mut selection_range: Option<SelectionRange> = None;
+    for span in iter::once(Offset(0)..Offset(text.len())).chain(spans) {
+        let range = db.range_to_lsp_range(module.clone(), span);
+        if selection_range.as_ref().is_some_and(|it| it.range == range) {
+            continue;
+        }
+        selection_range = Some(SelectionRange {
+            range,
+            parent: selection_range.map(Box::new),
+        });
+    }
+    selection_range.unwrap()
+}
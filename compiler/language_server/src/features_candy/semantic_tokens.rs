@@ -1,42 +1,132 @@
 use candy_frontend::{
-    cst::{Cst, CstKind, UnwrapWhitespaceAndComment},
+    ast_to_hir::AstToHir,
+    cst::{self, Cst, CstKind, UnwrapWhitespaceAndComment},
+    hir::{self, Body, Expression},
     module::{Module, ModuleDb},
     position::PositionConversionDb,
     rcst_to_cst::RcstToCst,
 };
 use enumset::EnumSet;
 use lsp_types::SemanticToken;
+use rustc_hash::{FxHashMap, FxHashSet};
 
-use crate::semantic_tokens::{SemanticTokenType, SemanticTokensBuilder};
+use crate::{
+    cancellation::CancellationToken,
+    semantic_tokens::{SemanticTokenModifier, SemanticTokenType, SemanticTokensBuilder},
+};
 
-pub fn semantic_tokens<DB: ModuleDb + PositionConversionDb + RcstToCst>(
+pub fn semantic_tokens<DB: ModuleDb + PositionConversionDb + RcstToCst + AstToHir>(
     db: &DB,
     module: Module,
+    cancellation: &CancellationToken,
 ) -> Vec<SemanticToken> {
-    let text = db.get_module_content_as_string(module.clone()).unwrap();
-    let line_start_offsets = db.line_start_offsets(module.clone());
-    let mut builder = SemanticTokensBuilder::new(&*text, &*line_start_offsets);
+    let line_index = db.line_index(module.clone());
+    let mut context = Context {
+        builder: SemanticTokensBuilder::new(&line_index),
+        reference_types: reference_types(db, module.clone()),
+    };
     let cst = db.cst(module).unwrap();
-    visit_csts(&mut builder, &cst, None);
-    builder.finish()
+    for cst in cst.iter() {
+        if cancellation.is_cancelled() {
+            break;
+        }
+        visit_cst(&mut context, cst, None);
+    }
+    context.builder.finish()
+}
+
+struct Context<'a> {
+    builder: SemanticTokensBuilder<'a>,
+    reference_types: FxHashMap<cst::Id, SemanticTokenType>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct IdentifierUsage {
+    token_type: SemanticTokenType,
+    modifiers: EnumSet<SemanticTokenModifier>,
+}
+impl IdentifierUsage {
+    fn declaration(token_type: SemanticTokenType) -> Self {
+        Self {
+            token_type,
+            modifiers: SemanticTokenModifier::Declaration.into(),
+        }
+    }
+    fn reference(token_type: SemanticTokenType) -> Self {
+        Self {
+            token_type,
+            modifiers: EnumSet::empty(),
+        }
+    }
+
+    fn is_declaration(self) -> bool {
+        self.modifiers.contains(SemanticTokenModifier::Declaration)
+    }
 }
 
-fn visit_csts(
-    builder: &mut SemanticTokensBuilder<'_>,
-    csts: &[Cst],
-    token_type_for_identifier: Option<SemanticTokenType>,
+/// Uses the HIR to find out what identifiers that aren't declarations refer to.
+fn reference_types<DB: AstToHir>(db: &DB, module: Module) -> FxHashMap<cst::Id, SemanticTokenType> {
+    let Ok((body, hir_to_ast_ids)) = db.hir(module) else {
+        return FxHashMap::default();
+    };
+
+    let mut parameters = FxHashSet::default();
+    let mut expressions = FxHashMap::default();
+    collect_parameters_and_expressions(&body, &mut parameters, &mut expressions);
+
+    let mut reference_types = FxHashMap::default();
+    for (id, expression) in &expressions {
+        let Expression::Reference(target) = expression else {
+            continue;
+        };
+        let token_type = if parameters.contains(target) {
+            SemanticTokenType::Parameter
+        } else if let Some(Expression::Function(_)) = expressions.get(target) {
+            SemanticTokenType::Function
+        } else {
+            SemanticTokenType::Variable
+        };
+
+        let Some(cst_id) = hir_to_ast_ids
+            .get(*id)
+            .and_then(|ast_id| db.ast_to_cst_id(ast_id))
+        else {
+            continue;
+        };
+        reference_types.insert(cst_id, token_type);
+    }
+    reference_types
+}
+fn collect_parameters_and_expressions<'a>(
+    body: &'a Body,
+    parameters: &mut FxHashSet<&'a hir::Id>,
+    expressions: &mut FxHashMap<&'a hir::Id, &'a Expression>,
 ) {
+    for (id, expression) in &body.expressions {
+        expressions.insert(id, expression);
+        match expression {
+            Expression::Match { cases, .. } => {
+                for (_, body) in cases {
+                    collect_parameters_and_expressions(body, parameters, expressions);
+                }
+            }
+            Expression::Function(function) => {
+                parameters.extend(&function.parameters);
+                collect_parameters_and_expressions(&function.body, parameters, expressions);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn visit_csts(context: &mut Context<'_>, csts: &[Cst], identifier_usage: Option<IdentifierUsage>) {
     for cst in csts {
-        visit_cst(builder, cst, token_type_for_identifier);
+        visit_cst(context, cst, identifier_usage);
     }
 }
-fn visit_cst(
-    builder: &mut SemanticTokensBuilder<'_>,
-    cst: &Cst,
-    token_type_for_identifier: Option<SemanticTokenType>,
-) {
+fn visit_cst(context: &mut Context<'_>, cst: &Cst, identifier_usage: Option<IdentifierUsage>) {
     match &cst.kind {
-        CstKind::EqualsSign => builder.add(
+        CstKind::EqualsSign => context.builder.add(
             cst.data.span.clone(),
             SemanticTokenType::Operator,
             EnumSet::empty(),
@@ -52,43 +142,67 @@ fn visit_cst(
         | CstKind::ClosingBracket
         | CstKind::OpeningCurlyBrace
         | CstKind::ClosingCurlyBrace => {}
-        CstKind::Arrow => builder.add(
+        CstKind::Arrow => context.builder.add(
             cst.data.span.clone(),
             SemanticTokenType::Operator,
             EnumSet::empty(),
         ),
         CstKind::SingleQuote => {} // handled by parent
         CstKind::DoubleQuote => {} // handled by parent
-        CstKind::Percent => builder.add(
+        CstKind::Percent => context.builder.add(
             cst.data.span.clone(),
             SemanticTokenType::Operator,
             EnumSet::empty(),
         ),
         CstKind::Octothorpe => {} // handled by parent
+        CstKind::IfKeyword | CstKind::ThenKeyword | CstKind::ElseKeyword => context.builder.add(
+            cst.data.span.clone(),
+            SemanticTokenType::Keyword,
+            EnumSet::empty(),
+        ),
+        CstKind::BinaryOperator(_) => context.builder.add(
+            cst.data.span.clone(),
+            SemanticTokenType::Operator,
+            EnumSet::empty(),
+        ),
         CstKind::Whitespace(_) | CstKind::Newline(_) => {}
         CstKind::Comment { octothorpe, .. } => {
-            visit_cst(builder, octothorpe, None);
-            builder.add(
+            visit_cst(context, octothorpe, None);
+            context.builder.add(
                 cst.data.span.clone(),
                 SemanticTokenType::Comment,
                 EnumSet::empty(),
             );
         }
         CstKind::TrailingWhitespace { child, whitespace } => {
-            visit_cst(builder, child, token_type_for_identifier);
-            visit_csts(builder, whitespace, token_type_for_identifier);
+            visit_cst(context, child, identifier_usage);
+            visit_csts(context, whitespace, identifier_usage);
         }
-        CstKind::Identifier { .. } => builder.add(
-            cst.data.span.clone(),
-            token_type_for_identifier.unwrap_or(SemanticTokenType::Variable),
-            EnumSet::empty(),
-        ),
-        CstKind::Symbol { .. } => builder.add(
+        CstKind::Identifier(name) => {
+            let usage = identifier_usage.unwrap_or_else(|| {
+                IdentifierUsage::reference(
+                    context
+                        .reference_types
+                        .get(&cst.data.id)
+                        .copied()
+                        .unwrap_or(SemanticTokenType::Variable),
+                )
+            });
+            // Values in Candy can't be mutated.
+            let mut modifiers = usage.modifiers | SemanticTokenModifier::Readonly;
+            if name == "✨" {
+                modifiers |= SemanticTokenModifier::Builtin;
+            }
+            context
+                .builder
+                .add(cst.data.span.clone(), usage.token_type, modifiers);
+        }
+        CstKind::Symbol { .. } => context.builder.add(
             cst.data.span.clone(),
             SemanticTokenType::Symbol,
             EnumSet::empty(),
         ),
-        CstKind::Int { .. } => builder.add(
+        CstKind::Int { .. } | CstKind::Float { .. } => context.builder.add(
             cst.data.span.clone(),
             SemanticTokenType::Int,
             EnumSet::empty(),
@@ -98,13 +212,13 @@ fn visit_cst(
             opening_double_quote,
         } => {
             for opening_single_quote in opening_single_quotes {
-                builder.add(
+                context.builder.add(
                     opening_single_quote.data.span.clone(),
                     SemanticTokenType::Text,
                     EnumSet::empty(),
                 );
             }
-            builder.add(
+            context.builder.add(
                 opening_double_quote.data.span.clone(),
                 SemanticTokenType::Text,
                 EnumSet::empty(),
@@ -114,13 +228,13 @@ fn visit_cst(
             closing_double_quote,
             closing_single_quotes,
         } => {
-            builder.add(
+            context.builder.add(
                 closing_double_quote.data.span.clone(),
                 SemanticTokenType::Text,
                 EnumSet::empty(),
             );
             for closing_single_quote in closing_single_quotes {
-                builder.add(
+                context.builder.add(
                     closing_single_quote.data.span.clone(),
                     SemanticTokenType::Text,
                     EnumSet::empty(),
@@ -132,14 +246,14 @@ fn visit_cst(
             parts,
             closing,
         } => {
-            visit_cst(builder, opening, None);
+            visit_cst(context, opening, None);
             for line in parts {
-                visit_cst(builder, line, None);
+                visit_cst(context, line, None);
             }
-            visit_cst(builder, closing, None);
+            visit_cst(context, closing, None);
         }
         CstKind::TextNewline(_) => {}
-        CstKind::TextPart(_) => builder.add(
+        CstKind::TextPart(_) | CstKind::TextEscape { .. } => context.builder.add(
             cst.data.span.clone(),
             SemanticTokenType::Text,
             EnumSet::empty(),
@@ -150,47 +264,75 @@ fn visit_cst(
             closing_curly_braces,
         } => {
             for opening_curly_brace in opening_curly_braces {
-                visit_cst(builder, opening_curly_brace, None);
+                visit_cst(context, opening_curly_brace, None);
             }
-            visit_cst(builder, expression, None);
+            visit_cst(context, expression, None);
             for closing_curly_brace in closing_curly_braces {
-                visit_cst(builder, closing_curly_brace, None);
+                visit_cst(context, closing_curly_brace, None);
             }
         }
         CstKind::BinaryBar { left, bar, right } => {
-            visit_cst(builder, left, None);
-            visit_cst(builder, bar, None);
-            visit_cst(builder, right, None);
+            visit_cst(context, left, None);
+            visit_cst(context, bar, None);
+            visit_cst(context, right, None);
+        }
+        CstKind::BinaryOperation {
+            left,
+            operator,
+            right,
+        } => {
+            visit_cst(context, left, None);
+            visit_cst(context, operator, None);
+            visit_cst(context, right, None);
         }
         CstKind::Parenthesized {
             opening_parenthesis,
             inner,
             closing_parenthesis,
         } => {
-            visit_cst(builder, opening_parenthesis, None);
-            visit_cst(builder, inner, None);
-            visit_cst(builder, closing_parenthesis, None);
+            visit_cst(context, opening_parenthesis, None);
+            visit_cst(context, inner, None);
+            visit_cst(context, closing_parenthesis, None);
         }
         CstKind::Call {
             receiver,
             arguments,
         } => {
-            visit_cst(builder, receiver, Some(SemanticTokenType::Function));
-            visit_csts(builder, arguments, None);
+            // The left side of a function definition (`foo a b = …`) is also
+            // parsed as a call.
+            if identifier_usage.is_some_and(IdentifierUsage::is_declaration) {
+                visit_cst(
+                    context,
+                    receiver,
+                    Some(IdentifierUsage::declaration(SemanticTokenType::Function)),
+                );
+                visit_csts(
+                    context,
+                    arguments,
+                    Some(IdentifierUsage::declaration(SemanticTokenType::Parameter)),
+                );
+            } else {
+                visit_cst(
+                    context,
+                    receiver,
+                    Some(IdentifierUsage::reference(SemanticTokenType::Function)),
+                );
+                visit_csts(context, arguments, None);
+            }
         }
         CstKind::List {
             opening_parenthesis,
             items,
             closing_parenthesis,
         } => {
-            visit_cst(builder, opening_parenthesis, None);
-            visit_csts(builder, items, token_type_for_identifier);
-            visit_cst(builder, closing_parenthesis, None);
+            visit_cst(context, opening_parenthesis, None);
+            visit_csts(context, items, identifier_usage);
+            visit_cst(context, closing_parenthesis, None);
         }
         CstKind::ListItem { value, comma } => {
-            visit_cst(builder, value, token_type_for_identifier);
+            visit_cst(context, value, identifier_usage);
             if let Some(comma) = comma {
-                visit_cst(builder, comma, None);
+                visit_cst(context, comma, None);
             }
         }
         CstKind::Struct {
@@ -198,9 +340,9 @@ fn visit_cst(
             fields,
             closing_bracket,
         } => {
-            visit_cst(builder, opening_bracket, None);
-            visit_csts(builder, fields, token_type_for_identifier);
-            visit_cst(builder, closing_bracket, None);
+            visit_cst(context, opening_bracket, None);
+            visit_csts(context, fields, identifier_usage);
+            visit_cst(context, closing_bracket, None);
         }
         CstKind::StructField {
             key_and_colon,
@@ -208,40 +350,64 @@ fn visit_cst(
             comma,
         } => {
             if let Some(box (key, colon)) = key_and_colon {
-                visit_cst(builder, key, token_type_for_identifier);
-                visit_cst(builder, colon, None);
+                visit_cst(context, key, identifier_usage);
+                visit_cst(context, colon, None);
             }
-            visit_cst(builder, value, token_type_for_identifier);
+            visit_cst(context, value, identifier_usage);
             if let Some(comma) = comma {
-                visit_cst(builder, comma, None);
+                visit_cst(context, comma, None);
             }
         }
         CstKind::StructAccess { struct_, dot, key } => {
-            visit_cst(builder, struct_, None);
-            visit_cst(builder, dot, None);
-            visit_cst(
-                builder,
-                key,
-                Some(token_type_for_identifier.unwrap_or(SemanticTokenType::Symbol)),
-            );
+            visit_cst(context, struct_, None);
+            visit_cst(context, dot, None);
+            let usage = if matches!(
+                &struct_.unwrap_whitespace_and_comment().kind,
+                CstKind::Identifier(name) if name == "✨",
+            ) {
+                IdentifierUsage {
+                    token_type: SemanticTokenType::Function,
+                    modifiers: SemanticTokenModifier::Builtin.into(),
+                }
+            } else {
+                IdentifierUsage::reference(
+                    identifier_usage.map_or(SemanticTokenType::Symbol, |it| it.token_type),
+                )
+            };
+            visit_cst(context, key, Some(usage));
         }
         CstKind::Match {
             expression,
             percent,
             cases,
         } => {
-            visit_cst(builder, expression, None);
-            visit_cst(builder, percent, None);
-            visit_csts(builder, cases, None);
+            visit_cst(context, expression, None);
+            visit_cst(context, percent, None);
+            visit_csts(context, cases, None);
+        }
+        CstKind::If {
+            if_keyword,
+            condition,
+            then_keyword,
+            then,
+            else_keyword,
+            else_,
+        } => {
+            visit_cst(context, if_keyword, None);
+            visit_cst(context, condition, None);
+            visit_cst(context, then_keyword, None);
+            visit_cst(context, then, None);
+            visit_cst(context, else_keyword, None);
+            visit_cst(context, else_, None);
         }
         CstKind::MatchCase {
             pattern,
             arrow,
             body,
         } => {
-            visit_cst(builder, pattern, None);
-            visit_cst(builder, arrow, None);
-            visit_csts(builder, body, None);
+            visit_cst(context, pattern, None);
+            visit_cst(context, arrow, None);
+            visit_csts(context, body, None);
         }
         CstKind::Function {
             opening_curly_brace,
@@ -249,38 +415,37 @@ fn visit_cst(
             body,
             closing_curly_brace,
         } => {
-            visit_cst(builder, opening_curly_brace, None);
+            visit_cst(context, opening_curly_brace, None);
             if let Some((parameters, arrow)) = parameters_and_arrow {
-                visit_csts(builder, parameters, Some(SemanticTokenType::Parameter));
-                visit_cst(builder, arrow, None);
+                visit_csts(
+                    context,
+                    parameters,
+                    Some(IdentifierUsage::declaration(SemanticTokenType::Parameter)),
+                );
+                visit_cst(context, arrow, None);
             }
-            visit_csts(builder, body, None);
-            visit_cst(builder, closing_curly_brace, None);
+            visit_csts(context, body, None);
+            visit_cst(context, closing_curly_brace, None);
         }
         CstKind::Assignment {
             left,
             assignment_sign,
             body,
         } => {
-            if let CstKind::Call {
-                receiver,
-                arguments,
-            } = &left.kind
+            let token_type = if let [single] = body.as_slice()
+                && single.unwrap_whitespace_and_comment().kind.is_function()
             {
-                visit_cst(builder, receiver, Some(SemanticTokenType::Function));
-                visit_csts(builder, arguments, Some(SemanticTokenType::Parameter));
+                SemanticTokenType::Function
             } else {
-                let token_type = if let [single] = body.as_slice()
-                    && single.unwrap_whitespace_and_comment().kind.is_function()
-                {
-                    SemanticTokenType::Function
-                } else {
-                    SemanticTokenType::Variable
-                };
-                visit_cst(builder, left, Some(token_type));
-            }
-            visit_cst(builder, assignment_sign, None);
-            visit_csts(builder, body, None);
+                SemanticTokenType::Variable
+            };
+            visit_cst(
+                context,
+                left,
+                Some(IdentifierUsage::declaration(token_type)),
+            );
+            visit_cst(context, assignment_sign, None);
+            visit_csts(context, body, None);
         }
         CstKind::Error { .. } => {}
     }
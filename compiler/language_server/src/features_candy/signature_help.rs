@@ -0,0 +1,224 @@
+use super::{
+    find_definition::{find_exported_definition, find_imported_definition, find_used_module},
+    hover::find_documentation,
+};
+use crate::database::Database;
+use candy_frontend::{
+    ast_to_hir::AstToHir,
+    builtin_functions::BuiltinFunction,
+    cst::{Cst, CstKind, UnwrapWhitespaceAndComment},
+    hir::{Expression, HirDb, Id, IdKey},
+    module::{Module, ModuleDb},
+    position::Offset,
+    rcst_to_cst::RcstToCst,
+};
+use lsp_types::{
+    Documentation, MarkupContent, MarkupKind, ParameterInformation, ParameterLabel, SignatureHelp,
+    SignatureInformation,
+};
+use tracing::debug;
+
+pub fn signature_help(db: &Database, module: Module, offset: Offset) -> Option<SignatureHelp> {
+    let text = db.get_module_content_as_string(module.clone())?;
+    let csts = db.cst(module.clone()).ok()?;
+
+    // When the cursor is behind some whitespace, the user is about to type the
+    // next argument.
+    let end_of_code = Offset(text[..*offset].trim_end_matches([' ', '\t']).len());
+    let is_after_whitespace = end_of_code < offset;
+
+    let (receiver, arguments) = csts
+        .iter()
+        .find_map(|cst| find_call(cst, end_of_code))
+        .or_else(|| {
+            // A function without arguments is parsed as a simple reference.
+            if !is_after_whitespace {
+                return None;
+            }
+            let receiver = csts
+                .iter()
+                .find_map(|cst| find_function_ending_at(cst, end_of_code))?;
+            Some((receiver, [].as_slice()))
+        })?;
+    let receiver = receiver.unwrap_whitespace_and_comment();
+    debug!("Showing signature help for {receiver}");
+
+    let receiver_id = db.cst_to_last_hir_id(module, receiver.data.id)?;
+    let parameters = find_parameter_names(db, receiver_id.clone())?;
+    let documentation = find_documentation(db, receiver_id).map(|documentation| {
+        Documentation::MarkupContent(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: documentation,
+        })
+    });
+
+    let active_parameter = arguments
+        .iter()
+        .take_while(|argument| {
+            let end = argument.unwrap_whitespace_and_comment().data.span.end;
+            end < end_of_code || (is_after_whitespace && end == end_of_code)
+        })
+        .count();
+
+    // Parameter labels are offsets into the signature label, measured in
+    // UTF-16 code units.
+    let mut label = text[*receiver.data.span.start..*receiver.data.span.end].to_string();
+    let mut parameter_informations = vec![];
+    for parameter in parameters {
+        label.push(' ');
+        let start = label.encode_utf16().count();
+        label.push_str(&parameter);
+        let end = label.encode_utf16().count();
+        parameter_informations.push(ParameterInformation {
+            label: ParameterLabel::LabelOffsets([
+                start.try_into().unwrap(),
+                end.try_into().unwrap(),
+            ]),
+            documentation: None,
+        });
+    }
+
+    let active_parameter = active_parameter.try_into().unwrap();
+    Some(SignatureHelp {
+        signatures: vec![SignatureInformation {
+            label,
+            documentation,
+            parameters: Some(parameter_informations),
+            active_parameter: Some(active_parameter),
+        }],
+        active_signature: Some(0),
+        active_parameter: Some(active_parameter),
+    })
+}
+
+/// Finds the innermost call that contains the `offset`.
+fn find_call(cst: &Cst, offset: Offset) -> Option<(&Cst, &[Cst])> {
+    if !(cst.data.span.start < offset && offset <= cst.data.span.end) {
+        return None;
+    }
+
+    let call = match &cst.kind {
+        CstKind::Call {
+            receiver,
+            arguments,
+        } => Some((&**receiver, arguments.as_slice())),
+        _ => None,
+    };
+    expression_children(cst)
+        .into_iter()
+        .find_map(|child| find_call(child, offset))
+        .or(call)
+}
+fn find_function_ending_at(cst: &Cst, offset: Offset) -> Option<&Cst> {
+    if !(cst.data.span.start < offset && offset <= cst.data.span.end) {
+        return None;
+    }
+
+    match &cst.kind {
+        CstKind::Identifier(_) | CstKind::StructAccess { .. } if cst.data.span.end == offset => {
+            Some(cst)
+        }
+        _ => expression_children(cst)
+            .into_iter()
+            .find_map(|child| find_function_ending_at(child, offset)),
+    }
+}
+/// Returns the children of the `cst`, except for patterns such as the left
+/// side of assignments, which look like calls but aren't.
+fn expression_children(cst: &Cst) -> Vec<&Cst> {
+    match &cst.kind {
+        CstKind::Assignment { body, .. } | CstKind::MatchCase { body, .. } => body.iter().collect(),
+        CstKind::Function { body, .. } => body.iter().collect(),
+        kind => kind.children(),
+    }
+}
+
+/// Follows references, imports, and struct accesses until reaching the
+/// function that's called.
+pub fn find_parameter_names(db: &Database, mut id: Id) -> Option<Vec<String>> {
+    loop {
+        match db.find_expression(id.clone())? {
+            Expression::Reference(target) => id = target,
+            Expression::PatternIdentifierReference(_) => {
+                id = find_imported_definition(db, &id)?;
+            }
+            Expression::Function(function) => {
+                return Some(
+                    function
+                        .parameters
+                        .iter()
+                        .map(|parameter| match parameter.keys.last() {
+                            Some(IdKey::Named { name, .. }) => name.clone(),
+                            _ => "_".to_string(),
+                        })
+                        .collect(),
+                );
+            }
+            Expression::Builtin(builtin) => {
+                return Some(
+                    builtin
+                        .parameter_names()
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect(),
+                );
+            }
+            Expression::Call {
+                function,
+                arguments,
+            } => id = find_struct_access_target(db, &function, &arguments)?,
+            _ => return None,
+        }
+    }
+}
+/// If the call is a struct access (like `foo.bar`), returns the ID of the
+/// accessed field's value.
+pub fn find_struct_access_target(db: &Database, function: &Id, arguments: &[Id]) -> Option<Id> {
+    if !is_struct_get(db, function) {
+        return None;
+    }
+    let [struct_, key] = arguments else {
+        return None;
+    };
+    let Expression::Symbol(key) = db.find_expression(key.clone())? else {
+        return None;
+    };
+    find_struct_field(db, struct_.clone(), &key)
+}
+/// Struct accesses are lowered to calls of `structGet`, which is either the
+/// builtin itself (inside the `Builtins` package) or retrieved from the
+/// `Builtins` package.
+fn is_struct_get(db: &Database, function: &Id) -> bool {
+    match db.find_expression(function.clone()) {
+        Some(Expression::Builtin(BuiltinFunction::StructGet)) => true,
+        Some(Expression::Call { function, .. }) => matches!(
+            db.find_expression(function),
+            Some(Expression::Builtin(BuiltinFunction::StructGet)),
+        ),
+        _ => false,
+    }
+}
+fn find_struct_field(db: &Database, mut struct_: Id, key: &str) -> Option<Id> {
+    if let Some(module) = find_used_module(db, &struct_) {
+        return find_exported_definition(db, module, key);
+    }
+
+    loop {
+        match db.find_expression(struct_.clone())? {
+            Expression::Reference(target) => struct_ = target,
+            Expression::PatternIdentifierReference(_) => {
+                struct_ = find_imported_definition(db, &struct_)?;
+                if let Some(module) = find_used_module(db, &struct_) {
+                    return find_exported_definition(db, module, key);
+                }
+            }
+            Expression::Struct(fields) => {
+                return fields.into_iter().find_map(|(field_key, value)| {
+                    matches!(db.find_expression(field_key), Some(Expression::Symbol(it)) if it == key)
+                        .then_some(value)
+                });
+            }
+            _ => return None,
+        }
+    }
+}
@@ -9,7 +9,8 @@ use candy_frontend::{
     mir_optimize::{OptimizeMir, OptimizedMirResult},
     mir_to_lir::{LirResult, MirToLir},
     module::{Module, ModuleKind, PackagesPath},
-    position::{line_start_offsets_raw, Offset},
+    position::{LineIndex, Offset},
+    rcst_to_cst::{CstResult, RcstToCst},
     rich_ir::{
         ReferenceCollection, ReferenceKey, RichIr, RichIrBuilder, ToRichIr, TokenModifier,
         TokenType,
@@ -58,6 +59,30 @@ impl Server {
         let open_irs = features.ir.open_irs.read().await;
         Ok(open_irs.get(&params.uri).unwrap().ir.text.clone())
     }
+
+    /// Maps ranges in the original source code to the corresponding ranges in
+    /// the IR so that the client can highlight them when the user selects
+    /// source code.
+    pub async fn candy_ir_source_mapping(
+        &self,
+        params: ViewIrParams,
+    ) -> jsonrpc::Result<Vec<IrSourceMapping>> {
+        let state = self.state.read().await;
+        let features = state.require_features();
+        let open_irs = features.ir.open_irs.read().await;
+        let Some(open_ir) = open_irs.get(&params.uri) else {
+            return Ok(vec![]);
+        };
+        let db = self.db.lock().await;
+        Ok(open_ir.source_mapping(&db))
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IrSourceMapping {
+    pub source_range: lsp_types::Range,
+    pub ir_ranges: Vec<lsp_types::Range>,
 }
 
 #[derive(Debug, Default)]
@@ -104,49 +129,50 @@ impl IrFeatures {
     fn create(db: &Database, config: IrConfig) -> OpenIr {
         let ir = match &config.ir {
             Ir::Rcst => Self::rich_ir_for_rcst(&config.module, db.rcst(config.module.clone())),
+            Ir::Cst => Self::rich_ir_for_cst(&config.module, db.cst(config.module.clone())),
             Ir::Ast => Self::rich_ir_for_ast(&config.module, db.ast(config.module.clone())),
             Ir::Hir => Self::rich_ir_for_hir(&config.module, db.hir(config.module.clone())),
             Ir::Mir(tracing_config) => Self::rich_ir_for_mir(
                 &config.module,
                 db.mir(
                     ExecutionTarget::Module(config.module.clone()),
-                    *tracing_config,
+                    tracing_config.clone(),
                 ),
-                *tracing_config,
+                tracing_config.clone(),
             ),
             Ir::OptimizedMir(tracing_config) => Self::rich_ir_for_optimized_mir(
                 &config.module,
                 db.optimized_mir(
                     ExecutionTarget::Module(config.module.clone()),
-                    *tracing_config,
+                    tracing_config.clone(),
                 ),
-                *tracing_config,
+                tracing_config.clone(),
             ),
             Ir::Lir(tracing_config) => Self::rich_ir_for_lir(
                 &config.module,
                 &db.lir(
                     ExecutionTarget::Module(config.module.clone()),
-                    *tracing_config,
+                    tracing_config.clone(),
                 ),
-                *tracing_config,
+                tracing_config.clone(),
             ),
             Ir::OptimizedLir(tracing_config) => Self::rich_ir_for_optimized_lir(
                 &config.module,
                 db.optimized_lir(
                     ExecutionTarget::Module(config.module.clone()),
-                    *tracing_config,
+                    tracing_config.clone(),
                 ),
-                *tracing_config,
+                tracing_config.clone(),
             ),
             Ir::VmByteCode(tracing_config) => Self::rich_ir_for_vm_byte_code(
                 &config.module,
                 &candy_vm::lir_to_byte_code::compile_byte_code(
                     db,
                     ExecutionTarget::Module(config.module.clone()),
-                    *tracing_config,
+                    tracing_config.clone(),
                 )
                 .0,
-                *tracing_config,
+                tracing_config.clone(),
             ),
             #[cfg(feature = "inkwell")]
             Ir::LlvmIr => db
@@ -154,11 +180,11 @@ impl IrFeatures {
                 .unwrap(),
         };
 
-        let line_start_offsets = line_start_offsets_raw(&ir.text);
+        let line_index = LineIndex::new(&ir.text);
         OpenIr {
             config,
             ir,
-            line_start_offsets,
+            line_index,
         }
     }
     fn rich_ir_for_rcst(module: &Module, rcst: RcstResult) -> RichIr {
@@ -167,6 +193,25 @@ impl IrFeatures {
             Err(error) => Self::build_rich_ir_for_module_error(builder, module, error),
         })
     }
+    fn rich_ir_for_cst(module: &Module, cst: CstResult) -> RichIr {
+        Self::rich_ir_for("CST", module, None, |builder| match cst {
+            Ok(cst) => {
+                // Referencing the spans allows highlighting the corresponding
+                // top-level CST nodes when selecting source code.
+                for (index, cst) in cst.iter().enumerate() {
+                    if index > 0 {
+                        builder.push_newline();
+                    }
+                    let range = builder.push(format!("{cst:?}"), None, EnumSet::empty());
+                    builder.push_reference(
+                        ReferenceKey::ModuleWithSpan(module.clone(), cst.data.span.clone()),
+                        range,
+                    );
+                }
+            }
+            Err(error) => Self::build_rich_ir_for_module_error(builder, module, error),
+        })
+    }
     fn rich_ir_for_ast(module: &Module, asts: AstResult) -> RichIr {
         Self::rich_ir_for("AST", module, None, |builder| match asts {
             Ok((asts, _)) => asts.build_rich_ir(builder),
@@ -240,7 +285,7 @@ impl IrFeatures {
         builder.push_comment_line(format!("{ir_name} for module {module}"));
         if let Some(tracing_config) = tracing_config.into() {
             builder.push_newline();
-            builder.push_tracing_config(tracing_config);
+            builder.push_tracing_config(&tracing_config);
         }
         builder.push_newline();
 
@@ -282,7 +327,7 @@ impl IrFeatures {
 struct OpenIr {
     config: IrConfig,
     ir: RichIr,
-    line_start_offsets: Vec<Offset>,
+    line_index: LineIndex,
 }
 #[derive(Clone, Debug)]
 struct IrConfig {
@@ -328,6 +373,7 @@ impl IrConfig {
         let ir = IrDiscriminants::try_from(ir).unwrap_or_else(|_| panic!("Unsupported IR: {ir}"));
         let ir = match ir {
             IrDiscriminants::Rcst => Ir::Rcst,
+            IrDiscriminants::Cst => Ir::Cst,
             IrDiscriminants::Ast => Ir::Ast,
             IrDiscriminants::Hir => Ir::Hir,
             IrDiscriminants::Mir => Ir::Mir(tracing_config.expect("Tracing config is missing.")),
@@ -367,16 +413,11 @@ impl UrlFromIrConfig for Url {
                 ModuleKind::Asset => "asset".into(),
             },
         );
-        match &config.ir {
-            Ir::Mir(tracing_config)
-            | Ir::OptimizedMir(tracing_config)
-            | Ir::Lir(tracing_config) => {
-                details.insert(
-                    "tracingConfig".to_string(),
-                    serde_json::to_value(tracing_config).unwrap(),
-                );
-            }
-            _ => {}
+        if let Some(tracing_config) = config.ir.tracing_config() {
+            details.insert(
+                "tracingConfig".to_string(),
+                serde_json::to_value(tracing_config).unwrap(),
+            );
         }
 
         Self::parse(
@@ -398,6 +439,7 @@ impl UrlFromIrConfig for Url {
 )]
 pub enum Ir {
     Rcst,
+    Cst,
     Ast,
     Hir,
     Mir(TracingConfig),
@@ -409,14 +451,14 @@ pub enum Ir {
     LlvmIr,
 }
 impl Ir {
-    const fn tracing_config(&self) -> Option<TracingConfig> {
+    const fn tracing_config(&self) -> Option<&TracingConfig> {
         match self {
-            Self::Rcst | Self::Ast | Self::Hir => None,
+            Self::Rcst | Self::Cst | Self::Ast | Self::Hir => None,
             Self::Mir(tracing_config)
             | Self::OptimizedMir(tracing_config)
             | Self::Lir(tracing_config)
             | Self::OptimizedLir(tracing_config)
-            | Self::VmByteCode(tracing_config) => Some(*tracing_config),
+            | Self::VmByteCode(tracing_config) => Some(tracing_config),
             #[cfg(feature = "inkwell")]
             Self::LlvmIr => None,
         }
@@ -498,6 +540,7 @@ impl LanguageFeatures for IrFeatures {
 
         let (uri, target_range) = match &key {
             ReferenceKey::Int(_)
+            | ReferenceKey::Float(_)
             | ReferenceKey::Text(_)
             | ReferenceKey::Symbol(_)
             | ReferenceKey::BuiltinFunction(_) => {
@@ -527,6 +570,7 @@ impl LanguageFeatures for IrFeatures {
                         config
                             .ir
                             .tracing_config()
+                            .cloned()
                             .unwrap_or_else(TracingConfig::off),
                     ),
                 };
@@ -541,6 +585,7 @@ impl LanguageFeatures for IrFeatures {
                         config
                             .ir
                             .tracing_config()
+                            .cloned()
                             .unwrap_or_else(TracingConfig::off),
                     ),
                 };
@@ -558,7 +603,7 @@ impl LanguageFeatures for IrFeatures {
     fn supports_folding_ranges(&self) -> bool {
         true
     }
-    async fn folding_ranges(&self, _db: &Mutex<Database>, uri: Url) -> Vec<FoldingRange> {
+    async fn folding_ranges(&self, _db: &Arc<Mutex<Database>>, uri: Url) -> Vec<FoldingRange> {
         let open_irs = self.open_irs.read().await;
         let Some(open_ir) = open_irs.get(&uri) else {
             return vec![];
@@ -571,7 +616,7 @@ impl LanguageFeatures for IrFeatures {
     }
     async fn references(
         &self,
-        _db: &Mutex<Database>,
+        _db: &Arc<Mutex<Database>>,
         uri: Url,
         position: lsp_types::Position,
         only_in_same_document: bool,
@@ -608,7 +653,7 @@ impl LanguageFeatures for IrFeatures {
     fn supports_semantic_tokens(&self) -> bool {
         true
     }
-    async fn semantic_tokens(&self, _db: &Mutex<Database>, uri: Url) -> Vec<SemanticToken> {
+    async fn semantic_tokens(&self, _db: &Arc<Mutex<Database>>, uri: Url) -> Vec<SemanticToken> {
         let open_irs = self.open_irs.read().await;
         let Some(open_ir) = open_irs.get(&uri) else {
             return vec![];
@@ -674,8 +719,41 @@ impl OpenIr {
         })
     }
 
+    fn source_mapping(&self, db: &Database) -> Vec<IrSourceMapping> {
+        let module = &self.config.module;
+        let mut mappings = self
+            .ir
+            .references
+            .iter()
+            .filter_map(|(key, references)| {
+                let span = match key {
+                    ReferenceKey::HirId(id) if &id.module == module => db.hir_id_to_span(id)?,
+                    ReferenceKey::ModuleWithSpan(span_module, span) if span_module == module => {
+                        span.clone()
+                    }
+                    _ => return None,
+                };
+                let ir_ranges = references
+                    .definition
+                    .iter()
+                    .chain(&references.references)
+                    .map(|it| self.range_to_lsp_range(it))
+                    .collect();
+                Some((span, ir_ranges))
+            })
+            .collect::<Vec<_>>();
+        mappings.sort_by_key(|(span, _)| (span.start, span.end));
+        mappings
+            .into_iter()
+            .map(|(span, ir_ranges)| IrSourceMapping {
+                source_range: db.range_to_lsp_range(module.clone(), span),
+                ir_ranges,
+            })
+            .collect()
+    }
+
     fn semantic_tokens(&self) -> Vec<SemanticToken> {
-        let mut builder = SemanticTokensBuilder::new(&self.ir.text, &self.line_start_offsets);
+        let mut builder = SemanticTokensBuilder::new(&self.line_index);
         for annotation in &self.ir.annotations {
             let Some(token_type) = annotation.token_type else {
                 continue;
@@ -694,10 +772,10 @@ impl OpenIr {
     }
 
     fn lsp_position_to_offset(&self, position: lsp_types::Position) -> Offset {
-        lsp_position_to_offset_raw(&self.ir.text, &self.line_start_offsets, position)
+        lsp_position_to_offset_raw(&self.line_index, position)
     }
     fn range_to_lsp_range(&self, range: &Range<Offset>) -> lsp_types::Range {
-        range_to_lsp_range_raw(&self.ir.text, &self.line_start_offsets, range)
+        range_to_lsp_range_raw(&self.line_index, range)
     }
 }
 
@@ -712,7 +790,7 @@ impl TokenTypeToSemantic for TokenType {
             Self::Comment => SemanticTokenType::Comment,
             Self::Symbol => SemanticTokenType::Symbol,
             Self::Text => SemanticTokenType::Text,
-            Self::Int => SemanticTokenType::Int,
+            Self::Int | Self::Float => SemanticTokenType::Int,
             Self::Address => SemanticTokenType::Address,
             Self::Constant => SemanticTokenType::Constant,
         }
@@ -16,6 +16,8 @@
     clippy::too_many_lines
 )]
 
+pub mod cancellation;
+pub mod config;
 pub mod database;
 pub mod debug_adapter;
 pub mod features;
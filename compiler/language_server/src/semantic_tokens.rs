@@ -1,6 +1,6 @@
 use std::ops::Range;
 
-use candy_frontend::position::Offset;
+use candy_frontend::position::{LineIndex, Offset};
 use enumset::{EnumSet, EnumSetType};
 use lazy_static::lazy_static;
 use lsp_types::{Position, SemanticToken, SemanticTokensLegend};
@@ -23,6 +23,7 @@ pub enum SemanticTokenType {
     Operator,
     Address,
     Constant,
+    Keyword,
 }
 lazy_static! {
     static ref TOKEN_TYPE_MAPPING: FxHashMap<SemanticTokenType, u32> = SemanticTokenType::iter()
@@ -45,6 +46,7 @@ impl SemanticTokenType {
             Self::Operator => lsp_types::SemanticTokenType::OPERATOR,
             Self::Address => lsp_types::SemanticTokenType::EVENT,
             Self::Constant => lsp_types::SemanticTokenType::VARIABLE,
+            Self::Keyword => lsp_types::SemanticTokenType::KEYWORD,
         }
     }
 }
@@ -52,7 +54,7 @@ impl SemanticTokenType {
 #[derive(Debug, EnumIter, EnumSetType)]
 #[enumset(repr = "u32")]
 pub enum SemanticTokenModifier {
-    Definition,
+    Declaration,
     Readonly,
     Builtin,
 }
@@ -69,7 +71,7 @@ lazy_static! {
 impl SemanticTokenModifier {
     pub const fn as_lsp(self) -> lsp_types::SemanticTokenModifier {
         match self {
-            Self::Definition => lsp_types::SemanticTokenModifier::DEFINITION,
+            Self::Declaration => lsp_types::SemanticTokenModifier::DECLARATION,
             Self::Readonly => lsp_types::SemanticTokenModifier::READONLY,
             Self::Builtin => lsp_types::SemanticTokenModifier::DEFAULT_LIBRARY,
         }
@@ -77,20 +79,14 @@ impl SemanticTokenModifier {
 }
 
 pub struct SemanticTokensBuilder<'a> {
-    text: &'a str,
-    line_start_offsets: &'a [Offset],
+    line_index: &'a LineIndex,
     tokens: Vec<SemanticToken>,
     cursor: Position,
 }
 impl<'a> SemanticTokensBuilder<'a> {
-    pub fn new<S, L>(text: &'a S, line_start_offsets: &'a L) -> Self
-    where
-        S: AsRef<str>,
-        L: AsRef<[Offset]>,
-    {
+    pub fn new(line_index: &'a LineIndex) -> Self {
         Self {
-            text: text.as_ref(),
-            line_start_offsets: line_start_offsets.as_ref(),
+            line_index,
             tokens: Vec::new(),
             cursor: Position::new(0, 0),
         }
@@ -103,15 +99,16 @@ impl<'a> SemanticTokensBuilder<'a> {
         modifiers: EnumSet<SemanticTokenModifier>,
     ) {
         // Reduce the token to multiple single-line tokens.
-        let mut range = range_to_lsp_range_raw(self.text, self.line_start_offsets, &range);
+        let mut range = range_to_lsp_range_raw(self.line_index, &range);
 
         if range.start.line != range.end.line {
             while range.start.line != range.end.line {
                 assert!(range.start.line < range.end.line);
 
-                let line_length = *self.line_start_offsets[(range.start.line as usize) + 1]
-                    - *self.line_start_offsets[range.start.line as usize]
-                    - 1;
+                let line = range.start.line as usize;
+                let line_length = self
+                    .line_index
+                    .utf16_length(self.line_index.line_start(line)..self.line_index.line_end(line));
                 self.add_single_line(
                     range.start,
                     line_length.try_into().unwrap(),
@@ -138,7 +135,7 @@ impl<'a> SemanticTokensBuilder<'a> {
         start: Position,
         length: u32,
         type_: SemanticTokenType,
-        mut modifiers: EnumSet<SemanticTokenModifier>,
+        modifiers: EnumSet<SemanticTokenModifier>,
     ) {
         assert!(
             start >= self.cursor,
@@ -146,11 +143,6 @@ impl<'a> SemanticTokensBuilder<'a> {
             self.cursor,
         );
 
-        if type_ == SemanticTokenType::Variable {
-            modifiers.insert(SemanticTokenModifier::Definition);
-        }
-        modifiers.insert(SemanticTokenModifier::Readonly);
-
         self.tokens.push(SemanticToken {
             delta_line: start.line - self.cursor.line,
             delta_start: if start.line == self.cursor.line {
@@ -1,9 +1,11 @@
 use crate::{
+    config::ServerConfig,
     database::Database,
     debug_adapter::DebugSessionManager,
     features::{LanguageFeatures, Reference, RenameError},
     features_candy::{
         analyzer::{insights::Hint, HintsNotification},
+        execution::{Execution, FUZZ_FUNCTION_COMMAND, RUN_MAIN_COMMAND},
         CandyFeatures, ServerStatusNotification,
     },
     features_ir::{IrFeatures, UpdateIrNotification},
@@ -11,29 +13,45 @@ use crate::{
     utils::{module_from_url, module_to_url},
 };
 use async_trait::async_trait;
-use candy_frontend::module::{Module, ModuleKind, PackagesPath};
+use candy_frontend::module::{Module, ModuleKind, MutableModuleProviderOwner, PackagesPath};
 use lsp_types::{
-    Diagnostic, DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
-    DocumentFilter, DocumentFormattingParams, DocumentHighlight, DocumentHighlightKind,
-    DocumentHighlightParams, FoldingRange, FoldingRangeParams, GotoDefinitionParams,
-    GotoDefinitionResponse, InitializeParams, InitializeResult, InitializedParams, Location,
-    MessageType, Position, PrepareRenameResponse, ReferenceParams, Registration, RenameOptions,
-    RenameParams, SemanticTokens, SemanticTokensFullOptions, SemanticTokensOptions,
+    request::WorkDoneProgressCreate, CodeLens, CodeLensParams, Diagnostic,
+    DidChangeConfigurationParams, DidChangeTextDocumentParams, DidChangeWatchedFilesParams,
+    DidChangeWatchedFilesRegistrationOptions, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, DocumentFilter, DocumentFormattingParams, DocumentHighlight,
+    DocumentHighlightKind, DocumentHighlightParams, DocumentOnTypeFormattingOptions,
+    DocumentOnTypeFormattingParams, ExecuteCommandOptions, ExecuteCommandParams, FileChangeType,
+    FileSystemWatcher, FoldingRange, FoldingRangeParams, GlobPattern, GotoDefinitionParams,
+    GotoDefinitionResponse, Hover, HoverParams, InitializeParams, InitializeResult,
+    InitializedParams, LinkedEditingRangeParams, LinkedEditingRanges, Location, MessageType,
+    NumberOrString, Position, PrepareRenameResponse, ProgressParams, ProgressParamsValue,
+    ProgressToken, ReferenceParams, Registration, RenameOptions, RenameParams, SelectionRange,
+    SelectionRangeParams, SemanticTokens, SemanticTokensFullOptions, SemanticTokensOptions,
     SemanticTokensParams, SemanticTokensRegistrationOptions, SemanticTokensResult,
-    SemanticTokensServerCapabilities, ServerCapabilities, ServerInfo, StaticRegistrationOptions,
+    SemanticTokensServerCapabilities, ServerCapabilities, ServerInfo, SignatureHelp,
+    SignatureHelpOptions, SignatureHelpParams, StaticRegistrationOptions,
     TextDocumentChangeRegistrationOptions, TextDocumentPositionParams,
-    TextDocumentRegistrationOptions, TextEdit, Url, WorkDoneProgressOptions, WorkspaceEdit,
+    TextDocumentRegistrationOptions, TextEdit, Url, WorkDoneProgress, WorkDoneProgressBegin,
+    WorkDoneProgressCreateParams, WorkDoneProgressEnd, WorkDoneProgressOptions,
+    WorkDoneProgressReport, WorkspaceEdit,
 };
 use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
-use std::{borrow::Cow, mem};
+use std::{
+    borrow::Cow,
+    mem,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+};
 use tokio::sync::{Mutex, RwLock, RwLockMappedWriteGuard, RwLockReadGuard, RwLockWriteGuard};
 use tower_lsp::{jsonrpc, Client, ClientSocket, LanguageServer, LspService};
 use tracing::{debug, span, Level};
 
 pub struct Server {
     pub client: Client,
-    pub db: Mutex<Database>,
+    pub db: Arc<Mutex<Database>>,
     pub state: RwLock<ServerState>,
 }
 #[derive(Debug)]
@@ -125,8 +143,39 @@ impl ServerFeatures {
 pub struct AnalyzerClient {
     client: Client,
     packages_path: PackagesPath,
+    next_progress_id: AtomicU32,
 }
 impl AnalyzerClient {
+    /// Starts reporting progress using the client's `window/workDoneProgress`.
+    ///
+    /// If the client doesn't support that, the returned [`Progress`] doesn't
+    /// report anything.
+    pub async fn begin_progress(&self, title: impl Into<String>) -> Progress<'_> {
+        let id = self.next_progress_id.fetch_add(1, Ordering::Relaxed);
+        let token = NumberOrString::String(format!("candy/progress/{id}"));
+        let token = self
+            .client
+            .send_request::<WorkDoneProgressCreate>(WorkDoneProgressCreateParams {
+                token: token.clone(),
+            })
+            .await
+            .ok()
+            .map(|()| token);
+        let progress = Progress {
+            client: &self.client,
+            token,
+        };
+        progress
+            .send(WorkDoneProgress::Begin(WorkDoneProgressBegin {
+                title: title.into(),
+                cancellable: Some(false),
+                message: None,
+                percentage: Some(0),
+            }))
+            .await;
+        progress
+    }
+
     pub async fn update_status(&self, status: Option<String>) {
         self.client
             .send_notification::<ServerStatusNotification>(ServerStatusNotification {
@@ -153,6 +202,37 @@ impl AnalyzerClient {
     }
 }
 
+pub struct Progress<'a> {
+    client: &'a Client,
+    token: Option<ProgressToken>,
+}
+impl<'a> Progress<'a> {
+    pub async fn report(&self, message: impl Into<String>, percentage: u32) {
+        self.send(WorkDoneProgress::Report(WorkDoneProgressReport {
+            cancellable: Some(false),
+            message: Some(message.into()),
+            percentage: Some(percentage),
+        }))
+        .await;
+    }
+    pub async fn end(self) {
+        self.send(WorkDoneProgress::End(WorkDoneProgressEnd { message: None }))
+            .await;
+    }
+
+    async fn send(&self, progress: WorkDoneProgress) {
+        let Some(token) = &self.token else {
+            return;
+        };
+        self.client
+            .send_notification::<lsp_types::notification::Progress>(ProgressParams {
+                token: token.clone(),
+                value: ProgressParamsValue::WorkDone(progress),
+            })
+            .await;
+    }
+}
+
 impl Server {
     pub fn create(packages_path: PackagesPath) -> (LspService<Self>, ClientSocket) {
         let (service, client) = LspService::build(|client| {
@@ -163,6 +243,7 @@ impl Server {
                         AnalyzerClient {
                             client: client.clone(),
                             packages_path: packages_path.clone(),
+                            next_progress_id: AtomicU32::new(0),
                         },
                     ),
                     ir: IrFeatures::default(),
@@ -172,9 +253,9 @@ impl Server {
 
             Self {
                 client,
-                db: Mutex::new(Database::new_with_file_system_module_provider(
+                db: Arc::new(Mutex::new(Database::new_with_file_system_module_provider(
                     packages_path,
-                )),
+                ))),
                 state: RwLock::new(state),
             }
         })
@@ -187,6 +268,8 @@ impl Server {
             Self::candy_debug_adapter_message,
         )
         .custom_method("candy/viewIr", Self::candy_view_ir)
+        .custom_method("candy/irSourceMapping", Self::candy_ir_source_mapping)
+        .custom_method("candy/evaluateExpression", Self::candy_evaluate_expression)
         .finish();
 
         (service, client)
@@ -228,8 +311,19 @@ impl LanguageServer for Server {
 
         {
             let state = self.state.read().await;
+            // Older clients only send the deprecated `root_uri`.
+            #[allow(deprecated)]
+            let workspace_root = params
+                .workspace_folders
+                .as_ref()
+                .and_then(|folders| folders.first())
+                .map(|folder| &folder.uri)
+                .or(params.root_uri.as_ref())
+                .and_then(|uri| uri.to_file_path().ok());
             for features in state.require_features().all_features() {
-                features.initialize().await;
+                features
+                    .initialize(&self.db, workspace_root.as_deref())
+                    .await;
             }
         }
 
@@ -268,6 +362,10 @@ impl LanguageServer for Server {
                 debug_session_manager,
             });
         }
+        self.update_config(&ServerConfig::from_json(
+            params.initialization_options.as_ref().unwrap(),
+        ))
+        .await;
 
         Ok(InitializeResult {
             // We only support dynamic registration for now.
@@ -318,6 +416,18 @@ impl LanguageServer for Server {
                     "textDocument/definition",
                     features.registration_options_where(|it| it.supports_find_definition()),
                 ),
+                registration(
+                    "textDocument/hover",
+                    features.registration_options_where(|it| it.supports_hover()),
+                ),
+                registration(
+                    "textDocument/selectionRange",
+                    features.registration_options_where(|it| it.supports_selection_ranges()),
+                ),
+                registration(
+                    "textDocument/linkedEditingRange",
+                    features.registration_options_where(|it| it.supports_linked_editing_ranges()),
+                ),
                 registration(
                     "textDocument/references",
                     features.registration_options_where(|it| it.supports_references()),
@@ -334,6 +444,18 @@ impl LanguageServer for Server {
                     "textDocument/formatting",
                     features.registration_options_where(|it| it.supports_format()),
                 ),
+                registration(
+                    "textDocument/onTypeFormatting",
+                    OnTypeFormattingRegistrationOptions {
+                        text_document_registration_options: features
+                            .registration_options_where(|it| it.supports_format_on_type()),
+                        on_type_formatting_options: DocumentOnTypeFormattingOptions {
+                            // We indent new lines.
+                            first_trigger_character: "\n".to_string(),
+                            more_trigger_character: None,
+                        },
+                    },
+                ),
                 registration(
                     "textDocument/rename",
                     RenameRegistrationOptions {
@@ -347,6 +469,21 @@ impl LanguageServer for Server {
                         },
                     },
                 ),
+                registration(
+                    "textDocument/signatureHelp",
+                    SignatureHelpRegistrationOptions {
+                        text_document_registration_options: features
+                            .registration_options_where(|it| it.supports_signature_help()),
+                        signature_help_options: SignatureHelpOptions {
+                            // Arguments are separated by spaces.
+                            trigger_characters: Some(vec![" ".to_string()]),
+                            retrigger_characters: None,
+                            work_done_progress_options: WorkDoneProgressOptions {
+                                work_done_progress: None,
+                            },
+                        },
+                    },
+                ),
                 registration(
                     "textDocument/semanticTokens",
                     SemanticTokensServerCapabilities::SemanticTokensRegistrationOptions(
@@ -366,6 +503,37 @@ impl LanguageServer for Server {
                         },
                     ),
                 ),
+                registration(
+                    "textDocument/codeLens",
+                    features.registration_options_where(|it| it.supports_code_lenses()),
+                ),
+                registration(
+                    "workspace/executeCommand",
+                    ExecuteCommandOptions {
+                        commands: vec![
+                            RUN_MAIN_COMMAND.to_string(),
+                            FUZZ_FUNCTION_COMMAND.to_string(),
+                        ],
+                        work_done_progress_options: WorkDoneProgressOptions {
+                            work_done_progress: None,
+                        },
+                    },
+                ),
+                registration(
+                    "workspace/didChangeWatchedFiles",
+                    DidChangeWatchedFilesRegistrationOptions {
+                        watchers: vec![FileSystemWatcher {
+                            glob_pattern: GlobPattern::String("**/*.candy".to_string()),
+                            kind: None,
+                        }],
+                    },
+                ),
+                registration(
+                    "workspace/didChangeConfiguration",
+                    DidChangeConfigurationRegistrationOptions {
+                        section: "candy".to_string(),
+                    },
+                ),
             ])
             .await
             .expect("Dynamic capability registration failed.");
@@ -385,6 +553,26 @@ impl LanguageServer for Server {
         Ok(())
     }
 
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        self.update_config(&ServerConfig::from_json(&params.settings))
+            .await;
+    }
+
+    async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+        let state = self.require_running_state().await;
+        let mut db = self.db.lock().await;
+        for change in params.changes {
+            let Ok(module) = module_from_url(&change.uri, ModuleKind::Code, &state.packages_path)
+            else {
+                continue;
+            };
+            if change.typ != FileChangeType::CHANGED {
+                db.invalidate_modules_of_package(&module.package);
+            }
+            db.invalidate_module(&module);
+        }
+    }
+
     async fn did_open(&self, params: DidOpenTextDocumentParams) {
         let state = self.require_running_state().await;
         let features = self.features_from_url(&state.features, &params.text_document.uri);
@@ -410,9 +598,11 @@ impl LanguageServer for Server {
 
         let module_result = module_from_url(
             &params.text_document.uri,
-            if std::path::Path::new(params.text_document.uri.path())
-                .extension()
-                .map_or(false, |ext| ext.eq_ignore_ascii_case("candy"))
+            // Documents that aren't saved yet don't have an extension.
+            if params.text_document.uri.scheme() != "file"
+                || std::path::Path::new(params.text_document.uri.path())
+                    .extension()
+                    .map_or(false, |ext| ext.eq_ignore_ascii_case("candy"))
             {
                 ModuleKind::Code
             } else {
@@ -464,6 +654,36 @@ impl LanguageServer for Server {
         Ok(response)
     }
 
+    async fn hover(&self, params: HoverParams) -> jsonrpc::Result<Option<Hover>> {
+        let state = self.require_running_state().await;
+        let features = self.features_from_url(
+            &state.features,
+            &params.text_document_position_params.text_document.uri,
+        );
+        assert!(features.supports_hover());
+        Ok(features
+            .hover(
+                &self.db,
+                params.text_document_position_params.text_document.uri,
+                params.text_document_position_params.position,
+            )
+            .await)
+    }
+
+    async fn selection_range(
+        &self,
+        params: SelectionRangeParams,
+    ) -> jsonrpc::Result<Option<Vec<SelectionRange>>> {
+        let state = self.require_running_state().await;
+        let features = self.features_from_url(&state.features, &params.text_document.uri);
+        assert!(features.supports_selection_ranges());
+        Ok(Some(
+            features
+                .selection_ranges(&self.db, params.text_document.uri, params.positions)
+                .await,
+        ))
+    }
+
     async fn references(&self, params: ReferenceParams) -> jsonrpc::Result<Option<Vec<Location>>> {
         let uri = params.text_document_position.text_document.uri;
         let highlights = self
@@ -539,10 +759,45 @@ impl LanguageServer for Server {
         let features = self.features_from_url(&state.features, &params.text_document.uri);
         assert!(features.supports_format());
         Ok(Some(
-            features.format(&self.db, params.text_document.uri).await,
+            features
+                .format(&self.db, params.text_document.uri, params.options)
+                .await,
         ))
     }
 
+    async fn on_type_formatting(
+        &self,
+        params: DocumentOnTypeFormattingParams,
+    ) -> jsonrpc::Result<Option<Vec<TextEdit>>> {
+        let state = self.require_running_state().await;
+        let uri = params.text_document_position.text_document.uri;
+        let features = self.features_from_url(&state.features, &uri);
+        assert!(features.supports_format_on_type());
+        Ok(Some(
+            features
+                .format_on_type(
+                    &self.db,
+                    uri,
+                    params.text_document_position.position,
+                    params.options,
+                )
+                .await,
+        ))
+    }
+
+    async fn linked_editing_range(
+        &self,
+        params: LinkedEditingRangeParams,
+    ) -> jsonrpc::Result<Option<LinkedEditingRanges>> {
+        let state = self.require_running_state().await;
+        let uri = params.text_document_position_params.text_document.uri;
+        let features = self.features_from_url(&state.features, &uri);
+        assert!(features.supports_linked_editing_ranges());
+        Ok(features
+            .linked_editing_ranges(&self.db, uri, params.text_document_position_params.position)
+            .await)
+    }
+
     async fn prepare_rename(
         &self,
         params: TextDocumentPositionParams,
@@ -580,6 +835,25 @@ impl LanguageServer for Server {
         }
     }
 
+    async fn signature_help(
+        &self,
+        params: SignatureHelpParams,
+    ) -> jsonrpc::Result<Option<SignatureHelp>> {
+        let state = self.require_running_state().await;
+        let features = self.features_from_url(
+            &state.features,
+            &params.text_document_position_params.text_document.uri,
+        );
+        assert!(features.supports_signature_help());
+        Ok(features
+            .signature_help(
+                &self.db,
+                params.text_document_position_params.text_document.uri,
+                params.text_document_position_params.position,
+            )
+            .await)
+    }
+
     async fn semantic_tokens_full(
         &self,
         params: SemanticTokensParams,
@@ -594,8 +868,38 @@ impl LanguageServer for Server {
             data: tokens,
         })))
     }
+
+    async fn code_lens(&self, params: CodeLensParams) -> jsonrpc::Result<Option<Vec<CodeLens>>> {
+        let state = self.require_running_state().await;
+        let uri = params.text_document.uri;
+        let features = self.features_from_url(&state.features, &uri);
+        assert!(features.supports_code_lenses());
+        Ok(Some(features.code_lenses(&self.db, uri).await))
+    }
+
+    async fn execute_command(
+        &self,
+        params: ExecuteCommandParams,
+    ) -> jsonrpc::Result<Option<serde_json::Value>> {
+        let execution = Execution::from_command(params).map_err(jsonrpc::Error::invalid_params)?;
+        debug!("Starting execution: {execution:?}");
+        let db = self.db.lock().await;
+        let execution_id = execution
+            .start(&db, self.client.clone())
+            .map_err(jsonrpc::Error::invalid_params)?;
+        Ok(Some(serde_json::json!({ "executionId": execution_id })))
+    }
 }
 impl Server {
+    async fn update_config(&self, config: &ServerConfig) {
+        debug!("Updating the configuration: {config:?}");
+        config.log_level.apply();
+        let state = self.state.read().await;
+        for features in state.require_features().all_features() {
+            features.update_config(config).await;
+        }
+    }
+
     async fn references_raw(
         &self,
         uri: Url,
@@ -619,6 +923,24 @@ impl Server {
     }
 }
 
+/// <https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#documentOnTypeFormattingRegistrationOptions>
+#[derive(Debug, Eq, PartialEq, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OnTypeFormattingRegistrationOptions {
+    #[serde(flatten)]
+    pub text_document_registration_options: TextDocumentRegistrationOptions,
+
+    #[serde(flatten)]
+    pub on_type_formatting_options: DocumentOnTypeFormattingOptions,
+}
+
+/// <https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#didChangeConfigurationRegistrationOptions>
+#[derive(Debug, Eq, PartialEq, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidChangeConfigurationRegistrationOptions {
+    pub section: String,
+}
+
 /// <https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#renameRegistrationOptions>
 #[derive(Debug, Eq, PartialEq, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -629,3 +951,14 @@ pub struct RenameRegistrationOptions {
     #[serde(flatten)]
     pub rename_options: RenameOptions,
 }
+
+/// <https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#signatureHelpRegistrationOptions>
+#[derive(Debug, Eq, PartialEq, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureHelpRegistrationOptions {
+    #[serde(flatten)]
+    pub text_document_registration_options: TextDocumentRegistrationOptions,
+
+    #[serde(flatten)]
+    pub signature_help_options: SignatureHelpOptions,
+}
@@ -3,11 +3,11 @@ use candy_frontend::{
     cst::CstDb,
     error::CompilerError,
     module::{Module, ModuleDb, ModuleKind, Package, PackagesPath},
-    position::{line_start_offsets_raw, Offset, PositionConversionDb},
+    position::{LineIndex, Offset, PositionConversionDb},
 };
 use extension_trait::extension_trait;
 use itertools::Itertools;
-use lsp_types::{Diagnostic, DiagnosticSeverity, Position, Url};
+use lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Position, Url};
 use std::ops::Range;
 
 #[must_use]
@@ -30,7 +30,7 @@ pub fn error_to_diagnostic(db: &Database, module: Module, error: &CompilerError)
     Diagnostic {
         range: db.range_to_lsp_range(module, error.span.clone()),
         severity: Some(DiagnosticSeverity::ERROR),
-        code: None,
+        code: Some(NumberOrString::String(error.payload.code().to_owned())),
         code_description: None,
         source: Some("🍭 Candy".to_owned()),
         message: error.payload.to_string(),
@@ -48,18 +48,15 @@ pub fn module_from_url(
     match url.scheme() {
         "file" => Module::from_path(packages_path, &url.to_file_path().unwrap(), kind)
             .map_err(|it| it.to_string()),
-        "untitled" => Ok(Module {
+        // Documents that aren't saved to disk (like untitled ones) only exist
+        // in memory. Each of them gets its own package.
+        _ => Ok(Module {
             package: Package::Anonymous {
-                url: url
-                    .to_string()
-                    .strip_prefix("untitled:")
-                    .unwrap()
-                    .to_string(),
+                url: url.to_string(),
             },
             path: vec![],
             kind,
         }),
-        _ => Err(format!("Unsupported URI scheme: {}", url.scheme())),
     }
 }
 
@@ -77,7 +74,9 @@ pub fn module_to_url(module: &Module, packages_path: &PackagesPath) -> Option<Ur
             )
             .unwrap(),
         ),
-        Package::Anonymous { url } => Some(Url::parse(&format!("untitled:{url}")).unwrap()),
+        // Generated modules also use anonymous packages, but their "URL"
+        // isn't a valid one.
+        Package::Anonymous { url } => Url::parse(url).ok(),
         Package::Tooling(_) => None,
     }
 }
@@ -87,101 +86,42 @@ pub fn module_to_url(module: &Module, packages_path: &PackagesPath) -> Option<Ur
 #[extension_trait]
 pub impl<DB: ModuleDb + PositionConversionDb + ?Sized> LspPositionConversion for DB {
     fn lsp_position_to_offset(&self, module: Module, position: Position) -> Offset {
-        let text = self.get_module_content_as_string(module.clone()).unwrap();
-        let line_start_offsets = self.line_start_offsets(module);
-        lsp_position_to_offset_raw(&text, &line_start_offsets, position)
+        lsp_position_to_offset_raw(&self.line_index(module), position)
     }
 
     fn range_to_lsp_range(&self, module: Module, range: Range<Offset>) -> lsp_types::Range {
-        lsp_types::Range {
-            start: self.offset_to_lsp_position(module.clone(), range.start),
-            end: self.offset_to_lsp_position(module, range.end),
-        }
+        range_to_lsp_range_raw(&self.line_index(module), &range)
     }
     fn offset_to_lsp_position(&self, module: Module, offset: Offset) -> Position {
-        let text = self.get_module_content_as_string(module.clone()).unwrap();
-        let line_start_offsets = self.line_start_offsets(module);
-        offset_to_lsp_position_raw(&*text, &*line_start_offsets, offset)
+        offset_to_lsp_position_raw(&self.line_index(module), offset)
     }
 }
 
 #[must_use]
 pub fn lsp_range_to_range_raw(text: &str, range: lsp_types::Range) -> Range<Offset> {
-    let line_start_offsets = line_start_offsets_raw(text);
-    let start = lsp_position_to_offset_raw(text, &line_start_offsets, range.start);
-    let end = lsp_position_to_offset_raw(text, &line_start_offsets, range.end);
+    let line_index = LineIndex::new(text);
+    let start = lsp_position_to_offset_raw(&line_index, range.start);
+    let end = lsp_position_to_offset_raw(&line_index, range.end);
     start..end
 }
 #[must_use]
-pub fn lsp_position_to_offset_raw(
-    text: &str,
-    line_start_offsets: &[Offset],
-    position: Position,
-) -> Offset {
-    let line_offset = line_start_offsets[position.line as usize];
-    let line_length = if position.line as usize == line_start_offsets.len() - 1 {
-        text.len() - *line_offset
-    } else {
-        *line_start_offsets[(position.line + 1) as usize] - *line_offset
-    };
-
-    let line = &text[*line_offset..*line_offset + line_length];
-
-    let words = line.encode_utf16().collect::<Vec<_>>();
-    let char_offset = if position.character as usize >= words.len() {
-        line_length
-    } else {
-        String::from_utf16(&words[0..position.character as usize])
-            .unwrap()
-            .len()
-    };
-
-    Offset(*line_offset + char_offset)
+pub fn lsp_position_to_offset_raw(line_index: &LineIndex, position: Position) -> Offset {
+    line_index.utf16_position_to_offset(position.line as usize, position.character as usize)
 }
 
 #[must_use]
-pub fn range_to_lsp_range_raw<S, L>(
-    text: S,
-    line_start_offsets: L,
-    range: &Range<Offset>,
-) -> lsp_types::Range
-where
-    S: AsRef<str>,
-    L: AsRef<[Offset]>,
-{
-    let text = text.as_ref();
-    let line_start_offsets = line_start_offsets.as_ref();
+pub fn range_to_lsp_range_raw(line_index: &LineIndex, range: &Range<Offset>) -> lsp_types::Range {
     lsp_types::Range {
-        start: offset_to_lsp_position_raw(text, line_start_offsets, range.start),
-        end: offset_to_lsp_position_raw(text, line_start_offsets, range.end),
+        start: offset_to_lsp_position_raw(line_index, range.start),
+        end: offset_to_lsp_position_raw(line_index, range.end),
     }
 }
 #[must_use]
-pub fn offset_to_lsp_position_raw<S, L>(
-    text: S,
-    line_start_offsets: L,
-    mut offset: Offset,
-) -> Position
-where
-    S: AsRef<str>,
-    L: AsRef<[Offset]>,
-{
-    let text = text.as_ref();
-    let line_start_offsets = line_start_offsets.as_ref();
-
-    if *offset > text.len() {
-        *offset = text.len();
-    }
-
-    let line = line_start_offsets
-        .binary_search(&offset)
-        .unwrap_or_else(|i| i - 1);
-
-    let line_start = line_start_offsets[line];
-    let character_utf16_offset = text[*line_start..*offset].encode_utf16().count();
+pub fn offset_to_lsp_position_raw(line_index: &LineIndex, offset: Offset) -> Position {
+    let (line, character) = line_index.offset_to_utf16_position(offset);
     Position {
         line: line.try_into().unwrap(),
-        character: character_utf16_offset.try_into().unwrap(),
+        character: character.try_into().unwrap(),
     }
 }
 
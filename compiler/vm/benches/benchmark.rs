@@ -3,6 +3,7 @@ use criterion::{
     Criterion,
 };
 use criterion_cycles_per_byte::CyclesPerByte;
+use itertools::Itertools;
 use tracing::Level;
 use tracing_subscriber::{
     filter,
@@ -50,6 +51,10 @@ fn benchmark_vm_runtime<M: Measurement>(c: &mut Criterion<M>, prefix: &str) {
     benchmark!("hello_world", r#"main _ := "Hello, world!""#, 100);
     benchmark!("fibonacci", 15, create_fibonacci_code, 20);
     benchmark!("PLB/binarytrees", 6, create_binary_trees_code, 10);
+    benchmark!("struct_access", 8, create_struct_access_code, 20);
+    benchmark!("struct_access", 64, create_struct_access_code, 10);
+    benchmark!("struct_access", 512, create_struct_access_code, 10);
+    benchmark!("record_access", 50, create_record_access_code, 10);
 
     group.finish();
 }
@@ -71,6 +76,35 @@ fib n =
 main _ := fib {n}"#,
     )
 }
+/// Reads every field of a struct with `n` fields.
+fn create_struct_access_code(n: usize) -> String {
+    let fields = (0..n).map(|i| format!("Field{i}: {i}")).join(", ");
+    format!(
+        r#"[int, iterator, result, struct] = use "Core"
+
+main _ :=
+  fields = [{fields}]
+  fields
+  | struct.getKeys
+  | iterator.fromList
+  | iterator.map {{ key -> fields | struct.get key | result.unwrap }}
+  | iterator.sum
+"#,
+    )
+}
+/// Creates `n` records and reads their fields, so each field access always
+/// sees records of the same shape.
+fn create_record_access_code(n: usize) -> String {
+    format!(
+        r#"[int, iterator] = use "Core"
+
+main _ :=
+  iterator.generate {n} {{ i -> [X: i, Y: i | int.add 1, Z: 2] }}
+  | iterator.map {{ point -> point.x | int.multiply point.y | int.add point.z }}
+  | iterator.sum
+"#,
+    )
+}
 /// https://programming-language-benchmarks.vercel.app/problem/binarytrees
 fn create_binary_trees_code(n: usize) -> String {
     format!(
@@ -6,7 +6,7 @@ use candy_frontend::{
     hir::HirDbStorage,
     hir_to_mir::{ExecutionTarget, HirToMirStorage},
     lir_optimize::OptimizeLirStorage,
-    mir_optimize::OptimizeMirStorage,
+    mir_optimize::{MirPassesOwner, OptimizeMirStorage},
     mir_to_lir::MirToLirStorage,
     module::{
         GetModuleContentQuery, InMemoryModuleProvider, Module, ModuleDbStorage, ModuleKind,
@@ -14,7 +14,7 @@ use candy_frontend::{
     },
     position::PositionConversionStorage,
     rcst_to_cst::RcstToCstStorage,
-    string_to_rcst::StringToRcstStorage,
+    string_to_rcst::{RcstCacheOwner, StringToRcstStorage},
     CallTracingMode, TracingConfig, TracingMode,
 };
 use candy_vm::{
@@ -33,6 +33,8 @@ const TRACING: TracingConfig = TracingConfig {
     register_fuzzables: TracingMode::Off,
     calls: CallTracingMode::OnlyForPanicTraces,
     evaluated_expressions: TracingMode::Off,
+    memoize_calls: false,
+    overrides: Vec::new(),
 };
 lazy_static! {
     static ref PACKAGE: Package = Package::User("/".into());
@@ -69,6 +71,8 @@ impl ModuleProviderOwner for Database {
         &self.module_provider
     }
 }
+impl RcstCacheOwner for Database {}
+impl MirPassesOwner for Database {}
 impl MutableModuleProviderOwner for Database {
     fn get_in_memory_module_provider(&mut self) -> &mut InMemoryModuleProvider {
         &mut self.module_provider
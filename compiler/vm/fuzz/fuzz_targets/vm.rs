@@ -8,7 +8,7 @@ use candy_frontend::{
     hir::HirDbStorage,
     hir_to_mir::{ExecutionTarget, HirToMirStorage},
     lir_optimize::OptimizeLirStorage,
-    mir_optimize::OptimizeMirStorage,
+    mir_optimize::{MirPassesOwner, OptimizeMirStorage},
     mir_to_lir::MirToLirStorage,
     module::{
         InMemoryModuleProvider, Module, ModuleDbStorage, ModuleKind, ModuleProvider,
@@ -16,7 +16,7 @@ use candy_frontend::{
     },
     position::PositionConversionStorage,
     rcst_to_cst::RcstToCstStorage,
-    string_to_rcst::StringToRcstStorage,
+    string_to_rcst::{RcstCacheOwner, StringToRcstStorage},
     TracingConfig,
 };
 use candy_vm::{
@@ -64,6 +64,8 @@ impl ModuleProviderOwner for Database {
         &self.module_provider
     }
 }
+impl RcstCacheOwner for Database {}
+impl MirPassesOwner for Database {}
 
 fuzz_target!(|data: &[u8]| {
     let mut db = Database::default();
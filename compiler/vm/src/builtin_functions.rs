@@ -1,17 +1,30 @@
 use crate::{
-    heap::{Data, Function, Heap, HirId, InlineObject, Int, List, Struct, Tag, Text, ToDebugText},
+    byte_code::ByteCode,
+    heap::{
+        Bytes, Data, DataDiscriminants, Float, Function, Heap, HirId, InlineObject, Int, List,
+        Struct, Tag, Text, ToDebugText,
+    },
     instructions::InstructionResult,
-    vm::{CallHandle, MachineState, Panic},
+    memoization::MemoizationCache,
+    tracer::{stack_trace::StackTracer, Tracer},
+    vm::{CallHandle, MachineState, Panic, DEFAULT_MAX_CALL_DEPTH},
 };
 use candy_frontend::{
     builtin_functions::BuiltinFunction,
     format::{MaxLength, Precedence},
+    utils::DoHash,
 };
 use derive_more::Deref;
 use itertools::Itertools;
 use num_bigint::BigInt;
+use num_traits::Signed;
 use paste::paste;
+use rustc_hash::FxHashMap;
 use std::{
+    cmp,
+    fmt::{self, Display, Formatter},
+    mem,
+    ops::Range,
     str::FromStr,
     sync::atomic::{AtomicBool, Ordering},
 };
@@ -26,14 +39,31 @@ impl MachineState {
     pub(super) fn run_builtin_function(
         &mut self,
         heap: &mut Heap,
+        byte_code: &ByteCode,
+        tracer: &mut impl Tracer,
         builtin_function: BuiltinFunction,
         args: &[InlineObject],
         responsible: HirId,
     ) -> InstructionResult {
         let result = span!(Level::TRACE, "Running builtin").in_scope(|| match &builtin_function {
+            BuiltinFunction::BytesConcat => heap.bytes_concat(args),
+            BuiltinFunction::BytesGet => heap.bytes_get(args),
+            BuiltinFunction::BytesLength => heap.bytes_length(args),
+            BuiltinFunction::BytesSlice => heap.bytes_slice(args),
+            BuiltinFunction::CompareTo => heap.compare_to(args),
             BuiltinFunction::Equals => heap.equals(args),
+            BuiltinFunction::FloatAdd => heap.float_add(args),
+            BuiltinFunction::FloatCompareTo => heap.float_compare_to(args),
+            BuiltinFunction::FloatDivide => heap.float_divide(args),
+            BuiltinFunction::FloatFromInt => heap.float_from_int(args),
+            BuiltinFunction::FloatMultiply => heap.float_multiply(args),
+            BuiltinFunction::FloatSubtract => heap.float_subtract(args),
+            BuiltinFunction::FloatToText => heap.float_to_text(args),
+            BuiltinFunction::FloatTruncate => heap.float_truncate(args),
             BuiltinFunction::FunctionRun => Heap::function_run(args, responsible),
+            BuiltinFunction::FunctionTry => heap.function_try(args, responsible, byte_code, tracer),
             BuiltinFunction::GetArgumentCount => heap.get_argument_count(args),
+            BuiltinFunction::HashValue => heap.hash_value(args),
             BuiltinFunction::IfElse => heap.if_else(args, responsible),
             BuiltinFunction::IntAdd => heap.int_add(args),
             BuiltinFunction::IntBitLength => heap.int_bit_length(args),
@@ -42,23 +72,37 @@ impl MachineState {
             BuiltinFunction::IntBitwiseXor => heap.int_bitwise_xor(args),
             BuiltinFunction::IntCompareTo => heap.int_compare_to(args),
             BuiltinFunction::IntDivideTruncating => heap.int_divide_truncating(args),
+            BuiltinFunction::IntGcd => heap.int_gcd(args),
             BuiltinFunction::IntModulo => heap.int_modulo(args),
             BuiltinFunction::IntMultiply => heap.int_multiply(args),
             BuiltinFunction::IntParse => heap.int_parse(args),
+            BuiltinFunction::IntPow => heap.int_pow(args),
             BuiltinFunction::IntRemainder => heap.int_remainder(args),
             BuiltinFunction::IntShiftLeft => heap.int_shift_left(args),
             BuiltinFunction::IntShiftRight => heap.int_shift_right(args),
+            BuiltinFunction::IntSqrt => heap.int_sqrt(args),
             BuiltinFunction::IntSubtract => heap.int_subtract(args),
+            BuiltinFunction::ListConcatenate => heap.list_concatenate(args),
             BuiltinFunction::ListFilled => heap.list_filled(args),
             BuiltinFunction::ListGet => heap.list_get(args),
             BuiltinFunction::ListInsert => heap.list_insert(args),
             BuiltinFunction::ListLength => heap.list_length(args),
             BuiltinFunction::ListRemoveAt => heap.list_remove_at(args),
             BuiltinFunction::ListReplace => heap.list_replace(args),
+            BuiltinFunction::ListSlice => heap.list_slice(args),
+            BuiltinFunction::ListSort => heap.list_sort(args, responsible, byte_code, tracer),
+            BuiltinFunction::Panic => heap.panic(args, responsible),
             BuiltinFunction::Print => heap.print(args),
+            BuiltinFunction::RegexCaptures => heap.regex_captures(args),
+            BuiltinFunction::RegexFind => heap.regex_find(args),
+            BuiltinFunction::RegexIsMatch => heap.regex_is_match(args),
+            BuiltinFunction::StructEntries => heap.struct_entries(args),
             BuiltinFunction::StructGet => heap.struct_get(args),
             BuiltinFunction::StructGetKeys => heap.struct_get_keys(args),
             BuiltinFunction::StructHasKey => heap.struct_has_key(args),
+            BuiltinFunction::StructInsert => heap.struct_insert(args),
+            BuiltinFunction::StructMerge => heap.struct_merge(args),
+            BuiltinFunction::StructRemove => heap.struct_remove(args),
             BuiltinFunction::TagGetValue => heap.tag_get_value(args),
             BuiltinFunction::TagHasValue => heap.tag_has_value(args),
             BuiltinFunction::TagWithoutValue => heap.tag_without_value(args),
@@ -69,11 +113,17 @@ impl MachineState {
             BuiltinFunction::TextEndsWith => heap.text_ends_with(args),
             BuiltinFunction::TextFromUtf8 => heap.text_from_utf8(args),
             BuiltinFunction::TextGetRange => heap.text_get_range(args),
+            BuiltinFunction::TextIndexOf => heap.text_index_of(args),
             BuiltinFunction::TextIsEmpty => heap.text_is_empty(args),
             BuiltinFunction::TextLength => heap.text_length(args),
+            BuiltinFunction::TextReplace => heap.text_replace(args),
+            BuiltinFunction::TextSplit => heap.text_split(args),
             BuiltinFunction::TextStartsWith => heap.text_starts_with(args),
             BuiltinFunction::TextTrimEnd => heap.text_trim_end(args),
             BuiltinFunction::TextTrimStart => heap.text_trim_start(args),
+            BuiltinFunction::TextToLower => heap.text_to_lower(args),
+            BuiltinFunction::TextToUpper => heap.text_to_upper(args),
+            BuiltinFunction::TextToUtf8Bytes => heap.text_to_utf8_bytes(args),
             BuiltinFunction::ToDebugText => heap.to_debug_text(args),
             BuiltinFunction::TypeOf => heap.type_of(args),
         });
@@ -88,15 +138,109 @@ impl MachineState {
                 responsible,
             }) => self.call_function(function, &[], responsible),
             Ok(CallHandle(call)) => InstructionResult::CallHandle(call),
-            Err(reason) => InstructionResult::Panic(Panic {
-                reason,
+            Ok(SuccessfulBehavior::Panic(panic)) => InstructionResult::Panic(panic),
+            Err(error) => InstructionResult::Panic(Panic {
+                reason: error.to_string(),
                 responsible: responsible.get().clone(),
+                builtin_error: Some(error),
+                payload: None,
             }),
         }
     }
 }
 
-type BuiltinResult = Result<SuccessfulBehavior, String>;
+/// Why a builtin function failed.
+///
+/// Usually, the wrappers in the `Builtins` package check the arguments with
+/// `needs` before calling a builtin, so these errors only occur if a builtin is
+/// called directly or fails for reasons that can't be checked beforehand.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BuiltinError {
+    WrongArgumentCount {
+        expected: usize,
+        actual: usize,
+    },
+    TypeMismatch {
+        expected: &'static str,
+        actual: &'static str,
+    },
+    NotAByte {
+        value: String,
+    },
+    DivideByZero,
+    NegativeExponent,
+    ExponentTooLarge,
+    SquareRootOfNegative,
+    NegativeShiftAmount,
+    ShiftAmountTooLarge,
+    IndexOutOfBounds {
+        index: BigInt,
+        length: usize,
+    },
+    InvalidLength {
+        length: BigInt,
+        max: usize,
+    },
+    InvalidRegex(String),
+    ComparatorCalledHandle,
+    ComparatorPanicked {
+        reason: String,
+    },
+    InvalidComparatorResult(&'static str),
+    TriedFunctionCalledHandle,
+}
+impl Display for BuiltinError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::WrongArgumentCount { expected, actual } => write!(
+                f,
+                "A builtin function expected {expected} arguments, but got {actual}.",
+            ),
+            Self::TypeMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "Expected a value of type `{expected}`, but got `{actual}`."
+                )
+            }
+            Self::NotAByte { value } => write!(f, "Value is not a byte: {value}."),
+            Self::DivideByZero => write!(f, "Tried to divide by zero."),
+            Self::NegativeExponent => write!(f, "The exponent must not be negative."),
+            Self::ExponentTooLarge => write!(f, "The exponent is too large."),
+            Self::SquareRootOfNegative => {
+                write!(f, "Tried to take the square root of a negative number.")
+            }
+            Self::NegativeShiftAmount => write!(f, "The shift amount must not be negative."),
+            Self::ShiftAmountTooLarge => write!(f, "Shifts by that much are not supported."),
+            Self::IndexOutOfBounds { index, length } => {
+                write!(
+                    f,
+                    "Index {index} is out of bounds for a length of {length}."
+                )
+            }
+            Self::InvalidLength { length, max } => {
+                write!(f, "Length {length} is not between 0 and {max}.")
+            }
+            Self::InvalidRegex(error) => write!(f, "Invalid regex: {error}"),
+            Self::ComparatorCalledHandle => write!(
+                f,
+                "The comparator of a sort called a handle, which is not supported.",
+            ),
+            Self::ComparatorPanicked { reason } => {
+                write!(f, "The comparator of a sort panicked: {reason}")
+            }
+            Self::InvalidComparatorResult(error) => write!(
+                f,
+                "The comparator of a sort returned an invalid value: {error}",
+            ),
+            Self::TriedFunctionCalledHandle => write!(
+                f,
+                "A function run using `✨.functionTry` called a handle, which is not supported.",
+            ),
+        }
+    }
+}
+
+type BuiltinResult = Result<SuccessfulBehavior, BuiltinError>;
 enum SuccessfulBehavior {
     Return(InlineObject),
     DivergeControlFlow {
@@ -104,6 +248,8 @@ enum SuccessfulBehavior {
         responsible: HirId,
     },
     CallHandle(CallHandle),
+    /// Used by `✨.panic`, which panics intentionally.
+    Panic(Panic),
 }
 
 impl From<SuccessfulBehavior> for BuiltinResult {
@@ -118,12 +264,18 @@ macro_rules! unpack {
             let ( $( $arg, )+ ) = if let [$( $arg, )+] = $args {
                 ( $( *$arg, )+ )
             } else {
-                panic!("A builtin function was called with the wrong number of arguments.");
+                return Err(BuiltinError::WrongArgumentCount {
+                    expected: [$( stringify!($arg), )+].len(),
+                    actual: $args.len(),
+                });
             };
             let ( $( $arg, )+ ): ( $( UnpackedData<$type>, )+ ) = ( $(
                 UnpackedData {
                     object: $arg,
-                    data: $arg.try_into().unwrap(),
+                    data: $arg.try_into().map_err(|_| BuiltinError::TypeMismatch {
+                        expected: stringify!($type),
+                        actual: DataDiscriminants::from(Data::from($arg)).into(),
+                    })?,
                 },
             )+ );
 
@@ -137,12 +289,18 @@ macro_rules! unpack_and_later_drop {
             let ( $( $arg, )+ ) = if let [$( $arg, )+] = $args {
                 ( $( *$arg, )+ )
             } else {
-                panic!("A builtin function was called with the wrong number of arguments.");
+                return Err(BuiltinError::WrongArgumentCount {
+                    expected: [$( stringify!($arg), )+].len(),
+                    actual: $args.len(),
+                });
             };
             let ( $( $arg, )+ ): ( $( UnpackedData<$type>, )+ ) = ( $(
                 UnpackedData {
                     object: $arg,
-                    data: $arg.try_into().unwrap(),
+                    data: $arg.try_into().map_err(|_| BuiltinError::TypeMismatch {
+                        expected: stringify!($type),
+                        actual: DataDiscriminants::from(Data::from($arg)).into(),
+                    })?,
                 },
             )+ );
 
@@ -162,13 +320,131 @@ macro_rules! unpack_and_later_drop {
 #[allow(clippy::enum_glob_use)]
 use SuccessfulBehavior::*;
 
+/// Converts the `index` into a `usize` that is less than the `length` (or equal
+/// to it if `allow_end` is set).
+fn checked_index(index: Int, length: usize, allow_end: bool) -> Result<usize, BuiltinError> {
+    index
+        .try_get::<usize>()
+        .filter(|it| *it < length || (allow_end && *it == length))
+        .ok_or_else(|| BuiltinError::IndexOutOfBounds {
+            index: index.get().into_owned(),
+            length,
+        })
+}
+fn checked_range(
+    start_inclusive: Int,
+    end_exclusive: Int,
+    length: usize,
+) -> Result<Range<usize>, BuiltinError> {
+    let end_exclusive = checked_index(end_exclusive, length, true)?;
+    let start_inclusive = checked_index(start_inclusive, end_exclusive, true)?;
+    Ok(start_inclusive..end_exclusive)
+}
+/// Converts the `length` into a `usize` that is at most `max`.
+fn checked_length(length: Int, max: usize) -> Result<usize, BuiltinError> {
+    length
+        .try_get::<usize>()
+        .filter(|it| *it <= max)
+        .ok_or_else(|| BuiltinError::InvalidLength {
+            length: length.get().into_owned(),
+            max,
+        })
+}
+/// Converts the shift `amount` into a `u32`, returning `None` if it's larger.
+fn checked_shift_amount(amount: Int) -> Result<Option<u32>, BuiltinError> {
+    if amount.get().is_negative() {
+        return Err(BuiltinError::NegativeShiftAmount);
+    }
+    Ok(amount.try_get::<u32>())
+}
+fn check_divisor(divisor: Int) -> Result<(), BuiltinError> {
+    if divisor.try_get::<i64>() == Some(0) {
+        Err(BuiltinError::DivideByZero)
+    } else {
+        Ok(())
+    }
+}
+
 impl Heap {
+    fn bytes_concat(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |a: Bytes, b: Bytes| {
+            Return(a.concatenate(self, *b).into())
+        })
+    }
+    fn bytes_get(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |bytes: Bytes, index: Int| {
+            let index = checked_index(*index, bytes.len(), false)?;
+            Return(bytes.get_at(self, index).into())
+        })
+    }
+    fn bytes_length(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |bytes: Bytes| {
+            Return(bytes.length(self).into())
+        })
+    }
+    fn bytes_slice(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(
+            self,
+            args,
+            |bytes: Bytes, start_inclusive: Int, end_exclusive: Int| {
+                checked_range(*start_inclusive, *end_exclusive, bytes.len())?;
+                Return(bytes.slice(self, *start_inclusive..*end_exclusive).into())
+            }
+        )
+    }
+
+    fn compare_to(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |a: Any, b: Any| {
+            Return(Tag::create_ordering(self, (**a).cmp(&**b)).into())
+        })
+    }
     fn equals(&mut self, args: &[InlineObject]) -> BuiltinResult {
         unpack_and_later_drop!(self, args, |a: Any, b: Any| {
             Return(Tag::create_bool(self, **a == **b).into())
         })
     }
 
+    fn float_add(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |a: Float, b: Float| {
+            Return(a.add(self, *b).into())
+        })
+    }
+    fn float_compare_to(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |a: Float, b: Float| {
+            Return(a.compare_to(self, *b).into())
+        })
+    }
+    fn float_divide(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |dividend: Float, divisor: Float| {
+            Return(dividend.divide(self, *divisor).into())
+        })
+    }
+    fn float_from_int(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |int: Int| {
+            Return(Float::create_from_int(self, true, *int).into())
+        })
+    }
+    fn float_multiply(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |factor_a: Float, factor_b: Float| {
+            Return(factor_a.multiply(self, *factor_b).into())
+        })
+    }
+    fn float_subtract(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |minuend: Float, subtrahend: Float| {
+            Return(minuend.subtract(self, *subtrahend).into())
+        })
+    }
+    fn float_to_text(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |float: Float| {
+            Return(float.to_text(self).into())
+        })
+    }
+    fn float_truncate(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |float: Float| {
+            Return(float.truncate(self).into())
+        })
+    }
+
     fn function_run(args: &[InlineObject], responsible: HirId) -> BuiltinResult {
         unpack!(self, args, |function: Any| {
             match **function {
@@ -188,6 +464,66 @@ impl Heap {
             }
         })
     }
+    /// Runs the function and returns `Ok returnValue` or, if it panics,
+    /// `Error [Reason: reason, Payload: payload, StackTrace: stackTrace]`. The
+    /// payload is only present if the panic was caused by `✨.panic`. The stack
+    /// trace is a list of texts that starts with the innermost call and only
+    /// contains calls inside the function. It's empty if calls aren't traced.
+    ///
+    /// Like [`Heap::list_sort`], this runs the function to completion in a
+    /// separate machine state. Calls that didn't finish because of the panic
+    /// are ended in the tracer.
+    fn function_try(
+        &mut self,
+        args: &[InlineObject],
+        responsible: HirId,
+        byte_code: &ByteCode,
+        tracer: &mut impl Tracer,
+    ) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |function: Any| {
+            let mut tracer = TryScopeTracer {
+                inner: tracer,
+                stack_tracer: StackTracer::default(),
+            };
+            let result = match run_to_completion(
+                self,
+                byte_code,
+                &mut tracer,
+                function.object,
+                &[],
+                responsible,
+            ) {
+                Ok(return_value) => Ok(return_value),
+                Err(NestedRunError::CalledHandle) => {
+                    return Err(BuiltinError::TriedFunctionCalledHandle);
+                }
+                Err(NestedRunError::Panicked(panic)) => {
+                    tracer.inner.panicked(self, &panic);
+                    let stack_trace = tracer
+                        .stack_tracer
+                        .format_calls_without_positions()
+                        .into_iter()
+                        .map(|call| Text::create(self, true, &call).into())
+                        .collect_vec();
+                    while !tracer.stack_tracer.call_stack.is_empty() {
+                        tracer.call_ended(self, None);
+                    }
+
+                    let reason = Text::create(self, true, &panic.reason);
+                    let stack_trace = List::create(self, true, &stack_trace);
+                    let mut fields = vec![
+                        (self.default_symbols().reason, reason.into()),
+                        (self.default_symbols().stack_trace, stack_trace.into()),
+                    ];
+                    if let Some(payload) = panic.payload {
+                        fields.push((self.default_symbols().payload, payload));
+                    }
+                    Err(Struct::create_with_symbol_keys(self, true, fields).into())
+                }
+            };
+            Return(Tag::create_result(self, true, result).into())
+        })
+    }
     fn get_argument_count(&mut self, args: &[InlineObject]) -> BuiltinResult {
         unpack_and_later_drop!(self, args, |function: Any| {
             let count = match **function {
@@ -200,6 +536,12 @@ impl Heap {
         })
     }
 
+    fn hash_value(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |value: Any| {
+            Return(Int::create(self, true, value.data.do_hash()).into())
+        })
+    }
+
     fn if_else(&mut self, args: &[InlineObject], responsible: HirId) -> BuiltinResult {
         unpack!(self, args, |condition: Tag,
                              then: Function,
@@ -250,11 +592,18 @@ impl Heap {
     }
     fn int_divide_truncating(&mut self, args: &[InlineObject]) -> BuiltinResult {
         unpack_and_later_drop!(self, args, |dividend: Int, divisor: Int| {
+            check_divisor(*divisor)?;
             Return(dividend.int_divide_truncating(self, *divisor).into())
         })
     }
+    fn int_gcd(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |a: Int, b: Int| {
+            Return(a.gcd(self, *b).into())
+        })
+    }
     fn int_modulo(&mut self, args: &[InlineObject]) -> BuiltinResult {
         unpack_and_later_drop!(self, args, |dividend: Int, divisor: Int| {
+            check_divisor(*divisor)?;
             Return(dividend.modulo(self, *divisor).into())
         })
     }
@@ -282,30 +631,66 @@ impl Heap {
             Return(Tag::create_result(self, true, result).into())
         })
     }
+    fn int_pow(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |base: Int, exponent: Int| {
+            if exponent.get().is_negative() {
+                return Err(BuiltinError::NegativeExponent);
+            }
+            let exponent = exponent
+                .try_get::<u32>()
+                .ok_or(BuiltinError::ExponentTooLarge)?;
+            Return(base.pow(self, exponent).into())
+        })
+    }
     fn int_remainder(&mut self, args: &[InlineObject]) -> BuiltinResult {
         unpack_and_later_drop!(self, args, |dividend: Int, divisor: Int| {
+            check_divisor(*divisor)?;
             Return(dividend.remainder(self, *divisor).into())
         })
     }
     fn int_shift_left(&mut self, args: &[InlineObject]) -> BuiltinResult {
         unpack_and_later_drop!(self, args, |value: Int, amount: Int| {
+            // Larger shifts would create ints that take up gigabytes.
+            checked_shift_amount(*amount)?.ok_or(BuiltinError::ShiftAmountTooLarge)?;
             Return(value.shift_left(self, *amount).into())
         })
     }
     fn int_shift_right(&mut self, args: &[InlineObject]) -> BuiltinResult {
         unpack_and_later_drop!(self, args, |value: Int, amount: Int| {
+            if checked_shift_amount(*amount)?.is_none() {
+                // All bits are shifted out.
+                let result = if value.get().is_negative() { -1 } else { 0 };
+                return Ok(Return(Int::create(self, true, result).into()));
+            }
             Return(value.shift_right(self, *amount).into())
         })
     }
+    fn int_sqrt(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |value: Int| {
+            if value.get().is_negative() {
+                return Err(BuiltinError::SquareRootOfNegative);
+            }
+            Return(value.sqrt(self).into())
+        })
+    }
     fn int_subtract(&mut self, args: &[InlineObject]) -> BuiltinResult {
         unpack_and_later_drop!(self, args, |minuend: Int, subtrahend: Int| {
             Return(minuend.subtract(self, *subtrahend).into())
         })
     }
 
+    fn list_concatenate(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |a: List, b: List| {
+            let new_list = a.concatenate(self, **b);
+            for item in new_list.items() {
+                item.dup(self);
+            }
+            Return(new_list.into())
+        })
+    }
     fn list_filled(&mut self, args: &[InlineObject]) -> BuiltinResult {
         unpack!(self, args, |length: Int, item: Any| {
-            let length_usize = length.try_get().unwrap();
+            let length_usize = checked_length(*length, List::MAX_LEN)?;
             length.object.drop(self);
 
             let item_object = item.object;
@@ -320,7 +705,7 @@ impl Heap {
     }
     fn list_get(&mut self, args: &[InlineObject]) -> BuiltinResult {
         unpack_and_later_drop!(self, args, |list: List, index: Int| {
-            let index = index.try_get().unwrap();
+            let index = checked_index(*index, list.len(), false)?;
             let item = list.get(index);
             item.dup(self);
             Return(item)
@@ -328,7 +713,7 @@ impl Heap {
     }
     fn list_insert(&mut self, args: &[InlineObject]) -> BuiltinResult {
         unpack!(self, args, |list: List, index: Int, item: Any| {
-            let index_usize = index.try_get().unwrap();
+            let index_usize = checked_index(*index, list.len(), true)?;
             index.object.drop(self);
 
             let new_list = list.insert(self, index_usize, item.object).into();
@@ -343,12 +728,13 @@ impl Heap {
     }
     fn list_remove_at(&mut self, args: &[InlineObject]) -> BuiltinResult {
         unpack_and_later_drop!(self, args, |list: List, index: Int| {
-            Return(list.remove(self, index.try_get().unwrap()).into())
+            let index = checked_index(*index, list.len(), false)?;
+            Return(list.remove(self, index).into())
         })
     }
     fn list_replace(&mut self, args: &[InlineObject]) -> BuiltinResult {
         unpack!(self, args, |list: List, index: Int, new_item: Any| {
-            let index_usize = index.try_get().unwrap();
+            let index_usize = checked_index(*index, list.len(), false)?;
             index.object.drop(self);
 
             list.get(index_usize).drop(self);
@@ -358,6 +744,106 @@ impl Heap {
             Return(new_list)
         })
     }
+    fn list_slice(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(
+            self,
+            args,
+            |list: List, start_inclusive: Int, end_exclusive: Int| {
+                let range = checked_range(*start_inclusive, *end_exclusive, list.len())?;
+                let new_list = list.slice(self, range);
+                for item in new_list.items() {
+                    item.dup(self);
+                }
+                Return(new_list.into())
+            }
+        )
+    }
+    /// Sorts the list using a Candy function that compares two items.
+    ///
+    /// A builtin can only diverge its control flow once, but sorting needs
+    /// many comparisons. Hence, we run each call of the comparator using
+    /// [`run_to_completion`].
+    fn list_sort(
+        &mut self,
+        args: &[InlineObject],
+        responsible: HirId,
+        byte_code: &ByteCode,
+        tracer: &mut impl Tracer,
+    ) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |list: List, compare: Any| {
+            let mut compare = |heap: &mut Self, a: InlineObject, b: InlineObject| {
+                a.dup(heap);
+                b.dup(heap);
+
+                let result = run_to_completion(
+                    heap,
+                    byte_code,
+                    tracer,
+                    compare.object,
+                    &[a, b],
+                    responsible,
+                )
+                .map_err(|error| match error {
+                    NestedRunError::CalledHandle => BuiltinError::ComparatorCalledHandle,
+                    NestedRunError::Panicked(panic) => BuiltinError::ComparatorPanicked {
+                        reason: panic.reason,
+                    },
+                })?;
+                let ordering = Tag::try_from(result)
+                    .map_err(|_| "Expected a tag.")
+                    .and_then(|tag| tag.try_into_ordering(heap))
+                    .map_err(BuiltinError::InvalidComparatorResult);
+                result.drop(heap);
+                ordering
+            };
+
+            // A stable, bottom-up merge sort. Unlike the standard library's
+            // sort, it supports comparators that fail or that don't implement
+            // a total order.
+            let mut items = list.items().to_vec();
+            let mut buffer = Vec::with_capacity(items.len());
+            let mut width = 1;
+            while width < items.len() {
+                buffer.clear();
+                for start in (0..items.len()).step_by(2 * width) {
+                    let middle = cmp::min(start + width, items.len());
+                    let end = cmp::min(start + 2 * width, items.len());
+                    let (mut left, mut right) = (start, middle);
+                    while left < middle && right < end {
+                        if compare(self, items[right], items[left])? == cmp::Ordering::Less {
+                            buffer.push(items[right]);
+                            right += 1;
+                        } else {
+                            buffer.push(items[left]);
+                            left += 1;
+                        }
+                    }
+                    buffer.extend_from_slice(&items[left..middle]);
+                    buffer.extend_from_slice(&items[right..end]);
+                }
+                mem::swap(&mut items, &mut buffer);
+                width *= 2;
+            }
+
+            for item in &items {
+                item.dup(self);
+            }
+            Return(List::create(self, true, &items).into())
+        })
+    }
+
+    fn panic(&mut self, args: &[InlineObject], responsible: HirId) -> BuiltinResult {
+        unpack!(self, args, |reason: Text, payload: Any| {
+            let panic = Panic {
+                reason: reason.get().to_string(),
+                responsible: responsible.get().clone(),
+                builtin_error: None,
+                payload: Some(payload.object),
+            };
+            reason.object.drop(self);
+            SuccessfulBehavior::Panic(panic)
+        })
+    }
 
     fn print(&mut self, args: &[InlineObject]) -> BuiltinResult {
         unpack_and_later_drop!(self, args, |message: Text| {
@@ -370,6 +856,61 @@ impl Heap {
         })
     }
 
+    fn regex_captures(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |text: Text, pattern: Text| {
+            self.regex(pattern.get())
+                .map(|regex| {
+                    let not_found = Tag::create(self.default_symbols().not_found);
+                    let Some(captures) = regex.captures(text.get()) else {
+                        return Return(not_found.into());
+                    };
+
+                    let found = self.default_symbols().found;
+                    let groups = captures
+                        .iter()
+                        .map(|group| {
+                            group.map_or_else(
+                                || not_found.into(),
+                                |group| {
+                                    let group = Text::create(self, true, group.as_str());
+                                    Tag::create_with_value(self, true, found, group).into()
+                                },
+                            )
+                        })
+                        .collect_vec();
+                    let groups = List::create(self, true, &groups);
+                    Return(Tag::create_with_value(self, true, found, groups).into())
+                })
+                .map_err(|error| BuiltinError::InvalidRegex(error.to_string()))
+        })
+    }
+    fn regex_find(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |text: Text, pattern: Text| {
+            self.regex(pattern.get())
+                .map(|regex| match regex.find(text.get()) {
+                    Some(match_) => {
+                        let match_ = Text::create(self, true, match_.as_str());
+                        let found = self.default_symbols().found;
+                        Return(Tag::create_with_value(self, true, found, match_).into())
+                    }
+                    None => Return(Tag::create(self.default_symbols().not_found).into()),
+                })
+                .map_err(|error| BuiltinError::InvalidRegex(error.to_string()))
+        })
+    }
+    fn regex_is_match(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |text: Text, pattern: Text| {
+            self.regex(pattern.get())
+                .map(|regex| Return(Tag::create_bool(self, regex.is_match(text.get())).into()))
+                .map_err(|error| BuiltinError::InvalidRegex(error.to_string()))
+        })
+    }
+
+    fn struct_entries(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |struct_: Struct| {
+            Return(struct_.entries(self).into())
+        })
+    }
     fn struct_get(&mut self, args: &[InlineObject]) -> BuiltinResult {
         unpack_and_later_drop!(self, args, |struct_: Struct, key: Any| {
             let value = struct_.get(key.object).unwrap();
@@ -379,7 +920,8 @@ impl Heap {
     }
     fn struct_get_keys(&mut self, args: &[InlineObject]) -> BuiltinResult {
         unpack_and_later_drop!(self, args, |struct_: Struct| {
-            Return(List::create(self, true, struct_.keys()).into())
+            let keys = struct_.iter().map(|(_, key, _)| key).collect_vec();
+            Return(List::create(self, true, &keys).into())
         })
     }
     fn struct_has_key(&mut self, args: &[InlineObject]) -> BuiltinResult {
@@ -387,6 +929,41 @@ impl Heap {
             Return(Tag::create_bool(self, struct_.contains(key.object)).into())
         })
     }
+    fn struct_insert(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |struct_: Struct, key: Any, value: Any| {
+            if struct_.get(key.object) == Some(value.object) {
+                // The struct wouldn't change, so we can share it.
+                struct_.object.dup(self);
+                Return(struct_.object)
+            } else {
+                Return(struct_.insert(self, key.object, value.object).into())
+            }
+        })
+    }
+    fn struct_merge(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |a: Struct, b: Struct| {
+            if b.len() == 0 {
+                a.object.dup(self);
+                Return(a.object)
+            } else if a.len() == 0 {
+                b.object.dup(self);
+                Return(b.object)
+            } else {
+                Return(a.merge(self, **b).into())
+            }
+        })
+    }
+    fn struct_remove(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |struct_: Struct, key: Any| {
+            if struct_.contains(key.object) {
+                Return(struct_.remove(self, key.object).into())
+            } else {
+                // The struct wouldn't change, so we can share it.
+                struct_.object.dup(self);
+                Return(struct_.object)
+            }
+        })
+    }
 
     fn tag_get_value(&mut self, args: &[InlineObject]) -> BuiltinResult {
         unpack_and_later_drop!(self, args, |tag: Tag| {
@@ -432,21 +1009,33 @@ impl Heap {
         })
     }
     fn text_from_utf8(&mut self, args: &[InlineObject]) -> BuiltinResult {
-        unpack!(self, args, |bytes: List| {
-            // TODO: Remove `u8` checks once we have `needs` ensuring that the bytes are valid.
-            let real_bytes: Vec<_> = bytes
-                .items()
-                .iter()
-                .map(|&it| {
-                    Int::try_from(it)
-                        .ok()
-                        .and_then(Int::try_get)
-                        .ok_or_else(|| format!("Value is not a byte: {it}."))
-                })
-                .try_collect()?;
+        unpack!(self, args, |bytes: Any| {
+            let real_bytes: Vec<_> = match **bytes {
+                Data::Bytes(bytes) => bytes.get().to_vec(),
+                Data::List(list) => {
+                    // TODO: Remove `u8` checks once we have `needs` ensuring that the bytes are valid.
+                    list.items()
+                        .iter()
+                        .map(|&it| {
+                            Int::try_from(it)
+                                .ok()
+                                .and_then(Int::try_get)
+                                .ok_or_else(|| BuiltinError::NotAByte {
+                                    value: it.to_string(),
+                                })
+                        })
+                        .try_collect()?
+                }
+                data => {
+                    return Err(BuiltinError::TypeMismatch {
+                        expected: "Bytes or List",
+                        actual: DataDiscriminants::from(data).into(),
+                    });
+                }
+            };
             let text = String::from_utf8(real_bytes)
                 .map(|it| {
-                    bytes.drop(self);
+                    bytes.object.drop(self);
                     Text::create(self, true, &it).into()
                 })
                 .map_err(|_| {
@@ -466,13 +1055,16 @@ impl Heap {
             self,
             args,
             |text: Text, start_inclusive: Int, end_exclusive: Int| {
-                Return(
-                    text.get_range(self, *start_inclusive..*end_exclusive)
-                        .into(),
-                )
+                let range = checked_range(*start_inclusive, *end_exclusive, text.grapheme_len())?;
+                Return(text.get_range(self, range).into())
             }
         )
     }
+    fn text_index_of(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |text: Text, pattern: Text| {
+            Return(text.index_of(self, *pattern).into())
+        })
+    }
     fn text_is_empty(&mut self, args: &[InlineObject]) -> BuiltinResult {
         unpack_and_later_drop!(self, args, |text: Text| {
             Return(text.is_empty(self).into())
@@ -483,6 +1075,20 @@ impl Heap {
             Return(text.length(self).into())
         })
     }
+    fn text_replace(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(
+            self,
+            args,
+            |text: Text, pattern: Text, replacement: Text| {
+                Return(text.replace(self, *pattern, *replacement).into())
+            }
+        )
+    }
+    fn text_split(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |text: Text, separator: Text| {
+            Return(text.split(self, *separator).into())
+        })
+    }
     fn text_starts_with(&mut self, args: &[InlineObject]) -> BuiltinResult {
         unpack_and_later_drop!(self, args, |text: Text, prefix: Text| {
             Return(text.starts_with(self, *prefix).into())
@@ -498,6 +1104,21 @@ impl Heap {
             Return(text.trim_start(self).into())
         })
     }
+    fn text_to_lower(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |text: Text| {
+            Return(text.to_lower(self).into())
+        })
+    }
+    fn text_to_upper(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |text: Text| {
+            Return(text.to_upper(self).into())
+        })
+    }
+    fn text_to_utf8_bytes(&mut self, args: &[InlineObject]) -> BuiltinResult {
+        unpack_and_later_drop!(self, args, |text: Text| {
+            Return(Bytes::create(self, true, text.get().as_bytes()).into())
+        })
+    }
 
     #[allow(clippy::wrong_self_convention)]
     fn to_debug_text(&mut self, args: &[InlineObject]) -> BuiltinResult {
@@ -513,7 +1134,9 @@ impl Heap {
         unpack_and_later_drop!(self, args, |value: Any| {
             let type_text = match **value {
                 Data::Int(_) => self.default_symbols().int,
+                Data::Float(_) => self.default_symbols().float,
                 Data::Text(_) => self.default_symbols().text,
+                Data::Bytes(_) => self.default_symbols().bytes,
                 Data::Tag(_) => self.default_symbols().tag,
                 Data::List(_) => self.default_symbols().list,
                 Data::Struct(_) => self.default_symbols().struct_,
@@ -529,6 +1152,116 @@ impl Heap {
     }
 }
 
+enum NestedRunError {
+    CalledHandle,
+    Panicked(Box<Panic>),
+}
+
+/// Runs a function to completion in a separate machine state that shares the
+/// byte code and tracer with the current VM.
+///
+/// Builtins can only diverge their control flow once, so builtins that need
+/// the results of multiple calls or that need to handle panics use this.
+/// Handles can't be called since that would require pausing the whole VM.
+fn run_to_completion(
+    heap: &mut Heap,
+    byte_code: &ByteCode,
+    tracer: &mut impl Tracer,
+    callee: InlineObject,
+    arguments: &[InlineObject],
+    responsible: HirId,
+) -> Result<InlineObject, NestedRunError> {
+    let mut state = MachineState {
+        next_instruction: None,
+        data_stack: vec![],
+        call_stack: vec![],
+        max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+        struct_get_cache: FxHashMap::default(),
+        memoization_cache: MemoizationCache::default(),
+    };
+    let mut result = state.call(heap, byte_code, tracer, callee, arguments, responsible);
+    loop {
+        match result {
+            InstructionResult::Done => {}
+            InstructionResult::CallHandle(_) => {
+                state.memoization_cache.clear(heap);
+                return Err(NestedRunError::CalledHandle);
+            }
+            InstructionResult::Panic(panic) => {
+                state.memoization_cache.clear(heap);
+                return Err(NestedRunError::Panicked(Box::new(panic)));
+            }
+        }
+        let Some(instruction_pointer) = state.next_instruction else {
+            break;
+        };
+        state.next_instruction = Some(instruction_pointer.next());
+        heap.set_allocation_site(instruction_pointer);
+        let instruction = &byte_code.instructions[*instruction_pointer];
+        result = state.run_instruction(heap, byte_code, instruction, tracer);
+    }
+    state.memoization_cache.clear(heap);
+    Ok(state.data_stack.pop().unwrap())
+}
+
+/// Forwards everything to another tracer while also recording the calls inside
+/// a `✨.functionTry` using a [`StackTracer`].
+///
+/// The inner tracer is a trait object so that nested `✨.functionTry` calls
+/// don't instantiate this type recursively.
+struct TryScopeTracer<'a> {
+    inner: &'a mut dyn Tracer,
+    stack_tracer: StackTracer,
+}
+impl Tracer for TryScopeTracer<'_> {
+    fn value_evaluated(&mut self, heap: &mut Heap, expression: HirId, value: InlineObject) {
+        self.inner.value_evaluated(heap, expression, value);
+    }
+
+    fn found_fuzzable_function(&mut self, heap: &mut Heap, definition: HirId, function: Function) {
+        self.inner
+            .found_fuzzable_function(heap, definition, function);
+    }
+
+    fn call_started(
+        &mut self,
+        heap: &mut Heap,
+        call_site: HirId,
+        callee: InlineObject,
+        arguments: Vec<InlineObject>,
+        responsible: HirId,
+    ) {
+        self.stack_tracer
+            .call_started(heap, call_site, callee, arguments.clone(), responsible);
+        self.inner
+            .call_started(heap, call_site, callee, arguments, responsible);
+    }
+    fn call_ended(&mut self, heap: &mut Heap, return_value: Option<InlineObject>) {
+        self.stack_tracer.call_ended(heap, return_value);
+        self.inner.call_ended(heap, return_value);
+    }
+    fn tail_call(
+        &mut self,
+        heap: &mut Heap,
+        call_site: HirId,
+        callee: InlineObject,
+        arguments: Vec<InlineObject>,
+        responsible: HirId,
+    ) {
+        self.stack_tracer
+            .tail_call(heap, call_site, callee, arguments.clone(), responsible);
+        self.inner
+            .tail_call(heap, call_site, callee, arguments, responsible);
+    }
+    fn tail_call_optimized(&mut self, heap: &mut Heap, function: Function) {
+        self.inner.tail_call_optimized(heap, function);
+    }
+
+    fn panicked(&mut self, heap: &mut Heap, panic: &Panic) {
+        self.inner.panicked(heap, panic);
+    }
+}
+
 #[derive(Deref)]
 struct UnpackedData<T> {
     object: InlineObject,
@@ -542,7 +1275,7 @@ struct Any {
     data: Data,
 }
 impl TryInto<Any> for InlineObject {
-    type Error = String;
+    type Error = ();
 
     fn try_into(self) -> Result<Any, Self::Error> {
         Ok(Any { data: self.into() })
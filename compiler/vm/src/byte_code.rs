@@ -1,3 +1,4 @@
+use crate::fuzzing_callbacks::FuzzingCallbacks;
 use crate::heap::{Function, HirId, InlineData, InlineObject};
 use crate::heap::{Heap, Text};
 use crate::instruction_pointer::InstructionPointer;
@@ -22,8 +23,13 @@ pub struct ByteCode {
     pub constant_heap: Heap,
     pub instructions: Vec<Instruction>,
     pub(super) origins: Vec<FxHashSet<hir::Id>>,
+    /// Starts of function bodies whose call results may be memoized.
+    pub(super) memoized_bodies: FxHashSet<InstructionPointer>,
     pub module_function: Function,
     pub responsible_module: HirId,
+    /// Only available if the byte code was compiled with fuzzable functions
+    /// being registered.
+    pub fuzzing_callbacks: Option<FuzzingCallbacks>,
 }
 
 pub type StackOffset = usize; // 0 is the last item, 1 the one before that, etc.
@@ -65,6 +71,25 @@ pub enum Instruction {
     /// Leaves the top stack item untouched, but removes n below.
     PopMultipleBelowTop(usize),
 
+    /// Superinstruction for consecutive `PushFromStack` instructions. Each
+    /// offset is relative to the stack after pushing the previous items.
+    PushMultipleFromStack(Vec<StackOffset>),
+
+    /// Superinstruction for `PushFromStack` followed by `Dup`. Increases the
+    /// reference count of an item back in the stack by `amount`.
+    ///
+    /// a -> a
+    DupFromStack { offset: StackOffset, amount: usize },
+
+    /// Superinstruction for `PushFromStack` followed by `Drop`. Decreases the
+    /// reference count of an item back in the stack by one.
+    ///
+    /// a -> a
+    DropFromStack(StackOffset),
+
+    /// Superinstruction for `PopMultipleBelowTop` followed by `Return`.
+    PopMultipleBelowTopAndReturn(usize),
+
     /// Increases the reference count by `amount`.
     ///
     /// a, value -> a
@@ -92,9 +117,14 @@ pub enum Instruction {
     /// Like `Call`, but after popping the stack entries for the call itself, it
     /// also pops the given number of local stack entries before actually
     /// executing the call.
+    ///
+    /// If the callee is a function whose body starts at `current_body` (i.e.,
+    /// the function calls itself), no call is performed at all. Instead, the
+    /// current frame is reused by jumping back to the start of the body.
     TailCall {
         num_locals_to_pop: usize,
         num_args: usize, // excluding the responsible argument
+        current_body: InstructionPointer,
     },
 
     /// Returns from the current function to the original caller. Leaves the
@@ -154,11 +184,17 @@ impl Instruction {
             Self::PushFromStack(_) => {
                 stack.push(result);
             }
-            Self::PopMultipleBelowTop(n) => {
+            Self::PopMultipleBelowTop(n) | Self::PopMultipleBelowTopAndReturn(n) => {
                 let top = stack.pop().unwrap();
                 stack.pop_multiple(*n);
                 stack.push(top);
             }
+            Self::PushMultipleFromStack(offsets) => {
+                for offset in offsets {
+                    stack.push(stack[stack.len() - 1 - offset]);
+                }
+            }
+            Self::DupFromStack { .. } | Self::DropFromStack(_) => {}
             Self::Dup { amount: _ } => {
                 stack.pop();
             }
@@ -174,6 +210,7 @@ impl Instruction {
             Self::TailCall {
                 num_locals_to_pop,
                 num_args,
+                current_body: _,
             } => {
                 stack.pop(); // responsible
                 stack.pop_multiple(*num_args);
@@ -276,7 +313,7 @@ impl ToRichIr for ByteCode {
             let origins = &self.origins[i];
             if origins != previous_origins {
                 builder.push(
-                    format!("# {}", origins.iter().join(", ")),
+                    format!("# {}", origins.iter().sorted().join(", ")),
                     TokenType::Comment,
                     EnumSet::empty(),
                 );
@@ -362,10 +399,21 @@ impl Instruction {
                 builder.push(" ", None, EnumSet::empty());
                 builder.push(offset.to_string(), None, EnumSet::empty());
             }
-            Self::PopMultipleBelowTop(count) => {
+            Self::PopMultipleBelowTop(count) | Self::PopMultipleBelowTopAndReturn(count) => {
                 builder.push(" ", None, EnumSet::empty());
                 builder.push(count.to_string(), None, EnumSet::empty());
             }
+            Self::PushMultipleFromStack(offsets) => {
+                builder.push(" ", None, EnumSet::empty());
+                builder.push(offsets.iter().join(", "), None, EnumSet::empty());
+            }
+            Self::DupFromStack { offset, amount } => {
+                builder.push(format!(" {offset} by {amount}"), None, EnumSet::empty());
+            }
+            Self::DropFromStack(offset) => {
+                builder.push(" ", None, EnumSet::empty());
+                builder.push(offset.to_string(), None, EnumSet::empty());
+            }
             Self::Dup { amount } => {
                 builder.push(" by ", None, EnumSet::empty());
                 builder.push(amount.to_string(), None, EnumSet::empty());
@@ -381,10 +429,11 @@ impl Instruction {
             Self::TailCall {
                 num_locals_to_pop,
                 num_args,
+                current_body,
             } => {
                 builder.push(
                     format!(
-                        " with {num_locals_to_pop} locals and {num_args} {}",
+                        " with {num_locals_to_pop} locals and {num_args} {} in body starting at {current_body:?}",
                         arguments_plural(*num_args),
                     ),
                     None,
@@ -430,7 +479,7 @@ pub impl RichIrForByteCode for RichIr {
     fn for_byte_code(
         module: &Module,
         byte_code: &ByteCode,
-        tracing_config: TracingConfig,
+        tracing_config: &TracingConfig,
     ) -> RichIr {
         let mut builder = RichIrBuilder::default();
         builder.push(
@@ -1,24 +1,33 @@
 use crate::{
     byte_code::ByteCode,
-    heap::{Data, Handle, Heap, InlineObject, Int, List, Struct, Tag, Text},
+    execution_controller::{ExecutionController, RunLimitedNumberOfInstructions},
+    heap::{Data, Handle, Heap, InlineObject, Int, List, Struct, Tag, Text, ToDebugText},
     tracer::Tracer,
     vm::VmHandleCall,
-    StateAfterRun, StateAfterRunForever, Vm, VmFinished,
+    StateAfterRun, StateAfterRunForever, Vm, VmFinished, CAN_USE_STDOUT,
 };
-use candy_frontend::utils::HashMapExtension;
+use candy_frontend::{
+    format::{MaxLength, Precedence},
+    utils::HashMapExtension,
+};
+use clap::ValueEnum;
 use itertools::Itertools;
 use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
 use std::{
     borrow::{Borrow, Cow},
+    fmt::{self, Debug, Display, Formatter},
     fs::File,
-    io::{self, BufRead, Read},
+    io::{self, BufRead, Read, Write},
     mem,
     net::SocketAddr,
     str::FromStr,
-    time::SystemTime,
+    sync::atomic::Ordering,
+    time::{SystemTime, UNIX_EPOCH},
+    vec,
 };
 use tiny_http::{Request, Response, Server};
-use tracing::info;
+use tracing::{error, info};
 
 pub trait Environment {
     fn handle<B: Borrow<ByteCode>, T: Tracer>(
@@ -52,6 +61,13 @@ impl<B: Borrow<ByteCode>, T: Tracer> Vm<B, T> {
     pub fn run_forever_without_handles(self, heap: &mut Heap) -> VmFinished<T> {
         self.run_forever_with_environment(heap, &mut EmptyEnvironment)
     }
+    pub fn run_without_handles_with_controller(
+        self,
+        heap: &mut Heap,
+        controller: &mut impl ExecutionController,
+    ) -> StateAfterRunWithoutHandles<B, T> {
+        self.run_with_controller(heap, &mut EmptyEnvironment, controller)
+    }
 }
 
 pub struct DefaultEnvironment {
@@ -79,7 +95,13 @@ pub struct DefaultEnvironment {
     stdin_handle: Handle,
     stdout_handle: Handle,
 
+    // Log
+    log_handles: LogHandles,
+    log_level: LogLevel,
+
     dynamic_handles: FxHashMap<Handle, DynamicHandle>,
+
+    event_log: EventLog,
 }
 #[derive(Debug)]
 #[allow(clippy::enum_variant_names)]
@@ -97,6 +119,30 @@ struct HttpServerState {
 type HttpServerIndex = usize;
 type HttpRequestId = usize;
 
+/// The result of a handle whose behavior differs between runs of the same
+/// program.
+///
+/// Recording these events during one run and replaying them in a later run
+/// lets the program observe the same time, random bytes, and input again.
+/// Files and HTTP requests are not recorded.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NondeterministicEvent {
+    #[serde(rename_all = "camelCase")]
+    SystemClock {
+        nanoseconds_since_unix_epoch: u128,
+    },
+    RandomBytes(Result<Vec<u8>, String>),
+    Stdin(String),
+}
+#[derive(Debug, Default)]
+enum EventLog {
+    #[default]
+    Off,
+    Recording(Vec<NondeterministicEvent>),
+    Replaying(vec::IntoIter<NondeterministicEvent>),
+}
+
 impl DefaultEnvironment {
     pub fn new(heap: &mut Heap, args: &[String]) -> (Struct, Self) {
         let arguments = args
@@ -135,6 +181,8 @@ impl DefaultEnvironment {
         let stdin_handle = Handle::new(heap, 0);
         let stdout_handle = Handle::new(heap, 1);
 
+        let (log_object, log_handles) = LogHandles::new(heap);
+
         let environment_object = Struct::create_with_symbol_keys(
             heap,
             true,
@@ -152,6 +200,7 @@ impl DefaultEnvironment {
                 ),
                 (heap.default_symbols().stdin, **stdin_handle),
                 (heap.default_symbols().stdout, **stdout_handle),
+                (heap.default_symbols().log, log_object.into()),
             ],
         );
         let environment = Self {
@@ -164,10 +213,65 @@ impl DefaultEnvironment {
             get_random_bytes_handle,
             stdin_handle,
             stdout_handle,
+            log_handles,
+            log_level: LogLevel::Debug,
             dynamic_handles: FxHashMap::default(),
+            event_log: EventLog::Off,
         };
         (environment_object, environment)
     }
+
+    /// Only messages logged with at least this level are printed. By default,
+    /// all messages are printed.
+    pub fn set_log_level(&mut self, log_level: LogLevel) {
+        self.log_level = log_level;
+    }
+
+    /// Records the results of nondeterministic handles from now on. Get them
+    /// using [`Self::recorded_events`].
+    pub fn record(&mut self) {
+        self.event_log = EventLog::Recording(vec![]);
+    }
+    /// Returns the recorded `events` instead of calling the clock, the random
+    /// number generator, or stdin.
+    ///
+    /// If the program diverges from the recording, e.g., because it changed,
+    /// an error is logged and the environment continues without replaying.
+    pub fn replay(&mut self, events: Vec<NondeterministicEvent>) {
+        self.event_log = EventLog::Replaying(events.into_iter());
+    }
+    #[must_use]
+    pub fn recorded_events(&self) -> Option<&[NondeterministicEvent]> {
+        match &self.event_log {
+            EventLog::Recording(events) => Some(events),
+            EventLog::Off | EventLog::Replaying(_) => None,
+        }
+    }
+
+    fn record_or_replay<T: Clone + Debug>(
+        &mut self,
+        to_event: impl FnOnce(T) -> NondeterministicEvent,
+        from_event: impl FnOnce(NondeterministicEvent) -> Option<T>,
+        run: impl FnOnce() -> T,
+    ) -> T {
+        if let EventLog::Replaying(events) = &mut self.event_log {
+            if let Some(event) = events.next() {
+                if let Some(value) = from_event(event.clone()) {
+                    return value;
+                }
+                error!("The program diverged from the recording, which contains {event:?} at this point. Continuing without replaying.");
+            } else {
+                error!("The recording ended. Continuing without replaying.");
+            }
+            self.event_log = EventLog::Off;
+        }
+
+        let value = run();
+        if let EventLog::Recording(events) = &mut self.event_log {
+            events.push(to_event(value.clone()));
+        }
+        value
+    }
 }
 impl Environment for DefaultEnvironment {
     fn handle<B: Borrow<ByteCode>, T: Tracer>(
@@ -176,7 +280,7 @@ impl Environment for DefaultEnvironment {
         call: VmHandleCall<B, T>,
     ) -> Vm<B, T> {
         let result = if call.handle == self.system_clock_handle {
-            Self::system_clock(heap, &call.arguments)
+            self.system_clock(heap, &call.arguments)
         } else if call.handle == self.file_open_handle {
             self.file_open(heap, &call.arguments)
         } else if call.handle == self.file_read_to_end_handle {
@@ -186,11 +290,13 @@ impl Environment for DefaultEnvironment {
         } else if call.handle == self.http_server_handle {
             self.http_server(heap, &call.arguments)
         } else if call.handle == self.get_random_bytes_handle {
-            Self::get_random_bytes(heap, &call.arguments)
+            self.get_random_bytes(heap, &call.arguments)
         } else if call.handle == self.stdin_handle {
-            Self::stdin(heap, &call.arguments)
+            self.stdin(heap, &call.arguments)
         } else if call.handle == self.stdout_handle {
             Self::stdout(heap, &call.arguments)
+        } else if let Some(level) = self.log_handles.level_of(call.handle) {
+            self.log(heap, level, &call.arguments)
         } else {
             let dynamic_handle = self.dynamic_handles.get(&call.handle).unwrap_or_else(|| {
                 panic!(
@@ -226,12 +332,27 @@ impl Environment for DefaultEnvironment {
 impl DefaultEnvironment {
     // Clock
 
-    fn system_clock(heap: &mut Heap, arguments: &[InlineObject]) -> InlineObject {
+    fn system_clock(&mut self, heap: &mut Heap, arguments: &[InlineObject]) -> InlineObject {
         let [] = arguments else { unreachable!() };
 
-        let now = SystemTime::now();
-        let since_unix_epoch = now.duration_since(SystemTime::UNIX_EPOCH).unwrap();
-        Int::create(heap, true, since_unix_epoch.as_nanos()).into()
+        let since_unix_epoch = self.record_or_replay(
+            |nanoseconds_since_unix_epoch| NondeterministicEvent::SystemClock {
+                nanoseconds_since_unix_epoch,
+            },
+            |event| match event {
+                NondeterministicEvent::SystemClock {
+                    nanoseconds_since_unix_epoch,
+                } => Some(nanoseconds_since_unix_epoch),
+                _ => None,
+            },
+            || {
+                let now = SystemTime::now();
+                now.duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap()
+                    .as_nanos()
+            },
+        );
+        Int::create(heap, true, since_unix_epoch).into()
     }
 
     // File
@@ -530,7 +651,7 @@ impl DefaultEnvironment {
 
     // Random
 
-    fn get_random_bytes(heap: &mut Heap, arguments: &[InlineObject]) -> InlineObject {
+    fn get_random_bytes(&mut self, heap: &mut Heap, arguments: &[InlineObject]) -> InlineObject {
         let [length] = arguments else { unreachable!() };
         let Data::Int(length) = (*length).into() else {
             // TODO: Panic
@@ -551,11 +672,25 @@ impl DefaultEnvironment {
             return Tag::create_result(heap, true, Err(message.into())).into();
         };
 
-        let mut bytes = vec![0u8; length];
-        if let Err(error) = getrandom::getrandom(&mut bytes) {
-            let message = Text::create(heap, true, &error.to_string());
-            return Tag::create_result(heap, true, Err(message.into())).into();
-        }
+        let bytes = self.record_or_replay(
+            NondeterministicEvent::RandomBytes,
+            |event| match event {
+                NondeterministicEvent::RandomBytes(bytes) => Some(bytes),
+                _ => None,
+            },
+            || {
+                let mut bytes = vec![0u8; length];
+                getrandom::getrandom(&mut bytes).map_err(|error| error.to_string())?;
+                Ok(bytes)
+            },
+        );
+        let bytes = match bytes {
+            Ok(bytes) => bytes,
+            Err(error) => {
+                let message = Text::create(heap, true, &error);
+                return Tag::create_result(heap, true, Err(message.into())).into();
+            }
+        };
 
         let bytes = bytes
             .into_iter()
@@ -567,18 +702,29 @@ impl DefaultEnvironment {
 
     // Stdio
 
-    fn stdin(heap: &mut Heap, arguments: &[InlineObject]) -> InlineObject {
+    fn stdin(&mut self, heap: &mut Heap, arguments: &[InlineObject]) -> InlineObject {
         assert!(arguments.is_empty());
-        let input = {
-            let stdin = io::stdin();
-            stdin.lock().lines().next().unwrap().unwrap()
-        };
+        let input = self.record_or_replay(
+            NondeterministicEvent::Stdin,
+            |event| match event {
+                NondeterministicEvent::Stdin(input) => Some(input),
+                _ => None,
+            },
+            || {
+                let stdin = io::stdin();
+                stdin.lock().lines().next().unwrap().unwrap()
+            },
+        );
         Text::create(heap, true, &input).into()
     }
     fn stdout(heap: &Heap, arguments: &[InlineObject]) -> InlineObject {
         let [message] = arguments else { unreachable!() };
         if let Data::Text(text) = (*message).into() {
-            println!("{}", text.get());
+            if CAN_USE_STDOUT.load(Ordering::Relaxed) {
+                println!("{}", text.get());
+            } else {
+                eprintln!("{}", text.get());
+            }
         } else {
             info!("Non-text value sent to stdout: {message:?}");
         }
@@ -586,6 +732,23 @@ impl DefaultEnvironment {
         Tag::create_nothing(heap).into()
     }
 
+    // Log
+
+    /// Log messages go to stderr, even if stdout is available, so that they
+    /// don't mix with the program's regular output.
+    fn log(&self, heap: &Heap, level: LogLevel, arguments: &[InlineObject]) -> InlineObject {
+        let [message] = arguments else { unreachable!() };
+        if level >= self.log_level {
+            let _ = writeln!(
+                io::stderr(),
+                "{} {level:<5} {}",
+                format_time_of_day(SystemTime::now()),
+                LogHandles::message_to_string(*message),
+            );
+        }
+        Tag::create_nothing(heap).into()
+    }
+
     fn create_dynamic_handle(
         &mut self,
         heap: &mut Heap,
@@ -608,6 +771,97 @@ impl HttpServerState {
     }
 }
 
+/// The severity of a message logged using `environment.log`.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, ValueEnum)]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+impl Display for LogLevel {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let text = match self {
+            Self::Debug => "DEBUG",
+            Self::Info => "INFO",
+            Self::Warn => "WARN",
+            Self::Error => "ERROR",
+        };
+        // Supports padding, e.g., `{level:<5}`.
+        f.pad(text)
+    }
+}
+
+/// The handles of the `environment.log` struct, which contains one function
+/// per [`LogLevel`] that takes a message.
+///
+/// Environments other than [`DefaultEnvironment`] can use this to provide
+/// logging as well.
+pub struct LogHandles {
+    debug: Handle,
+    info: Handle,
+    warn: Handle,
+    error: Handle,
+}
+impl LogHandles {
+    pub fn new(heap: &mut Heap) -> (Struct, Self) {
+        let handles = Self {
+            debug: Handle::new(heap, 1),
+            info: Handle::new(heap, 1),
+            warn: Handle::new(heap, 1),
+            error: Handle::new(heap, 1),
+        };
+        let log_object = Struct::create_with_symbol_keys(
+            heap,
+            true,
+            [
+                (heap.default_symbols().debug, **handles.debug),
+                (heap.default_symbols().info, **handles.info),
+                (heap.default_symbols().warn, **handles.warn),
+                (heap.default_symbols().error, **handles.error),
+            ],
+        );
+        (log_object, handles)
+    }
+
+    #[must_use]
+    pub fn level_of(&self, handle: Handle) -> Option<LogLevel> {
+        if handle == self.debug {
+            Some(LogLevel::Debug)
+        } else if handle == self.info {
+            Some(LogLevel::Info)
+        } else if handle == self.warn {
+            Some(LogLevel::Warn)
+        } else if handle == self.error {
+            Some(LogLevel::Error)
+        } else {
+            None
+        }
+    }
+
+    /// Texts are logged as they are, other values using their debug text.
+    #[must_use]
+    pub fn message_to_string(message: InlineObject) -> String {
+        match Data::from(message) {
+            Data::Text(text) => text.get().to_string(),
+            _ => message.to_debug_text(Precedence::Low, MaxLength::Unlimited),
+        }
+    }
+}
+
+/// Formats the UTC time of day as `HH:MM:SS.mmm`.
+fn format_time_of_day(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds_of_day = since_epoch.as_secs() % (24 * 60 * 60);
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        seconds_of_day / 3600,
+        seconds_of_day / 60 % 60,
+        seconds_of_day % 60,
+        since_epoch.subsec_millis(),
+    )
+}
+
 #[must_use]
 pub enum StateAfterRunWithoutHandles<B: Borrow<ByteCode>, T: Tracer> {
     Running(Vm<B, T>),
@@ -629,18 +883,16 @@ impl<B: Borrow<ByteCode>, T: Tracer> Vm<B, T> {
     }
 
     pub fn run_n_with_environment(
-        mut self,
+        self,
         heap: &mut Heap,
         environment: &mut impl Environment,
         max_instructions: usize,
     ) -> StateAfterRunWithoutHandles<B, T> {
-        for _ in 0..max_instructions {
-            match self.run_with_environment(heap, environment) {
-                StateAfterRunWithoutHandles::Running(vm) => self = vm,
-                finished @ StateAfterRunWithoutHandles::Finished(_) => return finished,
-            }
-        }
-        StateAfterRunWithoutHandles::Running(self)
+        self.run_with_controller(
+            heap,
+            environment,
+            &mut RunLimitedNumberOfInstructions::new(max_instructions),
+        )
     }
 
     pub fn run_forever_with_environment(
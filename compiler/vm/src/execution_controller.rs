@@ -0,0 +1,320 @@
+//! Execution controllers decide how long a VM may run before it returns
+//! control to its caller.
+//!
+//! Controllers can be combined using [`ExecutionController::and`], e.g., to
+//! run at most 1000 instructions, for at most 10 ms, and only while the
+//! execution isn't paused:
+//!
+//! ```ignore
+//! let pause_signal = PauseSignal::default();
+//! let mut controller = RunLimitedNumberOfInstructions::new(1000)
+//!     .and(WallClockController::new(Duration::from_millis(10)))
+//!     .and(PauseSignalController::new(pause_signal.clone()));
+//! match vm.run_with_controller(&mut heap, &mut environment, &mut controller) {
+//!     StateAfterRunWithoutHandles::Running(vm) => { /* A controller stopped the VM. */ }
+//!     StateAfterRunWithoutHandles::Finished(finished) => { /* … */ }
+//! }
+//! ```
+
+use crate::{
+    byte_code::ByteCode,
+    environment::{Environment, StateAfterRunWithoutHandles},
+    heap::Heap,
+    tracer::Tracer,
+    Vm, VmFinished,
+};
+use std::{
+    borrow::Borrow,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+pub trait ExecutionController {
+    /// Called before each instruction. Returning `false` stops the VM.
+    fn should_continue_running(&mut self) -> bool;
+    fn instruction_executed(&mut self) {}
+
+    /// A controller that only continues running while both `self` and
+    /// `other` want to continue.
+    fn and<C: ExecutionController>(self, other: C) -> AndController<Self, C>
+    where
+        Self: Sized,
+    {
+        AndController(self, other)
+    }
+}
+impl<C: ExecutionController + ?Sized> ExecutionController for &mut C {
+    fn should_continue_running(&mut self) -> bool {
+        (**self).should_continue_running()
+    }
+    fn instruction_executed(&mut self) {
+        (**self).instruction_executed();
+    }
+}
+/// `None` doesn't limit the execution.
+impl<C: ExecutionController> ExecutionController for Option<C> {
+    fn should_continue_running(&mut self) -> bool {
+        self.as_mut()
+            .map_or(true, ExecutionController::should_continue_running)
+    }
+    fn instruction_executed(&mut self) {
+        if let Some(controller) = self {
+            controller.instruction_executed();
+        }
+    }
+}
+
+pub struct RunForever;
+impl ExecutionController for RunForever {
+    fn should_continue_running(&mut self) -> bool {
+        true
+    }
+}
+
+pub struct RunLimitedNumberOfInstructions {
+    remaining_instructions: usize,
+}
+impl RunLimitedNumberOfInstructions {
+    #[must_use]
+    pub const fn new(max_instructions: usize) -> Self {
+        Self {
+            remaining_instructions: max_instructions,
+        }
+    }
+
+    #[must_use]
+    pub const fn remaining_instructions(&self) -> usize {
+        self.remaining_instructions
+    }
+}
+impl ExecutionController for RunLimitedNumberOfInstructions {
+    fn should_continue_running(&mut self) -> bool {
+        self.remaining_instructions > 0
+    }
+    fn instruction_executed(&mut self) {
+        self.remaining_instructions -= 1;
+    }
+}
+
+/// Stops the VM once the given duration elapsed since the controller was
+/// created.
+pub struct WallClockController {
+    deadline: Instant,
+    instructions_until_next_check: usize,
+    is_timed_out: bool,
+}
+impl WallClockController {
+    /// Checking the time for each instruction would slow down the VM, so we
+    /// only check it after this many instructions.
+    const INSTRUCTIONS_BETWEEN_CHECKS: usize = 1000;
+
+    #[must_use]
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            deadline: Instant::now() + duration,
+            instructions_until_next_check: 0,
+            is_timed_out: false,
+        }
+    }
+
+    #[must_use]
+    pub const fn is_timed_out(&self) -> bool {
+        self.is_timed_out
+    }
+}
+impl ExecutionController for WallClockController {
+    fn should_continue_running(&mut self) -> bool {
+        if !self.is_timed_out && self.instructions_until_next_check == 0 {
+            self.is_timed_out = Instant::now() >= self.deadline;
+            self.instructions_until_next_check = Self::INSTRUCTIONS_BETWEEN_CHECKS;
+        }
+        !self.is_timed_out
+    }
+    fn instruction_executed(&mut self) {
+        self.instructions_until_next_check -= 1;
+    }
+}
+
+/// Pauses and resumes executions from the outside, e.g., from another thread
+/// or an async task.
+///
+/// Clones share the same state.
+#[derive(Clone, Debug, Default)]
+pub struct PauseSignal(Arc<PauseSignalState>);
+#[derive(Debug, Default)]
+struct PauseSignalState {
+    /// Checked before each instruction, so this doesn't require locking.
+    is_paused: AtomicBool,
+    /// Only used for waiting until the signal is resumed.
+    lock: Mutex<()>,
+    resumed: Condvar,
+}
+impl PauseSignal {
+    pub fn pause(&self) {
+        self.0.is_paused.store(true, Ordering::Relaxed);
+    }
+    pub fn resume(&self) {
+        let _guard = self.0.lock.lock().unwrap();
+        self.0.is_paused.store(false, Ordering::Relaxed);
+        self.0.resumed.notify_all();
+    }
+    #[must_use]
+    pub fn is_paused(&self) -> bool {
+        self.0.is_paused.load(Ordering::Relaxed)
+    }
+    /// Blocks the current thread until the signal is resumed. Returns
+    /// immediately if it isn't paused.
+    pub fn wait_until_resumed(&self) {
+        let _guard = self
+            .0
+            .resumed
+            .wait_while(self.0.lock.lock().unwrap(), |()| self.is_paused())
+            .unwrap();
+    }
+}
+
+/// Stops the VM while its [`PauseSignal`] is paused.
+pub struct PauseSignalController {
+    signal: PauseSignal,
+}
+impl PauseSignalController {
+    #[must_use]
+    pub const fn new(signal: PauseSignal) -> Self {
+        Self { signal }
+    }
+}
+impl ExecutionController for PauseSignalController {
+    fn should_continue_running(&mut self) -> bool {
+        !self.signal.is_paused()
+    }
+}
+
+/// Created by [`ExecutionController::and`].
+pub struct AndController<A: ExecutionController, B: ExecutionController>(pub A, pub B);
+impl<A: ExecutionController, B: ExecutionController> ExecutionController for AndController<A, B> {
+    fn should_continue_running(&mut self) -> bool {
+        // Both are asked so that controllers relying on being called before
+        // each instruction stay up to date.
+        let a = self.0.should_continue_running();
+        let b = self.1.should_continue_running();
+        a && b
+    }
+    fn instruction_executed(&mut self) {
+        self.0.instruction_executed();
+        self.1.instruction_executed();
+    }
+}
+
+impl<B: Borrow<ByteCode>, T: Tracer> Vm<B, T> {
+    /// Runs the VM until it finishes or the `controller` stops it.
+    pub fn run_with_controller(
+        mut self,
+        heap: &mut Heap,
+        environment: &mut impl Environment,
+        controller: &mut impl ExecutionController,
+    ) -> StateAfterRunWithoutHandles<B, T> {
+        while controller.should_continue_running() {
+            match self.run_with_environment(heap, environment) {
+                StateAfterRunWithoutHandles::Running(vm) => self = vm,
+                finished @ StateAfterRunWithoutHandles::Finished(_) => return finished,
+            }
+            controller.instruction_executed();
+        }
+        StateAfterRunWithoutHandles::Running(self)
+    }
+
+    /// Runs a slice of at most [`Self::INSTRUCTIONS_PER_SLICE`] instructions
+    /// (or fewer if the `controller` stops the VM earlier) and then yields to
+    /// the async executor, so that hosts running other tasks on the same
+    /// thread stay responsive.
+    ///
+    /// This works with any executor since yielding only wakes the current
+    /// task again. The returned future isn't [`Send`] because the heap isn't,
+    /// so it has to run on the thread owning the heap.
+    #[allow(clippy::future_not_send)]
+    pub async fn run_until_yield(
+        self,
+        heap: &mut Heap,
+        environment: &mut impl Environment,
+        controller: &mut impl ExecutionController,
+    ) -> StateAfterRunWithoutHandles<B, T> {
+        let mut controller =
+            RunLimitedNumberOfInstructions::new(Self::INSTRUCTIONS_PER_SLICE).and(controller);
+        let state = self.run_with_controller(heap, environment, &mut controller);
+        YieldNow { has_yielded: false }.await;
+        state
+    }
+    pub const INSTRUCTIONS_PER_SLICE: usize = 1000;
+
+    /// Runs the VM to completion, yielding to the async executor after each
+    /// slice of instructions.
+    #[allow(clippy::future_not_send)]
+    pub async fn run_forever_async(
+        mut self,
+        heap: &mut Heap,
+        environment: &mut impl Environment,
+    ) -> VmFinished<T> {
+        loop {
+            match self
+                .run_until_yield(heap, environment, &mut RunForever)
+                .await
+            {
+                StateAfterRunWithoutHandles::Running(vm) => self = vm,
+                StateAfterRunWithoutHandles::Finished(finished) => return finished,
+            }
+        }
+    }
+}
+
+/// Returns [`Poll::Pending`] once so that the executor polls other tasks
+/// before continuing with the current one.
+struct YieldNow {
+    has_yielded: bool,
+}
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, context: &mut Context) -> Poll<()> {
+        if self.has_yielded {
+            return Poll::Ready(());
+        }
+        self.has_yielded = true;
+        context.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn and_controller_stops_when_either_stops() {
+        let signal = PauseSignal::default();
+        let mut controller =
+            RunLimitedNumberOfInstructions::new(2).and(PauseSignalController::new(signal.clone()));
+        assert!(controller.should_continue_running());
+        controller.instruction_executed();
+
+        signal.pause();
+        assert!(!controller.should_continue_running());
+        signal.resume();
+        assert!(controller.should_continue_running());
+        controller.instruction_executed();
+
+        assert!(!controller.should_continue_running());
+    }
+
+    #[test]
+    fn wall_clock_controller_times_out() {
+        let mut controller = WallClockController::new(Duration::ZERO);
+        assert!(!controller.should_continue_running());
+        assert!(controller.is_timed_out());
+    }
+}
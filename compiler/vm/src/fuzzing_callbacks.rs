@@ -0,0 +1,119 @@
+//! Synthetic functions that the fuzzer passes to functions expecting callbacks.
+//!
+//! Byte code compiled with fuzzable functions being registered contains a body
+//! for each [`FuzzingCallbackKind`] and supported argument count. Functions
+//! using these bodies capture a description text (e.g., `{ a -> a }`) as their
+//! first value so that fuzzing inputs containing them can be shown to the user.
+
+use crate::{
+    byte_code::{ByteCode, Instruction},
+    instruction_pointer::InstructionPointer,
+};
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::ops::RangeInclusive;
+use strum::VariantArray;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, VariantArray)]
+pub enum FuzzingCallbackKind {
+    /// Captures the description and a value. Returns the value.
+    Constant,
+    /// Captures the description. Returns its first argument.
+    Identity,
+    /// Captures the description, a reason text, and a responsible HIR ID.
+    /// Panics with these.
+    Panicking,
+    /// Captures the description. Returns a list of its arguments.
+    RecordingArguments,
+}
+impl FuzzingCallbackKind {
+    #[must_use]
+    pub const fn captured_count(self) -> usize {
+        match self {
+            Self::Constant => 2,
+            Self::Identity | Self::RecordingArguments => 1,
+            Self::Panicking => 3,
+        }
+    }
+    #[must_use]
+    pub const fn argument_counts(self) -> RangeInclusive<usize> {
+        match self {
+            Self::Identity => 1..=FuzzingCallbacks::MAX_ARGUMENT_COUNT,
+            Self::Constant | Self::Panicking | Self::RecordingArguments => {
+                0..=FuzzingCallbacks::MAX_ARGUMENT_COUNT
+            }
+        }
+    }
+
+    /// When the body starts, the stack contains the captured values, the
+    /// arguments, and the responsible parameter. As usual, the arguments are
+    /// owned by the body while the captured values are owned by the function.
+    fn instructions(self, argument_count: usize) -> Vec<Instruction> {
+        let locals_count = self.captured_count() + argument_count + 1;
+        // The last captured value is right below the first argument.
+        let last_captured_offset = argument_count + 1;
+        let mut instructions = vec![];
+        match self {
+            Self::Constant => {
+                instructions.extend((1..=argument_count).map(Instruction::DropFromStack));
+                instructions.push(Instruction::DupFromStack {
+                    offset: last_captured_offset,
+                    amount: 1,
+                });
+                instructions.push(Instruction::PushFromStack(last_captured_offset));
+                instructions.push(Instruction::PopMultipleBelowTopAndReturn(locals_count));
+            }
+            Self::Identity => {
+                instructions.extend((1..argument_count).map(Instruction::DropFromStack));
+                instructions.push(Instruction::PushFromStack(argument_count));
+                instructions.push(Instruction::PopMultipleBelowTopAndReturn(locals_count));
+            }
+            Self::Panicking => {
+                // Reason, then the responsible HIR ID.
+                instructions.push(Instruction::PushFromStack(last_captured_offset + 1));
+                instructions.push(Instruction::PushFromStack(last_captured_offset + 1));
+                instructions.push(Instruction::Panic);
+            }
+            Self::RecordingArguments => {
+                // Ownership of the arguments moves to the list.
+                instructions.extend(
+                    (0..argument_count).map(|_| Instruction::PushFromStack(argument_count)),
+                );
+                instructions.push(Instruction::CreateList {
+                    num_items: argument_count,
+                });
+                instructions.push(Instruction::PopMultipleBelowTopAndReturn(locals_count));
+            }
+        }
+        instructions
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FuzzingCallbacks {
+    bodies: FxHashMap<(FuzzingCallbackKind, usize), InstructionPointer>,
+}
+impl FuzzingCallbacks {
+    pub const MAX_ARGUMENT_COUNT: usize = 3;
+
+    /// Appends the bodies of all callbacks to the byte code.
+    pub(crate) fn compile(byte_code: &mut ByteCode) -> Self {
+        let mut bodies = FxHashMap::default();
+        for kind in FuzzingCallbackKind::VARIANTS {
+            for argument_count in kind.argument_counts() {
+                let start: InstructionPointer = byte_code.instructions.len().into();
+                let instructions = kind.instructions(argument_count);
+                byte_code
+                    .origins
+                    .extend(instructions.iter().map(|_| FxHashSet::default()));
+                byte_code.instructions.extend(instructions);
+                bodies.insert((*kind, argument_count), start);
+            }
+        }
+        Self { bodies }
+    }
+
+    #[must_use]
+    pub fn body(&self, kind: FuzzingCallbackKind, argument_count: usize) -> InstructionPointer {
+        self.bodies[&(kind, argument_count)]
+    }
+}
@@ -0,0 +1,303 @@
+//! Conversions between Rust values and objects on the heap.
+//!
+//! Implementations for container types like [`Vec`], [`HashMap`], [`Option`],
+//! and [`Result`] delegate to the implementations of their items, so
+//! implementing both traits for your own type makes it usable in all of them.
+
+use super::{
+    Bytes, Float, Function, Handle, Heap, HirId, InlineObject, Int, List, Struct, Tag, Text,
+};
+use itertools::Itertools;
+use num_bigint::BigInt;
+use rustc_hash::FxHashMap;
+use std::{
+    collections::HashMap,
+    hash::{BuildHasher, Hash},
+};
+
+pub trait IntoHeapValue {
+    /// Creates the value on the heap. The returned object is owned by the
+    /// caller.
+    fn into_heap_value(self, heap: &mut Heap) -> InlineObject;
+}
+pub trait TryFromHeapValue: Sized {
+    /// Reads the value from the heap. This doesn't change the object's
+    /// reference count.
+    fn try_from_heap_value(heap: &Heap, object: InlineObject) -> Result<Self, &'static str>;
+}
+
+/// Returns the text to use as a tag's symbol, reusing the default symbols.
+pub fn create_symbol(heap: &mut Heap, name: &str) -> Text {
+    heap.default_symbols()
+        .get(name)
+        .unwrap_or_else(|| Text::create(heap, true, name))
+}
+
+impl IntoHeapValue for InlineObject {
+    fn into_heap_value(self, _heap: &mut Heap) -> InlineObject {
+        self
+    }
+}
+impl TryFromHeapValue for InlineObject {
+    fn try_from_heap_value(_heap: &Heap, object: InlineObject) -> Result<Self, &'static str> {
+        Ok(object)
+    }
+}
+macro_rules! impl_for_objects {
+    ($($type:ty),* $(,)?) => {
+        $(
+            impl IntoHeapValue for $type {
+                fn into_heap_value(self, _heap: &mut Heap) -> InlineObject {
+                    self.into()
+                }
+            }
+            impl TryFromHeapValue for $type {
+                fn try_from_heap_value(
+                    _heap: &Heap,
+                    object: InlineObject,
+                ) -> Result<Self, &'static str> {
+                    object.try_into()
+                }
+            }
+        )*
+    };
+}
+impl_for_objects!(Bytes, Float, Function, Handle, HirId, Int, List, Struct, Tag, Text);
+
+// Primitives
+
+macro_rules! impl_for_ints {
+    ($($type:ty),* $(,)?) => {
+        $(
+            impl IntoHeapValue for $type {
+                fn into_heap_value(self, heap: &mut Heap) -> InlineObject {
+                    Int::create(heap, true, self).into()
+                }
+            }
+            impl TryFromHeapValue for $type {
+                fn try_from_heap_value(
+                    _heap: &Heap,
+                    object: InlineObject,
+                ) -> Result<Self, &'static str> {
+                    Int::try_from(object)?
+                        .try_get()
+                        .ok_or(concat!("Expected an int that fits into `", stringify!($type), "`."))
+                }
+            }
+        )*
+    };
+}
+impl_for_ints!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+impl IntoHeapValue for BigInt {
+    fn into_heap_value(self, heap: &mut Heap) -> InlineObject {
+        Int::create_from_bigint(heap, true, self).into()
+    }
+}
+impl TryFromHeapValue for BigInt {
+    fn try_from_heap_value(_heap: &Heap, object: InlineObject) -> Result<Self, &'static str> {
+        Ok(Int::try_from(object)?.get().into_owned())
+    }
+}
+
+impl IntoHeapValue for f64 {
+    fn into_heap_value(self, heap: &mut Heap) -> InlineObject {
+        Float::create(heap, true, self).into()
+    }
+}
+impl TryFromHeapValue for f64 {
+    fn try_from_heap_value(_heap: &Heap, object: InlineObject) -> Result<Self, &'static str> {
+        Ok(*Float::try_from(object)?.get())
+    }
+}
+
+impl IntoHeapValue for bool {
+    fn into_heap_value(self, heap: &mut Heap) -> InlineObject {
+        Tag::create_bool(heap, self).into()
+    }
+}
+impl TryFromHeapValue for bool {
+    fn try_from_heap_value(heap: &Heap, object: InlineObject) -> Result<Self, &'static str> {
+        Tag::try_from(object)?.try_into_bool(heap)
+    }
+}
+
+/// `()` corresponds to `Nothing`.
+impl IntoHeapValue for () {
+    fn into_heap_value(self, heap: &mut Heap) -> InlineObject {
+        Tag::create_nothing(heap).into()
+    }
+}
+impl TryFromHeapValue for () {
+    fn try_from_heap_value(heap: &Heap, object: InlineObject) -> Result<Self, &'static str> {
+        let tag = Tag::try_from(object)?;
+        if tag.symbol() != heap.default_symbols().nothing || tag.value().is_some() {
+            return Err("Expected `Nothing`.");
+        }
+        Ok(())
+    }
+}
+
+impl IntoHeapValue for &str {
+    fn into_heap_value(self, heap: &mut Heap) -> InlineObject {
+        Text::create(heap, true, self).into()
+    }
+}
+impl IntoHeapValue for String {
+    fn into_heap_value(self, heap: &mut Heap) -> InlineObject {
+        self.as_str().into_heap_value(heap)
+    }
+}
+impl TryFromHeapValue for String {
+    fn try_from_heap_value(_heap: &Heap, object: InlineObject) -> Result<Self, &'static str> {
+        Ok(Text::try_from(object)?.get().to_string())
+    }
+}
+
+// Containers
+
+impl<T: IntoHeapValue> IntoHeapValue for Vec<T> {
+    fn into_heap_value(self, heap: &mut Heap) -> InlineObject {
+        let items = self
+            .into_iter()
+            .map(|it| it.into_heap_value(heap))
+            .collect_vec();
+        List::create(heap, true, &items).into()
+    }
+}
+impl<T: TryFromHeapValue> TryFromHeapValue for Vec<T> {
+    fn try_from_heap_value(heap: &Heap, object: InlineObject) -> Result<Self, &'static str> {
+        List::try_from(object)?
+            .items()
+            .iter()
+            .map(|it| T::try_from_heap_value(heap, *it))
+            .try_collect()
+    }
+}
+
+/// Maps correspond to structs.
+impl<K: IntoHeapValue, V: IntoHeapValue, S> IntoHeapValue for HashMap<K, V, S> {
+    fn into_heap_value(self, heap: &mut Heap) -> InlineObject {
+        let fields: FxHashMap<_, _> = self
+            .into_iter()
+            .map(|(key, value)| (key.into_heap_value(heap), value.into_heap_value(heap)))
+            .collect();
+        Struct::create(heap, true, &fields).into()
+    }
+}
+impl<K, V, S> TryFromHeapValue for HashMap<K, V, S>
+where
+    K: TryFromHeapValue + Eq + Hash,
+    V: TryFromHeapValue,
+    S: BuildHasher + Default,
+{
+    fn try_from_heap_value(heap: &Heap, object: InlineObject) -> Result<Self, &'static str> {
+        Struct::try_from(object)?
+            .iter()
+            .map(|(_, key, value)| {
+                Ok((
+                    K::try_from_heap_value(heap, key)?,
+                    V::try_from_heap_value(heap, value)?,
+                ))
+            })
+            .try_collect()
+    }
+}
+
+/// Options correspond to `Some value` and `None`.
+impl<T: IntoHeapValue> IntoHeapValue for Option<T> {
+    fn into_heap_value(self, heap: &mut Heap) -> InlineObject {
+        match self {
+            Some(value) => {
+                let value = value.into_heap_value(heap);
+                let symbol = create_symbol(heap, "Some");
+                Tag::create_with_value(heap, true, symbol, value).into()
+            }
+            None => Tag::create(create_symbol(heap, "None")).into(),
+        }
+    }
+}
+impl<T: TryFromHeapValue> TryFromHeapValue for Option<T> {
+    fn try_from_heap_value(heap: &Heap, object: InlineObject) -> Result<Self, &'static str> {
+        let tag = Tag::try_from(object)?;
+        match (tag.symbol().get(), tag.value()) {
+            ("Some", Some(value)) => T::try_from_heap_value(heap, value).map(Some),
+            ("None", None) => Ok(None),
+            _ => Err("Expected `Some value` or `None`."),
+        }
+    }
+}
+
+/// Results correspond to `Ok value` and `Error value`.
+impl<T: IntoHeapValue, E: IntoHeapValue> IntoHeapValue for Result<T, E> {
+    fn into_heap_value(self, heap: &mut Heap) -> InlineObject {
+        let value = match self {
+            Ok(value) => Ok(value.into_heap_value(heap)),
+            Err(error) => Err(error.into_heap_value(heap)),
+        };
+        Tag::create_result(heap, true, value).into()
+    }
+}
+impl<T: TryFromHeapValue, E: TryFromHeapValue> TryFromHeapValue for Result<T, E> {
+    fn try_from_heap_value(heap: &Heap, object: InlineObject) -> Result<Self, &'static str> {
+        let tag = Tag::try_from(object)?;
+        let symbols = heap.default_symbols();
+        match tag.value() {
+            Some(value) if tag.symbol() == symbols.ok => {
+                T::try_from_heap_value(heap, value).map(Ok)
+            }
+            Some(value) if tag.symbol() == symbols.error => {
+                E::try_from_heap_value(heap, value).map(Err)
+            }
+            _ => Err("Expected `Ok value` or `Error value`."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IntoHeapValue, TryFromHeapValue};
+    use crate::heap::Heap;
+    use num_bigint::BigInt;
+    use rustc_hash::FxHashMap;
+    use std::fmt::Debug;
+
+    #[test]
+    fn round_trip() {
+        let mut heap = Heap::default();
+        test(&mut heap, 42u8);
+        test(&mut heap, -1234i64);
+        test(&mut heap, u128::MAX);
+        test(&mut heap, BigInt::from(i64::MAX) * 4);
+        test(&mut heap, 1.5f64);
+        test(&mut heap, true);
+        test(&mut heap, ());
+        test(&mut heap, "Hello".to_string());
+        test(&mut heap, vec![1, 2, 3]);
+        test(&mut heap, Some(vec![Some(1), None]));
+        test(&mut heap, Ok::<_, String>(1));
+        test(&mut heap, Err::<i32, _>("Oops".to_string()));
+        test(
+            &mut heap,
+            FxHashMap::from_iter([("Foo".to_string(), 1), ("Bar".to_string(), 2)]),
+        );
+    }
+    #[test]
+    fn errors() {
+        let mut heap = Heap::default();
+        let object = 300.into_heap_value(&mut heap);
+        assert!(u8::try_from_heap_value(&heap, object).is_err());
+        assert!(String::try_from_heap_value(&heap, object).is_err());
+        assert!(bool::try_from_heap_value(&heap, object).is_err());
+    }
+
+    #[track_caller]
+    fn test<T: IntoHeapValue + TryFromHeapValue + Clone + Debug + PartialEq>(
+        heap: &mut Heap,
+        value: T,
+    ) {
+        let object = value.clone().into_heap_value(heap);
+        assert_eq!(T::try_from_heap_value(heap, object), Ok(value));
+        object.drop(heap);
+    }
+}
@@ -0,0 +1,226 @@
+//! Deep equality, hashing, and ordering of values.
+//!
+//! Lists, structs, tags, and functions can nest arbitrarily deep, so these
+//! operations traverse values using explicit work lists instead of recursion.
+//! That way, comparing or hashing deeply nested values (e.g., long linked lists
+//! built from nested tags) can't overflow the Rust stack.
+//!
+//! Values are immutable and can only reference values created before them, so
+//! they can't contain cycles.
+
+use super::{Data, DataDiscriminants, InlineObject, Struct};
+use itertools::Itertools;
+use std::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+    mem,
+};
+
+pub fn equals(a: InlineObject, b: InlineObject) -> bool {
+    let mut pending = vec![(a, b)];
+    while let Some((a, b)) = pending.pop() {
+        if a.raw_word() == b.raw_word() {
+            continue;
+        }
+
+        match (Data::from(a), Data::from(b)) {
+            (Data::Tag(a), Data::Tag(b)) => {
+                if a.symbol() != b.symbol() {
+                    return false;
+                }
+                match (a.value(), b.value()) {
+                    (None, None) => {}
+                    (Some(a), Some(b)) => pending.push((a, b)),
+                    _ => return false,
+                }
+            }
+            (Data::List(a), Data::List(b)) => {
+                if a.len() != b.len() {
+                    return false;
+                }
+                pending.extend(a.items().iter().copied().zip(b.items().iter().copied()));
+            }
+            (Data::Struct(a), Data::Struct(b)) => {
+                if a.len() != b.len() {
+                    return false;
+                }
+                for ((a_hash, a_key, a_value), (b_hash, b_key, b_value)) in a.iter().zip(b.iter()) {
+                    if a_hash != b_hash {
+                        return false;
+                    }
+                    pending.push((a_key, b_key));
+                    pending.push((a_value, b_value));
+                }
+            }
+            (Data::Function(a), Data::Function(b)) => {
+                // TODO: Compare the underlying HIR ID once we have it here (plus captured stuff)
+                if a.argument_count() != b.argument_count()
+                    || a.body() != b.body()
+                    || a.captured_len() != b.captured_len()
+                {
+                    return false;
+                }
+                pending.extend(
+                    a.captured()
+                        .iter()
+                        .copied()
+                        .zip(b.captured().iter().copied()),
+                );
+            }
+            // All remaining kinds don't contain other values.
+            (a, b) => {
+                if a != b {
+                    return false;
+                }
+            }
+        }
+    }
+    true
+}
+
+pub fn hash<H: Hasher>(object: InlineObject, state: &mut H) {
+    let mut pending = vec![object];
+    while let Some(object) = pending.pop() {
+        let data = Data::from(object);
+        match data {
+            Data::Tag(tag) => {
+                mem::discriminant(&data).hash(state);
+                tag.symbol().hash(state);
+                let value = tag.value();
+                value.is_some().hash(state);
+                pending.extend(value);
+            }
+            Data::List(list) => {
+                mem::discriminant(&data).hash(state);
+                list.len().hash(state);
+                pending.extend(list.items().iter().rev());
+            }
+            Data::Struct(struct_) => {
+                // The hashes of the keys already identify them well enough.
+                mem::discriminant(&data).hash(state);
+                let (hashes, values): (Vec<_>, Vec<_>) =
+                    struct_.iter().map(|(hash, _, value)| (hash, value)).unzip();
+                hashes.hash(state);
+                pending.extend(values.into_iter().rev());
+            }
+            Data::Function(function) => {
+                mem::discriminant(&data).hash(state);
+                function.argument_count().hash(state);
+                function.body().hash(state);
+                function.captured_len().hash(state);
+                pending.extend(function.captured().iter().rev());
+            }
+            // All remaining kinds don't contain other values.
+            data => data.hash(state),
+        }
+    }
+}
+
+pub fn compare(a: InlineObject, b: InlineObject) -> Ordering {
+    let mut pending = vec![Task::Compare(a, b)];
+    while let Some(task) = pending.pop() {
+        let (a, b) = match task {
+            Task::Compare(a, b) => (a, b),
+            Task::Decide(Ordering::Equal) => continue,
+            Task::Decide(ordering) => return ordering,
+        };
+        if a.raw_word() == b.raw_word() {
+            continue;
+        }
+
+        let (a, b) = (Data::from(a), Data::from(b));
+        let kind_ordering = DataDiscriminants::from(a).cmp(&DataDiscriminants::from(b));
+        if kind_ordering != Ordering::Equal {
+            return kind_ordering;
+        }
+
+        match (a, b) {
+            (Data::Tag(a), Data::Tag(b)) => {
+                let symbol_ordering = a.symbol().cmp(&b.symbol());
+                if symbol_ordering != Ordering::Equal {
+                    return symbol_ordering;
+                }
+                match (a.value(), b.value()) {
+                    (None, None) => {}
+                    (None, Some(_)) => return Ordering::Less,
+                    (Some(_), None) => return Ordering::Greater,
+                    (Some(a), Some(b)) => pending.push(Task::Compare(a, b)),
+                }
+            }
+            (Data::List(a), Data::List(b)) => {
+                push_lexicographic_comparison(&mut pending, a.items(), b.items());
+            }
+            (Data::Struct(a), Data::Struct(b)) => {
+                // Structs are compared like lists of their entries sorted by
+                // key: first by their keys, then by the values of those keys.
+                let sorted_entries = |struct_: &Struct| {
+                    struct_
+                        .iter()
+                        .map(|(_, key, value)| (key, value))
+                        .sorted_by_key(|(key, _)| *key)
+                        .collect_vec()
+                };
+                let (a, b) = (sorted_entries(&a), sorted_entries(&b));
+                let values = |entries: &[(InlineObject, InlineObject)]| {
+                    entries.iter().map(|(_, value)| *value).collect_vec()
+                };
+                push_lexicographic_comparison(&mut pending, &values(&a), &values(&b));
+                let keys = |entries: &[(InlineObject, InlineObject)]| {
+                    entries.iter().map(|(key, _)| *key).collect_vec()
+                };
+                push_lexicographic_comparison(&mut pending, &keys(&a), &keys(&b));
+            }
+            // All remaining kinds don't contain other values.
+            (a, b) => {
+                let ordering = a.cmp(&b);
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+        }
+    }
+    Ordering::Equal
+}
+enum Task {
+    Compare(InlineObject, InlineObject),
+    /// Decides the comparison if all tasks pushed after this one resulted in
+    /// [`Ordering::Equal`], e.g., comparing the lengths of lists after their
+    /// common items.
+    Decide(Ordering),
+}
+fn push_lexicographic_comparison(pending: &mut Vec<Task>, a: &[InlineObject], b: &[InlineObject]) {
+    // Tasks are popped from the end, so we push them in reverse order.
+    pending.push(Task::Decide(a.len().cmp(&b.len())));
+    pending.extend(a.iter().zip(b).rev().map(|(a, b)| Task::Compare(*a, *b)));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::heap::{Heap, InlineObject, Int, List};
+    use candy_frontend::utils::DoHash;
+    use std::cmp::Ordering;
+
+    fn create_nested_list(heap: &mut Heap, depth: usize, innermost: i64) -> InlineObject {
+        let mut list: InlineObject = Int::create(heap, true, innermost).into();
+        for _ in 0..depth {
+            list = List::create(heap, true, &[list]).into();
+        }
+        list
+    }
+
+    #[test]
+    fn deeply_nested_values_dont_overflow_the_stack() {
+        // The nested lists are freed when the heap is dropped, which doesn't
+        // recurse either.
+        let mut heap = Heap::default();
+        let a = create_nested_list(&mut heap, 100_000, 1);
+        let b = create_nested_list(&mut heap, 100_000, 1);
+        let c = create_nested_list(&mut heap, 100_000, 2);
+
+        assert!(a == b);
+        assert!(a != c);
+        assert_eq!(a.do_hash(), b.do_hash());
+        assert_eq!(a.cmp(&b), Ordering::Equal);
+        assert_eq!(a.cmp(&c), Ordering::Less);
+    }
+}
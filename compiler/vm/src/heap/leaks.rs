@@ -0,0 +1,282 @@
+use super::{
+    Data, Heap, HeapData, HeapObject, HeapObjectTrait, InlineData, InlineObject, ObjectInHeap,
+    ToDebugText,
+};
+use crate::{byte_code::ByteCode, handle_id::HandleId, instruction_pointer::InstructionPointer};
+use candy_frontend::{
+    format::{MaxLength, Precedence},
+    hir,
+};
+use itertools::Itertools;
+use rustc_hash::FxHashMap;
+use std::fmt::{self, Display, Formatter};
+
+/// Reference-counted objects and handles that are still alive, usually
+/// collected after everything that should own them was dropped.
+///
+/// Similar to valgrind, leaked objects are split into directly leaked ones
+/// (that are referenced from outside the leaked objects) and indirectly leaked
+/// ones (that are only kept alive by other leaked objects). Fixing the directly
+/// leaked objects usually also fixes the indirect ones.
+#[derive(Debug)]
+pub struct LeakReport {
+    pub objects: Vec<LeakedObject>,
+    /// Handles with their remaining reference count.
+    pub handles: Vec<(HandleId, usize)>,
+}
+/// A snapshot of a leaked object. It doesn't reference the heap, so it stays
+/// valid after the heap is dropped.
+#[derive(Debug)]
+pub struct LeakedObject {
+    pub address: usize,
+    /// The object's value, shortened for big objects.
+    pub value: String,
+    pub size: usize,
+    pub reference_count: usize,
+    /// How many references to this object come from other leaked objects.
+    pub references_from_leaked_objects: usize,
+    /// The instruction that was running when this object was allocated. Only
+    /// available with the `leak_tracking` feature.
+    pub allocation_site: Option<InstructionPointer>,
+    /// The functions containing the allocation site.
+    pub allocated_in: Vec<hir::Id>,
+}
+impl LeakedObject {
+    #[must_use]
+    pub const fn is_directly_leaked(&self) -> bool {
+        self.reference_count > self.references_from_leaked_objects
+    }
+    /// Other leaked objects reference this object more often than its
+    /// reference count says, so it would be freed too early.
+    #[must_use]
+    pub const fn has_suspicious_reference_count(&self) -> bool {
+        self.reference_count < self.references_from_leaked_objects
+    }
+}
+
+impl Heap {
+    /// Collects all reference-counted objects and handles that are still
+    /// alive.
+    ///
+    /// Call this after dropping everything you own, e.g., the return value of
+    /// the VM and the environment. Objects that aren't reference counted (such
+    /// as the default symbols) are never reported. The byte code is used to
+    /// find the functions in which objects were allocated.
+    #[must_use]
+    pub fn leak_report(&self, byte_code: Option<&ByteCode>) -> LeakReport {
+        // Keyed by address since equal objects can be stored multiple times.
+        let mut references = FxHashMap::<ObjectInHeap, usize>::default();
+        for object in self.iter().filter(|it| it.is_reference_counted()) {
+            for child in HeapData::from(object).children() {
+                if let Some(child) = referenced_heap_object(child)
+                    && child.is_reference_counted()
+                {
+                    *references.entry(ObjectInHeap(child)).or_default() += 1;
+                }
+            }
+        }
+
+        let objects = self
+            .iter()
+            .filter_map(|object| {
+                let allocation_site = self.allocation_site(object);
+                Some(LeakedObject {
+                    address: object.address().addr().get(),
+                    value: format_value(object),
+                    size: HeapData::from(object).total_size(),
+                    reference_count: object.reference_count()?,
+                    references_from_leaked_objects: references
+                        .get(&ObjectInHeap(object))
+                        .copied()
+                        .unwrap_or_default(),
+                    allocation_site,
+                    allocated_in: allocation_site
+                        .zip(byte_code)
+                        .map(|(site, byte_code)| {
+                            byte_code
+                                .functions_behind(site)
+                                .iter()
+                                .cloned()
+                                .sorted()
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                })
+            })
+            .sorted_by_key(|it| (!it.is_directly_leaked(), it.allocation_site, it.address))
+            .collect();
+        let handles = self
+            .handle_refcounts
+            .iter()
+            .map(|(handle_id, reference_count)| (*handle_id, *reference_count))
+            .sorted()
+            .collect();
+        LeakReport { objects, handles }
+    }
+
+    #[cfg(feature = "leak_tracking")]
+    fn allocation_site(&self, object: HeapObject) -> Option<InstructionPointer> {
+        self.allocation_sites.get(&ObjectInHeap(object)).copied()
+    }
+    #[cfg(not(feature = "leak_tracking"))]
+    #[allow(clippy::unused_self)]
+    const fn allocation_site(&self, _object: HeapObject) -> Option<InstructionPointer> {
+        None
+    }
+}
+
+fn format_value(object: HeapObject) -> String {
+    let object = InlineObject::from(object);
+    if let Data::HirId(id) = object.into() {
+        id.to_string()
+    } else {
+        object.to_debug_text(Precedence::Low, MaxLength::Limited(60))
+    }
+}
+
+fn referenced_heap_object(object: InlineObject) -> Option<HeapObject> {
+    match InlineData::from(object) {
+        InlineData::Pointer(pointer) => Some(pointer.get()),
+        InlineData::Tag(tag) => referenced_heap_object(tag.get().into()),
+        _ => None,
+    }
+}
+
+impl HeapData {
+    /// The objects this object holds a reference to.
+    #[must_use]
+    fn children(self) -> Vec<InlineObject> {
+        match self {
+            Self::List(list) => list.items().to_vec(),
+            Self::Struct(struct_) => struct_
+                .keys()
+                .iter()
+                .chain(struct_.values())
+                .chain(struct_.nodes())
+                .copied()
+                .collect(),
+            Self::Tag(tag) => vec![tag.symbol().into(), tag.value()],
+            Self::Function(function) => function.captured().to_vec(),
+            Self::Text(text) => text
+                .rope_parts()
+                .map_or_else(Vec::new, |(left, right)| vec![left.into(), right.into()]),
+            Self::Int(_) | Self::Float(_) | Self::Bytes(_) | Self::HirId(_) => vec![],
+        }
+    }
+}
+
+impl LeakReport {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty() && self.handles.is_empty()
+    }
+}
+impl Display for LeakReport {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let (direct, indirect): (Vec<_>, Vec<_>) =
+            self.objects.iter().partition(|it| it.is_directly_leaked());
+        let total_size =
+            |objects: &[&LeakedObject]| -> usize { objects.iter().map(|it| it.size).sum() };
+        writeln!(f, "Leak summary:")?;
+        writeln!(
+            f,
+            "  directly leaked:   {} objects ({} bytes)",
+            direct.len(),
+            total_size(&direct),
+        )?;
+        writeln!(
+            f,
+            "  indirectly leaked: {} objects ({} bytes)",
+            indirect.len(),
+            total_size(&indirect),
+        )?;
+        write!(f, "  handles:           {}", self.handles.len())?;
+
+        // Indirectly leaked objects are only listed if their reference count
+        // looks wrong. Otherwise, they disappear once the directly leaked
+        // objects are fixed.
+        for object in direct.iter().chain(
+            indirect
+                .iter()
+                .filter(|it| it.has_suspicious_reference_count()),
+        ) {
+            write!(
+                f,
+                "\n{:#x} ({}",
+                object.address,
+                format_reference_count(object.reference_count),
+            )?;
+            if object.references_from_leaked_objects > 0 {
+                write!(
+                    f,
+                    ", {} from leaked objects",
+                    object.references_from_leaked_objects,
+                )?;
+            }
+            if object.has_suspicious_reference_count() {
+                write!(f, ", suspicious")?;
+            }
+            write!(f, "): {}", object.value)?;
+
+            if let Some(allocation_site) = object.allocation_site {
+                write!(f, "\n  allocated by instruction {}", *allocation_site)?;
+                if !object.allocated_in.is_empty() {
+                    write!(f, " in {}", object.allocated_in.iter().join(", "))?;
+                }
+            }
+        }
+        for (handle_id, reference_count) in &self.handles {
+            write!(
+                f,
+                "\nhandle {handle_id:?} ({})",
+                format_reference_count(*reference_count),
+            )?;
+        }
+        Ok(())
+    }
+}
+fn format_reference_count(reference_count: usize) -> String {
+    format!(
+        "{reference_count} {}",
+        if reference_count == 1 { "ref" } else { "refs" },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::heap::{Heap, InlineObject, List, Text};
+
+    #[test]
+    fn leak_report_distinguishes_direct_and_indirect_leaks() {
+        let mut heap = Heap::default();
+        assert!(heap.leak_report(None).is_empty());
+
+        let text = Text::create(&mut heap, true, "Hello");
+        let list = List::create(&mut heap, true, &[text.into()]);
+        let report = heap.leak_report(None);
+        assert_eq!(report.objects.len(), 2);
+        let text_address = text.address().addr().get();
+        let leaked_text = report
+            .objects
+            .iter()
+            .find(|it| it.address == text_address)
+            .unwrap();
+        assert!(!leaked_text.is_directly_leaked());
+        assert!(!leaked_text.has_suspicious_reference_count());
+
+        // Another list referencing the text without a corresponding dup.
+        let other_list = List::create(&mut heap, true, &[text.into()]);
+        let report = heap.leak_report(None);
+        let leaked_text = report
+            .objects
+            .iter()
+            .find(|it| it.address == text_address)
+            .unwrap();
+        assert!(leaked_text.has_suspicious_reference_count());
+
+        InlineObject::from(text).dup(&mut heap);
+        InlineObject::from(other_list).drop(&mut heap);
+        InlineObject::from(list).drop(&mut heap);
+        assert!(heap.leak_report(None).is_empty());
+    }
+}
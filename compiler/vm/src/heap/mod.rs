@@ -1,7 +1,12 @@
+pub(crate) use self::conversion::create_symbol;
+pub use self::leaks::{LeakReport, LeakedObject};
 use self::object_heap::text::HeapText;
+pub use self::stats::{HeapStats, ObjectCounts};
 pub use self::{
+    conversion::{IntoHeapValue, TryFromHeapValue},
     object::{
-        Builtin, Data, DataDiscriminants, Function, Handle, HirId, Int, List, Struct, Tag, Text,
+        Builtin, Bytes, Data, DataDiscriminants, Float, Function, Handle, HirId, Int, List, Struct,
+        Tag, Text,
     },
     object_heap::{HeapData, HeapObject, HeapObjectTrait},
     object_inline::{
@@ -9,9 +14,10 @@ pub use self::{
         InlineObjectSliceCloneToHeap, InlineObjectTrait, ToDebugText,
     },
 };
-use crate::handle_id::HandleId;
+use crate::{handle_id::HandleId, instruction_pointer::InstructionPointer};
 use candy_frontend::id::IdGenerator;
 use derive_more::{DebugCustom, Deref, Pointer};
+use regex::Regex;
 use rustc_hash::{FxHashMap, FxHashSet};
 use std::{
     alloc::{self, Allocator, Layout},
@@ -21,9 +27,13 @@ use std::{
 };
 use tracing::debug;
 
+mod conversion;
+mod deep;
+mod leaks;
 mod object;
 mod object_heap;
 mod object_inline;
+mod stats;
 
 pub const DEBUG_ALLOCATIONS: bool = false;
 
@@ -32,6 +42,16 @@ pub struct Heap {
     default_symbols: Option<DefaultSymbols>,
     handle_id_generator: IdGenerator<HandleId>,
     handle_refcounts: FxHashMap<HandleId, usize>,
+    /// Compiled regexes, keyed by their pattern. They are stored outside of the
+    /// heap objects so that each pattern is only compiled once.
+    regex_cache: FxHashMap<String, Regex>,
+    stats: HeapStats,
+    /// For each object, the instruction that was running when it was
+    /// allocated. Used for leak reports.
+    #[cfg(feature = "leak_tracking")]
+    allocation_sites: FxHashMap<ObjectInHeap, InstructionPointer>,
+    #[cfg(feature = "leak_tracking")]
+    current_allocation_site: Option<InstructionPointer>,
 }
 
 impl Heap {
@@ -72,10 +92,17 @@ impl Heap {
             object.set_reference_count(1);
         }
         self.objects.insert(ObjectInHeap(object));
+        self.stats.record_allocation(HeapData::from(object));
+        #[cfg(feature = "leak_tracking")]
+        if let Some(allocation_site) = self.current_allocation_site {
+            self.allocation_sites
+                .insert(ObjectInHeap(object), allocation_site);
+        }
         object
     }
     /// Don't call this method directly, call [drop] or [free] instead!
     pub(super) fn deallocate(&mut self, object: HeapData) {
+        self.stats.record_free(object);
         object.deallocate_external_stuff();
         let layout = Layout::from_size_align(
             2 * HeapObject::WORD_SIZE + object.content_size(),
@@ -83,9 +110,23 @@ impl Heap {
         )
         .unwrap();
         self.objects.remove(&ObjectInHeap(*object));
+        #[cfg(feature = "leak_tracking")]
+        self.allocation_sites.remove(&ObjectInHeap(*object));
         unsafe { alloc::Global.deallocate(object.address().cast(), layout) };
     }
 
+    /// Objects allocated from now on are attributed to the instruction at
+    /// `instruction_pointer` in leak reports. This does nothing unless the
+    /// `leak_tracking` feature is enabled.
+    #[cfg(feature = "leak_tracking")]
+    pub fn set_allocation_site(&mut self, instruction_pointer: InstructionPointer) {
+        self.current_allocation_site = Some(instruction_pointer);
+    }
+    #[cfg(not(feature = "leak_tracking"))]
+    #[allow(clippy::unused_self)]
+    #[inline]
+    pub fn set_allocation_site(&mut self, _instruction_pointer: InstructionPointer) {}
+
     pub(self) fn notify_handle_created(&mut self, handle_id: HandleId) {
         *self.handle_refcounts.entry(handle_id).or_default() += 1;
     }
@@ -110,6 +151,21 @@ impl Heap {
         for (handle_id, refcount) in mem::take(&mut other.handle_refcounts) {
             *self.handle_refcounts.entry(handle_id).or_default() += refcount;
         }
+        self.regex_cache.extend(mem::take(&mut other.regex_cache));
+        self.stats.merge(&mem::take(&mut other.stats));
+        #[cfg(feature = "leak_tracking")]
+        self.allocation_sites
+            .extend(mem::take(&mut other.allocation_sites));
+    }
+
+    /// Returns the compiled regex for the `pattern`, compiling it on first use.
+    pub fn regex(&mut self, pattern: &str) -> Result<Regex, regex::Error> {
+        if let Some(regex) = self.regex_cache.get(pattern) {
+            return Ok(regex.clone());
+        }
+        let regex = Regex::new(pattern)?;
+        self.regex_cache.insert(pattern.to_string(), regex.clone());
+        Ok(regex)
     }
 
     #[must_use]
@@ -139,6 +195,12 @@ impl Heap {
             default_symbols: None,
             handle_id_generator: self.handle_id_generator.clone(),
             handle_refcounts: self.handle_refcounts.clone(),
+            regex_cache: self.regex_cache.clone(),
+            stats: HeapStats::default(),
+            #[cfg(feature = "leak_tracking")]
+            allocation_sites: FxHashMap::default(),
+            #[cfg(feature = "leak_tracking")]
+            current_allocation_site: self.current_allocation_site,
         };
 
         let mut mapping = FxHashMap::default();
@@ -152,6 +214,14 @@ impl Heap {
         for object in &self.objects {
             _ = object.clone_to_heap_with_mapping(&mut cloned, &mut mapping);
         }
+        #[cfg(feature = "leak_tracking")]
+        for (original, clone) in &mapping {
+            if let Some(allocation_site) = self.allocation_sites.get(&ObjectInHeap(*original)) {
+                cloned
+                    .allocation_sites
+                    .insert(ObjectInHeap(*clone), *allocation_site);
+            }
+        }
 
         (cloned, mapping)
     }
@@ -191,6 +261,12 @@ impl Default for Heap {
             default_symbols: None,
             handle_id_generator: IdGenerator::default(),
             handle_refcounts: FxHashMap::default(),
+            regex_cache: FxHashMap::default(),
+            stats: HeapStats::default(),
+            #[cfg(feature = "leak_tracking")]
+            allocation_sites: FxHashMap::default(),
+            #[cfg(feature = "leak_tracking")]
+            current_allocation_site: None,
         };
         heap.default_symbols = Some(DefaultSymbols::new(&mut heap));
         heap
@@ -232,28 +308,39 @@ pub struct DefaultSymbols {
     // Sorted alphabetically
     pub arguments: Text,
     pub builtin: Text,
+    pub bytes: Text,
     pub close: Text,
+    pub debug: Text,
     pub equal: Text,
     pub error: Text,
     pub false_: Text,
     pub file: Text,
     pub file_system: Text,
+    pub float: Text,
+    pub found: Text,
+    pub found_at: Text,
     pub function: Text,
     pub get_random_bytes: Text,
     pub get_next_request: Text,
     pub greater: Text,
     pub http_server: Text,
+    pub info: Text,
     pub int: Text,
     pub less: Text,
     pub list: Text,
+    pub log: Text,
     pub not_an_integer: Text,
+    pub not_found: Text,
     pub not_utf8: Text,
     pub nothing: Text,
     pub ok: Text,
     pub open: Text,
+    pub payload: Text,
     pub read_to_end: Text,
+    pub reason: Text,
     pub request: Text,
     pub send_response: Text,
+    pub stack_trace: Text,
     pub stdin: Text,
     pub stdout: Text,
     pub struct_: Text,
@@ -261,34 +348,46 @@ pub struct DefaultSymbols {
     pub tag: Text,
     pub text: Text,
     pub true_: Text,
+    pub warn: Text,
 }
 impl DefaultSymbols {
     pub fn new(heap: &mut Heap) -> Self {
         Self {
             arguments: Text::create(heap, false, "Arguments"),
             builtin: Text::create(heap, false, "Builtin"),
+            bytes: Text::create(heap, false, "Bytes"),
             close: Text::create(heap, false, "Close"),
+            debug: Text::create(heap, false, "Debug"),
             equal: Text::create(heap, false, "Equal"),
             error: Text::create(heap, false, "Error"),
             false_: Text::create(heap, false, "False"),
             file: Text::create(heap, false, "File"),
             file_system: Text::create(heap, false, "FileSystem"),
+            float: Text::create(heap, false, "Float"),
+            found: Text::create(heap, false, "Found"),
+            found_at: Text::create(heap, false, "FoundAt"),
             function: Text::create(heap, false, "Function"),
             get_next_request: Text::create(heap, false, "GetNextRequest"),
             get_random_bytes: Text::create(heap, false, "GetRandomBytes"),
             greater: Text::create(heap, false, "Greater"),
             http_server: Text::create(heap, false, "HttpServer"),
+            info: Text::create(heap, false, "Info"),
             int: Text::create(heap, false, "Int"),
             less: Text::create(heap, false, "Less"),
             list: Text::create(heap, false, "List"),
+            log: Text::create(heap, false, "Log"),
             not_an_integer: Text::create(heap, false, "NotAnInteger"),
+            not_found: Text::create(heap, false, "NotFound"),
             not_utf8: Text::create(heap, false, "NotUtf8"),
             nothing: Text::create(heap, false, "Nothing"),
             ok: Text::create(heap, false, "Ok"),
             open: Text::create(heap, false, "Open"),
+            payload: Text::create(heap, false, "Payload"),
             read_to_end: Text::create(heap, false, "ReadToEnd"),
+            reason: Text::create(heap, false, "Reason"),
             request: Text::create(heap, false, "Request"),
             send_response: Text::create(heap, false, "SendResponse"),
+            stack_trace: Text::create(heap, false, "StackTrace"),
             stdin: Text::create(heap, false, "Stdin"),
             stdout: Text::create(heap, false, "Stdout"),
             struct_: Text::create(heap, false, "Struct"),
@@ -296,6 +395,7 @@ impl DefaultSymbols {
             tag: Text::create(heap, false, "Tag"),
             text: Text::create(heap, false, "Text"),
             true_: Text::create(heap, false, "True"),
+            warn: Text::create(heap, false, "Warn"),
         }
     }
     fn clone_to_heap_with_mapping(
@@ -315,28 +415,39 @@ impl DefaultSymbols {
         Self {
             arguments: clone_to_heap(heap, address_map, self.arguments),
             builtin: clone_to_heap(heap, address_map, self.builtin),
+            bytes: clone_to_heap(heap, address_map, self.bytes),
             close: clone_to_heap(heap, address_map, self.close),
+            debug: clone_to_heap(heap, address_map, self.debug),
             equal: clone_to_heap(heap, address_map, self.equal),
             error: clone_to_heap(heap, address_map, self.error),
             false_: clone_to_heap(heap, address_map, self.false_),
             file: clone_to_heap(heap, address_map, self.file),
             file_system: clone_to_heap(heap, address_map, self.file_system),
+            float: clone_to_heap(heap, address_map, self.float),
+            found: clone_to_heap(heap, address_map, self.found),
+            found_at: clone_to_heap(heap, address_map, self.found_at),
             function: clone_to_heap(heap, address_map, self.function),
             get_next_request: clone_to_heap(heap, address_map, self.get_next_request),
             get_random_bytes: clone_to_heap(heap, address_map, self.get_random_bytes),
             greater: clone_to_heap(heap, address_map, self.greater),
             http_server: clone_to_heap(heap, address_map, self.http_server),
+            info: clone_to_heap(heap, address_map, self.info),
             int: clone_to_heap(heap, address_map, self.int),
             less: clone_to_heap(heap, address_map, self.less),
             list: clone_to_heap(heap, address_map, self.list),
+            log: clone_to_heap(heap, address_map, self.log),
             not_an_integer: clone_to_heap(heap, address_map, self.not_an_integer),
+            not_found: clone_to_heap(heap, address_map, self.not_found),
             not_utf8: clone_to_heap(heap, address_map, self.not_utf8),
             nothing: clone_to_heap(heap, address_map, self.nothing),
             ok: clone_to_heap(heap, address_map, self.ok),
             open: clone_to_heap(heap, address_map, self.open),
+            payload: clone_to_heap(heap, address_map, self.payload),
             read_to_end: clone_to_heap(heap, address_map, self.read_to_end),
+            reason: clone_to_heap(heap, address_map, self.reason),
             request: clone_to_heap(heap, address_map, self.request),
             send_response: clone_to_heap(heap, address_map, self.send_response),
+            stack_trace: clone_to_heap(heap, address_map, self.stack_trace),
             stdin: clone_to_heap(heap, address_map, self.stdin),
             stdout: clone_to_heap(heap, address_map, self.stdout),
             struct_: clone_to_heap(heap, address_map, self.struct_),
@@ -344,6 +455,7 @@ impl DefaultSymbols {
             tag: clone_to_heap(heap, address_map, self.tag),
             text: clone_to_heap(heap, address_map, self.text),
             true_: clone_to_heap(heap, address_map, self.true_),
+            warn: clone_to_heap(heap, address_map, self.warn),
         }
     }
 
@@ -356,32 +468,43 @@ impl DefaultSymbols {
             .map(|it| symbols[it])
     }
     #[must_use]
-    pub const fn all_symbols(&self) -> [Text; 31] {
+    pub const fn all_symbols(&self) -> [Text; 43] {
         [
             self.arguments,
             self.builtin,
+            self.bytes,
             self.close,
+            self.debug,
             self.equal,
             self.error,
             self.false_,
             self.file,
             self.file_system,
+            self.float,
+            self.found,
+            self.found_at,
             self.function,
             self.get_next_request,
             self.get_random_bytes,
             self.greater,
             self.http_server,
+            self.info,
             self.int,
             self.less,
             self.list,
+            self.log,
             self.not_an_integer,
+            self.not_found,
             self.not_utf8,
             self.nothing,
             self.ok,
             self.open,
+            self.payload,
             self.read_to_end,
+            self.reason,
             self.request,
             self.send_response,
+            self.stack_trace,
             self.stdin,
             self.stdout,
             self.struct_,
@@ -389,6 +512,7 @@ impl DefaultSymbols {
             self.tag,
             self.text,
             self.true_,
+            self.warn,
         ]
     }
 }
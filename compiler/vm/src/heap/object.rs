@@ -1,23 +1,26 @@
 use super::{
     object_heap::{
-        function::HeapFunction, hir_id::HeapHirId, int::HeapInt, list::HeapList,
-        struct_::HeapStruct, tag::HeapTag, text::HeapText, HeapData, HeapObject,
+        bytes::HeapBytes, float::HeapFloat, function::HeapFunction, hir_id::HeapHirId,
+        int::HeapInt, list::HeapList, struct_::HeapStruct, tag::HeapTag, text::HeapText, HeapData,
+        HeapObject,
     },
     object_inline::{
-        builtin::InlineBuiltin, handle::InlineHandle, int::InlineInt, tag::InlineTag, InlineData,
-        InlineObject,
+        builtin::InlineBuiltin, float::InlineFloat, handle::InlineHandle, int::InlineInt,
+        tag::InlineTag, InlineData, InlineObject,
     },
     Heap,
 };
 use crate::{
     handle_id::HandleId,
     instruction_pointer::InstructionPointer,
-    utils::{impl_debug_display_via_debugdisplay, DebugDisplay},
+    utils::{impl_debug_display_via_debugdisplay, impl_eq_hash_ord_via_get, DebugDisplay},
 };
-use candy_frontend::{builtin_functions::BuiltinFunction, hir::Id};
+use candy_frontend::{builtin_functions::BuiltinFunction, format::format_float, hir::Id};
 use derive_more::{Deref, From};
 use num_bigint::BigInt;
-use num_traits::Signed;
+use num_integer::{Integer, Roots};
+use num_traits::{FromPrimitive, Signed, ToPrimitive};
+use ordered_float::OrderedFloat;
 use rustc_hash::FxHashMap;
 use std::{
     borrow::Cow,
@@ -29,11 +32,13 @@ use std::{
 use strum::{EnumDiscriminants, IntoStaticStr};
 
 #[derive(Clone, Copy, EnumDiscriminants, Eq, Hash, IntoStaticStr, Ord, PartialEq, PartialOrd)]
-#[strum_discriminants(derive(IntoStaticStr))]
+#[strum_discriminants(derive(Hash, IntoStaticStr, Ord, PartialOrd))]
 pub enum Data {
     Int(Int),
+    Float(Float),
     Tag(Tag),
     Text(Text),
+    Bytes(Bytes),
     List(List),
     Struct(Struct),
     HirId(HirId),
@@ -60,6 +65,7 @@ impl From<InlineObject> for Data {
             InlineData::Builtin(builtin) => Self::Builtin(Builtin(builtin)),
             InlineData::Tag(symbol_id) => Self::Tag(Tag::Inline(symbol_id)),
             InlineData::Handle(handle) => Self::Handle(Handle(handle)),
+            InlineData::Float(float) => Self::Float(Float::Inline(float)),
         }
     }
 }
@@ -67,10 +73,12 @@ impl From<HeapObject> for Data {
     fn from(object: HeapObject) -> Self {
         match object.into() {
             HeapData::Int(int) => Self::Int(Int::Heap(int)),
+            HeapData::Float(float) => Self::Float(Float::Heap(float)),
             HeapData::List(list) => Self::List(List(list)),
             HeapData::Struct(struct_) => Self::Struct(Struct(struct_)),
             HeapData::Tag(tag) => Self::Tag(Tag::Heap(tag)),
             HeapData::Text(text) => Self::Text(Text(text)),
+            HeapData::Bytes(bytes) => Self::Bytes(Bytes(bytes)),
             HeapData::Function(function) => Self::Function(Function(function)),
             HeapData::HirId(hir_id) => Self::HirId(HirId(hir_id)),
         }
@@ -81,8 +89,10 @@ impl DebugDisplay for Data {
     fn fmt(&self, f: &mut Formatter, is_debug: bool) -> fmt::Result {
         match self {
             Self::Int(int) => DebugDisplay::fmt(int, f, is_debug),
+            Self::Float(float) => DebugDisplay::fmt(float, f, is_debug),
             Self::Tag(tag) => DebugDisplay::fmt(tag, f, is_debug),
             Self::Text(text) => DebugDisplay::fmt(text, f, is_debug),
+            Self::Bytes(bytes) => DebugDisplay::fmt(bytes, f, is_debug),
             Self::List(list) => DebugDisplay::fmt(list, f, is_debug),
             Self::Struct(struct_) => DebugDisplay::fmt(struct_, f, is_debug),
             Self::HirId(hir_id) => DebugDisplay::fmt(hir_id, f, is_debug),
@@ -163,6 +173,34 @@ impl Int {
             (Self::Heap(lhs), Self::Heap(rhs)) => lhs.modulo(heap, rhs.get()),
         }
     }
+    #[must_use]
+    pub fn pow(self, heap: &mut Heap, exponent: u32) -> Self {
+        if let Self::Inline(base) = self
+            && let Some(result) = base.get().checked_pow(exponent)
+        {
+            return Self::create(heap, true, result);
+        }
+        Self::create_from_bigint(heap, true, self.get().pow(exponent))
+    }
+    /// The integer square root, i.e., the square root rounded down. The value
+    /// must not be negative.
+    #[must_use]
+    pub fn sqrt(self, heap: &mut Heap) -> Self {
+        match self {
+            Self::Inline(int) => Self::create(heap, true, int.get().sqrt()),
+            Self::Heap(int) => Self::create_from_bigint(heap, true, int.get().sqrt()),
+        }
+    }
+    /// The greatest common divisor, which is never negative.
+    #[must_use]
+    pub fn gcd(self, heap: &mut Heap, rhs: Self) -> Self {
+        match (self, rhs) {
+            (Self::Inline(lhs), Self::Inline(rhs)) => {
+                Self::create(heap, true, lhs.get().gcd(&rhs.get()))
+            }
+            _ => Self::create_from_bigint(heap, true, self.get().gcd(&rhs.get())),
+        }
+    }
 
     #[must_use]
     pub fn compare_to(self, heap: &Heap, rhs: Self) -> Tag {
@@ -298,6 +336,90 @@ impl PartialOrd for Int {
     }
 }
 
+// Float
+
+#[derive(Clone, Copy, From)]
+pub enum Float {
+    Inline(InlineFloat),
+    Heap(HeapFloat),
+}
+
+impl Float {
+    #[must_use]
+    pub fn create(heap: &mut Heap, is_reference_counted: bool, value: f64) -> Self {
+        InlineFloat::try_from(value).map_or_else(
+            |()| HeapFloat::create(heap, is_reference_counted, value).into(),
+            Into::into,
+        )
+    }
+    #[must_use]
+    pub fn create_from_int(heap: &mut Heap, is_reference_counted: bool, value: Int) -> Self {
+        let value = value.get().to_f64().unwrap();
+        Self::create(heap, is_reference_counted, value)
+    }
+
+    #[must_use]
+    pub fn get(self) -> OrderedFloat<f64> {
+        match self {
+            Self::Inline(float) => float.get(),
+            Self::Heap(float) => float.get(),
+        }
+    }
+
+    float_operator_fn!(add, +);
+    float_operator_fn!(subtract, -);
+    float_operator_fn!(multiply, *);
+    float_operator_fn!(divide, /);
+
+    #[must_use]
+    pub fn compare_to(self, heap: &Heap, rhs: Self) -> Tag {
+        Tag::create_ordering(heap, self.get().cmp(&rhs.get()))
+    }
+
+    #[must_use]
+    pub fn truncate(self, heap: &mut Heap) -> Int {
+        let value = BigInt::from_f64(self.get().trunc()).unwrap();
+        Int::create_from_bigint(heap, true, value)
+    }
+    #[must_use]
+    pub fn to_text(self, heap: &mut Heap) -> Text {
+        Text::create(heap, true, &format_float(*self.get()))
+    }
+}
+
+macro_rules! float_operator_fn {
+    ($name:ident, $operator:tt) => {
+        #[must_use]
+        pub fn $name(self, heap: &mut Heap, rhs: Self) -> Self {
+            Self::create(heap, true, *self.get() $operator *rhs.get())
+        }
+    };
+}
+use float_operator_fn;
+
+impl DebugDisplay for Float {
+    fn fmt(&self, f: &mut Formatter, is_debug: bool) -> fmt::Result {
+        match self {
+            Self::Inline(float) => DebugDisplay::fmt(float, f, is_debug),
+            Self::Heap(float) => DebugDisplay::fmt(float, f, is_debug),
+        }
+    }
+}
+impl_debug_display_via_debugdisplay!(Float);
+
+impl_eq_hash_ord_via_get!(Float);
+
+impl From<Float> for InlineObject {
+    fn from(value: Float) -> Self {
+        match value {
+            Float::Inline(float) => *float,
+            Float::Heap(float) => (*float).into(),
+        }
+    }
+}
+impl_try_froms!(Float, "Expected a float.");
+impl_try_from_heap_object!(Float, "Expected a float.");
+
 // Tag
 
 #[derive(Clone, Copy, Eq, From, Hash, PartialEq)]
@@ -389,6 +511,23 @@ impl Tag {
             Self::Heap(_) => Err("Expected a tag without a value, found {value:?}."),
         }
     }
+    pub fn try_into_ordering(self, heap: &Heap) -> Result<Ordering, &'static str> {
+        match self {
+            Self::Inline(tag) => {
+                let symbol = tag.get();
+                if symbol == heap.default_symbols().less {
+                    Ok(Ordering::Less)
+                } else if symbol == heap.default_symbols().equal {
+                    Ok(Ordering::Equal)
+                } else if symbol == heap.default_symbols().greater {
+                    Ok(Ordering::Greater)
+                } else {
+                    Err("Expected `Less`, `Equal`, or `Greater`.")
+                }
+            }
+            Self::Heap(_) => Err("Expected a tag without a value."),
+        }
+    }
 
     #[must_use]
     pub const fn has_value(&self) -> bool {
@@ -462,12 +601,30 @@ impls_via_0!(Text);
 impl_try_froms!(Text, "Expected a text.");
 impl_try_from_heap_object!(Text, "Expected a text.");
 
+// Bytes
+
+#[derive(Clone, Copy, Deref, Eq, From, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Bytes(HeapBytes);
+
+impl Bytes {
+    #[must_use]
+    pub fn create(heap: &mut Heap, is_reference_counted: bool, value: &[u8]) -> Self {
+        HeapBytes::create(heap, is_reference_counted, value).into()
+    }
+}
+
+impls_via_0!(Bytes);
+impl_try_froms!(Bytes, "Expected bytes.");
+impl_try_from_heap_object!(Bytes, "Expected bytes.");
+
 // List
 
 #[derive(Clone, Copy, Deref, Eq, From, Hash, Ord, PartialEq, PartialOrd)]
 pub struct List(HeapList);
 
 impl List {
+    pub const MAX_LEN: usize = HeapList::MAX_LEN;
+
     #[must_use]
     pub fn create(heap: &mut Heap, is_reference_counted: bool, items: &[InlineObject]) -> Self {
         HeapList::create(heap, is_reference_counted, items).into()
@@ -0,0 +1,119 @@
+use super::{utils::heap_object_impls, HeapObjectTrait};
+use crate::{
+    heap::{object_heap::HeapObject, Bytes, Heap, Int},
+    utils::{impl_debug_display_via_debugdisplay, impl_eq_hash_ord_via_get, DebugDisplay},
+};
+use derive_more::Deref;
+use itertools::Itertools;
+use rustc_hash::FxHashMap;
+use std::{
+    fmt::{self, Formatter},
+    ops::Range,
+    ptr::{self, NonNull},
+    slice,
+};
+
+#[derive(Clone, Copy, Deref)]
+pub struct HeapBytes(HeapObject);
+
+impl HeapBytes {
+    const LEN_SHIFT: usize = 5;
+
+    #[must_use]
+    pub const fn new_unchecked(object: HeapObject) -> Self {
+        Self(object)
+    }
+    #[must_use]
+    pub fn create(heap: &mut Heap, is_reference_counted: bool, value: &[u8]) -> Self {
+        let len = value.len();
+        assert_eq!(
+            (len << Self::LEN_SHIFT) >> Self::LEN_SHIFT,
+            len,
+            "Bytes are too long.",
+        );
+        let bytes = Self(heap.allocate(
+            HeapObject::KIND_TEXT,
+            is_reference_counted,
+            HeapObject::TEXT_IS_BYTES_MASK | ((len as u64) << Self::LEN_SHIFT),
+            len,
+        ));
+        unsafe { ptr::copy_nonoverlapping(value.as_ptr(), bytes.bytes_pointer().as_ptr(), len) };
+        bytes
+    }
+
+    #[must_use]
+    pub fn len(self) -> usize {
+        (self.header_word() >> Self::LEN_SHIFT) as usize
+    }
+    #[must_use]
+    fn bytes_pointer(self) -> NonNull<u8> {
+        self.content_word_pointer(0).cast()
+    }
+    #[must_use]
+    pub fn get<'a>(self) -> &'a [u8] {
+        unsafe { slice::from_raw_parts(self.bytes_pointer().as_ptr(), self.len()) }
+    }
+
+    #[must_use]
+    pub fn length(self, heap: &mut Heap) -> Int {
+        Int::create(heap, true, self.len())
+    }
+    #[must_use]
+    pub fn get_at(self, heap: &mut Heap, index: usize) -> Int {
+        Int::create(heap, true, self.get()[index])
+    }
+    #[must_use]
+    pub fn slice(self, heap: &mut Heap, range: Range<Int>) -> Bytes {
+        // TODO: Support indices larger than usize.
+        let start_inclusive = range
+            .start
+            .try_get::<usize>()
+            .expect("Tried to slice bytes with an index that's too large for usize.");
+        let end_exclusive = range
+            .end
+            .try_get::<usize>()
+            .expect("Tried to slice bytes with an index that's too large for usize.");
+        Bytes::create(heap, true, &self.get()[start_inclusive..end_exclusive])
+    }
+    #[must_use]
+    pub fn concatenate(self, heap: &mut Heap, other: Bytes) -> Bytes {
+        Bytes::create(heap, true, &[self.get(), other.get()].concat())
+    }
+}
+
+impl DebugDisplay for HeapBytes {
+    fn fmt(&self, f: &mut Formatter, _is_debug: bool) -> fmt::Result {
+        write!(f, "<{:02x}>", self.get().iter().format(" "))
+    }
+}
+impl_debug_display_via_debugdisplay!(HeapBytes);
+
+impl_eq_hash_ord_via_get!(HeapBytes);
+
+heap_object_impls!(HeapBytes);
+
+impl HeapObjectTrait for HeapBytes {
+    fn content_size(self) -> usize {
+        self.len()
+    }
+
+    fn clone_content_to_heap_with_mapping(
+        self,
+        _heap: &mut Heap,
+        clone: HeapObject,
+        _address_map: &mut FxHashMap<HeapObject, HeapObject>,
+    ) {
+        let clone = Self(clone);
+        unsafe {
+            ptr::copy_nonoverlapping(
+                self.bytes_pointer().as_ptr(),
+                clone.bytes_pointer().as_ptr(),
+                self.len(),
+            );
+        };
+    }
+
+    fn drop_children(self, _heap: &mut Heap) {}
+
+    fn deallocate_external_stuff(self) {}
+}
@@ -0,0 +1,68 @@
+use super::{utils::heap_object_impls, HeapObjectTrait};
+use crate::{
+    heap::{object_heap::HeapObject, object_inline::float::InlineFloat, Heap},
+    utils::{impl_debug_display_via_debugdisplay, impl_eq_hash_ord_via_get, DebugDisplay},
+};
+use candy_frontend::format::format_float;
+use derive_more::Deref;
+use ordered_float::OrderedFloat;
+use rustc_hash::FxHashMap;
+use std::fmt::{self, Formatter};
+
+#[derive(Clone, Copy, Deref)]
+pub struct HeapFloat(HeapObject);
+
+impl HeapFloat {
+    #[must_use]
+    pub const fn new_unchecked(object: HeapObject) -> Self {
+        Self(object)
+    }
+    #[must_use]
+    pub fn create(heap: &mut Heap, is_reference_counted: bool, value: f64) -> Self {
+        debug_assert!(!InlineFloat::fits(value));
+
+        let float = Self(heap.allocate(
+            HeapObject::KIND_FLOAT,
+            is_reference_counted,
+            0,
+            HeapObject::WORD_SIZE,
+        ));
+        float.unsafe_set_content_word(0, value.to_bits());
+        float
+    }
+
+    #[must_use]
+    pub fn get(self) -> OrderedFloat<f64> {
+        OrderedFloat(f64::from_bits(self.unsafe_get_content_word(0)))
+    }
+}
+
+impl DebugDisplay for HeapFloat {
+    fn fmt(&self, f: &mut Formatter, _is_debug: bool) -> fmt::Result {
+        write!(f, "{}", format_float(*self.get()))
+    }
+}
+impl_debug_display_via_debugdisplay!(HeapFloat);
+
+impl_eq_hash_ord_via_get!(HeapFloat);
+
+heap_object_impls!(HeapFloat);
+
+impl HeapObjectTrait for HeapFloat {
+    fn content_size(self) -> usize {
+        HeapObject::WORD_SIZE
+    }
+
+    fn clone_content_to_heap_with_mapping(
+        self,
+        _heap: &mut Heap,
+        clone: HeapObject,
+        _address_map: &mut FxHashMap<HeapObject, HeapObject>,
+    ) {
+        clone.unsafe_set_content_word(0, self.unsafe_get_content_word(0));
+    }
+
+    fn drop_children(self, _heap: &mut Heap) {}
+
+    fn deallocate_external_stuff(self) {}
+}
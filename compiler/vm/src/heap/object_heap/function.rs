@@ -1,6 +1,6 @@
 use super::{utils::heap_object_impls, HeapObjectTrait};
 use crate::{
-    heap::{object_heap::HeapObject, Heap, InlineObject},
+    heap::{deep, object_heap::HeapObject, Heap, InlineObject},
     instruction_pointer::InstructionPointer,
     utils::{impl_debug_display_via_debugdisplay, DebugDisplay},
 };
@@ -134,18 +134,13 @@ impl_debug_display_via_debugdisplay!(HeapFunction);
 impl Eq for HeapFunction {}
 impl PartialEq for HeapFunction {
     fn eq(&self, other: &Self) -> bool {
-        // TODO: Compare the underlying HIR ID once we have it here (plus captured stuff)
-        self.captured() == other.captured()
-            && self.argument_count() == other.argument_count()
-            && self.body() == other.body()
+        deep::equals(self.0.into(), other.0.into())
     }
 }
 
 impl Hash for HeapFunction {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.captured().hash(state);
-        self.argument_count().hash(state);
-        self.body().hash(state);
+        deep::hash(self.0.into(), state);
     }
 }
 
@@ -1,6 +1,6 @@
 use super::{utils::heap_object_impls, HeapObjectTrait};
 use crate::{
-    heap::{object_heap::HeapObject, Heap, InlineObject},
+    heap::{deep, object_heap::HeapObject, Heap, InlineObject},
     utils::{impl_debug_display_via_debugdisplay, DebugDisplay},
 };
 use derive_more::Deref;
@@ -10,6 +10,7 @@ use std::{
     fmt::{self, Formatter},
     hash::{Hash, Hasher},
     num::NonZeroU64,
+    ops::Range,
     ptr::{self, NonNull},
     slice,
 };
@@ -20,6 +21,10 @@ pub struct HeapList(HeapObject);
 impl HeapList {
     const LEN_SHIFT: usize = 4;
 
+    /// The maximum length of a list. Longer lists couldn't be stored in the
+    /// header word and their items would exceed the maximum allocation size.
+    pub const MAX_LEN: usize = (isize::MAX as usize) >> Self::LEN_SHIFT;
+
     #[must_use]
     pub const fn new_unchecked(object: HeapObject) -> Self {
         Self(object)
@@ -110,6 +115,27 @@ impl HeapList {
         new_list
     }
     #[must_use]
+    pub fn concatenate(self, heap: &mut Heap, other: Self) -> Self {
+        let new_list = Self::create_uninitialized(heap, true, self.len() + other.len());
+        unsafe {
+            ptr::copy_nonoverlapping(
+                self.content_word_pointer(0).as_ptr(),
+                new_list.content_word_pointer(0).as_ptr(),
+                self.len(),
+            );
+            ptr::copy_nonoverlapping(
+                other.content_word_pointer(0).as_ptr(),
+                new_list.content_word_pointer(self.len()).as_ptr(),
+                other.len(),
+            );
+        }
+        new_list
+    }
+    #[must_use]
+    pub fn slice(self, heap: &mut Heap, range: Range<usize>) -> Self {
+        Self::create(heap, true, &self.items()[range])
+    }
+    #[must_use]
     pub fn replace(self, heap: &mut Heap, index: usize, value: InlineObject) -> Self {
         assert!(index < self.len());
 
@@ -140,19 +166,19 @@ impl_debug_display_via_debugdisplay!(HeapList);
 impl Eq for HeapList {}
 impl PartialEq for HeapList {
     fn eq(&self, other: &Self) -> bool {
-        self.items() == other.items()
+        deep::equals(self.0.into(), other.0.into())
     }
 }
 
 impl Hash for HeapList {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.items().hash(state);
+        deep::hash(self.0.into(), state);
     }
 }
 
 impl Ord for HeapList {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.items().cmp(other.items())
+        deep::compare(self.0.into(), other.0.into())
     }
 }
 impl PartialOrd for HeapList {
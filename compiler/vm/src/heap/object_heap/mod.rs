@@ -1,6 +1,6 @@
 use self::{
-    function::HeapFunction, hir_id::HeapHirId, int::HeapInt, list::HeapList, struct_::HeapStruct,
-    tag::HeapTag, text::HeapText,
+    bytes::HeapBytes, float::HeapFloat, function::HeapFunction, hir_id::HeapHirId, int::HeapInt,
+    list::HeapList, struct_::HeapStruct, tag::HeapTag, text::HeapText,
 };
 use super::{Data, Heap};
 use crate::{
@@ -20,6 +20,8 @@ use std::{
 };
 use tracing::debug;
 
+pub(super) mod bytes;
+pub(super) mod float;
 pub(super) mod function;
 pub(super) mod hir_id;
 pub(super) mod int;
@@ -61,6 +63,12 @@ impl HeapObject {
     const KIND_LIST: u64 = 0b100;
     const KIND_STRUCT: u64 = 0b101;
     const KIND_HIR_ID: u64 = 0b110;
+    const KIND_FLOAT: u64 = 0b111;
+
+    // Texts and bytes share a kind and are distinguished by the bit following
+    // the reference count flag.
+    const TEXT_IS_BYTES_SHIFT: usize = 4;
+    const TEXT_IS_BYTES_MASK: u64 = 0b1 << Self::TEXT_IS_BYTES_SHIFT;
 
     pub const IS_REFERENCE_COUNTED_SHIFT: usize = 3;
     pub const IS_REFERENCE_COUNTED_MASK: u64 = 0b1 << Self::IS_REFERENCE_COUNTED_SHIFT;
@@ -277,9 +285,11 @@ pub trait HeapObjectTrait: Copy + Into<HeapObject> {
 #[enum_dispatch(HeapObjectTrait)]
 pub enum HeapData {
     Int(HeapInt),
+    Float(HeapFloat),
     List(HeapList),
     Struct(HeapStruct),
     Text(HeapText),
+    Bytes(HeapBytes),
     Tag(HeapTag),
     Function(HeapFunction),
     HirId(HeapHirId),
@@ -289,9 +299,11 @@ impl DebugDisplay for HeapData {
     fn fmt(&self, f: &mut Formatter, is_debug: bool) -> fmt::Result {
         match self {
             Self::Int(int) => DebugDisplay::fmt(int, f, is_debug),
+            Self::Float(float) => DebugDisplay::fmt(float, f, is_debug),
             Self::List(list) => DebugDisplay::fmt(list, f, is_debug),
             Self::Struct(struct_) => DebugDisplay::fmt(struct_, f, is_debug),
             Self::Text(text) => DebugDisplay::fmt(text, f, is_debug),
+            Self::Bytes(bytes) => DebugDisplay::fmt(bytes, f, is_debug),
             Self::Tag(tag) => DebugDisplay::fmt(tag, f, is_debug),
             Self::Function(function) => DebugDisplay::fmt(function, f, is_debug),
             Self::HirId(hir_id) => DebugDisplay::fmt(hir_id, f, is_debug),
@@ -315,7 +327,13 @@ impl From<HeapObject> for HeapData {
             HeapObject::KIND_LIST => Self::List(HeapList::new_unchecked(object)),
             HeapObject::KIND_STRUCT => Self::Struct(HeapStruct::new_unchecked(object)),
             HeapObject::KIND_TAG => Self::Tag(HeapTag::new_unchecked(object)),
-            HeapObject::KIND_TEXT => Self::Text(HeapText::new_unchecked(object)),
+            HeapObject::KIND_TEXT => {
+                if header_word & HeapObject::TEXT_IS_BYTES_MASK == 0 {
+                    Self::Text(HeapText::new_unchecked(object))
+                } else {
+                    Self::Bytes(HeapBytes::new_unchecked(object))
+                }
+            }
             HeapObject::KIND_FUNCTION => Self::Function(HeapFunction::new_unchecked(object)),
             HeapObject::KIND_HIR_ID => {
                 assert_eq!(
@@ -324,6 +342,13 @@ impl From<HeapObject> for HeapData {
                 );
                 Self::HirId(HeapHirId::new_unchecked(object))
             }
+            HeapObject::KIND_FLOAT => {
+                assert_eq!(
+                    header_word & !HeapObject::IS_REFERENCE_COUNTED_MASK,
+                    HeapObject::KIND_FLOAT,
+                );
+                Self::Float(HeapFloat::new_unchecked(object))
+            }
             tag => panic!("Invalid tag: {tag:b}"),
         }
     }
@@ -334,9 +359,11 @@ impl Deref for HeapData {
     fn deref(&self) -> &Self::Target {
         match &self {
             Self::Int(int) => int,
+            Self::Float(float) => float,
             Self::List(list) => list,
             Self::Struct(struct_) => struct_,
             Self::Text(text) => text,
+            Self::Bytes(bytes) => bytes,
             Self::Tag(tag) => tag,
             Self::Function(function) => function,
             Self::HirId(hir_id) => hir_id,
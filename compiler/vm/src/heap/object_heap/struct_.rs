@@ -1,11 +1,11 @@
 use super::{utils::heap_object_impls, HeapObjectTrait};
 use crate::{
-    heap::{object_heap::HeapObject, Heap, InlineObject},
+    heap::{deep, object_heap::HeapObject, Heap, InlineObject, List, Struct},
     utils::{impl_debug_display_via_debugdisplay, DebugDisplay},
 };
 use candy_frontend::utils::DoHash;
 use derive_more::Deref;
-use itertools::{izip, Itertools};
+use itertools::{izip, Either, Itertools};
 use rustc_hash::FxHashMap;
 use std::{
     cmp::Ordering,
@@ -14,11 +14,35 @@ use std::{
     ptr, slice,
 };
 
+/// A struct is either flat, storing its fields sorted by the hashes of their
+/// keys, or a hash array mapped trie (HAMT) if it has many fields.
+///
+/// Each trie node stores some fields directly and the remaining ones in nested
+/// nodes, using five bits of the hashes per level, starting with the most
+/// significant ones. Hence, iterating over a trie also visits the fields sorted
+/// by their hashes. Fields whose hashes only differ in the lowest bits are
+/// stored in flat structs at the bottom of the trie.
+///
+/// Inserting into or removing from a trie only copies the nodes on the path to
+/// the field and shares all other nodes with the original struct.
 #[derive(Clone, Copy, Deref)]
 pub struct HeapStruct(HeapObject);
 
+type Field = (u64, InlineObject, InlineObject);
+
 impl HeapStruct {
-    const LEN_SHIFT: usize = 4;
+    const IS_TRIE_SHIFT: usize = 4;
+    const IS_TRIE_MASK: u64 = 0b1 << Self::IS_TRIE_SHIFT;
+    const LEN_SHIFT: usize = 5;
+
+    /// Fields are sorted by the hashes of their keys. Up to this number of
+    /// fields, scanning the hashes linearly is faster than a binary search.
+    const LINEAR_SEARCH_MAX_LEN: usize = 8;
+    /// Structs with more fields are stored as a trie.
+    const FLAT_MAX_LEN: usize = 32;
+    const TRIE_BITS_PER_LEVEL: usize = 5;
+    /// Trie nodes at this depth would run out of hash bits, so they are flat.
+    const TRIE_MAX_DEPTH: usize = 64 / Self::TRIE_BITS_PER_LEVEL;
 
     #[must_use]
     pub const fn new_unchecked(object: HeapObject) -> Self {
@@ -30,26 +54,31 @@ impl HeapStruct {
         is_reference_counted: bool,
         value: &FxHashMap<InlineObject, InlineObject>,
     ) -> Self {
-        let len = value.len();
-        assert_eq!(
-            (len << Self::LEN_SHIFT) >> Self::LEN_SHIFT,
-            len,
-            "Struct is too long.",
-        );
-        let entries = value
+        let fields = value
             .iter()
             // PERF: Reuse hashes from the map.
             .map(|(&key, &value)| (key.do_hash(), key, value))
-            .sorted_by_key(|(hash, _, _)| *hash);
+            .sorted_by_key(|(hash, _, _)| *hash)
+            .collect_vec();
+        if fields.len() <= Self::FLAT_MAX_LEN {
+            Self::create_flat(heap, is_reference_counted, &fields)
+        } else {
+            Self::create_trie(heap, is_reference_counted, 0, &fields)
+        }
+    }
+    /// The fields must be sorted by their hashes.
+    #[must_use]
+    fn create_flat(heap: &mut Heap, is_reference_counted: bool, fields: &[Field]) -> Self {
+        let len = fields.len();
         let struct_ = Self::create_uninitialized(heap, is_reference_counted, len);
         unsafe {
-            for (index, (hash, key, value)) in entries.enumerate() {
-                *struct_.content_word_pointer(index).as_ptr() = hash;
-                *struct_.content_word_pointer(index + len).cast().as_ptr() = key;
+            for (index, (hash, key, value)) in fields.iter().enumerate() {
+                *struct_.content_word_pointer(index).as_ptr() = *hash;
+                *struct_.content_word_pointer(index + len).cast().as_ptr() = *key;
                 *struct_
                     .content_word_pointer(index + 2 * len)
                     .cast()
-                    .as_ptr() = value;
+                    .as_ptr() = *value;
             }
         };
         struct_
@@ -68,57 +97,298 @@ impl HeapStruct {
             3 * len * HeapObject::WORD_SIZE,
         ))
     }
+    /// Creates a trie node containing the fields, which must be sorted by their
+    /// hashes.
+    #[must_use]
+    fn create_trie(
+        heap: &mut Heap,
+        is_reference_counted: bool,
+        depth: usize,
+        fields: &[Field],
+    ) -> Self {
+        if depth == Self::TRIE_MAX_DEPTH {
+            return Self::create_flat(heap, is_reference_counted, fields);
+        }
+
+        let mut data_map = 0;
+        let mut data = vec![];
+        let mut node_map = 0;
+        let mut nodes = vec![];
+        for (chunk, group) in &fields
+            .iter()
+            .group_by(|(hash, _, _)| Self::hash_chunk(*hash, depth))
+        {
+            let group = group.copied().collect_vec();
+            if let [field] = group.as_slice() {
+                data_map |= 1 << chunk;
+                data.push(*field);
+            } else {
+                node_map |= 1 << chunk;
+                let node = Self::create_trie(heap, is_reference_counted, depth + 1, &group);
+                nodes.push(node.into_inline_object());
+            }
+        }
+        Self::create_trie_node(
+            heap,
+            is_reference_counted,
+            fields.len(),
+            (data_map, &data),
+            (node_map, &nodes),
+        )
+    }
+    /// `len` is the number of fields in the whole subtrie.
+    #[must_use]
+    fn create_trie_node(
+        heap: &mut Heap,
+        is_reference_counted: bool,
+        len: usize,
+        (data_map, data): (u32, &[Field]),
+        (node_map, nodes): (u32, &[InlineObject]),
+    ) -> Self {
+        debug_assert_eq!(data_map.count_ones() as usize, data.len());
+        debug_assert_eq!(node_map.count_ones() as usize, nodes.len());
+        debug_assert_eq!(data_map & node_map, 0);
+        assert_eq!(
+            (len << Self::LEN_SHIFT) >> Self::LEN_SHIFT,
+            len,
+            "Struct is too long.",
+        );
+        let node = Self(heap.allocate(
+            HeapObject::KIND_STRUCT,
+            is_reference_counted,
+            Self::IS_TRIE_MASK | (len as u64) << Self::LEN_SHIFT,
+            (1 + 3 * data.len() + nodes.len()) * HeapObject::WORD_SIZE,
+        ));
+        node.unsafe_set_content_word(0, u64::from(data_map) | u64::from(node_map) << 32);
+        let data_len = data.len();
+        for (index, (hash, key, value)) in data.iter().enumerate() {
+            node.unsafe_set_content_word(1 + index, *hash);
+            node.unsafe_set_content_word(1 + data_len + index, key.raw_word().get());
+            node.unsafe_set_content_word(1 + 2 * data_len + index, value.raw_word().get());
+        }
+        for (index, child) in nodes.iter().enumerate() {
+            node.unsafe_set_content_word(1 + 3 * data_len + index, child.raw_word().get());
+        }
+        node
+    }
 
     #[must_use]
     pub fn len(self) -> usize {
         (self.header_word() >> Self::LEN_SHIFT) as usize
     }
     #[must_use]
+    pub fn is_trie(self) -> bool {
+        self.header_word() & Self::IS_TRIE_MASK != 0
+    }
+    /// The bitmaps of the fields stored directly in a trie node and of its
+    /// nested nodes.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    fn bitmaps(self) -> (u32, u32) {
+        debug_assert!(self.is_trie());
+        let word = self.unsafe_get_content_word(0);
+        (word as u32, (word >> 32) as u32)
+    }
+    /// The number of fields stored directly in this object.
+    #[must_use]
+    fn local_len(self) -> usize {
+        if self.is_trie() {
+            self.bitmaps().0.count_ones() as usize
+        } else {
+            self.len()
+        }
+    }
+    /// The hashes of the fields stored directly in this object.
+    ///
+    /// For tries, that's only some of the fields. Use [`Self::iter`] to visit
+    /// all of them.
+    #[must_use]
     pub fn hashes<'a>(self) -> &'a [u64] {
         self.items(0)
     }
+    /// The keys of the fields stored directly in this object.
     #[must_use]
     pub fn keys<'a>(self) -> &'a [InlineObject] {
         self.items(1)
     }
+    /// The values of the fields stored directly in this object.
     #[must_use]
     pub fn values<'a>(self) -> &'a [InlineObject] {
         self.items(2)
     }
-    pub fn iter<'a>(self) -> impl Iterator<Item = (u64, InlineObject, InlineObject)> + 'a {
-        izip!(
-            self.hashes().iter().copied(),
-            self.keys().iter().copied(),
-            self.values().iter().copied(),
-        )
-    }
     #[must_use]
     fn items<'a, T>(self, items_index: usize) -> &'a [T] {
-        let len = self.len();
+        let offset = usize::from(self.is_trie());
+        let len = self.local_len();
         unsafe {
             slice::from_raw_parts(
-                self.content_word_pointer(items_index * len).cast().as_ptr(),
+                self.content_word_pointer(offset + items_index * len)
+                    .cast()
+                    .as_ptr(),
                 len,
             )
         }
     }
+    /// The nested nodes of a trie.
+    #[must_use]
+    pub fn nodes<'a>(self) -> &'a [InlineObject] {
+        if !self.is_trie() {
+            return &[];
+        }
+        let (data_map, node_map) = self.bitmaps();
+        unsafe {
+            slice::from_raw_parts(
+                self.content_word_pointer(1 + 3 * data_map.count_ones() as usize)
+                    .cast()
+                    .as_ptr(),
+                node_map.count_ones() as usize,
+            )
+        }
+    }
+    #[must_use]
+    fn node(self, index: usize) -> Self {
+        *Struct::try_from(self.nodes()[index]).unwrap()
+    }
+    fn into_inline_object(self) -> InlineObject {
+        Struct::from(self).into()
+    }
+    fn local_fields<'a>(self) -> impl Iterator<Item = Field> + 'a {
+        izip!(
+            self.hashes().iter().copied(),
+            self.keys().iter().copied(),
+            self.values().iter().copied(),
+        )
+    }
+    /// All fields, sorted by the hashes of their keys.
+    pub fn iter<'a>(self) -> impl Iterator<Item = Field> + 'a {
+        if !self.is_trie() {
+            return Either::Left(self.local_fields());
+        }
+
+        let mut fields = Vec::with_capacity(self.len());
+        self.collect_fields(&mut fields);
+        Either::Right(fields.into_iter())
+    }
+    fn collect_fields(self, fields: &mut Vec<Field>) {
+        if !self.is_trie() {
+            fields.extend(self.local_fields());
+            return;
+        }
+
+        let (data_map, node_map) = self.bitmaps();
+        let mut local_fields = self.local_fields();
+        let mut nodes = self.nodes().iter();
+        for chunk in 0..u32::BITS {
+            let bit = 1 << chunk;
+            if data_map & bit != 0 {
+                fields.push(local_fields.next().unwrap());
+            } else if node_map & bit != 0 {
+                (*Struct::try_from(*nodes.next().unwrap()).unwrap()).collect_fields(fields);
+            }
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    const fn hash_chunk(hash: u64, depth: usize) -> u32 {
+        let shift = 64 - Self::TRIE_BITS_PER_LEVEL * (depth + 1);
+        ((hash >> shift) & ((1 << Self::TRIE_BITS_PER_LEVEL) - 1)) as u32
+    }
+    /// The index of the `bit` among the set bits of the `bitmap`.
+    const fn index_in_bitmap(bitmap: u32, bit: u32) -> usize {
+        (bitmap & (bit - 1)).count_ones() as usize
+    }
 
     #[must_use]
     pub fn contains(self, key: InlineObject) -> bool {
-        self.index_of_key(key, key.do_hash()).is_ok()
+        self.find(key, key.do_hash()).is_some()
+    }
+    /// The index of the key's field in [`Self::keys`] and [`Self::values`].
+    ///
+    /// Tries don't store all fields there, so this always returns `None` for
+    /// them.
+    #[must_use]
+    pub fn index_of(self, key: InlineObject) -> Option<usize> {
+        if self.is_trie() {
+            return None;
+        }
+        self.index_of_key(key, key.do_hash()).ok()
     }
     #[must_use]
     pub fn get(self, key: impl Into<InlineObject>) -> Option<InlineObject> {
         let key = key.into();
-        self.index_of_key(key, key.do_hash())
+        self.find(key, key.do_hash())
+    }
+    fn find(self, key: InlineObject, hash: u64) -> Option<InlineObject> {
+        let mut node = self;
+        let mut depth = 0;
+        while node.is_trie() {
+            let (data_map, node_map) = node.bitmaps();
+            let bit = 1 << Self::hash_chunk(hash, depth);
+            if data_map & bit != 0 {
+                let index = Self::index_in_bitmap(data_map, bit);
+                return (node.hashes()[index] == hash && node.keys()[index] == key)
+                    .then(|| node.values()[index]);
+            }
+            if node_map & bit == 0 {
+                return None;
+            }
+            node = node.node(Self::index_in_bitmap(node_map, bit));
+            depth += 1;
+        }
+        node.index_of_key(key, hash)
             .ok()
-            .map(|index| self.values()[index])
+            .map(|index| node.values()[index])
     }
+
+    /// The new struct holds its own references to all of its fields.
     #[must_use]
     pub fn insert(self, heap: &mut Heap, key: InlineObject, value: InlineObject) -> Self {
         let hash = key.do_hash();
-        match self.index_of_key(key, hash) {
-            Ok(index) => self.replace_at_index(heap, index, value),
+        if self.is_trie() {
+            return self.insert_into_trie(heap, 0, (hash, key, value)).0;
+        }
+
+        let struct_ = match self.index_of_key(key, hash) {
+            Err(_) if self.len() == Self::FLAT_MAX_LEN => {
+                let fields = self
+                    .local_fields()
+                    .chain([(hash, key, value)])
+                    .sorted_by_key(|(hash, _, _)| *hash)
+                    .collect_vec();
+                Self::create_trie(heap, true, 0, &fields)
+            }
+            index => self.insert_into_flat(heap, index, (hash, key, value)),
+        };
+        struct_.dup_fields(heap);
+        struct_
+    }
+    /// Doesn't increase any reference counts.
+    #[must_use]
+    fn insert_into_flat(
+        self,
+        heap: &mut Heap,
+        index: Result<usize, usize>,
+        (hash, key, value): Field,
+    ) -> Self {
+        match index {
+            Ok(index) => {
+                let struct_ = Self::create_uninitialized(heap, true, self.len());
+                unsafe {
+                    ptr::copy_nonoverlapping(
+                        self.content_word_pointer(0).as_ptr(),
+                        struct_.content_word_pointer(0).as_ptr(),
+                        3 * self.len(),
+                    );
+                    ptr::write(
+                        struct_
+                            .content_word_pointer(2 * self.len() + index)
+                            .cast()
+                            .as_ptr(),
+                        value,
+                    );
+                }
+                struct_
+            }
             Err(index) => {
                 let struct_ = Self::create_uninitialized(heap, true, self.len() + 1);
                 // PERF: Merge consecutive copies.
@@ -129,27 +399,199 @@ impl HeapStruct {
             }
         }
     }
+    /// Returns the new node and whether it contains one more field than this
+    /// one.
+    ///
+    /// Like all nodes created when changing a trie, the new node holds its own
+    /// references to its fields and nested nodes.
     #[must_use]
-    pub fn replace_at_index(self, heap: &mut Heap, index: usize, value: InlineObject) -> Self {
-        assert!(index < self.len());
+    fn insert_into_trie(self, heap: &mut Heap, depth: usize, field: Field) -> (Self, bool) {
+        let (hash, key, _) = field;
+        if !self.is_trie() {
+            let index = self.index_of_key(key, hash);
+            let node = self.insert_into_flat(heap, index, field);
+            node.dup_fields(heap);
+            return (node, index.is_err());
+        }
 
-        let struct_ = Self::create_uninitialized(heap, true, self.len());
-        unsafe {
-            ptr::copy_nonoverlapping(
-                self.content_word_pointer(0).as_ptr(),
-                struct_.content_word_pointer(0).as_ptr(),
-                3 * self.len(),
+        let (mut data_map, mut node_map) = self.bitmaps();
+        let mut data = self.local_fields().collect_vec();
+        let mut nodes = self.nodes().to_vec();
+        let bit = 1 << Self::hash_chunk(hash, depth);
+        let (new_node_index, is_new) = if data_map & bit != 0 {
+            let index = Self::index_in_bitmap(data_map, bit);
+            let (existing_hash, existing_key, _) = data[index];
+            if existing_hash == hash && existing_key == key {
+                data[index] = field;
+                (None, false)
+            } else {
+                let existing_field = data.remove(index);
+                data_map ^= bit;
+                let fields = [existing_field, field]
+                    .into_iter()
+                    .sorted_by_key(|(hash, _, _)| *hash)
+                    .collect_vec();
+                let node = Self::create_trie(heap, true, depth + 1, &fields);
+                node.dup_fields(heap);
+                node_map |= bit;
+                let index = Self::index_in_bitmap(node_map, bit);
+                nodes.insert(index, node.into_inline_object());
+                (Some(index), true)
+            }
+        } else if node_map & bit != 0 {
+            let index = Self::index_in_bitmap(node_map, bit);
+            let (node, is_new) = self.node(index).insert_into_trie(heap, depth + 1, field);
+            nodes[index] = node.into_inline_object();
+            (Some(index), is_new)
+        } else {
+            data_map |= bit;
+            data.insert(Self::index_in_bitmap(data_map, bit), field);
+            (None, true)
+        };
+
+        let node = Self::create_trie_node(
+            heap,
+            true,
+            self.len() + usize::from(is_new),
+            (data_map, &data),
+            (node_map, &nodes),
+        );
+        node.dup_local_references(heap, new_node_index);
+        (node, is_new)
+    }
+
+    /// The new struct holds its own references to all of its fields.
+    #[must_use]
+    pub fn remove(self, heap: &mut Heap, key: InlineObject) -> Self {
+        let hash = key.do_hash();
+        if self.is_trie() && self.len() > Self::FLAT_MAX_LEN + 1 {
+            return self.remove_from_trie(heap, 0, hash, key);
+        }
+
+        let struct_ = if self.is_trie() {
+            let fields = self
+                .iter()
+                .filter(|(existing_hash, existing_key, _)| {
+                    *existing_hash != hash || *existing_key != key
+                })
+                .collect_vec();
+            assert_eq!(
+                fields.len(),
+                self.len() - 1,
+                "Tried to remove a key that's not in the struct.",
             );
-            ptr::write(
-                struct_
-                    .content_word_pointer(2 * self.len() + index)
-                    .cast()
-                    .as_ptr(),
-                value,
+            Self::create_flat(heap, true, &fields)
+        } else {
+            self.remove_from_flat(heap, hash, key)
+        };
+        struct_.dup_fields(heap);
+        struct_
+    }
+    /// Doesn't increase any reference counts.
+    #[must_use]
+    fn remove_from_flat(self, heap: &mut Heap, hash: u64, key: InlineObject) -> Self {
+        let index = self
+            .index_of_key(key, hash)
+            .expect("Tried to remove a key that's not in the struct.");
+
+        let struct_ = Self::create_uninitialized(heap, true, self.len() - 1);
+        self.remove_from_items(struct_, 0, index);
+        self.remove_from_items(struct_, 1, index);
+        self.remove_from_items(struct_, 2, index);
+        struct_
+    }
+    /// Like all nodes created when changing a trie, the new node holds its own
+    /// references to its fields and nested nodes.
+    #[must_use]
+    fn remove_from_trie(self, heap: &mut Heap, depth: usize, hash: u64, key: InlineObject) -> Self {
+        if !self.is_trie() {
+            let node = self.remove_from_flat(heap, hash, key);
+            node.dup_fields(heap);
+            return node;
+        }
+
+        let (mut data_map, mut node_map) = self.bitmaps();
+        let mut data = self.local_fields().collect_vec();
+        let mut nodes = self.nodes().to_vec();
+        let bit = 1 << Self::hash_chunk(hash, depth);
+        let mut new_node_index = None;
+        let mut inlined_node = None;
+        if data_map & bit != 0 {
+            let index = Self::index_in_bitmap(data_map, bit);
+            let (existing_hash, existing_key, _) = data[index];
+            assert!(
+                existing_hash == hash && existing_key == key,
+                "Tried to remove a key that's not in the struct.",
             );
+            data.remove(index);
+            data_map ^= bit;
+        } else {
+            assert!(
+                node_map & bit != 0,
+                "Tried to remove a key that's not in the struct.",
+            );
+            let index = Self::index_in_bitmap(node_map, bit);
+            let node = self
+                .node(index)
+                .remove_from_trie(heap, depth + 1, hash, key);
+            if node.len() == 1 {
+                // Nodes containing a single field are inlined into their
+                // parent, just like when creating the trie from scratch.
+                nodes.remove(index);
+                node_map ^= bit;
+                data_map |= bit;
+                data.insert(
+                    Self::index_in_bitmap(data_map, bit),
+                    node.iter().next().unwrap(),
+                );
+                inlined_node = Some(node);
+            } else {
+                nodes[index] = node.into_inline_object();
+                new_node_index = Some(index);
+            }
+        }
+
+        let node = Self::create_trie_node(
+            heap,
+            true,
+            self.len() - 1,
+            (data_map, &data),
+            (node_map, &nodes),
+        );
+        node.dup_local_references(heap, new_node_index);
+        if let Some(inlined_node) = inlined_node {
+            inlined_node.into_inline_object().drop(heap);
         }
+        node
+    }
+
+    /// Fields of `other` take precedence over fields of `self` with the same
+    /// key. The new struct holds its own references to all of its fields.
+    #[must_use]
+    pub fn merge(self, heap: &mut Heap, other: Self) -> Self {
+        let mut fields: FxHashMap<_, _> = self.iter().map(|(_, key, value)| (key, value)).collect();
+        for (_, key, value) in other.iter() {
+            fields.insert(key, value);
+        }
+        let struct_ = Self::create(heap, true, &fields);
+        struct_.dup_fields(heap);
         struct_
     }
+    /// Returns a list of `(key, value)` lists, sorted by key so that the order
+    /// doesn't depend on the keys' hashes.
+    #[must_use]
+    pub fn entries(self, heap: &mut Heap) -> List {
+        let entries = self
+            .iter()
+            .sorted_by_key(|(_, key, _)| *key)
+            .map(|(_, key, value)| {
+                key.dup(heap);
+                value.dup(heap);
+                List::create(heap, true, &[key, value]).into()
+            })
+            .collect_vec();
+        List::create(heap, true, &entries)
+    }
     fn insert_into_items<T>(self, other: Self, items_index: usize, index: usize, item: T) {
         let self_base = items_index * self.len();
         let other_base = items_index * other.len();
@@ -171,13 +613,65 @@ impl HeapStruct {
         }
     }
 
+    fn remove_from_items(self, other: Self, items_index: usize, index: usize) {
+        let self_base = items_index * self.len();
+        let other_base = items_index * other.len();
+        unsafe {
+            ptr::copy_nonoverlapping(
+                self.content_word_pointer(self_base).as_ptr(),
+                other.content_word_pointer(other_base).as_ptr(),
+                index,
+            );
+            ptr::copy_nonoverlapping(
+                self.content_word_pointer(self_base + index + 1).as_ptr(),
+                other.content_word_pointer(other_base + index).as_ptr(),
+                self.len() - index - 1,
+            );
+        }
+    }
+
+    /// Increases the reference counts of all keys and values, e.g., after
+    /// they have been copied from another struct.
+    ///
+    /// For tries, this includes the fields in nested nodes, so the nodes
+    /// themselves must be new.
+    fn dup_fields(self, heap: &mut Heap) {
+        for (_, key, value) in self.iter() {
+            key.dup(heap);
+            value.dup(heap);
+        }
+    }
+    /// Increases the reference counts of the fields and nodes a trie node
+    /// references directly, except for the new nested node at the given
+    /// index.
+    fn dup_local_references(self, heap: &mut Heap, new_node_index: Option<usize>) {
+        for (_, key, value) in self.local_fields() {
+            key.dup(heap);
+            value.dup(heap);
+        }
+        for (index, node) in self.nodes().iter().enumerate() {
+            if Some(index) != new_node_index {
+                node.dup(heap);
+            }
+        }
+    }
+
     /// If the struct contains the key, returns the index of its field.
     /// Otherwise, returns the index of where the key would be inserted.
+    ///
+    /// Only works for flat structs.
     fn index_of_key(self, key: InlineObject, key_hash: u64) -> Result<usize, usize> {
+        debug_assert!(!self.is_trie());
         let hashes = self.hashes();
         let keys = self.keys();
-        let index_of_first_hash_occurrence =
-            hashes.partition_point(|existing_hash| *existing_hash < key_hash);
+        let index_of_first_hash_occurrence = if hashes.len() <= Self::LINEAR_SEARCH_MAX_LEN {
+            hashes
+                .iter()
+                .position(|existing_hash| *existing_hash >= key_hash)
+                .unwrap_or(hashes.len())
+        } else {
+            hashes.partition_point(|existing_hash| *existing_hash < key_hash)
+        };
         hashes[index_of_first_hash_occurrence..]
             .iter()
             .enumerate()
@@ -192,22 +686,19 @@ impl HeapStruct {
 
 impl DebugDisplay for HeapStruct {
     fn fmt(&self, f: &mut Formatter, is_debug: bool) -> fmt::Result {
-        let keys = self.keys();
-        if keys.is_empty() {
+        if self.len() == 0 {
             return write!(f, "[]");
         }
-        let values = self.values();
         if is_debug {
-            let hashes = self.hashes();
             write!(
                 f,
                 "[{}]",
-                izip!(hashes, keys, values)
+                self.iter()
                     .map(|(hash, key, value)| {
                         (
                             format!("{hash:016X}"),
-                            DebugDisplay::to_string(key, is_debug),
-                            DebugDisplay::to_string(value, is_debug),
+                            DebugDisplay::to_string(&key, is_debug),
+                            DebugDisplay::to_string(&value, is_debug),
                         )
                     })
                     .map(|(hash, key, value)| format!("{hash} → {key}: {value}"))
@@ -217,11 +708,10 @@ impl DebugDisplay for HeapStruct {
             write!(
                 f,
                 "[{}]",
-                keys.iter()
-                    .zip(values.iter())
-                    .map(|(key, value)| (
-                        DebugDisplay::to_string(key, is_debug),
-                        DebugDisplay::to_string(value, is_debug)
+                self.iter()
+                    .map(|(_, key, value)| (
+                        DebugDisplay::to_string(&key, is_debug),
+                        DebugDisplay::to_string(&value, is_debug)
                     ))
                     .sorted_by(|(key_a, _), (key_b, _)| key_a.cmp(key_b))
                     .map(|(key, value)| format!("{key}: {value}"))
@@ -235,26 +725,19 @@ impl_debug_display_via_debugdisplay!(HeapStruct);
 impl Eq for HeapStruct {}
 impl PartialEq for HeapStruct {
     fn eq(&self, other: &Self) -> bool {
-        self.hashes() == other.hashes()
-            && self.values() == other.values()
-            && self.keys() == other.keys()
+        deep::equals(self.0.into(), other.0.into())
     }
 }
 
 impl Hash for HeapStruct {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.hashes().hash(state);
-        self.values().hash(state);
+        deep::hash(self.0.into(), state);
     }
 }
 
 impl Ord for HeapStruct {
     fn cmp(&self, other: &Self) -> Ordering {
-        let mut self_keys = self.keys().to_vec();
-        self_keys.sort();
-        let mut other_keys = other.keys().to_vec();
-        other_keys.sort();
-        self_keys.cmp(&other_keys)
+        deep::compare(self.0.into(), other.0.into())
     }
 }
 impl PartialOrd for HeapStruct {
@@ -267,7 +750,11 @@ heap_object_impls!(HeapStruct);
 
 impl HeapObjectTrait for HeapStruct {
     fn content_size(self) -> usize {
-        3 * self.len() * HeapObject::WORD_SIZE
+        if self.is_trie() {
+            (1 + 3 * self.local_len() + self.nodes().len()) * HeapObject::WORD_SIZE
+        } else {
+            3 * self.len() * HeapObject::WORD_SIZE
+        }
     }
 
     fn clone_content_to_heap_with_mapping(
@@ -277,25 +764,21 @@ impl HeapObjectTrait for HeapStruct {
         address_map: &mut FxHashMap<HeapObject, HeapObject>,
     ) {
         let clone = Self(clone);
+        // Tries start with their bitmaps.
+        let offset = usize::from(self.is_trie());
+        let len = self.local_len();
         unsafe {
             ptr::copy_nonoverlapping(
                 self.content_word_pointer(0).as_ptr(),
                 clone.content_word_pointer(0).as_ptr(),
-                self.len(),
+                offset + len,
             );
         };
-        for (index, &key) in self.keys().iter().enumerate() {
-            clone.unsafe_set_content_word(
-                self.len() + index,
-                key.clone_to_heap_with_mapping(heap, address_map)
-                    .raw_word()
-                    .get(),
-            );
-        }
-        for (index, &value) in self.values().iter().enumerate() {
+        let children = self.keys().iter().chain(self.values()).chain(self.nodes());
+        for (index, &child) in children.enumerate() {
             clone.unsafe_set_content_word(
-                2 * self.len() + index,
-                value
+                offset + len + index,
+                child
                     .clone_to_heap_with_mapping(heap, address_map)
                     .raw_word()
                     .get(),
@@ -310,7 +793,82 @@ impl HeapObjectTrait for HeapStruct {
         for value in self.values() {
             value.drop(heap);
         }
+        for node in self.nodes() {
+            node.drop(heap);
+        }
     }
 
     fn deallocate_external_stuff(self) {}
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::heap::{Heap, InlineObject, Int, Struct, Text};
+    use itertools::Itertools;
+    use rustc_hash::FxHashMap;
+
+    #[test]
+    fn large_structs_are_tries_sharing_unchanged_nodes() {
+        let mut heap = Heap::default();
+        let len = 1000;
+        let fields = (0..len)
+            .map(|index| {
+                let key = Text::create(&mut heap, true, &format!("key{index}")).into();
+                let value = Int::create(&mut heap, true, index).into();
+                (key, value)
+            })
+            .collect_vec();
+
+        let mut struct_ = Struct::create(&mut heap, true, &FxHashMap::default());
+        for (key, value) in &fields {
+            let new_struct = struct_.insert(&mut heap, *key, *value);
+            if struct_.is_trie() {
+                // Only the nodes on the path to the new field are copied.
+                let shared_nodes = new_struct
+                    .nodes()
+                    .iter()
+                    .filter(|it| {
+                        struct_
+                            .nodes()
+                            .iter()
+                            .any(|old| old.raw_word() == it.raw_word())
+                    })
+                    .count();
+                assert!(shared_nodes + 1 >= struct_.nodes().len());
+            }
+            InlineObject::from(struct_).drop(&mut heap);
+            struct_ = new_struct.into();
+        }
+        assert!(struct_.is_trie());
+        assert_eq!(struct_.len(), len);
+        for (key, value) in &fields {
+            assert_eq!(struct_.get(*key), Some(*value));
+        }
+        let hashes = struct_.iter().map(|(hash, _, _)| hash).collect_vec();
+        assert!(hashes.windows(2).all(|it| it[0] <= it[1]));
+
+        let created = Struct::create(&mut heap, true, &fields.iter().copied().collect());
+        for (key, value) in &fields {
+            key.dup(&mut heap);
+            value.dup(&mut heap);
+        }
+        assert!(created == struct_);
+        InlineObject::from(created).drop(&mut heap);
+
+        for (index, (key, _)) in fields.iter().enumerate() {
+            let new_struct = struct_.remove(&mut heap, *key);
+            InlineObject::from(struct_).drop(&mut heap);
+            struct_ = new_struct.into();
+            assert_eq!(struct_.len(), len - index - 1);
+            assert!(!struct_.contains(*key));
+        }
+        assert!(!struct_.is_trie());
+
+        InlineObject::from(struct_).drop(&mut heap);
+        for (key, value) in fields {
+            key.drop(&mut heap);
+            value.drop(&mut heap);
+        }
+        assert!(heap.leak_report(None).is_empty());
+    }
+}
@@ -1,6 +1,6 @@
 use super::{utils::heap_object_impls, HeapObjectTrait};
 use crate::{
-    heap::{object_heap::HeapObject, Heap, InlineObject, Text},
+    heap::{deep, object_heap::HeapObject, Heap, InlineObject, Text},
     utils::{impl_debug_display_via_debugdisplay, DebugDisplay},
 };
 use derive_more::Deref;
@@ -79,22 +79,19 @@ impl_debug_display_via_debugdisplay!(HeapTag);
 impl Eq for HeapTag {}
 impl PartialEq for HeapTag {
     fn eq(&self, other: &Self) -> bool {
-        self.symbol() == other.symbol() && self.value() == other.value()
+        deep::equals(self.0.into(), other.0.into())
     }
 }
 
 impl Hash for HeapTag {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.symbol().hash(state);
-        self.value().hash(state);
+        deep::hash(self.0.into(), state);
     }
 }
 
 impl Ord for HeapTag {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.symbol()
-            .cmp(&other.symbol())
-            .then_with(|| self.value().cmp(&other.value()))
+        deep::compare(self.0.into(), other.0.into())
     }
 }
 impl PartialOrd for HeapTag {
@@ -1,24 +1,51 @@
-use super::{utils::heap_object_impls, HeapObjectTrait};
+use super::{utils::heap_object_impls, HeapData, HeapObjectTrait};
 use crate::{
-    heap::{object_heap::HeapObject, Heap, Int, List, Tag, Text},
-    utils::{impl_debug_display_via_debugdisplay, impl_eq_hash_ord_via_get, DebugDisplay},
+    heap::{object_heap::HeapObject, Heap, InlineObject, Int, List, Tag, Text},
+    utils::{impl_debug_display_via_debugdisplay, DebugDisplay},
 };
+use candy_frontend::utils::GraphemeAwareSearch;
 use derive_more::Deref;
 use itertools::Itertools;
 use rustc_hash::FxHashMap;
 use std::{
+    cmp::Ordering,
     fmt::{self, Formatter},
+    hash::{Hash, Hasher},
     ops::Range,
     ptr::{self, NonNull},
     slice, str,
 };
 use unicode_segmentation::UnicodeSegmentation;
 
+/// A text is either flat, storing its UTF-8 bytes inline, or a rope.
+///
+/// Ropes are created by concatenating long texts. They only reference the two
+/// concatenated texts, so building a text in a loop doesn't copy everything
+/// that was built so far in each iteration. When the actual bytes of a rope are
+/// needed, only that rope is flattened into a separate buffer that is cached
+/// until the rope is freed. Nested ropes aren't flattened on their own, but
+/// buffers that they cached earlier stay alive because code might still
+/// borrow them.
+///
+/// Texts are hashed using a polynomial hash of their bytes. The hash of a
+/// concatenation can be computed from the hashes of its parts, so ropes store
+/// their hash and hashing them doesn't flatten them.
 #[derive(Clone, Copy, Deref)]
 pub struct HeapText(HeapObject);
 
 impl HeapText {
-    const BYTE_LEN_SHIFT: usize = 4;
+    const IS_ROPE_SHIFT: usize = 5;
+    const IS_ROPE_MASK: u64 = 0b1 << Self::IS_ROPE_SHIFT;
+    const BYTE_LEN_SHIFT: usize = 6;
+
+    /// Shorter concatenations are copied right away since that's cheaper than
+    /// creating a rope and flattening it later.
+    const MIN_ROPE_BYTE_LEN: usize = 256;
+    const ROPE_CONTENT_SIZE: usize = 4 * HeapObject::WORD_SIZE;
+
+    /// The content hash is computed modulo this Mersenne prime.
+    const HASH_MODULUS: u64 = (1 << 61) - 1;
+    const HASH_BASE: u64 = 0x0100_0000_01b3;
 
     #[must_use]
     pub const fn new_unchecked(object: HeapObject) -> Self {
@@ -41,28 +68,167 @@ impl HeapText {
         unsafe { ptr::copy_nonoverlapping(value.as_ptr(), text.text_pointer().as_ptr(), byte_len) };
         text
     }
+    /// Creates a rope referencing both texts. The rope takes ownership of the
+    /// texts.
+    fn create_rope(heap: &mut Heap, left: Text, right: Text) -> Self {
+        let byte_len = left.byte_len() + right.byte_len();
+        assert_eq!(
+            (byte_len << Self::BYTE_LEN_SHIFT) >> Self::BYTE_LEN_SHIFT,
+            byte_len,
+            "Text is too long.",
+        );
+        let rope = Self(heap.allocate(
+            HeapObject::KIND_TEXT,
+            true,
+            Self::IS_ROPE_MASK | (byte_len as u64) << Self::BYTE_LEN_SHIFT,
+            Self::ROPE_CONTENT_SIZE,
+        ));
+        let content_hash = Self::add_hash_mod(
+            Self::multiply_hash_mod(left.content_hash(), Self::hash_base_power(right.byte_len())),
+            right.content_hash(),
+        );
+        unsafe {
+            *rope.rope_part_pointer(0).as_mut() = left.into();
+            *rope.rope_part_pointer(1).as_mut() = right.into();
+            *rope.flattened_pointer().as_mut() = ptr::null_mut();
+            *rope.content_hash_pointer().as_mut() = content_hash;
+        };
+        rope
+    }
 
     #[must_use]
     pub fn byte_len(self) -> usize {
         (self.header_word() >> Self::BYTE_LEN_SHIFT) as usize
     }
     #[must_use]
+    fn is_rope(self) -> bool {
+        self.header_word() & Self::IS_ROPE_MASK != 0
+    }
+    #[must_use]
     fn text_pointer(self) -> NonNull<u8> {
         self.content_word_pointer(0).cast()
     }
+    #[must_use]
+    fn rope_part_pointer(self, index: usize) -> NonNull<InlineObject> {
+        debug_assert!(self.is_rope());
+        self.content_word_pointer(index).cast()
+    }
+    /// Points to the cached bytes of a flattened rope or null if the rope
+    /// wasn't flattened yet.
+    #[must_use]
+    fn flattened_pointer(self) -> NonNull<*mut u8> {
+        debug_assert!(self.is_rope());
+        self.content_word_pointer(2).cast()
+    }
+    #[must_use]
+    fn content_hash_pointer(self) -> NonNull<u64> {
+        debug_assert!(self.is_rope());
+        self.content_word_pointer(3).cast()
+    }
+    /// The two texts a rope consists of, or `None` for flat texts.
+    #[must_use]
+    pub fn rope_parts(self) -> Option<(Text, Text)> {
+        if !self.is_rope() {
+            return None;
+        }
+        let parts = [0, 1].map(|index| {
+            let part = unsafe { *self.rope_part_pointer(index).as_ref() };
+            Text::try_from(part).unwrap()
+        });
+        Some(parts.into())
+    }
+
     #[must_use]
     pub fn get<'a>(self) -> &'a str {
-        let pointer = self.text_pointer().as_ptr();
-        unsafe { str::from_utf8_unchecked(slice::from_raw_parts(pointer, self.byte_len())) }
+        if let Some(text) = self.get_if_flat() {
+            return text;
+        }
+
+        let flattened = Box::into_raw(self.flatten().into_boxed_str());
+        unsafe { *self.flattened_pointer().as_mut() = flattened.cast() };
+        self.get_if_flat().unwrap()
+    }
+    /// Returns the bytes of flat texts and already flattened ropes.
+    #[must_use]
+    fn get_if_flat<'a>(self) -> Option<&'a str> {
+        let pointer = if self.is_rope() {
+            unsafe { *self.flattened_pointer().as_ref() }
+        } else {
+            self.text_pointer().as_ptr()
+        };
+        if pointer.is_null() {
+            return None;
+        }
+        Some(unsafe { str::from_utf8_unchecked(slice::from_raw_parts(pointer, self.byte_len())) })
+    }
+    fn flatten(self) -> String {
+        // Ropes built in a loop are deeply nested, so we don't use recursion.
+        let mut flattened = String::with_capacity(self.byte_len());
+        let mut texts = vec![self];
+        while let Some(text) = texts.pop() {
+            if let Some(flat) = text.get_if_flat() {
+                flattened.push_str(flat);
+            } else {
+                let (left, right) = text.rope_parts().unwrap();
+                texts.push(*right);
+                texts.push(*left);
+            }
+        }
+        flattened
+    }
+    fn free_flattened(self) {
+        let flattened = unsafe { *self.flattened_pointer().as_ref() };
+        if flattened.is_null() {
+            return;
+        }
+        drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(flattened, self.byte_len())) });
+        unsafe { *self.flattened_pointer().as_mut() = ptr::null_mut() };
+    }
+
+    /// A polynomial hash of all bytes, i.e., `b₀·baseⁿ⁻¹ + … + bₙ₋₁·base⁰`
+    /// with `bᵢ` being the bytes shifted by one so that zero bytes count.
+    fn content_hash(self) -> u64 {
+        if self.is_rope() {
+            return unsafe { *self.content_hash_pointer().as_ref() };
+        }
+        self.get().bytes().fold(0, |hash, byte| {
+            Self::add_hash_mod(
+                Self::multiply_hash_mod(hash, Self::HASH_BASE),
+                u64::from(byte) + 1,
+            )
+        })
+    }
+    const fn hash_base_power(mut exponent: usize) -> u64 {
+        let mut power = 1;
+        let mut base = Self::HASH_BASE;
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                power = Self::multiply_hash_mod(power, base);
+            }
+            base = Self::multiply_hash_mod(base, base);
+            exponent >>= 1;
+        }
+        power
+    }
+    const fn add_hash_mod(a: u64, b: u64) -> u64 {
+        (a + b) % Self::HASH_MODULUS
+    }
+    #[allow(clippy::cast_possible_truncation)]
+    const fn multiply_hash_mod(a: u64, b: u64) -> u64 {
+        ((a as u128 * b as u128) % Self::HASH_MODULUS as u128) as u64
     }
 
     #[must_use]
     pub fn is_empty(self, heap: &Heap) -> Tag {
-        Tag::create_bool(heap, self.get().is_empty())
+        Tag::create_bool(heap, self.byte_len() == 0)
     }
     #[must_use]
     pub fn length(self, heap: &mut Heap) -> Int {
-        Int::create(heap, true, self.get().graphemes(true).count())
+        Int::create(heap, true, self.grapheme_len())
+    }
+    #[must_use]
+    pub fn grapheme_len(self) -> usize {
+        self.get().graphemes(true).count()
     }
     #[must_use]
     pub fn characters(self, heap: &mut Heap) -> List {
@@ -86,28 +252,65 @@ impl HeapText {
         Tag::create_bool(heap, self.get().ends_with(suffix.get()))
     }
     #[must_use]
-    pub fn get_range(self, heap: &mut Heap, range: Range<Int>) -> Text {
-        // TODO: Support indices larger than usize.
-        let start_inclusive = range
-            .start
-            .try_get()
-            .expect("Tried to get a range from a text with an index that's too large for usize.");
-        let end_exclusive = range
-            .end
-            .try_get::<usize>()
-            .expect("Tried to get a range from a text with an index that's too large for usize.");
+    pub fn get_range(self, heap: &mut Heap, range: Range<usize>) -> Text {
         let text: String = self
             .get()
             .graphemes(true)
-            .skip(start_inclusive)
-            .take(end_exclusive - start_inclusive)
+            .skip(range.start)
+            .take(range.len())
             .collect();
         Text::create(heap, true, &text)
     }
 
+    #[must_use]
+    pub fn index_of(self, heap: &mut Heap, pattern: Text) -> Tag {
+        match self.get().grapheme_index_of(pattern.get()) {
+            Some(index) => {
+                let index = Int::create(heap, true, index);
+                let found_at = heap.default_symbols().found_at;
+                Tag::create_with_value(heap, true, found_at, index)
+            }
+            None => Tag::create(heap.default_symbols().not_found),
+        }
+    }
+    #[must_use]
+    pub fn split(self, heap: &mut Heap, separator: Text) -> List {
+        let parts = self
+            .get()
+            .grapheme_split(separator.get())
+            .into_iter()
+            .map(|it| Text::create(heap, true, it).into())
+            .collect_vec();
+        List::create(heap, true, &parts)
+    }
+    #[must_use]
+    pub fn replace(self, heap: &mut Heap, pattern: Text, replacement: Text) -> Text {
+        Text::create(
+            heap,
+            true,
+            &self
+                .get()
+                .grapheme_replace(pattern.get(), replacement.get()),
+        )
+    }
+    #[must_use]
+    pub fn to_lower(self, heap: &mut Heap) -> Text {
+        Text::create(heap, true, &self.get().to_lowercase())
+    }
+    #[must_use]
+    pub fn to_upper(self, heap: &mut Heap) -> Text {
+        Text::create(heap, true, &self.get().to_uppercase())
+    }
+
     #[must_use]
     pub fn concatenate(self, heap: &mut Heap, other: Text) -> Text {
-        Text::create(heap, true, &format!("{}{}", self.get(), other.get()))
+        if self.byte_len() + other.byte_len() < Self::MIN_ROPE_BYTE_LEN {
+            return Text::create(heap, true, &format!("{}{}", self.get(), other.get()));
+        }
+
+        self.dup();
+        other.dup();
+        Self::create_rope(heap, self.into(), other).into()
     }
     #[must_use]
     pub fn trim_start(self, heap: &mut Heap) -> Text {
@@ -126,22 +329,87 @@ impl DebugDisplay for HeapText {
 }
 impl_debug_display_via_debugdisplay!(HeapText);
 
-impl_eq_hash_ord_via_get!(HeapText);
+impl Eq for HeapText {}
+impl PartialEq for HeapText {
+    fn eq(&self, other: &Self) -> bool {
+        self.byte_len() == other.byte_len() && self.get() == other.get()
+    }
+}
+impl Hash for HeapText {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.byte_len().hash(state);
+        self.content_hash().hash(state);
+    }
+}
+impl Ord for HeapText {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.get().cmp(other.get())
+    }
+}
+impl PartialOrd for HeapText {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
 
 heap_object_impls!(HeapText);
 
 impl HeapObjectTrait for HeapText {
     fn content_size(self) -> usize {
-        self.byte_len()
+        if self.is_rope() {
+            Self::ROPE_CONTENT_SIZE
+        } else {
+            self.byte_len()
+        }
     }
 
     fn clone_content_to_heap_with_mapping(
         self,
-        _heap: &mut Heap,
+        heap: &mut Heap,
         clone: HeapObject,
-        _address_map: &mut FxHashMap<HeapObject, HeapObject>,
+        address_map: &mut FxHashMap<HeapObject, HeapObject>,
     ) {
         let clone = Self(clone);
+        if let Some((left, right)) = self.rope_parts() {
+            // Cloning deeply nested ropes recursively could overflow the stack,
+            // so we clone nested ropes bottom-up before cloning this one.
+            let mut pending = vec![left, right];
+            while let Some(&rope) = pending.last() {
+                let Some(parts) = rope.rope_parts() else {
+                    pending.pop();
+                    continue;
+                };
+                if address_map.contains_key(&**rope) {
+                    pending.pop();
+                    continue;
+                }
+
+                let uncloned_parts = <[Text; 2]>::from(parts)
+                    .into_iter()
+                    .filter(|it| it.is_rope() && !address_map.contains_key(&***it))
+                    .collect_vec();
+                if uncloned_parts.is_empty() {
+                    pending.pop();
+                    let rope_clone = rope.clone_to_heap_with_mapping(heap, address_map);
+                    // Looking up the clone below counts as the parent's
+                    // reference.
+                    rope_clone.set_reference_count(rope_clone.reference_count().unwrap() - 1);
+                } else {
+                    pending.extend(uncloned_parts);
+                }
+            }
+
+            let left = left.clone_to_heap_with_mapping(heap, address_map);
+            let right = right.clone_to_heap_with_mapping(heap, address_map);
+            unsafe {
+                *clone.rope_part_pointer(0).as_mut() = left.into();
+                *clone.rope_part_pointer(1).as_mut() = right.into();
+                *clone.flattened_pointer().as_mut() = ptr::null_mut();
+                *clone.content_hash_pointer().as_mut() = self.content_hash();
+            };
+            return;
+        }
+
         unsafe {
             ptr::copy_nonoverlapping(
                 self.text_pointer().as_ptr(),
@@ -151,7 +419,91 @@ impl HeapObjectTrait for HeapText {
         };
     }
 
-    fn drop_children(self, _heap: &mut Heap) {}
+    fn drop_children(self, heap: &mut Heap) {
+        let Some((left, right)) = self.rope_parts() else {
+            return;
+        };
+
+        // Dropping a deeply nested rope recursively could overflow the stack,
+        // so we free nested ropes that are only referenced by their parent
+        // right here.
+        let mut parts = vec![left, right];
+        while let Some(part) = parts.pop() {
+            if let Some((left, right)) = part.rope_parts()
+                && part.reference_count() == Some(1)
+            {
+                part.set_reference_count(0);
+                parts.push(left);
+                parts.push(right);
+                heap.deallocate(HeapData::Text(*part));
+            } else {
+                part.drop(heap);
+            }
+        }
+    }
+
+    fn deallocate_external_stuff(self) {
+        if self.is_rope() {
+            self.free_flattened();
+        }
+    }
+}
 
-    fn deallocate_external_stuff(self) {}
+#[cfg(test)]
+mod tests {
+    use crate::heap::{Heap, InlineObject, Text};
+    use rustc_hash::FxHasher;
+    use std::hash::{BuildHasher, BuildHasherDefault};
+
+    #[test]
+    fn concatenating_long_texts_creates_ropes() {
+        let mut heap = Heap::default();
+        let initial_live_objects = heap.stats().live_objects();
+        let part = Text::create(&mut heap, true, "abc");
+        let mut text = Text::create(&mut heap, true, "");
+        for _ in 0..100_000 {
+            let concatenated = text.concatenate(&mut heap, part);
+            InlineObject::from(text).drop(&mut heap);
+            text = concatenated;
+        }
+        assert!(text.rope_parts().is_some());
+        assert_eq!(text.byte_len(), 300_000);
+        assert_eq!(text.get(), "abc".repeat(100_000));
+
+        let flat = Text::create(&mut heap, true, &"abc".repeat(100_000));
+        let hasher = BuildHasherDefault::<FxHasher>::default();
+        assert_eq!(hasher.hash_one(text), hasher.hash_one(flat));
+        assert!(text == flat);
+
+        let (cloned_heap, mapping) = heap.clone();
+        let cloned_text = Text::try_from(mapping[&**text]).unwrap();
+        assert_eq!(cloned_text.get(), text.get());
+        drop(cloned_heap);
+
+        // Dropping the outermost rope frees all nested ones.
+        InlineObject::from(text).drop(&mut heap);
+        assert_eq!(heap.stats().live_objects(), initial_live_objects + 2);
+        InlineObject::from(flat).drop(&mut heap);
+        InlineObject::from(part).drop(&mut heap);
+        assert!(heap.leak_report(None).is_empty());
+    }
+
+    #[test]
+    fn flattening_a_rope_keeps_copies_of_nested_ropes() {
+        let mut heap = Heap::default();
+        let part = Text::create(&mut heap, true, &"a".repeat(200));
+        let inner = part.concatenate(&mut heap, part);
+        let inner_str = inner.get();
+        let outer = inner.concatenate(&mut heap, part);
+
+        // Now, `outer` is the only one referencing `inner`.
+        InlineObject::from(inner).drop(&mut heap);
+        assert_eq!(outer.get(), "a".repeat(600));
+        assert!(inner.get_if_flat().is_some());
+        assert_eq!(inner_str, "a".repeat(400));
+
+        InlineObject::from(outer).drop(&mut heap);
+        InlineObject::from(part).drop(&mut heap);
+        assert!(heap.leak_report(None).is_empty());
+    }
 }
@@ -0,0 +1,91 @@
+use super::{InlineObject, InlineObjectTrait};
+use crate::{
+    heap::{object_heap::HeapObject, Heap},
+    utils::{impl_debug_display_via_debugdisplay, impl_eq_hash_ord_via_get, DebugDisplay},
+};
+use candy_frontend::format::format_float;
+use derive_more::Deref;
+use ordered_float::OrderedFloat;
+use rustc_hash::FxHashMap;
+use std::{
+    fmt::{self, Formatter},
+    num::NonZeroU64,
+};
+
+#[derive(Clone, Copy, Deref)]
+pub struct InlineFloat(InlineObject);
+impl InlineFloat {
+    #[must_use]
+    pub const fn new_unchecked(object: InlineObject) -> Self {
+        Self(object)
+    }
+
+    /// The kind bits overlap with the lowest bits of the mantissa, so only
+    /// floats that have these bits set to zero can be stored inline.
+    #[must_use]
+    pub fn fits(value: f64) -> bool {
+        value.to_bits() & InlineObject::KIND_MASK == 0
+    }
+    #[must_use]
+    pub fn from_unchecked(value: f64) -> Self {
+        debug_assert!(Self::fits(value), "Float is too precise.");
+        let header_word = InlineObject::KIND_FLOAT | value.to_bits();
+        let header_word = unsafe { NonZeroU64::new_unchecked(header_word) };
+        Self(InlineObject(header_word))
+    }
+
+    #[must_use]
+    pub fn get(self) -> OrderedFloat<f64> {
+        OrderedFloat(f64::from_bits(
+            self.raw_word().get() & !InlineObject::KIND_MASK,
+        ))
+    }
+}
+
+impl DebugDisplay for InlineFloat {
+    fn fmt(&self, f: &mut Formatter, _is_debug: bool) -> fmt::Result {
+        write!(f, "{}", format_float(*self.get()))
+    }
+}
+impl_debug_display_via_debugdisplay!(InlineFloat);
+
+impl_eq_hash_ord_via_get!(InlineFloat);
+
+impl TryFrom<f64> for InlineFloat {
+    type Error = ();
+
+    fn try_from(value: f64) -> Result<Self, Self::Error> {
+        if Self::fits(value) {
+            Ok(Self::from_unchecked(value))
+        } else {
+            Err(())
+        }
+    }
+}
+
+impl InlineObjectTrait for InlineFloat {
+    fn clone_to_heap_with_mapping(
+        self,
+        _heap: &mut Heap,
+        _address_map: &mut FxHashMap<HeapObject, HeapObject>,
+    ) -> Self {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InlineFloat;
+
+    #[test]
+    fn round_trip() {
+        for value in [0.0, -0.0, 1.0, -1.5, 0.25, f64::INFINITY, f64::NEG_INFINITY] {
+            assert_eq!(
+                InlineFloat::try_from(value).unwrap().get().to_bits(),
+                value.to_bits(),
+            );
+        }
+        assert!(InlineFloat::try_from(f64::NAN).unwrap().get().is_nan());
+        assert!(InlineFloat::try_from(0.1).is_err());
+    }
+}
@@ -122,7 +122,9 @@ impl InlineInt {
     pub fn shift_right(self, rhs: Self) -> Self {
         // SAFETY: The value can only get closer to zero, so it must be covered by our range as
         // well.
-        Self::from_unchecked(self.get() >> rhs.get())
+        // Shifting by 63 or more bits already shifts out all bits except for
+        // the sign, and Rust doesn't allow shifting by 64 or more bits.
+        Self::from_unchecked(self.get() >> rhs.get().min(i64::from(i64::BITS) - 1))
     }
 
     #[must_use]
@@ -289,6 +291,12 @@ mod tests {
             assert_eq!(inline_int(1).shift_right(shift), inline_int(0));
             assert_eq!(inline_int(-1).shift_right(shift), inline_int(-1));
         }
+
+        {
+            let shift = inline_int(1000);
+            assert_eq!(inline_int(5).shift_right(shift), inline_int(0));
+            assert_eq!(inline_int(-5).shift_right(shift), inline_int(-1));
+        }
     }
 
     #[test]
@@ -1,8 +1,8 @@
 use self::{
-    builtin::InlineBuiltin, handle::InlineHandle, int::InlineInt, pointer::InlinePointer,
-    tag::InlineTag,
+    builtin::InlineBuiltin, float::InlineFloat, handle::InlineHandle, int::InlineInt,
+    pointer::InlinePointer, tag::InlineTag,
 };
-use super::{object_heap::HeapObject, Data, Heap};
+use super::{deep, object_heap::HeapObject, Data, Function, Heap};
 use crate::{
     handle_id::HandleId,
     utils::{impl_debug_display_via_debugdisplay, DebugDisplay},
@@ -22,6 +22,7 @@ use std::{
 };
 
 pub(super) mod builtin;
+pub(super) mod float;
 pub(super) mod handle;
 pub(super) mod int;
 pub(super) mod pointer;
@@ -57,6 +58,7 @@ impl InlineObject {
     pub const KIND_BUILTIN: u64 = 0b010;
     pub const KIND_TAG: u64 = 0b011;
     pub const KIND_HANDLE: u64 = 0b100;
+    pub const KIND_FLOAT: u64 = 0b101;
 
     #[must_use]
     pub const fn new(value: NonZeroU64) -> Self {
@@ -119,17 +121,17 @@ impl_debug_display_via_debugdisplay!(InlineObject);
 impl Eq for InlineObject {}
 impl PartialEq for InlineObject {
     fn eq(&self, other: &Self) -> bool {
-        InlineData::from(*self) == InlineData::from(*other)
+        deep::equals(*self, *other)
     }
 }
 impl Hash for InlineObject {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        InlineData::from(*self).hash(state);
+        deep::hash(*self, state);
     }
 }
 impl Ord for InlineObject {
     fn cmp(&self, other: &Self) -> Ordering {
-        Data::from(*self).cmp(&Data::from(*other))
+        deep::compare(*self, *other)
     }
 }
 impl PartialOrd for InlineObject {
@@ -167,6 +169,7 @@ pub enum InlineData {
     Builtin(InlineBuiltin),
     Tag(InlineTag),
     Handle(InlineHandle),
+    Float(InlineFloat),
 }
 impl InlineData {
     fn handle_id(&self) -> Option<HandleId> {
@@ -187,6 +190,7 @@ impl From<InlineObject> for InlineData {
             InlineObject::KIND_BUILTIN => Self::Builtin(InlineBuiltin::new_unchecked(object)),
             InlineObject::KIND_TAG => Self::Tag(InlineTag::new_unchecked(object)),
             InlineObject::KIND_HANDLE => Self::Handle(InlineHandle::new_unchecked(object)),
+            InlineObject::KIND_FLOAT => Self::Float(InlineFloat::new_unchecked(object)),
             _ => panic!("Unknown inline value type: {value:016x}"),
         }
     }
@@ -200,6 +204,7 @@ impl DebugDisplay for InlineData {
             Self::Builtin(value) => value.fmt(f, is_debug),
             Self::Tag(value) => value.fmt(f, is_debug),
             Self::Handle(value) => value.fmt(f, is_debug),
+            Self::Float(value) => value.fmt(f, is_debug),
         }
     }
 }
@@ -215,6 +220,7 @@ impl Deref for InlineData {
             Self::Builtin(value) => value,
             Self::Tag(value) => value,
             Self::Handle(value) => value,
+            Self::Float(value) => value,
         }
     }
 }
@@ -222,14 +228,26 @@ impl Deref for InlineData {
 #[extension_trait]
 pub impl ToDebugText for InlineObject {
     fn to_debug_text(self, precendence: Precedence, max_length: MaxLength) -> String {
+        self.to_debug_text_describing_functions(precendence, max_length, &|_| None)
+    }
+    /// Like [`Self::to_debug_text`], but shows functions using the description
+    /// returned by `describe_function` (if any) instead of `{ … }`.
+    fn to_debug_text_describing_functions<'a>(
+        self,
+        precendence: Precedence,
+        max_length: MaxLength,
+        describe_function: &impl Fn(Function) -> Option<&'a str>,
+    ) -> String {
         format_value(self, precendence, max_length, &|value| {
             Some(match value.into() {
                 Data::Int(int) => FormatValue::Int(int.get()),
+                Data::Float(float) => FormatValue::Float(*float.get()),
                 Data::Tag(tag) => FormatValue::Tag {
                     symbol: tag.symbol().get(),
                     value: tag.value(),
                 },
                 Data::Text(text) => FormatValue::Text(text.get()),
+                Data::Bytes(bytes) => FormatValue::Bytes(bytes.get()),
                 Data::List(list) => FormatValue::List(list.items()),
                 Data::Struct(struct_) => FormatValue::Struct(Cow::Owned(
                     struct_
@@ -238,7 +256,9 @@ pub impl ToDebugText for InlineObject {
                         .collect_vec(),
                 )),
                 Data::HirId(_) => unreachable!(),
-                Data::Function(_) | Data::Builtin(_) | Data::Handle(_) => FormatValue::Function,
+                Data::Function(function) => describe_function(function)
+                    .map_or(FormatValue::Function, FormatValue::DescribedFunction),
+                Data::Builtin(_) | Data::Handle(_) => FormatValue::Function,
             })
         })
         .unwrap()
@@ -0,0 +1,166 @@
+use super::{Heap, HeapData, HeapObjectTrait};
+use std::fmt::{self, Display, Formatter};
+
+/// Counters about the heap's allocations.
+///
+/// They are updated on every allocation and deallocation, so reading them is
+/// free and they don't require a feature flag (unlike the allocation sites used
+/// by leak reports).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct HeapStats {
+    pub allocations: usize,
+    pub frees: usize,
+    pub live_bytes: usize,
+    pub peak_live_bytes: usize,
+    pub ints: ObjectCounts,
+    pub floats: ObjectCounts,
+    pub texts: ObjectCounts,
+    pub bytes: ObjectCounts,
+    pub tags: ObjectCounts,
+    pub lists: ObjectCounts,
+    pub structs: ObjectCounts,
+    pub functions: ObjectCounts,
+    pub hir_ids: ObjectCounts,
+}
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ObjectCounts {
+    /// How many objects of this kind were allocated in total.
+    pub allocated: usize,
+    /// How many objects of this kind are currently alive.
+    pub live: usize,
+}
+
+impl HeapStats {
+    #[must_use]
+    pub const fn live_objects(&self) -> usize {
+        self.allocations - self.frees
+    }
+
+    pub(super) fn record_allocation(&mut self, object: HeapData) {
+        self.allocations += 1;
+        self.live_bytes += object.total_size();
+        self.peak_live_bytes = self.peak_live_bytes.max(self.live_bytes);
+        let counts = self.counts_mut(object);
+        counts.allocated += 1;
+        counts.live += 1;
+    }
+    pub(super) fn record_free(&mut self, object: HeapData) {
+        self.frees += 1;
+        self.live_bytes -= object.total_size();
+        self.counts_mut(object).live -= 1;
+    }
+    /// Takes over the objects counted by `other`, e.g., when adopting another
+    /// heap.
+    pub(super) fn merge(&mut self, other: &Self) {
+        self.allocations += other.allocations;
+        self.frees += other.frees;
+        self.live_bytes += other.live_bytes;
+        self.peak_live_bytes = self.peak_live_bytes.max(self.live_bytes);
+        for (counts, (_, other)) in self.all_counts_mut().into_iter().zip(other.all_counts()) {
+            counts.allocated += other.allocated;
+            counts.live += other.live;
+        }
+    }
+
+    fn counts_mut(&mut self, object: HeapData) -> &mut ObjectCounts {
+        match object {
+            HeapData::Int(_) => &mut self.ints,
+            HeapData::Float(_) => &mut self.floats,
+            HeapData::Text(_) => &mut self.texts,
+            HeapData::Bytes(_) => &mut self.bytes,
+            HeapData::Tag(_) => &mut self.tags,
+            HeapData::List(_) => &mut self.lists,
+            HeapData::Struct(_) => &mut self.structs,
+            HeapData::Function(_) => &mut self.functions,
+            HeapData::HirId(_) => &mut self.hir_ids,
+        }
+    }
+    const fn all_counts(&self) -> [(&'static str, ObjectCounts); 9] {
+        [
+            ("ints", self.ints),
+            ("floats", self.floats),
+            ("texts", self.texts),
+            ("bytes", self.bytes),
+            ("tags", self.tags),
+            ("lists", self.lists),
+            ("structs", self.structs),
+            ("functions", self.functions),
+            ("HIR IDs", self.hir_ids),
+        ]
+    }
+    fn all_counts_mut(&mut self) -> [&mut ObjectCounts; 9] {
+        [
+            &mut self.ints,
+            &mut self.floats,
+            &mut self.texts,
+            &mut self.bytes,
+            &mut self.tags,
+            &mut self.lists,
+            &mut self.structs,
+            &mut self.functions,
+            &mut self.hir_ids,
+        ]
+    }
+}
+
+impl Heap {
+    #[must_use]
+    pub const fn stats(&self) -> &HeapStats {
+        &self.stats
+    }
+}
+
+impl Display for HeapStats {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "Heap statistics:")?;
+        writeln!(f, "  allocations: {}", self.allocations)?;
+        writeln!(f, "  frees:       {}", self.frees)?;
+        writeln!(
+            f,
+            "  live:        {} objects ({} bytes, peak {} bytes)",
+            self.live_objects(),
+            self.live_bytes,
+            self.peak_live_bytes,
+        )?;
+        write!(f, "  objects by kind (allocated / live):")?;
+        for (name, counts) in self.all_counts() {
+            if counts.allocated > 0 {
+                write!(
+                    f,
+                    "\n    {:<10} {} / {}",
+                    format!("{name}:"),
+                    counts.allocated,
+                    counts.live,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::heap::{Heap, InlineObject, List, Text};
+
+    #[test]
+    fn stats_count_allocations_and_frees() {
+        let mut heap = Heap::default();
+        let initial = *heap.stats();
+
+        let text = Text::create(&mut heap, true, "Hello");
+        let list = List::create(&mut heap, true, &[text.into()]);
+        let stats = *heap.stats();
+        assert_eq!(stats.allocations, initial.allocations + 2);
+        assert_eq!(stats.texts.live, initial.texts.live + 1);
+        assert_eq!(stats.lists.live, initial.lists.live + 1);
+        assert!(stats.live_bytes > initial.live_bytes);
+
+        InlineObject::from(list).drop(&mut heap);
+        let stats = *heap.stats();
+        assert_eq!(stats.frees, initial.frees + 2);
+        assert_eq!(stats.live_objects(), initial.live_objects());
+        assert_eq!(stats.live_bytes, initial.live_bytes);
+        assert_eq!(stats.texts.allocated, initial.texts.allocated + 1);
+        assert!(stats.peak_live_bytes > stats.live_bytes);
+    }
+}
@@ -1,9 +1,11 @@
 use crate::{
-    byte_code::Instruction,
-    heap::{Data, Function, Heap, HirId, InlineObject, List, Struct, Tag, Text},
+    byte_code::{ByteCode, Instruction},
+    heap::{Builtin, Data, Function, Heap, HirId, InlineObject, List, Struct, Tag, Text},
+    instruction_pointer::InstructionPointer,
     tracer::Tracer,
     vm::{CallHandle, MachineState, Panic},
 };
+use candy_frontend::builtin_functions::BuiltinFunction;
 use itertools::Itertools;
 use tracing::trace;
 
@@ -19,6 +21,7 @@ impl MachineState {
     pub fn run_instruction(
         &mut self,
         heap: &mut Heap,
+        byte_code: &ByteCode,
         instruction: &Instruction,
         tracer: &mut impl Tracer,
     ) -> InstructionResult {
@@ -106,6 +109,29 @@ impl MachineState {
                 self.push_to_data_stack(top);
                 InstructionResult::Done
             }
+            Instruction::PushMultipleFromStack(offsets) => {
+                for offset in offsets {
+                    let address = self.get_from_data_stack(*offset);
+                    self.push_to_data_stack(address);
+                }
+                InstructionResult::Done
+            }
+            Instruction::DupFromStack { offset, amount } => {
+                self.get_from_data_stack(*offset).dup_by(heap, *amount);
+                InstructionResult::Done
+            }
+            Instruction::DropFromStack(offset) => {
+                self.get_from_data_stack(*offset).drop(heap);
+                InstructionResult::Done
+            }
+            Instruction::PopMultipleBelowTopAndReturn(n) => {
+                let top = self.pop_from_data_stack();
+                self.pop_multiple_from_data_stack(*n);
+                self.push_to_data_stack(top);
+                self.next_instruction = self.call_stack.pop();
+                self.on_return(heap);
+                InstructionResult::Done
+            }
             Instruction::Dup { amount } => {
                 self.pop_from_data_stack().dup_by(heap, *amount);
                 InstructionResult::Done
@@ -115,6 +141,7 @@ impl MachineState {
                 InstructionResult::Done
             }
             Instruction::Call { num_args } => {
+                let call_site = self.next_instruction;
                 let responsible = self.pop_from_data_stack().try_into().unwrap();
                 let mut arguments = (0..*num_args)
                     .map(|_| self.pop_from_data_stack())
@@ -123,12 +150,22 @@ impl MachineState {
                 arguments.reverse();
                 let callee = self.pop_from_data_stack();
 
-                self.call(heap, callee, &arguments, responsible)
+                self.call_from_site(
+                    heap,
+                    byte_code,
+                    tracer,
+                    call_site,
+                    callee,
+                    &arguments,
+                    responsible,
+                )
             }
             Instruction::TailCall {
                 num_locals_to_pop,
                 num_args,
+                current_body,
             } => {
+                let call_site = self.next_instruction;
                 let responsible = self.pop_from_data_stack().try_into().unwrap();
                 let mut arguments = (0..*num_args)
                     .map(|_| self.pop_from_data_stack())
@@ -138,13 +175,47 @@ impl MachineState {
                 let callee = self.pop_from_data_stack();
                 self.pop_multiple_from_data_stack(*num_locals_to_pop);
 
+                if let Data::Function(function) = callee.into()
+                    && function.body() == *current_body
+                    && !byte_code.memoized_bodies.contains(current_body)
+                {
+                    // The function calls itself. Instead of returning to our
+                    // caller and calling the function again, we reuse the
+                    // current frame and jump back to the start of the body.
+                    // This doesn't nest calls deeper, so only the arity check
+                    // of `call_function` applies. Memoized functions take the
+                    // regular path so that their results get cached.
+                    assert_eq!(function.argument_count(), arguments.len());
+                    tracer.tail_call_optimized(heap, function);
+                    self.data_stack.extend_from_slice(function.captured());
+                    self.data_stack.extend_from_slice(&arguments);
+                    self.push_to_data_stack(responsible);
+                    self.next_instruction = Some(*current_body);
+                    return InstructionResult::Done;
+                }
+
                 // Tail calling a function is basically just a normal call, but
                 // pretending we are our caller.
                 self.next_instruction = self.call_stack.pop();
-                self.call(heap, callee, &arguments, responsible)
+                let result = self.call_from_site(
+                    heap,
+                    byte_code,
+                    tracer,
+                    call_site,
+                    callee,
+                    &arguments,
+                    responsible,
+                );
+                if matches!(result, InstructionResult::Done) {
+                    // The callee might have returned immediately, e.g., if it
+                    // is a builtin function.
+                    self.on_return(heap);
+                }
+                result
             }
             Instruction::Return => {
                 self.next_instruction = self.call_stack.pop();
+                self.on_return(heap);
                 InstructionResult::Done
             }
             Instruction::Panic => {
@@ -164,6 +235,8 @@ impl MachineState {
                 InstructionResult::Panic(Panic {
                     reason: reason.get().to_string(),
                     responsible: responsible.get().clone(),
+                    builtin_error: None,
+                    payload: None,
                 })
             }
             Instruction::TraceCallStarts { num_args } => {
@@ -220,18 +293,83 @@ impl MachineState {
         }
     }
 
+    /// Like [`Self::call`], but calls of `✨.structGet` use the inline cache of
+    /// the call site. The call site is identified by the instruction pointer
+    /// after the call instruction.
+    #[allow(clippy::too_many_arguments)]
+    fn call_from_site(
+        &mut self,
+        heap: &mut Heap,
+        byte_code: &ByteCode,
+        tracer: &mut impl Tracer,
+        call_site: Option<InstructionPointer>,
+        callee: InlineObject,
+        arguments: &[InlineObject],
+        responsible: HirId,
+    ) -> InstructionResult {
+        // Comparing the raw words is cheaper than converting every callee.
+        let struct_get = InlineObject::from(Builtin::create(BuiltinFunction::StructGet));
+        if let Some(call_site) = call_site
+            && callee.raw_word() == struct_get.raw_word()
+            && let Some(value) = self.struct_get_with_inline_cache(call_site, arguments)
+        {
+            value.dup(heap);
+            for argument in arguments {
+                argument.drop(heap);
+            }
+            self.push_to_data_stack(value);
+            return InstructionResult::Done;
+        }
+
+        self.call(heap, byte_code, tracer, callee, arguments, responsible)
+    }
+    /// Returns [`None`] if the arguments are invalid so that the actual builtin
+    /// can report the error.
+    fn struct_get_with_inline_cache(
+        &mut self,
+        call_site: InstructionPointer,
+        arguments: &[InlineObject],
+    ) -> Option<InlineObject> {
+        let [struct_, key] = arguments else {
+            return None;
+        };
+        let struct_ = Struct::try_from(*struct_).ok()?;
+        if struct_.is_trie() {
+            // Tries don't store their fields at fixed indexes.
+            return struct_.get(*key);
+        }
+        let cached_index = self.struct_get_cache.entry(call_site).or_insert(usize::MAX);
+        if struct_.keys().get(*cached_index) == Some(key) {
+            return Some(struct_.values()[*cached_index]);
+        }
+
+        let index = struct_.index_of(*key)?;
+        *cached_index = index;
+        Some(struct_.values()[index])
+    }
+
     pub fn call(
         &mut self,
         heap: &mut Heap,
+        byte_code: &ByteCode,
+        tracer: &mut impl Tracer,
         callee: InlineObject,
         arguments: &[InlineObject],
         responsible: HirId,
     ) -> InstructionResult {
         match callee.into() {
-            Data::Function(function) => self.call_function(function, arguments, responsible),
-            Data::Builtin(builtin) => {
-                self.run_builtin_function(heap, builtin.get(), arguments, responsible)
+            Data::Function(function) if byte_code.memoized_bodies.contains(&function.body()) => {
+                self.call_memoized_function(heap, function, arguments, responsible)
             }
+            Data::Function(function) => self.call_function(function, arguments, responsible),
+            Data::Builtin(builtin) => self.run_builtin_function(
+                heap,
+                byte_code,
+                tracer,
+                builtin.get(),
+                arguments,
+                responsible,
+            ),
             Data::Handle(handle) => {
                 assert_eq!(handle.argument_count(), arguments.len());
                 InstructionResult::CallHandle(CallHandle {
@@ -251,6 +389,17 @@ impl MachineState {
     ) -> InstructionResult {
         assert_eq!(function.argument_count(), arguments.len());
         if let Some(next_instruction) = self.next_instruction {
+            if self.call_stack.len() >= self.max_call_depth {
+                return InstructionResult::Panic(Panic {
+                    reason: format!(
+                        "Stack overflow: Calls are nested more than {} levels deep.",
+                        self.max_call_depth,
+                    ),
+                    responsible: responsible.get().clone(),
+                    builtin_error: None,
+                    payload: None,
+                });
+            }
             self.call_stack.push(next_instruction);
         }
         self.data_stack.extend_from_slice(function.captured());
@@ -260,6 +409,36 @@ impl MachineState {
         InstructionResult::Done
     }
 
+    fn call_memoized_function(
+        &mut self,
+        heap: &mut Heap,
+        function: Function,
+        arguments: &[InlineObject],
+        responsible: HirId,
+    ) -> InstructionResult {
+        if let Some(result) = self.memoization_cache.get(heap, function, arguments) {
+            for argument in arguments {
+                argument.drop(heap);
+            }
+            self.push_to_data_stack(result);
+            return InstructionResult::Done;
+        }
+
+        // Without a caller to return to, we can't tell when the call finishes.
+        if self.next_instruction.is_some() {
+            self.memoization_cache
+                .on_call(heap, self.call_stack.len(), function, arguments);
+        }
+        self.call_function(function, arguments, responsible)
+    }
+    /// Stores the return value of memoized calls that just finished.
+    fn on_return(&mut self, heap: &mut Heap) {
+        if let Some(result) = self.data_stack.last() {
+            self.memoization_cache
+                .on_return(heap, self.call_stack.len(), *result);
+        }
+    }
+
     fn get_from_data_stack(&self, offset: usize) -> InlineObject {
         self.data_stack[self.data_stack.len() - 1 - offset]
     }
@@ -274,3 +453,204 @@ impl MachineState {
         self.data_stack.truncate(self.data_stack.len() - amount);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::InstructionResult;
+    use crate::{
+        byte_code::{ByteCode, Instruction},
+        heap::{create_symbol, Function, Heap, HirId, InlineObject, Int, Struct, Tag},
+        instruction_pointer::InstructionPointer,
+        memoization::MemoizationCache,
+        tracer::Tracer,
+        vm::{MachineState, DEFAULT_MAX_CALL_DEPTH},
+    };
+    use candy_frontend::hir::Id;
+    use rustc_hash::{FxHashMap, FxHashSet};
+
+    #[test]
+    fn struct_get_inline_cache_handles_different_shapes() {
+        let mut heap = Heap::default();
+        let mut key = |name| -> InlineObject { Tag::create(create_symbol(&mut heap, name)).into() };
+        let (foo, bar, baz) = (key("Foo"), key("Bar"), key("Baz"));
+        let mut create_struct = |fields: &[(InlineObject, i64)]| -> InlineObject {
+            let fields = fields
+                .iter()
+                .map(|(key, value)| (*key, Int::create(&mut heap, true, *value).into()))
+                .collect::<FxHashMap<_, _>>();
+            Struct::create(&mut heap, true, &fields).into()
+        };
+        let first = create_struct(&[(foo, 1), (bar, 2)]);
+        let second = create_struct(&[(bar, 3), (baz, 4)]);
+
+        let mut state = MachineState {
+            next_instruction: None,
+            data_stack: vec![],
+            call_stack: vec![],
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            struct_get_cache: FxHashMap::default(),
+            memoization_cache: MemoizationCache::default(),
+        };
+        let call_site = InstructionPointer::null_pointer();
+        let mut get = |struct_, key| {
+            state
+                .struct_get_with_inline_cache(call_site, &[struct_, key])
+                .map(|value| i64::try_from(Int::try_from(value).unwrap().get().as_ref()).unwrap())
+        };
+        assert_eq!(get(first, bar), Some(2));
+        assert_eq!(get(first, bar), Some(2));
+        assert_eq!(get(second, bar), Some(3));
+        assert_eq!(get(second, baz), Some(4));
+        assert_eq!(get(first, foo), Some(1));
+        assert_eq!(get(first, baz), None);
+    }
+
+    #[test]
+    fn exceeding_max_call_depth_panics() {
+        let mut heap = Heap::default();
+        let function =
+            Function::create(&mut heap, true, &[], 0, InstructionPointer::null_pointer());
+        let responsible = HirId::create(&mut heap, true, Id::user());
+
+        let mut state = MachineState {
+            next_instruction: Some(InstructionPointer::null_pointer()),
+            data_stack: vec![],
+            call_stack: vec![],
+            max_call_depth: 2,
+            struct_get_cache: FxHashMap::default(),
+            memoization_cache: MemoizationCache::default(),
+        };
+        for _ in 0..2 {
+            let result = state.call_function(function, &[], responsible);
+            assert!(matches!(result, InstructionResult::Done));
+        }
+        let result = state.call_function(function, &[], responsible);
+        assert!(matches!(result, InstructionResult::Panic(_)));
+        assert_eq!(state.call_stack.len(), 2);
+    }
+
+    fn empty_byte_code(heap: &mut Heap) -> ByteCode {
+        ByteCode {
+            module: Id::user().module,
+            constant_heap: Heap::default(),
+            instructions: vec![],
+            origins: vec![],
+            memoized_bodies: FxHashSet::default(),
+            module_function: Function::create(
+                heap,
+                true,
+                &[],
+                0,
+                InstructionPointer::null_pointer(),
+            ),
+            responsible_module: HirId::create(heap, true, Id::user()),
+            fuzzing_callbacks: None,
+        }
+    }
+    /// Calls `function` from `caller` and returns a state that is about to
+    /// run the body of `function`.
+    fn state_in_function(
+        function: Function,
+        caller: InstructionPointer,
+        responsible: HirId,
+    ) -> MachineState {
+        let mut state = MachineState {
+            next_instruction: Some(caller),
+            data_stack: vec![],
+            call_stack: vec![],
+            max_call_depth: 2,
+            struct_get_cache: FxHashMap::default(),
+            memoization_cache: MemoizationCache::default(),
+        };
+        let result = state.call_function(function, &[], responsible);
+        assert!(matches!(result, InstructionResult::Done));
+        state
+    }
+    /// Counts the tail calls that reused the current frame.
+    #[derive(Default)]
+    struct OptimizedTailCalls(usize);
+    impl Tracer for OptimizedTailCalls {
+        fn tail_call_optimized(&mut self, _heap: &mut Heap, _function: Function) {
+            self.0 += 1;
+        }
+    }
+
+    /// Pushes `callee` and tail calls it from the function with the body
+    /// `current_body`, which has only the responsibility as a local.
+    ///
+    /// Also returns whether the call reused the current frame.
+    fn tail_call(
+        state: &mut MachineState,
+        heap: &mut Heap,
+        byte_code: &ByteCode,
+        callee: Function,
+        current_body: InstructionPointer,
+        responsible: HirId,
+    ) -> (InstructionResult, bool) {
+        state.data_stack.push(callee.into());
+        state.data_stack.push(responsible.into());
+        let instruction = Instruction::TailCall {
+            num_locals_to_pop: 1,
+            num_args: 0,
+            current_body,
+        };
+        let mut tracer = OptimizedTailCalls::default();
+        let result = state.run_instruction(heap, byte_code, &instruction, &mut tracer);
+        (result, tracer.0 == 1)
+    }
+
+    #[test]
+    fn self_tail_calls_exceeding_max_call_depth_complete() {
+        let mut heap = Heap::default();
+        let byte_code = empty_byte_code(&mut heap);
+        let body = InstructionPointer::from(1);
+        let function = Function::create(&mut heap, true, &[], 0, body);
+        let responsible = HirId::create(&mut heap, true, Id::user());
+        let caller = InstructionPointer::null_pointer();
+
+        let mut state = state_in_function(function, caller, responsible);
+        for _ in 0..100 * state.max_call_depth {
+            let (result, reused_frame) = tail_call(
+                &mut state,
+                &mut heap,
+                &byte_code,
+                function,
+                body,
+                responsible,
+            );
+            assert!(matches!(result, InstructionResult::Done));
+            assert!(reused_frame);
+            assert_eq!(state.next_instruction, Some(body));
+        }
+        assert_eq!(state.call_stack, vec![caller]);
+        assert_eq!(state.data_stack.len(), 1);
+    }
+
+    #[test]
+    fn tail_calls_to_other_functions_return_to_the_caller() {
+        let mut heap = Heap::default();
+        let byte_code = empty_byte_code(&mut heap);
+        let (current_body, callee_body) =
+            (InstructionPointer::from(1), InstructionPointer::from(2));
+        let current = Function::create(&mut heap, true, &[], 0, current_body);
+        let callee = Function::create(&mut heap, true, &[], 0, callee_body);
+        let responsible = HirId::create(&mut heap, true, Id::user());
+        let caller = InstructionPointer::null_pointer();
+
+        let mut state = state_in_function(current, caller, responsible);
+        let (result, reused_frame) = tail_call(
+            &mut state,
+            &mut heap,
+            &byte_code,
+            callee,
+            current_body,
+            responsible,
+        );
+        assert!(matches!(result, InstructionResult::Done));
+        assert!(!reused_frame);
+        // The callee takes our frame, so it returns to our caller.
+        assert_eq!(state.next_instruction, Some(callee_body));
+        assert_eq!(state.call_stack, vec![caller]);
+        assert_eq!(state.data_stack.len(), 1);
+    }
+}
@@ -26,19 +26,28 @@
     clippy::too_many_lines
 )]
 
-pub use builtin_functions::CAN_USE_STDOUT;
+pub use builtin_functions::{BuiltinError, CAN_USE_STDOUT};
 pub use instruction_pointer::InstructionPointer;
+pub use runtime::{ExecutionLimit, ExecutionLimits, Runtime, RuntimeError, Value};
 pub use utils::PopulateInMemoryProviderFromFileSystem;
-pub use vm::{Panic, StateAfterRun, StateAfterRunForever, Vm, VmFinished};
+pub use vm::{
+    Panic, StateAfterRun, StateAfterRunForever, Vm, VmFinished, VmHandleCall,
+    DEFAULT_MAX_CALL_DEPTH,
+};
 
 mod builtin_functions;
 pub mod byte_code;
 pub mod environment;
+pub mod execution_controller;
+pub mod fuzzing_callbacks;
 mod handle_id;
 pub mod heap;
 mod instruction_pointer;
 mod instructions;
 pub mod lir_to_byte_code;
+pub mod memoization;
+pub mod native_functions;
+mod runtime;
 pub mod tracer;
 mod utils;
 mod vm;
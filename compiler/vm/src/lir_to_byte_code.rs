@@ -1,6 +1,7 @@
 use crate::{
     byte_code::{ByteCode, Instruction, StackOffset},
-    heap::{Builtin, Function, Heap, HirId, InlineObject, Int, List, Struct, Tag, Text},
+    fuzzing_callbacks::FuzzingCallbacks,
+    heap::{Builtin, Float, Function, Heap, HirId, InlineObject, Int, List, Struct, Tag, Text},
     instruction_pointer::InstructionPointer,
 };
 use candy_frontend::{
@@ -29,6 +30,7 @@ where
     Db: CstDb + OptimizeLir,
 {
     let module = target.module().clone();
+    let register_fuzzables = tracing.register_fuzzables.is_enabled();
     #[allow(clippy::map_unwrap_or)]
     let (lir, errors) = db.optimized_lir(target, tracing).unwrap_or_else(|error| {
         let mut constants = Constants::default();
@@ -40,6 +42,7 @@ where
             FxHashSet::from_iter([hir::Id::new(module.clone(), vec![])]),
             0,
             0,
+            false,
         );
         let reason_id = body.push(Expression::Constant(reason_id));
         let responsible_id = body.push(Expression::Constant(responsible_id));
@@ -57,7 +60,10 @@ where
             .collect();
         (Arc::new(lir), Arc::new(errors))
     });
-    let byte_code = LoweringContext::compile(module, lir.as_ref());
+    let mut byte_code = LoweringContext::compile(module, lir.as_ref());
+    if register_fuzzables {
+        byte_code.fuzzing_callbacks = Some(FuzzingCallbacks::compile(&mut byte_code));
+    }
     (byte_code, errors)
 }
 
@@ -65,6 +71,7 @@ struct LoweringContext<'c> {
     lir: &'c Lir,
     byte_code: ByteCode,
     constant_mapping: FxHashMap<ConstantId, InlineObject>,
+    text_mapping: FxHashMap<String, Text>,
     body_mapping: FxHashMap<BodyId, InstructionPointer>,
     stack: Vec<Id>,
     instructions: Vec<Instruction>,
@@ -87,13 +94,16 @@ impl<'c> LoweringContext<'c> {
             constant_heap,
             instructions: vec![],
             origins: vec![],
+            memoized_bodies: FxHashSet::default(),
             module_function,
             responsible_module,
+            fuzzing_callbacks: None,
         };
         let mut context = LoweringContext {
             lir,
             byte_code,
             constant_mapping: FxHashMap::default(),
+            text_mapping: FxHashMap::default(),
             body_mapping: FxHashMap::default(),
             stack: vec![],
             instructions: vec![],
@@ -113,8 +123,14 @@ impl<'c> LoweringContext<'c> {
     fn compile_body(&mut self, body_id: BodyId) -> InstructionPointer {
         let old_stack = mem::take(&mut self.stack);
         let old_instructions = mem::take(&mut self.instructions);
+        // Bodies are compiled one after another, so this body's instructions
+        // will start right after the ones we already compiled.
+        let start: InstructionPointer = self.byte_code.instructions.len().into();
 
         let body = self.lir.bodies().get(body_id);
+        if body.is_memoized() {
+            self.byte_code.memoized_bodies.insert(start);
+        }
         for captured in body.captured_ids() {
             self.stack.push(captured);
         }
@@ -134,6 +150,7 @@ impl<'c> LoweringContext<'c> {
             self.instructions.push(Instruction::TailCall {
                 num_locals_to_pop: self.stack.len() - 1,
                 num_args,
+                current_body: start,
             });
         } else {
             let dummy_id = Id::from_usize(0);
@@ -144,8 +161,9 @@ impl<'c> LoweringContext<'c> {
             self.emit(dummy_id, Instruction::Return);
         }
 
+        self.instructions = fuse_superinstructions(mem::take(&mut self.instructions));
         let num_instructions = self.instructions.len();
-        let start = self.byte_code.instructions.len().into();
+        assert_eq!(*start, self.byte_code.instructions.len());
         self.byte_code.instructions.append(&mut self.instructions);
         self.byte_code
             .origins
@@ -161,15 +179,7 @@ impl<'c> LoweringContext<'c> {
     fn compile_expression(&mut self, id: Id, expression: &Expression) {
         match expression {
             Expression::CreateTag { symbol, value } => {
-                let symbol = self
-                    .byte_code
-                    .constant_heap
-                    .default_symbols()
-                    .get(symbol)
-                    .unwrap_or_else(|| {
-                        Text::create(&mut self.byte_code.constant_heap, false, symbol)
-                    });
-
+                let symbol = self.get_text(symbol);
                 self.emit_reference_to(*value);
                 self.emit(id, Instruction::CreateTag { symbol });
             }
@@ -330,18 +340,12 @@ impl<'c> LoweringContext<'c> {
                 Int::create_from_bigint(&mut self.byte_code.constant_heap, false, int.clone())
                     .into()
             }
-            Constant::Text(text) => {
-                Text::create(&mut self.byte_code.constant_heap, false, text).into()
+            Constant::Float(float) => {
+                Float::create(&mut self.byte_code.constant_heap, false, **float).into()
             }
+            Constant::Text(text) => self.get_text(text).into(),
             Constant::Tag { symbol, value } => {
-                let symbol = self
-                    .byte_code
-                    .constant_heap
-                    .default_symbols()
-                    .get(symbol)
-                    .unwrap_or_else(|| {
-                        Text::create(&mut self.byte_code.constant_heap, false, symbol)
-                    });
+                let symbol = self.get_text(symbol);
                 let value = value.map(|id| self.get_constant(id));
                 Tag::create_with_value_option(
                     &mut self.byte_code.constant_heap,
@@ -382,6 +386,23 @@ impl<'c> LoweringContext<'c> {
         constant
     }
 
+    /// Texts and symbols with the same content share one object in the
+    /// constant heap.
+    fn get_text(&mut self, text: &str) -> Text {
+        if let Some(object) = self.text_mapping.get(text) {
+            return *object;
+        }
+
+        let object = self
+            .byte_code
+            .constant_heap
+            .default_symbols()
+            .get(text)
+            .unwrap_or_else(|| Text::create(&mut self.byte_code.constant_heap, false, text));
+        self.text_mapping.force_insert(text.to_string(), object);
+        object
+    }
+
     fn emit_reference_to(&mut self, id: Id) {
         let offset = self.stack.find_id(id);
         self.emit(id, Instruction::PushFromStack(offset));
@@ -392,6 +413,56 @@ impl<'c> LoweringContext<'c> {
     }
 }
 
+/// A peephole pass that fuses common instruction sequences into
+/// superinstructions so that the VM needs fewer dispatch steps.
+fn fuse_superinstructions(instructions: Vec<Instruction>) -> Vec<Instruction> {
+    let mut fused: Vec<Instruction> = Vec::with_capacity(instructions.len());
+    for instruction in instructions {
+        let Some(previous) = fused.last_mut() else {
+            fused.push(instruction);
+            continue;
+        };
+        match (&mut *previous, instruction) {
+            (Instruction::PushFromStack(first), Instruction::PushFromStack(second)) => {
+                *previous = Instruction::PushMultipleFromStack(vec![*first, second]);
+            }
+            (Instruction::PushMultipleFromStack(offsets), Instruction::PushFromStack(offset)) => {
+                offsets.push(offset);
+            }
+            (Instruction::PushFromStack(offset), Instruction::Dup { amount }) => {
+                *previous = Instruction::DupFromStack {
+                    offset: *offset,
+                    amount,
+                };
+            }
+            (Instruction::PushFromStack(offset), Instruction::Drop) => {
+                *previous = Instruction::DropFromStack(*offset);
+            }
+            // The pushed value may belong to an earlier expression, so we only
+            // fuse the last push with the `Dup` or `Drop`.
+            (
+                Instruction::PushMultipleFromStack(offsets),
+                instruction @ (Instruction::Dup { .. } | Instruction::Drop),
+            ) => {
+                let offset = offsets.pop().unwrap();
+                if let [single] = offsets.as_slice() {
+                    *previous = Instruction::PushFromStack(*single);
+                }
+                fused.push(match instruction {
+                    Instruction::Dup { amount } => Instruction::DupFromStack { offset, amount },
+                    Instruction::Drop => Instruction::DropFromStack(offset),
+                    _ => unreachable!(),
+                });
+            }
+            (Instruction::PopMultipleBelowTop(n), Instruction::Return) => {
+                *previous = Instruction::PopMultipleBelowTopAndReturn(*n);
+            }
+            (_, instruction) => fused.push(instruction),
+        }
+    }
+    fused
+}
+
 #[extension_trait]
 impl StackExt for Vec<Id> {
     fn pop_multiple(&mut self, n: usize) {
@@ -406,3 +477,37 @@ impl StackExt for Vec<Id> {
             .unwrap_or_else(|| panic!("Id {id} not found in stack: {}", self.iter().join(" ")))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::fuse_superinstructions;
+    use crate::byte_code::Instruction;
+
+    #[test]
+    fn fuses_superinstructions() {
+        let instructions = vec![
+            Instruction::PushFromStack(1),
+            Instruction::PushFromStack(3),
+            Instruction::PushFromStack(0),
+            Instruction::Dup { amount: 2 },
+            Instruction::PushFromStack(2),
+            Instruction::Drop,
+            Instruction::Call { num_args: 1 },
+            Instruction::PopMultipleBelowTop(4),
+            Instruction::Return,
+        ];
+        assert_eq!(
+            fuse_superinstructions(instructions),
+            vec![
+                Instruction::PushMultipleFromStack(vec![1, 3]),
+                Instruction::DupFromStack {
+                    offset: 0,
+                    amount: 2,
+                },
+                Instruction::DropFromStack(2),
+                Instruction::Call { num_args: 1 },
+                Instruction::PopMultipleBelowTopAndReturn(4),
+            ],
+        );
+    }
+}
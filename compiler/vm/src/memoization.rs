@@ -0,0 +1,140 @@
+//! Caching of results of calls to deterministic functions.
+//!
+//! When compiling with [`TracingConfig::memoize_calls`] enabled, function
+//! bodies that are deterministic and call other functions are marked as
+//! memoized (see [`ByteCode::memoized_bodies`]). When such a function is called
+//! with arguments it was already called with, the VM reuses the previous result
+//! instead of running the function again.
+//!
+//! [`ByteCode::memoized_bodies`]: crate::byte_code::ByteCode
+//! [`TracingConfig::memoize_calls`]: candy_frontend::TracingConfig
+
+use crate::heap::{Function, Heap, InlineObject};
+use rustc_hash::FxHashMap;
+
+#[derive(Debug, Default)]
+pub struct MemoizationCache {
+    results: FxHashMap<CallKey, InlineObject>,
+    /// Memoized calls that are currently running, together with the length of
+    /// the call stack after they return.
+    running: Vec<(usize, CallKey)>,
+}
+#[derive(Debug, Eq, Hash, PartialEq)]
+struct CallKey {
+    function: Function,
+    arguments: Vec<InlineObject>,
+}
+
+impl MemoizationCache {
+    /// Returns the result of a previous call, adding a reference to it.
+    #[must_use]
+    pub fn get(
+        &self,
+        heap: &mut Heap,
+        function: Function,
+        arguments: &[InlineObject],
+    ) -> Option<InlineObject> {
+        // PERF: Avoid allocating a key just for the lookup.
+        let key = CallKey {
+            function,
+            arguments: arguments.to_vec(),
+        };
+        let result = *self.results.get(&key)?;
+        result.dup(heap);
+        Some(result)
+    }
+
+    /// Records that a memoized call started. Once the call stack shrinks back
+    /// to `call_stack_length`, [`Self::on_return`] stores the result.
+    pub fn on_call(
+        &mut self,
+        heap: &mut Heap,
+        call_stack_length: usize,
+        function: Function,
+        arguments: &[InlineObject],
+    ) {
+        InlineObject::from(function).dup(heap);
+        for argument in arguments {
+            argument.dup(heap);
+        }
+        let key = CallKey {
+            function,
+            arguments: arguments.to_vec(),
+        };
+        self.running.push((call_stack_length, key));
+    }
+    /// Stores `result` for all running calls that just returned.
+    ///
+    /// Tail calls can finish several memoized calls at once, all of which
+    /// return the same value.
+    pub fn on_return(&mut self, heap: &mut Heap, call_stack_length: usize, result: InlineObject) {
+        while let Some((length, _)) = self.running.last()
+            && *length == call_stack_length
+        {
+            let (_, key) = self.running.pop().unwrap();
+            // The entry API would silently discard the key of an existing
+            // entry, but we have to drop its references.
+            #[allow(clippy::map_entry)]
+            if self.results.contains_key(&key) {
+                key.drop(heap);
+            } else {
+                result.dup(heap);
+                self.results.insert(key, result);
+            }
+        }
+    }
+
+    pub fn clear(&mut self, heap: &mut Heap) {
+        for (key, result) in self.results.drain() {
+            key.drop(heap);
+            result.drop(heap);
+        }
+        for (_, key) in self.running.drain(..) {
+            key.drop(heap);
+        }
+    }
+}
+
+impl CallKey {
+    fn drop(self, heap: &mut Heap) {
+        InlineObject::from(self.function).drop(heap);
+        for argument in self.arguments {
+            argument.drop(heap);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MemoizationCache;
+    use crate::heap::{Function, Heap, InlineObject, Text};
+
+    #[test]
+    fn memoized_results_are_reused_and_released() {
+        let mut heap = Heap::default();
+        let objects_before = heap.iter().count();
+
+        let function = Function::create(&mut heap, true, &[], 1, 0.into());
+        let argument: InlineObject = Text::create(&mut heap, true, "argument").into();
+        let result: InlineObject = Text::create(&mut heap, true, "result").into();
+
+        let mut cache = MemoizationCache::default();
+        assert_eq!(cache.get(&mut heap, function, &[argument]), None);
+
+        cache.on_call(&mut heap, 1, function, &[argument]);
+        // A nested call returning doesn't finish the memoized one.
+        cache.on_return(&mut heap, 2, result);
+        assert_eq!(cache.get(&mut heap, function, &[argument]), None);
+        cache.on_return(&mut heap, 1, result);
+
+        let cached = cache.get(&mut heap, function, &[argument]).unwrap();
+        assert_eq!(cached, result);
+        cached.drop(&mut heap);
+
+        cache.clear(&mut heap);
+        InlineObject::from(function).drop(&mut heap);
+        argument.drop(&mut heap);
+        result.drop(&mut heap);
+        assert_eq!(heap.iter().count(), objects_before);
+    }
+}
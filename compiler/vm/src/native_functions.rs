@@ -0,0 +1,136 @@
+//! Host functions that embedders can make available to Candy code.
+//!
+//! Native functions are exposed as handles in the environment struct passed to
+//! the `main` function. Calling one of these handles calls the registered Rust
+//! callback:
+//!
+//! ```ignore
+//! let mut native_functions = NativeFunctions::default();
+//! native_functions.register("Double", 1, |heap, arguments| {
+//!     let value = i64::try_from_heap_value(heap, arguments[0])?;
+//!     Ok((value * 2).into_heap_value(heap))
+//! });
+//! let runtime = Runtime::with_environment(byte_code, |heap| {
+//!     native_functions.into_environment(heap)
+//! });
+//! ```
+//!
+//! In Candy, the function is then available as `environment.double 21`, which
+//! returns `Ok 42`.
+
+use crate::{
+    byte_code::ByteCode,
+    environment::{EmptyEnvironment, Environment},
+    heap::{create_symbol, Handle, Heap, InlineObject, Struct, Tag, Text},
+    tracer::Tracer,
+    vm::VmHandleCall,
+    Vm,
+};
+use rustc_hash::FxHashMap;
+use std::borrow::Borrow;
+
+/// Receives the arguments and returns either a value or an error message.
+/// Arguments are borrowed, the returned value is owned by the VM.
+pub type NativeFunction =
+    Box<dyn FnMut(&mut Heap, &[InlineObject]) -> Result<InlineObject, String>>;
+
+#[derive(Default)]
+pub struct NativeFunctions {
+    functions: Vec<(String, usize, NativeFunction)>,
+}
+impl NativeFunctions {
+    /// Registers a function that's available in the environment under the
+    /// `symbol`, e.g., `Double` for `environment.double`.
+    ///
+    /// Candy code receives `Ok value` if the function succeeds and
+    /// `Error message` otherwise.
+    pub fn register(
+        &mut self,
+        symbol: impl Into<String>,
+        argument_count: usize,
+        function: impl FnMut(&mut Heap, &[InlineObject]) -> Result<InlineObject, String> + 'static,
+    ) -> &mut Self {
+        self.functions
+            .push((symbol.into(), argument_count, Box::new(function)));
+        self
+    }
+
+    /// Creates an environment that only contains the native functions.
+    pub fn into_environment(self, heap: &mut Heap) -> (Struct, NativeFunctionsEnvironment) {
+        let environment_object = Struct::create(heap, true, &FxHashMap::default());
+        self.install(heap, environment_object, EmptyEnvironment)
+    }
+    /// Adds the native functions to an existing environment, e.g., the one
+    /// created by [`DefaultEnvironment::new`]. Calls to other handles are
+    /// forwarded to the `inner` environment.
+    ///
+    /// This takes ownership of the `environment_object` and returns the new
+    /// one containing the native functions.
+    ///
+    /// [`DefaultEnvironment::new`]: crate::environment::DefaultEnvironment::new
+    pub fn install<E: Environment>(
+        self,
+        heap: &mut Heap,
+        environment_object: Struct,
+        inner: E,
+    ) -> (Struct, NativeFunctionsEnvironment<E>) {
+        let mut fields = FxHashMap::default();
+        let mut functions = FxHashMap::default();
+        for (symbol, argument_count, function) in self.functions {
+            let handle = Handle::new(heap, argument_count);
+            let symbol = create_symbol(heap, &symbol);
+            fields.insert(Tag::create(symbol).into(), handle.into());
+            functions.insert(handle, function);
+        }
+        let native_functions_object = Struct::create(heap, true, &fields);
+
+        let merged = environment_object.merge(heap, *native_functions_object);
+        InlineObject::from(environment_object).drop(heap);
+        InlineObject::from(native_functions_object).drop(heap);
+
+        let environment = NativeFunctionsEnvironment { functions, inner };
+        (merged.into(), environment)
+    }
+}
+
+pub struct NativeFunctionsEnvironment<E: Environment = EmptyEnvironment> {
+    functions: FxHashMap<Handle, NativeFunction>,
+    inner: E,
+}
+impl<E: Environment> Environment for NativeFunctionsEnvironment<E> {
+    fn handle<B: Borrow<ByteCode>, T: Tracer>(
+        &mut self,
+        heap: &mut Heap,
+        call: VmHandleCall<B, T>,
+    ) -> Vm<B, T> {
+        let Some(function) = self.functions.get_mut(&call.handle) else {
+            return self.inner.handle(heap, call);
+        };
+
+        let result = function(heap, &call.arguments)
+            .map_err(|message| Text::create(heap, true, &message).into());
+        let result = Tag::create_result(heap, true, result);
+        call.complete(heap, result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NativeFunctions;
+    use crate::heap::{create_symbol, Data, Heap, IntoHeapValue, Tag, TryFromHeapValue};
+
+    #[test]
+    fn functions_are_added_to_the_environment() {
+        let mut heap = Heap::default();
+        let mut native_functions = NativeFunctions::default();
+        native_functions.register("Double", 1, |heap, arguments| {
+            let value = i64::try_from_heap_value(heap, arguments[0])?;
+            Ok((value * 2).into_heap_value(heap))
+        });
+        let (environment_object, _) = native_functions.into_environment(&mut heap);
+
+        let key = Tag::create(create_symbol(&mut heap, "Double"));
+        let handle = environment_object.get(key).unwrap();
+        assert!(matches!(Data::from(handle), Data::Handle(_)));
+    }
+}
@@ -0,0 +1,609 @@
+//! A high-level API for embedding Candy in other Rust programs.
+//!
+//! A [`Runtime`] owns everything needed to run a compiled module: the byte
+//! code, the heap, and the environment that handles calls to handles. After
+//! running the module, you can call its exported functions and convert values
+//! between Rust and Candy using [`Value`].
+//!
+//! The byte code has to be compiled for [`ExecutionTarget::Module`] so that
+//! running it returns the export struct:
+//!
+//! ```ignore
+//! let byte_code = compile_byte_code(&db, ExecutionTarget::Module(module), tracing).0;
+//! let mut runtime = Runtime::new(byte_code);
+//! let sum = runtime.call_exported_function("add", &[Value::from(1), Value::from(2)])?;
+//! ```
+//!
+//! To observe the execution, e.g., to print calls while the program runs, pass
+//! a [`Tracer`] to [`Runtime::with_tracer`]. Stack traces of panics are
+//! recorded regardless.
+//!
+//! To stop programs that run for too long, set [`ExecutionLimits`] using
+//! [`Runtime::with_execution_limits`]. To pause them from another thread, use
+//! [`Runtime::with_pause_signal`].
+//!
+//! [`ExecutionTarget::Module`]: candy_frontend::hir_to_mir::ExecutionTarget::Module
+
+use crate::{
+    byte_code::ByteCode,
+    environment::{EmptyEnvironment, Environment, StateAfterRunWithoutHandles},
+    execution_controller::{
+        ExecutionController, PauseSignal, PauseSignalController, RunLimitedNumberOfInstructions,
+        WallClockController,
+    },
+    heap::{
+        create_symbol, Bytes, Data, Float, Function, Heap, HirId, InlineObject, Int, IntoHeapValue,
+        LeakReport, List, Struct, Tag, Text, TryFromHeapValue,
+    },
+    tracer::{stack_trace::StackTracer, DummyTracer, Tracer},
+    Panic, Vm, VmFinished, DEFAULT_MAX_CALL_DEPTH,
+};
+use candy_frontend::hir::Id;
+use itertools::Itertools;
+use num_bigint::BigInt;
+use ordered_float::OrderedFloat;
+use rustc_hash::FxHashMap;
+use std::{
+    fmt::{self, Display, Formatter},
+    rc::Rc,
+    time::Duration,
+};
+
+pub struct Runtime<E: Environment = EmptyEnvironment, T: Tracer = DummyTracer> {
+    byte_code: Rc<ByteCode>,
+    heap: Heap,
+    environment: E,
+    /// The struct passed to the `main` function.
+    environment_object: Struct,
+    /// The struct returned by the module, or `None` if it didn't run yet.
+    exports: Option<Struct>,
+    tracer: T,
+    max_call_depth: usize,
+    budget: ExecutionBudget,
+}
+
+/// Limits the execution of all code run by a [`Runtime`] together, i.e.,
+/// running the module and calling its functions.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ExecutionLimits {
+    pub max_instructions: Option<usize>,
+    pub timeout: Option<Duration>,
+}
+impl ExecutionLimits {
+    #[must_use]
+    pub const fn is_unlimited(&self) -> bool {
+        self.max_instructions.is_none() && self.timeout.is_none()
+    }
+}
+
+/// How much of the [`ExecutionLimits`] all VMs of a runtime used so far.
+#[derive(Default)]
+struct ExecutionBudget {
+    limits: ExecutionLimits,
+    instructions: Option<RunLimitedNumberOfInstructions>,
+    /// Started when the first VM starts running.
+    wall_clock: Option<WallClockController>,
+    pause_signal: Option<PauseSignal>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ExecutionLimit {
+    Instructions(usize),
+    Timeout(Duration),
+}
+impl Display for ExecutionLimit {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Instructions(max_instructions) => {
+                write!(f, "the limit of {max_instructions} instructions")
+            }
+            Self::Timeout(timeout) => write!(f, "the timeout of {timeout:?}"),
+        }
+    }
+}
+
+impl Runtime<EmptyEnvironment> {
+    /// Creates a runtime without any environment services, i.e., Candy code
+    /// panics when it calls a handle.
+    #[must_use]
+    pub fn new(byte_code: ByteCode) -> Self {
+        Self::with_environment(byte_code, |heap| {
+            let environment_object = Struct::create(heap, true, &FxHashMap::default());
+            (environment_object, EmptyEnvironment)
+        })
+    }
+}
+impl<E: Environment> Runtime<E> {
+    /// Creates a runtime with custom environment services.
+    ///
+    /// The `create_environment` function receives the runtime's heap and
+    /// returns the environment struct passed to the `main` function (usually
+    /// containing handles) together with the [`Environment`] that handles
+    /// calls to these handles, e.g., [`DefaultEnvironment::new`].
+    ///
+    /// [`DefaultEnvironment::new`]: crate::environment::DefaultEnvironment::new
+    pub fn with_environment(
+        byte_code: ByteCode,
+        create_environment: impl FnOnce(&mut Heap) -> (Struct, E),
+    ) -> Self {
+        let mut heap = Heap::default();
+        let (environment_object, environment) = create_environment(&mut heap);
+        Self {
+            byte_code: Rc::new(byte_code),
+            heap,
+            environment,
+            environment_object,
+            exports: None,
+            tracer: DummyTracer,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            budget: ExecutionBudget::default(),
+        }
+    }
+
+    /// Replaces the runtime's tracer, which is notified about everything the
+    /// VM does in addition to the internal stack tracer.
+    #[must_use]
+    pub fn with_tracer<T: Tracer>(self, tracer: T) -> Runtime<E, T> {
+        Runtime {
+            byte_code: self.byte_code,
+            heap: self.heap,
+            environment: self.environment,
+            environment_object: self.environment_object,
+            exports: self.exports,
+            tracer,
+            max_call_depth: self.max_call_depth,
+            budget: self.budget,
+        }
+    }
+}
+impl<E: Environment, T: Tracer> Runtime<E, T> {
+    #[must_use]
+    pub const fn heap(&self) -> &Heap {
+        &self.heap
+    }
+    pub fn heap_mut(&mut self) -> &mut Heap {
+        &mut self.heap
+    }
+    #[must_use]
+    pub const fn environment(&self) -> &E {
+        &self.environment
+    }
+    #[must_use]
+    pub const fn tracer(&self) -> &T {
+        &self.tracer
+    }
+    pub fn tracer_mut(&mut self) -> &mut T {
+        &mut self.tracer
+    }
+
+    /// Limits how deeply calls can be nested in the code run by this runtime.
+    /// Exceeding the limit panics with a stack overflow. Defaults to
+    /// [`DEFAULT_MAX_CALL_DEPTH`].
+    #[must_use]
+    pub const fn with_max_call_depth(mut self, max_call_depth: usize) -> Self {
+        self.max_call_depth = max_call_depth;
+        self
+    }
+
+    /// Stops the code run by this runtime once it exceeds the `limits`, which
+    /// results in [`RuntimeError::LimitExceeded`].
+    #[must_use]
+    pub const fn with_execution_limits(mut self, limits: ExecutionLimits) -> Self {
+        self.budget.instructions = match limits.max_instructions {
+            Some(max_instructions) => Some(RunLimitedNumberOfInstructions::new(max_instructions)),
+            None => None,
+        };
+        self.budget.limits = limits;
+        self
+    }
+
+    /// While the `pause_signal` is paused, running code blocks the current
+    /// thread. Time spent paused counts towards the timeout of the
+    /// [`ExecutionLimits`].
+    #[must_use]
+    pub fn with_pause_signal(mut self, pause_signal: PauseSignal) -> Self {
+        self.budget.pause_signal = Some(pause_signal);
+        self
+    }
+
+    /// Runs the module (unless it already ran) and returns its export struct.
+    pub fn run_module(&mut self) -> Result<Struct, RuntimeError> {
+        if let Some(exports) = self.exports {
+            return Ok(exports);
+        }
+
+        let vm = Vm::for_module(
+            self.byte_code.clone(),
+            &mut self.heap,
+            (StackTracer::default(), &mut self.tracer),
+        )
+        .with_max_call_depth(self.max_call_depth);
+        let exports = Self::run_vm(vm, &mut self.heap, &mut self.environment, &mut self.budget)?;
+        let exports = Struct::try_from(exports).map_err(RuntimeError::NotConvertible)?;
+        self.exports = Some(exports);
+        Ok(exports)
+    }
+
+    /// Calls the exported `main` function with the environment struct.
+    ///
+    /// The returned object is owned by the caller.
+    pub fn run_main(&mut self) -> Result<InlineObject, RuntimeError> {
+        let environment_object: InlineObject = self.environment_object.into();
+        environment_object.dup(&mut self.heap);
+        self.call_exported_function_with_objects("main", &[environment_object])
+    }
+
+    /// Calls the exported function with the given `name`, e.g., `foo` for a
+    /// definition `foo a b := …`.
+    ///
+    /// The arguments and the return value are converted from and to Rust
+    /// values, so this doesn't work for functions returning functions or
+    /// handles.
+    pub fn call_exported_function(
+        &mut self,
+        name: &str,
+        arguments: &[Value],
+    ) -> Result<Value, RuntimeError> {
+        let arguments = arguments
+            .iter()
+            .map(|it| it.to_heap(&mut self.heap))
+            .collect_vec();
+        let return_value = self.call_exported_function_with_objects(name, &arguments)?;
+        let result = Value::from_heap(return_value).map_err(RuntimeError::NotConvertible);
+        return_value.drop(&mut self.heap);
+        result
+    }
+    /// Like [`Self::call_exported_function`], but works with objects on the
+    /// runtime's heap directly.
+    ///
+    /// The runtime takes ownership of the `arguments` and the returned object
+    /// is owned by the caller.
+    pub fn call_exported_function_with_objects(
+        &mut self,
+        name: &str,
+        arguments: &[InlineObject],
+    ) -> Result<InlineObject, RuntimeError> {
+        let function = self.exported_function(name)?;
+        self.call_function(function, arguments)
+    }
+    /// Calls a function living on the runtime's heap, e.g., one that was
+    /// returned from an exported function.
+    ///
+    /// The runtime takes ownership of the `function` and the `arguments`, and
+    /// the returned object is owned by the caller.
+    pub fn call_function(
+        &mut self,
+        function: Function,
+        arguments: &[InlineObject],
+    ) -> Result<InlineObject, RuntimeError> {
+        let responsible = HirId::create(&mut self.heap, true, Id::user());
+        let vm = Vm::for_function(
+            self.byte_code.clone(),
+            &mut self.heap,
+            function,
+            arguments,
+            responsible,
+            (StackTracer::default(), &mut self.tracer),
+        )
+        .with_max_call_depth(self.max_call_depth);
+        Self::run_vm(vm, &mut self.heap, &mut self.environment, &mut self.budget)
+    }
+
+    /// Drops everything the runtime owns except for the heap and reports
+    /// objects and handles that are still alive afterwards.
+    ///
+    /// Objects returned by the runtime have to be dropped before, otherwise
+    /// they show up in the report.
+    #[must_use]
+    pub fn tear_down(mut self) -> LeakReport {
+        if let Some(exports) = self.exports.take() {
+            InlineObject::from(exports).drop(&mut self.heap);
+        }
+        InlineObject::from(self.environment_object).drop(&mut self.heap);
+        self.heap.leak_report(Some(&self.byte_code))
+    }
+
+    /// Returns the value exported with the given `name`, e.g., `foo` for a
+    /// definition `foo := …`.
+    ///
+    /// The export struct keeps its reference, so the returned object is only
+    /// valid as long as the runtime lives.
+    pub fn exported_value(&mut self, name: &str) -> Result<InlineObject, RuntimeError> {
+        let exports = self.run_module()?;
+        exports
+            .iter()
+            .find(|(_, key, _)| {
+                Tag::try_from(*key).is_ok_and(|key| key.symbol().get() == export_symbol(name))
+            })
+            .map(|(_, _, value)| value)
+            .ok_or_else(|| RuntimeError::UnknownExport(name.to_string()))
+    }
+    fn exported_function(&mut self, name: &str) -> Result<Function, RuntimeError> {
+        let function = self.exported_value(name)?;
+        let function = Function::try_from(function).map_err(RuntimeError::NotConvertible)?;
+        // The export struct keeps its reference.
+        InlineObject::from(function).dup(&mut self.heap);
+        Ok(function)
+    }
+    fn run_vm(
+        mut vm: Vm<Rc<ByteCode>, (StackTracer, &mut T)>,
+        heap: &mut Heap,
+        environment: &mut E,
+        budget: &mut ExecutionBudget,
+    ) -> Result<InlineObject, RuntimeError> {
+        if budget.limits.is_unlimited() && budget.pause_signal.is_none() {
+            return Self::finish(vm.run_forever_with_environment(heap, environment));
+        }
+
+        if budget.wall_clock.is_none() {
+            budget.wall_clock = budget.limits.timeout.map(WallClockController::new);
+        }
+        loop {
+            let mut controller = (&mut budget.instructions)
+                .and(&mut budget.wall_clock)
+                .and(budget.pause_signal.clone().map(PauseSignalController::new));
+            vm = match vm.run_with_controller(heap, environment, &mut controller) {
+                StateAfterRunWithoutHandles::Running(vm) => vm,
+                StateAfterRunWithoutHandles::Finished(finished) => return Self::finish(finished),
+            };
+
+            if let Some(max_instructions) = budget.limits.max_instructions
+                && budget
+                    .instructions
+                    .as_ref()
+                    .is_some_and(|it| it.remaining_instructions() == 0)
+            {
+                return Err(Self::limit_exceeded(
+                    vm,
+                    ExecutionLimit::Instructions(max_instructions),
+                ));
+            }
+            if let Some(timeout) = budget.limits.timeout
+                && budget
+                    .wall_clock
+                    .as_ref()
+                    .is_some_and(WallClockController::is_timed_out)
+            {
+                return Err(Self::limit_exceeded(vm, ExecutionLimit::Timeout(timeout)));
+            }
+            if let Some(pause_signal) = &budget.pause_signal {
+                pause_signal.wait_until_resumed();
+            }
+        }
+    }
+    fn limit_exceeded(
+        vm: Vm<Rc<ByteCode>, (StackTracer, &mut T)>,
+        limit: ExecutionLimit,
+    ) -> RuntimeError {
+        let (stack_tracer, _) = vm.into_tracer();
+        RuntimeError::LimitExceeded {
+            limit,
+            stack_tracer: Box::new(stack_tracer),
+        }
+    }
+    fn finish(finished: VmFinished<(StackTracer, &mut T)>) -> Result<InlineObject, RuntimeError> {
+        let VmFinished {
+            tracer: (stack_tracer, _),
+            result,
+        } = finished;
+        result.map_err(|panic| RuntimeError::Panicked {
+            panic: Box::new(panic),
+            stack_tracer: Box::new(stack_tracer),
+        })
+    }
+}
+
+/// Exported definitions are stored under capitalized symbols, e.g., `foo` is
+/// exported as `Foo`.
+fn export_symbol(name: &str) -> String {
+    let mut chars = name.chars();
+    chars.next().map_or_else(String::new, |first| {
+        first.to_uppercase().chain(chars).collect()
+    })
+}
+
+#[derive(Debug)]
+pub enum RuntimeError {
+    /// The module doesn't export anything with this name.
+    UnknownExport(String),
+    Panicked {
+        panic: Box<Panic>,
+        stack_tracer: Box<StackTracer>,
+    },
+    /// A value had an unexpected type or couldn't be converted to a [`Value`].
+    NotConvertible(&'static str),
+    /// The code ran longer than allowed by the runtime's [`ExecutionLimits`].
+    /// The stack trace shows where the execution was stopped.
+    LimitExceeded {
+        limit: ExecutionLimit,
+        stack_tracer: Box<StackTracer>,
+    },
+}
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownExport(name) => write!(f, "The module doesn't export `{name}`."),
+            Self::Panicked { panic, .. } => {
+                write!(
+                    f,
+                    "The code panicked: {} ({} is responsible)",
+                    panic.reason, panic.responsible
+                )
+            }
+            Self::NotConvertible(reason) => write!(f, "{reason}"),
+            Self::LimitExceeded { limit, .. } => {
+                write!(f, "The code was stopped because it exceeded {limit}.")
+            }
+        }
+    }
+}
+
+/// A Candy value that lives in Rust land, independent of any heap.
+///
+/// Functions, builtins, and handles can't be represented since they only make
+/// sense in the context of their heap and byte code.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Value {
+    Int(BigInt),
+    Float(OrderedFloat<f64>),
+    Tag {
+        symbol: String,
+        value: Option<Box<Value>>,
+    },
+    Text(String),
+    Bytes(Vec<u8>),
+    List(Vec<Value>),
+    Struct(Vec<(Value, Value)>),
+}
+impl Value {
+    #[must_use]
+    pub fn tag(symbol: impl Into<String>) -> Self {
+        Self::Tag {
+            symbol: symbol.into(),
+            value: None,
+        }
+    }
+    #[must_use]
+    pub fn tag_with_value(symbol: impl Into<String>, value: Self) -> Self {
+        Self::Tag {
+            symbol: symbol.into(),
+            value: Some(Box::new(value)),
+        }
+    }
+
+    /// Creates the value on the heap. The returned object is owned by the
+    /// caller.
+    pub fn to_heap(&self, heap: &mut Heap) -> InlineObject {
+        match self {
+            Self::Int(int) => Int::create_from_bigint(heap, true, int.clone()).into(),
+            Self::Float(float) => Float::create(heap, true, **float).into(),
+            Self::Tag { symbol, value } => {
+                let symbol = create_symbol(heap, symbol);
+                let value = value.as_ref().map(|it| it.to_heap(heap));
+                Tag::create_with_value_option(heap, true, symbol, value).into()
+            }
+            Self::Text(text) => Text::create(heap, true, text).into(),
+            Self::Bytes(bytes) => Bytes::create(heap, true, bytes).into(),
+            Self::List(items) => {
+                let items = items.iter().map(|it| it.to_heap(heap)).collect_vec();
+                List::create(heap, true, &items).into()
+            }
+            Self::Struct(fields) => {
+                let fields = fields
+                    .iter()
+                    .map(|(key, value)| (key.to_heap(heap), value.to_heap(heap)))
+                    .collect();
+                Struct::create(heap, true, &fields).into()
+            }
+        }
+    }
+    /// Copies the object from the heap. This doesn't change the object's
+    /// reference count.
+    pub fn from_heap(object: InlineObject) -> Result<Self, &'static str> {
+        let value = match Data::from(object) {
+            Data::Int(int) => Self::Int(int.get().into_owned()),
+            Data::Float(float) => Self::Float(float.get()),
+            Data::Tag(tag) => Self::Tag {
+                symbol: tag.symbol().get().to_string(),
+                value: tag
+                    .value()
+                    .map(|it| Self::from_heap(it).map(Box::new))
+                    .transpose()?,
+            },
+            Data::Text(text) => Self::Text(text.get().to_string()),
+            Data::Bytes(bytes) => Self::Bytes(bytes.get().to_vec()),
+            Data::List(list) => Self::List(
+                list.items()
+                    .iter()
+                    .map(|it| Self::from_heap(*it))
+                    .try_collect()?,
+            ),
+            Data::Struct(struct_) => Self::Struct(
+                struct_
+                    .iter()
+                    .map(|(_, key, value)| {
+                        Ok::<_, &'static str>((Self::from_heap(key)?, Self::from_heap(value)?))
+                    })
+                    .try_collect()?,
+            ),
+            Data::HirId(_) | Data::Function(_) | Data::Builtin(_) | Data::Handle(_) => {
+                return Err(
+                    "Functions, builtins, handles, and HIR IDs can't be converted to values.",
+                );
+            }
+        };
+        Ok(value)
+    }
+}
+impl IntoHeapValue for Value {
+    fn into_heap_value(self, heap: &mut Heap) -> InlineObject {
+        self.to_heap(heap)
+    }
+}
+impl TryFromHeapValue for Value {
+    fn try_from_heap_value(_heap: &Heap, object: InlineObject) -> Result<Self, &'static str> {
+        Self::from_heap(object)
+    }
+}
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Self::Int(value.into())
+    }
+}
+impl From<BigInt> for Value {
+    fn from(value: BigInt) -> Self {
+        Self::Int(value)
+    }
+}
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Self::Float(value.into())
+    }
+}
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Self::tag(if value { "True" } else { "False" })
+    }
+}
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Self::Text(value.to_string())
+    }
+}
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Self::Text(value)
+    }
+}
+impl From<Vec<Self>> for Value {
+    fn from(value: Vec<Self>) -> Self {
+        Self::List(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Value;
+    use crate::heap::Heap;
+    use num_bigint::BigInt;
+
+    #[test]
+    fn value_round_trip() {
+        let mut heap = Heap::default();
+        let values = [
+            Value::from(42),
+            Value::from(BigInt::from(i64::MAX) * 4),
+            Value::from(1.5),
+            Value::from(true),
+            Value::tag_with_value("Ok", Value::from("Hello")),
+            Value::Bytes(vec![1, 2, 3]),
+            Value::from(vec![Value::from(1), Value::tag("Foo")]),
+            Value::Struct(vec![(Value::tag("Name"), Value::from("Candy"))]),
+        ];
+        for value in values {
+            let object = value.to_heap(&mut heap);
+            assert_eq!(Value::from_heap(object), Ok(value));
+            object.drop(&mut heap);
+        }
+    }
+}
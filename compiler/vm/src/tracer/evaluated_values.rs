@@ -21,6 +21,18 @@ impl EvaluatedValuesTracer {
     pub const fn values(&self) -> &FxHashMap<Id, InlineObject> {
         &self.evaluated_values
     }
+
+    /// Removes the values of expressions whose ID doesn't satisfy the
+    /// `predicate`.
+    pub fn retain(&mut self, heap: &mut Heap, mut predicate: impl FnMut(&Id) -> bool) {
+        self.evaluated_values.retain(|id, value| {
+            let keep = predicate(id);
+            if !keep {
+                value.drop(heap);
+            }
+            keep
+        });
+    }
 }
 impl Tracer for EvaluatedValuesTracer {
     fn value_evaluated(&mut self, heap: &mut Heap, expression: HirId, value: InlineObject) {
@@ -1,5 +1,8 @@
 pub use self::dummy::DummyTracer;
-use crate::heap::{Function, Heap, HirId, InlineObject};
+use crate::{
+    heap::{Function, Heap, HirId, InlineObject},
+    vm::Panic,
+};
 
 mod dummy;
 pub mod evaluated_values;
@@ -36,4 +39,110 @@ pub trait Tracer {
         _responsible: HirId,
     ) {
     }
+    /// A function called itself as its last action and the VM reused the
+    /// current frame instead of performing an actual call.
+    fn tail_call_optimized(&mut self, _heap: &mut Heap, _function: Function) {}
+
+    /// The VM panicked or a panic was caught by `✨.functionTry`.
+    ///
+    /// When a panic is caught, the calls that didn't finish because of it are
+    /// ended afterwards using [`Tracer::call_ended`] without a return value.
+    fn panicked(&mut self, _heap: &mut Heap, _panic: &Panic) {}
+}
+
+/// Only traces if a tracer is present.
+impl<T: Tracer> Tracer for Option<T> {
+    fn value_evaluated(&mut self, heap: &mut Heap, expression: HirId, value: InlineObject) {
+        if let Some(tracer) = self {
+            tracer.value_evaluated(heap, expression, value);
+        }
+    }
+
+    fn found_fuzzable_function(&mut self, heap: &mut Heap, definition: HirId, function: Function) {
+        if let Some(tracer) = self {
+            tracer.found_fuzzable_function(heap, definition, function);
+        }
+    }
+
+    fn call_started(
+        &mut self,
+        heap: &mut Heap,
+        call_site: HirId,
+        callee: InlineObject,
+        arguments: Vec<InlineObject>,
+        responsible: HirId,
+    ) {
+        if let Some(tracer) = self {
+            tracer.call_started(heap, call_site, callee, arguments, responsible);
+        }
+    }
+    fn call_ended(&mut self, heap: &mut Heap, return_value: Option<InlineObject>) {
+        if let Some(tracer) = self {
+            tracer.call_ended(heap, return_value);
+        }
+    }
+    fn tail_call(
+        &mut self,
+        heap: &mut Heap,
+        call_site: HirId,
+        callee: InlineObject,
+        arguments: Vec<InlineObject>,
+        responsible: HirId,
+    ) {
+        if let Some(tracer) = self {
+            tracer.tail_call(heap, call_site, callee, arguments, responsible);
+        }
+    }
+    fn tail_call_optimized(&mut self, heap: &mut Heap, function: Function) {
+        if let Some(tracer) = self {
+            tracer.tail_call_optimized(heap, function);
+        }
+    }
+
+    fn panicked(&mut self, heap: &mut Heap, panic: &Panic) {
+        if let Some(tracer) = self {
+            tracer.panicked(heap, panic);
+        }
+    }
+}
+
+impl<T: Tracer + ?Sized> Tracer for &mut T {
+    fn value_evaluated(&mut self, heap: &mut Heap, expression: HirId, value: InlineObject) {
+        (**self).value_evaluated(heap, expression, value);
+    }
+
+    fn found_fuzzable_function(&mut self, heap: &mut Heap, definition: HirId, function: Function) {
+        (**self).found_fuzzable_function(heap, definition, function);
+    }
+
+    fn call_started(
+        &mut self,
+        heap: &mut Heap,
+        call_site: HirId,
+        callee: InlineObject,
+        arguments: Vec<InlineObject>,
+        responsible: HirId,
+    ) {
+        (**self).call_started(heap, call_site, callee, arguments, responsible);
+    }
+    fn call_ended(&mut self, heap: &mut Heap, return_value: Option<InlineObject>) {
+        (**self).call_ended(heap, return_value);
+    }
+    fn tail_call(
+        &mut self,
+        heap: &mut Heap,
+        call_site: HirId,
+        callee: InlineObject,
+        arguments: Vec<InlineObject>,
+        responsible: HirId,
+    ) {
+        (**self).tail_call(heap, call_site, callee, arguments, responsible);
+    }
+    fn tail_call_optimized(&mut self, heap: &mut Heap, function: Function) {
+        (**self).tail_call_optimized(heap, function);
+    }
+
+    fn panicked(&mut self, heap: &mut Heap, panic: &Panic) {
+        (**self).panicked(heap, panic);
+    }
 }
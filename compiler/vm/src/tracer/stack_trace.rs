@@ -4,12 +4,13 @@ use candy_frontend::{
     ast_to_hir::AstToHir,
     cst::CstKind,
     format::{MaxLength, Precedence},
+    hir,
     module::PackagesPath,
-    position::{PositionConversionDb, RangeOfPosition},
+    position::{Offset, PositionConversionDb, RangeOfPosition},
 };
 use itertools::Itertools;
 use pad::PadStr;
-use std::{env::current_dir, path::Path};
+use std::{env::current_dir, ops::Range, path::Path};
 
 #[derive(Debug, Default)]
 pub struct StackTracer {
@@ -89,41 +90,155 @@ impl Tracer for StackTracer {
             responsible,
         };
         call.dup(heap);
-        self.call_stack.last_mut().unwrap().push(call);
+
+        // Recursion using tail calls (e.g., loops implemented using
+        // `recursive`) would otherwise grow the stack trace indefinitely. When
+        // the callee is already part of the current frame, we drop the calls
+        // in between, similar to how the VM reuses the frame.
+        let calls = self.call_stack.last_mut().unwrap();
+        if let Some(index) = calls
+            .iter()
+            .position(|it| is_same_callee(it.callee, callee))
+        {
+            for call in calls.drain(index..) {
+                call.drop(heap);
+            }
+        }
+        calls.push(call);
     }
 }
 
 impl StackTracer {
-    pub fn format<DB>(&self, db: &DB, packages_path: &PackagesPath) -> String
+    /// Stack traces containing more calls (e.g., after a stack overflow) only
+    /// show the innermost and outermost calls.
+    const MAX_FORMATTED_CALLS: usize = 64;
+
+    /// Formats the stack trace with the innermost call first.
+    ///
+    /// Each call is followed by the line of source code containing it. With
+    /// `include_arguments`, calls also show the argument values they received.
+    pub fn format<DB>(
+        &self,
+        db: &DB,
+        packages_path: &PackagesPath,
+        include_arguments: bool,
+    ) -> String
     where
         DB: AstToHir + PositionConversionDb,
     {
         let current_package_path = current_dir().ok(); // current_package.to_path(packages_path).unwrap();
-        let caller_locations_and_calls = self
-            .call_stack
+        let calls = self.call_stack.iter().flatten().rev().collect_vec();
+        let omitted_calls = calls.len().saturating_sub(Self::MAX_FORMATTED_CALLS);
+        let kept_at_start = Self::MAX_FORMATTED_CALLS / 2;
+        let formatted_calls = calls[..kept_at_start.min(calls.len())]
             .iter()
-            .flatten()
-            .rev()
-            .map(|it| Self::format_call(db, packages_path, current_package_path.as_deref(), it))
+            .chain(&calls[(kept_at_start + omitted_calls).min(calls.len())..])
+            .map(|it| {
+                let (location, call) = Self::format_call(
+                    db,
+                    packages_path,
+                    current_package_path.as_deref(),
+                    it,
+                    include_arguments.then_some(MaxLength::Unlimited),
+                );
+                (
+                    location,
+                    call,
+                    Self::format_code_frame(db, it.call_site.get()),
+                )
+            })
             .collect_vec();
 
-        let longest_location = caller_locations_and_calls
+        let longest_location = formatted_calls
             .iter()
-            .map(|(location, _)| location.len())
+            .map(|(location, _, _)| location.len())
             .max()
             .unwrap_or_default();
 
-        caller_locations_and_calls
+        let mut entries = formatted_calls
             .into_iter()
-            .map(|(location, call)| format!("{} {}", location.pad_to_width(longest_location), call))
-            .join("\n")
+            .map(|(location, call, code_frame)| {
+                let mut entry = format!("{} {}", location.pad_to_width(longest_location), call);
+                if let Some(code_frame) = code_frame {
+                    entry.push('\n');
+                    entry.push_str(&code_frame);
+                }
+                entry
+            })
+            .collect_vec();
+        if omitted_calls > 0 {
+            entries.insert(kept_at_start, format!("… {omitted_calls} more calls …"));
+        }
+        entries.join("\n")
+    }
+
+    /// Explains who's responsible for a panic by listing the calls from the
+    /// one that panicked up to the `responsible` code, innermost first.
+    ///
+    /// Usually, the innermost call is a `needs` that wasn't satisfied and the
+    /// responsible code is the call that passed the faulty value. The calls in
+    /// between pass on the responsibility, e.g., because they're in functions
+    /// with curly braces. Each hop is followed by its line of source code.
+    pub fn format_responsibility_chain<DB>(
+        &self,
+        db: &DB,
+        packages_path: &PackagesPath,
+        responsible: &hir::Id,
+    ) -> String
+    where
+        DB: AstToHir + PositionConversionDb,
+    {
+        let current_directory = current_dir().ok();
+        let calls = self.call_stack.iter().flatten().rev().collect_vec();
+        let responsible_index = calls
+            .iter()
+            .position(|call| call.call_site.get() == responsible);
+
+        let mut hops = calls[..responsible_index.map_or(calls.len().min(1), |index| index + 1)]
+            .iter()
+            .enumerate()
+            .map(|(index, call)| {
+                let (location, call_string) =
+                    Self::format_call(db, packages_path, current_directory.as_deref(), call, None);
+                let mut hop = if index == 0 {
+                    format!("The program panicked in `{call_string}` at {location}.")
+                } else {
+                    format!("That's part of the call of `{call_string}` at {location}.")
+                };
+                if Some(index) == responsible_index {
+                    hop.push_str(" This call is responsible for the panic.");
+                } else if index > 0 {
+                    hop.push_str(" It passes on the responsibility.");
+                }
+                if let Some(code_frame) = Self::format_code_frame(db, call.call_site.get()) {
+                    hop.push('\n');
+                    hop.push_str(&code_frame);
+                }
+                hop
+            })
+            .collect_vec();
+        if responsible_index.is_none() {
+            // The responsible code isn't a call on the stack, e.g., if a
+            // `needs` at the top level of a module failed.
+            let mut hop = format!("{responsible} is responsible for the panic.");
+            if let Some(code_frame) = Self::format_code_frame(db, responsible) {
+                hop.push('\n');
+                hop.push_str(&code_frame);
+            }
+            hops.push(hop);
+        }
+        hops.join("\n")
     }
 
-    fn format_call<DB>(
+    /// Formats a single call, returning the caller's location and the call
+    /// itself. Each argument is shortened to `max_length`. Without a
+    /// `max_length`, only the callee is shown.
+    pub fn format_call<DB>(
         db: &DB,
         packages_path: &PackagesPath,
         current_directory: Option<&Path>,
         call: &Call,
+        max_length: Option<MaxLength>,
     ) -> (String, String)
     where
         DB: AstToHir + PositionConversionDb,
@@ -143,11 +258,8 @@ impl StackTracer {
             db.hir_to_cst_id(hir_id)
         };
 
-        let span_string = cst_id.map(|id| {
-            let cst = db.find_cst(module.clone(), id);
-            db.range_to_positions(module.clone(), cst.data.span)
-                .format()
-        });
+        let span_string = Self::call_site_span(db, hir_id)
+            .map(|span| db.range_to_positions(module.clone(), span).format());
         #[allow(clippy::map_unwrap_or)]
         let caller_location_string = hir_id
             .module
@@ -171,31 +283,113 @@ impl StackTracer {
                     .unwrap_or_else(|| hir_id.to_string())
             });
 
-        let call_string = format!(
-            "{} {}",
-            cst_id
-                .and_then(|id| {
-                    let cst = db.find_cst(hir_id.module.clone(), id);
-                    match cst.kind {
-                        CstKind::Call { receiver, .. } => extract_receiver_name(&receiver),
-                        _ => None,
-                    }
-                })
-                .unwrap_or_else(|| callee.to_string()),
-            arguments
-                .iter()
-                .map(|it| {
-                    if let Data::HirId(id) = (*it).into() {
-                        // Only occurs for `needs` calls.
-                        id.to_string()
-                    } else {
-                        it.to_debug_text(Precedence::High, MaxLength::Unlimited)
-                    }
-                })
-                .join(" "),
-        );
+        let callee_string = cst_id
+            .and_then(|id| {
+                let cst = db.find_cst(hir_id.module.clone(), id);
+                match cst.kind {
+                    CstKind::Call { receiver, .. } => extract_receiver_name(&receiver),
+                    _ => None,
+                }
+            })
+            .unwrap_or_else(|| callee.to_string());
+        let call_string = match max_length {
+            Some(max_length) => format!(
+                "{callee_string} {}",
+                Self::format_arguments(arguments, max_length),
+            ),
+            None => callee_string,
+        };
         (caller_location_string, call_string)
     }
+
+    fn call_site_span<DB>(db: &DB, hir_id: &hir::Id) -> Option<Range<Offset>>
+    where
+        DB: AstToHir + PositionConversionDb,
+    {
+        if hir_id.module.package.is_tooling() {
+            return None;
+        }
+        // Call sites generated by the compiler don't have a CST, so we fall
+        // back to the closest surrounding code.
+        db.hir_to_cst_id(hir_id).map_or_else(
+            || db.hir_id_to_best_effort_span(hir_id),
+            |id| Some(db.find_cst(hir_id.module.clone(), id).data.span),
+        )
+    }
+
+    /// The source line containing the call site with the call site marked
+    /// below it:
+    ///
+    /// ```text
+    ///     2 | f a = int.add a "x"
+    ///       |       ^^^^^^^^^^^^^
+    /// ```
+    fn format_code_frame<DB>(db: &DB, hir_id: &hir::Id) -> Option<String>
+    where
+        DB: AstToHir + PositionConversionDb,
+    {
+        let span = Self::call_site_span(db, hir_id)?;
+        let source = db.get_module_content_as_string(hir_id.module.clone())?;
+        let range = db.range_to_positions(hir_id.module.clone(), span.clone());
+        let line_start = *db
+            .line_index(hir_id.module.clone())
+            .line_start(range.start.line);
+        let line = source[line_start..]
+            .split('\n')
+            .next()
+            .unwrap_or_default()
+            .trim_end_matches('\r');
+
+        let marker_start = (*span.start - line_start).min(line.len());
+        let marker_end = (*span.end - line_start).clamp(marker_start, line.len());
+        let line_number = (range.start.line + 1).to_string();
+        let gutter = " ".repeat(line_number.len());
+        Some(format!(
+            "    {line_number} | {line}\n    {gutter} | {}{}",
+            " ".repeat(line[..marker_start].chars().count()),
+            "^".repeat(line[marker_start..marker_end].chars().count().max(1)),
+        ))
+    }
+
+    /// Formats each call without looking up source positions, so call sites
+    /// are identified by their HIR IDs. The innermost call comes first.
+    #[must_use]
+    pub fn format_calls_without_positions(&self) -> Vec<String> {
+        self.call_stack
+            .iter()
+            .flatten()
+            .rev()
+            .map(|call| {
+                format!(
+                    "{} {} {}",
+                    call.call_site.get(),
+                    call.callee,
+                    Self::format_arguments(&call.arguments, MaxLength::Unlimited),
+                )
+            })
+            .collect()
+    }
+
+    fn format_arguments(arguments: &[InlineObject], max_length: MaxLength) -> String {
+        arguments
+            .iter()
+            .map(|it| {
+                if let Data::HirId(id) = (*it).into() {
+                    // Only occurs for `needs` calls.
+                    id.to_string()
+                } else {
+                    it.to_debug_text(Precedence::High, max_length)
+                }
+            })
+            .join(" ")
+    }
+}
+
+fn is_same_callee(a: InlineObject, b: InlineObject) -> bool {
+    match (a.into(), b.into()) {
+        (Data::Function(a), Data::Function(b)) => a.body() == b.body(),
+        _ => a == b,
+    }
 }
 
 fn extract_receiver_name(cst_kind: &CstKind) -> Option<String> {
@@ -1,5 +1,8 @@
 use super::Tracer;
-use crate::heap::{Function, Heap, HirId, InlineObject};
+use crate::{
+    heap::{Function, Heap, HirId, InlineObject},
+    vm::Panic,
+};
 use impl_trait_for_tuples::impl_for_tuples;
 
 #[impl_for_tuples(2, 3)]
@@ -37,4 +40,10 @@ impl Tracer for Tuple {
     ) {
         for_tuples!( #(Tuple.tail_call(heap, call_site, callee, arguments.clone(), responsible);)* );
     }
+    fn tail_call_optimized(&mut self, heap: &mut Heap, function: Function) {
+        for_tuples!( #(Tuple.tail_call_optimized(heap, function);)* );
+    }
+    fn panicked(&mut self, heap: &mut Heap, panic: &Panic) {
+        for_tuples!( #(Tuple.panicked(heap, panic);)* );
+    }
 }
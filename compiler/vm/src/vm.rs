@@ -1,14 +1,22 @@
 use crate::{
+    builtin_functions::BuiltinError,
     byte_code::ByteCode,
     heap::{Function, Handle, Heap, HirId, InlineObject, Struct},
     instruction_pointer::InstructionPointer,
     instructions::InstructionResult,
+    memoization::MemoizationCache,
     tracer::Tracer,
 };
-use candy_frontend::hir::{self, Id};
+use candy_frontend::{
+    ast_to_hir::AstToHir,
+    hir::{self, Id},
+    module::Module,
+    position::Offset,
+};
 use derive_more::Deref;
 use extension_trait::extension_trait;
-use std::{borrow::Borrow, collections::HashMap, fmt::Debug, hash::Hash};
+use rustc_hash::FxHashMap;
+use std::{borrow::Borrow, collections::HashMap, fmt::Debug, hash::Hash, ops::Range};
 
 /// A VM represents a Candy program that thinks it's currently running. Because
 /// VMs are first-class Rust structs, they enable other code to store "freezed"
@@ -33,10 +41,25 @@ struct VmInner<B: Borrow<ByteCode>, T: Tracer> {
     /// on its own.
     environment_for_main_function: Option<Struct>,
 }
+/// Calls nested deeper than this panic with a stack overflow unless configured
+/// otherwise using [`Vm::with_max_call_depth`].
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 100_000;
+
 pub struct MachineState {
     pub next_instruction: Option<InstructionPointer>,
     pub data_stack: Vec<InlineObject>,
     pub call_stack: Vec<InstructionPointer>,
+    /// Calling a function when the call stack already contains this many
+    /// frames panics instead of growing the stack until we run out of memory.
+    pub max_call_depth: usize,
+    /// Inline caches for calls of `✨.structGet`: For each call site, the index
+    /// of the field that was accessed last time.
+    ///
+    /// Structs with the same keys store their fields in the same order, so
+    /// the cached index is usually correct. Before using it, we check that the
+    /// struct actually contains the requested key at that index.
+    pub struct_get_cache: FxHashMap<InstructionPointer, usize>,
+    pub memoization_cache: MemoizationCache,
 }
 
 #[derive(Debug)]
@@ -50,6 +73,31 @@ pub struct CallHandle {
 pub struct Panic {
     pub reason: String,
     pub responsible: Id,
+    /// If a builtin function failed, this contains the structured reason.
+    pub builtin_error: Option<BuiltinError>,
+    /// An arbitrary value passed to `✨.panic`. The panic owns this value.
+    pub payload: Option<InlineObject>,
+}
+impl Panic {
+    #[must_use]
+    pub const fn module(&self) -> &Module {
+        &self.responsible.module
+    }
+    /// The span of the code that's responsible for this panic.
+    ///
+    /// Panics only store the responsible HIR ID and we look up the span when
+    /// it's needed since this requires (re)parsing the module. If the
+    /// responsible code was generated by the compiler, this is the span of the
+    /// closest surrounding code.
+    #[must_use]
+    pub fn span(&self, db: &impl AstToHir) -> Option<Range<Offset>> {
+        db.hir_id_to_best_effort_span(&self.responsible)
+    }
+    #[must_use]
+    pub fn display_span(&self, db: &impl AstToHir) -> Option<Range<Offset>> {
+        db.hir_id_to_display_span(&self.responsible)
+            .or_else(|| self.span(db))
+    }
 }
 
 impl<B, T> Vm<B, T>
@@ -92,6 +140,9 @@ where
             next_instruction: None,
             data_stack: vec![],
             call_stack: vec![],
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            struct_get_cache: FxHashMap::default(),
+            memoization_cache: MemoizationCache::default(),
         };
         state.call_function(function, arguments, responsible);
 
@@ -120,6 +171,15 @@ where
         Self::for_function(byte_code, heap, function, &[], responsible, tracer)
     }
 
+    /// Limits how deeply calls can be nested. Exceeding the limit panics with
+    /// a stack overflow. This also applies to the main function started by a
+    /// VM created using [`Self::for_main_function`].
+    #[must_use]
+    pub fn with_max_call_depth(mut self, max_call_depth: usize) -> Self {
+        self.inner.state.max_call_depth = max_call_depth;
+        self
+    }
+
     #[must_use]
     pub const fn byte_code(&self) -> &B {
         &self.inner.byte_code
@@ -136,6 +196,13 @@ where
     pub fn call_stack(&self) -> &[InstructionPointer] {
         &self.inner.state.call_stack
     }
+
+    /// Stops the VM and returns its tracer, e.g., to inspect where a VM was
+    /// when it ran out of time.
+    #[must_use]
+    pub fn into_tracer(self) -> T {
+        self.inner.tracer
+    }
 }
 
 #[derive(Deref)]
@@ -183,6 +250,7 @@ where
         let Some(current_instruction) = self.inner.state.next_instruction else {
             let return_value = self.inner.state.data_stack.pop().unwrap();
             self.inner.tracer.call_ended(heap, Some(return_value));
+            self.inner.state.memoization_cache.clear(heap);
 
             if let Some(environment) = self.inner.environment_for_main_function {
                 // We just ran the whole module which returned the main
@@ -196,7 +264,8 @@ where
                     &[environment.into()],
                     responsible,
                     self.inner.tracer,
-                );
+                )
+                .with_max_call_depth(self.inner.state.max_call_depth);
                 return StateAfterRun::Running(new_vm);
             }
 
@@ -206,28 +275,31 @@ where
             });
         };
 
-        let instruction = self
-            .inner
-            .byte_code
-            .borrow()
+        let byte_code = self.inner.byte_code.borrow();
+        let instruction = byte_code
             .instructions
             .get(*current_instruction)
             .expect("invalid instruction pointer");
         self.inner.state.next_instruction = Some(current_instruction.next());
+        heap.set_allocation_site(current_instruction);
 
-        let result = self
-            .inner
-            .state
-            .run_instruction(heap, instruction, &mut self.inner.tracer);
+        let result =
+            self.inner
+                .state
+                .run_instruction(heap, byte_code, instruction, &mut self.inner.tracer);
         match result {
             InstructionResult::Done => StateAfterRun::Running(self),
             InstructionResult::CallHandle(call) => {
                 StateAfterRun::CallingHandle(VmHandleCall { vm: self, call })
             }
-            InstructionResult::Panic(panic) => StateAfterRun::Finished(VmFinished {
-                tracer: self.inner.tracer,
-                result: Err(panic),
-            }),
+            InstructionResult::Panic(panic) => {
+                self.inner.state.memoization_cache.clear(heap);
+                self.inner.tracer.panicked(heap, &panic);
+                StateAfterRun::Finished(VmFinished {
+                    tracer: self.inner.tracer,
+                    result: Err(panic),
+                })
+            }
         }
     }
 
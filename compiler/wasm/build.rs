@@ -0,0 +1,65 @@
+//! There's no file system in the browser, so we embed the `Builtins` and `Core`
+//! packages into the binary.
+
+use std::{
+    env,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+const PACKAGES: [&str; 2] = ["Builtins", "Core"];
+
+fn main() {
+    let packages_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../packages");
+
+    let mut modules = String::new();
+    modules.push_str("&[\n");
+    for package in PACKAGES {
+        let package_path = packages_path.join(package);
+        println!("cargo:rerun-if-changed={}", package_path.display());
+        for file in candy_files(&package_path) {
+            let mut path = file
+                .strip_prefix(&package_path)
+                .unwrap()
+                .components()
+                .map(|it| it.as_os_str().to_str().unwrap().to_string())
+                .collect::<Vec<_>>();
+            let last = path.pop().unwrap();
+            let last = last.strip_suffix(".candy").unwrap();
+            if last != "_" {
+                path.push(last.to_string());
+            }
+
+            let file = fs::canonicalize(&file).unwrap();
+            writeln!(
+                modules,
+                "    ({package:?}, &{path:?}, include_str!({:?})),",
+                file.display().to_string(),
+            )
+            .unwrap();
+        }
+    }
+    modules.push_str("]\n");
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    fs::write(out_dir.join("packages.rs"), modules).unwrap();
+}
+
+fn candy_files(directory: &Path) -> Vec<PathBuf> {
+    let mut entries = fs::read_dir(directory)
+        .unwrap()
+        .map(|it| it.unwrap().path())
+        .collect::<Vec<_>>();
+    entries.sort();
+
+    let mut files = vec![];
+    for path in entries {
+        if path.is_dir() {
+            files.extend(candy_files(&path));
+        } else if path.extension().is_some_and(|it| it == "candy") {
+            files.push(path);
+        }
+    }
+    files
+}
@@ -0,0 +1,236 @@
+//! Compiles and runs Candy code in the browser, e.g., for a playground.
+//!
+//! Build it with `wasm-pack build compiler/wasm --target web`.
+//!
+//! Only the frontend and the VM are included: Everything depending on tokio or
+//! `notify` (the language server, file watching, etc.) lives in the CLI and
+//! language server crates. Because there's no file system in the browser, the
+//! `Builtins` and `Core` packages are embedded into the binary and programs
+//! only get an environment containing `stdout` instead of the
+//! [`DefaultEnvironment`](candy_vm::environment::DefaultEnvironment).
+//!
+//! ```js
+//! import init, { Playground } from "./pkg/candy_wasm.js";
+//!
+//! await init();
+//! const playground = new Playground();
+//! playground.update('main environment := environment.stdout "Hello, world!"');
+//! for (const diagnostic of playground.diagnostics()) {
+//!   console.log(diagnostic.startLine, diagnostic.message);
+//! }
+//! console.log(playground.run());
+//! ```
+
+#![warn(clippy::nursery, clippy::pedantic, unused_crate_dependencies)]
+#![allow(
+    clippy::missing_panics_doc,
+    clippy::module_name_repetitions,
+    clippy::must_use_candidate
+)]
+
+use candy_frontend::{
+    ast::AstDbStorage,
+    ast_to_hir::{AstToHir, AstToHirStorage},
+    cst::CstDbStorage,
+    cst_to_ast::CstToAstStorage,
+    hir::{CollectErrors, HirDbStorage},
+    hir_to_mir::{ExecutionTarget, HirToMirStorage},
+    lir_optimize::OptimizeLirStorage,
+    mir_optimize::{MirPassesOwner, OptimizeMirStorage},
+    mir_to_lir::MirToLirStorage,
+    module::{
+        GetModuleContentQuery, InMemoryModuleProvider, Module, ModuleDbStorage, ModuleKind,
+        ModuleProvider, ModuleProviderOwner, MutableModuleProviderOwner, Package,
+    },
+    position::{PositionConversionDb, PositionConversionStorage},
+    rcst_to_cst::RcstToCstStorage,
+    string_to_rcst::{RcstCacheOwner, StringToRcstStorage},
+    TracingConfig,
+};
+use candy_vm::{
+    heap::{Data, Tag},
+    lir_to_byte_code::compile_byte_code,
+    native_functions::NativeFunctions,
+    Runtime, RuntimeError,
+};
+use getrandom as _;
+use js_sys::Array;
+use std::{cell::RefCell, fmt::Write, rc::Rc};
+use wasm_bindgen::prelude::*;
+
+/// The `Builtins` and `Core` modules as `(package, path, source code)`,
+/// generated by our build script.
+const PACKAGES: &[(&str, &[&str], &str)] = include!(concat!(env!("OUT_DIR"), "/packages.rs"));
+
+#[salsa::database(
+    AstDbStorage,
+    AstToHirStorage,
+    CstDbStorage,
+    CstToAstStorage,
+    HirDbStorage,
+    HirToMirStorage,
+    MirToLirStorage,
+    ModuleDbStorage,
+    OptimizeLirStorage,
+    OptimizeMirStorage,
+    PositionConversionStorage,
+    RcstToCstStorage,
+    StringToRcstStorage
+)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    module_provider: InMemoryModuleProvider,
+}
+impl Database {
+    fn with_embedded_packages() -> Self {
+        let mut db = Self::default();
+        for (package, path, source_code) in PACKAGES {
+            let module = Module {
+                package: Package::Managed((*package).into()),
+                path: path.iter().map(|it| (*it).to_string()).collect(),
+                kind: ModuleKind::Code,
+            };
+            db.module_provider.add_str(&module, *source_code);
+        }
+        db
+    }
+}
+impl salsa::Database for Database {}
+impl ModuleProviderOwner for Database {
+    fn get_module_provider(&self) -> &dyn ModuleProvider {
+        &self.module_provider
+    }
+}
+impl RcstCacheOwner for Database {}
+impl MirPassesOwner for Database {}
+impl MutableModuleProviderOwner for Database {
+    fn get_in_memory_module_provider(&mut self) -> &mut InMemoryModuleProvider {
+        &mut self.module_provider
+    }
+    fn invalidate_module(&mut self, module: &Module) {
+        GetModuleContentQuery.in_db_mut(self).invalidate(module);
+    }
+}
+
+fn playground_module() -> Module {
+    Module {
+        package: Package::Anonymous {
+            url: "playground".to_string(),
+        },
+        path: vec![],
+        kind: ModuleKind::Code,
+    }
+}
+
+/// Keeps the analysis results between edits so that updating the code only
+/// recomputes what changed.
+#[wasm_bindgen]
+pub struct Playground {
+    db: Database,
+}
+#[wasm_bindgen]
+impl Playground {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        let mut db = Database::with_embedded_packages();
+        db.did_open_module(&playground_module(), vec![]);
+        Self { db }
+    }
+
+    /// Replaces the playground's source code.
+    pub fn update(&mut self, source_code: &str) {
+        self.db
+            .did_change_module(&playground_module(), source_code.as_bytes().to_vec());
+    }
+
+    /// Returns the [`Diagnostic`]s of the current source code.
+    pub fn diagnostics(&self) -> Array {
+        let module = playground_module();
+        let Ok((hir, _)) = self.db.hir(module.clone()) else {
+            return Array::new();
+        };
+        let mut errors = vec![];
+        hir.collect_errors(&mut errors);
+
+        errors
+            .into_iter()
+            .map(|error| {
+                let range = self.db.range_to_positions(module.clone(), error.span);
+                JsValue::from(Diagnostic {
+                    start_line: range.start.line,
+                    start_character: range.start.character,
+                    end_line: range.end.line,
+                    end_character: range.end.character,
+                    message: error.payload.to_string(),
+                })
+            })
+            .collect()
+    }
+
+    /// Runs the `main` function and returns everything it wrote to stdout,
+    /// followed by a description of the panic if the code panicked.
+    pub fn run(&self) -> String {
+        let (byte_code, _) = compile_byte_code(
+            &self.db,
+            ExecutionTarget::Module(playground_module()),
+            TracingConfig::off(),
+        );
+
+        let output = Rc::new(RefCell::new(String::new()));
+        let mut native_functions = NativeFunctions::default();
+        let stdout = output.clone();
+        native_functions.register("Stdout", 1, move |heap, arguments| {
+            let mut stdout = stdout.borrow_mut();
+            match Data::from(arguments[0]) {
+                Data::Text(text) => stdout.push_str(text.get()),
+                other => write!(stdout, "{other}").unwrap(),
+            }
+            stdout.push('\n');
+            Ok(Tag::create_nothing(heap).into())
+        });
+
+        let mut runtime =
+            Runtime::with_environment(byte_code, |heap| native_functions.into_environment(heap));
+        let result = runtime.run_main();
+
+        let mut output = output.take();
+        match result {
+            Ok(_) => {}
+            Err(RuntimeError::UnknownExport(_)) => {
+                output.push_str("The module doesn't export a main function.\n");
+            }
+            Err(error) => writeln!(output, "{error}").unwrap(),
+        }
+        output
+    }
+}
+impl Default for Playground {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An error in the playground's source code. Lines and characters are
+/// zero-based.
+#[wasm_bindgen(getter_with_clone)]
+pub struct Diagnostic {
+    #[wasm_bindgen(js_name = startLine)]
+    pub start_line: usize,
+    #[wasm_bindgen(js_name = startCharacter)]
+    pub start_character: usize,
+    #[wasm_bindgen(js_name = endLine)]
+    pub end_line: usize,
+    #[wasm_bindgen(js_name = endCharacter)]
+    pub end_character: usize,
+    pub message: String,
+}
+
+/// Compiles and runs the `source_code` in one go. Use a [`Playground`] to
+/// analyze code incrementally.
+#[wasm_bindgen(js_name = compileAndRun)]
+pub fn compile_and_run(source_code: &str) -> String {
+    let mut playground = Playground::new();
+    playground.update(source_code);
+    playground.run()
+}
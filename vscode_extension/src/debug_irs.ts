@@ -1,10 +1,17 @@
 import * as vscode from "vscode";
 import { LanguageClient } from "vscode-languageclient/node";
-import { updateIrType, viewIr, ViewIrParams } from "./lsp_custom_protocol";
+import {
+  irSourceMapping,
+  IrSourceMapping,
+  updateIrType,
+  viewIr,
+  ViewIrParams,
+} from "./lsp_custom_protocol";
 import { combineCancellationTokens, PromiseOr } from "./utils";
 
 type Ir =
   | { type: "rcst" }
+  | { type: "cst" }
   | { type: "ast" }
   | { type: "hir" }
   | { type: "mir"; tracingConfig: TracingConfig }
@@ -18,6 +25,8 @@ function getIrTitle(irType: IrType): string {
   switch (irType) {
     case "rcst":
       return "RCST";
+    case "cst":
+      return "CST";
     case "ast":
       return "AST";
     case "hir":
@@ -50,6 +59,7 @@ export function registerDebugIrCommands(client: LanguageClient) {
   });
 
   registerDebugIrCommand("rcst", "viewRcst", () => ({ type: "rcst" }));
+  registerDebugIrCommand("cst", "viewCst", () => ({ type: "cst" }));
   registerDebugIrCommand("ast", "viewAst", () => ({ type: "ast" }));
   registerDebugIrCommand("hir", "viewHir", () => ({ type: "hir" }));
   registerDebugIrCommand("mir", "viewMir", async () => {
@@ -95,6 +105,8 @@ export function registerDebugIrCommands(client: LanguageClient) {
     return { type: "vmByteCode", tracingConfig };
   });
   registerDebugIrCommand("llvmIr", "viewLlvmIr", () => ({ type: "llvmIr" }));
+
+  registerSelectionHighlighting(client);
 }
 
 function registerDocumentProvider(
@@ -151,6 +163,52 @@ function registerDebugIrCommand(
   });
 }
 
+// Selection Highlighting
+
+// When selecting source code, highlights the corresponding parts of IRs that
+// are visible next to it.
+function registerSelectionHighlighting(client: LanguageClient) {
+  const decorationType = vscode.window.createTextEditorDecorationType({
+    backgroundColor: new vscode.ThemeColor(
+      "editor.selectionHighlightBackground",
+    ),
+  });
+  vscode.window.onDidChangeTextEditorSelection(async (event) => {
+    const sourceUri = event.textEditor.document.uri;
+    if (sourceUri.scheme === irScheme) {
+      return;
+    }
+
+    const irEditors = vscode.window.visibleTextEditors.filter(
+      (editor) =>
+        editor.document.uri.scheme === irScheme &&
+        decodeUri(editor.document.uri).originalUri.toString() ===
+          sourceUri.toString(),
+    );
+    for (const irEditor of irEditors) {
+      const mappings = await client.sendRequest(irSourceMapping, {
+        uri: irEditor.document.uri.toString(),
+      });
+      const ranges = event.selections.flatMap((selection) =>
+        mappings
+          .filter((mapping) =>
+            toVsCodeRange(mapping.sourceRange).intersection(selection),
+          )
+          .flatMap((mapping) => mapping.irRanges.map(toVsCodeRange)),
+      );
+      irEditor.setDecorations(decorationType, ranges);
+    }
+  });
+}
+function toVsCodeRange(range: IrSourceMapping["sourceRange"]): vscode.Range {
+  return new vscode.Range(
+    range.start.line,
+    range.start.character,
+    range.end.line,
+    range.end.character,
+  );
+}
+
 // Tracing Config
 
 interface TracingConfig {
@@ -36,6 +36,25 @@ export async function activate(context: vscode.ExtensionContext) {
   const clientOptions: LanguageClientOptions = {
     outputChannelName: "🍭 Candy Language Server",
     initializationOptions: { packagesPath },
+    middleware: {
+      provideDocumentFormattingEdits: (document, options, token, next) =>
+        next(document, withFormatterOptions(options), token),
+      provideOnTypeFormattingEdits: (
+        document,
+        position,
+        character,
+        options,
+        token,
+        next,
+      ) =>
+        next(
+          document,
+          position,
+          character,
+          withFormatterOptions(options),
+          token,
+        ),
+    },
   };
 
   client = new LanguageClient(
@@ -52,6 +71,20 @@ export async function activate(context: vscode.ExtensionContext) {
   registerDebugAdapter(context, client);
 }
 
+function withFormatterOptions(
+  options: vscode.FormattingOptions,
+): vscode.FormattingOptions {
+  const configuration = vscode.workspace.getConfiguration("candy.formatter");
+  return {
+    ...options,
+    maxLineWidth: configuration.get<number>("maxLineWidth", 100),
+    trailingComma: configuration.get<string>(
+      "trailingComma",
+      "multiline-only",
+    ),
+  };
+}
+
 export function deactivate(): Thenable<void> | undefined {
   if (!client) {
     return undefined;
@@ -3,6 +3,7 @@ import {
   DocumentUri,
   NotificationType,
   Position,
+  Range,
   RequestType,
 } from "vscode-languageclient";
 
@@ -35,6 +36,16 @@ export const viewIr = new RequestType<ViewIrParams, string, void>(
   "candy/viewIr",
 );
 
+export const irSourceMapping = new RequestType<
+  ViewIrParams,
+  IrSourceMapping[],
+  void
+>("candy/irSourceMapping");
+export interface IrSourceMapping {
+  readonly sourceRange: Range;
+  readonly irRanges: Range[];
+}
+
 export const updateIrType = new NotificationType<UpdateIrParams>(
   "candy/updateIr",
 );